@@ -0,0 +1,34 @@
+//! Criterion benchmarks of `bench --synthetic`
+//!
+//! The generator is a binary, so each iteration runs it on a fixed number of
+//! synthetic posts: rendering markdown, sanitizing, validating and hashing.
+//! Compare releases with `cargo bench -- --save-baseline <name>` and
+//! `--baseline <name>`; `secureblog-rs bench --synthetic N` shows the stages.
+
+// `criterion_group!` declares an undocumented `pub fn`
+#![allow(missing_docs)]
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::process::{Command, Stdio};
+
+fn synthetic(posts: usize) {
+    let status = Command::new(env!("CARGO_BIN_EXE_secureblog-rs"))
+        .args(["bench", "--synthetic", &posts.to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .expect("failed to run secureblog-rs");
+    assert!(status.success(), "bench --synthetic {posts} failed");
+}
+
+fn benchmarks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("synthetic");
+    group.sample_size(10);
+    for posts in [10, 100] {
+        group.bench_with_input(BenchmarkId::from_parameter(posts), &posts, |b, &posts| b.iter(|| synthetic(posts)));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, benchmarks);
+criterion_main!(benches);
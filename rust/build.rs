@@ -0,0 +1,22 @@
+//! Embeds the compiler version and `Cargo.lock` hash for `--attest-self`
+
+use sha2::{Digest, Sha256};
+use std::process::Command;
+
+fn main() {
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|out| String::from_utf8(out.stdout).ok())
+        .map_or_else(|| "unknown".to_string(), |v| v.trim().to_string());
+    println!("cargo:rustc-env=SECUREBLOG_RUSTC_VERSION={version}");
+
+    let lock_hash = std::fs::read("Cargo.lock")
+        .map_or_else(|_| "none".to_string(), |lock| format!("sha256:{:x}", Sha256::digest(lock)));
+    println!("cargo:rustc-env=SECUREBLOG_CARGO_LOCK_SHA256={lock_hash}");
+
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    println!("cargo:rerun-if-env-changed=RUSTC");
+}
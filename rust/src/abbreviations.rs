@@ -0,0 +1,158 @@
+//! `<abbr>` for the acronyms listed in `data/abbreviations.yaml`
+//!
+//! The file maps each acronym to its expansion (`CSP: Content Security
+//! Policy`). Every whole-word occurrence in a post's text is wrapped in
+//! `<abbr title="...">`, so screen readers and hover text explain the jargon
+//! without markup in the markdown. Code, preformatted text, existing `<abbr>`
+//! elements and attribute values are left alone.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use crate::security::escape_html as escape;
+
+/// Site-wide abbreviations
+pub const ABBREVIATIONS: &str = "data/abbreviations.yaml";
+
+/// Elements whose text is never expanded
+const SKIPPED: [&str; 7] = ["abbr", "code", "kbd", "pre", "samp", "script", "style"];
+
+/// Acronyms and the matcher over their escaped forms
+#[derive(Debug, Clone)]
+pub struct Abbreviations {
+    /// Escaped acronym → `<abbr>` element
+    elements: HashMap<String, String>,
+    pattern: Option<Regex>,
+}
+
+impl Abbreviations {
+    /// Build the matcher; longer acronyms win over their prefixes (`HSTS` before `HST`)
+    pub fn new(entries: &BTreeMap<String, String>) -> Result<Self> {
+        let mut elements = HashMap::new();
+        for (acronym, title) in entries {
+            if acronym.is_empty() || acronym.chars().any(char::is_whitespace) || title.trim().is_empty() {
+                anyhow::bail!("Invalid abbreviation {acronym:?}: acronyms are single words with a non-empty expansion");
+            }
+            let escaped = escape(acronym);
+            elements.insert(escaped.clone(), format!("<abbr title=\"{}\">{escaped}</abbr>", escape(title.trim())));
+        }
+        let mut keys: Vec<&String> = elements.keys().collect();
+        keys.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        let pattern = if keys.is_empty() {
+            None
+        } else {
+            let alternatives: Vec<String> = keys.iter().map(|key| regex::escape(key)).collect();
+            Some(Regex::new(&alternatives.join("|"))?)
+        };
+        Ok(Self { elements, pattern })
+    }
+
+    /// Load the abbreviations file (none when it does not exist)
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Self::new(&BTreeMap::new());
+        }
+        let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let entries: BTreeMap<String, String> =
+            serde_yaml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))?;
+        Self::new(&entries).with_context(|| format!("Invalid {}", path.display()))
+    }
+
+    /// Whole-word occurrences in `text`, replaced by their `<abbr>` elements
+    fn expand_text(&self, pattern: &Regex, text: &str, output: &mut String) {
+        let mut last = 0;
+        let mut start = 0;
+        while let Some(word) = pattern.find_at(text, start) {
+            let before = text[..word.start()].chars().next_back();
+            let after = text[word.end()..].chars().next();
+            if before.is_some_and(char::is_alphanumeric) || after.is_some_and(char::is_alphanumeric) {
+                start = word.start() + word.as_str().chars().next().map_or(1, char::len_utf8);
+                continue;
+            }
+            output.push_str(&text[last..word.start()]);
+            output.push_str(&self.elements[word.as_str()]);
+            last = word.end();
+            start = word.end();
+        }
+        output.push_str(&text[last..]);
+    }
+
+    /// Wrap the acronyms in the text of rendered `html`
+    pub fn apply(&self, html: &str) -> String {
+        let Some(pattern) = &self.pattern else {
+            return html.to_string();
+        };
+        let mut output = String::with_capacity(html.len());
+        let mut skipped: Vec<String> = Vec::new();
+        let mut rest = html;
+        while !rest.is_empty() {
+            if let Some(tag) = rest.strip_prefix('<') {
+                let end = tag.find('>').map_or(rest.len(), |i| i + 2);
+                let element = &rest[..end];
+                let name = element
+                    .trim_start_matches(['<', '/'])
+                    .chars()
+                    .take_while(char::is_ascii_alphanumeric)
+                    .collect::<String>()
+                    .to_ascii_lowercase();
+                if SKIPPED.contains(&name.as_str()) {
+                    if element.starts_with("</") {
+                        if let Some(open) = skipped.iter().rposition(|open| *open == name) {
+                            skipped.truncate(open);
+                        }
+                    } else if !element.ends_with("/>") {
+                        skipped.push(name);
+                    }
+                }
+                output.push_str(element);
+                rest = &rest[end..];
+            } else {
+                let end = rest.find('<').unwrap_or(rest.len());
+                if skipped.is_empty() {
+                    self.expand_text(pattern, &rest[..end], &mut output);
+                } else {
+                    output.push_str(&rest[..end]);
+                }
+                rest = &rest[end..];
+            }
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn abbreviations() -> Abbreviations {
+        let entries = BTreeMap::from([
+            ("CSP".to_string(), "Content Security Policy".to_string()),
+            ("HSTS".to_string(), "HTTP Strict Transport Security".to_string()),
+            ("R&D".to_string(), "Research & \"Development\"".to_string()),
+        ]);
+        Abbreviations::new(&entries).unwrap()
+    }
+
+    #[test]
+    fn test_wraps_whole_words_in_text() {
+        let html = "<p>CSP and HSTS, not CSPs or xCSP. R&amp;D.</p>";
+        assert_eq!(
+            abbreviations().apply(html),
+            "<p><abbr title=\"Content Security Policy\">CSP</abbr> and <abbr title=\"HTTP Strict Transport Security\">HSTS</abbr>, \
+             not CSPs or xCSP. <abbr title=\"Research &amp; &quot;Development&quot;\">R&amp;D</abbr>.</p>"
+        );
+    }
+
+    #[test]
+    fn test_leaves_code_attributes_and_abbr_alone() {
+        let html = "<pre><code>CSP</code></pre><a href=\"/csp\" title=\"CSP\">CSP</a><abbr title=\"x\">HSTS</abbr>";
+        assert_eq!(
+            abbreviations().apply(html),
+            "<pre><code>CSP</code></pre><a href=\"/csp\" title=\"CSP\"><abbr title=\"Content Security Policy\">CSP</abbr></a><abbr title=\"x\">HSTS</abbr>"
+        );
+        assert!(Abbreviations::new(&BTreeMap::from([("TL S".to_string(), "x".to_string())])).is_err());
+    }
+}
@@ -0,0 +1,221 @@
+//! Static ActivityPub actor, outbox and WebFinger export
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{Config, Post};
+
+/// Directory (under the output) holding the ActivityPub documents
+const AP_DIR: &str = "activitypub";
+
+/// ActivityPub export settings
+#[derive(Debug, Clone, Deserialize)]
+pub struct ActivityPubConfig {
+    /// Account name, followable as `@username@domain`
+    #[serde(default = "default_username")]
+    pub username: String,
+    /// Actor profile summary
+    #[serde(default)]
+    pub summary: String,
+    /// PEM public key published on the actor document
+    #[serde(default)]
+    pub public_key: Option<PathBuf>,
+}
+
+fn default_username() -> String {
+    "blog".to_string()
+}
+
+/// Host part of the site URL (`https://example.com/blog` -> `example.com`)
+fn domain(site_url: &str) -> &str {
+    let rest = site_url.split_once("://").map_or(site_url, |(_, rest)| rest);
+    rest.split('/').next().unwrap_or(rest)
+}
+
+/// Actor document
+pub fn actor(config: &Config, ap: &ActivityPubConfig, public_key: Option<&str>) -> Value {
+    let base = config.url.trim_end_matches('/');
+    let id = format!("{base}/{AP_DIR}/actor.json");
+
+    let mut actor = json!({
+        "@context": [
+            "https://www.w3.org/ns/activitystreams",
+            "https://w3id.org/security/v1"
+        ],
+        "id": id,
+        "type": "Person",
+        "preferredUsername": ap.username,
+        "name": config.title,
+        "summary": ap.summary,
+        "url": format!("{base}/"),
+        "inbox": format!("{base}/{AP_DIR}/inbox"),
+        "outbox": format!("{base}/{AP_DIR}/outbox.json"),
+        "manuallyApprovesFollowers": false,
+    });
+
+    if let Some(pem) = public_key {
+        actor["publicKey"] = json!({
+            "id": format!("{id}#main-key"),
+            "owner": id,
+            "publicKeyPem": pem,
+        });
+    }
+
+    actor
+}
+
+/// Article object for a post
+pub fn article(config: &Config, post: &Post) -> Value {
+    let base = config.url.trim_end_matches('/');
+    json!({
+        "@context": "https://www.w3.org/ns/activitystreams",
+        "id": format!("{base}/{AP_DIR}/posts/{}.json", post.meta.slug),
+        "type": "Article",
+        "name": post.meta.title,
+        "content": post.html,
+        "url": post.permalink(&config.url),
+        "attributedTo": format!("{base}/{AP_DIR}/actor.json"),
+        "published": post.meta.date.to_rfc3339(),
+        "to": ["https://www.w3.org/ns/activitystreams#Public"],
+        "tag": post.meta.tags.iter().map(|tag| json!({
+            "type": "Hashtag",
+            "name": format!("#{tag}"),
+        })).collect::<Vec<_>>(),
+    })
+}
+
+/// Whether a post is federated: public, and not behind the server's auth like protected posts
+fn federated(post: &Post) -> bool {
+    post.meta.status.is_public() && !post.meta.protected
+}
+
+/// Outbox collection of `Create` activities, newest first
+pub fn outbox(config: &Config, posts: &[Post]) -> Value {
+    let base = config.url.trim_end_matches('/');
+    let items: Vec<Value> = posts
+        .iter()
+        .filter(|p| federated(p))
+        .map(|post| {
+            let mut object = article(config, post);
+            if let Some(map) = object.as_object_mut() {
+                map.remove("@context");
+            }
+            json!({
+                "id": format!("{base}/{AP_DIR}/posts/{}.json#create", post.meta.slug),
+                "type": "Create",
+                "actor": format!("{base}/{AP_DIR}/actor.json"),
+                "published": post.meta.date.to_rfc3339(),
+                "to": ["https://www.w3.org/ns/activitystreams#Public"],
+                "object": object,
+            })
+        })
+        .collect();
+
+    json!({
+        "@context": "https://www.w3.org/ns/activitystreams",
+        "id": format!("{base}/{AP_DIR}/outbox.json"),
+        "type": "OrderedCollection",
+        "totalItems": items.len(),
+        "orderedItems": items,
+    })
+}
+
+/// WebFinger response for `acct:username@domain`
+pub fn webfinger(config: &Config, ap: &ActivityPubConfig) -> Value {
+    let base = config.url.trim_end_matches('/');
+    json!({
+        "subject": format!("acct:{}@{}", ap.username, domain(&config.url)),
+        "aliases": [format!("{base}/{AP_DIR}/actor.json")],
+        "links": [{
+            "rel": "self",
+            "type": "application/activity+json",
+            "href": format!("{base}/{AP_DIR}/actor.json"),
+        }],
+    })
+}
+
+/// Write the actor, outbox, per-post objects and WebFinger document
+///
+/// Static hosts must serve these with `application/activity+json` and
+/// `application/jrd+json` content types respectively.
+pub fn generate(config: &Config, ap: &ActivityPubConfig, posts: &[Post]) -> Result<()> {
+    let public_key = ap
+        .public_key
+        .as_ref()
+        .map(|path| {
+            fs::read_to_string(path)
+                .with_context(|| format!("Failed to read ActivityPub public key: {}", path.display()))
+        })
+        .transpose()?;
+
+    let dir = config.output.join(AP_DIR);
+    write_json(&dir.join("actor.json"), &actor(config, ap, public_key.as_deref()))?;
+    write_json(&dir.join("outbox.json"), &outbox(config, posts))?;
+    for post in posts.iter().filter(|p| federated(p)) {
+        write_json(
+            &dir.join("posts").join(format!("{}.json", post.meta.slug)),
+            &article(config, post),
+        )?;
+    }
+    write_json(
+        &config.output.join(".well-known").join("webfinger"),
+        &webfinger(config, ap),
+    )
+}
+
+fn write_json(path: &Path, value: &Value) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(value)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::status::PostStatus;
+    use crate::PostMeta;
+
+    fn ap() -> ActivityPubConfig {
+        ActivityPubConfig {
+            username: "news".to_string(),
+            summary: String::new(),
+            public_key: None,
+        }
+    }
+
+    #[test]
+    fn test_webfinger_subject() {
+        let config = Config {
+            url: "https://blog.example/".to_string(),
+            ..Config::default()
+        };
+        let doc = webfinger(&config, &ap());
+        assert_eq!(doc["subject"], "acct:news@blog.example");
+        assert_eq!(doc["links"][0]["href"], "https://blog.example/activitypub/actor.json");
+    }
+
+    #[test]
+    fn test_outbox_skips_drafts() {
+        let config = Config::default();
+        let post = |slug: &str, status: PostStatus| Post {
+            meta: PostMeta {
+                title: slug.to_string(),
+                tags: vec!["xss".to_string()],
+                slug: slug.to_string(),
+                status,
+                ..PostMeta::default()
+            },
+            html: "<p>Hi</p>".to_string(),
+            ..Post::default()
+        };
+        let doc = outbox(&config, &[post("a", PostStatus::Published), post("b", PostStatus::Draft)]);
+        assert_eq!(doc["totalItems"], 1);
+        assert_eq!(doc["orderedItems"][0]["object"]["tag"][0]["name"], "#xss");
+        assert!(doc["orderedItems"][0]["object"].get("@context").is_none());
+    }
+}
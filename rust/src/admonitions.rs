@@ -0,0 +1,123 @@
+//! `> [!NOTE]` style callouts
+//!
+//! A blockquote whose first line is `[!NOTE]`, `[!TIP]`, `[!IMPORTANT]`,
+//! `[!WARNING]` or `[!CAUTION]` becomes an `<aside>` with a title, using only
+//! the classes and attributes the sanitizer allows.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Opening blockquote and its marker paragraph (or first line)
+static MARKER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)<blockquote>\s*<p>\[!(note|tip|important|warning|caution)\][ \t]*(</p>|\n)").unwrap()
+});
+
+/// Callout kinds with their class suffix and visible title
+pub const KINDS: [(&str, &str); 5] = [
+    ("note", "Note"),
+    ("tip", "Tip"),
+    ("important", "Important"),
+    ("warning", "Warning"),
+    ("caution", "Caution"),
+];
+
+/// Classes allowed on `<aside>` by the sanitizer
+pub const ASIDE_CLASSES: [&str; 6] = [
+    "admonition",
+    "admonition-note",
+    "admonition-tip",
+    "admonition-important",
+    "admonition-warning",
+    "admonition-caution",
+];
+
+/// Class of the title paragraph
+pub const TITLE_CLASS: &str = "admonition-title";
+
+/// Offset of the `</blockquote>` closing the blockquote opened just before `from`
+fn matching_close(html: &str, from: usize) -> Option<usize> {
+    let mut depth = 1;
+    let mut pos = from;
+    loop {
+        let open = html[pos..].find("<blockquote").map(|i| pos + i);
+        let close = html[pos..].find("</blockquote>").map(|i| pos + i)?;
+        match open {
+            Some(open) if open < close => {
+                depth += 1;
+                pos = open + "<blockquote".len();
+            }
+            _ => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(close);
+                }
+                pos = close + "</blockquote>".len();
+            }
+        }
+    }
+}
+
+/// Turn callout blockquotes in rendered post HTML into `<aside>` elements
+pub fn apply(html: &str) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(capture) = MARKER.captures(rest) {
+        let whole = capture.get(0).unwrap();
+        let Some(close) = matching_close(rest, whole.end()) else {
+            break;
+        };
+        let kind = capture[1].to_ascii_lowercase();
+        let title = KINDS.iter().find(|(k, _)| *k == kind).map_or("Note", |(_, title)| title);
+
+        output.push_str(&rest[..whole.start()]);
+        output.push_str(&format!(
+            "<aside class=\"admonition admonition-{kind}\" role=\"note\" aria-label=\"{title}\">\n\
+             <p class=\"{TITLE_CLASS}\">{title}</p>\n"
+        ));
+        if &capture[2] == "\n" {
+            output.push_str("<p>");
+        }
+        output.push_str(apply(rest[whole.end()..close].trim_start()).trim_end());
+        output.push_str("\n</aside>");
+        rest = &rest[close + "</blockquote>".len()..];
+    }
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::sanitize_html;
+    use crate::SecurityPolicy;
+
+    #[test]
+    fn test_callout_with_text_on_next_line() {
+        let html = "<blockquote>\n<p>[!WARNING]\nDo not run this in production.</p>\n</blockquote>\n";
+        assert_eq!(
+            apply(html),
+            "<aside class=\"admonition admonition-warning\" role=\"note\" aria-label=\"Warning\">\n\
+             <p class=\"admonition-title\">Warning</p>\n<p>Do not run this in production.</p>\n</aside>\n"
+        );
+    }
+
+    #[test]
+    fn test_callout_with_separate_paragraphs() {
+        let html = "<blockquote>\n<p>[!note]</p>\n<p>One.</p>\n<blockquote>\n<p>Quoted.</p>\n</blockquote>\n</blockquote>";
+        let aside = apply(html);
+        assert!(aside.starts_with("<aside class=\"admonition admonition-note\""));
+        assert!(aside.ends_with("<p>One.</p>\n<blockquote>\n<p>Quoted.</p>\n</blockquote>\n</aside>"));
+    }
+
+    #[test]
+    fn test_plain_blockquotes_are_untouched() {
+        let html = "<blockquote>\n<p>[!UNKNOWN] text</p>\n</blockquote>\n<p>[!NOTE] outside</p>";
+        assert_eq!(apply(html), html);
+    }
+
+    #[test]
+    fn test_callout_markup_survives_sanitizer() {
+        let aside = apply("<blockquote>\n<p>[!CAUTION]\nHot.</p>\n</blockquote>");
+        assert_eq!(sanitize_html(&aside, &SecurityPolicy::default()), aside);
+    }
+}
@@ -0,0 +1,90 @@
+//! Permalink anchors on headings
+//!
+//! Each `<h1>`-`<h6>` of a rendered post gets an `id` (its own, or one
+//! slugified from its text, made unique within the post) and ends with a
+//! small `<a class="heading-anchor">` link to itself. It is plain markup: the
+//! theme's CSS decides how unobtrusive it is, and no script is involved.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashSet;
+
+use crate::security::escape_html as escape;
+use crate::slug::slugify;
+
+/// A heading with its level, attributes and content
+static HEADING: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<h([1-6])((?:\s[^>]*)?)>(.*?)</h[1-6]>").unwrap());
+
+/// An `id` attribute
+static ID: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\bid="([^"]*)""#).unwrap());
+
+/// Markup inside a heading, dropped for its slug
+static TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]*>").unwrap());
+
+/// Slug of a heading's text content
+fn heading_slug(content: &str) -> String {
+    let text = TAG.replace_all(content, "");
+    let text = text.replace("&amp;", "&").replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&#39;", "'");
+    let slug = slugify(&text);
+    if slug.is_empty() {
+        "section".to_string()
+    } else {
+        slug
+    }
+}
+
+/// Add ids and anchor links showing `symbol` to every heading of `html`
+pub fn apply(html: &str, symbol: &str) -> String {
+    let mut taken: HashSet<String> = ID.captures_iter(html).map(|capture| capture[1].to_string()).collect();
+    HEADING
+        .replace_all(html, |capture: &regex::Captures| {
+            let (level, attributes, content) = (&capture[1], &capture[2], &capture[3]);
+            // The id as it appears in the markup, already escaped
+            let (id, attributes) = match ID.captures(attributes) {
+                Some(existing) => (existing[1].to_string(), attributes.to_string()),
+                None => {
+                    let base = heading_slug(content);
+                    let mut id = base.clone();
+                    let mut n = 1;
+                    while taken.contains(&id) {
+                        n += 1;
+                        id = format!("{base}-{n}");
+                    }
+                    taken.insert(id.clone());
+                    // Slugs are letters, digits and dashes: nothing to escape
+                    (id.clone(), format!(" id=\"{id}\"{attributes}"))
+                }
+            };
+            format!(
+                "<h{level}{attributes}>{content} <a class=\"heading-anchor\" href=\"#{id}\" aria-label=\"Link to this section\">{}</a></h{level}>",
+                escape(symbol)
+            )
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ids_from_text_are_unique() {
+        let html = "<h2>Install &amp; <em>verify</em></h2><p id=\"setup\">x</p><h3>Setup</h3><h2>Setup</h2>";
+        assert_eq!(
+            apply(html, "§"),
+            "<h2 id=\"install-verify\">Install &amp; <em>verify</em> <a class=\"heading-anchor\" href=\"#install-verify\" aria-label=\"Link to this section\">§</a></h2>\
+             <p id=\"setup\">x</p>\
+             <h3 id=\"setup-2\">Setup <a class=\"heading-anchor\" href=\"#setup-2\" aria-label=\"Link to this section\">§</a></h3>\
+             <h2 id=\"setup-3\">Setup <a class=\"heading-anchor\" href=\"#setup-3\" aria-label=\"Link to this section\">§</a></h2>"
+        );
+    }
+
+    #[test]
+    fn test_existing_ids_are_kept() {
+        assert_eq!(
+            apply("<h2 class=\"x\" id=\"keep\">T</h2><h4>!!</h4>", "#"),
+            "<h2 class=\"x\" id=\"keep\">T <a class=\"heading-anchor\" href=\"#keep\" aria-label=\"Link to this section\">#</a></h2>\
+             <h4 id=\"section\">!! <a class=\"heading-anchor\" href=\"#section\" aria-label=\"Link to this section\">#</a></h4>"
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! GitHub Actions annotations for check results (`--format github`)
+//!
+//! Each problem becomes a `::error file=...,line=...::message` workflow
+//! command on stdout, which GitHub shows on the matching line of a PR diff.
+
+use std::fmt;
+use std::fmt::Write as _;
+
+/// Annotation severity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    /// Fails the check
+    Error,
+    /// Advisory
+    Warning,
+}
+
+/// One problem to annotate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    /// Severity
+    pub level: Level,
+    /// Path relative to the repository root
+    pub file: Option<String>,
+    /// 1-based line
+    pub line: Option<usize>,
+    /// Description
+    pub message: String,
+}
+
+impl Annotation {
+    /// Error annotation for `file`, optionally at `line`
+    pub fn error(file: impl Into<String>, line: Option<usize>, message: impl Into<String>) -> Self {
+        Self { level: Level::Error, file: Some(file.into()), line, message: message.into() }
+    }
+
+    /// Warning annotation for `file`, optionally at `line`
+    pub fn warning(file: impl Into<String>, line: Option<usize>, message: impl Into<String>) -> Self {
+        Self { level: Level::Warning, file: Some(file.into()), line, message: message.into() }
+    }
+
+    /// Annotation not tied to a file
+    #[cfg(any(feature = "network", test))]
+    pub fn general(level: Level, message: impl Into<String>) -> Self {
+        Self { level, file: None, line: None, message: message.into() }
+    }
+}
+
+impl fmt::Display for Annotation {
+    /// Plain `file:line: message`, for `--format text`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, "{file}:{line}: {}", self.message),
+            (Some(file), None) => write!(f, "{file}: {}", self.message),
+            (None, _) => f.write_str(&self.message),
+        }
+    }
+}
+
+/// Escape a command's message
+fn escape_data(value: &str) -> String {
+    value.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+/// Escape a command property value
+fn escape_property(value: &str) -> String {
+    escape_data(value).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// The workflow command line for an annotation
+pub fn command(annotation: &Annotation) -> String {
+    let name = match annotation.level {
+        Level::Error => "error",
+        Level::Warning => "warning",
+    };
+    let mut properties = Vec::new();
+    if let Some(file) = &annotation.file {
+        properties.push(format!("file={}", escape_property(file)));
+    }
+    if let Some(line) = annotation.line {
+        properties.push(format!("line={line}"));
+    }
+    let mut command = format!("::{name}");
+    if !properties.is_empty() {
+        let _ = write!(command, " {}", properties.join(","));
+    }
+    let _ = write!(command, "::{}", escape_data(&annotation.message));
+    command
+}
+
+/// Print annotations to stdout, where the Actions runner reads them
+pub fn print(annotations: &[Annotation]) {
+    for annotation in annotations {
+        println!("{}", command(annotation));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workflow_commands() {
+        assert_eq!(
+            command(&Annotation::error("content/post.md", Some(12), "Unknown word \"teh\"")),
+            "::error file=content/post.md,line=12::Unknown word \"teh\""
+        );
+        assert_eq!(
+            command(&Annotation::error("dist/a,b:c.html", None, "100% broken\nlink")),
+            "::error file=dist/a%2Cb%3Ac.html::100%25 broken%0Alink"
+        );
+        assert_eq!(
+            command(&Annotation::general(Level::Warning, "No TLSA record")),
+            "::warning::No TLSA record"
+        );
+    }
+}
@@ -0,0 +1,137 @@
+//! Anonymity mode: no generator identifiers, author names or times of day in the output
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveTime, Utc};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+use crate::{Config, Post};
+
+/// `<meta name="generator|author">`, `article:author` and `rel="author"` links
+static META_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?is)<meta\s[^>]*(?:name|property)\s*=\s*["']?(?:generator|author|article:author)["']?[^>]*>\n?|<link\s[^>]*rel\s*=\s*["']?author["']?[^>]*>\n?"#,
+    )
+    .unwrap()
+});
+
+/// Atom `<generator>` element
+static FEED_GENERATOR_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?s)[ \t]*<generator\b[^>]*>.*?</generator>\n?").unwrap());
+
+/// RFC 3339 timestamps in date fields only: `datetime=` attributes, Atom
+/// `<published>`/`<updated>`, and JSON-LD, JSON Feed and ActivityPub date keys.
+/// Timestamps in post text, code blocks and URLs are content and left alone.
+static TIMESTAMP_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(\bdatetime\s*=\s*["']?|<(?:published|updated)>\s*|"(?:date(?:Published|Modified|Created)|date_(?:published|modified)|published|updated)"\s*:\s*")(\d{4}-\d{2}-\d{2}T\d{2}:\d{2}(?::\d{2}(?:\.\d+)?)?(?:Z|[+-]\d{2}:\d{2})?)"#,
+    )
+    .unwrap()
+});
+
+/// Start of the UTC day containing `date`
+pub fn midnight(date: DateTime<Utc>) -> DateTime<Utc> {
+    date.date_naive().and_time(NaiveTime::MIN).and_utc()
+}
+
+/// Truncate post dates to UTC midnight before anything is rendered
+pub fn normalize_dates(posts: &mut [Post]) {
+    for post in posts {
+        post.meta.date = midnight(post.meta.date);
+        post.meta.updated = post.meta.updated.map(midnight).filter(|updated| *updated > post.meta.date);
+    }
+}
+
+/// Site config whose author is the site itself, for feeds, JSON-LD and h-cards
+pub fn site_config(config: &Config) -> Config {
+    Config { author: config.title.clone(), ..config.clone() }
+}
+
+/// Rewrite one date field's timestamp as UTC midnight, leaving strings that do not parse alone
+fn timestamp_to_midnight(caps: &Captures<'_>) -> String {
+    let (field, text) = (&caps[1], &caps[2]);
+    DateTime::parse_from_rfc3339(text).map_or_else(
+        |_| caps[0].to_string(),
+        |date| format!("{field}{}", midnight(date.with_timezone(&Utc)).to_rfc3339()),
+    )
+}
+
+/// Remove identifying metadata from a generated HTML or XML document
+pub fn scrub(document: &str) -> String {
+    let document = META_PATTERN.replace_all(document, "");
+    let document = FEED_GENERATOR_PATTERN.replace_all(&document, "");
+    TIMESTAMP_PATTERN.replace_all(&document, timestamp_to_midnight).into_owned()
+}
+
+/// Scrub every HTML, XML and JSON file in the output
+pub fn apply(output_dir: &Path) -> Result<()> {
+    for entry in WalkDir::new(output_dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| matches!(e.path().extension().and_then(|s| s.to_str()), Some("html" | "htm" | "xml" | "json")))
+    {
+        let path = entry.path();
+        let document = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let scrubbed = scrub(&document);
+        if scrubbed != document {
+            fs::write(path, scrubbed).with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_midnight() {
+        let date = Utc.with_ymd_and_hms(2024, 3, 1, 23, 59, 0).unwrap();
+        assert_eq!(midnight(date), Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_normalize_drops_same_day_update() {
+        let mut post = Post::default();
+        post.meta.date = Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap();
+        post.meta.updated = Some(Utc.with_ymd_and_hms(2024, 3, 1, 18, 0, 0).unwrap());
+        normalize_dates(std::slice::from_mut(&mut post));
+        assert_eq!(post.meta.date.to_rfc3339(), "2024-03-01T00:00:00+00:00");
+        assert_eq!(post.meta.updated, None);
+    }
+
+    #[test]
+    fn test_scrub_removes_generator_and_author() {
+        let html = "<head>\n<meta name=\"generator\" content=\"secureblog-rs 0.1.0\">\n<meta name=\"author\" content=\"Ada\">\n<link rel=\"author\" href=\"/about\">\n<title>x</title></head>";
+        assert_eq!(scrub(html), "<head>\n<title>x</title></head>");
+
+        let feed = "<feed>\n  <generator version=\"0.1\">secureblog-rs</generator>\n  <title>x</title>\n</feed>";
+        assert_eq!(scrub(feed), "<feed>\n  <title>x</title>\n</feed>");
+    }
+
+    #[test]
+    fn test_scrub_converts_timestamps_to_utc_midnight() {
+        let html = "<time datetime=\"2024-03-01T22:30:00-05:00\">March 1</time>";
+        assert_eq!(scrub(html), "<time datetime=\"2024-03-02T00:00:00+00:00\">March 1</time>");
+        assert_eq!(scrub("<updated>2024-03-01T08:15:42Z</updated>"), "<updated>2024-03-01T00:00:00+00:00</updated>");
+        assert_eq!(scrub("version 2024-03-01"), "version 2024-03-01");
+
+        let json_ld = r#"{"datePublished": "2024-03-01T08:15:42Z", "dateModified":"2024-03-02T10:00:00+02:00"}"#;
+        assert_eq!(
+            scrub(json_ld),
+            r#"{"datePublished": "2024-03-01T00:00:00+00:00", "dateModified":"2024-03-02T00:00:00+00:00"}"#
+        );
+    }
+
+    #[test]
+    fn test_scrub_leaves_timestamps_in_content_alone() {
+        let html = "<p>The alert fired at 2024-03-01T08:15:42Z.</p>\n<pre><code>ts=2024-03-01T08:15:42Z</code></pre>\n\
+                    <a href=\"/logs?since=2024-03-01T08:15:42Z\">logs</a>";
+        assert_eq!(scrub(html), html);
+    }
+}
@@ -0,0 +1,94 @@
+//! Archetype templates for `new <kind> "Title"`
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{markdown, slug::slugify_with, Config};
+
+/// Used for `new post` when `archetypes/post.md` does not exist
+const DEFAULT_POST: &str = "---\ntitle: \"{{title}}\"\ndate: {{date}}\nslug: \"{{slug}}\"\ntags: []\nstatus: draft\n---\n\n";
+
+/// Escape a value for use inside a double-quoted YAML string
+fn yaml_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Substitute `{{title}}`, `{{date}}` and `{{slug}}` in an archetype
+pub fn render(template: &str, title: &str, date: DateTime<Utc>, slug: &str) -> String {
+    template
+        .replace("{{title}}", &yaml_escape(title))
+        .replace("{{date}}", &date.to_rfc3339())
+        .replace("{{slug}}", &yaml_escape(slug))
+}
+
+/// Archetype for `kind`, falling back to the built-in post template
+pub fn load(dir: &Path, kind: &str) -> Result<String> {
+    if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        anyhow::bail!("Invalid archetype name: {kind}");
+    }
+
+    let path = dir.join(format!("{kind}.md"));
+    if path.exists() {
+        return fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read archetype: {}", path.display()));
+    }
+    if kind == "post" {
+        return Ok(DEFAULT_POST.to_string());
+    }
+    anyhow::bail!("No archetype for '{kind}' (expected {})", path.display())
+}
+
+/// Create `content/<slug>.md` from the archetype, refusing to overwrite
+pub fn create(config: &Config, kind: &str, title: &str, now: DateTime<Utc>) -> Result<PathBuf> {
+    let slug = slugify_with(title, config.slugs);
+    if slug.is_empty() {
+        anyhow::bail!("Title '{title}' does not produce a usable slug");
+    }
+
+    let content = render(&load(&config.archetypes, kind)?, title, now, &slug);
+    markdown::parse_frontmatter(&content)
+        .with_context(|| format!("Archetype '{kind}' does not produce valid frontmatter"))?;
+
+    let path = config.content.join(format!("{slug}.md"));
+    if path.exists() {
+        anyhow::bail!("{} already exists", path.display());
+    }
+    fs::create_dir_all(&config.content)?;
+    fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_render_substitutes_placeholders() {
+        let date = Utc.with_ymd_and_hms(2024, 3, 1, 9, 30, 0).unwrap();
+        let out = render(DEFAULT_POST, "Say \"hi\"", date, "say-hi");
+        assert!(out.contains("title: \"Say \\\"hi\\\"\"\n"));
+        assert!(out.contains("date: 2024-03-01T09:30:00+00:00\n"));
+        assert!(out.contains("slug: \"say-hi\"\n"));
+        assert!(!out.contains("{{"));
+    }
+
+    #[test]
+    fn test_default_post_is_valid_yaml() {
+        let date = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        let out = render(DEFAULT_POST, "A: \"tricky\" title", date, "a-tricky-title");
+        let yaml = out.trim_start_matches("---\n").split("\n---\n").next().unwrap();
+        let meta: crate::PostMeta = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(meta.title, "A: \"tricky\" title");
+        assert_eq!(meta.status, crate::status::PostStatus::Draft);
+    }
+
+    #[test]
+    fn test_load_rejects_path_traversal() {
+        assert!(load(Path::new("archetypes"), "../secrets").is_err());
+        assert!(load(Path::new("missing-dir"), "note").is_err());
+        assert!(load(Path::new("missing-dir"), "post").is_ok());
+    }
+}
@@ -0,0 +1,147 @@
+//! Site-wide static files (`static/` by default)
+//!
+//! Everything under the directory is copied to the same path in the output,
+//! where the integrity manifest picks it up. Files are checked by extension on
+//! the way: SVGs and PDFs are sanitized, JPEG and PNG images are re-encoded
+//! without EXIF or other metadata, stylesheets must pass the CSS checks, and
+//! JavaScript is rejected outright. A static file may not replace a generated
+//! page or theme asset.
+
+use anyhow::{Context, Result};
+use image::ImageFormat;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use walkdir::WalkDir;
+
+use crate::security::css_violations;
+use crate::svg::sanitize_svg;
+use crate::{gallery, normalize, pdf, SecurityPolicy};
+
+/// Extensions copied from the static directory
+const EXTENSIONS: [&str; 19] = [
+    "png", "jpg", "jpeg", "gif", "webp", "avif", "ico", "svg", "css", "woff", "woff2", "pdf", "txt", "asc", "xml",
+    "json", "webmanifest", "pub", "html",
+];
+
+/// Script extensions, never published
+const SCRIPTS: [&str; 4] = ["js", "mjs", "cjs", "wasm"];
+
+/// A static file ready to write
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaticFile {
+    /// Path relative to the static directory and the output, `/`-separated
+    pub path: String,
+    /// Bytes to publish
+    pub content: Vec<u8>,
+}
+
+/// Validate and transform one file by extension; `None` for unsupported files
+pub fn process(path: &str, raw: Vec<u8>, policy: &SecurityPolicy) -> Result<Option<Vec<u8>>> {
+    let ext = path.rsplit_once('.').map_or("", |(_, ext)| ext).to_ascii_lowercase();
+    if SCRIPTS.contains(&ext.as_str()) {
+        anyhow::bail!("JavaScript is not published");
+    }
+    if !EXTENSIONS.contains(&ext.as_str()) {
+        return Ok(None);
+    }
+    let content = match ext.as_str() {
+        "svg" => sanitize_svg(&String::from_utf8_lossy(&raw)).into_bytes(),
+        "pdf" if !policy.allow_pdf => anyhow::bail!("PDFs are not allowed by the policy"),
+        "pdf" => pdf::sanitize(&raw)?.0,
+        "jpg" | "jpeg" | "png" => {
+            let (image, format) = gallery::decode(&raw).context("Not a readable image")?;
+            let expected = if ext == "png" { ImageFormat::Png } else { ImageFormat::Jpeg };
+            if format != expected {
+                anyhow::bail!("Contents are {format:?}, not .{ext}");
+            }
+            gallery::encode(&image, format)?
+        }
+        "css" => {
+            let css = String::from_utf8(raw).context("Stylesheet is not UTF-8")?;
+            let violations: Vec<String> = css_violations(&css, policy).into_iter().map(|(_, v)| v).collect();
+            if !violations.is_empty() {
+                anyhow::bail!("{}", violations.join(", "));
+            }
+            css.into_bytes()
+        }
+        _ => raw,
+    };
+    Ok(Some(content))
+}
+
+/// Read and process every file under `static_dir`, reporting all rejected files together
+pub fn collect(static_dir: &Path, policy: &SecurityPolicy) -> Result<Vec<StaticFile>> {
+    let mut files = Vec::new();
+    let mut rejected: Vec<(PathBuf, anyhow::Error)> = Vec::new();
+    for entry in WalkDir::new(static_dir).min_depth(1).sort_by_file_name() {
+        let entry = entry?;
+        if entry.path_is_symlink() {
+            warn!("Skipping symlink {}", entry.path().display());
+            continue;
+        }
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let path = normalize::relative(entry.path(), static_dir)?;
+        let raw = fs::read(entry.path()).with_context(|| format!("Failed to read {}", entry.path().display()))?;
+        match process(&path, raw, policy) {
+            Ok(Some(content)) => files.push(StaticFile { path, content }),
+            Ok(None) => warn!("Skipping unsupported static file {}", entry.path().display()),
+            Err(e) => rejected.push((entry.path().to_path_buf(), e)),
+        }
+    }
+    if !rejected.is_empty() {
+        let lines: Vec<String> = rejected.iter().map(|(path, e)| format!("  {}: {e:#}", path.display())).collect();
+        anyhow::bail!("{} static files rejected:\n{}", rejected.len(), lines.join("\n"));
+    }
+    Ok(files)
+}
+
+/// Copy the static directory into the output, if it exists
+pub fn copy(static_dir: &Path, output_dir: &Path, policy: &SecurityPolicy) -> Result<usize> {
+    if !static_dir.is_dir() {
+        return Ok(0);
+    }
+    let files = collect(static_dir, policy)?;
+    for file in &files {
+        let target = output_dir.join(&file.path);
+        if target.exists() {
+            anyhow::bail!("{} would replace generated file {}", static_dir.join(&file.path).display(), target.display());
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&target, &file.content).with_context(|| format!("Failed to write {}", target.display()))?;
+    }
+    info!("📦 Copied {} static files from {}", files.len(), static_dir.display());
+    Ok(files.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, RgbImage};
+
+    #[test]
+    fn test_process_by_extension() {
+        let policy = SecurityPolicy::default();
+        assert!(process("js/app.js", b"alert(1)".to_vec(), &policy).is_err());
+        assert_eq!(process("notes.docx", vec![1], &policy).unwrap(), None);
+        assert_eq!(process("robots.txt", b"User-agent: *".to_vec(), &policy).unwrap().unwrap(), b"User-agent: *");
+
+        let svg = process("logo.svg", br#"<svg><script>x</script><path d="M0 0"/></svg>"#.to_vec(), &policy).unwrap();
+        assert!(!String::from_utf8(svg.unwrap()).unwrap().contains("script"));
+
+        assert!(process("site.css", b"@import 'https://evil.test/x.css';".to_vec(), &policy).is_err());
+        assert!(process("photo.png", b"<html>not an image</html>".to_vec(), &policy).is_err());
+    }
+
+    #[test]
+    fn test_images_are_reencoded_as_their_extension() {
+        let policy = SecurityPolicy::default();
+        let png = gallery::encode(&DynamicImage::ImageRgb8(RgbImage::new(2, 2)), ImageFormat::Png).unwrap();
+        assert!(process("a.png", png.clone(), &policy).unwrap().is_some());
+        assert!(process("a.jpg", png, &policy).is_err());
+    }
+}
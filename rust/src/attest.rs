@@ -0,0 +1,106 @@
+//! `--attest-self`: hash and build metadata of the running generator binary
+//!
+//! CI can pin the expected values in `config.yaml` so a tampered or
+//! mismatched generator is caught before it builds anything.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+
+/// Expected values; each one that is set must match
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ExpectedBinary {
+    /// `sha256:<hex>` of the executable
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// `rustc --version` the binary was built with
+    #[serde(default)]
+    pub rustc: Option<String>,
+    /// `sha256:<hex>` of the `Cargo.lock` it was built from
+    #[serde(default)]
+    pub cargo_lock: Option<String>,
+}
+
+/// Identity of the running binary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfAttestation {
+    /// Generator version
+    pub version: String,
+    /// `sha256:<hex>` of the executable file
+    pub sha256: String,
+    /// Compiler version embedded at build time
+    pub rustc: String,
+    /// `Cargo.lock` hash embedded at build time (`none` without a lock file)
+    pub cargo_lock: String,
+}
+
+impl SelfAttestation {
+    /// Hash the current executable and read the embedded build metadata
+    pub fn collect() -> Result<Self> {
+        let exe = std::env::current_exe().context("Cannot locate the running binary")?;
+        let content = fs::read(&exe).with_context(|| format!("Failed to read {}", exe.display()))?;
+        Ok(Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            sha256: format!("sha256:{:x}", Sha256::digest(content)),
+            rustc: env!("SECUREBLOG_RUSTC_VERSION").to_string(),
+            cargo_lock: env!("SECUREBLOG_CARGO_LOCK_SHA256").to_string(),
+        })
+    }
+
+    /// Differences from the expected values, as `field: expected X, found Y`
+    pub fn mismatches(&self, expected: &ExpectedBinary) -> Vec<String> {
+        [
+            ("sha256", expected.sha256.as_deref(), &self.sha256),
+            ("rustc", expected.rustc.as_deref(), &self.rustc),
+            ("cargo_lock", expected.cargo_lock.as_deref(), &self.cargo_lock),
+        ]
+        .into_iter()
+        .filter_map(|(field, expected, actual)| {
+            let expected = expected?.trim();
+            (!expected.eq_ignore_ascii_case(actual)).then(|| format!("{field}: expected {expected}, found {actual}"))
+        })
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attestation() -> SelfAttestation {
+        SelfAttestation {
+            version: "0.1.0".to_string(),
+            sha256: "sha256:00ff".to_string(),
+            rustc: "rustc 1.82.0 (f6e511eec 2024-10-15)".to_string(),
+            cargo_lock: "sha256:abcd".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_only_set_fields_are_compared() {
+        assert!(attestation().mismatches(&ExpectedBinary::default()).is_empty());
+        let expected = ExpectedBinary { sha256: Some("SHA256:00FF".to_string()), ..ExpectedBinary::default() };
+        assert!(attestation().mismatches(&expected).is_empty());
+    }
+
+    #[test]
+    fn test_mismatches_name_the_field() {
+        let expected = ExpectedBinary {
+            rustc: Some("rustc 1.83.0".to_string()),
+            cargo_lock: Some("sha256:abcd".to_string()),
+            ..ExpectedBinary::default()
+        };
+        assert_eq!(
+            attestation().mismatches(&expected),
+            ["rustc: expected rustc 1.83.0, found rustc 1.82.0 (f6e511eec 2024-10-15)"]
+        );
+    }
+
+    #[test]
+    fn test_collect_reads_running_binary() {
+        let attestation = SelfAttestation::collect().unwrap();
+        assert!(attestation.sha256.starts_with("sha256:"));
+        assert!(!attestation.rustc.is_empty());
+    }
+}
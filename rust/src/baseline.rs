@@ -0,0 +1,204 @@
+//! `.secureblog-baseline.json`: reviewed findings that do not fail the build
+//!
+//! Adopting the validator on an existing site usually turns up findings that
+//! cannot all be fixed at once. Each baselined finding carries the reviewer's
+//! justification; entries without one suppress nothing. Findings not in the
+//! baseline still fail the build, and `baseline update` regenerates the file
+//! from the last build's output, keeping existing justifications.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use tracing::{error, info, warn};
+
+use crate::{explain, links, security, SecurityPolicy};
+
+/// Baseline file, next to `config.yaml`
+pub const BASELINE: &str = ".secureblog-baseline.json";
+
+/// A known finding
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Suppression {
+    /// Finding exactly as reported (`security: ...`, `links: ...`)
+    pub finding: String,
+    /// Why it is acceptable; empty entries are not honoured
+    #[serde(default)]
+    pub justification: String,
+}
+
+/// Baseline file contents
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Baseline {
+    /// Suppressed findings, sorted
+    #[serde(default)]
+    pub findings: Vec<Suppression>,
+}
+
+impl Baseline {
+    /// Read the baseline, empty when the file does not exist
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content).with_context(|| format!("Invalid baseline {}", path.display()))
+    }
+
+    /// Justification for a finding, if it is baselined with one
+    fn justification(&self, finding: &str) -> Option<&str> {
+        self.findings
+            .iter()
+            .find(|s| s.finding == finding)
+            .map(|s| s.justification.trim())
+            .filter(|j| !j.is_empty())
+    }
+}
+
+/// A security violation or broken link in the output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// `security` or `links`
+    pub check: &'static str,
+    /// Rule id for `check --explain` (`javascript`, `external`, ..., `links`)
+    pub rule: &'static str,
+    /// File relative to the output directory
+    pub file: String,
+    /// 1-based line, when known
+    pub line: Option<usize>,
+    /// Description, including the file
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    /// The baseline key: `<check>: <message>`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.check, self.message)
+    }
+}
+
+/// Security violations and broken links in the output
+pub fn findings(output_dir: &Path, policy: &SecurityPolicy) -> Result<Vec<Finding>> {
+    let mut findings: Vec<Finding> = security::output_violations(output_dir, policy)?
+        .into_iter()
+        .map(|v| Finding { check: "security", rule: v.check.id(), message: v.to_string(), file: v.file, line: v.line })
+        .collect();
+    let site = links::scan_site(output_dir)?;
+    findings.extend(links::check_links(&site).into_iter().map(|e| Finding {
+        check: "links",
+        rule: explain::LINKS,
+        message: format!("{} -> {}: {}", e.page, e.link, e.reason),
+        file: e.page,
+        line: None,
+    }));
+    Ok(findings)
+}
+
+/// Findings not covered by a justified baseline entry
+pub fn new_findings<'a>(baseline: &Baseline, findings: &'a [Finding]) -> Vec<&'a Finding> {
+    findings
+        .iter()
+        .filter(|f| baseline.justification(&f.to_string()).is_none())
+        .collect()
+}
+
+/// Fail on findings outside the baseline; note suppressed and stale entries
+pub fn enforce(baseline: &Baseline, findings: &[Finding]) -> Result<()> {
+    let new = new_findings(baseline, findings);
+    let suppressed = findings.len() - new.len();
+    if suppressed > 0 {
+        info!("🗂️  {} findings suppressed by {}", suppressed, BASELINE);
+    }
+    let current: BTreeSet<String> = findings.iter().map(Finding::to_string).collect();
+    for stale in baseline.findings.iter().filter(|s| !current.contains(&s.finding)) {
+        warn!("Baselined finding no longer occurs, remove it: {}", stale.finding);
+    }
+
+    if !new.is_empty() {
+        error!("Findings not in {}:", BASELINE);
+        for finding in &new {
+            error!("  - {}", finding);
+        }
+        anyhow::bail!("Validation failed with {} new findings", new.len());
+    }
+    Ok(())
+}
+
+/// Baseline covering exactly `findings`, keeping justifications already written
+pub fn updated(baseline: &Baseline, findings: &[Finding]) -> Baseline {
+    let existing: BTreeMap<&str, &str> =
+        baseline.findings.iter().map(|s| (s.finding.as_str(), s.justification.as_str())).collect();
+    let mut findings: Vec<Suppression> = findings
+        .iter()
+        .map(|finding| {
+            let finding = finding.to_string();
+            let justification = existing.get(finding.as_str()).copied().unwrap_or_default().to_string();
+            Suppression { finding, justification }
+        })
+        .collect();
+    findings.sort_by(|a, b| a.finding.cmp(&b.finding));
+    findings.dedup();
+    Baseline { findings }
+}
+
+/// Rewrite the baseline from the findings in `output_dir`
+pub fn update(path: &Path, output_dir: &Path, policy: &SecurityPolicy) -> Result<()> {
+    if !output_dir.exists() {
+        anyhow::bail!("No output in {}, run build first", output_dir.display());
+    }
+    let baseline = updated(&Baseline::load(path)?, &findings(output_dir, policy)?);
+    fs::write(path, serde_json::to_string_pretty(&baseline)? + "\n")
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    let unjustified = baseline.findings.iter().filter(|s| s.justification.trim().is_empty()).count();
+    info!("✅ {} findings in {}", baseline.findings.len(), path.display());
+    if unjustified > 0 {
+        warn!("{} findings need a justification before they are suppressed", unjustified);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suppression(finding: &str, justification: &str) -> Suppression {
+        Suppression { finding: finding.to_string(), justification: justification.to_string() }
+    }
+
+    fn finding(check: &'static str, message: &str) -> Finding {
+        Finding { check, rule: check, file: String::new(), line: None, message: message.to_string() }
+    }
+
+    #[test]
+    fn test_only_justified_entries_suppress() {
+        let baseline = Baseline {
+            findings: vec![
+                suppression("security: Inline styles found in legacy/old.html", "Pre-2019 archive, frozen"),
+                suppression("links: a.html -> b.html: missing", ""),
+            ],
+        };
+        let findings = [
+            finding("security", "Inline styles found in legacy/old.html"),
+            finding("links", "a.html -> b.html: missing"),
+            finding("security", "JavaScript file found in app.js"),
+        ];
+        assert_eq!(new_findings(&baseline, &findings), [&findings[1], &findings[2]]);
+        assert!(enforce(&baseline, &findings).is_err());
+        assert!(enforce(&baseline, &findings[..1]).is_ok());
+    }
+
+    #[test]
+    fn test_update_keeps_justifications_and_drops_fixed_findings() {
+        let baseline = Baseline {
+            findings: vec![suppression("security: b", "Reviewed"), suppression("security: fixed", "Old")],
+        };
+        let findings = [finding("security", "b"), finding("links", "a")];
+        assert_eq!(
+            updated(&baseline, &findings).findings,
+            [suppression("links: a", ""), suppression("security: b", "Reviewed")]
+        );
+    }
+}
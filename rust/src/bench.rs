@@ -0,0 +1,151 @@
+//! `bench --synthetic N`: timings of the build's hot paths on generated posts
+//!
+//! N posts with headings, lists, tables, code and links are rendered,
+//! sanitized, validated and hashed with the default security policy, and
+//! each stage's wall time is reported. The posts depend only on N and the
+//! site config is not read, so runs of two releases on the same machine are
+//! directly comparable. `cargo bench` runs the same command under Criterion.
+
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::{checksums, security, SecurityPolicy};
+
+/// Wall time of one stage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stage {
+    /// `render`, `sanitize`, `validate` or `hash`
+    pub name: &'static str,
+    /// Posts or pages processed
+    pub items: usize,
+    /// Time for all of them
+    pub elapsed: Duration,
+}
+
+/// Source of synthetic post `index`, the same on every run
+pub fn synthetic_post(index: usize) -> String {
+    let mut post = format!(
+        "---\ntitle: \"Synthetic post {index}\"\nslug: bench-{index}\ndate: 2024-01-01T00:00:00Z\ntags: [bench, tag{}]\n---\n\n",
+        index % 10
+    );
+    for section in 0..8 {
+        let _ = write!(
+            post,
+            "## Section {section}\n\nHardening note {index}.{section} with *emphasis*, `inline code` and a \
+             [reference](https://example.org/{index}/{section}) to the [previous post](/bench-{}.html).\n\n\
+             - first point\n- second point with **bold** text\n- third point\n\n\
+             | Setting | Value |\n|---|---|\n| ssl_protocols | TLSv1.3 |\n| server_tokens | off |\n\n\
+             ```nginx\nserver {{\n    listen 443 ssl;\n    add_header X-Frame-Options DENY;\n}}\n```\n\n",
+            index.saturating_sub(1)
+        );
+    }
+    post
+}
+
+fn timed<T>(name: &'static str, items: usize, stages: &mut Vec<Stage>, run: impl FnOnce() -> Result<T>) -> Result<T> {
+    let start = Instant::now();
+    let result = run()?;
+    stages.push(Stage { name, items, elapsed: start.elapsed() });
+    Ok(result)
+}
+
+/// Render, sanitize, validate and hash `posts` synthetic posts in a scratch directory
+pub fn run(posts: usize) -> Result<Vec<Stage>> {
+    // Default checks, with the host the synthetic posts link to allowed
+    let policy = SecurityPolicy { allowed_hosts: vec!["example.org".to_string()], ..SecurityPolicy::default() };
+    let scratch = std::env::temp_dir().join(format!("secureblog-bench-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&scratch);
+    fs::create_dir_all(&scratch).with_context(|| format!("Failed to create {}", scratch.display()))?;
+    let sources: Vec<String> = (0..posts).map(synthetic_post).collect();
+    let site = crate::SiteData::load()?;
+    let mut stages = Vec::new();
+
+    let rendered = timed("render", posts, &mut stages, || {
+        sources
+            .iter()
+            .enumerate()
+            .map(|(index, source)| {
+                let path = PathBuf::from(format!("content/bench-{index}.md"));
+                crate::parse_post(&path, source, chrono_tz::UTC, &policy, &site)
+            })
+            .collect::<Result<Vec<_>>>()
+    })?;
+
+    timed("sanitize", posts, &mut stages, || {
+        Ok(rendered.iter().map(|post| security::sanitize_html(&post.html, &policy).len()).sum::<usize>())
+    })?;
+
+    let pages = write_pages(&scratch, &rendered)?;
+    let violations = timed("validate", posts, &mut stages, || security::output_violations(&scratch, &policy))?;
+    if let Some(violation) = violations.first() {
+        anyhow::bail!("Synthetic pages failed validation: {violation}");
+    }
+
+    timed("hash", posts, &mut stages, || pages.iter().map(|page| checksums::hash_file(page)).collect::<Result<Vec<_>>>())?;
+
+    fs::remove_dir_all(&scratch).with_context(|| format!("Failed to remove {}", scratch.display()))?;
+    Ok(stages)
+}
+
+/// One minimal page per post, as the validator and hasher see output
+fn write_pages(dir: &Path, posts: &[crate::Post]) -> Result<Vec<PathBuf>> {
+    posts
+        .iter()
+        .map(|post| {
+            let page = dir.join(post.path());
+            let html = format!(
+                "<!DOCTYPE html>\n<html lang=\"en\"><head><meta charset=\"utf-8\"><title>{}</title></head>\n\
+                 <body><article>{}</article></body></html>\n",
+                security::escape_html(&post.meta.title),
+                post.html
+            );
+            fs::write(&page, html).with_context(|| format!("Failed to write {}", page.display()))?;
+            Ok(page)
+        })
+        .collect()
+}
+
+/// Stage timings as an aligned table with the time per item
+pub fn table(stages: &[Stage]) -> String {
+    let mut table = format!("{:<10} {:>8} {:>12} {:>14}\n", "stage", "items", "total ms", "per item µs");
+    for stage in stages {
+        let per_item = stage.elapsed.as_secs_f64() * 1e6 / stage.items.max(1) as f64;
+        let _ = writeln!(
+            table,
+            "{:<10} {:>8} {:>12.1} {:>14.1}",
+            stage.name,
+            stage.items,
+            stage.elapsed.as_secs_f64() * 1e3,
+            per_item
+        );
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_synthetic_posts_are_stable() {
+        assert_eq!(synthetic_post(7), synthetic_post(7));
+        assert_ne!(synthetic_post(7), synthetic_post(8));
+        let (meta, _) = crate::markdown::parse_frontmatter(&synthetic_post(3)).unwrap();
+        assert_eq!(meta.title, "Synthetic post 3");
+        assert_eq!(meta.slug, "bench-3");
+        assert_eq!(meta.tags, ["bench", "tag3"]);
+    }
+
+    #[test]
+    fn test_table() {
+        let stages = [Stage { name: "render", items: 4, elapsed: Duration::from_millis(2) }];
+        assert_eq!(
+            table(&stages),
+            "stage         items     total ms    per item µs\n\
+             render            4          2.0          500.0\n"
+        );
+    }
+}
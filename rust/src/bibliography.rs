@@ -0,0 +1,275 @@
+//! `[@key]` citations resolved against bibliography data files
+//!
+//! Sources come from the site-wide `data/bibliography.yaml` and, overriding
+//! it key by key, the post's own `<post>.bibliography.yaml` next to its
+//! source (`index.bibliography.yaml` in a page bundle). Cited sources are
+//! numbered in order of first citation, each citation links to its entry and
+//! the post ends with a "Sources" list. A source can be a file of the post's
+//! page bundle: it links to the published copy with its SHA-256, so readers
+//! can check they have the document that was cited.
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::bundles::Asset;
+use crate::security::{escape_html as escape, external_link_rel};
+use crate::SecurityPolicy;
+
+/// Sources every post can cite
+pub const SITE_BIBLIOGRAPHY: &str = "data/bibliography.yaml";
+
+/// One citable source
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Source {
+    pub title: String,
+    #[serde(default)]
+    pub author: Option<String>,
+    /// Publication date or year, as written
+    #[serde(default)]
+    pub date: Option<String>,
+    #[serde(default)]
+    pub url: Option<String>,
+    /// File of the post's page bundle, relative to its `index.md`
+    #[serde(default)]
+    pub file: Option<String>,
+}
+
+/// Citation key → source
+pub type Bibliography = BTreeMap<String, Source>;
+
+/// `[@key]` or `[@a; @b]`
+static CITATION: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[(@[\w:.-]+(?:\s*;\s*@[\w:.-]+)*)\]").unwrap());
+
+/// Start of a fenced code block (citations inside one are left alone)
+static FENCE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s{0,3}(`{3,}|~{3,})").unwrap());
+
+/// Text a citation becomes until the rendered HTML is available
+fn placeholder(index: usize) -> String {
+    format!("SECUREBLOG-CITE-{index}")
+}
+
+/// The sources a post cites, in order of first citation, and the numbers cited at each citation
+#[derive(Debug, Clone, Default)]
+pub struct Citations {
+    pub sources: Vec<(String, Source)>,
+    groups: Vec<Vec<usize>>,
+}
+
+/// The post's own bibliography file
+pub fn post_bibliography(source: &Path) -> PathBuf {
+    source.with_extension("bibliography.yaml")
+}
+
+fn load_file(path: &Path) -> Result<Bibliography> {
+    if !path.exists() {
+        return Ok(Bibliography::new());
+    }
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_yaml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// The site bibliography, read once per build
+pub fn load_site() -> Result<Bibliography> {
+    load_file(Path::new(SITE_BIBLIOGRAPHY))
+}
+
+/// The site bibliography with the post's entries over it
+pub fn load(site: &Bibliography, source: &Path) -> Result<Bibliography> {
+    let mut bibliography = site.clone();
+    bibliography.extend(load_file(&post_bibliography(source))?);
+    Ok(bibliography)
+}
+
+impl Citations {
+    /// Number of `key`, assigning the next one on first citation
+    fn number(&mut self, key: &str, bibliography: &Bibliography) -> Result<usize> {
+        if let Some(index) = self.sources.iter().position(|(cited, _)| cited == key) {
+            return Ok(index + 1);
+        }
+        let source = bibliography.get(key).with_context(|| format!("Unknown citation [@{key}]"))?;
+        self.sources.push((key.to_string(), source.clone()));
+        Ok(self.sources.len())
+    }
+}
+
+/// Replace the citations outside code with placeholders, numbering the sources
+pub fn expand(markdown: &str, bibliography: &Bibliography) -> Result<(String, Citations)> {
+    let mut citations = Citations::default();
+    if !markdown.contains("[@") {
+        return Ok((markdown.to_string(), citations));
+    }
+    let mut output = String::with_capacity(markdown.len());
+    let mut fence: Option<String> = None;
+    for (number, line) in markdown.lines().enumerate() {
+        if let Some(open) = &fence {
+            if line.trim_start().starts_with(open.as_str()) {
+                fence = None;
+            }
+        } else if let Some(capture) = FENCE.captures(line) {
+            fence = Some(capture[1].to_string());
+        } else {
+            // Odd segments between backticks are code spans
+            for (i, segment) in line.split('`').enumerate() {
+                if i > 0 {
+                    output.push('`');
+                }
+                if i % 2 == 1 {
+                    output.push_str(segment);
+                    continue;
+                }
+                let mut last = 0;
+                for capture in CITATION.captures_iter(segment) {
+                    let whole = capture.get(0).unwrap();
+                    let mut group = Vec::new();
+                    for key in capture[1].split(';') {
+                        let key = key.trim().trim_start_matches('@');
+                        group.push(citations.number(key, bibliography).with_context(|| format!("line {}", number + 1))?);
+                    }
+                    output.push_str(&segment[last..whole.start()]);
+                    output.push_str(&placeholder(citations.groups.len()));
+                    citations.groups.push(group);
+                    last = whole.end();
+                }
+                output.push_str(&segment[last..]);
+            }
+            output.push('\n');
+            continue;
+        }
+        output.push_str(line);
+        output.push('\n');
+    }
+    Ok((output, citations))
+}
+
+/// Published copy of a bundle file
+fn bundle_asset<'a>(key: &str, file: &str, assets: &'a [Asset]) -> Result<&'a Asset> {
+    let file = file.trim_start_matches("./");
+    assets
+        .iter()
+        .find(|asset| asset.source == file)
+        .with_context(|| format!("Source {key}: {file} is not a published file of this page bundle"))
+}
+
+/// One entry of the sources list
+fn entry(number: usize, key: &str, source: &Source, assets: &[Asset], rel: &str, policy: &SecurityPolicy) -> Result<String> {
+    let mut li = format!("<li id=\"source-{number}\">");
+    if let Some(author) = &source.author {
+        let _ = write!(li, "{}, ", escape(author));
+    }
+    let _ = write!(li, "<cite>{}</cite>", escape(&source.title));
+    if let Some(date) = &source.date {
+        let _ = write!(li, " ({})", escape(date));
+    }
+    li.push('.');
+    if let Some(url) = &source.url {
+        if !url.starts_with("https://") || url.contains(['"', '\'', '<', '>', ' ']) {
+            anyhow::bail!("Source {key}: url must be a plain https:// URL, got {url:?}");
+        }
+        if policy.no_external && !policy.allows_url(url) {
+            anyhow::bail!("Source {key}: {url} is not in allowed_hosts (no_external is set)");
+        }
+        let _ = write!(li, " <a href=\"{0}\" rel=\"{rel}\">{0}</a>", escape(url));
+    }
+    if let Some(file) = &source.file {
+        let asset = bundle_asset(key, file, assets)?;
+        let hash = format!("{:x}", Sha256::digest(&asset.content));
+        let _ = write!(
+            li,
+            " <a href=\"/{}\">{}</a> <span class=\"attachment\">(SHA-256 <code>{}</code>)</span>",
+            escape(&asset.path),
+            escape(&asset.source),
+            hash
+        );
+    }
+    li.push_str("</li>\n");
+    Ok(li)
+}
+
+/// Put the numbered citations where the placeholders were rendered and append the sources list
+pub fn insert(html: &str, citations: &Citations, assets: &[Asset], policy: &SecurityPolicy) -> Result<String> {
+    if citations.sources.is_empty() {
+        return Ok(html.to_string());
+    }
+    let mut html = html.to_string();
+    // Highest first, so `-1` never replaces the start of `-12`
+    for (index, group) in citations.groups.iter().enumerate().rev() {
+        let links: Vec<String> = group.iter().map(|n| format!("<a href=\"#source-{n}\">{n}</a>")).collect();
+        html = html.replace(&placeholder(index), &format!("<sup class=\"citation\">[{}]</sup>", links.join(", ")));
+    }
+
+    let rel = external_link_rel(policy);
+    html.push_str("<section class=\"sources\">\n<h2>Sources</h2>\n<ol>\n");
+    for (index, (key, source)) in citations.sources.iter().enumerate() {
+        html.push_str(&entry(index + 1, key, source, assets, &rel, policy)?);
+    }
+    html.push_str("</ol>\n</section>\n");
+    Ok(html)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BIBLIOGRAPHY: &str = r#"
+cve:
+  title: "CVE-2024-3094 <xz backdoor>"
+  author: "NVD"
+  date: "2024"
+  url: "https://nvd.example/CVE-2024-3094"
+advisory:
+  title: "Vendor advisory"
+  file: "advisory.txt"
+"#;
+
+    fn bibliography() -> Bibliography {
+        serde_yaml::from_str(BIBLIOGRAPHY).unwrap()
+    }
+
+    #[test]
+    fn test_expand_numbers_in_order_of_first_citation() {
+        let markdown = "See [@advisory; @cve].\n\nAgain [@cve] but not `[@cve]`.\n\n```\n[@missing]\n```\n";
+        let (expanded, citations) = expand(markdown, &bibliography()).unwrap();
+        assert_eq!(
+            expanded,
+            "See SECUREBLOG-CITE-0.\n\nAgain SECUREBLOG-CITE-1 but not `[@cve]`.\n\n```\n[@missing]\n```\n"
+        );
+        let keys: Vec<&str> = citations.sources.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, ["advisory", "cve"]);
+        assert_eq!(citations.groups, [vec![1, 2], vec![2]]);
+
+        let error = expand("Line\n[@missing]\n", &bibliography()).unwrap_err();
+        assert_eq!(format!("{error:#}"), "line 2: Unknown citation [@missing]");
+    }
+
+    #[test]
+    fn test_insert_links_and_hashes_sources() {
+        let (_, citations) = expand("[@advisory; @cve] [@cve]", &bibliography()).unwrap();
+        let assets = [Asset {
+            source: "advisory.txt".to_string(),
+            path: "post/advisory.0123456789abcdef.txt".to_string(),
+            content: b"patched".to_vec(),
+            color: None,
+        }];
+        let policy = SecurityPolicy { allowed_hosts: vec!["nvd.example".to_string()], ..SecurityPolicy::default() };
+        let html = insert("<p>SECUREBLOG-CITE-0 SECUREBLOG-CITE-1</p>", &citations, &assets, &policy).unwrap();
+        assert!(html.starts_with(
+            "<p><sup class=\"citation\">[<a href=\"#source-1\">1</a>, <a href=\"#source-2\">2</a>]</sup> <sup class=\"citation\">[<a href=\"#source-2\">2</a>]</sup></p>"
+        ));
+        let hash = format!("{:x}", Sha256::digest(b"patched"));
+        assert!(html.contains(&format!(
+            "<li id=\"source-1\"><cite>Vendor advisory</cite>. <a href=\"/post/advisory.0123456789abcdef.txt\">advisory.txt</a> <span class=\"attachment\">(SHA-256 <code>{hash}</code>)</span></li>"
+        )));
+        assert!(html.contains("<li id=\"source-2\">NVD, <cite>CVE-2024-3094 &lt;xz backdoor&gt;</cite> (2024). <a href=\"https://nvd.example/CVE-2024-3094\" rel=\"noopener noreferrer\">"));
+
+        assert!(insert("", &citations, &[], &policy).is_err());
+        assert!(insert("", &citations, &assets, &SecurityPolicy::default()).is_err());
+    }
+}
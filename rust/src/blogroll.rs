@@ -0,0 +1,205 @@
+//! Blogroll page and webring links from `data/blogroll.yaml`
+//!
+//! The data file lists the blogs the site recommends and, optionally, the
+//! webring it belongs to. The build renders `blogroll/index.html` and adds
+//! the ring's previous/next links to the home page. Every URL is an external
+//! link like any other: it must be https, pass the `no_external` allowlist
+//! and carries the policy's `rel` tokens.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fmt::Write as _;
+use std::fs;
+use tracing::info;
+
+use crate::security::{escape_html as escape, external_link_rel};
+use crate::{inject, Config, SecurityPolicy};
+
+/// Output path of the rendered page
+pub const BLOGROLL_PAGE: &str = "blogroll/index.html";
+
+/// One recommended blog
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Blog {
+    pub name: String,
+    pub url: String,
+    /// Atom or RSS feed
+    #[serde(default)]
+    pub feed: Option<String>,
+    /// One line on why it is worth reading
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// The webring this site is a member of
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Webring {
+    pub name: String,
+    /// Ring home page
+    pub url: String,
+    /// Previous member
+    pub previous: String,
+    /// Next member
+    pub next: String,
+}
+
+/// `data/blogroll.yaml` contents
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlogrollData {
+    #[serde(default)]
+    pub blogroll: Vec<Blog>,
+    #[serde(default)]
+    pub webring: Option<Webring>,
+}
+
+impl BlogrollData {
+    /// Every URL in the file with the field it came from
+    fn urls(&self) -> Vec<(String, &str)> {
+        let mut urls = Vec::new();
+        for blog in &self.blogroll {
+            urls.push((format!("blogroll[{}].url", blog.name), blog.url.as_str()));
+            if let Some(feed) = &blog.feed {
+                urls.push((format!("blogroll[{}].feed", blog.name), feed.as_str()));
+            }
+        }
+        if let Some(ring) = &self.webring {
+            for (field, url) in [("url", &ring.url), ("previous", &ring.previous), ("next", &ring.next)] {
+                urls.push((format!("webring.{field}"), url.as_str()));
+            }
+        }
+        urls
+    }
+
+    /// Require https URLs the policy allows as external resources
+    pub fn validate(&self, policy: &SecurityPolicy) -> Result<()> {
+        for (field, url) in self.urls() {
+            if !url.starts_with("https://") || url.contains(['"', '\'', '<', '>', ' ']) {
+                anyhow::bail!("{field}: must be a plain https:// URL, got {url:?}");
+            }
+            if policy.no_external && !policy.allows_url(url) {
+                anyhow::bail!("{field}: {url} is not in allowed_hosts (no_external is set)");
+            }
+        }
+        Ok(())
+    }
+}
+
+fn link(url: &str, text: &str, rel: &str) -> String {
+    format!("<a href=\"{}\" rel=\"{rel}\">{}</a>", escape(url), escape(text))
+}
+
+/// `<nav>` with the ring's previous, home and next links
+pub fn webring_nav(ring: &Webring, policy: &SecurityPolicy) -> String {
+    let rel = external_link_rel(policy);
+    format!(
+        "<nav class=\"webring\" aria-label=\"{name}\">{previous} · {home} · {next}</nav>\n",
+        name = escape(&ring.name),
+        previous = link(&ring.previous, "← Previous", &rel),
+        home = link(&ring.url, &ring.name, &rel),
+        next = link(&ring.next, "Next →", &rel),
+    )
+}
+
+/// Static blogroll page, in the order of the data file
+pub fn render(config: &Config, data: &BlogrollData, policy: &SecurityPolicy) -> String {
+    let rel = external_link_rel(policy);
+    let title = format!("Blogroll - {}", config.title);
+    let mut html = format!(
+        concat!(
+            "<!DOCTYPE html>\n<html lang=\"{language}\">\n<head>\n<meta charset=\"utf-8\">\n",
+            "<title>{title}</title>\n</head>\n<body>\n<main>\n<h1>{title}</h1>\n<ul class=\"blogroll\">\n",
+        ),
+        language = escape(&config.i18n.language),
+        title = escape(&title),
+    );
+    for blog in &data.blogroll {
+        let _ = write!(html, "<li>{}", link(&blog.url, &blog.name, &rel));
+        if let Some(description) = &blog.description {
+            let _ = write!(html, " - {}", escape(description));
+        }
+        if let Some(feed) = &blog.feed {
+            let _ = write!(html, " ({})", link(feed, "feed", &rel));
+        }
+        html.push_str("</li>\n");
+    }
+    html.push_str("</ul>\n");
+    if let Some(ring) = &data.webring {
+        html.push_str(&webring_nav(ring, policy));
+    }
+    html.push_str("</main>\n</body>\n</html>\n");
+    html
+}
+
+/// Write the blogroll page and the home page's webring links, if the data file exists
+pub fn apply(config: &Config, policy: &SecurityPolicy) -> Result<()> {
+    if !config.blogroll.exists() {
+        return Ok(());
+    }
+    let content = fs::read_to_string(&config.blogroll)
+        .with_context(|| format!("Failed to read {}", config.blogroll.display()))?;
+    let data: BlogrollData =
+        serde_yaml::from_str(&content).with_context(|| format!("Failed to parse {}", config.blogroll.display()))?;
+    data.validate(policy).with_context(|| format!("Invalid {}", config.blogroll.display()))?;
+
+    if !data.blogroll.is_empty() {
+        let path = config.output.join(BLOGROLL_PAGE);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, render(config, &data, policy)).with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    if let Some(ring) = &data.webring {
+        inject::inject_into_file(&config.output.join("index.html"), &["</footer>", "</body>"], &webring_nav(ring, policy))?;
+    }
+    info!("🔗 Blogroll with {} blogs{}", data.blogroll.len(), if data.webring.is_some() { " and webring links" } else { "" });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: &str = r#"
+blogroll:
+  - name: "Ada's <notes>"
+    url: "https://ada.example/"
+    feed: "https://ada.example/atom.xml"
+    description: "Kernel hardening"
+webring:
+  name: "Sec Ring"
+  url: "https://ring.example/"
+  previous: "https://prev.example/"
+  next: "https://next.example/"
+"#;
+
+    fn policy() -> SecurityPolicy {
+        SecurityPolicy {
+            allowed_hosts: ["ada.example", "ring.example", "prev.example", "next.example"].map(String::from).to_vec(),
+            ..SecurityPolicy::default()
+        }
+    }
+
+    #[test]
+    fn test_urls_follow_the_allowlist() {
+        let data: BlogrollData = serde_yaml::from_str(DATA).unwrap();
+        assert!(data.validate(&policy()).is_ok());
+        let error = data.validate(&SecurityPolicy::default()).unwrap_err().to_string();
+        assert!(error.starts_with("blogroll[Ada's <notes>].url: https://ada.example/ is not in allowed_hosts"), "{error}");
+        let plain: BlogrollData = serde_yaml::from_str("blogroll:\n  - { name: x, url: 'http://x.example/' }\n").unwrap();
+        assert!(plain.validate(&SecurityPolicy { no_external: false, ..SecurityPolicy::default() }).is_err());
+    }
+
+    #[test]
+    fn test_render_escapes_and_sets_rel() {
+        let data: BlogrollData = serde_yaml::from_str(DATA).unwrap();
+        let policy = SecurityPolicy { external_link_rel: vec!["nofollow".to_string()], ..policy() };
+        let html = render(&Config::default(), &data, &policy);
+        assert!(html.contains(
+            "<li><a href=\"https://ada.example/\" rel=\"noopener noreferrer nofollow\">Ada&#39;s &lt;notes&gt;</a> - Kernel hardening"
+        ));
+        assert!(html.contains("<a href=\"https://next.example/\" rel=\"noopener noreferrer nofollow\">Next →</a></nav>"));
+    }
+}
@@ -0,0 +1,204 @@
+//! "Built from commit X" provenance footer and the manifest root hash
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use tracing::debug;
+use walkdir::WalkDir;
+
+use crate::{cdn, checksums, inject, normalize};
+use crate::security::escape_html;
+
+/// Files left out of the root hash: the manifest that records it, and the
+/// checksum lists and CDN files written after it from the finished output.
+/// Their detached signatures and Rekor entries (`<name>.<suffix>`) are left out too.
+pub const EXCLUDED: [&str; 6] =
+    ["integrity.json", "SHA256SUMS", "B3SUMS", cdn::BUILD_ID, cdn::CACHE_MANIFEST, cdn::ETAG_MAP];
+
+/// The injected footer element (excluded from the root hash)
+static FOOTER_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"<p class="build-info"[^>]*>.*?</p>\n?"#).unwrap());
+
+/// Provenance of a build, shown on every page and recorded in `integrity.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BuildInfo {
+    /// Source commit (full hex id), if the content is in a git repository
+    pub commit: Option<String>,
+    /// Whether the source commit carries a signature
+    pub signed: bool,
+    /// Generator name and version
+    pub generator: String,
+    /// Root hash over every output file (`sha256:<hex>`)
+    pub root: String,
+}
+
+impl BuildInfo {
+    /// Collect provenance for `output_dir` built from `content_dir` at `rev` (HEAD when `None`)
+    pub fn collect(content_dir: &Path, output_dir: &Path, rev: Option<&str>) -> Result<Self> {
+        let (commit, signed) = source_commit(content_dir, rev).map_or((None, false), |(id, signed)| (Some(id), signed));
+        Ok(Self {
+            commit,
+            signed,
+            generator: format!("secureblog-rs/{}", env!("CARGO_PKG_VERSION")),
+            root: root_hash(output_dir)?,
+        })
+    }
+
+    /// Footer element carrying the provenance in visible text and data attributes
+    pub fn footer(&self) -> String {
+        let commit = self.commit.as_deref().unwrap_or("unknown");
+        let short = commit.get(..12).unwrap_or(commit);
+        format!(
+            "<p class=\"build-info\" data-commit=\"{commit}\" data-signed=\"{signed}\" data-generator=\"{generator}\" data-root=\"{root}\">Built from commit <code>{short}</code>{status} by {generator}, root <code>{root}</code></p>\n",
+            commit = escape_html(commit),
+            signed = self.signed,
+            generator = escape_html(&self.generator),
+            root = escape_html(&self.root),
+            short = escape_html(short),
+            status = if self.signed { " (signed)" } else { "" },
+        )
+    }
+}
+
+/// Commit `rev` (or HEAD) of the repository containing `dir` and whether it is signed
+fn source_commit(dir: &Path, rev: Option<&str>) -> Option<(String, bool)> {
+    let repo = match gix::discover(dir) {
+        Ok(repo) => repo,
+        Err(e) => {
+            debug!("No git repository for build info: {}", e);
+            return None;
+        }
+    };
+    let commit = match rev {
+        Some(rev) => repo.rev_parse_single(rev).ok()?.object().ok()?.peel_to_commit().ok()?,
+        None => repo.head_commit().ok()?,
+    };
+    let signed = commit
+        .decode()
+        .is_ok_and(|decoded| decoded.extra_headers().pgp_signature().is_some());
+    Some((commit.id.to_string(), signed))
+}
+
+/// Remove the build footer so hashing is stable before and after injection
+pub fn strip_footer(html: &str) -> String {
+    FOOTER_PATTERN.replace_all(html, "").into_owned()
+}
+
+/// Whether `relative` is one of the [`EXCLUDED`] files or a signature of one
+pub fn is_excluded(relative: &str) -> bool {
+    EXCLUDED
+        .iter()
+        .any(|name| relative.strip_prefix(name).is_some_and(|rest| rest.is_empty() || rest.starts_with('.')))
+}
+
+/// SHA-256 over the sorted `path sha256` lines of every output file except the [`EXCLUDED`] ones
+///
+/// HTML pages are hashed with the build footer removed. The excluded files
+/// are named rather than relying on the root being computed before they are
+/// written, so verifiers can recompute the root from a deployed site that has
+/// all of them.
+pub fn root_hash(output_dir: &Path) -> Result<String> {
+    let mut hashes = BTreeMap::new();
+    for entry in WalkDir::new(output_dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+    {
+        let path = entry.path();
+        let relative = normalize::relative(path, output_dir)?;
+        if is_excluded(&relative) {
+            continue;
+        }
+
+        // Pages are read whole to drop the footer; anything else is hashed as a stream
+        let hash = if matches!(path.extension().and_then(|s| s.to_str()), Some("html" | "htm")) {
+            let content = fs::read(path)?;
+            match std::str::from_utf8(&content) {
+                Ok(html) => format!("{:x}", Sha256::digest(strip_footer(html).as_bytes())),
+                Err(_) => format!("{:x}", Sha256::digest(&content)),
+            }
+        } else {
+            checksums::hash_file(path)?.sha256
+        };
+        hashes.insert(relative, hash);
+    }
+    Ok(format!("sha256:{}", root_of(&hashes)))
+}
+
+fn root_of(hashes: &BTreeMap<String, String>) -> String {
+    let mut digest = Sha256::new();
+    for (path, hash) in hashes {
+        digest.update(format!("{path} {hash}\n"));
+    }
+    format!("{:x}", digest.finalize())
+}
+
+/// Add the footer to every HTML page in the output
+pub fn apply(output_dir: &Path, info: &BuildInfo) -> Result<()> {
+    let footer = info.footer();
+    for entry in WalkDir::new(output_dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| matches!(e.path().extension().and_then(|s| s.to_str()), Some("html" | "htm")))
+    {
+        inject::inject_into_file(entry.path(), &["</footer>", "</body>"], &footer)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info() -> BuildInfo {
+        BuildInfo {
+            commit: Some("0123456789abcdef0123456789abcdef01234567".to_string()),
+            signed: true,
+            generator: "secureblog-rs/0.1.0".to_string(),
+            root: "sha256:00ff".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_footer_contains_provenance() {
+        let footer = info().footer();
+        assert!(footer.contains("data-commit=\"0123456789abcdef0123456789abcdef01234567\""));
+        assert!(footer.contains("<code>0123456789ab</code> (signed)"));
+        assert!(footer.contains("data-root=\"sha256:00ff\""));
+    }
+
+    #[test]
+    fn test_strip_footer_restores_page() {
+        let page = "<footer><p>2024</p></footer></body>";
+        let with_footer = inject::insert_before(page, &["</footer>"], &info().footer()).unwrap();
+        assert_ne!(with_footer, page);
+        assert_eq!(strip_footer(&with_footer), page);
+    }
+
+    #[test]
+    fn test_manifest_and_checksum_files_are_excluded() {
+        for path in [
+            "integrity.json", "integrity.json.sig", "integrity.json.rekor.json", "SHA256SUMS", "B3SUMS.sig",
+            "build-id.txt", "cache-manifest.json", "etags.map",
+        ] {
+            assert!(is_excluded(path), "{path} included");
+        }
+        for path in ["index.html", "integrity.jsonl", "docs/SHA256SUMS", "atom.xml.sig"] {
+            assert!(!is_excluded(path), "{path} excluded");
+        }
+    }
+
+    #[test]
+    fn test_root_of_depends_on_paths_and_hashes() {
+        let a = BTreeMap::from([("a.html".to_string(), "11".to_string())]);
+        let b = BTreeMap::from([("b.html".to_string(), "11".to_string())]);
+        assert_ne!(root_of(&a), root_of(&b));
+        assert_eq!(root_of(&a), root_of(&a.clone()));
+    }
+}
@@ -0,0 +1,200 @@
+//! `build-report.json`: one machine-readable summary per build for CI to archive
+//!
+//! Posts and pages built, output size, warnings logged, phase timings, the
+//! manifest hash and the tool versions. It is written next to the config, not
+//! into the output, so it never changes the manifest it describes.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context as LayerContext, Layer};
+use walkdir::WalkDir;
+
+use crate::overrides;
+
+/// Warnings logged since startup
+static WARNINGS: AtomicUsize = AtomicUsize::new(0);
+
+/// Tracing layer counting `WARN` events
+pub struct WarningCounter;
+
+impl<S: Subscriber> Layer<S> for WarningCounter {
+    fn on_event(&self, event: &Event<'_>, _ctx: LayerContext<'_, S>) {
+        if *event.metadata().level() == Level::WARN {
+            WARNINGS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Warnings logged so far
+pub fn warnings() -> usize {
+    WARNINGS.load(Ordering::Relaxed)
+}
+
+/// Duration of one build phase
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Timing {
+    /// Phase name (`load`, `generate`, ...)
+    pub phase: &'static str,
+    /// Wall-clock milliseconds
+    pub ms: u64,
+}
+
+/// Phase timer
+#[derive(Debug)]
+pub struct Timings {
+    start: Instant,
+    last: Instant,
+    warnings: usize,
+    phases: Vec<Timing>,
+}
+
+impl Timings {
+    /// Start timing a build
+    pub fn start() -> Self {
+        let now = Instant::now();
+        Self { start: now, last: now, warnings: warnings(), phases: Vec::new() }
+    }
+
+    /// End `phase` and start the next one
+    pub fn lap(&mut self, phase: &'static str) {
+        let now = Instant::now();
+        self.phases.push(Timing { phase, ms: millis(now - self.last) });
+        self.last = now;
+    }
+}
+
+fn millis(duration: std::time::Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+/// Versions of the tools that produced the build
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Tools {
+    /// Generator name and version
+    pub generator: String,
+    /// Compiler the generator was built with
+    pub rustc: String,
+    /// `Cargo.lock` hash the generator was built from
+    pub cargo_lock: String,
+}
+
+impl Tools {
+    /// Versions embedded in this binary
+    pub fn current() -> Self {
+        Self {
+            generator: format!("secureblog-rs/{}", env!("CARGO_PKG_VERSION")),
+            rustc: env!("SECUREBLOG_RUSTC_VERSION").to_string(),
+            cargo_lock: env!("SECUREBLOG_CARGO_LOCK_SHA256").to_string(),
+        }
+    }
+}
+
+/// Counts of what a build published
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PostCounts {
+    /// Published posts
+    pub published: usize,
+    /// Posts in the review tree
+    pub review: usize,
+    /// Archived posts kept as tombstones
+    pub archived: usize,
+}
+
+/// `build-report.json` contents
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BuildReport {
+    /// Manifest root hash, the same as `build-id.txt`
+    pub build_id: String,
+    /// `sha256:<hex>` of `integrity.json`
+    pub manifest_sha256: String,
+    /// Posts by outcome
+    pub posts: PostCounts,
+    /// HTML pages in the output
+    pub pages: usize,
+    /// Files in the output
+    pub files: usize,
+    /// Bytes in the output
+    pub total_bytes: u64,
+    /// Warnings logged during the build
+    pub warnings: usize,
+    /// Per-page and per-section security policy overrides in effect
+    pub overrides: Vec<overrides::Record>,
+    /// Wall-clock milliseconds for the whole build
+    pub total_ms: u64,
+    /// Per-phase timings, in build order
+    pub timings: Vec<Timing>,
+    /// Generator and toolchain versions
+    pub tools: Tools,
+}
+
+impl BuildReport {
+    /// Summarize the finished output
+    pub fn collect(
+        output_dir: &Path,
+        build_id: &str,
+        posts: PostCounts,
+        overrides: Vec<overrides::Record>,
+        timings: Timings,
+    ) -> Result<Self> {
+        let manifest = fs::read(output_dir.join("integrity.json")).context("Failed to read integrity.json")?;
+        let (mut pages, mut files, mut total_bytes) = (0, 0, 0);
+        for entry in WalkDir::new(output_dir).into_iter().filter_map(Result::ok).filter(|e| e.file_type().is_file()) {
+            files += 1;
+            total_bytes += entry.metadata()?.len();
+            if entry.path().extension().is_some_and(|ext| ext == "html") {
+                pages += 1;
+            }
+        }
+        Ok(Self {
+            build_id: build_id.to_string(),
+            manifest_sha256: format!("sha256:{:x}", Sha256::digest(&manifest)),
+            posts,
+            pages,
+            files,
+            total_bytes,
+            warnings: warnings().saturating_sub(timings.warnings),
+            overrides,
+            total_ms: millis(timings.start.elapsed()),
+            timings: timings.phases,
+            tools: Tools::current(),
+        })
+    }
+
+    /// Write the report as pretty JSON
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)? + "\n")
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_counts_output() {
+        let dir = std::env::temp_dir().join(format!("secureblog-build-report-{}", std::process::id()));
+        fs::create_dir_all(dir.join("css")).unwrap();
+        fs::write(dir.join("integrity.json"), "{}").unwrap();
+        fs::write(dir.join("index.html"), "<p>hi</p>").unwrap();
+        fs::write(dir.join("css/site.css"), "p{}").unwrap();
+
+        let mut timings = Timings::start();
+        timings.lap("load");
+        timings.lap("generate");
+        let posts = PostCounts { published: 1, ..PostCounts::default() };
+        let report = BuildReport::collect(&dir, "sha256:root", posts, Vec::new(), timings).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!((report.pages, report.files, report.total_bytes), (1, 3, 14));
+        assert_eq!(report.manifest_sha256, format!("sha256:{:x}", Sha256::digest(b"{}")));
+        assert_eq!(report.timings.iter().map(|t| t.phase).collect::<Vec<_>>(), ["load", "generate"]);
+        assert!(report.tools.generator.starts_with("secureblog-rs/"));
+    }
+}
@@ -0,0 +1,193 @@
+//! Page bundles: `content/my-post/index.md` with its files alongside
+//!
+//! Colocated assets are published under `<slug>/` with a content hash in the
+//! name, SVGs and PDFs are sanitized on the way, and relative `src`/`href`
+//! references in the post are rewritten to the published paths.
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+use walkdir::WalkDir;
+
+use crate::slug::slugify;
+use crate::svg::sanitize_svg;
+use crate::{bibliography, normalize, pdf, placeholders, Post, SecurityPolicy};
+
+/// Post file that makes its directory a bundle
+pub const INDEX: &str = "index.md";
+
+/// Extensions published from a bundle (`.md` and `.css` are handled elsewhere)
+const ASSET_EXTENSIONS: [&str; 10] = ["png", "jpg", "jpeg", "gif", "webp", "avif", "svg", "pdf", "txt", "asc"];
+
+/// Relative reference in rendered HTML
+static REFERENCE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\b(src|href)="([^"]*)""#).unwrap());
+
+/// A processed file published with its post
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Asset {
+    /// Path relative to the bundle directory, `/`-separated
+    pub source: String,
+    /// Published path relative to the site root
+    pub path: String,
+    /// Bytes written to the output
+    pub content: Vec<u8>,
+    /// Dominant colour of a raster image, shown while it loads
+    pub color: Option<[u8; 3]>,
+}
+
+/// Whether `source` is the `index.md` of a page bundle
+pub fn is_bundle(source: &Path) -> bool {
+    source.file_name().and_then(|s| s.to_str()) == Some(INDEX)
+}
+
+/// Slug of a bundle: its directory name
+pub fn bundle_slug(source: &Path) -> Option<String> {
+    let dir = source.parent()?.file_name()?.to_str()?;
+    Some(slugify(dir)).filter(|slug| !slug.is_empty())
+}
+
+/// Published name: `<slug>/<stem>.<hash>.<ext>`, keeping subdirectories
+fn published_path(slug: &str, source: &str, content: &[u8]) -> String {
+    let hash = format!("{:x}", Sha256::digest(content));
+    let (dir, name) = source.rsplit_once('/').map_or(("", source), |(dir, name)| (dir, name));
+    let (stem, ext) = name.rsplit_once('.').unwrap_or((name, ""));
+    let dir = if dir.is_empty() { String::new() } else { format!("{dir}/") };
+    format!("{slug}/{dir}{stem}.{}.{ext}", &hash[..16])
+}
+
+/// Collect and process the assets next to a bundle's `index.md`
+///
+/// Nested bundles and symlinks are skipped; other files must have an allowed extension.
+pub fn collect(source: &Path, slug: &str, policy: &SecurityPolicy) -> Result<Vec<Asset>> {
+    let dir = source.parent().unwrap_or_else(|| Path::new("."));
+    let mut assets = Vec::new();
+    let mut walker = WalkDir::new(dir).min_depth(1).sort_by_file_name().into_iter();
+    while let Some(entry) = walker.next() {
+        let entry = entry?;
+        if entry.file_type().is_dir() && entry.path().join(INDEX).exists() {
+            walker.skip_current_dir();
+            continue;
+        }
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let ext = entry.path().extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+        if ext == "md" || ext == "markdown" || ext == "css" || entry.path() == bibliography::post_bibliography(source) {
+            continue;
+        }
+        if !ASSET_EXTENSIONS.contains(&ext.as_str()) {
+            warn!("Skipping unsupported bundle file {}", entry.path().display());
+            continue;
+        }
+
+        let relative = normalize::relative(entry.path(), dir)?;
+        let raw = fs::read(entry.path()).with_context(|| format!("Failed to read {}", entry.path().display()))?;
+        let (content, color) = match ext.as_str() {
+            "svg" => (sanitize_svg(&String::from_utf8_lossy(&raw)).into_bytes(), None),
+            "pdf" if !policy.allow_pdf => anyhow::bail!("{} is a PDF, which the policy does not allow", entry.path().display()),
+            "pdf" => {
+                let (clean, removed) =
+                    pdf::sanitize(&raw).with_context(|| format!("Failed to sanitize {}", entry.path().display()))?;
+                if removed > 0 {
+                    warn!("Removed {} active or identifying entries from {}", removed, entry.path().display());
+                }
+                (clean, None)
+            }
+            "png" | "jpg" | "jpeg" => {
+                let color = placeholders::of_bytes(&raw);
+                (raw, color)
+            }
+            _ => (raw, None),
+        };
+        let path = published_path(slug, &relative, &content);
+        assets.push(Asset { source: relative, path, content, color });
+    }
+    Ok(assets)
+}
+
+/// Point relative `src`/`href` references at the published assets
+///
+/// Images with a dominant colour also get its placeholder class.
+pub fn rewrite_references(html: &str, assets: &[Asset]) -> String {
+    if assets.is_empty() {
+        return html.to_string();
+    }
+    let by_source: HashMap<&str, &Asset> = assets.iter().map(|a| (a.source.as_str(), a)).collect();
+    REFERENCE
+        .replace_all(html, |capture: &regex::Captures| {
+            let reference = capture[2].trim_start_matches("./");
+            let (path, suffix) = reference.find(['#', '?']).map_or((reference, ""), |i| reference.split_at(i));
+            match by_source.get(path) {
+                Some(asset) => {
+                    let placeholder = match asset.color {
+                        Some(color) if &capture[1] == "src" => format!(" class=\"{}\"", placeholders::class(color)),
+                        _ => String::new(),
+                    };
+                    format!("{}=\"/{}{suffix}\"{placeholder}", &capture[1], asset.path)
+                }
+                None => capture[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// Write the assets of `posts` into `output_dir`
+pub fn publish(output_dir: &Path, posts: &[Post]) -> Result<usize> {
+    let mut count = 0;
+    for asset in posts.iter().flat_map(|post| &post.assets) {
+        let target: PathBuf = output_dir.join(&asset.path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&target, &asset.content).with_context(|| format!("Failed to write {}", target.display()))?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(source: &str, path: &str) -> Asset {
+        Asset { source: source.to_string(), path: path.to_string(), ..Asset::default() }
+    }
+
+    #[test]
+    fn test_bundle_detection_and_slug() {
+        let source = Path::new("content/My Kernel Bug/index.md");
+        assert!(is_bundle(source));
+        assert_eq!(bundle_slug(source).as_deref(), Some("my-kernel-bug"));
+        assert!(!is_bundle(Path::new("content/post.md")));
+    }
+
+    #[test]
+    fn test_published_path_is_hashed() {
+        assert_eq!(published_path("post", "diagram.png", b""), "post/diagram.e3b0c44298fc1c14.png");
+        assert_eq!(published_path("post", "img/a.b.svg", b""), "post/img/a.b.e3b0c44298fc1c14.svg");
+    }
+
+    #[test]
+    fn test_relative_references_are_rewritten() {
+        let assets = [asset("diagram.png", "post/diagram.0011.png"), asset("img/poc.txt", "post/img/poc.22.txt")];
+        let html = r#"<img src="diagram.png"><img src="./diagram.png"><a href="img/poc.txt#L3">PoC</a><a href="/diagram.png">x</a><img src="other.png">"#;
+        assert_eq!(
+            rewrite_references(html, &assets),
+            r#"<img src="/post/diagram.0011.png"><img src="/post/diagram.0011.png"><a href="/post/img/poc.22.txt#L3">PoC</a><a href="/diagram.png">x</a><img src="other.png">"#
+        );
+    }
+
+    #[test]
+    fn test_colored_images_get_placeholder_class() {
+        let assets = [Asset { color: Some([1, 2, 3]), ..asset("a.png", "post/a.00.png") }];
+        assert_eq!(
+            rewrite_references(r#"<img src="a.png" alt=""><a href="a.png">full</a>"#, &assets),
+            r#"<img src="/post/a.00.png" class="ph-010203" alt=""><a href="/post/a.00.png">full</a>"#
+        );
+    }
+}
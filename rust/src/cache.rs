@@ -0,0 +1,142 @@
+//! Build cache persisted between builds
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+use crate::normalize;
+
+/// Current cache format version
+const CACHE_VERSION: u32 = 1;
+
+/// Data carried over from the previous build
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BuildCache {
+    /// Cache format version
+    pub version: u32,
+    /// Output file sizes (relative path -> bytes)
+    #[serde(default)]
+    pub sizes: BTreeMap<String, u64>,
+}
+
+impl BuildCache {
+    /// Load the cache, starting fresh if it is missing or from another version
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::new());
+        }
+
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read build cache: {}", path.display()))?;
+        match serde_json::from_str::<Self>(&content) {
+            Ok(cache) if cache.version == CACHE_VERSION => Ok(cache),
+            _ => Ok(Self::new()),
+        }
+    }
+
+    /// Write the cache to disk
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write build cache: {}", path.display()))
+    }
+
+    fn new() -> Self {
+        Self {
+            version: CACHE_VERSION,
+            ..Self::default()
+        }
+    }
+}
+
+/// An output file that grew beyond the configured threshold
+#[derive(Debug, Clone, PartialEq)]
+pub struct SizeRegression {
+    /// Path relative to the output directory
+    pub path: String,
+    /// Size in the previous build (bytes)
+    pub previous: u64,
+    /// Size in this build (bytes)
+    pub current: u64,
+    /// Growth relative to the previous size
+    pub growth_percent: f64,
+}
+
+/// Collect the size of every file in the output directory
+pub fn collect_sizes(output_dir: &Path) -> Result<BTreeMap<String, u64>> {
+    let mut sizes = BTreeMap::new();
+
+    for entry in WalkDir::new(output_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let relative = normalize::relative(entry.path(), output_dir)?;
+        let size = entry.metadata()?.len();
+        sizes.insert(relative, size);
+    }
+
+    Ok(sizes)
+}
+
+/// Find files that grew more than `threshold_percent` since the previous build
+///
+/// Files that are new in this build have no baseline and are not reported.
+#[allow(clippy::cast_precision_loss)]
+pub fn size_regressions(
+    previous: &BTreeMap<String, u64>,
+    current: &BTreeMap<String, u64>,
+    threshold_percent: f64,
+) -> Vec<SizeRegression> {
+    current
+        .iter()
+        .filter_map(|(path, &size)| {
+            let &before = previous.get(path)?;
+            if before == 0 || size <= before {
+                return None;
+            }
+            let growth_percent = (size - before) as f64 / before as f64 * 100.0;
+            (growth_percent > threshold_percent).then(|| SizeRegression {
+                path: path.clone(),
+                previous: before,
+                current: size,
+                growth_percent,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sizes(entries: &[(&str, u64)]) -> BTreeMap<String, u64> {
+        entries.iter().map(|(p, s)| ((*p).to_string(), *s)).collect()
+    }
+
+    #[test]
+    fn test_size_regressions_over_threshold() {
+        let previous = sizes(&[("index.html", 1000), ("about.html", 1000)]);
+        let current = sizes(&[("index.html", 1500), ("about.html", 1100)]);
+        let regressions = size_regressions(&previous, &current, 20.0);
+        assert_eq!(regressions.len(), 1);
+        assert_eq!(regressions[0].path, "index.html");
+        assert!((regressions[0].growth_percent - 50.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_size_regressions_ignores_new_and_shrunk_files() {
+        let previous = sizes(&[("index.html", 1000)]);
+        let current = sizes(&[("index.html", 500), ("new.html", 9000)]);
+        assert!(size_regressions(&previous, &current, 20.0).is_empty());
+    }
+
+    #[test]
+    fn test_cache_load_missing_file() {
+        let cache = BuildCache::load(Path::new("does-not-exist.json")).unwrap();
+        assert_eq!(cache.version, CACHE_VERSION);
+        assert!(cache.sizes.is_empty());
+    }
+}
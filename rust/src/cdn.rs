@@ -0,0 +1,143 @@
+//! `build-id.txt`, `cache-manifest.json` and `etags.map` for deploy tooling, CDNs and origins
+//!
+//! The build ID is the manifest root hash. The cache manifest maps every
+//! output path to its hash and a recommended `Cache-Control`: content-hashed
+//! files are immutable, documents always revalidate. `etags.map` gives nginx
+//! a strong ETag per path: the same SHA-256 `integrity.json` lists.
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use tracing::info;
+use walkdir::WalkDir;
+
+use crate::{checksums, normalize};
+
+/// Build ID file
+pub const BUILD_ID: &str = "build-id.txt";
+
+/// Cache manifest file
+pub const CACHE_MANIFEST: &str = "cache-manifest.json";
+
+/// nginx `map` include with one ETag per path
+pub const ETAG_MAP: &str = "etags.map";
+
+/// Content-hashed file names: `name.<16+ hex>.ext`
+static FINGERPRINT: Lazy<Regex> = Lazy::new(|| Regex::new(r"\.[0-9a-f]{16,64}\.[A-Za-z0-9]+$").unwrap());
+
+/// Never changes at this URL
+pub const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// Cached, but checked with the origin on every use
+pub const REVALIDATE: &str = "public, max-age=0, must-revalidate";
+
+/// Stable assets without a hash in the name
+pub const SHORT: &str = "public, max-age=86400";
+
+/// Documents and verification files that must reflect the latest build
+const DOCUMENT_EXTENSIONS: [&str; 10] = ["html", "htm", "xml", "json", "txt", "opml", "webmanifest", "sig", "asc", ""];
+
+/// Recommended `Cache-Control` for an output path
+pub fn cache_control(path: &str) -> &'static str {
+    if FINGERPRINT.is_match(path) {
+        return IMMUTABLE;
+    }
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let ext = name.rsplit_once('.').map_or("", |(_, ext)| ext).to_ascii_lowercase();
+    if DOCUMENT_EXTENSIONS.contains(&ext.as_str()) {
+        REVALIDATE
+    } else {
+        SHORT
+    }
+}
+
+/// One entry of the cache manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CacheEntry {
+    /// `sha256:<hex>` of the file
+    pub sha256: String,
+    /// Recommended `Cache-Control`
+    pub cache_control: &'static str,
+}
+
+/// `cache-manifest.json` contents
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CacheManifest {
+    /// Same value as `build-id.txt`
+    pub build_id: String,
+    /// `/path` → entry, sorted
+    pub files: BTreeMap<String, CacheEntry>,
+}
+
+/// Strong ETag for a file's SHA-256 hex digest
+pub fn etag(sha256_hex: &str) -> String {
+    format!("\"{sha256_hex}\"")
+}
+
+/// Body of an nginx `map $uri $etag { ... }` block (paths and ETags are single-quoted)
+pub fn etag_map(files: &BTreeMap<String, CacheEntry>) -> String {
+    let mut map = String::from("# map $uri $secureblog_etag { include etags.map; }  then: etag off; add_header ETag $secureblog_etag;\n");
+    for (path, entry) in files {
+        let hex = entry.sha256.trim_start_matches("sha256:");
+        let _ = writeln!(map, "'{}' '{}';", path.replace('\\', "\\\\").replace('\'', "\\'"), etag(hex));
+    }
+    map
+}
+
+/// Write `build-id.txt`, `cache-manifest.json` and `etags.map` covering every other output file
+pub fn write(output_dir: &Path, build_id: &str) -> Result<()> {
+    let mut files = BTreeMap::new();
+    for entry in WalkDir::new(output_dir).into_iter().filter_map(Result::ok).filter(|e| e.file_type().is_file()) {
+        let relative = normalize::relative(entry.path(), output_dir)?;
+        if [BUILD_ID, CACHE_MANIFEST, ETAG_MAP].contains(&relative.as_str()) {
+            continue;
+        }
+        let hashes = checksums::hash_file(entry.path())?;
+        files.insert(
+            format!("/{relative}"),
+            CacheEntry {
+                sha256: format!("sha256:{}", hashes.sha256),
+                cache_control: cache_control(&relative),
+            },
+        );
+    }
+
+    fs::write(output_dir.join(BUILD_ID), format!("{build_id}\n")).context("Failed to write build-id.txt")?;
+    fs::write(output_dir.join(ETAG_MAP), etag_map(&files)).context("Failed to write etags.map")?;
+    let manifest = CacheManifest { build_id: build_id.to_string(), files };
+    fs::write(output_dir.join(CACHE_MANIFEST), serde_json::to_string_pretty(&manifest)?)
+        .context("Failed to write cache-manifest.json")?;
+    info!("🆔 Build {} ({} files in cache-manifest.json)", build_id, manifest.files.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_control_by_path() {
+        assert_eq!(cache_control("css/post.0123456789abcdef.css"), IMMUTABLE);
+        assert_eq!(cache_control("post/gallery/a.thumb.0123456789abcdef.jpg"), IMMUTABLE);
+        assert_eq!(cache_control("index.html"), REVALIDATE);
+        assert_eq!(cache_control("atom.xml.sig"), REVALIDATE);
+        assert_eq!(cache_control("SHA256SUMS"), REVALIDATE);
+        assert_eq!(cache_control(".well-known/security.txt"), REVALIDATE);
+        assert_eq!(cache_control("style.css"), SHORT);
+        assert_eq!(cache_control("fonts/inter.woff2"), SHORT);
+    }
+
+    #[test]
+    fn test_etag_map_lines() {
+        let entry = |hex: &str| CacheEntry { sha256: format!("sha256:{hex}"), cache_control: REVALIDATE };
+        let files = BTreeMap::from([("/index.html".to_string(), entry("ab12")), ("/it's.html".to_string(), entry("cd34"))]);
+        let map = etag_map(&files);
+        assert!(map.starts_with('#'));
+        assert!(map.ends_with("'/index.html' '\"ab12\"';\n'/it\\'s.html' '\"cd34\"';\n"));
+    }
+}
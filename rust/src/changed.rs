@@ -0,0 +1,121 @@
+//! `check --changed`: validate only the posts a set of changed files affects
+//!
+//! A post depends on its own source, the repository files it includes, its
+//! `css:` stylesheet, its gallery directories and, for a page bundle,
+//! everything in its directory. Only posts depending on a changed file are
+//! loaded, rendered and prose-checked, which keeps a pre-commit hook fast on
+//! large sites.
+
+use anyhow::Result;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::{bundles, gallery, include, markdown};
+
+/// A post source and the paths (files or directories) its output depends on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependencies {
+    /// Markdown source
+    pub source: PathBuf,
+    /// The source itself, then everything it pulls in
+    pub paths: Vec<PathBuf>,
+}
+
+/// `path` without `.` components, so `./content/a.md` matches `content/a.md`
+fn normalize(path: &Path) -> PathBuf {
+    path.components().filter(|c| !matches!(c, Component::CurDir)).collect()
+}
+
+/// What a post's output depends on, from its source text
+pub fn dependencies(source: &Path, content: &str) -> Dependencies {
+    let dir = source.parent().unwrap_or_else(|| Path::new(""));
+    let mut paths = vec![normalize(source)];
+    if bundles::is_bundle(source) {
+        paths.push(normalize(dir));
+    }
+    paths.extend(include::included_files(content).iter().map(|file| normalize(Path::new(file))));
+    paths.extend(gallery::gallery_dirs(content).iter().map(|gallery| normalize(&dir.join(gallery))));
+    if let Some(css) = markdown::parse_frontmatter(content).ok().and_then(|(meta, _)| meta.css) {
+        paths.push(normalize(&dir.join(css)));
+    }
+    Dependencies { source: source.to_path_buf(), paths }
+}
+
+/// Dependencies of every post under `content_dir`
+pub fn scan(content_dir: &Path) -> Result<Vec<Dependencies>> {
+    let mut posts = Vec::new();
+    for entry in WalkDir::new(content_dir).sort_by_file_name().into_iter().filter_map(Result::ok) {
+        let is_markdown = entry.path().extension().is_some_and(|ext| ext == "md" || ext == "markdown");
+        if entry.file_type().is_file() && is_markdown {
+            posts.push(dependencies(entry.path(), &fs::read_to_string(entry.path())?));
+        }
+    }
+    Ok(posts)
+}
+
+/// Sources of the posts depending on any of `changed`
+pub fn affected(posts: &[Dependencies], changed: &[PathBuf]) -> Vec<PathBuf> {
+    let changed: Vec<PathBuf> = changed.iter().map(|path| normalize(path)).collect();
+    posts
+        .iter()
+        .filter(|post| post.paths.iter().any(|dep| changed.iter().any(|path| path.starts_with(dep))))
+        .map(|post| post.source.clone())
+        .collect()
+}
+
+/// Every existing file some post depends on, for comparing against git
+pub fn watched(posts: &[Dependencies]) -> Vec<PathBuf> {
+    let mut files = BTreeSet::new();
+    for dep in posts.iter().flat_map(|post| &post.paths) {
+        if dep.is_dir() {
+            files.extend(
+                WalkDir::new(dep)
+                    .into_iter()
+                    .filter_map(Result::ok)
+                    .filter(|e| e.file_type().is_file())
+                    .map(|e| e.into_path()),
+            );
+        } else if dep.is_file() {
+            files.insert(dep.clone());
+        }
+    }
+    files.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(source: &str, paths: &[&str]) -> Dependencies {
+        Dependencies { source: PathBuf::from(source), paths: paths.iter().map(PathBuf::from).collect() }
+    }
+
+    #[test]
+    fn test_dependencies_from_source() {
+        let content = "---\ntitle: x\n---\n{{< include file=\"src/poc.rs\" lines=\"1-3\" hash=\"sha256:x\" >}}\n{{< gallery dir=\"photos/\" >}}\n";
+        let deps = dependencies(Path::new("./content/bug/index.md"), content);
+        assert_eq!(
+            deps.paths,
+            [
+                PathBuf::from("content/bug/index.md"),
+                PathBuf::from("content/bug"),
+                PathBuf::from("src/poc.rs"),
+                PathBuf::from("content/bug/photos"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_only_dependents_are_affected() {
+        let posts = [
+            post("content/a.md", &["content/a.md", "src/poc.rs"]),
+            post("content/b/index.md", &["content/b/index.md", "content/b"]),
+            post("content/c.md", &["content/c.md"]),
+        ];
+        let changed = [PathBuf::from("./src/poc.rs"), PathBuf::from("content/b/diagram.png")];
+        assert_eq!(affected(&posts, &changed), [PathBuf::from("content/a.md"), PathBuf::from("content/b/index.md")]);
+        assert!(affected(&posts, &[PathBuf::from("README.md")]).is_empty());
+    }
+}
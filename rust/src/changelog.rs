@@ -0,0 +1,66 @@
+//! Per-post changelog from git history
+//!
+//! Every commit that changed a post's source is listed at the end of the
+//! post with its date, message and short commit id, so readers of an
+//! advisory can see when it changed and why without trusting `updated:`.
+
+use anyhow::{Context, Result};
+use chrono_tz::Tz;
+use std::path::Path;
+
+use crate::git::{self, LogEntry};
+use crate::security::escape_html as escape;
+use crate::{inject, Config, Post};
+
+/// `<section class="changelog">` listing the commits, newest first
+pub fn section(entries: &[LogEntry], zone: Tz) -> String {
+    let mut html = String::from("<section class=\"changelog\">\n<h2>Changelog</h2>\n<ol reversed>\n");
+    for entry in entries {
+        let id = entry.commit.to_string();
+        html.push_str(&format!(
+            "<li><time datetime=\"{}\">{}</time> {} <code>{}</code></li>\n",
+            entry.time.to_rfc3339(),
+            entry.time.with_timezone(&zone).format("%Y-%m-%d"),
+            escape(&entry.summary),
+            &id[..id.len().min(12)]
+        ));
+    }
+    html.push_str("</ol>\n</section>\n");
+    html
+}
+
+/// Add the changelog to every post tracked by git
+pub fn apply(config: &Config, posts: &[Post]) -> Result<()> {
+    let sources: Vec<&Path> = posts.iter().map(|p| p.source.as_path()).collect();
+    let log = git::log_of(&sources).context("Failed to read git history for changelogs")?;
+    let zone = config.timezone.display_zone();
+    for post in posts {
+        if let Some(entries) = log.get(&post.source) {
+            inject::inject_into_file(
+                &config.output.join(post.path()),
+                &["</article>", "</main>", "</body>"],
+                &section(entries, zone),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_section_lists_commits() {
+        let entries = [LogEntry {
+            time: Utc.with_ymd_and_hms(2024, 5, 9, 23, 30, 0).unwrap(),
+            commit: gix::ObjectId::from_hex(b"0123456789abcdef0123456789abcdef01234567").unwrap(),
+            summary: "Correct affected <versions>".to_string(),
+        }];
+        let html = section(&entries, chrono_tz::Europe::Berlin);
+        assert!(html.contains(
+            "<li><time datetime=\"2024-05-09T23:30:00+00:00\">2024-05-10</time> Correct affected &lt;versions&gt; <code>0123456789ab</code></li>"
+        ));
+    }
+}
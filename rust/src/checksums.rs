@@ -0,0 +1,201 @@
+//! `SHA256SUMS` and `B3SUMS` in coreutils format
+//!
+//! Mirrors can check a copy with `sha256sum -c SHA256SUMS` or `b3sum -c B3SUMS`
+//! from the site root, without any secureblog tooling.
+//!
+//! Files are hashed as a stream in bounded chunks, with BLAKE3 spread over
+//! all cores, so multi-gigabyte videos and PDFs never have to fit in memory.
+//! The integrity manifest uses the same [`hash_file`].
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use tracing::info;
+use walkdir::WalkDir;
+
+use crate::detached::{self, SignFilesConfig};
+use crate::{normalize, vfs};
+
+/// Most bytes of a file held in memory while hashing it
+const CHUNK: u64 = 4 * 1024 * 1024;
+
+/// Chunks at least this large are hashed with BLAKE3 on every core
+const PARALLEL_MIN: usize = 128 * 1024;
+
+/// Checksum files and the tool that verifies each
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    /// `sha256sum -c SHA256SUMS`
+    Sha256,
+    /// `b3sum -c B3SUMS`
+    Blake3,
+}
+
+impl Algorithm {
+    /// Both algorithms, in the order they are written
+    pub const ALL: [Self; 2] = [Self::Sha256, Self::Blake3];
+
+    /// Name of the checksum file
+    pub const fn file_name(self) -> &'static str {
+        match self {
+            Self::Sha256 => "SHA256SUMS",
+            Self::Blake3 => "B3SUMS",
+        }
+    }
+
+    /// Lowercase hex digest of a hashed file
+    pub fn digest(self, hashes: &FileHashes) -> &str {
+        match self {
+            Self::Sha256 => &hashes.sha256,
+            Self::Blake3 => &hashes.blake3,
+        }
+    }
+}
+
+/// Size and digests of one file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileHashes {
+    /// Length in bytes
+    pub size: u64,
+    /// SHA-256, lowercase hex
+    pub sha256: String,
+    /// BLAKE3, lowercase hex
+    pub blake3: String,
+}
+
+/// Hash everything `reader` yields, one bounded chunk at a time
+pub fn hash_reader(mut reader: impl Read) -> std::io::Result<FileHashes> {
+    let (mut sha256, mut blake3) = (Sha256::new(), blake3::Hasher::new());
+    let (mut chunk, mut size) = (Vec::new(), 0);
+    loop {
+        chunk.clear();
+        reader.by_ref().take(CHUNK).read_to_end(&mut chunk)?;
+        if chunk.is_empty() {
+            break;
+        }
+        sha256.update(&chunk);
+        if chunk.len() >= PARALLEL_MIN {
+            blake3.update_rayon(&chunk);
+        } else {
+            blake3.update(&chunk);
+        }
+        size += chunk.len() as u64;
+    }
+    Ok(FileHashes { size, sha256: format!("{:x}", sha256.finalize()), blake3: blake3.finalize().to_hex().to_string() })
+}
+
+/// Hashes of the file at `path`, streamed from disk
+pub fn hash_file(path: &Path) -> Result<FileHashes> {
+    let reader = vfs::current().open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    hash_reader(reader).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+/// Whether `path` is a checksum file or its signature (never listed)
+fn is_checksum_file(path: &str) -> bool {
+    Algorithm::ALL
+        .iter()
+        .any(|algorithm| path == algorithm.file_name() || path.strip_suffix(".sig") == Some(algorithm.file_name()))
+}
+
+/// One checksum line; names with `\` or a newline are escaped the way coreutils does
+pub fn line(hash: &str, path: &str) -> String {
+    if path.contains(['\\', '\n']) {
+        format!("\\{hash}  {}\n", path.replace('\\', "\\\\").replace('\n', "\\n"))
+    } else {
+        format!("{hash}  {path}\n")
+    }
+}
+
+/// Checksum listing of `files` (relative `/`-separated path -> hashes), sorted by path
+pub fn listing(algorithm: Algorithm, files: &BTreeMap<String, FileHashes>) -> String {
+    let mut listing = String::new();
+    for (path, hashes) in files {
+        let _ = write!(listing, "{}", line(algorithm.digest(hashes), path));
+    }
+    listing
+}
+
+/// Every output file except the checksum files themselves
+fn output_files(output_dir: &Path) -> Result<BTreeMap<String, FileHashes>> {
+    let mut files = BTreeMap::new();
+    for entry in WalkDir::new(output_dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+    {
+        let path = entry.path();
+        let relative = normalize::relative(path, output_dir)?;
+        if is_checksum_file(&relative) {
+            continue;
+        }
+        files.insert(relative, hash_file(path)?);
+    }
+    Ok(files)
+}
+
+/// Write `SHA256SUMS` and `B3SUMS` into the output, signed with the `sign_files` key when configured
+pub fn write(output_dir: &Path, signing: Option<&SignFilesConfig>) -> Result<()> {
+    let files = output_files(output_dir)?;
+    let key = signing.map(|config| detached::load_key(&config.key)).transpose()?;
+
+    for algorithm in Algorithm::ALL {
+        let path = output_dir.join(algorithm.file_name());
+        let listing = listing(algorithm, &files);
+        fs::write(&path, &listing).with_context(|| format!("Failed to write {}", path.display()))?;
+        if let Some(key) = &key {
+            let signature_path = detached::signature_path(&path);
+            fs::write(&signature_path, detached::sign(key, listing.as_bytes())?)
+                .with_context(|| format!("Failed to write {}", signature_path.display()))?;
+        }
+    }
+
+    info!("🧾 Checksums of {} files in SHA256SUMS and B3SUMS{}", files.len(), if key.is_some() { " (signed)" } else { "" });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listing_matches_coreutils_format() {
+        let files = BTreeMap::from([
+            ("posts/b.html".to_string(), hash_reader(&b"b"[..]).unwrap()),
+            ("a.txt".to_string(), hash_reader(&b""[..]).unwrap()),
+        ]);
+        assert_eq!(
+            listing(Algorithm::Sha256, &files),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855  a.txt\n\
+             3e23e8160039594a33894f6564e1b1348bbd7a0088d42c4acb73eeaed59c009d  posts/b.html\n"
+        );
+        assert!(listing(Algorithm::Blake3, &files)
+            .starts_with("af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262  a.txt\n"));
+    }
+
+    #[test]
+    fn test_chunked_hashes_match_one_shot() {
+        let content: Vec<u8> = (0..CHUNK as usize + PARALLEL_MIN + 3).map(|i| (i % 251) as u8).collect();
+        let hashes = hash_reader(content.as_slice()).unwrap();
+        assert_eq!(hashes.size, content.len() as u64);
+        assert_eq!(hashes.sha256, format!("{:x}", Sha256::digest(&content)));
+        assert_eq!(hashes.blake3, blake3::hash(&content).to_hex().to_string());
+    }
+
+    #[test]
+    fn test_escaped_names() {
+        assert_eq!(line("00", "a\\b\nc"), "\\00  a\\\\b\\nc\n");
+        assert_eq!(line("00", "plain name"), "00  plain name\n");
+    }
+
+    #[test]
+    fn test_checksum_files_are_not_listed() {
+        assert!(is_checksum_file("SHA256SUMS"));
+        assert!(is_checksum_file("B3SUMS.sig"));
+        assert!(!is_checksum_file("integrity.json"));
+        assert!(!is_checksum_file("posts/SHA256SUMS"));
+    }
+}
@@ -0,0 +1,234 @@
+//! Archived copies of the links cited by advisories
+//!
+//! `citations archive` (an explicit online step) submits every external link
+//! of the posts with a cited tag to the Wayback Machine and archive.today and
+//! records the snapshot URLs in a state file committed with the site. Builds
+//! stay offline: they read the state file and append an "Archived
+//! references" section to those posts, so evidence cited by an advisory
+//! stays reachable after the original page moves or disappears. The archive
+//! hosts must be in the policy's `allowed_hosts` under `no_external`.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::security::escape_html as escape;
+use crate::{inject, webmention, Config, Post};
+
+/// Citation archiving settings
+#[derive(Debug, Clone, Deserialize)]
+pub struct CitationsConfig {
+    /// Posts with any of these tags get the appendix
+    #[serde(default = "default_tags")]
+    pub tags: Vec<String>,
+    /// Services links are submitted to
+    #[serde(default = "default_services")]
+    pub services: Vec<Service>,
+    /// Recorded snapshots, committed with the site
+    #[serde(default = "default_state")]
+    pub state: PathBuf,
+}
+
+fn default_tags() -> Vec<String> {
+    vec!["advisory".to_string()]
+}
+
+fn default_services() -> Vec<Service> {
+    vec![Service::Wayback, Service::ArchiveToday]
+}
+
+fn default_state() -> PathBuf {
+    PathBuf::from("citations.json")
+}
+
+/// Web archive a link is submitted to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Service {
+    /// web.archive.org
+    Wayback,
+    /// archive.today (archive.ph)
+    ArchiveToday,
+}
+
+impl Service {
+    /// Name shown in the appendix
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Wayback => "Wayback Machine",
+            Self::ArchiveToday => "archive.today",
+        }
+    }
+}
+
+/// A recorded snapshot of one link
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Archived copy
+    pub url: String,
+    /// When it was submitted (RFC 3339)
+    pub at: String,
+}
+
+/// Snapshots keyed by cited URL, then service
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CitationState {
+    #[serde(default)]
+    pub links: BTreeMap<String, BTreeMap<Service, Snapshot>>,
+}
+
+impl CitationState {
+    /// Load the state file (empty if missing); snapshot URLs must be `https://`
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read citation state: {}", path.display()))?;
+        let state: Self = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse citation state: {}", path.display()))?;
+        for snapshot in state.links.values().flat_map(BTreeMap::values) {
+            if !snapshot.url.starts_with("https://") {
+                anyhow::bail!("Snapshot {} in {} is not an https:// URL", snapshot.url, path.display());
+            }
+        }
+        Ok(state)
+    }
+
+    /// Write the state file
+    #[cfg(feature = "network")]
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)? + "\n")
+            .with_context(|| format!("Failed to write citation state: {}", path.display()))
+    }
+}
+
+/// Whether a post's links are archived
+pub fn is_cited(citations: &CitationsConfig, post: &Post) -> bool {
+    post.meta.tags.iter().any(|tag| citations.tags.contains(tag))
+}
+
+/// `<section class="citations">` for the links with snapshots, if any
+pub fn appendix(links: &[String], state: &CitationState) -> Option<String> {
+    let mut items = String::new();
+    for link in links {
+        let Some(snapshots) = state.links.get(link).filter(|s| !s.is_empty()) else {
+            continue;
+        };
+        let copies: Vec<String> = snapshots
+            .iter()
+            .map(|(service, snapshot)| {
+                format!("<a href=\"{}\" title=\"{}\">{}</a>", escape(&snapshot.url), escape(&snapshot.at), service.label())
+            })
+            .collect();
+        items.push_str(&format!("<li><cite>{}</cite>: {}</li>\n", escape(link), copies.join(", ")));
+    }
+    (!items.is_empty()).then(|| {
+        format!("<section class=\"citations\">\n<h2>Archived references</h2>\n<ol>\n{items}</ol>\n</section>\n")
+    })
+}
+
+/// Append archived references to every cited post
+pub fn apply(config: &Config, posts: &[Post], citations: &CitationsConfig) -> Result<()> {
+    let state = CitationState::load(&citations.state)?;
+    for post in posts.iter().filter(|p| is_cited(citations, p)) {
+        if let Some(section) = appendix(&webmention::outbound_links(&post.html, &config.url), &state) {
+            inject::inject_into_file(&config.output.join(post.path()), &["</article>", "</main>", "</body>"], &section)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "network")]
+pub use online::archive_all;
+
+#[cfg(feature = "network")]
+mod online {
+    use super::{is_cited, CitationState, CitationsConfig, Service, Snapshot};
+    use anyhow::{Context, Result};
+    use chrono::Utc;
+    use tracing::{info, warn};
+    use url::Url;
+
+    use crate::{webmention, Config, Post};
+
+    /// Submit `link` and return the snapshot URL the service redirects to
+    fn submit(agent: &ureq::Agent, service: Service, link: &str) -> Result<Option<String>> {
+        let (base, request) = match service {
+            Service::Wayback => ("https://web.archive.org/", format!("https://web.archive.org/save/{link}")),
+            Service::ArchiveToday => {
+                let query: String = url::form_urlencoded::byte_serialize(link.as_bytes()).collect();
+                ("https://archive.ph/", format!("https://archive.ph/submit/?url={query}"))
+            }
+        };
+        let response = agent.get(&request).call().with_context(|| format!("Failed to submit {link}"))?;
+        let headers = response.headers();
+        let location = ["location", "content-location"]
+            .iter()
+            .find_map(|name| headers.get(*name).and_then(|v| v.to_str().ok()).map(str::to_string))
+            .or_else(|| {
+                // archive.today answers `Refresh: 0;url=https://archive.ph/wip/...`
+                let refresh = headers.get("refresh")?.to_str().ok()?;
+                Some(refresh.split_once("url=")?.1.trim().to_string())
+            });
+        location
+            .map(|l| Url::parse(base)?.join(&l).map(String::from))
+            .transpose()
+            .context("Invalid snapshot URL")
+            .map(|url| url.filter(|u| u.starts_with("https://")))
+    }
+
+    /// Submit every link of the cited posts not yet recorded; returns the number of new snapshots
+    pub fn archive_all(config: &Config, posts: &[Post], citations: &CitationsConfig, state: &mut CitationState) -> Result<usize> {
+        let agent = crate::net::agent_without_redirects();
+        let mut archived = 0;
+        for post in posts.iter().filter(|p| is_cited(citations, p)) {
+            for link in webmention::outbound_links(&post.html, &config.url) {
+                for &service in &citations.services {
+                    if state.links.get(&link).is_some_and(|s| s.contains_key(&service)) {
+                        continue;
+                    }
+                    match submit(&agent, service, &link) {
+                        Ok(Some(url)) => {
+                            info!("🗄️  {} -> {}", link, url);
+                            let snapshot = Snapshot { url, at: Utc::now().to_rfc3339() };
+                            state.links.entry(link.clone()).or_default().insert(service, snapshot);
+                            archived += 1;
+                        }
+                        Ok(None) => warn!("{} returned no snapshot for {}", service.label(), link),
+                        Err(e) => warn!("Skipping {} on {}: {:#}", link, service.label(), e),
+                    }
+                }
+            }
+        }
+        Ok(archived)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_appendix_lists_recorded_snapshots() {
+        let mut state = CitationState::default();
+        state.links.entry("https://vendor.example/advisory?id=1&x=2".to_string()).or_default().insert(
+            Service::Wayback,
+            Snapshot {
+                url: "https://web.archive.org/web/20240509000000/https://vendor.example/advisory?id=1&x=2".to_string(),
+                at: "2024-05-09T00:00:00+00:00".to_string(),
+            },
+        );
+        let links = ["https://vendor.example/advisory?id=1&x=2".to_string(), "https://unarchived.example/".to_string()];
+        assert_eq!(
+            appendix(&links, &state).unwrap(),
+            "<section class=\"citations\">\n<h2>Archived references</h2>\n<ol>\n\
+             <li><cite>https://vendor.example/advisory?id=1&amp;x=2</cite>: \
+             <a href=\"https://web.archive.org/web/20240509000000/https://vendor.example/advisory?id=1&amp;x=2\" \
+             title=\"2024-05-09T00:00:00+00:00\">Wayback Machine</a></li>\n</ol>\n</section>\n"
+        );
+        assert_eq!(appendix(&links[1..], &state), None);
+    }
+}
@@ -0,0 +1,121 @@
+//! OpenPGP cleartext-signed post sources (`<page>.md.asc`)
+//!
+//! The markdown file a post was built from is published next to its page as
+//! a cleartext-signed message and linked from the page, so readers can check
+//! with `gpg --verify` what the author wrote, independent of the renderer,
+//! templates and everything else between the source and the HTML.
+
+use anyhow::{Context, Result};
+use pgp::cleartext::CleartextSignedMessage;
+use pgp::types::PublicKeyTrait;
+use pgp::{ArmorOptions, Deserializable, SignedSecretKey};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::security::escape_html as escape;
+use crate::{inject, Config, Post};
+
+/// Source signature settings
+#[derive(Debug, Clone, Deserialize)]
+pub struct SignSourcesConfig {
+    /// ASCII-armored OpenPGP secret key without a passphrase (e.g. from a CI secret)
+    pub key: PathBuf,
+}
+
+/// Load the secret key, checking its self-signatures
+pub fn load_key(path: &Path) -> Result<SignedSecretKey> {
+    let armored =
+        fs::read_to_string(path).with_context(|| format!("Failed to read OpenPGP key: {}", path.display()))?;
+    let (key, _) = SignedSecretKey::from_string(&armored)
+        .with_context(|| format!("Invalid armored OpenPGP secret key: {}", path.display()))?;
+    key.verify().with_context(|| format!("OpenPGP key {} has invalid self-signatures", path.display()))?;
+    Ok(key)
+}
+
+/// Upper-case hex fingerprint of the key, as `gpg` prints it
+pub fn fingerprint(key: &SignedSecretKey) -> String {
+    key.fingerprint().as_bytes().iter().map(|b| format!("{b:02X}")).collect()
+}
+
+/// Cleartext-signed armored message of `text`
+pub fn sign(key: &SignedSecretKey, text: &str) -> Result<String> {
+    let message = CleartextSignedMessage::sign(rand::thread_rng(), text, key, String::new)
+        .context("Failed to sign (passphrase-protected keys are not supported)")?;
+    Ok(message.to_armored_string(ArmorOptions::default())?)
+}
+
+/// Output path of a post's signed source: the page path with `.md.asc` for `.html`
+pub fn signed_source_path(page: &str) -> String {
+    format!("{}.md.asc", page.strip_suffix(".html").unwrap_or(page))
+}
+
+/// Link to the signed source, relative to the page
+pub fn source_link(page: &str, fingerprint: &str) -> String {
+    let path = signed_source_path(page);
+    let name = path.rsplit('/').next().unwrap_or(&path);
+    format!(
+        "<p class=\"signed-source\"><a href=\"{}\" type=\"text/plain\">Signed markdown source</a> \
+         (OpenPGP key <code>{}</code>)</p>\n",
+        escape(name),
+        escape(fingerprint)
+    )
+}
+
+/// Posts whose source is signed and published; protected posts are not, as their
+/// `<page>.md.asc` would sit outside the protected location
+fn signed_posts(posts: &[Post]) -> Vec<&Post> {
+    posts.iter().filter(|post| !post.meta.protected).collect()
+}
+
+/// Write `<page>.md.asc` for every unprotected post and link it from the post's page
+pub fn apply(config: &Config, posts: &[Post], sign_sources: &SignSourcesConfig) -> Result<usize> {
+    let key = load_key(&sign_sources.key)?;
+    let fingerprint = fingerprint(&key);
+    let posts = signed_posts(posts);
+    for post in &posts {
+        let page = post.path();
+        let target = config.output.join(signed_source_path(&page));
+        fs::write(&target, sign(&key, &post.source_text)?).with_context(|| format!("Failed to write {}", target.display()))?;
+        inject::inject_into_file(
+            &config.output.join(&page),
+            &["</article>", "</main>", "</body>"],
+            &source_link(&page, &fingerprint),
+        )?;
+    }
+    info!("🔏 Signed {} post sources (OpenPGP {})", posts.len(), fingerprint);
+    Ok(posts.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_source_sits_next_to_the_page() {
+        assert_eq!(signed_source_path("hardening-nginx.html"), "hardening-nginx.md.asc");
+        assert_eq!(signed_source_path("2024/05/hardening-nginx/index.html"), "2024/05/hardening-nginx/index.md.asc");
+    }
+
+    #[test]
+    fn test_protected_sources_are_not_signed() {
+        let mut protected = Post::default();
+        protected.meta.slug = "members".to_string();
+        protected.meta.protected = true;
+        let mut public = Post::default();
+        public.meta.slug = "hello".to_string();
+        let posts = [protected, public];
+        let signed: Vec<&str> = signed_posts(&posts).iter().map(|post| post.meta.slug.as_str()).collect();
+        assert_eq!(signed, ["hello"]);
+    }
+
+    #[test]
+    fn test_source_link_is_relative() {
+        assert_eq!(
+            source_link("2024/05/x/index.html", "ABCD"),
+            "<p class=\"signed-source\"><a href=\"index.md.asc\" type=\"text/plain\">Signed markdown source</a> \
+             (OpenPGP key <code>ABCD</code>)</p>\n"
+        );
+    }
+}
@@ -0,0 +1,733 @@
+//! Command-line parsing
+
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+use crate::freshness::Age;
+
+/// Subcommand selected on the command line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Generate the site (default)
+    Build {
+        /// Build the content of this commit from the object database instead of the working tree
+        rev: Option<String>,
+        /// Build into memory and list the output files that would change
+        dry_run: bool,
+    },
+    /// Build into the preview directory with pages that refresh themselves
+    Preview {
+        /// Seconds between refreshes
+        refresh: u32,
+    },
+    /// Run a standalone check, reporting in the given format
+    Check(CheckCommand, OutputFormat),
+    /// Send webmentions for published posts (post-deploy, needs network)
+    Webmention,
+    /// Record the output as deployed and list changed pages (post-deploy)
+    Deployed {
+        /// Also submit the changed URLs to IndexNow and ping the sitemap (needs network)
+        notify: bool,
+    },
+    /// Submit links of cited posts to web archives (needs network)
+    CitationsArchive,
+    /// Create a content file from an archetype
+    New {
+        /// Archetype name (`post`, `note`, ...)
+        kind: String,
+        /// Title of the new post
+        title: String,
+    },
+    /// Convert another generator's site into `content/`
+    Import {
+        /// Source generator
+        from: ImportFormat,
+        /// Root of the site to import
+        path: PathBuf,
+    },
+    /// List posts grouped by workflow status
+    Status,
+    /// Audit config, content, archetypes, key files and output paths before building
+    Doctor,
+    /// Scaffold a new site: config, hardened templates, sample post, security.txt, .gitignore
+    Init {
+        /// Directory to create the site in
+        dir: PathBuf,
+    },
+    /// Page weight, bandwidth and carbon estimate for the built site
+    Report {
+        /// Monthly page views, overriding the configured assumption
+        pageviews: Option<u64>,
+    },
+    /// Published posts not updated within a period, oldest first
+    ReportStale {
+        /// Last update older than this is stale
+        older_than: Age,
+        /// Only posts with one of these tags (all posts when empty)
+        tags: Vec<String>,
+    },
+    /// Aggregate access logs into the stats data file
+    Stats {
+        /// Common or combined format access logs
+        logs: Vec<PathBuf>,
+    },
+    /// Package built posts for offline reading
+    Export(ExportCommand),
+    /// Verify signatures of a release or build offline
+    Verify(VerifyCommand),
+    /// Hash of the running binary and its embedded build metadata, checked against the config
+    AttestSelf,
+    /// Record the hashes of the configured theme's files in `<theme>.lock`
+    ThemeLock,
+    /// Vendor a theme into `themes/` at a pinned revision
+    ThemeInstall {
+        /// Git URL or repository path
+        source: String,
+        /// Tag, branch or commit, resolved to a commit in the lock
+        rev: String,
+    },
+    /// Check the configured theme against its lock and pinned commit
+    ThemeVerify,
+    /// Rewrite `.secureblog-baseline.json` from the findings in the last build's output
+    BaselineUpdate,
+    /// Check the chain of published content snapshots and list what changed
+    HistoryVerify {
+        /// Snapshot directory, e.g. a mirror's `archive/` (defaults to the configured one)
+        dir: Option<PathBuf>,
+    },
+    /// Time rendering, sanitizing, validating and hashing generated posts
+    Bench {
+        /// Number of synthetic posts
+        posts: usize,
+    },
+    /// Print the JSON context the templates receive for one output page
+    DumpContext {
+        /// Output path or URL path (`index.html`, `/2024/05/slug/`)
+        page: String,
+    },
+}
+
+/// Offline verification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyCommand {
+    /// cosign / Sigstore bundle against the embedded Fulcio and Rekor roots
+    CosignBundle {
+        /// Bundle JSON
+        bundle: PathBuf,
+        /// Signed file (defaults to `integrity.json` in the output directory)
+        artifact: Option<PathBuf>,
+        /// Required certificate identity (email or workflow URI)
+        identity: String,
+        /// Required OIDC issuer of the certificate
+        issuer: String,
+    },
+    /// Manifest signature and its stored Rekor entry, including the inclusion proof
+    Rekor {
+        /// Manifest (defaults to `integrity.json` in the output directory)
+        manifest: Option<PathBuf>,
+        /// Required signing key fingerprint (defaults to `rekor.fingerprint`, then the `sign_files` key's)
+        fingerprint: Option<String>,
+    },
+}
+
+/// Offline export formats
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExportCommand {
+    /// Self-contained HTML file per post
+    Bundle {
+        /// Post slug or `all`
+        target: String,
+    },
+    /// EPUB3 book of the selected posts
+    Epub(Selection),
+    /// Deterministic tarball of the whole site with its manifest and signature
+    Archive,
+}
+
+/// Posts included in a book
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selection {
+    /// Slugs in reading order, or `all`
+    Posts(Vec<String>),
+    /// Every post of the named series, oldest first
+    Series(String),
+}
+
+/// How check results are reported
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Log lines
+    #[default]
+    Text,
+    /// GitHub Actions `::error file=...,line=...::` annotations on stdout
+    Github,
+}
+
+/// Checks runnable separately from the build
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckCommand {
+    /// Spelling and repeated word linting of markdown sources
+    Prose,
+    /// Security violations and broken links in the built output, outside the baseline
+    Output {
+        /// Show the matched line and each rule's explanation
+        verbose: bool,
+    },
+    /// Why a rule exists and how to fix or allowlist its findings
+    Explain {
+        /// Built-in check, `links` or site rule name
+        rule: String,
+    },
+    /// Only the posts affected by these files (default: files differing from git HEAD)
+    Changed {
+        /// Changed files, e.g. from a pre-commit hook
+        paths: Vec<PathBuf>,
+    },
+    /// Build twice and fail if the outputs differ in any byte
+    Determinism,
+    /// Warn about published posts past their `review_by:` date
+    Reviews,
+    /// Compare a deployed site's response headers with the generated `_headers`
+    Headers {
+        /// Site root, e.g. `https://example.com` (defaults to `url` from the config)
+        url: Option<String>,
+    },
+    /// HSTS preload list requirements for the site's domain
+    Hsts {
+        /// Domain to check (defaults to the host of `url`)
+        domain: Option<String>,
+    },
+    /// CAA, DNSSEC and optionally DANE TLSA records of the site's domain
+    Dns {
+        /// Domain to check (defaults to the host of `url`)
+        domain: Option<String>,
+        /// Also require a TLSA record for `_443._tcp`
+        tlsa: bool,
+    },
+}
+
+/// Site generators that can be imported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// `_posts/`, `_drafts/` and `_config.yml`
+    Jekyll,
+    /// `content/` sections with TOML frontmatter and `config.toml`
+    Zola,
+}
+
+/// Parse command-line arguments (excluding the program name)
+pub fn parse<I>(args: I) -> Result<Command>
+where
+    I: IntoIterator<Item = String>,
+{
+    let args: Vec<String> = args.into_iter().collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        [] => Ok(Command::Build { rev: None, dry_run: false }),
+        ["build", rest @ ..] => parse_build(rest),
+        ["check", rest @ ..] => parse_check(rest),
+        ["preview"] => Ok(Command::Preview { refresh: crate::preview::DEFAULT_INTERVAL }),
+        ["preview", "--refresh", seconds] => match seconds.parse() {
+            Ok(refresh) if refresh > 0 => Ok(Command::Preview { refresh }),
+            _ => anyhow::bail!("Invalid refresh interval: {seconds} (whole seconds, at least 1)"),
+        },
+        ["preview", ..] => anyhow::bail!("Usage: preview [--refresh SECONDS]"),
+        ["webmention", "send"] => Ok(Command::Webmention),
+        ["deployed"] => Ok(Command::Deployed { notify: false }),
+        ["deployed", "--notify"] => Ok(Command::Deployed { notify: true }),
+        ["deployed", ..] => anyhow::bail!("Usage: deployed [--notify]"),
+        ["citations", "archive"] => Ok(Command::CitationsArchive),
+        ["citations", ..] => anyhow::bail!("Usage: citations archive"),
+        ["new", kind, title] => Ok(Command::New {
+            kind: (*kind).to_string(),
+            title: (*title).to_string(),
+        }),
+        ["new", ..] => anyhow::bail!("Usage: new <kind> \"Title\""),
+        ["import", "jekyll", path] => Ok(Command::Import {
+            from: ImportFormat::Jekyll,
+            path: PathBuf::from(path),
+        }),
+        ["import", "zola", path] => Ok(Command::Import {
+            from: ImportFormat::Zola,
+            path: PathBuf::from(path),
+        }),
+        ["import", ..] => anyhow::bail!("Usage: import jekyll|zola <site-dir>"),
+        ["status"] => Ok(Command::Status),
+        ["doctor"] => Ok(Command::Doctor),
+        ["--attest-self"] => Ok(Command::AttestSelf),
+        ["theme", "lock"] => Ok(Command::ThemeLock),
+        ["theme", "install", spec] => {
+            let (source, rev) = crate::vendor::parse_spec(spec)?;
+            Ok(Command::ThemeInstall { source, rev })
+        }
+        ["theme", "verify"] => Ok(Command::ThemeVerify),
+        ["theme", ..] => anyhow::bail!("Usage: theme lock | theme install <git-url-or-path>@<rev> | theme verify"),
+        ["baseline", "update"] => Ok(Command::BaselineUpdate),
+        ["baseline", ..] => anyhow::bail!("Usage: baseline update"),
+        ["history", "verify"] => Ok(Command::HistoryVerify { dir: None }),
+        ["history", "verify", dir] => Ok(Command::HistoryVerify { dir: Some(PathBuf::from(dir)) }),
+        ["history", ..] => anyhow::bail!("Usage: history verify [dir]"),
+        ["render", "--dump-context", page] => Ok(Command::DumpContext { page: (*page).to_string() }),
+        ["render", ..] => anyhow::bail!("Usage: render --dump-context <page>"),
+        ["bench", "--synthetic", n] => match n.parse() {
+            Ok(posts) if posts > 0 => Ok(Command::Bench { posts }),
+            _ => anyhow::bail!("Invalid post count: {n} (at least 1)"),
+        },
+        ["bench", ..] => anyhow::bail!("Usage: bench --synthetic N"),
+        ["init", dir] => Ok(Command::Init { dir: PathBuf::from(dir) }),
+        ["init", ..] => anyhow::bail!("Usage: init <dir>"),
+        ["report"] => Ok(Command::Report { pageviews: None }),
+        ["report", "--pageviews", n] => Ok(Command::Report {
+            pageviews: Some(n.parse().with_context(|| format!("Invalid page view count: {n}"))?),
+        }),
+        ["report", "stale", rest @ ..] => parse_stale(rest),
+        ["stats", "logs", logs @ ..] if !logs.is_empty() => Ok(Command::Stats {
+            logs: logs.iter().map(PathBuf::from).collect(),
+        }),
+        ["stats", ..] => anyhow::bail!("Usage: stats logs <access.log>..."),
+        ["export", "bundle", target] => Ok(Command::Export(ExportCommand::Bundle {
+            target: (*target).to_string(),
+        })),
+        ["export", "archive"] => Ok(Command::Export(ExportCommand::Archive)),
+        ["export", "epub", "--series", name] => Ok(Command::Export(ExportCommand::Epub(Selection::Series(
+            (*name).to_string(),
+        )))),
+        ["export", "epub", targets @ ..] if !targets.is_empty() && !targets[0].starts_with("--") => {
+            Ok(Command::Export(ExportCommand::Epub(Selection::Posts(
+                targets.iter().map(|t| (*t).to_string()).collect(),
+            ))))
+        }
+        ["export", ..] => anyhow::bail!("Usage: export bundle <slug|all> | export epub <slug...|all|--series NAME> | export archive"),
+        ["verify", "--cosign-bundle", bundle, rest @ ..] => parse_cosign_verify(bundle, rest),
+        ["verify", "--rekor", rest @ ..] => parse_rekor_verify(rest),
+        ["verify", ..] => anyhow::bail!(
+            "Usage: verify --cosign-bundle <bundle.json> [artifact] --identity <email|uri> --issuer <url> | verify --rekor [manifest] [--fingerprint SHA256:...]"
+        ),
+        [other, ..] => anyhow::bail!("Unknown command: {other}"),
+    }
+}
+
+/// Check name, its arguments and an optional `--format text|github`
+fn parse_check(args: &[&str]) -> Result<Command> {
+    let (format, args) = match args.iter().position(|arg| *arg == "--format") {
+        Some(i) => {
+            let format = match args.get(i + 1) {
+                Some(&"text") => OutputFormat::Text,
+                Some(&"github") => OutputFormat::Github,
+                _ => anyhow::bail!("Usage: --format text|github"),
+            };
+            (format, [&args[..i], &args[i + 2..]].concat())
+        }
+        None => (OutputFormat::Text, args.to_vec()),
+    };
+
+    let check = match args.as_slice() {
+        ["prose"] => CheckCommand::Prose,
+        ["output"] => CheckCommand::Output { verbose: false },
+        ["output", "--verbose"] => CheckCommand::Output { verbose: true },
+        ["--explain", rule] => CheckCommand::Explain { rule: (*rule).to_string() },
+        ["--changed", paths @ ..] if !paths.iter().any(|p| p.starts_with("--")) => CheckCommand::Changed {
+            paths: paths.iter().map(PathBuf::from).collect(),
+        },
+        ["determinism"] => CheckCommand::Determinism,
+        ["reviews"] => CheckCommand::Reviews,
+        ["headers", url] => CheckCommand::Headers { url: Some((*url).to_string()) },
+        ["headers"] => CheckCommand::Headers { url: None },
+        ["hsts", domain] => CheckCommand::Hsts { domain: Some((*domain).to_string()) },
+        ["hsts"] => CheckCommand::Hsts { domain: None },
+        ["dns", rest @ ..] => {
+            let tlsa = rest.contains(&"--tlsa");
+            match rest.iter().filter(|arg| **arg != "--tlsa").collect::<Vec<_>>().as_slice() {
+                [] => CheckCommand::Dns { domain: None, tlsa },
+                [domain] if !domain.starts_with("--") => CheckCommand::Dns { domain: Some((*domain).to_string()), tlsa },
+                _ => anyhow::bail!("Usage: check dns [domain] [--tlsa]"),
+            }
+        }
+        [other, ..] => anyhow::bail!("Unknown check: {other}"),
+        [] => anyhow::bail!("Missing check name (available: prose, output, --changed, --explain, determinism, reviews, headers, hsts, dns)"),
+    };
+    if format == OutputFormat::Github && matches!(check, CheckCommand::Headers { .. }) {
+        anyhow::bail!("check headers reports per page and does not support --format github");
+    }
+    if format == OutputFormat::Github && matches!(check, CheckCommand::Explain { .. } | CheckCommand::Output { verbose: true }) {
+        anyhow::bail!("Explanations are text only and do not support --format github");
+    }
+    Ok(Command::Check(check, format))
+}
+
+/// `--rev <commit>` and `--dry-run` after `build`, in any order
+fn parse_build(args: &[&str]) -> Result<Command> {
+    let dry_run = args.contains(&"--dry-run");
+    match args.iter().copied().filter(|arg| *arg != "--dry-run").collect::<Vec<_>>().as_slice() {
+        [] => Ok(Command::Build { rev: None, dry_run }),
+        ["--rev", rev] if !rev.starts_with("--") => Ok(Command::Build { rev: Some((*rev).to_string()), dry_run }),
+        _ => anyhow::bail!("Usage: build [--rev <commit>] [--dry-run]"),
+    }
+}
+
+/// Optional artifact and `--identity` after `verify --cosign-bundle <bundle>`
+fn parse_cosign_verify(bundle: &str, mut rest: &[&str]) -> Result<Command> {
+    const USAGE: &str = "Usage: verify --cosign-bundle <bundle.json> [artifact] --identity <email|uri> --issuer <url>";
+    let mut artifact = None;
+    let (mut identity, mut issuer) = (None, None);
+    while !rest.is_empty() {
+        match rest {
+            ["--identity", value, tail @ ..] => {
+                identity = Some((*value).to_string());
+                rest = tail;
+            }
+            ["--issuer", value, tail @ ..] => {
+                issuer = Some((*value).to_string());
+                rest = tail;
+            }
+            [path, tail @ ..] if artifact.is_none() && !path.starts_with("--") => {
+                artifact = Some(PathBuf::from(path));
+                rest = tail;
+            }
+            _ => anyhow::bail!(USAGE),
+        }
+    }
+    let (Some(identity), Some(issuer)) = (identity, issuer) else {
+        anyhow::bail!("{USAGE} (a bundle proves nothing without the signer it must come from)");
+    };
+    Ok(Command::Verify(VerifyCommand::CosignBundle { bundle: PathBuf::from(bundle), artifact, identity, issuer }))
+}
+
+fn parse_rekor_verify(rest: &[&str]) -> Result<Command> {
+    let (fingerprint, rest) = match rest {
+        [manifest @ .., "--fingerprint", fingerprint] => (Some((*fingerprint).to_string()), manifest),
+        _ => (None, rest),
+    };
+    let manifest = match rest {
+        [] => None,
+        [manifest] if !manifest.starts_with("--") => Some(PathBuf::from(manifest)),
+        _ => anyhow::bail!("Usage: verify --rekor [manifest] [--fingerprint SHA256:...]"),
+    };
+    Ok(Command::Verify(VerifyCommand::Rekor { manifest, fingerprint }))
+}
+
+fn parse_stale(mut rest: &[&str]) -> Result<Command> {
+    const USAGE: &str = "Usage: report stale --older-than <2y|18m|6w|90d> [--tag <tag>]...";
+    let mut older_than = None;
+    let mut tags = Vec::new();
+    while !rest.is_empty() {
+        match rest {
+            ["--older-than", age, tail @ ..] => {
+                older_than = Some(Age::parse(age)?);
+                rest = tail;
+            }
+            ["--tag", tag, tail @ ..] => {
+                tags.push((*tag).to_string());
+                rest = tail;
+            }
+            _ => anyhow::bail!(USAGE),
+        }
+    }
+    let older_than = older_than.context(USAGE)?;
+    Ok(Command::ReportStale { older_than, tags })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| (*s).to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_defaults_to_build() {
+        assert_eq!(parse(args(&[])).unwrap(), Command::Build { rev: None, dry_run: false });
+        assert_eq!(parse(args(&["build"])).unwrap(), Command::Build { rev: None, dry_run: false });
+        assert_eq!(
+            parse(args(&["build", "--dry-run", "--rev", "v1.0"])).unwrap(),
+            Command::Build { rev: Some("v1.0".to_string()), dry_run: true }
+        );
+        assert!(parse(args(&["build", "--rev"])).is_err());
+        assert!(parse(args(&["build", "--rev", "--dry-run"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_check_prose() {
+        assert_eq!(
+            parse(args(&["check", "prose"])).unwrap(),
+            Command::Check(CheckCommand::Prose, OutputFormat::Text)
+        );
+        assert_eq!(
+            parse(args(&["check", "determinism", "--format", "github"])).unwrap(),
+            Command::Check(CheckCommand::Determinism, OutputFormat::Github)
+        );
+        assert_eq!(
+            parse(args(&["check", "reviews"])).unwrap(),
+            Command::Check(CheckCommand::Reviews, OutputFormat::Text)
+        );
+    }
+
+    #[test]
+    fn test_parse_check_headers() {
+        assert_eq!(
+            parse(args(&["check", "headers", "https://example.com"])).unwrap(),
+            Command::Check(CheckCommand::Headers { url: Some("https://example.com".to_string()) }, OutputFormat::Text)
+        );
+        assert_eq!(
+            parse(args(&["check", "headers"])).unwrap(),
+            Command::Check(CheckCommand::Headers { url: None }, OutputFormat::Text)
+        );
+        assert_eq!(
+            parse(args(&["check", "hsts", "example.com"])).unwrap(),
+            Command::Check(CheckCommand::Hsts { domain: Some("example.com".to_string()) }, OutputFormat::Text)
+        );
+        assert_eq!(
+            parse(args(&["check", "dns", "--tlsa", "example.com"])).unwrap(),
+            Command::Check(CheckCommand::Dns { domain: Some("example.com".to_string()), tlsa: true }, OutputFormat::Text)
+        );
+        assert_eq!(
+            parse(args(&["check", "dns"])).unwrap(),
+            Command::Check(CheckCommand::Dns { domain: None, tlsa: false }, OutputFormat::Text)
+        );
+        assert!(parse(args(&["check", "dns", "a.com", "b.com"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_check_explain() {
+        assert_eq!(
+            parse(args(&["check", "--explain", "inline_styles"])).unwrap(),
+            Command::Check(CheckCommand::Explain { rule: "inline_styles".to_string() }, OutputFormat::Text)
+        );
+        assert_eq!(
+            parse(args(&["check", "output", "--verbose"])).unwrap(),
+            Command::Check(CheckCommand::Output { verbose: true }, OutputFormat::Text)
+        );
+        assert!(parse(args(&["check", "--explain"])).is_err());
+        assert!(parse(args(&["check", "output", "--verbose", "--format", "github"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_check_format() {
+        assert_eq!(
+            parse(args(&["check", "output", "--format", "github"])).unwrap(),
+            Command::Check(CheckCommand::Output { verbose: false }, OutputFormat::Github)
+        );
+        assert_eq!(
+            parse(args(&["check", "--format", "github", "dns", "--tlsa"])).unwrap(),
+            Command::Check(CheckCommand::Dns { domain: None, tlsa: true }, OutputFormat::Github)
+        );
+        assert_eq!(
+            parse(args(&["check", "prose", "--format", "text"])).unwrap(),
+            Command::Check(CheckCommand::Prose, OutputFormat::Text)
+        );
+        assert_eq!(
+            parse(args(&["check", "--changed", "content/a.md", "src/poc.rs", "--format", "github"])).unwrap(),
+            Command::Check(
+                CheckCommand::Changed { paths: vec![PathBuf::from("content/a.md"), PathBuf::from("src/poc.rs")] },
+                OutputFormat::Github
+            )
+        );
+        assert_eq!(
+            parse(args(&["check", "--changed"])).unwrap(),
+            Command::Check(CheckCommand::Changed { paths: Vec::new() }, OutputFormat::Text)
+        );
+        assert!(parse(args(&["check", "prose", "--format", "xml"])).is_err());
+        assert!(parse(args(&["check", "prose", "--format"])).is_err());
+        assert!(parse(args(&["check", "headers", "--format", "github"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_webmention_send() {
+        assert_eq!(parse(args(&["webmention", "send"])).unwrap(), Command::Webmention);
+        assert!(parse(args(&["webmention"])).is_err());
+        assert_eq!(parse(args(&["citations", "archive"])).unwrap(), Command::CitationsArchive);
+        assert_eq!(parse(args(&["deployed"])).unwrap(), Command::Deployed { notify: false });
+        assert_eq!(parse(args(&["deployed", "--notify"])).unwrap(), Command::Deployed { notify: true });
+        assert!(parse(args(&["deployed", "--ping"])).is_err());
+        assert!(parse(args(&["citations"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_bench() {
+        assert_eq!(parse(args(&["bench", "--synthetic", "500"])).unwrap(), Command::Bench { posts: 500 });
+        assert!(parse(args(&["bench", "--synthetic", "0"])).is_err());
+        assert!(parse(args(&["bench"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_report() {
+        assert_eq!(parse(args(&["report"])).unwrap(), Command::Report { pageviews: None });
+        assert_eq!(
+            parse(args(&["report", "--pageviews", "2500"])).unwrap(),
+            Command::Report { pageviews: Some(2500) }
+        );
+        assert!(parse(args(&["report", "--pageviews", "many"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_report_stale() {
+        assert_eq!(
+            parse(args(&["report", "stale", "--older-than", "2y"])).unwrap(),
+            Command::ReportStale { older_than: Age::Months(24), tags: vec![] }
+        );
+        assert_eq!(
+            parse(args(&["report", "stale", "--tag", "tls", "--older-than", "90d", "--tag", "ssh"])).unwrap(),
+            Command::ReportStale { older_than: Age::Days(90), tags: vec!["tls".to_string(), "ssh".to_string()] }
+        );
+        assert!(parse(args(&["report", "stale"])).is_err());
+        assert!(parse(args(&["report", "stale", "--older-than", "2x"])).is_err());
+        assert!(parse(args(&["report", "stale", "--older-than", "2y", "--tag"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_new() {
+        assert_eq!(
+            parse(args(&["new", "post", "Hello World"])).unwrap(),
+            Command::New { kind: "post".to_string(), title: "Hello World".to_string() }
+        );
+        assert!(parse(args(&["new", "post"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_import() {
+        assert_eq!(
+            parse(args(&["import", "jekyll", "../old-site"])).unwrap(),
+            Command::Import { from: ImportFormat::Jekyll, path: PathBuf::from("../old-site") }
+        );
+        assert_eq!(
+            parse(args(&["import", "zola", "site"])).unwrap(),
+            Command::Import { from: ImportFormat::Zola, path: PathBuf::from("site") }
+        );
+        assert!(parse(args(&["import", "hugo", "site"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_status() {
+        assert_eq!(parse(args(&["status"])).unwrap(), Command::Status);
+        assert_eq!(parse(args(&["doctor"])).unwrap(), Command::Doctor);
+        assert_eq!(parse(args(&["--attest-self"])).unwrap(), Command::AttestSelf);
+        assert_eq!(parse(args(&["theme", "lock"])).unwrap(), Command::ThemeLock);
+        assert!(parse(args(&["theme"])).is_err());
+        assert_eq!(parse(args(&["theme", "verify"])).unwrap(), Command::ThemeVerify);
+        assert_eq!(
+            parse(args(&["theme", "install", "../paper@v2"])).unwrap(),
+            Command::ThemeInstall { source: "../paper".to_string(), rev: "v2".to_string() }
+        );
+        assert!(parse(args(&["theme", "install", "../paper"])).is_err());
+        assert_eq!(parse(args(&["baseline", "update"])).unwrap(), Command::BaselineUpdate);
+        assert!(parse(args(&["baseline"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_preview() {
+        assert_eq!(parse(args(&["preview"])).unwrap(), Command::Preview { refresh: 2 });
+        assert_eq!(parse(args(&["preview", "--refresh", "5"])).unwrap(), Command::Preview { refresh: 5 });
+        assert!(parse(args(&["preview", "--refresh", "0"])).is_err());
+        assert!(parse(args(&["preview", "--refresh"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_history_verify() {
+        assert_eq!(parse(args(&["history", "verify"])).unwrap(), Command::HistoryVerify { dir: None });
+        assert_eq!(
+            parse(args(&["history", "verify", "mirror/archive"])).unwrap(),
+            Command::HistoryVerify { dir: Some(PathBuf::from("mirror/archive")) }
+        );
+        assert!(parse(args(&["history"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_render_dump_context() {
+        assert_eq!(
+            parse(args(&["render", "--dump-context", "/"])).unwrap(),
+            Command::DumpContext { page: "/".to_string() }
+        );
+        assert!(parse(args(&["render"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_init() {
+        assert_eq!(parse(args(&["init", "my-blog"])).unwrap(), Command::Init { dir: PathBuf::from("my-blog") });
+        assert!(parse(args(&["init"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_stats_logs() {
+        assert_eq!(
+            parse(args(&["stats", "logs", "access.log", "access.log.1"])).unwrap(),
+            Command::Stats { logs: vec![PathBuf::from("access.log"), PathBuf::from("access.log.1")] }
+        );
+        assert!(parse(args(&["stats", "logs"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown() {
+        assert!(parse(args(&["deploy"])).is_err());
+        assert!(parse(args(&["check"])).is_err());
+        assert!(parse(args(&["check", "nothing"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_verify_cosign_bundle() {
+        assert_eq!(
+            parse(args(&[
+                "verify",
+                "--cosign-bundle",
+                "dist.sigstore.json",
+                "--identity",
+                "me@example.com",
+                "--issuer",
+                "https://github.com/login/oauth"
+            ]))
+            .unwrap(),
+            Command::Verify(VerifyCommand::CosignBundle {
+                bundle: PathBuf::from("dist.sigstore.json"),
+                artifact: None,
+                identity: "me@example.com".to_string(),
+                issuer: "https://github.com/login/oauth".to_string(),
+            })
+        );
+        assert_eq!(
+            parse(args(&["verify", "--cosign-bundle", "b.json", "site.tar.gz", "--issuer", "https://x", "--identity", "me"])).unwrap(),
+            Command::Verify(VerifyCommand::CosignBundle {
+                bundle: PathBuf::from("b.json"),
+                artifact: Some(PathBuf::from("site.tar.gz")),
+                identity: "me".to_string(),
+                issuer: "https://x".to_string(),
+            })
+        );
+        assert!(parse(args(&["verify", "--cosign-bundle", "b.json"])).is_err());
+        assert!(parse(args(&["verify", "--cosign-bundle", "b.json", "--identity", "me@example.com"])).is_err());
+        assert_eq!(
+            parse(args(&["verify", "--rekor"])).unwrap(),
+            Command::Verify(VerifyCommand::Rekor { manifest: None, fingerprint: None })
+        );
+        assert_eq!(
+            parse(args(&["verify", "--rekor", "m.json", "--fingerprint", "SHA256:abc"])).unwrap(),
+            Command::Verify(VerifyCommand::Rekor {
+                manifest: Some(PathBuf::from("m.json")),
+                fingerprint: Some("SHA256:abc".to_string()),
+            })
+        );
+        assert!(parse(args(&["verify", "--rekor", "--fingerprint"])).is_err());
+        assert!(parse(args(&["verify"])).is_err());
+        assert!(parse(args(&["verify", "--cosign-bundle", "b.json", "a", "b", "--identity", "i", "--issuer", "u"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_export() {
+        assert_eq!(
+            parse(args(&["export", "bundle", "all"])).unwrap(),
+            Command::Export(ExportCommand::Bundle { target: "all".to_string() })
+        );
+        assert!(parse(args(&["export", "bundle"])).is_err());
+        assert_eq!(
+            parse(args(&["export", "epub", "part-1", "part-2"])).unwrap(),
+            Command::Export(ExportCommand::Epub(Selection::Posts(vec!["part-1".to_string(), "part-2".to_string()])))
+        );
+        assert_eq!(
+            parse(args(&["export", "epub", "--series", "Fuzzing"])).unwrap(),
+            Command::Export(ExportCommand::Epub(Selection::Series("Fuzzing".to_string())))
+        );
+        assert!(parse(args(&["export", "epub"])).is_err());
+        assert!(parse(args(&["export", "epub", "--series"])).is_err());
+        assert_eq!(parse(args(&["export", "archive"])).unwrap(), Command::Export(ExportCommand::Archive));
+    }
+}
@@ -0,0 +1,211 @@
+//! Static comments stored as `comments/<slug>/*.yaml`
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use tracing::info;
+
+use crate::security::{escape_html, external_link_rel};
+use crate::{i18n, inject, markdown, Config, Post, SecurityPolicy};
+
+/// Maximum length of a comment author name
+const MAX_AUTHOR_LEN: usize = 100;
+
+/// Maximum size of a comment body (bytes of markdown)
+const MAX_BODY_LEN: usize = 10 * 1024;
+
+/// A single comment file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Comment {
+    /// Display name of the commenter
+    pub author: String,
+    /// When the comment was made
+    pub date: DateTime<Utc>,
+    /// Comment text (markdown, sanitized on render)
+    pub body: String,
+    /// Optional commenter website (https only)
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+impl Comment {
+    /// Check field limits and URL scheme
+    pub fn validate(&self) -> Result<()> {
+        let author = self.author.trim();
+        if author.is_empty() || author.chars().count() > MAX_AUTHOR_LEN {
+            anyhow::bail!("author must be 1-{MAX_AUTHOR_LEN} characters");
+        }
+        if self.body.trim().is_empty() || self.body.len() > MAX_BODY_LEN {
+            anyhow::bail!("body must be non-empty and at most {MAX_BODY_LEN} bytes");
+        }
+        if let Some(url) = &self.url {
+            if !url.starts_with("https://") || url.contains(['"', '\'', '<', '>', ' ']) {
+                anyhow::bail!("url must be a plain https:// URL");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Load and validate every comment for `slug`, oldest first
+pub fn load_comments(comments_dir: &Path, slug: &str) -> Result<Vec<Comment>> {
+    let dir = comments_dir.join(slug);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut paths: Vec<_> = fs::read_dir(&dir)
+        .with_context(|| format!("Failed to read comments: {}", dir.display()))?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.extension()
+                .and_then(|s| s.to_str())
+                .is_some_and(|ext| ext == "yaml" || ext == "yml")
+        })
+        .collect();
+    paths.sort();
+
+    let mut comments = paths
+        .iter()
+        .map(|path| {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read comment: {}", path.display()))?;
+            let comment: Comment = serde_yaml::from_str(&content)
+                .with_context(|| format!("Failed to parse comment: {}", path.display()))?;
+            comment
+                .validate()
+                .with_context(|| format!("Invalid comment: {}", path.display()))?;
+            Ok(comment)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    comments.sort_by_key(|c| c.date);
+    Ok(comments)
+}
+
+/// Render comments into a sanitized `<section>`
+pub fn render_comments(comments: &[Comment], language: &str, zone: Tz, policy: &SecurityPolicy) -> Result<String> {
+    let mut html = String::from("<section class=\"comments\" id=\"comments\">\n<h2>Comments</h2>\n");
+    // Links in comments are the commenters', never endorsed by the site
+    let mut policy = policy.clone();
+    for token in ["nofollow", "ugc"] {
+        if !policy.external_link_rel.iter().any(|t| t == token) {
+            policy.external_link_rel.push(token.to_string());
+        }
+    }
+    let policy = &policy;
+
+    for (index, comment) in comments.iter().enumerate() {
+        let author = escape_html(comment.author.trim());
+        // Commenter links are external resources, only allowed when the policy permits them
+        let author = match comment.url.as_ref().filter(|url| !policy.no_external || policy.allows_url(url)) {
+            Some(url) => format!(
+                "<a href=\"{}\" rel=\"{}\">{author}</a>",
+                escape_html(url),
+                external_link_rel(policy)
+            ),
+            None => author,
+        };
+        let body = markdown::render_markdown(&comment.body, policy)?;
+
+        write!(
+            html,
+            "<article class=\"comment\" id=\"comment-{n}\">\n<header><strong>{author}</strong> \
+             {time}</header>\n{body}\n</article>\n",
+            n = index + 1,
+            time = i18n::time_element(&comment.date.with_timezone(&zone), language, None),
+        )?;
+    }
+
+    html.push_str("</section>\n");
+    Ok(html)
+}
+
+/// Render comments under every post that has any
+pub fn apply(config: &Config, posts: &[Post], policy: &SecurityPolicy) -> Result<()> {
+    for post in posts {
+        let comments = load_comments(&config.comments, &post.meta.slug)?;
+        if comments.is_empty() {
+            continue;
+        }
+
+        let section = render_comments(&comments, &config.i18n.language, config.timezone.display_zone(), policy)?;
+        inject::inject_into_file(
+            &config.output.join(post.path()),
+            &["</article>", "</main>", "</body>"],
+            &section,
+        )?;
+        info!("Rendered {} comments for {}", comments.len(), post.meta.slug);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comment(yaml: &str) -> Result<Comment> {
+        let comment: Comment = serde_yaml::from_str(yaml)?;
+        comment.validate()?;
+        Ok(comment)
+    }
+
+    #[test]
+    fn test_comment_validation() {
+        assert!(comment("author: Ada\ndate: 2026-01-01T00:00:00Z\nbody: Nice post\n").is_ok());
+        assert!(comment("author: ''\ndate: 2026-01-01T00:00:00Z\nbody: x\n").is_err());
+        assert!(comment("author: Ada\ndate: 2026-01-01T00:00:00Z\nbody: x\nurl: javascript:alert(1)\n").is_err());
+        assert!(comment("author: Ada\ndate: 2026-01-01T00:00:00Z\nbody: x\nscript: y\n").is_err());
+    }
+
+    #[test]
+    fn test_author_is_escaped() {
+        let comments = vec![Comment {
+            author: "<script>alert(1)</script>".to_string(),
+            date: "2026-01-01T00:00:00Z".parse().unwrap(),
+            body: "Hello".to_string(),
+            url: None,
+        }];
+        let html = render_comments(&comments, "en", Tz::UTC, &SecurityPolicy::default()).unwrap();
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("id=\"comment-1\""));
+    }
+
+    #[test]
+    fn test_comment_links_are_ugc() {
+        let comments = vec![Comment {
+            author: "Ada".to_string(),
+            date: "2026-01-01T00:00:00Z".parse().unwrap(),
+            body: "See [this](https://example.org/)".to_string(),
+            url: Some("https://ada.example/".to_string()),
+        }];
+        let policy = SecurityPolicy { no_external: false, ..SecurityPolicy::default() };
+        let html = render_comments(&comments, "en", Tz::UTC, &policy).unwrap();
+        assert!(html.contains("<a href=\"https://ada.example/\" rel=\"noopener noreferrer nofollow ugc\">Ada</a>"));
+        assert!(html.contains("<a href=\"https://example.org/\" rel=\"noopener noreferrer nofollow ugc\">this</a>"));
+    }
+
+    #[test]
+    fn test_commenter_links_follow_no_external() {
+        let comments = vec![Comment {
+            author: "Ada".to_string(),
+            date: "2026-01-01T00:00:00Z".parse().unwrap(),
+            body: "Thanks".to_string(),
+            url: Some("https://ada.example/".to_string()),
+        }];
+        let policy = SecurityPolicy { no_external: true, ..SecurityPolicy::default() };
+        let html = render_comments(&comments, "en", Tz::UTC, &policy).unwrap();
+        assert!(!html.contains("ada.example") && html.contains("<strong>Ada</strong>"));
+
+        let policy = SecurityPolicy { no_external: true, allowed_hosts: vec!["ada.example".to_string()], ..SecurityPolicy::default() };
+        let html = render_comments(&comments, "en", Tz::UTC, &policy).unwrap();
+        assert!(html.contains("<a href=\"https://ada.example/\""));
+    }
+}
@@ -0,0 +1,240 @@
+//! Template contexts: `render --dump-context <page>` and golden tests
+//!
+//! The index and every post page are rendered from a JSON-shaped context:
+//! the site's settings plus the post (or the post list) the page shows.
+//! Dumping it lets theme authors see exactly which fields a template can use.
+//! In tests, `assert_golden` compares a context against a checked-in JSON file
+//! so the suite notices when the data templates rely on changes.
+
+use anyhow::Result;
+use serde::Serialize;
+#[cfg(test)]
+use anyhow::Context;
+#[cfg(test)]
+use serde_json::Value;
+#[cfg(test)]
+use std::{fs, path::Path};
+
+use crate::{excerpt, Config, Post};
+
+/// Set to rewrite golden files with the current contexts instead of comparing
+#[cfg(test)]
+pub const UPDATE_GOLDEN_ENV: &str = "SECUREBLOG_UPDATE_GOLDEN";
+
+/// Site-wide fields, the same on every page
+#[derive(Debug, Clone, Serialize)]
+pub struct SiteContext<'a> {
+    pub title: &'a str,
+    pub url: &'a str,
+    pub author: &'a str,
+}
+
+/// One post, in full on its own page and without `content` in lists
+#[derive(Debug, Clone, Serialize)]
+pub struct PostContext<'a> {
+    pub title: &'a str,
+    pub slug: &'a str,
+    pub date: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated: Option<String>,
+    pub tags: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub series: Option<&'a str>,
+    /// Frontmatter description, or the lead's text up to 160 characters
+    pub description: String,
+    /// Site-relative URL
+    pub url: String,
+    pub permalink: String,
+    /// Sanitized HTML of the body
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<&'a str>,
+    pub content_hash: &'a str,
+}
+
+/// Everything a template receives for one output page
+#[derive(Debug, Clone, Serialize)]
+pub struct PageContext<'a> {
+    /// Output path relative to the site root
+    pub page: String,
+    pub site: SiteContext<'a>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post: Option<PostContext<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub posts: Option<Vec<PostContext<'a>>>,
+}
+
+/// Site fields from the config
+pub fn site(config: &Config) -> SiteContext<'_> {
+    SiteContext { title: &config.title, url: &config.url, author: &config.author }
+}
+
+/// Context fields of a post; `full` includes the rendered body
+pub fn post<'a>(config: &Config, post: &'a Post, full: bool) -> PostContext<'a> {
+    PostContext {
+        title: &post.meta.title,
+        slug: &post.meta.slug,
+        date: post.meta.date.to_rfc3339(),
+        updated: post.meta.updated.map(|updated| updated.to_rfc3339()),
+        tags: &post.meta.tags,
+        series: post.meta.series.as_deref(),
+        description: excerpt::description(post),
+        url: post.url_path(),
+        permalink: post.permalink(&config.url),
+        content: full.then_some(post.html.as_str()),
+        content_hash: &post.hash,
+    }
+}
+
+/// `/`, `2024/05/slug/` and `/slug.html` style pages as output paths
+fn output_path(page: &str) -> String {
+    let page = page.trim_start_matches('/');
+    if page.is_empty() || page.ends_with('/') {
+        format!("{page}index.html")
+    } else {
+        page.to_string()
+    }
+}
+
+/// Context of one output page of the published `posts`
+pub fn for_page<'a>(config: &'a Config, posts: &'a [Post], page: &str) -> Result<PageContext<'a>> {
+    let path = output_path(page);
+    if let Some(found) = posts.iter().find(|p| p.path() == path) {
+        return Ok(PageContext { page: path, site: site(config), post: Some(post(config, found, true)), posts: None });
+    }
+    if path == "index.html" {
+        // A protected post's description comes from its body, which only its own URL may show
+        let list = posts.iter().filter(|p| !p.meta.protected).map(|p| post(config, p, false)).collect();
+        return Ok(PageContext { page: path, site: site(config), post: None, posts: Some(list) });
+    }
+    let mut pages: Vec<String> = posts.iter().map(Post::path).collect();
+    pages.insert(0, "index.html".to_string());
+    anyhow::bail!("No templated page {path} (available: {})", pages.join(", "))
+}
+
+/// Pretty JSON of a page's context, as printed by `render --dump-context`
+pub fn dump(config: &Config, posts: &[Post], page: &str) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&for_page(config, posts, page)?)?)
+}
+
+/// JSON pointers of the values that differ between `expected` and `actual`
+#[cfg(test)]
+fn differences(expected: &Value, actual: &Value, pointer: &str, found: &mut Vec<String>) {
+    match (expected, actual) {
+        (Value::Object(a), Value::Object(b)) => {
+            for key in a.keys().chain(b.keys().filter(|key| !a.contains_key(*key))) {
+                let child = format!("{pointer}/{}", key.replace('~', "~0").replace('/', "~1"));
+                match (a.get(key), b.get(key)) {
+                    (Some(a), Some(b)) => differences(a, b, &child, found),
+                    (Some(_), None) => found.push(format!("{child}: missing")),
+                    (None, _) => found.push(format!("{child}: unexpected")),
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) if a.len() == b.len() => {
+            for (i, (a, b)) in a.iter().zip(b).enumerate() {
+                differences(a, b, &format!("{pointer}/{i}"), found);
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            found.push(format!("{pointer}: {} items, expected {}", b.len(), a.len()));
+        }
+        _ if expected != actual => found.push(format!("{pointer}: {actual}, expected {expected}")),
+        _ => {}
+    }
+}
+
+/// Compare a context with the golden JSON file at `golden`
+///
+/// With `SECUREBLOG_UPDATE_GOLDEN` set, the file is (re)written instead.
+/// A failure lists every differing field by JSON pointer.
+#[cfg(test)]
+pub fn assert_golden<T: Serialize>(golden: &Path, context: &T) -> Result<()> {
+    let actual = serde_json::to_value(context)?;
+    if std::env::var_os(UPDATE_GOLDEN_ENV).is_some() {
+        if let Some(parent) = golden.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(golden, serde_json::to_string_pretty(&actual)? + "\n")
+            .with_context(|| format!("Failed to write {}", golden.display()))?;
+        return Ok(());
+    }
+    let content = fs::read_to_string(golden).with_context(|| {
+        format!("Failed to read golden file {} (set {UPDATE_GOLDEN_ENV}=1 to create it)", golden.display())
+    })?;
+    let expected: Value =
+        serde_json::from_str(&content).with_context(|| format!("Invalid golden file {}", golden.display()))?;
+    let mut found = Vec::new();
+    differences(&expected, &actual, "", &mut found);
+    if !found.is_empty() {
+        anyhow::bail!(
+            "Context differs from {} (set {UPDATE_GOLDEN_ENV}=1 to accept):\n  {}",
+            golden.display(),
+            found.join("\n  ")
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PostMeta;
+    use chrono::TimeZone;
+
+    fn config() -> Config {
+        serde_yaml::from_str("title: Blog\nurl: https://example.com\nauthor: Jane\n").unwrap()
+    }
+
+    fn posts() -> Vec<Post> {
+        vec![Post {
+            meta: PostMeta {
+                title: "Hardening nginx".to_string(),
+                slug: "hardening-nginx".to_string(),
+                date: chrono::Utc.with_ymd_and_hms(2024, 5, 9, 0, 0, 0).unwrap(),
+                tags: vec!["nginx".to_string()],
+                ..PostMeta::default()
+            },
+            html: "<p>Hi</p>".to_string(),
+            hash: "abc".to_string(),
+            route: "2024/05/hardening-nginx/".to_string(),
+            ..Post::default()
+        }]
+    }
+
+    #[test]
+    fn test_post_and_index_contexts() {
+        let (config, posts) = (config(), posts());
+        let page = serde_json::to_value(for_page(&config, &posts, "/2024/05/hardening-nginx/").unwrap()).unwrap();
+        assert_eq!(page["page"], "2024/05/hardening-nginx/index.html");
+        assert_eq!(page["site"]["author"], "Jane");
+        assert_eq!(page["post"]["content"], "<p>Hi</p>");
+        assert_eq!(page["post"]["description"], "Hi");
+        assert_eq!(page["post"]["permalink"], "https://example.com/2024/05/hardening-nginx/");
+        assert!(page.get("posts").is_none());
+
+        let index = serde_json::to_value(for_page(&config, &posts, "/").unwrap()).unwrap();
+        assert_eq!(index["posts"][0]["date"], "2024-05-09T00:00:00+00:00");
+        assert!(index["posts"][0].get("content").is_none());
+
+        let missing = for_page(&config, &posts, "about.html").unwrap_err().to_string();
+        assert!(missing.ends_with("(available: index.html, 2024/05/hardening-nginx/index.html)"));
+    }
+
+    #[test]
+    fn test_golden_lists_differing_fields() {
+        let (config, posts) = (config(), posts());
+        let context = for_page(&config, &posts, "index.html").unwrap();
+        let path = std::env::temp_dir().join(format!("secureblog-golden-{}.json", std::process::id()));
+        let mut golden = serde_json::to_value(&context).unwrap();
+        fs::write(&path, golden.to_string()).unwrap();
+        assert!(assert_golden(&path, &context).is_ok());
+
+        golden["site"]["title"] = "Old".into();
+        golden["posts"][0]["summary"] = "x".into();
+        fs::write(&path, golden.to_string()).unwrap();
+        let error = assert_golden(&path, &context).unwrap_err().to_string();
+        fs::remove_file(&path).unwrap();
+        assert!(error.contains("/site/title: \"Blog\", expected \"Old\""));
+        assert!(error.contains("/posts/0/summary: missing"));
+    }
+}
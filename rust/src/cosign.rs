@@ -0,0 +1,684 @@
+//! Offline verification of cosign / Sigstore bundles
+//!
+//! `verify --cosign-bundle` checks a bundle produced by `cosign sign-blob
+//! --bundle`, `cosign attest-blob` or GitHub artifact attestations against the
+//! Sigstore public-good roots compiled into the binary: the Fulcio certificate
+//! chain, the artifact (or DSSE envelope) signature, the Rekor signed entry
+//! timestamp and the logged entry itself. No network access is needed.
+
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use x509_cert::der::asn1::{ObjectIdentifier, Utf8StringRef};
+use x509_cert::der::{Decode, Encode};
+use x509_cert::ext::pkix::name::GeneralName;
+use x509_cert::ext::pkix::SubjectAltName;
+use x509_cert::spki::SubjectPublicKeyInfoOwned;
+use x509_cert::Certificate;
+
+use crate::rekor::{self, InclusionProof};
+
+/// Sigstore public-good Fulcio root (`O=sigstore.dev, CN=sigstore`, valid until 2031-10-05)
+const FULCIO_ROOT: &str = "-----BEGIN CERTIFICATE-----
+MIIB9zCCAXygAwIBAgIUALZNAPFdxHPwjeDloDwyYChAO/4wCgYIKoZIzj0EAwMw
+KjEVMBMGA1UEChMMc2lnc3RvcmUuZGV2MREwDwYDVQQDEwhzaWdzdG9yZTAeFw0y
+MTEwMDcxMzU2NTlaFw0zMTEwMDUxMzU2NThaMCoxFTATBgNVBAoTDHNpZ3N0b3Jl
+LmRldjERMA8GA1UEAxMIc2lnc3RvcmUwdjAQBgcqhkjOPQIBBgUrgQQAIgNiAAT7
+XeFT4rb3PQGwS4IajtLk3/OlnpgangaBclYpsYBr5i+4ynB07ceb3LP0OIOZdxex
+X69c5iVuyJRQ+Hz05yi+UF3uBWAlHpiS5sh0+H2GHE7SXrk1EC5m1Tr19L9gg92j
+YzBhMA4GA1UdDwEB/wQEAwIBBjAPBgNVHRMBAf8EBTADAQH/MB0GA1UdDgQWBBRY
+wB5fkUWlZql6zJChkyLQKsXF+jAfBgNVHSMEGDAWgBRYwB5fkUWlZql6zJChkyLQ
+KsXF+jAKBggqhkjOPQQDAwNpADBmAjEAj1nHeXZp+13NWBNa+EDsDP8G1WWg1tCM
+WP/WHPqpaVo0jhsweNFZgSs0eE7wYI4qAjEA2WB9ot98sIkoF3vZYdd3/VtWB5b9
+TNMea7Ix/stJ5TfcLLeABLE4BNJOsQ4vnBHJ
+-----END CERTIFICATE-----";
+
+/// Fulcio intermediate (`CN=sigstore-intermediate`) that issues signing certificates
+const FULCIO_INTERMEDIATE: &str = "-----BEGIN CERTIFICATE-----
+MIICGjCCAaGgAwIBAgIUALnViVfnU0brJasmRkHrn/UnfaQwCgYIKoZIzj0EAwMw
+KjEVMBMGA1UEChMMc2lnc3RvcmUuZGV2MREwDwYDVQQDEwhzaWdzdG9yZTAeFw0y
+MjA0MTMyMDA2MTVaFw0zMTEwMDUxMzU2NThaMDcxFTATBgNVBAoTDHNpZ3N0b3Jl
+LmRldjEeMBwGA1UEAxMVc2lnc3RvcmUtaW50ZXJtZWRpYXRlMHYwEAYHKoZIzj0C
+AQYFK4EEACIDYgAE8RVS/ysH+NOvuDZyPIZtilgUF9NlarYpAd9HP1vBBH1U5CV7
+7LSS7s0ZiH4nE7Hv7ptS6LvvR/STk798LVgMzLlJ4HeIfF3tHSaexLcYpSASr1kS
+0N/RgBJz/9jWCiXno3sweTAOBgNVHQ8BAf8EBAMCAQYwEwYDVR0lBAwwCgYIKwYB
+BQUHAwMwEgYDVR0TAQH/BAgwBgEB/wIBADAdBgNVHQ4EFgQU39Ppz1YkEZb5qNjp
+KFWixi4YZD8wHwYDVR0jBBgwFoAUWMAeX5FFpWapesyQoZMi0CrFxfowCgYIKoZI
+zj0EAwMDZwAwZAIwPCsQK4DYiZYDPIaDi5HFKnfxXx6ASSVmERfsynYBiX2X6SJR
+nZU84/9DZdnFvvxmAjBOt6QpBlc4J/0DxvkTCqpclvziL6BCCPnjdlIB3Pu3BxsP
+mygUY7Ii2zbdCdliiow=
+-----END CERTIFICATE-----";
+
+/// Sigstore public-good Rekor log key; its SHA-256 is the log ID
+pub const REKOR_KEY: &str = "-----BEGIN PUBLIC KEY-----
+MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAE2G2Y+2tabdTV5BcGiBIx0a9fAFwr
+kBbmLSGtks4L3qX6yYY0zufBnhC8Ur/iy55GhWP/9A/bY2LhC30M9+RYtw==
+-----END PUBLIC KEY-----";
+
+const EC_PUBLIC_KEY: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.2.1");
+const ECDSA_SHA256: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.2");
+const ECDSA_SHA384: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.3");
+const SUBJECT_ALT_NAME: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.5.29.17");
+/// Fulcio OIDC issuer, raw bytes (deprecated) and DER `UTF8String`
+const FULCIO_ISSUER_V1: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.4.1.57264.1.1");
+const FULCIO_ISSUER_V2: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.4.1.57264.1.8");
+
+/// Decode the first PEM block of `pem` to DER
+pub fn pem_to_der(pem: &str) -> Result<Vec<u8>> {
+    let body: String = pem
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| !line.starts_with("-----BEGIN"))
+        .skip(1)
+        .take_while(|line| !line.starts_with("-----END"))
+        .collect();
+    if body.is_empty() {
+        anyhow::bail!("No PEM block found");
+    }
+    STANDARD.decode(body).context("Invalid base64 in PEM block")
+}
+
+/// ECDSA public key on one of the curves Sigstore uses
+enum EcKey {
+    P256(p256::ecdsa::VerifyingKey),
+    P384(p384::ecdsa::VerifyingKey),
+}
+
+impl EcKey {
+    fn from_spki(spki: &SubjectPublicKeyInfoOwned) -> Result<Self> {
+        if spki.algorithm.oid != EC_PUBLIC_KEY {
+            anyhow::bail!("Unsupported key algorithm {} (only ECDSA keys are supported)", spki.algorithm.oid);
+        }
+        // Uncompressed SEC1 points: 0x04 || X || Y
+        let point = spki.subject_public_key.raw_bytes();
+        match point.len() {
+            65 => Ok(Self::P256(p256::ecdsa::VerifyingKey::from_sec1_bytes(point).context("Invalid P-256 key")?)),
+            97 => Ok(Self::P384(p384::ecdsa::VerifyingKey::from_sec1_bytes(point).context("Invalid P-384 key")?)),
+            _ => anyhow::bail!("Unsupported elliptic curve"),
+        }
+    }
+
+    /// Verify a DER signature over `message` (SHA-256 for P-256, SHA-384 for P-384)
+    fn verify(&self, message: &[u8], signature: &[u8]) -> Result<()> {
+        use p256::ecdsa::signature::Verifier;
+        match self {
+            Self::P256(key) => key.verify(message, &p256::ecdsa::Signature::from_der(signature)?),
+            Self::P384(key) => key.verify(message, &p384::ecdsa::Signature::from_der(signature)?),
+        }
+        .map_err(|_| anyhow::anyhow!("signature does not verify"))
+    }
+
+    /// Hash used with this curve, as named in an X.509 signature algorithm
+    const fn signature_algorithm(&self) -> ObjectIdentifier {
+        match self {
+            Self::P256(_) => ECDSA_SHA256,
+            Self::P384(_) => ECDSA_SHA384,
+        }
+    }
+}
+
+/// Check that `issuer` signed `cert`
+fn verify_issued_by(cert: &Certificate, issuer: &Certificate) -> Result<()> {
+    let key = EcKey::from_spki(&issuer.tbs_certificate.subject_public_key_info)?;
+    if cert.signature_algorithm.oid != key.signature_algorithm() {
+        anyhow::bail!("Unexpected certificate signature algorithm {}", cert.signature_algorithm.oid);
+    }
+    key.verify(&cert.tbs_certificate.to_der()?, cert.signature.raw_bytes())
+}
+
+/// Embedded Fulcio root and intermediate, with the intermediate's signature checked
+fn fulcio_chain() -> Result<(Certificate, Certificate)> {
+    let root = Certificate::from_der(&pem_to_der(FULCIO_ROOT)?)?;
+    let intermediate = Certificate::from_der(&pem_to_der(FULCIO_INTERMEDIATE)?)?;
+    verify_issued_by(&intermediate, &root).context("Embedded Fulcio intermediate")?;
+    Ok((root, intermediate))
+}
+
+/// Check a signature by the embedded Rekor key (signed entry timestamps, checkpoints)
+pub fn verify_rekor_signature(message: &[u8], signature: &[u8]) -> Result<()> {
+    verify_by_key(&pem_to_der(REKOR_KEY)?, message, signature)
+}
+
+/// Check a signature by the ECDSA key whose DER `SubjectPublicKeyInfo` is `key`
+fn verify_by_key(key: &[u8], message: &[u8], signature: &[u8]) -> Result<()> {
+    EcKey::from_spki(&SubjectPublicKeyInfoOwned::from_der(key)?)?.verify(message, signature)
+}
+
+/// Log ID of the embedded Rekor key (hex SHA-256 of its DER encoding)
+pub fn rekor_log_id() -> Result<String> {
+    Ok(format!("{:x}", Sha256::digest(pem_to_der(REKOR_KEY)?)))
+}
+
+/// Certificate authority and transparency log a bundle must chain to
+struct TrustRoots {
+    /// Issuer of signing certificates
+    intermediate: Certificate,
+    /// Rekor log key (DER `SubjectPublicKeyInfo`)
+    rekor_key: Vec<u8>,
+}
+
+impl TrustRoots {
+    /// The Sigstore public-good roots embedded above
+    fn public_good() -> Result<Self> {
+        let (_root, intermediate) = fulcio_chain()?;
+        Ok(Self { intermediate, rekor_key: pem_to_der(REKOR_KEY)? })
+    }
+
+    fn log_id(&self) -> String {
+        format!("{:x}", Sha256::digest(&self.rekor_key))
+    }
+}
+
+/// `integratedTime` / `logIndex` are strings in Sigstore bundles and numbers in cosign's
+fn int_or_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum IntOrString {
+        Int(i64),
+        String(String),
+    }
+    match IntOrString::deserialize(deserializer)? {
+        IntOrString::Int(n) => Ok(n),
+        IntOrString::String(s) => s.parse().map_err(serde::de::Error::custom),
+    }
+}
+
+/// `application/vnd.dev.sigstore.bundle` (v0.1 - v0.3)
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SigstoreBundle {
+    verification_material: VerificationMaterial,
+    message_signature: Option<MessageSignature>,
+    dsse_envelope: Option<DsseEnvelope>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VerificationMaterial {
+    certificate: Option<RawBytes>,
+    x509_certificate_chain: Option<CertificateChain>,
+    #[serde(default)]
+    tlog_entries: Vec<TlogEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawBytes {
+    raw_bytes: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CertificateChain {
+    certificates: Vec<RawBytes>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TlogEntry {
+    #[serde(deserialize_with = "int_or_string")]
+    log_index: i64,
+    log_id: LogId,
+    #[serde(deserialize_with = "int_or_string")]
+    integrated_time: i64,
+    inclusion_promise: Option<InclusionPromise>,
+    inclusion_proof: Option<BundleInclusionProof>,
+    canonicalized_body: String,
+}
+
+/// Inclusion proof with base64 hashes, as bundles store it
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BundleInclusionProof {
+    #[serde(deserialize_with = "int_or_string")]
+    log_index: i64,
+    #[serde(deserialize_with = "int_or_string")]
+    tree_size: i64,
+    root_hash: String,
+    #[serde(default)]
+    hashes: Vec<String>,
+    checkpoint: Checkpoint,
+}
+
+#[derive(Debug, Deserialize)]
+struct Checkpoint {
+    envelope: String,
+}
+
+impl TryFrom<BundleInclusionProof> for InclusionProof {
+    type Error = anyhow::Error;
+
+    fn try_from(proof: BundleInclusionProof) -> Result<Self> {
+        Ok(Self {
+            log_index: u64::try_from(proof.log_index)?,
+            tree_size: u64::try_from(proof.tree_size)?,
+            root_hash: STANDARD.decode(&proof.root_hash)?,
+            hashes: proof.hashes.iter().map(|h| STANDARD.decode(h)).collect::<Result<_, _>>()?,
+            checkpoint: proof.checkpoint.envelope,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LogId {
+    key_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InclusionPromise {
+    signed_entry_timestamp: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MessageSignature {
+    signature: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DsseEnvelope {
+    payload: String,
+    payload_type: String,
+    signatures: Vec<DsseSignature>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DsseSignature {
+    sig: String,
+}
+
+/// `cosign sign-blob --bundle` output
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LegacyBundle {
+    base64_signature: String,
+    cert: String,
+    rekor_bundle: LegacyRekorBundle,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LegacyRekorBundle {
+    signed_entry_timestamp: String,
+    payload: LegacyPayload,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LegacyPayload {
+    body: String,
+    #[serde(deserialize_with = "int_or_string")]
+    integrated_time: i64,
+    #[serde(deserialize_with = "int_or_string")]
+    log_index: i64,
+    #[serde(rename = "logID")]
+    log_id: String,
+}
+
+/// What was signed
+enum Signed {
+    /// Signature over the artifact bytes
+    Blob { signature: Vec<u8> },
+    /// DSSE envelope whose in-toto statement names the artifact
+    Dsse { payload_type: String, payload: Vec<u8>, signature: Vec<u8> },
+}
+
+/// Rekor entry with its signed entry timestamp and, in newer bundles, an inclusion proof
+struct LogEntry {
+    body: String,
+    integrated_time: i64,
+    log_index: i64,
+    log_id: String,
+    set: Vec<u8>,
+    proof: Option<InclusionProof>,
+}
+
+/// Bundle in a format-independent shape
+struct Parsed {
+    certificate: Vec<u8>,
+    signed: Signed,
+    entry: LogEntry,
+}
+
+fn parse(json: &str) -> Result<Parsed> {
+    let value: serde_json::Value = serde_json::from_str(json).context("Bundle is not JSON")?;
+    if value.get("rekorBundle").is_some() {
+        let bundle: LegacyBundle = serde_json::from_value(value).context("Invalid cosign bundle")?;
+        let cert_pem = String::from_utf8(STANDARD.decode(&bundle.cert)?).context("Certificate is not PEM")?;
+        let payload = bundle.rekor_bundle.payload;
+        return Ok(Parsed {
+            certificate: pem_to_der(&cert_pem)?,
+            signed: Signed::Blob { signature: STANDARD.decode(&bundle.base64_signature)? },
+            entry: LogEntry {
+                body: payload.body,
+                integrated_time: payload.integrated_time,
+                log_index: payload.log_index,
+                log_id: payload.log_id,
+                set: STANDARD.decode(&bundle.rekor_bundle.signed_entry_timestamp)?,
+                proof: None,
+            },
+        });
+    }
+
+    let bundle: SigstoreBundle = serde_json::from_value(value).context("Invalid Sigstore bundle")?;
+    let material = bundle.verification_material;
+    let certificate = material
+        .certificate
+        .or_else(|| material.x509_certificate_chain.and_then(|chain| chain.certificates.into_iter().next()))
+        .context("Bundle has no signing certificate (key-based bundles are not supported)")?;
+    let entry = material.tlog_entries.into_iter().next().context("Bundle has no transparency log entry")?;
+    let promise = entry
+        .inclusion_promise
+        .context("Bundle has no signed entry timestamp to verify offline")?;
+
+    let signed = match (bundle.message_signature, bundle.dsse_envelope) {
+        (Some(message), _) => Signed::Blob { signature: STANDARD.decode(&message.signature)? },
+        (None, Some(envelope)) => Signed::Dsse {
+            payload: STANDARD.decode(&envelope.payload)?,
+            payload_type: envelope.payload_type,
+            signature: STANDARD
+                .decode(&envelope.signatures.first().context("DSSE envelope has no signature")?.sig)?,
+        },
+        (None, None) => anyhow::bail!("Bundle has neither a message signature nor a DSSE envelope"),
+    };
+
+    Ok(Parsed {
+        certificate: STANDARD.decode(&certificate.raw_bytes)?,
+        signed,
+        entry: LogEntry {
+            body: entry.canonicalized_body,
+            integrated_time: entry.integrated_time,
+            log_index: entry.log_index,
+            log_id: hex(&STANDARD.decode(&entry.log_id.key_id)?),
+            set: STANDARD.decode(&promise.signed_entry_timestamp)?,
+            proof: entry.inclusion_proof.map(InclusionProof::try_from).transpose()?,
+        },
+    })
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// DSSE pre-authentication encoding, the bytes a DSSE signature covers
+pub fn pae(payload_type: &str, payload: &[u8]) -> Vec<u8> {
+    let mut out = format!("DSSEv1 {} {payload_type} {} ", payload_type.len(), payload.len()).into_bytes();
+    out.extend_from_slice(payload);
+    out
+}
+
+/// Canonical JSON the Rekor signed entry timestamp is computed over
+pub fn set_payload(body: &str, integrated_time: i64, log_id: &str, log_index: i64) -> String {
+    // serde_json maps are sorted, which is the canonical key order
+    serde_json::json!({
+        "body": body,
+        "integratedTime": integrated_time,
+        "logID": log_id,
+        "logIndex": log_index,
+    })
+    .to_string()
+}
+
+/// Signer identity from the Fulcio certificate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    /// Email or URI subject alternative names (e.g. a workflow URL)
+    pub names: Vec<String>,
+    /// OIDC issuer that authenticated the signer
+    pub issuer: Option<String>,
+}
+
+impl fmt::Display for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.names.join(", "))?;
+        if let Some(issuer) = &self.issuer {
+            write!(f, " (issuer {issuer})")?;
+        }
+        Ok(())
+    }
+}
+
+fn identity(cert: &Certificate) -> Result<Identity> {
+    let mut identity = Identity { names: Vec::new(), issuer: None };
+    for extension in cert.tbs_certificate.extensions.iter().flatten() {
+        let value = extension.extn_value.as_bytes();
+        if extension.extn_id == SUBJECT_ALT_NAME {
+            for name in SubjectAltName::from_der(value)?.0 {
+                match name {
+                    GeneralName::Rfc822Name(email) => identity.names.push(email.to_string()),
+                    GeneralName::UniformResourceIdentifier(uri) => identity.names.push(uri.to_string()),
+                    _ => {}
+                }
+            }
+        } else if extension.extn_id == FULCIO_ISSUER_V2 {
+            identity.issuer = Some(Utf8StringRef::from_der(value)?.as_str().to_string());
+        } else if extension.extn_id == FULCIO_ISSUER_V1 && identity.issuer.is_none() {
+            identity.issuer = Some(String::from_utf8_lossy(value).into_owned());
+        }
+    }
+    if identity.names.is_empty() {
+        anyhow::bail!("Signing certificate has no email or URI identity");
+    }
+    Ok(identity)
+}
+
+/// Logged entry must describe this signature and artifact
+fn check_body(entry: &LogEntry, signed: &Signed, artifact_sha256: &str) -> Result<()> {
+    let body: serde_json::Value = serde_json::from_slice(&STANDARD.decode(&entry.body)?)
+        .context("Transparency log entry body is not JSON")?;
+    let spec = &body["spec"];
+    let signature_listed = |listed: &serde_json::Value, signature: &[u8]| {
+        listed.as_str().and_then(|s| STANDARD.decode(s).ok()).is_some_and(|s| s == signature)
+    };
+
+    match (body["kind"].as_str(), signed) {
+        (Some("hashedrekord"), Signed::Blob { signature }) => {
+            if spec["data"]["hash"]["value"].as_str() != Some(artifact_sha256) {
+                anyhow::bail!("Transparency log entry is for a different artifact");
+            }
+            if !signature_listed(&spec["signature"]["content"], signature) {
+                anyhow::bail!("Transparency log entry has a different signature");
+            }
+        }
+        (Some("dsse"), Signed::Dsse { payload, signature, .. }) => {
+            if spec["payloadHash"]["value"].as_str() != Some(&format!("{:x}", Sha256::digest(payload))) {
+                anyhow::bail!("Transparency log entry is for a different attestation");
+            }
+            let signatures = spec["signatures"].as_array().map(Vec::as_slice).unwrap_or_default();
+            if !signatures.iter().any(|s| signature_listed(&s["signature"], signature)) {
+                anyhow::bail!("Transparency log entry has a different signature");
+            }
+        }
+        (kind, _) => anyhow::bail!("Unsupported transparency log entry kind: {}", kind.unwrap_or("none")),
+    }
+    Ok(())
+}
+
+/// in-toto statement subject digests must include the artifact
+fn check_statement(payload: &[u8], artifact_sha256: &str) -> Result<()> {
+    let statement: serde_json::Value = serde_json::from_slice(payload).context("Attestation payload is not JSON")?;
+    let subjects = statement["subject"].as_array().map(Vec::as_slice).unwrap_or_default();
+    if !subjects.iter().any(|s| s["digest"]["sha256"].as_str() == Some(artifact_sha256)) {
+        anyhow::bail!("Attestation does not name this artifact as a subject");
+    }
+    Ok(())
+}
+
+/// Successful verification
+#[derive(Debug, Clone)]
+pub struct Verified {
+    /// Who signed, per the Fulcio certificate
+    pub identity: Identity,
+    /// Rekor log index
+    pub log_index: i64,
+    /// When Rekor logged the signature
+    pub integrated_time: DateTime<Utc>,
+}
+
+/// Signer a bundle must come from
+#[derive(Debug, Clone, Copy)]
+pub struct Expected<'a> {
+    /// Email or URI subject alternative name of the certificate
+    pub identity: &'a str,
+    /// OIDC issuer recorded in the certificate (e.g. `https://token.actions.githubusercontent.com`)
+    pub issuer: &'a str,
+}
+
+/// Verify `bundle` (JSON) for `artifact`, signed by the `expected` identity
+///
+/// Fulcio issues certificates to anyone with an OIDC account, so a bundle
+/// that verifies proves nothing until its identity and issuer are checked:
+/// both are required.
+pub fn verify(bundle: &str, artifact: &[u8], expected: Expected<'_>) -> Result<Verified> {
+    verify_with(&TrustRoots::public_good()?, bundle, artifact, expected)
+}
+
+fn verify_with(roots: &TrustRoots, bundle: &str, artifact: &[u8], expected: Expected<'_>) -> Result<Verified> {
+    let parsed = parse(bundle)?;
+    let artifact_sha256 = format!("{:x}", Sha256::digest(artifact));
+
+    // Certificate chains to the Fulcio roots
+    let leaf = Certificate::from_der(&parsed.certificate).context("Invalid signing certificate")?;
+    verify_issued_by(&leaf, &roots.intermediate).context("Signing certificate was not issued by Sigstore's Fulcio")?;
+
+    // Rekor vouches for the entry and the time it was logged
+    if parsed.entry.log_id != roots.log_id() {
+        anyhow::bail!("Entry is from an unknown transparency log ({})", parsed.entry.log_id);
+    }
+    let entry = &parsed.entry;
+    let payload = set_payload(&entry.body, entry.integrated_time, &entry.log_id, entry.log_index);
+    verify_by_key(&roots.rekor_key, payload.as_bytes(), &entry.set).context("Invalid signed entry timestamp")?;
+    if let Some(proof) = &entry.proof {
+        rekor::verify_inclusion(&STANDARD.decode(&entry.body)?, proof).context("Invalid inclusion proof")?;
+    }
+
+    // Short-lived certificate was valid when the signature was logged
+    let validity = &leaf.tbs_certificate.validity;
+    let logged = u64::try_from(entry.integrated_time).context("Invalid integrated time")?;
+    if logged < validity.not_before.to_unix_duration().as_secs() || logged > validity.not_after.to_unix_duration().as_secs() {
+        anyhow::bail!("Signing certificate was not valid when the signature was logged");
+    }
+
+    // Signature by the certificate key over the artifact or the attestation
+    let key = EcKey::from_spki(&leaf.tbs_certificate.subject_public_key_info)?;
+    match &parsed.signed {
+        Signed::Blob { signature } => key.verify(artifact, signature).context("Artifact signature")?,
+        Signed::Dsse { payload_type, payload, signature } => {
+            key.verify(&pae(payload_type, payload), signature).context("Attestation signature")?;
+            check_statement(payload, &artifact_sha256)?;
+        }
+    }
+    check_body(entry, &parsed.signed, &artifact_sha256)?;
+
+    let identity = identity(&leaf)?;
+    if !identity.names.iter().any(|name| name == expected.identity) {
+        anyhow::bail!("Signed by {identity}, not {}", expected.identity);
+    }
+    if identity.issuer.as_deref() != Some(expected.issuer) {
+        anyhow::bail!("Signed by {identity}, not via issuer {}", expected.issuer);
+    }
+
+    Ok(Verified {
+        identity,
+        log_index: entry.log_index,
+        integrated_time: DateTime::from_timestamp(entry.integrated_time, 0).context("Invalid integrated time")?,
+    })
+}
+
+/// Read and verify a bundle file for an artifact file
+pub fn verify_files(bundle: &Path, artifact: &Path, expected: Expected<'_>) -> Result<Verified> {
+    let json = fs::read_to_string(bundle).with_context(|| format!("Failed to read {}", bundle.display()))?;
+    let content = fs::read(artifact).with_context(|| format!("Failed to read {}", artifact.display()))?;
+    verify(&json, &content, expected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_roots_chain() {
+        let (root, intermediate) = fulcio_chain().unwrap();
+        verify_issued_by(&root, &root).unwrap();
+        assert!(verify_issued_by(&root, &intermediate).is_err());
+    }
+
+    #[test]
+    fn test_rekor_log_id() {
+        assert_eq!(rekor_log_id().unwrap(), "c0d23d6ad406973f9559f3ba2d1ca01f84147d8ffc5b8445c224f98b9591801d");
+    }
+
+    #[test]
+    fn test_pae() {
+        assert_eq!(pae("application/vnd.in-toto+json", b"{}"), b"DSSEv1 28 application/vnd.in-toto+json 2 {}");
+    }
+
+    #[test]
+    fn test_set_payload_is_canonical() {
+        assert_eq!(
+            set_payload("e30=", 1_700_000_000, "c0d2", 42),
+            r#"{"body":"e30=","integratedTime":1700000000,"logID":"c0d2","logIndex":42}"#
+        );
+    }
+
+    #[test]
+    fn test_statement_subject() {
+        let statement = br#"{"subject":[{"name":"dist.tar.gz","digest":{"sha256":"abc"}}]}"#;
+        assert!(check_statement(statement, "abc").is_ok());
+        assert!(check_statement(statement, "def").is_err());
+    }
+
+    #[test]
+    fn test_rejects_bundle_without_certificate() {
+        let bundle = r#"{"mediaType":"application/vnd.dev.sigstore.bundle.v0.3+json","verificationMaterial":{"publicKey":{"hint":"x"},"tlogEntries":[]},"messageSignature":{"signature":"AA=="}}"#;
+        let err = verify(bundle, b"artifact", GITHUB).unwrap_err();
+        assert!(err.to_string().contains("no signing certificate"));
+    }
+
+    /// Bundle signed through a test CA and log key standing in for Fulcio and Rekor
+    /// (openssl P-256 keys; leaf for me@example.com via GitHub's OAuth issuer)
+    fn fixture_roots() -> TrustRoots {
+        TrustRoots {
+            intermediate: Certificate::from_der(
+                &pem_to_der(include_str!("../tests/fixtures/cosign/intermediate.pem")).unwrap(),
+            )
+            .unwrap(),
+            rekor_key: pem_to_der(include_str!("../tests/fixtures/cosign/rekor.pem")).unwrap(),
+        }
+    }
+
+    const BUNDLE: &str = include_str!("../tests/fixtures/cosign/bundle.json");
+    const ARTIFACT: &[u8] = include_bytes!("../tests/fixtures/cosign/integrity.json");
+    const GITHUB: Expected<'static> = Expected { identity: "me@example.com", issuer: "https://github.com/login/oauth" };
+
+    #[test]
+    fn test_verifies_bundle_end_to_end() {
+        let verified = verify_with(&fixture_roots(), BUNDLE, ARTIFACT, GITHUB).unwrap();
+        assert_eq!(verified.identity.names, ["me@example.com"]);
+        assert_eq!(verified.identity.issuer.as_deref(), Some("https://github.com/login/oauth"));
+        assert_eq!(verified.log_index, 7);
+        assert_eq!(verified.integrated_time.to_rfc3339(), "2024-06-01T00:00:00+00:00");
+
+        // Only the public-good roots are trusted outside tests
+        assert!(verify(BUNDLE, ARTIFACT, GITHUB).is_err());
+        assert!(verify_with(&fixture_roots(), BUNDLE, b"tampered", GITHUB).is_err());
+    }
+
+    #[test]
+    fn test_rejects_other_identity_or_issuer() {
+        let err = verify_with(&fixture_roots(), BUNDLE, ARTIFACT, Expected { identity: "you@example.com", ..GITHUB })
+            .unwrap_err();
+        assert!(err.to_string().contains("not you@example.com"));
+        let google = Expected { issuer: "https://accounts.google.com", ..GITHUB };
+        let err = verify_with(&fixture_roots(), BUNDLE, ARTIFACT, google).unwrap_err();
+        assert!(err.to_string().contains("not via issuer https://accounts.google.com"));
+    }
+}
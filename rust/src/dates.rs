@@ -0,0 +1,124 @@
+//! Post creation and modification dates, optionally derived from git history
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::path::Path;
+use tracing::{debug, warn};
+
+use crate::{git, inject, Config, Post, PostMeta};
+
+/// First and last commit times of a file
+pub type CommitSpan = (DateTime<Utc>, DateTime<Utc>);
+
+/// Whether the frontmatter left `date` unset
+pub fn date_missing(meta: &PostMeta) -> bool {
+    meta.date == DateTime::<Utc>::UNIX_EPOCH
+}
+
+/// Fill `date` and `updated` from git where the frontmatter omits them
+pub fn fill_dates(meta: &mut PostMeta, span: CommitSpan) {
+    let (created, modified) = span;
+    if date_missing(meta) {
+        meta.date = created;
+    }
+    if meta.updated.is_none() && modified > meta.date {
+        meta.updated = Some(modified);
+    }
+}
+
+/// Fail on posts without a frontmatter `date` when git history is not consulted
+pub fn require_dates(posts: &[Post]) -> Result<()> {
+    let missing: Vec<String> =
+        posts.iter().filter(|p| date_missing(&p.meta)).map(|p| p.source.display().to_string()).collect();
+    if !missing.is_empty() {
+        anyhow::bail!("No date in frontmatter (set `date:` or enable git_dates): {}", missing.join(", "));
+    }
+    Ok(())
+}
+
+/// Populate missing post dates from the first and last commits touching each source file
+pub fn apply_git_dates(posts: &mut [Post]) -> Result<()> {
+    let sources: Vec<&Path> = posts.iter().map(|p| p.source.as_path()).collect();
+    let history = git::history_of(&sources).context("Failed to read git history for post dates")?;
+
+    for post in posts.iter_mut() {
+        match history.get(&post.source) {
+            Some(file) => fill_dates(&mut post.meta, (file.first.time, file.last.time)),
+            None if date_missing(&post.meta) => {
+                warn!("No date in frontmatter or git history: {}", post.source.display());
+            }
+            None => debug!("Not tracked by git: {}", post.source.display()),
+        }
+    }
+
+    Ok(())
+}
+
+/// `<meta property="article:modified_time">` for updated posts
+pub fn modified_meta(post: &Post) -> Option<String> {
+    post.meta.updated.map(|updated| {
+        format!(
+            "<meta property=\"article:modified_time\" content=\"{}\">\n",
+            updated.to_rfc3339()
+        )
+    })
+}
+
+/// Add modification time metadata to updated post pages
+pub fn apply(config: &Config, posts: &[Post]) -> Result<()> {
+    for post in posts {
+        if let Some(meta) = modified_meta(post) {
+            inject::inject_into_file(&config.output.join(post.path()), &["</head>"], &meta)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, day, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_require_dates_rejects_missing_date() {
+        let mut post = Post::default();
+        assert!(require_dates(std::slice::from_ref(&post)).is_err());
+        post.meta.date = at(1);
+        assert!(require_dates(&[post]).is_ok());
+    }
+
+    #[test]
+    fn test_fill_dates_only_replaces_missing() {
+        let mut meta = PostMeta::default();
+        fill_dates(&mut meta, (at(1), at(5)));
+        assert_eq!(meta.date, at(1));
+        assert_eq!(meta.updated, Some(at(5)));
+
+        let mut meta = PostMeta { date: at(3), updated: Some(at(4)), ..PostMeta::default() };
+        fill_dates(&mut meta, (at(1), at(9)));
+        assert_eq!(meta.date, at(3));
+        assert_eq!(meta.updated, Some(at(4)));
+    }
+
+    #[test]
+    fn test_single_commit_has_no_update() {
+        let mut meta = PostMeta::default();
+        fill_dates(&mut meta, (at(2), at(2)));
+        assert_eq!(meta.updated, None);
+    }
+
+    #[test]
+    fn test_modified_meta() {
+        let mut post = Post::default();
+        assert!(modified_meta(&post).is_none());
+        post.meta.updated = Some(at(7));
+        assert_eq!(
+            modified_meta(&post).unwrap(),
+            "<meta property=\"article:modified_time\" content=\"2024-01-07T12:00:00+00:00\">\n"
+        );
+    }
+}
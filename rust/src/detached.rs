@@ -0,0 +1,97 @@
+//! Detached SSH signatures (`<file>.sig`) for feeds, the sitemap and other published files
+//!
+//! Signatures use the `ssh-keygen -Y sign -n file` format, so mirrors can check
+//! them with `ssh-keygen -Y verify` and an `allowed_signers` file.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use ssh_key::{HashAlg, LineEnding, PrivateKey};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Namespace `ssh-keygen` uses for file signatures
+pub const FILE_NAMESPACE: &str = "file";
+
+/// Detached signature settings
+#[derive(Debug, Clone, Deserialize)]
+pub struct SignFilesConfig {
+    /// Unencrypted OpenSSH private key (e.g. from a CI secret)
+    pub key: PathBuf,
+    /// Output files to sign, relative to the output directory
+    #[serde(default = "default_files")]
+    pub files: Vec<String>,
+}
+
+fn default_files() -> Vec<String> {
+    vec!["atom.xml".to_string(), "sitemap.xml".to_string()]
+}
+
+/// Path of the detached signature for `file`
+pub fn signature_path(file: &Path) -> PathBuf {
+    let mut name = file.as_os_str().to_os_string();
+    name.push(".sig");
+    PathBuf::from(name)
+}
+
+/// Load the signing key, refusing passphrase-protected keys
+pub fn load_key(path: &Path) -> Result<PrivateKey> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read signing key: {}", path.display()))?;
+    let key = PrivateKey::from_openssh(content)
+        .with_context(|| format!("Invalid OpenSSH private key: {}", path.display()))?;
+    if key.is_encrypted() {
+        anyhow::bail!("Signing key {} is passphrase-protected", path.display());
+    }
+    Ok(key)
+}
+
+/// `SSHSIG` armored signature of `content`
+pub fn sign(key: &PrivateKey, content: &[u8]) -> Result<String> {
+    let signature = key
+        .sign(FILE_NAMESPACE, HashAlg::Sha512, content)
+        .context("Failed to sign")?;
+    Ok(signature.to_pem(LineEnding::LF)?)
+}
+
+/// Write `<file>.sig` next to each configured output file
+///
+/// Files the build did not produce are skipped with a warning.
+pub fn apply(output_dir: &Path, config: &SignFilesConfig) -> Result<usize> {
+    let key = load_key(&config.key)?;
+    let mut signed = 0;
+
+    for file in &config.files {
+        let path = output_dir.join(file);
+        if !path.is_file() {
+            warn!("Not signing {}: not in the output", file);
+            continue;
+        }
+        let content = fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let signature_path = signature_path(&path);
+        fs::write(&signature_path, sign(&key, &content)?)
+            .with_context(|| format!("Failed to write {}", signature_path.display()))?;
+        signed += 1;
+    }
+
+    info!("🔏 Signed {} files ({})", signed, key.public_key().to_openssh()?);
+    Ok(signed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_path_appends_suffix() {
+        assert_eq!(signature_path(Path::new("dist/atom.xml")), PathBuf::from("dist/atom.xml.sig"));
+        assert_eq!(signature_path(Path::new("dist/tags/xss/atom.xml")), PathBuf::from("dist/tags/xss/atom.xml.sig"));
+    }
+
+    #[test]
+    fn test_config_defaults_to_feed_and_sitemap() {
+        let config: SignFilesConfig = serde_yaml::from_str("key: keys/site").unwrap();
+        assert_eq!(config.files, ["atom.xml", "sitemap.xml"]);
+        assert!(load_key(Path::new("missing-key")).is_err());
+    }
+}
@@ -0,0 +1,98 @@
+//! Reproducibility audit: two builds of the same sources must be identical
+//!
+//! `check determinism` builds the site twice in-process, each into its own
+//! scratch directory, and compares the outputs byte for byte. Anything that
+//! leaks the build time, hash map order or the order parallel rendering
+//! finished in shows up as a file that differs, with the line of the first
+//! difference, before it breaks checksums and signatures on mirrors.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+use crate::normalize;
+
+/// A file the two builds disagree on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    /// Path relative to the output directory, with `/` separators
+    pub path: String,
+    /// 1-based line of the first differing byte, when both files have it
+    pub line: Option<usize>,
+    /// What differs
+    pub message: String,
+}
+
+/// Relative paths of every file under `dir`, sorted
+fn files(dir: &Path) -> Result<BTreeSet<String>> {
+    let mut files = BTreeSet::new();
+    for entry in WalkDir::new(dir) {
+        let entry = entry.with_context(|| format!("Failed to read {}", dir.display()))?;
+        if entry.file_type().is_file() {
+            files.insert(normalize::relative(entry.path(), dir)?);
+        }
+    }
+    Ok(files)
+}
+
+/// Byte offset of the first difference, if the contents differ
+pub fn first_difference(a: &[u8], b: &[u8]) -> Option<usize> {
+    match a.iter().zip(b).position(|(x, y)| x != y) {
+        Some(offset) => Some(offset),
+        None if a.len() != b.len() => Some(a.len().min(b.len())),
+        None => None,
+    }
+}
+
+/// Files that exist in only one output or differ between them
+pub fn compare(first: &Path, second: &Path) -> Result<Vec<Difference>> {
+    let (a, b) = (files(first)?, files(second)?);
+    let mut differences = Vec::new();
+    for path in a.union(&b) {
+        let difference = |line, message: &str| Difference { path: path.clone(), line, message: message.to_string() };
+        match (a.contains(path), b.contains(path)) {
+            (true, false) => differences.push(difference(None, "written by the first build only")),
+            (false, true) => differences.push(difference(None, "written by the second build only")),
+            _ => {
+                let x = fs::read(first.join(path)).with_context(|| format!("Failed to read {path}"))?;
+                let y = fs::read(second.join(path)).with_context(|| format!("Failed to read {path}"))?;
+                if let Some(offset) = first_difference(&x, &y) {
+                    let line = x[..offset].iter().filter(|&&byte| byte == b'\n').count() + 1;
+                    differences.push(difference(Some(line), &format!("differs between builds from byte {offset}")));
+                }
+            }
+        }
+    }
+    Ok(differences)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_reports_each_kind_of_difference() {
+        let root = std::env::temp_dir().join(format!("secureblog-determinism-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let (a, b) = (root.join("a"), root.join("b"));
+        for dir in [&a, &b] {
+            fs::create_dir_all(dir.join("tags")).unwrap();
+            fs::write(dir.join("index.html"), "<p>same</p>\n").unwrap();
+        }
+        fs::write(a.join("tags/rust.html"), "<ul>\n<li>a</li>\n<li>b</li>\n").unwrap();
+        fs::write(b.join("tags/rust.html"), "<ul>\n<li>b</li>\n<li>a</li>\n").unwrap();
+        fs::write(a.join("stray.txt"), "x").unwrap();
+
+        assert_eq!(
+            compare(&a, &b).unwrap(),
+            [
+                Difference { path: "stray.txt".to_string(), line: None, message: "written by the first build only".to_string() },
+                Difference { path: "tags/rust.html".to_string(), line: Some(2), message: "differs between builds from byte 9".to_string() },
+            ]
+        );
+        assert_eq!(first_difference(b"abc", b"abcd"), Some(3));
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,77 @@
+//! Semantic markup for ```diff and ```patch code blocks
+//!
+//! Added lines become `<ins>`, removed lines `<del>`, and hunk and file
+//! headers get classed spans, so a patch reads correctly without colour and
+//! without any client-side highlighter.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Rendered diff code block (content is already HTML-escaped)
+static DIFF_BLOCK: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?s)<pre><code class="language-(diff|patch)">(.*?)</code></pre>"#).unwrap()
+});
+
+/// Language classes allowed on `<code>` by the sanitizer
+pub const CODE_CLASSES: [&str; 2] = ["language-diff", "language-patch"];
+
+/// Classes of header and hunk line spans
+pub const SPAN_CLASSES: [&str; 2] = ["diff-header", "diff-hunk"];
+
+/// Mark up one line of a diff
+fn line(line: &str) -> String {
+    if line.starts_with("+++") || line.starts_with("---") || line.starts_with("diff ") || line.starts_with("index ") {
+        format!("<span class=\"diff-header\">{line}</span>")
+    } else if line.starts_with("@@") {
+        format!("<span class=\"diff-hunk\">{line}</span>")
+    } else if line.starts_with('+') {
+        format!("<ins>{line}</ins>")
+    } else if line.starts_with('-') {
+        format!("<del>{line}</del>")
+    } else {
+        line.to_string()
+    }
+}
+
+/// Mark up every diff block in rendered post HTML
+pub fn apply(html: &str) -> String {
+    DIFF_BLOCK
+        .replace_all(html, |capture: &regex::Captures| {
+            let body = capture[2].split('\n').map(line).collect::<Vec<_>>().join("\n");
+            format!("<pre><code class=\"language-{}\">{body}</code></pre>", &capture[1])
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::sanitize_html;
+    use crate::SecurityPolicy;
+
+    #[test]
+    fn test_diff_lines_are_marked_up() {
+        let html = "<pre><code class=\"language-diff\">--- a/auth.c\n+++ b/auth.c\n@@ -1,2 +1,2 @@\n-if (len &gt; 0)\n+if (len &gt; 0 &amp;&amp; len &lt; MAX)\n return;\n</code></pre>";
+        assert_eq!(
+            apply(html),
+            "<pre><code class=\"language-diff\"><span class=\"diff-header\">--- a/auth.c</span>\n\
+             <span class=\"diff-header\">+++ b/auth.c</span>\n\
+             <span class=\"diff-hunk\">@@ -1,2 +1,2 @@</span>\n\
+             <del>-if (len &gt; 0)</del>\n\
+             <ins>+if (len &gt; 0 &amp;&amp; len &lt; MAX)</ins>\n \
+             return;\n</code></pre>"
+        );
+    }
+
+    #[test]
+    fn test_other_code_blocks_are_untouched() {
+        let html = "<pre><code class=\"language-sh\">-rf\n+x\n</code></pre>";
+        assert_eq!(apply(html), html);
+    }
+
+    #[test]
+    fn test_diff_markup_survives_sanitizer() {
+        let html = apply("<pre><code class=\"language-patch\">@@ -1 +1 @@\n-a\n+b\n</code></pre>");
+        assert_eq!(sanitize_html(&html, &SecurityPolicy::default()), html);
+    }
+}
@@ -0,0 +1,230 @@
+//! DNS security posture of the site's domain: CAA, DNSSEC and (optionally) DANE TLSA
+//!
+//! Lookups go through a DNS-over-HTTPS JSON resolver so the validating resolver's
+//! `AD` (authenticated data) flag can be reported as the DNSSEC status.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fmt;
+
+/// Validating resolver queried by `check dns`
+pub const RESOLVER: &str = "https://cloudflare-dns.com/dns-query";
+
+/// CAA RR type
+pub const TYPE_CAA: u16 = 257;
+/// TLSA RR type
+pub const TYPE_TLSA: u16 = 52;
+
+/// Resolver JSON answer (`application/dns-json`)
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Response {
+    /// DNS RCODE (0 = NOERROR, 3 = NXDOMAIN)
+    #[serde(rename = "Status")]
+    pub status: u16,
+    /// Answer validated with DNSSEC by the resolver
+    #[serde(rename = "AD", default)]
+    pub authenticated: bool,
+    /// Answer records
+    #[serde(rename = "Answer", default)]
+    pub answer: Vec<Record>,
+}
+
+/// One answer record
+#[derive(Debug, Clone, Deserialize)]
+pub struct Record {
+    /// RR type number
+    #[serde(rename = "type")]
+    pub kind: u16,
+    /// Presentation-format data, e.g. `0 issue "letsencrypt.org"`
+    pub data: String,
+}
+
+impl Response {
+    /// Parse a resolver answer
+    pub fn parse(json: &str) -> Result<Self> {
+        serde_json::from_str(json).context("Invalid DNS-over-HTTPS response")
+    }
+
+    /// Data of the records of type `kind` (CNAMEs on the way are skipped)
+    pub fn records(&self, kind: u16) -> Vec<&str> {
+        self.answer.iter().filter(|r| r.kind == kind).map(|r| r.data.as_str()).collect()
+    }
+}
+
+/// A missing or weak DNS protection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Gap {
+    /// No CAA record on the domain or any parent: any CA may issue certificates
+    Caa,
+    /// CAA present but without an `issue` tag restricting issuers
+    CaaIssue,
+    /// CAA has no `iodef` contact for refused issuance requests
+    CaaIodef,
+    /// The resolver could not validate the answers with DNSSEC
+    Dnssec,
+    /// No TLSA record for `_443._tcp.<domain>`
+    Tlsa,
+}
+
+impl Gap {
+    /// Gaps worth reporting but not failing on
+    pub const fn is_advisory(&self) -> bool {
+        matches!(self, Self::CaaIodef)
+    }
+}
+
+impl fmt::Display for Gap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Caa => "no CAA record: any certificate authority may issue for this domain",
+            Self::CaaIssue => "CAA records have no `issue` tag limiting which CAs may issue",
+            Self::CaaIodef => "CAA has no `iodef` contact for refused issuance attempts",
+            Self::Dnssec => "answers are not DNSSEC-validated (zone unsigned or chain broken)",
+            Self::Tlsa => "no TLSA record at _443._tcp (DANE)",
+        })
+    }
+}
+
+/// Reject names that are not plain hostnames before building a query URL
+pub fn check_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= 253
+        && name.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && label.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+        });
+    if !valid {
+        anyhow::bail!("Not a valid domain name: {name}");
+    }
+    Ok(())
+}
+
+/// Names searched for CAA: the domain, then each parent above the TLD (RFC 8659 tree climbing)
+pub fn caa_candidates(domain: &str) -> Vec<&str> {
+    let mut names = vec![domain];
+    let mut rest = domain;
+    while let Some((_, parent)) = rest.split_once('.') {
+        if !parent.contains('.') {
+            break;
+        }
+        names.push(parent);
+        rest = parent;
+    }
+    names
+}
+
+/// Gaps in the effective CAA record set
+pub fn caa_gaps(records: &[&str]) -> Vec<Gap> {
+    if records.is_empty() {
+        return vec![Gap::Caa];
+    }
+    let tag = |record: &&str| record.split_whitespace().nth(1).map(str::to_ascii_lowercase);
+    let mut gaps = Vec::new();
+    if !records.iter().any(|r| tag(r).as_deref() == Some("issue")) {
+        gaps.push(Gap::CaaIssue);
+    }
+    if !records.iter().any(|r| tag(r).as_deref() == Some("iodef")) {
+        gaps.push(Gap::CaaIodef);
+    }
+    gaps
+}
+
+#[cfg(feature = "network")]
+pub use online::check_domain;
+
+#[cfg(feature = "network")]
+mod online {
+    use super::{caa_candidates, caa_gaps, check_name, Gap, Response, RESOLVER, TYPE_CAA, TYPE_TLSA};
+    use anyhow::{Context, Result};
+    use tracing::info;
+
+    fn query(agent: &ureq::Agent, name: &str, kind: &str) -> Result<Response> {
+        let mut response = agent
+            .get(RESOLVER)
+            .query("name", name)
+            .query("type", kind)
+            .header("accept", "application/dns-json")
+            .call()
+            .with_context(|| format!("DNS query for {name} {kind} failed"))?;
+        if !response.status().is_success() {
+            anyhow::bail!("Resolver answered HTTP {} for {name} {kind}", response.status());
+        }
+        let answer = Response::parse(&response.body_mut().read_to_string()?)?;
+        // NOERROR or NXDOMAIN; anything else (SERVFAIL on a broken DNSSEC chain, ...) is not an answer
+        if answer.status != 0 && answer.status != 3 {
+            anyhow::bail!("Resolver returned RCODE {} for {name} {kind}", answer.status);
+        }
+        Ok(answer)
+    }
+
+    /// Query CAA (with tree climbing), DNSSEC status and optionally TLSA for `domain`
+    pub fn check_domain(domain: &str, tlsa: bool) -> Result<Vec<Gap>> {
+        check_name(domain)?;
+        let agent = crate::net::agent();
+        let mut gaps = Vec::new();
+
+        let mut authenticated = true;
+        let mut caa = Vec::new();
+        for name in caa_candidates(domain) {
+            let response = query(&agent, name, "CAA")?;
+            authenticated &= response.authenticated;
+            caa = response.records(TYPE_CAA).into_iter().map(str::to_string).collect();
+            if !caa.is_empty() {
+                info!("CAA at {}: {}", name, caa.join(", "));
+                break;
+            }
+        }
+        gaps.extend(caa_gaps(&caa.iter().map(String::as_str).collect::<Vec<_>>()));
+        if !authenticated {
+            gaps.push(Gap::Dnssec);
+        }
+
+        if tlsa {
+            let name = format!("_443._tcp.{domain}");
+            if query(&agent, &name, "TLSA")?.records(TYPE_TLSA).is_empty() {
+                gaps.push(Gap::Tlsa);
+            }
+        }
+        Ok(gaps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_resolver_answer() {
+        let response = Response::parse(
+            r#"{"Status":0,"AD":true,"Answer":[{"name":"www.example.com","type":5,"TTL":60,"data":"example.com."},
+                {"name":"example.com","type":257,"TTL":300,"data":"0 issue \"letsencrypt.org\""}]}"#,
+        )
+        .unwrap();
+        assert!(response.authenticated);
+        assert_eq!(response.records(TYPE_CAA), ["0 issue \"letsencrypt.org\""]);
+        assert!(Response::parse(r#"{"Status":3}"#).unwrap().answer.is_empty());
+    }
+
+    #[test]
+    fn test_caa_tree_climbing_stops_above_tld() {
+        assert_eq!(caa_candidates("blog.example.co"), ["blog.example.co", "example.co"]);
+        assert_eq!(caa_candidates("example.com"), ["example.com"]);
+    }
+
+    #[test]
+    fn test_caa_gaps() {
+        assert_eq!(caa_gaps(&[]), [Gap::Caa]);
+        assert!(caa_gaps(&["0 issue \"letsencrypt.org\"", "0 iodef \"mailto:sec@example.com\""]).is_empty());
+        let gaps = caa_gaps(&["0 issuewild \";\""]);
+        assert_eq!(gaps, [Gap::CaaIssue, Gap::CaaIodef]);
+        assert!(gaps[1].is_advisory() && !gaps[0].is_advisory());
+    }
+
+    #[test]
+    fn test_check_name_rejects_query_injection() {
+        assert!(check_name("blog.example.com").is_ok());
+        assert!(check_name("example.com&type=A").is_err());
+        assert!(check_name("a..b").is_err());
+    }
+}
@@ -0,0 +1,267 @@
+//! `doctor`: pre-build audit of the config, content, archetypes, key files and output paths
+
+use chrono::Utc;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::{archetype, markdown, Config, SecurityPolicy};
+
+/// Result of one checklist item
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// Requirement met
+    Pass(String),
+    /// Works, but worth fixing
+    Warn(String),
+    /// Will break the build or leak something
+    Fail(String),
+}
+
+/// One checklist line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    /// What was checked
+    pub name: &'static str,
+    /// Outcome with details
+    pub outcome: Outcome,
+}
+
+impl Check {
+    /// Checklist item
+    pub const fn new(name: &'static str, outcome: Outcome) -> Self {
+        Self { name, outcome }
+    }
+
+    /// Whether this item fails the audit
+    pub const fn failed(&self) -> bool {
+        matches!(self.outcome, Outcome::Fail(_))
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (mark, detail) = match &self.outcome {
+            Outcome::Pass(detail) => ("PASS", detail),
+            Outcome::Warn(detail) => ("WARN", detail),
+            Outcome::Fail(detail) => ("FAIL", detail),
+        };
+        write!(f, "[{mark}] {}: {detail}", self.name)
+    }
+}
+
+/// Site URL and title look deployable
+pub fn check_config(config: &Config) -> Check {
+    let defaults = Config::default();
+    let outcome = if !config.url.starts_with("https://") {
+        Outcome::Fail(format!("url {} is not https://", config.url))
+    } else if config.url == defaults.url || config.title == defaults.title {
+        Outcome::Warn("title or url still has the default value".to_string())
+    } else {
+        Outcome::Pass(format!("{} at {}", config.title, config.url))
+    };
+    Check::new("config", outcome)
+}
+
+/// Every post loads and slugs are unique
+pub fn check_content(config: &Config, policy: &SecurityPolicy) -> Check {
+    if !config.content.is_dir() {
+        return Check::new("content", Outcome::Fail(format!("{} is not a directory", config.content.display())));
+    }
+    let posts = match crate::load_posts(&config.content, config.timezone.default_zone(), policy) {
+        Ok(posts) => posts,
+        Err(e) => return Check::new("content", Outcome::Fail(format!("{e:#}"))),
+    };
+
+    let mut slugs: BTreeMap<&str, usize> = BTreeMap::new();
+    for post in &posts {
+        *slugs.entry(post.meta.slug.as_str()).or_default() += 1;
+    }
+    let duplicates: Vec<&str> = slugs.into_iter().filter(|&(_, n)| n > 1).map(|(slug, _)| slug).collect();
+    let outcome = if !duplicates.is_empty() {
+        Outcome::Fail(format!("duplicate slugs: {}", duplicates.join(", ")))
+    } else if posts.is_empty() {
+        Outcome::Warn(format!("no posts in {}", config.content.display()))
+    } else {
+        Outcome::Pass(format!("{} posts", posts.len()))
+    };
+    Check::new("content", outcome)
+}
+
+/// Archetypes render to valid frontmatter
+pub fn check_archetypes(config: &Config) -> Check {
+    let mut kinds = vec!["post".to_string()];
+    if config.archetypes.is_dir() {
+        kinds.extend(
+            WalkDir::new(&config.archetypes)
+                .max_depth(1)
+                .into_iter()
+                .filter_map(Result::ok)
+                .filter(|e| e.path().extension().is_some_and(|ext| ext == "md"))
+                .filter_map(|e| e.path().file_stem().map(|s| s.to_string_lossy().into_owned()))
+                .filter(|kind| kind != "post"),
+        );
+    }
+
+    let broken: Vec<String> = kinds
+        .iter()
+        .filter(|kind| {
+            archetype::load(&config.archetypes, kind)
+                .map(|template| archetype::render(&template, "Doctor", Utc::now(), "doctor"))
+                .and_then(|content| markdown::parse_frontmatter(&content).map(|_| ()))
+                .is_err()
+        })
+        .cloned()
+        .collect();
+    let outcome = if broken.is_empty() {
+        Outcome::Pass(format!("{} archetypes", kinds.len()))
+    } else {
+        Outcome::Fail(format!("invalid frontmatter from: {}", broken.join(", ")))
+    };
+    Check::new("archetypes", outcome)
+}
+
+/// `path` lies inside `dir` (lexically, for paths that may not exist yet)
+fn is_within(path: &Path, dir: &Path) -> bool {
+    let normalize = |p: &Path| -> PathBuf { p.components().filter(|c| *c != Component::CurDir).collect() };
+    normalize(path).starts_with(normalize(dir))
+}
+
+/// Problems with a private key file: mode bits (`0o600`) and location
+pub fn key_problems(config: &Config, path: &Path, mode: Option<u32>) -> Vec<String> {
+    let mut problems = Vec::new();
+    if let Some(mode) = mode.filter(|mode| mode & 0o077 != 0) {
+        problems.push(format!("{} is readable by others (mode {:o}, use 600)", path.display(), mode & 0o777));
+    }
+    let published = [(config.content.as_path(), "content"), (config.output.as_path(), "output"), (Path::new("static"), "static")];
+    for (dir, what) in published {
+        if is_within(path, dir) {
+            problems.push(format!("{} is inside the {what} directory and may be published", path.display()));
+        }
+    }
+    problems
+}
+
+#[cfg(unix)]
+fn file_mode(path: &Path) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).ok().map(|m| m.permissions().mode())
+}
+
+#[cfg(not(unix))]
+fn file_mode(_path: &Path) -> Option<u32> {
+    None
+}
+
+/// Signing keys exist, are private to the user and are not under a published tree
+pub fn check_keys(config: &Config) -> Check {
+    let Some(sign_files) = &config.sign_files else {
+        return Check::new("keys", Outcome::Pass("no private keys configured".to_string()));
+    };
+    let key = &sign_files.key;
+    if !key.is_file() {
+        return Check::new("keys", Outcome::Fail(format!("{} not found", key.display())));
+    }
+    let problems = key_problems(config, key, file_mode(key));
+    let outcome = if problems.is_empty() {
+        Outcome::Pass(format!("{} is private", key.display()))
+    } else {
+        Outcome::Fail(problems.join("; "))
+    };
+    Check::new("keys", outcome)
+}
+
+/// Reason a directory the build deletes and recreates would destroy something else
+pub fn unsafe_output(dir: &Path, content: &Path) -> Option<String> {
+    if dir.as_os_str().is_empty() || dir.components().all(|c| matches!(c, Component::CurDir)) {
+        return Some("is the project root".to_string());
+    }
+    if dir.is_absolute() {
+        return Some("is absolute; keep it inside the project".to_string());
+    }
+    if dir.components().any(|c| c == Component::ParentDir) {
+        return Some("points outside the project".to_string());
+    }
+    if is_within(content, dir) || is_within(dir, content) {
+        return Some(format!("overlaps the content directory {}", content.display()));
+    }
+    if dir.join(".git").exists() || dir.join("config.yaml").exists() {
+        return Some("contains .git or config.yaml".to_string());
+    }
+    None
+}
+
+/// Output directories are safe to wipe on each build
+pub fn check_output(config: &Config) -> Check {
+    let problems: Vec<String> = [&config.output, &config.review_output, &config.export.output]
+        .into_iter()
+        .filter_map(|dir| unsafe_output(dir, &config.content).map(|reason| format!("{} {reason}", dir.display())))
+        .collect();
+    let outcome = if problems.is_empty() {
+        Outcome::Pass(format!("{} is safe to clean", config.output.display()))
+    } else {
+        Outcome::Fail(problems.join("; "))
+    };
+    Check::new("output", outcome)
+}
+
+/// Generator version and build features
+pub fn check_version() -> Check {
+    let network = if cfg!(feature = "network") { "with" } else { "without" };
+    Check::new(
+        "version",
+        Outcome::Pass(format!("secureblog-rs {} ({network} network feature)", env!("CARGO_PKG_VERSION"))),
+    )
+}
+
+/// Full checklist
+pub fn run(config: &Config, policy: &SecurityPolicy) -> Vec<Check> {
+    vec![
+        check_version(),
+        check_config(config),
+        check_content(config, policy),
+        check_archetypes(config),
+        check_keys(config),
+        check_output(config),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_requires_https() {
+        let config = Config { url: "http://blog.example".to_string(), ..Config::default() };
+        assert!(check_config(&config).failed());
+        assert!(matches!(check_config(&Config::default()).outcome, Outcome::Warn(_)));
+    }
+
+    #[test]
+    fn test_key_permissions_and_location() {
+        let config = Config::default();
+        assert!(key_problems(&config, Path::new(".secureblog/key"), Some(0o100_600)).is_empty());
+        assert_eq!(key_problems(&config, Path::new(".secureblog/key"), Some(0o100_644)).len(), 1);
+        assert_eq!(key_problems(&config, Path::new("./static/keys/site"), None).len(), 1);
+        assert_eq!(key_problems(&config, Path::new("content/key"), Some(0o100_640)).len(), 2);
+    }
+
+    #[test]
+    fn test_unsafe_output_dirs() {
+        let content = Path::new("content");
+        assert!(unsafe_output(Path::new("dist"), content).is_none());
+        assert!(unsafe_output(Path::new("."), content).is_some());
+        assert!(unsafe_output(Path::new(""), content).is_some());
+        assert!(unsafe_output(Path::new("../site"), content).is_some());
+        assert!(unsafe_output(Path::new("/var/www"), content).is_some());
+        assert!(unsafe_output(Path::new("content/out"), content).is_some());
+    }
+
+    #[test]
+    fn test_checklist_line() {
+        let check = Check::new("output", Outcome::Fail("dist is bad".to_string()));
+        assert_eq!(check.to_string(), "[FAIL] output: dist is bad");
+    }
+}
@@ -0,0 +1,168 @@
+//! Excerpts and meta descriptions
+//!
+//! A post's lead is everything before a `<!--more-->` line, or else its first
+//! paragraph. Feeds in summary mode use it as the entry summary. The meta
+//! description is the frontmatter `description:` or, when that is omitted,
+//! the lead's text cut at the last sentence that fits in 160 characters; it
+//! goes to the template context and, unless the page already has one, a
+//! `<meta name="description">` tag.
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::fs;
+
+use crate::security::escape_html as escape;
+use crate::{inject, Config, Post};
+
+/// Longest derived description, in characters
+pub const DESCRIPTION_LENGTH: usize = 160;
+
+/// Paragraph the marker becomes until the rendered HTML is available
+const PLACEHOLDER: &str = "SECUREBLOG-MORE";
+
+/// `<!--more-->` on its own line
+static MORE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*<!--\s*more\s*-->\s*$").unwrap());
+
+/// Start of a fenced code block (a marker inside one is left alone)
+static FENCE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s{0,3}(`{3,}|~{3,})").unwrap());
+
+static FIRST_PARAGRAPH: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<p\b[^>]*>.*?</p>").unwrap());
+
+/// Citation numbers, which mean nothing out of context
+static CITATION: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(?is)<sup class="citation">.*?</sup>"#).unwrap());
+
+static TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]*>").unwrap());
+
+/// Replace the first `<!--more-->` line outside code with a placeholder paragraph
+pub fn mark(markdown: &str) -> String {
+    let mut output = String::with_capacity(markdown.len());
+    let mut fence: Option<String> = None;
+    let mut marked = false;
+    for line in markdown.lines() {
+        if let Some(open) = &fence {
+            if line.trim_start().starts_with(open.as_str()) {
+                fence = None;
+            }
+        } else if let Some(capture) = FENCE.captures(line) {
+            fence = Some(capture[1].to_string());
+        } else if !marked && MORE.is_match(line) {
+            marked = true;
+            output.push('\n');
+            output.push_str(PLACEHOLDER);
+            output.push_str("\n\n");
+            continue;
+        }
+        output.push_str(line);
+        output.push('\n');
+    }
+    output
+}
+
+/// The post's HTML without the placeholder, and everything before it (empty without a marker)
+pub fn split(html: &str) -> (String, String) {
+    let paragraph = format!("<p>{PLACEHOLDER}</p>");
+    match html.find(&paragraph) {
+        Some(start) => {
+            let excerpt = html[..start].trim_end().to_string();
+            (format!("{}{}", &html[..start], html[start + paragraph.len()..].trim_start_matches('\n')), excerpt)
+        }
+        None => (html.to_string(), String::new()),
+    }
+}
+
+/// The lead as HTML: the excerpt before the marker, else the first paragraph, else the whole post
+pub fn lead(post: &Post) -> &str {
+    if !post.excerpt.is_empty() {
+        return &post.excerpt;
+    }
+    FIRST_PARAGRAPH.find(&post.html).map_or(post.html.as_str(), |m| m.as_str())
+}
+
+/// Text of an HTML fragment on one line
+fn plain_text(html: &str) -> String {
+    let text = TAG.replace_all(&CITATION.replace_all(html, ""), " ").into_owned();
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    // Tags between a word and its punctuation left a space behind
+    text.replace(" .", ".").replace(" ,", ",")
+}
+
+/// `text` cut after its last sentence that fits in `max` characters, or at a word with `…`
+fn truncate(text: &str, max: usize) -> String {
+    let Some((cut, _)) = text.char_indices().nth(max) else {
+        return text.to_string();
+    };
+    let head = &text[..cut];
+    let sentence = head
+        .char_indices()
+        .filter(|&(i, c)| matches!(c, '.' | '!' | '?') && text[i + 1..].starts_with(char::is_whitespace))
+        .map(|(i, _)| i + 1)
+        .last();
+    if let Some(end) = sentence {
+        return head[..end].to_string();
+    }
+    let end = head.rfind(char::is_whitespace).unwrap_or(cut);
+    format!("{}…", head[..end].trim_end_matches([',', ';', ':', ' ']))
+}
+
+/// The meta description: the frontmatter one, or derived from the lead
+pub fn description(post: &Post) -> String {
+    match post.meta.description.as_deref().map(str::trim).filter(|d| !d.is_empty()) {
+        Some(description) => description.to_string(),
+        None => truncate(&plain_text(lead(post)), DESCRIPTION_LENGTH),
+    }
+}
+
+/// Add `<meta name="description">` to post pages whose template did not
+pub fn apply(config: &Config, posts: &[Post]) -> Result<()> {
+    for post in posts {
+        let page = config.output.join(post.path());
+        let description = description(post);
+        if description.is_empty() || fs::read_to_string(&page)?.contains("name=\"description\"") {
+            continue;
+        }
+        let tag = format!("<meta name=\"description\" content=\"{}\">\n", escape(&description));
+        inject::inject_into_file(&page, &["</head>"], &tag)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PostMeta;
+
+    #[test]
+    fn test_more_marker() {
+        let markdown = "Intro.\n\n```\n<!--more-->\n```\n<!-- more -->\nRest.\n";
+        let marked = mark(markdown);
+        assert_eq!(marked, "Intro.\n\n```\n<!--more-->\n```\n\nSECUREBLOG-MORE\n\nRest.\n");
+        let (html, excerpt) = split("<p>Intro.</p>\n<p>SECUREBLOG-MORE</p>\n<p>Rest.</p>\n");
+        assert_eq!(html, "<p>Intro.</p>\n<p>Rest.</p>\n");
+        assert_eq!(excerpt, "<p>Intro.</p>");
+        assert_eq!(split("<p>x</p>"), ("<p>x</p>".to_string(), String::new()));
+    }
+
+    #[test]
+    fn test_description_cut_at_sentence() {
+        let html = "<p>CSP blocks <em>inline</em> scripts<sup class=\"citation\">[1]</sup>. It needs nonces &amp; hashes for \
+                    the rest, which most templates do not provide, so this post walks through adding them step by step. \
+                    A third sentence.</p><p>Second paragraph.</p>";
+        let mut post = Post { html: html.to_string(), ..Post::default() };
+        assert_eq!(
+            description(&post),
+            "CSP blocks inline scripts. It needs nonces & hashes for the rest, which most templates do not provide, \
+             so this post walks through adding them step by step."
+        );
+        post.meta = PostMeta { description: Some(" Explicit. ".to_string()), ..PostMeta::default() };
+        assert_eq!(description(&post), "Explicit.");
+        assert_eq!(truncate(&"word ".repeat(40), 20), "word word word word…");
+    }
+}
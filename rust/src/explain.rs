@@ -0,0 +1,235 @@
+//! `check --explain RULE` and `check output --verbose`
+//!
+//! Every finding names the rule behind it. An explanation says why the rule
+//! exists, how to fix the content, and how to allowlist a reviewed exception
+//! without weakening the rule for the rest of the site.
+
+use anyhow::Result;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::baseline::{Finding, BASELINE};
+use crate::{lint, Check, SecurityPolicy};
+
+/// Rule id of broken-link findings
+pub const LINKS: &str = "links";
+
+/// Why a rule exists and what to do about a finding
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    /// Rule id, as shown with findings
+    pub rule: String,
+    /// What the rule protects against
+    pub why: String,
+    /// How to change the content
+    pub fix: String,
+    /// How to accept a reviewed exception
+    pub allowlist: String,
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.rule)?;
+        writeln!(f, "  Why:       {}", self.why)?;
+        writeln!(f, "  Fix:       {}", self.fix)?;
+        write!(f, "  Allowlist: {}", self.allowlist)
+    }
+}
+
+fn explanation(rule: &str, why: &str, fix: &str, allowlist: &str) -> Explanation {
+    Explanation { rule: rule.to_string(), why: why.to_string(), fix: fix.to_string(), allowlist: allowlist.to_string() }
+}
+
+/// Explanation of a built-in check
+pub fn builtin(check: Check) -> Explanation {
+    let baselined = format!("add the finding with a justification to {BASELINE} (`baseline update`)");
+    match check {
+        Check::Javascript => explanation(
+            check.id(),
+            "Any script, event handler or javascript: URL turns an injected string into code running on every \
+             reader's machine. A site without JavaScript has no XSS to exploit and nothing to fingerprint readers with.",
+            "Remove the <script>, on*= handler or javascript: link; render the content at build time instead. \
+             JSON-LD blocks with valid JSON are not scripts and are allowed.",
+            &format!("There is no per-file allowlist for scripts; {baselined}, or set `severity: {{ javascript: warning }}`."),
+        ),
+        Check::InlineStyles => explanation(
+            check.id(),
+            "style=\"\" attributes need 'unsafe-inline' in the Content-Security-Policy, which also lets injected \
+             markup restyle the page (overlays, hidden text).",
+            "Move the rules into a stylesheet, for one post its `css:` frontmatter file.",
+            &format!("{baselined}, or set `severity: {{ inline_styles: warning }}` in security-policy.yaml."),
+        ),
+        Check::External => explanation(
+            check.id(),
+            "Every third-party resource is a request that tells another host who reads what, and a dependency \
+             that can change under the manifest's integrity hashes.",
+            "Download the resource into the site (static/ or a page bundle) and reference it by a relative path.",
+            "Add the host to `allowed_hosts` in security-policy.yaml (`cdn.example.com` or `*.example.org`).",
+        ),
+        Check::PageSize => explanation(
+            check.id(),
+            "Very large pages are slow on poor connections and usually hide embedded data or runaway includes.",
+            "Split the post, move large images out of data: URIs, or trim included code.",
+            &format!("Raise `max_page_size` in security-policy.yaml, or {baselined}."),
+        ),
+        Check::DataUri => explanation(
+            check.id(),
+            "data: URIs embed content the manifest cannot hash separately and are a common way to smuggle \
+             documents or scripts past URL-based checks.",
+            "Publish the image or font as a file and reference it by URL.",
+            &format!("Set `no_data_uris: false` in security-policy.yaml, or {baselined}."),
+        ),
+        Check::Mime => explanation(
+            check.id(),
+            "Browsers and CDNs can trust a file's bytes over its name, so HTML saved as .png still renders as a \
+             page, and executables can be smuggled out under any name. The HTML and CSS checks never read them.",
+            "Give the file the extension of what it really is, re-export the image, or remove the executable.",
+            &format!("{baselined}; there is no way to allow executables."),
+        ),
+        Check::Polyglot => explanation(
+            check.id(),
+            "A file that parses as two formats is served as one and run as the other: a GIF that is also a \
+             script, a PDF that is also a page. Each format passes its own checks, so neither catches it.",
+            "Re-export the file from the original source (opening and saving an image drops appended data).",
+            &format!("{baselined}, or set `severity: {{ polyglot: warning }}` in security-policy.yaml."),
+        ),
+        Check::FileType => explanation(
+            check.id(),
+            "Some types are executed or rendered with scripts wherever they are served (.svgz, .xhtml, .hta), \
+             and HTML in an image directory is usually an upload nobody meant to publish as a page.",
+            "Convert the file to a safe type (.svg, .html outside image directories) or remove it.",
+            "Edit `dangerous_extensions` or `image_dirs` in security-policy.yaml, or use a per-path `overrides:` severity.",
+        ),
+        Check::MetaRefresh => explanation(
+            check.id(),
+            "A refresh tag reloads or redirects the page without the reader asking, which is how open redirects \
+             and phishing hops work. The generator only adds one to `preview` pages, so in the output it means a \
+             preview build was deployed or a template added it.",
+            "Deploy the output of `build`, not the preview directory, and remove refresh tags from templates; \
+             use the permalink redirects for moved pages.",
+            &format!("{baselined}; preview pages should never need it."),
+        ),
+    }
+}
+
+/// Explanation of broken-link findings
+fn links() -> Explanation {
+    explanation(
+        LINKS,
+        "Broken internal links and fragments mean readers hit 404s and crawlers index a site that looks abandoned.",
+        "Point the link at an existing page or anchor; after moving a post, keep the old URL via the permalink redirects.",
+        &format!("Add the finding with a justification to {BASELINE} (`baseline update`)."),
+    )
+}
+
+/// Explanation of a site-specific rule from `security-policy.yaml`
+fn site_rule(spec: &lint::RuleSpec) -> Explanation {
+    let scope = if spec.tags.is_empty() { "every post".to_string() } else { format!("posts tagged {}", spec.tags.join(", ")) };
+    let (verb, pattern) = match (&spec.deny, &spec.require) {
+        (Some(pattern), _) => ("must not contain", pattern),
+        (None, Some(pattern)) => ("must contain", pattern),
+        (None, None) => return explanation(&spec.name, &spec.message, "", ""),
+    };
+    let pattern = match (&pattern.regex, &pattern.selector) {
+        (Some(regex), _) => format!("regex `{regex}`"),
+        (None, Some(selector)) => format!("selector `{selector}`"),
+        (None, None) => "nothing".to_string(),
+    };
+    explanation(
+        &spec.name,
+        &format!("{} (site rule: {scope} {verb} {pattern}).", spec.message.trim_end_matches('.')),
+        &format!("Change the post so its rendered HTML {verb} {pattern}."),
+        "Narrow the rule's `tags:` or set `severity: warning` on it in security-policy.yaml.",
+    )
+}
+
+/// Every rule id `explain` knows
+pub fn rule_ids(policy: &SecurityPolicy) -> Vec<String> {
+    Check::ALL
+        .iter()
+        .map(|check| check.id().to_string())
+        .chain([LINKS.to_string()])
+        .chain(policy.rules.iter().map(|rule| rule.name.clone()))
+        .collect()
+}
+
+/// Explanation of a built-in check, `links` or a site rule
+pub fn explain(policy: &SecurityPolicy, rule: &str) -> Result<Explanation> {
+    if let Some(check) = Check::ALL.iter().find(|check| check.id() == rule) {
+        return Ok(builtin(*check));
+    }
+    if rule == LINKS {
+        return Ok(links());
+    }
+    match policy.rules.iter().find(|spec| spec.name == rule) {
+        Some(spec) => Ok(site_rule(spec)),
+        None => anyhow::bail!("Unknown rule `{rule}` (available: {})", rule_ids(policy).join(", ")),
+    }
+}
+
+/// Findings with the line that matched, then one explanation per rule involved
+pub fn verbose(output_dir: &Path, findings: &[&Finding]) -> String {
+    let mut text = String::new();
+    let mut rules = BTreeSet::new();
+    for finding in findings {
+        text.push_str(&format!("[{}] {}\n", finding.rule, finding));
+        let matched = finding.line.and_then(|line| {
+            let content = fs::read_to_string(output_dir.join(&finding.file)).ok()?;
+            content.lines().nth(line - 1).map(|l| l.trim().chars().take(160).collect::<String>())
+        });
+        if let (Some(line), Some(matched)) = (finding.line, matched) {
+            text.push_str(&format!("    {}:{line}: {matched}\n", finding.file));
+        }
+        rules.insert(finding.rule);
+    }
+    for rule in rules {
+        let explanation = match Check::ALL.iter().find(|check| check.id() == rule) {
+            Some(check) => builtin(*check),
+            None => links(),
+        };
+        text.push_str(&format!("\n{explanation}\n"));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explain_builtin_and_site_rules() {
+        let policy: SecurityPolicy = serde_yaml::from_str(
+            "rules:\n  - name: no-doubleclick\n    deny: { selector: 'a[href*=doubleclick.net]' }\n    message: No ad trackers.\n",
+        )
+        .unwrap();
+        assert!(explain(&policy, "external").unwrap().allowlist.contains("allowed_hosts"));
+        assert_eq!(
+            explain(&policy, "no-doubleclick").unwrap().why,
+            "No ad trackers (site rule: every post must not contain selector `a[href*=doubleclick.net]`)."
+        );
+        let unknown = explain(&policy, "nope").unwrap_err().to_string();
+        assert!(unknown.contains("javascript, inline_styles, external, page_size, data_uri, mime, polyglot, file_type, meta_refresh, links, no-doubleclick"));
+    }
+
+    #[test]
+    fn test_verbose_shows_matched_line() {
+        let dir = std::env::temp_dir().join(format!("secureblog-explain-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.html"), "<p>ok</p>\n  <img src=\"https://evil.test/x.png\">\n").unwrap();
+        let finding = Finding {
+            check: "security",
+            rule: Check::External.id(),
+            file: "a.html".to_string(),
+            line: Some(2),
+            message: "External resource 'https://evil.test/x.png' in a.html".to_string(),
+        };
+        let text = verbose(&dir, &[&finding]);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(text.starts_with("[external] security: External resource"));
+        assert!(text.contains("    a.html:2: <img src=\"https://evil.test/x.png\">\n"));
+        assert!(text.contains("\nexternal\n  Why:"));
+    }
+}
@@ -0,0 +1,206 @@
+//! Deterministic offline archive of the whole built site
+
+use anyhow::{Context, Result};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+use crate::slug::slugify;
+use crate::sink::{self, OutputSink};
+use crate::{normalize, reproducible, vfs, Config};
+
+/// Integrity manifest written by the build
+const MANIFEST: &str = "integrity.json";
+
+/// Detached signatures of the manifest and its Rekor entry, copied next to it when present
+const SIGNATURE_SUFFIXES: &[&str] = &[".sig", ".crt", ".asc", ".sshsig", ".rekor.json"];
+
+const README: &str = "Offline copy of {title} ({url})
+
+Open site/index.html in a browser. Nothing in it loads from the network.
+
+To check the copy:
+  sha256sum -c INDEX.txt
+and verify integrity.json against its detached signature, e.g.
+  cosign verify-blob --signature integrity.json.sig --certificate integrity.json.crt integrity.json
+  ssh-keygen -Y verify -f allowed_signers -I <signer> -n file -s integrity.json.sig < integrity.json
+";
+
+/// `sha256sum`-compatible listing of the archived site, reading one file at a time
+pub fn index(paths: &[String], read: &dyn Fn(&str) -> Result<Vec<u8>>) -> Result<String> {
+    let mut index = String::new();
+    for path in paths {
+        let _ = writeln!(index, "{:x}  site/{path}", Sha256::digest(read(path)?));
+    }
+    Ok(index)
+}
+
+/// Stream the `.tar.gz` of the site under `top/` to `writer` and return it
+///
+/// `paths` are relative to the output root and `read` loads one of them, so
+/// only one file is held in memory at a time (each is read twice: once for
+/// `INDEX.txt`, once for its entry). Entries are sorted, carry no owners and
+/// all have the timestamp `mtime`, so the same site always produces the same bytes.
+pub fn archive<W: Write + Send>(
+    config: &Config,
+    top: &str,
+    paths: &[String],
+    read: &dyn Fn(&str) -> Result<Vec<u8>>,
+    mtime: u64,
+    writer: W,
+) -> Result<W> {
+    if !paths.iter().any(|path| path == MANIFEST) {
+        anyhow::bail!("{MANIFEST} missing from the output (run build first)");
+    }
+    let signatures: Vec<&String> = paths
+        .iter()
+        .filter(|path| SIGNATURE_SUFFIXES.iter().any(|suffix| **path == format!("{MANIFEST}{suffix}")))
+        .collect();
+    if signatures.is_empty() {
+        warn!("No detached signature next to {MANIFEST}; the archive can only be checked against INDEX.txt");
+    }
+
+    let mut paths = paths.to_vec();
+    paths.sort();
+    let readme = README.replace("{title}", &config.title).replace("{url}", &config.url);
+    let index = index(&paths, read)?;
+
+    // The gzip header has no file name and a zero mtime
+    let tar = sink::TarStream::new(GzEncoder::new(writer, Compression::best()), mtime);
+    tar.add_dir(top)?;
+    tar.write(&format!("{top}/README.txt"), readme.as_bytes())?;
+    tar.write(&format!("{top}/INDEX.txt"), index.as_bytes())?;
+    tar.write(&format!("{top}/{MANIFEST}"), &read(MANIFEST)?)?;
+    for path in &signatures {
+        tar.write(&format!("{top}/{path}"), &read(path)?)?;
+    }
+
+    let mut dirs = BTreeSet::new();
+    for path in &paths {
+        let mut parent = path.as_str();
+        while let Some((dir, _)) = parent.rsplit_once('/') {
+            dirs.insert(dir.to_string());
+            parent = dir;
+        }
+    }
+    tar.add_dir(&format!("{top}/site"))?;
+    for dir in &dirs {
+        tar.add_dir(&format!("{top}/site/{dir}"))?;
+    }
+    for path in &paths {
+        tar.write(&format!("{top}/site/{path}"), &read(path)?)?;
+    }
+    Ok(tar.finish()?.finish()?)
+}
+
+/// Write `<export>/<site>.tar.gz` from the built output, streaming it file by file
+pub fn export(config: &Config) -> Result<PathBuf> {
+    if !config.output.exists() {
+        anyhow::bail!("Output directory {} not found (run build first)", config.output.display());
+    }
+
+    let files = vfs::current();
+    let paths = files
+        .files(&config.output)
+        .with_context(|| format!("Failed to list {}", config.output.display()))?
+        .iter()
+        .map(|path| normalize::relative(path, &config.output))
+        .collect::<Result<Vec<_>>>()?;
+    let read = |path: &str| {
+        let source = config.output.join(path);
+        files.read(&source).with_context(|| format!("Failed to read {}", source.display()))
+    };
+
+    let top = slugify(&config.title);
+    let epoch = reproducible::source_date_epoch()?;
+    fs::create_dir_all(&config.export.output)
+        .with_context(|| format!("Failed to create {}", config.export.output.display()))?;
+    let path = config.export.output.join(format!("{top}.tar.gz"));
+
+    // Streamed into a partial file, so a failed export never leaves a truncated archive behind
+    let partial = path.with_extension("gz.partial");
+    let file = fs::File::create(&partial).with_context(|| format!("Failed to create {}", partial.display()))?;
+    let result = archive(config, &top, &paths, &read, epoch, BufWriter::new(file)).and_then(|mut writer| {
+        writer.flush()?;
+        Ok(())
+    });
+    if let Err(e) = result {
+        let _ = fs::remove_file(&partial);
+        return Err(e.context(format!("Failed to write {}", path.display())));
+    }
+    fs::rename(&partial, &path).with_context(|| format!("Failed to write {}", path.display()))?;
+    reproducible::normalize_file(&path, epoch)?;
+    info!("📦 {} files -> {}", paths.len(), path.display());
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    fn site() -> Vec<(String, Vec<u8>)> {
+        vec![
+            ("posts/a.html".to_string(), b"<p>a</p>".to_vec()),
+            ("index.html".to_string(), b"<p>home</p>".to_vec()),
+            ("integrity.json".to_string(), b"{}".to_vec()),
+            ("integrity.json.sig".to_string(), b"sig".to_vec()),
+        ]
+    }
+
+    fn build(files: &[(String, Vec<u8>)]) -> Result<Vec<u8>> {
+        let paths: Vec<String> = files.iter().map(|(path, _)| path.clone()).collect();
+        let read = |path: &str| {
+            let file = files.iter().find(|(p, _)| p == path);
+            file.map(|(_, content)| content.clone()).context("missing")
+        };
+        archive(&Config::default(), "blog", &paths, &read, 0, Vec::new())
+    }
+
+    fn entries(archive: &[u8]) -> Vec<String> {
+        let mut tar = Vec::new();
+        GzDecoder::new(archive).read_to_end(&mut tar).unwrap();
+        let mut names = Vec::new();
+        let mut offset = 0;
+        while tar[offset] != 0 {
+            let header = &tar[offset..offset + 512];
+            let name = String::from_utf8(header[..100].iter().copied().take_while(|&b| b != 0).collect()).unwrap();
+            let size = usize::from_str_radix(std::str::from_utf8(&header[124..135]).unwrap(), 8).unwrap();
+            names.push(name);
+            offset += 512 + size.div_ceil(512) * 512;
+        }
+        names
+    }
+
+    #[test]
+    fn test_archive_layout_and_determinism() {
+        let first = build(&site()).unwrap();
+        let mut reordered = site();
+        reordered.reverse();
+        assert_eq!(first, build(&reordered).unwrap());
+
+        assert_eq!(
+            entries(&first),
+            [
+                "blog/", "blog/README.txt", "blog/INDEX.txt", "blog/integrity.json", "blog/integrity.json.sig",
+                "blog/site/", "blog/site/posts/", "blog/site/index.html", "blog/site/integrity.json",
+                "blog/site/integrity.json.sig", "blog/site/posts/a.html",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_index_and_missing_manifest() {
+        let read = |_: &str| Ok::<_, anyhow::Error>(b"<p>a</p>".to_vec());
+        let index = index(&["posts/a.html".to_string()], &read).unwrap();
+        assert_eq!(index, format!("{:x}  site/posts/a.html\n", Sha256::digest(b"<p>a</p>")));
+        assert!(build(&site()[..2]).is_err());
+    }
+}
@@ -0,0 +1,241 @@
+//! Self-contained single-file HTML exports of posts
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use std::fs;
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+use super::data_uri;
+use crate::sink::{self, OutputSink};
+use crate::{inject, links, reproducible, security, Config, Post, SecurityPolicy};
+
+/// Policy for bundles: inline styles and `data:` assets only, still no scripts
+const BUNDLE_CSP: &str = "default-src 'none'; style-src 'unsafe-inline'; img-src data:; font-src data:; \
+                          media-src data:; form-action 'none'; base-uri 'none'";
+
+/// `@import` nesting followed when inlining stylesheets
+const MAX_IMPORT_DEPTH: usize = 8;
+
+static LINK_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<link\b[^>]*>").unwrap());
+
+static ATTRIBUTE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?is)\s([a-z-]+)\s*=\s*["']([^"']*)["']"#).unwrap());
+
+/// `src=` on images, media and sources
+static SRC_ATTRIBUTE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)(\ssrc\s*=\s*)(["'])([^"']*)["']"#).unwrap());
+
+/// `srcset=` candidates point at files a bundle does not carry
+static SRCSET_ATTRIBUTE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)\ssrcset\s*=\s*["'][^"']*["']"#).unwrap());
+
+static CSP_META: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)<meta\s[^>]*http-equiv\s*=\s*["']Content-Security-Policy["'][^>]*>"#).unwrap()
+});
+
+static CSS_IMPORT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)@import\s+(?:url\(\s*)?["']?([^"')\s;]+)["']?\s*\)?[^;]*;"#).unwrap()
+});
+
+static CSS_URL: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(?i)url\(\s*["']?([^"')]+?)["']?\s*\)"#).unwrap());
+
+/// A page with its assets inlined
+#[derive(Debug, Default)]
+pub struct Bundle {
+    /// The self-contained document
+    pub html: String,
+    /// References that could not be inlined (missing, unsupported or over the size cap)
+    pub skipped: Vec<String>,
+}
+
+/// Inlines assets of one page; `read` returns a file by its output-relative path
+struct Inliner<'a> {
+    read: &'a dyn Fn(&str) -> Option<Vec<u8>>,
+    max_inline_bytes: u64,
+    skipped: Vec<String>,
+}
+
+impl Inliner<'_> {
+    /// `data:` URI for a reference from `base`, or `None` to leave it alone
+    fn asset(&mut self, base: &str, reference: &str) -> Option<String> {
+        if reference.starts_with('#') {
+            return None;
+        }
+        let (target, _) = links::resolve(base, reference)?;
+        let uri = (self.read)(&target)
+            .filter(|content| content.len() as u64 <= self.max_inline_bytes)
+            .and_then(|content| data_uri(&target, &content));
+        if uri.is_none() {
+            self.skipped.push(target);
+        }
+        uri
+    }
+
+    /// Stylesheet with local `@import`s flattened and `url()`s inlined
+    fn stylesheet(&mut self, path: &str, depth: usize) -> Option<String> {
+        let css = String::from_utf8((self.read)(path)?).ok()?;
+        let css = CSS_IMPORT.replace_all(&css, |cap: &Captures<'_>| {
+            let imported = links::resolve(path, &cap[1])
+                .filter(|_| depth < MAX_IMPORT_DEPTH)
+                .and_then(|(target, _)| self.stylesheet(&target, depth + 1));
+            imported.unwrap_or_else(|| {
+                self.skipped.push(cap[1].to_string());
+                String::new()
+            })
+        });
+        let css = CSS_URL.replace_all(&css, |cap: &Captures<'_>| {
+            self.asset(path, cap[1].trim())
+                .map_or_else(|| cap[0].to_string(), |uri| format!("url(\"{uri}\")"))
+        });
+        Some(css.into_owned())
+    }
+
+    /// Replace a `<link>` with an inline `<style>` or `data:` icon where possible
+    fn link(&mut self, page: &str, tag: &str) -> String {
+        let mut rel = String::new();
+        let mut href = None;
+        for cap in ATTRIBUTE.captures_iter(tag) {
+            match cap[1].to_ascii_lowercase().as_str() {
+                "rel" => rel = cap[2].to_ascii_lowercase(),
+                "href" => href = Some(cap[2].to_string()),
+                _ => {}
+            }
+        }
+        let Some(href) = href else {
+            return tag.to_string();
+        };
+
+        if rel.split_whitespace().any(|r| r == "stylesheet") {
+            let css = links::resolve(page, &href).and_then(|(target, _)| self.stylesheet(&target, 0));
+            return css.map_or_else(
+                || {
+                    self.skipped.push(href.clone());
+                    String::new()
+                },
+                |css| format!("<style>\n{}\n</style>", css.replace("</style", "<\\/style")),
+            );
+        }
+        if rel.split_whitespace().any(|r| r == "icon") {
+            if let Some(uri) = self.asset(page, &href) {
+                return tag.replacen(&href, &uri, 1);
+            }
+        }
+        tag.to_string()
+    }
+}
+
+/// Inline the stylesheets, images and icons of `page` and tighten its CSP for `data:` assets
+pub fn bundle_page(
+    page: &str,
+    html: &str,
+    read: &dyn Fn(&str) -> Option<Vec<u8>>,
+    max_inline_bytes: u64,
+) -> Bundle {
+    let mut inliner = Inliner {
+        read,
+        max_inline_bytes,
+        skipped: Vec::new(),
+    };
+
+    let html = LINK_TAG.replace_all(html, |cap: &Captures<'_>| inliner.link(page, &cap[0]));
+    let html = SRCSET_ATTRIBUTE.replace_all(&html, "");
+    let html = SRC_ATTRIBUTE.replace_all(&html, |cap: &Captures<'_>| {
+        inliner
+            .asset(page, &cap[3])
+            .map_or_else(|| cap[0].to_string(), |uri| format!("{}{}{uri}{}", &cap[1], &cap[2], &cap[2]))
+    });
+
+    let csp = format!("<meta http-equiv=\"Content-Security-Policy\" content=\"{BUNDLE_CSP}\">");
+    let html = if CSP_META.is_match(&html) {
+        CSP_META.replace_all(&html, csp.as_str()).into_owned()
+    } else {
+        inject::insert_before(&html, &["</head>"], &format!("{csp}\n")).unwrap_or_else(|| html.into_owned())
+    };
+
+    Bundle {
+        html,
+        skipped: inliner.skipped,
+    }
+}
+
+/// Write `<export>/<slug>.html` bundles for `posts` from the built site
+///
+/// The bundles are validated with the site's security policy before returning.
+pub fn export(config: &Config, posts: &[&Post], policy: &SecurityPolicy) -> Result<Vec<PathBuf>> {
+    if !config.output.exists() {
+        anyhow::bail!("Output directory {} not found (run build first)", config.output.display());
+    }
+    let out_dir = config.export.output.join("bundle");
+    fs::create_dir_all(&out_dir).with_context(|| format!("Failed to create {}", out_dir.display()))?;
+    // Pages live in directories (`2024/05/post/index.html`), which the sink creates
+    let sink = sink::Disk { root: out_dir.clone() };
+
+    let read = |path: &str| fs::read(config.output.join(path)).ok();
+    let epoch = reproducible::source_date_epoch()?;
+    let mut written = Vec::new();
+
+    for post in posts {
+        let page = post.path();
+        let source = config.output.join(&page);
+        let html = fs::read_to_string(&source).with_context(|| format!("Failed to read {}", source.display()))?;
+
+        let bundle = bundle_page(&page, &html, &read, config.export.max_inline_bytes);
+        for reference in &bundle.skipped {
+            warn!("{}: not inlined: {}", page, reference);
+        }
+
+        sink.write(&page, bundle.html.as_bytes())?;
+        let path = out_dir.join(&page);
+        reproducible::normalize_file(&path, epoch)?;
+        info!("Bundled {} -> {}", page, path.display());
+        written.push(path);
+    }
+
+    security::validate_output(&out_dir, policy)?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn site() -> HashMap<&'static str, Vec<u8>> {
+        HashMap::from([
+            ("css/site.css", b"@import \"base.css\";\nbody { background: url(../img/bg.png) }".to_vec()),
+            ("css/base.css", b"p { margin: 0 }".to_vec()),
+            ("img/bg.png", b"png".to_vec()),
+            ("img/photo.jpg", vec![0; 64]),
+            ("favicon.ico", b"ico".to_vec()),
+        ])
+    }
+
+    fn bundle(html: &str, max_inline_bytes: u64) -> Bundle {
+        let files = site();
+        bundle_page("post.html", html, &|path| files.get(path).cloned(), max_inline_bytes)
+    }
+
+    #[test]
+    fn test_inlines_stylesheets_and_css_urls() {
+        let out = bundle("<head><link rel=\"stylesheet\" href=\"/css/site.css\"></head>", 1024);
+        assert!(out.html.contains("<style>\np { margin: 0 }\nbody { background: url(\"data:image/png;base64,cG5n\") }\n</style>"));
+        assert!(out.html.contains(BUNDLE_CSP));
+        assert!(out.skipped.is_empty());
+    }
+
+    #[test]
+    fn test_inlines_images_under_cap() {
+        let html = "<head><meta http-equiv=\"Content-Security-Policy\" content=\"default-src 'none'\">\
+                    <link rel=\"icon\" href=\"/favicon.ico\"></head>\
+                    <img src=\"img/bg.png\" srcset=\"img/bg-2x.png 2x\"><img src='img/photo.jpg'><a href=\"/about.html\">";
+        let out = bundle(html, 16);
+        assert!(out.html.contains("<img src=\"data:image/png;base64,cG5n\">"));
+        assert!(out.html.contains("href=\"data:image/x-icon;base64,aWNv\""));
+        assert!(out.html.contains("<img src='img/photo.jpg'>"));
+        assert!(out.html.contains("<a href=\"/about.html\">"));
+        assert!(!out.html.contains("default-src 'none'\">"));
+        assert_eq!(out.skipped, ["img/photo.jpg"]);
+    }
+}
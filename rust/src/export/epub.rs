@@ -0,0 +1,528 @@
+//! EPUB3 export of posts and series from their sanitized HTML
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use super::mime_type;
+use super::zip::ZipWriter;
+use crate::security::escape_html;
+use crate::slug::slugify;
+use crate::svg::sanitize_svg;
+use crate::{i18n, links, reproducible, Config, Post};
+
+const CONTAINER_XML: &str = concat!(
+    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+    "<container version=\"1.0\" xmlns=\"urn:oasis:names:tc:opendocument:xmlns:container\">\n",
+    "<rootfiles><rootfile full-path=\"OEBPS/content.opf\" media-type=\"application/oebps-package+xml\"/></rootfiles>\n",
+    "</container>\n",
+);
+
+/// Characters per line on the generated cover
+const COVER_LINE: usize = 22;
+
+/// HTML void elements, which XHTML requires to be self-closed
+static VOID_ELEMENT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)<(area|br|col|embed|hr|img|input|source|track|wbr)\b([^>]*?)\s*/?>").unwrap());
+
+static IMG_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<img\b[^>]*>").unwrap());
+
+static SRC_ATTRIBUTE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(?i)\ssrc\s*=\s*["']([^"']*)["']"#).unwrap());
+
+static ALT_ATTRIBUTE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(?i)\salt\s*=\s*["']([^"']*)["']"#).unwrap());
+
+static HREF_ATTRIBUTE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)(\shref\s*=\s*)["']([^"']*)["']"#).unwrap());
+
+/// EPUB settings
+#[derive(Debug, Clone, Deserialize)]
+pub struct EpubConfig {
+    /// Cover image (PNG, JPEG or SVG); a title cover is generated when absent
+    #[serde(default)]
+    pub cover: Option<PathBuf>,
+    /// Fonts embedded in the book, the first one used for body text
+    #[serde(default)]
+    pub fonts: Vec<PathBuf>,
+    /// Book language (BCP 47 tag)
+    #[serde(default = "default_language")]
+    pub language: String,
+}
+
+impl Default for EpubConfig {
+    fn default() -> Self {
+        Self {
+            cover: None,
+            fonts: Vec::new(),
+            language: default_language(),
+        }
+    }
+}
+
+fn default_language() -> String {
+    "en".to_string()
+}
+
+/// A book assembled from posts
+pub struct Book<'a> {
+    /// Book title
+    pub title: String,
+    /// Author name
+    pub author: String,
+    /// Book language
+    pub language: String,
+    /// Site URL, for links to pages outside the book
+    pub url: String,
+    /// Chapters in reading order
+    pub posts: Vec<&'a Post>,
+    /// Cover image (file name, content)
+    pub cover: Option<(String, Vec<u8>)>,
+    /// Embedded fonts (file name, content)
+    pub fonts: Vec<(String, Vec<u8>)>,
+    /// Timestamp of every package entry (seconds since the epoch)
+    pub mtime: u64,
+}
+
+/// A file in the package besides the chapters
+struct Resource {
+    id: String,
+    href: String,
+    mime: &'static str,
+    content: Vec<u8>,
+}
+
+/// Turn serialized HTML into well-formed XHTML
+pub fn xhtml(html: &str) -> String {
+    VOID_ELEMENT.replace_all(html, "<$1$2 />").replace("&nbsp;", "&#160;")
+}
+
+fn chapter_file(index: usize) -> String {
+    format!("chapter-{:03}.xhtml", index + 1)
+}
+
+fn file_extension(name: &str) -> String {
+    Path::new(name)
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+/// Break `text` into lines of about `width` characters
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + word.chars().count() < width => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+    lines
+}
+
+fn xhtml_document(title: &str, language: &str, body: &str) -> String {
+    format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n",
+            "<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" ",
+            "lang=\"{lang}\" xml:lang=\"{lang}\">\n",
+            "<head>\n<meta charset=\"utf-8\" />\n<title>{title}</title>\n",
+            "<link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\" />\n</head>\n",
+            "<body>\n{body}\n</body>\n</html>\n",
+        ),
+        lang = escape_html(language),
+        title = escape_html(title),
+        body = body,
+    )
+}
+
+impl Book<'_> {
+    /// Stable identifier derived from the included posts
+    fn identifier(&self) -> String {
+        let mut digest = Sha256::new();
+        for post in &self.posts {
+            digest.update(post.meta.slug.as_bytes());
+            digest.update(post.hash.as_bytes());
+        }
+        format!("urn:sha256:{:x}", digest.finalize())
+    }
+
+    fn cover_svg(&self) -> String {
+        let mut svg = String::from(concat!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 600 800\" width=\"600\" height=\"800\">\n",
+            "<rect width=\"600\" height=\"800\" fill=\"#0a0a0a\"/>\n",
+        ));
+        let mut y = 320;
+        for line in wrap(&self.title, COVER_LINE) {
+            let _ = writeln!(
+                svg,
+                "<text x=\"300\" y=\"{y}\" fill=\"#ffffff\" font-family=\"serif\" font-size=\"40\" text-anchor=\"middle\">{}</text>",
+                escape_html(&line)
+            );
+            y += 52;
+        }
+        let _ = writeln!(
+            svg,
+            "<text x=\"300\" y=\"{}\" fill=\"#bbbbbb\" font-family=\"serif\" font-size=\"24\" text-anchor=\"middle\">{}</text>\n</svg>",
+            y + 40,
+            escape_html(&self.author)
+        );
+        svg
+    }
+
+    fn stylesheet(fonts: &[Resource]) -> String {
+        let mut css = String::new();
+        let mut families = Vec::new();
+        for font in fonts {
+            let family = Path::new(&font.href).file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+            let _ = writeln!(css, "@font-face {{ font-family: \"{family}\"; src: url(\"{}\"); }}", font.href);
+            families.push(family);
+        }
+        let body_font = families.first().map_or_else(String::new, |family| format!("\"{family}\", "));
+        let _ = write!(
+            css,
+            concat!(
+                "body {{ font-family: {}serif; line-height: 1.5; }}\n",
+                "img {{ max-width: 100%; }}\npre {{ white-space: pre-wrap; }}\n",
+                ".cover {{ margin: 0; text-align: center; }}\n.cover img {{ max-height: 100%; }}\n",
+            ),
+            body_font
+        );
+        css
+    }
+
+    /// One chapter with images packaged and links pointed at chapters or the site
+    fn chapter(&self, post: &Post, chapters: &HashMap<String, String>, images: &mut ImageSet<'_>) -> String {
+        let page = post.path();
+        let html = IMG_TAG.replace_all(&post.html, |cap: &Captures<'_>| {
+            let tag = &cap[0];
+            let packaged = SRC_ATTRIBUTE
+                .captures(tag)
+                .and_then(|src| images.add(&page, &src[1]));
+            // Remote or missing images are not allowed in the package, keep their alt text
+            packaged.map_or_else(
+                || ALT_ATTRIBUTE.captures(tag).map(|alt| alt[1].to_string()).unwrap_or_default(),
+                |href| SRC_ATTRIBUTE.replace(tag, format!(" src=\"{href}\"").as_str()).into_owned(),
+            )
+        });
+        let html = HREF_ATTRIBUTE.replace_all(&html, |cap: &Captures<'_>| {
+            let href = &cap[2];
+            let internal = links::resolve(&page, href).filter(|_| !href.starts_with('#'));
+            let rewritten = internal.map_or_else(
+                || href.to_string(),
+                |(target, fragment)| {
+                    let fragment = if fragment.is_empty() { String::new() } else { format!("#{fragment}") };
+                    chapters.get(&target).map_or_else(
+                        || format!("{}/{target}{fragment}", self.url.trim_end_matches('/')),
+                        |file| format!("{file}{fragment}"),
+                    )
+                },
+            );
+            format!("{}\"{}\"", &cap[1], rewritten)
+        });
+
+        let body = format!(
+            "<article>\n<h1>{}</h1>\n<p class=\"date\">{}</p>\n{}\n</article>",
+            escape_html(&post.meta.title),
+            i18n::time_element(&post.meta.date, &self.language, None),
+            xhtml(&html)
+        );
+        xhtml_document(&post.meta.title, &self.language, &body)
+    }
+
+    fn nav(&self) -> String {
+        let mut items = String::new();
+        for (index, post) in self.posts.iter().enumerate() {
+            let _ = writeln!(
+                items,
+                "<li><a href=\"{}\">{}</a></li>",
+                chapter_file(index),
+                escape_html(&post.meta.title)
+            );
+        }
+        let body = format!("<nav epub:type=\"toc\" id=\"toc\">\n<h1>Contents</h1>\n<ol>\n{items}</ol>\n</nav>");
+        xhtml_document("Contents", &self.language, &body)
+    }
+
+    fn package(&self, resources: &[Resource]) -> String {
+        let modified = self
+            .posts
+            .iter()
+            .map(|p| p.modified())
+            .max()
+            .unwrap_or_default()
+            .format("%Y-%m-%dT%H:%M:%SZ");
+
+        let mut manifest = String::from(
+            "<item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n",
+        );
+        let mut spine = String::from("<itemref idref=\"cover\"/>\n<itemref idref=\"nav\"/>\n");
+        for index in 0..self.posts.len() {
+            let file = chapter_file(index);
+            let id = file.trim_end_matches(".xhtml");
+            let _ = writeln!(manifest, "<item id=\"{id}\" href=\"{file}\" media-type=\"application/xhtml+xml\"/>");
+            let _ = writeln!(spine, "<itemref idref=\"{id}\"/>");
+        }
+        for resource in resources {
+            let properties = if resource.id == "cover-image" { " properties=\"cover-image\"" } else { "" };
+            let _ = writeln!(
+                manifest,
+                "<item id=\"{}\" href=\"{}\" media-type=\"{}\"{properties}/>",
+                resource.id, resource.href, resource.mime
+            );
+        }
+
+        format!(
+            concat!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+                "<package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"book-id\" xml:lang=\"{lang}\">\n",
+                "<metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n",
+                "<dc:identifier id=\"book-id\">{id}</dc:identifier>\n<dc:title>{title}</dc:title>\n",
+                "<dc:creator>{author}</dc:creator>\n<dc:language>{lang}</dc:language>\n",
+                "<meta property=\"dcterms:modified\">{modified}</meta>\n<meta name=\"cover\" content=\"cover-image\"/>\n",
+                "</metadata>\n<manifest>\n{manifest}</manifest>\n<spine>\n{spine}</spine>\n</package>\n",
+            ),
+            lang = escape_html(&self.language),
+            id = self.identifier(),
+            title = escape_html(&self.title),
+            author = escape_html(&self.author),
+            modified = modified,
+            manifest = manifest,
+            spine = spine,
+        )
+    }
+
+    /// Package the book; `read` returns a built site file by its output-relative path
+    pub fn to_epub(&self, read: &dyn Fn(&str) -> Option<Vec<u8>>) -> Result<Vec<u8>> {
+        if self.posts.is_empty() {
+            anyhow::bail!("No posts to export");
+        }
+
+        let chapters: HashMap<String, String> = self
+            .posts
+            .iter()
+            .enumerate()
+            .map(|(index, post)| (post.path(), chapter_file(index)))
+            .collect();
+        let mut images = ImageSet { read, resources: Vec::new(), by_path: HashMap::new() };
+        let documents: Vec<String> = self.posts.iter().map(|post| self.chapter(post, &chapters, &mut images)).collect();
+
+        let (cover_name, cover) = match &self.cover {
+            Some((name, content)) => (name.clone(), content.clone()),
+            None => ("cover.svg".to_string(), self.cover_svg().into_bytes()),
+        };
+        let cover_href = format!("cover.{}", file_extension(&cover_name));
+        let cover_mime = mime_type(&cover_href)
+            .filter(|mime| mime.starts_with("image/"))
+            .with_context(|| format!("Unsupported cover image: {cover_name}"))?;
+        let cover = if cover_mime == "image/svg+xml" && self.cover.is_some() {
+            sanitize_svg(&String::from_utf8_lossy(&cover)).into_bytes()
+        } else {
+            cover
+        };
+        let cover_page = xhtml_document(
+            &self.title,
+            &self.language,
+            &format!("<section class=\"cover\" epub:type=\"cover\"><img src=\"{cover_href}\" alt=\"{}\" /></section>", escape_html(&self.title)),
+        );
+
+        let mut fonts = Vec::new();
+        for (index, (name, content)) in self.fonts.iter().enumerate() {
+            let href = format!("fonts/{}", Path::new(name).file_name().and_then(|s| s.to_str()).unwrap_or(name));
+            let mime = mime_type(&href)
+                .filter(|mime| mime.starts_with("font/"))
+                .with_context(|| format!("Unsupported font: {name}"))?;
+            fonts.push(Resource { id: format!("font-{}", index + 1), href, mime, content: content.clone() });
+        }
+
+        let mut resources = vec![
+            Resource { id: "cover".to_string(), href: "cover.xhtml".to_string(), mime: "application/xhtml+xml", content: cover_page.into_bytes() },
+            Resource { id: "cover-image".to_string(), href: cover_href, mime: cover_mime, content: cover },
+            Resource { id: "style".to_string(), href: "style.css".to_string(), mime: "text/css", content: Self::stylesheet(&fonts).into_bytes() },
+        ];
+        resources.extend(images.resources);
+        resources.extend(fonts);
+
+        // `mimetype` must come first and uncompressed for readers to detect the format
+        let mut zip = ZipWriter::new(self.mtime);
+        zip.add("mimetype", b"application/epub+zip", true)?;
+        zip.add("META-INF/container.xml", CONTAINER_XML.as_bytes(), false)?;
+        zip.add("OEBPS/content.opf", self.package(&resources).as_bytes(), false)?;
+        zip.add("OEBPS/nav.xhtml", self.nav().as_bytes(), false)?;
+        for (index, document) in documents.iter().enumerate() {
+            zip.add(&format!("OEBPS/{}", chapter_file(index)), document.as_bytes(), false)?;
+        }
+        for resource in &resources {
+            zip.add(&format!("OEBPS/{}", resource.href), &resource.content, false)?;
+        }
+        zip.finish()
+    }
+}
+
+/// Images referenced by chapters, packaged once each
+struct ImageSet<'a> {
+    read: &'a dyn Fn(&str) -> Option<Vec<u8>>,
+    resources: Vec<Resource>,
+    by_path: HashMap<String, String>,
+}
+
+impl ImageSet<'_> {
+    /// Package the image `src` of `page`, returning its href in the book
+    fn add(&mut self, page: &str, src: &str) -> Option<String> {
+        let (target, _) = links::resolve(page, src)?;
+        if let Some(href) = self.by_path.get(&target) {
+            return Some(href.clone());
+        }
+        let Some(mime) = mime_type(&target).filter(|mime| mime.starts_with("image/")) else {
+            warn!("{page}: not an image: {src}");
+            return None;
+        };
+        let Some(content) = (self.read)(&target) else {
+            warn!("{page}: image not found in output: {src}");
+            return None;
+        };
+        let content = if mime == "image/svg+xml" {
+            sanitize_svg(&String::from_utf8_lossy(&content)).into_bytes()
+        } else {
+            content
+        };
+
+        let number = self.resources.len() + 1;
+        let href = format!("images/{number:03}.{}", file_extension(&target));
+        self.resources.push(Resource { id: format!("image-{number:03}"), href: href.clone(), mime, content });
+        self.by_path.insert(target, href.clone());
+        Some(href)
+    }
+}
+
+/// Write `<export>/<title>.epub` from `posts` in reading order
+pub fn export(config: &Config, title: &str, posts: Vec<&Post>) -> Result<PathBuf> {
+    let epub = &config.export.epub;
+    let cover = match &epub.cover {
+        Some(path) => Some((
+            path.display().to_string(),
+            fs::read(path).with_context(|| format!("Failed to read cover: {}", path.display()))?,
+        )),
+        None => None,
+    };
+    let fonts = epub
+        .fonts
+        .iter()
+        .map(|path| {
+            let content = fs::read(path).with_context(|| format!("Failed to read font: {}", path.display()))?;
+            Ok((path.display().to_string(), content))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let book = Book {
+        title: title.to_string(),
+        author: config.author.clone(),
+        language: epub.language.clone(),
+        url: config.url.clone(),
+        posts,
+        cover,
+        fonts,
+        mtime: reproducible::source_date_epoch()?,
+    };
+    let content = book.to_epub(&|path| fs::read(config.output.join(path)).ok())?;
+
+    fs::create_dir_all(&config.export.output)
+        .with_context(|| format!("Failed to create {}", config.export.output.display()))?;
+    let path = config.export.output.join(format!("{}.epub", slugify(title)));
+    fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+    reproducible::normalize_file(&path, book.mtime)?;
+    info!("📚 {} chapters -> {}", book.posts.len(), path.display());
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PostMeta;
+    use chrono::{TimeZone, Utc};
+
+    fn post(slug: &str, html: &str) -> Post {
+        Post {
+            meta: PostMeta {
+                title: format!("{slug} & more"),
+                slug: slug.to_string(),
+                date: Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap(),
+                ..PostMeta::default()
+            },
+            html: html.to_string(),
+            hash: "abc".to_string(),
+            ..Post::default()
+        }
+    }
+
+    fn book(posts: Vec<&Post>) -> Book<'_> {
+        Book {
+            title: "Kernel bugs".to_string(),
+            author: "Ada".to_string(),
+            language: "en".to_string(),
+            url: "https://example.com/".to_string(),
+            posts,
+            cover: None,
+            fonts: vec![("fonts/Body.woff2".to_string(), b"wOF2".to_vec())],
+            mtime: 0,
+        }
+    }
+
+    #[test]
+    fn test_xhtml_self_closes_void_elements() {
+        assert_eq!(xhtml("<p>a<br>b&nbsp;<img src=\"x.png\" alt=\"\"></p><hr/>"), "<p>a<br />b&#160;<img src=\"x.png\" alt=\"\" /></p><hr />");
+    }
+
+    #[test]
+    fn test_chapter_packages_images_and_rewrites_links() {
+        let first = post("first", "<p><img src=\"/img/a.png\" alt=\"A\"><img src=\"https://cdn.example/b.png\" alt=\"remote\"></p>");
+        let second = post("second", "<p><a href=\"first.html#intro\">back</a> <a href=\"/about.html\">about</a> <a href=\"#x\">x</a></p>");
+        let book = book(vec![&first, &second]);
+        let chapters = HashMap::from([("first.html".to_string(), chapter_file(0)), ("second.html".to_string(), chapter_file(1))]);
+        let read = |path: &str| (path == "img/a.png").then(|| b"png".to_vec());
+        let mut images = ImageSet { read: &read, resources: Vec::new(), by_path: HashMap::new() };
+
+        let one = book.chapter(&first, &chapters, &mut images);
+        assert!(one.contains("<img src=\"images/001.png\" alt=\"A\" />remote</p>"));
+        assert!(one.contains("<h1>first &amp; more</h1>"));
+        let two = book.chapter(&second, &chapters, &mut images);
+        assert!(two.contains("href=\"chapter-001.xhtml#intro\""));
+        assert!(two.contains("href=\"https://example.com/about.html\""));
+        assert!(two.contains("href=\"#x\""));
+        assert_eq!(images.resources.len(), 1);
+    }
+
+    #[test]
+    fn test_to_epub_package() {
+        let first = post("first", "<p>Hello</p>");
+        let epub = book(vec![&first]).to_epub(&|_| None).unwrap();
+        assert_eq!(&epub[30..58], b"mimetypeapplication/epub+zip");
+
+        let package = book(vec![&first]).package(&[]);
+        assert!(package.contains("<dc:identifier id=\"book-id\">urn:sha256:"));
+        assert!(package.contains("<meta property=\"dcterms:modified\">2024-01-02T00:00:00Z</meta>"));
+        assert!(package.contains("<itemref idref=\"chapter-001\"/>"));
+        assert!(book(Vec::new()).to_epub(&|_| None).is_err());
+    }
+
+    #[test]
+    fn test_stylesheet_and_cover() {
+        let first = post("first", "");
+        let book = book(vec![&first]);
+        let font = Resource { id: "font-1".to_string(), href: "fonts/Body.woff2".to_string(), mime: "font/woff2", content: Vec::new() };
+        let css = Book::stylesheet(&[font]);
+        assert!(css.contains("@font-face { font-family: \"Body\"; src: url(\"fonts/Body.woff2\"); }"));
+        assert!(css.contains("body { font-family: \"Body\", serif;"));
+        assert_eq!(wrap("Notes on a very long series title", 12), ["Notes on a", "very long", "series title"]);
+        assert!(book.cover_svg().contains(">Kernel bugs</text>"));
+    }
+}
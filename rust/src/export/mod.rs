@@ -0,0 +1,163 @@
+//! Offline exports of the built site
+
+pub mod archive;
+pub mod bundle;
+pub mod epub;
+pub mod tar;
+pub mod zip;
+
+use anyhow::Result;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::Utc;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use crate::status::{self, Disposition};
+use crate::svg::sanitize_svg;
+use crate::Post;
+
+/// Export settings
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExportConfig {
+    /// Directory exports are written to
+    #[serde(default = "default_output")]
+    pub output: PathBuf,
+    /// Largest asset inlined as a `data:` URI; bigger files are left out
+    #[serde(default = "default_max_inline_bytes")]
+    pub max_inline_bytes: u64,
+    /// Cover, fonts and language of `export epub` books
+    #[serde(default)]
+    pub epub: epub::EpubConfig,
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        Self {
+            output: default_output(),
+            max_inline_bytes: default_max_inline_bytes(),
+            epub: epub::EpubConfig::default(),
+        }
+    }
+}
+
+fn default_output() -> PathBuf {
+    PathBuf::from("dist-export")
+}
+
+const fn default_max_inline_bytes() -> u64 {
+    256 * 1024
+}
+
+/// Published posts matching `target` (a slug or `all`); protected posts are never selected
+pub fn select<'a>(posts: &'a [Post], target: &str) -> Result<Vec<&'a Post>> {
+    let now = Utc::now();
+    let published: Vec<&Post> = posts
+        .iter()
+        .filter(|p| status::disposition(&p.meta, now) == Disposition::Published)
+        .collect();
+
+    if target == "all" {
+        return Ok(published.into_iter().filter(|p| !p.meta.protected).collect());
+    }
+    match published.into_iter().find(|p| p.meta.slug == target) {
+        // A file carries the body past the web server's auth
+        Some(post) if post.meta.protected => anyhow::bail!("'{target}' is protected and is never exported"),
+        Some(post) => Ok(vec![post]),
+        None => anyhow::bail!("No published post with slug '{target}'"),
+    }
+}
+
+/// Published posts of a series, oldest first
+pub fn select_series<'a>(posts: &'a [Post], series: &str) -> Result<Vec<&'a Post>> {
+    let mut selected: Vec<&Post> = select(posts, "all")?
+        .into_iter()
+        .filter(|p| p.meta.series.as_deref() == Some(series))
+        .collect();
+    if selected.is_empty() {
+        anyhow::bail!("No published posts in series '{series}'");
+    }
+    selected.sort_by_key(|p| p.meta.date);
+    Ok(selected)
+}
+
+/// MIME type of an asset that may be embedded, by extension
+pub fn mime_type(path: &str) -> Option<&'static str> {
+    let ext = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+    Some(match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "woff2" => "font/woff2",
+        "woff" => "font/woff",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "mp3" => "audio/mpeg",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => return None,
+    })
+}
+
+/// `data:` URI for an asset (SVG is sanitized first)
+pub fn data_uri(path: &str, content: &[u8]) -> Option<String> {
+    let mime = mime_type(path)?;
+    let encoded = if mime == "image/svg+xml" {
+        STANDARD.encode(sanitize_svg(&String::from_utf8_lossy(content)))
+    } else {
+        STANDARD.encode(content)
+    };
+    Some(format!("data:{mime};base64,{encoded}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::status::PostStatus;
+    use crate::PostMeta;
+
+    fn post(slug: &str, status: PostStatus) -> Post {
+        Post {
+            meta: PostMeta {
+                slug: slug.to_string(),
+                status,
+                ..PostMeta::default()
+            },
+            ..Post::default()
+        }
+    }
+
+    #[test]
+    fn test_select_only_published() {
+        let posts = [post("live", PostStatus::Published), post("wip", PostStatus::Draft)];
+        assert_eq!(select(&posts, "all").unwrap().len(), 1);
+        assert_eq!(select(&posts, "live").unwrap()[0].meta.slug, "live");
+        assert!(select(&posts, "wip").is_err());
+    }
+
+    #[test]
+    fn test_select_series_oldest_first() {
+        let mut posts = vec![post("part-2", PostStatus::Published), post("part-1", PostStatus::Published), post("other", PostStatus::Published)];
+        posts[0].meta.date = chrono::DateTime::from_timestamp(200, 0).unwrap();
+        posts[1].meta.date = chrono::DateTime::from_timestamp(100, 0).unwrap();
+        for post in &mut posts[..2] {
+            post.meta.series = Some("Fuzzing".to_string());
+        }
+        let slugs: Vec<&str> = select_series(&posts, "Fuzzing").unwrap().iter().map(|p| p.meta.slug.as_str()).collect();
+        assert_eq!(slugs, ["part-1", "part-2"]);
+        assert!(select_series(&posts, "Missing").is_err());
+    }
+
+    #[test]
+    fn test_data_uri() {
+        assert_eq!(data_uri("img/a.PNG", b"abc").unwrap(), "data:image/png;base64,YWJj");
+        assert!(data_uri("page.html", b"<p>").is_none());
+        let svg = data_uri("x.svg", b"<svg><script>alert(1)</script></svg>").unwrap();
+        let decoded = STANDARD.decode(svg.trim_start_matches("data:image/svg+xml;base64,")).unwrap();
+        assert!(!String::from_utf8(decoded).unwrap().contains("script"));
+    }
+}
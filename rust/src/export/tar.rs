@@ -0,0 +1,183 @@
+//! Minimal deterministic ustar writer and reader (regular files and directories only)
+
+use anyhow::{Context, Result};
+
+const BLOCK: usize = 512;
+
+/// Tar archive built in memory with zeroed owners and a fixed timestamp
+#[derive(Default)]
+pub struct TarWriter {
+    data: Vec<u8>,
+    mtime: u64,
+}
+
+/// Octal numeric field, NUL-terminated
+fn octal(field: &mut [u8], value: u64) {
+    let width = field.len() - 1;
+    field[..width].copy_from_slice(format!("{value:0width$o}").as_bytes());
+}
+
+/// Split `path` into ustar `prefix` and `name` fields
+fn split_path(path: &str) -> Result<(&str, &str)> {
+    if path.len() <= 100 {
+        return Ok(("", path));
+    }
+    path.char_indices()
+        .filter(|&(i, c)| c == '/' && i <= 155 && path.len() - i - 1 <= 100)
+        .map(|(i, _)| (&path[..i], &path[i + 1..]))
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Path too long for a tar archive: {path}"))
+}
+
+impl TarWriter {
+    /// Archive whose entries all carry `mtime` (seconds since the epoch)
+    pub fn new(mtime: u64) -> Self {
+        Self { data: Vec::new(), mtime }
+    }
+
+    fn header(&mut self, path: &str, mode: u64, size: u64, kind: u8) -> Result<()> {
+        let (prefix, name) = split_path(path)?;
+        let mut header = [0_u8; BLOCK];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        octal(&mut header[100..108], mode);
+        octal(&mut header[108..116], 0);
+        octal(&mut header[116..124], 0);
+        octal(&mut header[124..136], size);
+        octal(&mut header[136..148], self.mtime);
+        header[156] = kind;
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+
+        // Checksum is computed with its own field filled with spaces
+        header[148..156].copy_from_slice(b"        ");
+        let checksum: u64 = header.iter().map(|&b| u64::from(b)).sum();
+        header[148..156].copy_from_slice(format!("{checksum:06o}\0 ").as_bytes());
+
+        self.data.extend_from_slice(&header);
+        Ok(())
+    }
+
+    /// Add a directory entry (`path` without trailing slash)
+    pub fn add_dir(&mut self, path: &str) -> Result<()> {
+        self.header(&format!("{path}/"), 0o755, 0, b'5')
+    }
+
+    /// Add a regular file
+    pub fn add_file(&mut self, path: &str, content: &[u8]) -> Result<()> {
+        self.header(path, 0o644, content.len() as u64, b'0')?;
+        self.data.extend_from_slice(content);
+        let padding = (BLOCK - content.len() % BLOCK) % BLOCK;
+        self.data.resize(self.data.len() + padding, 0);
+        Ok(())
+    }
+
+    /// Bytes written since the last call, for streaming the archive as it grows
+    pub fn drain(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.data)
+    }
+
+    /// Append the end-of-archive marker and return the archive bytes
+    pub fn finish(mut self) -> Vec<u8> {
+        self.data.resize(self.data.len() + 2 * BLOCK, 0);
+        self.data
+    }
+}
+
+/// NUL-terminated text field
+fn text(field: &[u8]) -> &[u8] {
+    field.split(|&b| b == 0).next().unwrap_or_default()
+}
+
+/// Regular files of a ustar archive as `(path, content)`, in archive order
+///
+/// Directories are skipped; links, devices and pax or GNU extension headers are rejected.
+pub fn read_files(data: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut files = Vec::new();
+    let mut offset = 0;
+    while offset + BLOCK <= data.len() {
+        let header = &data[offset..offset + BLOCK];
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        let field = |range: std::ops::Range<usize>| std::str::from_utf8(text(&header[range])).unwrap_or("");
+        let checksum = u64::from_str_radix(field(148..156).trim(), 8).context("Invalid tar header checksum")?;
+        let actual: u64 = header
+            .iter()
+            .enumerate()
+            .map(|(i, &b)| if (148..156).contains(&i) { u64::from(b' ') } else { u64::from(b) })
+            .sum();
+        if checksum != actual {
+            anyhow::bail!("Corrupt tar header at byte {offset}");
+        }
+        let (prefix, name) = (field(345..500), field(0..100));
+        let path = if prefix.is_empty() { name.to_string() } else { format!("{prefix}/{name}") };
+        let size = u64::from_str_radix(field(124..136).trim(), 8).with_context(|| format!("Invalid size of {path}"))?;
+        let size = usize::try_from(size)?;
+        let start = offset + BLOCK;
+        let end = start.checked_add(size).filter(|&end| end <= data.len());
+        let end = end.with_context(|| format!("Truncated tar entry {path}"))?;
+        match header[156] {
+            b'0' | 0 => files.push((path, data[start..end].to_vec())),
+            b'5' => {}
+            kind => anyhow::bail!("Unsupported tar entry type '{}' for {path}", kind as char),
+        }
+        offset = start + size.div_ceil(BLOCK) * BLOCK;
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_header_and_padding() {
+        let mut tar = TarWriter::default();
+        tar.add_file("site/index.html", b"hello").unwrap();
+        let data = tar.finish();
+        assert_eq!(data.len(), 4 * BLOCK);
+        assert_eq!(&data[..15], b"site/index.html");
+        assert_eq!(&data[124..136], b"00000000005\0");
+        assert_eq!(&data[257..265], b"ustar\x0000");
+        assert_eq!(&data[BLOCK..BLOCK + 5], b"hello");
+        assert_eq!(&data[136..148], b"00000000000\0");
+
+        let checksum: u64 = data[..BLOCK]
+            .iter()
+            .enumerate()
+            .map(|(i, &b)| if (148..156).contains(&i) { u64::from(b' ') } else { u64::from(b) })
+            .sum();
+        assert_eq!(&data[148..154], format!("{checksum:06o}").as_bytes());
+    }
+
+    #[test]
+    fn test_mtime_field() {
+        let mut tar = TarWriter::new(1_700_000_000);
+        tar.add_dir("site").unwrap();
+        assert_eq!(&tar.finish()[136..148], format!("{:011o}\0", 1_700_000_000).as_bytes());
+    }
+
+    #[test]
+    fn test_read_back_written_files() {
+        let long = format!("{}/{}", "d".repeat(120), "post.md");
+        let mut tar = TarWriter::default();
+        tar.add_dir("content").unwrap();
+        tar.add_file("content/a.md", b"hello").unwrap();
+        tar.add_file(&long, &[7; 600]).unwrap();
+        let data = tar.finish();
+        assert_eq!(read_files(&data).unwrap(), [("content/a.md".to_string(), b"hello".to_vec()), (long, vec![7; 600])]);
+
+        let mut corrupt = data.clone();
+        corrupt[BLOCK] = b'X';
+        assert!(read_files(&corrupt).is_err());
+    }
+
+    #[test]
+    fn test_long_paths_use_prefix() {
+        let path = format!("{}/{}", "d".repeat(120), "f".repeat(90));
+        let (prefix, name) = split_path(&path).unwrap();
+        assert_eq!((prefix.len(), name.len()), (120, 90));
+        assert!(split_path(&"x".repeat(300)).is_err());
+    }
+}
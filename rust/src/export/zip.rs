@@ -0,0 +1,182 @@
+//! Minimal deterministic ZIP writer (stored and deflated entries, fixed timestamps)
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Timelike};
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+use std::io::Write;
+
+/// MS-DOS date of 1980-01-01, the earliest representable
+const DOS_EPOCH: u16 = (1 << 5) | 1;
+
+/// General purpose flag: names are UTF-8
+const UTF8_NAMES: u16 = 1 << 11;
+
+struct Entry {
+    name: String,
+    method: u16,
+    crc: u32,
+    compressed: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// ZIP archive built in memory; entries keep the order they were added in
+pub struct ZipWriter {
+    data: Vec<u8>,
+    entries: Vec<Entry>,
+    time: u16,
+    date: u16,
+}
+
+impl Default for ZipWriter {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+fn u32_len(len: usize) -> Result<u32> {
+    u32::try_from(len).context("Archive exceeds the ZIP32 size limit")
+}
+
+/// MS-DOS (time, date) of a Unix timestamp, clamped to 1980-01-01
+///
+/// DOS times have two-second resolution and no time zone; UTC is used.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn dos_datetime(epoch: u64) -> (u16, u16) {
+    let at = i64::try_from(epoch).ok().and_then(|secs| DateTime::from_timestamp(secs, 0));
+    match at {
+        // Every field is range-checked by chrono or the year bounds, so the casts are lossless
+        Some(at) if (1980..=2107).contains(&at.year()) => (
+            ((at.hour() << 11) | (at.minute() << 5) | (at.second() / 2)) as u16,
+            (((at.year() - 1980) as u32) << 9 | (at.month() << 5) | at.day()) as u16,
+        ),
+        _ => (0, DOS_EPOCH),
+    }
+}
+
+impl ZipWriter {
+    /// Archive whose entries all carry `mtime` (seconds since the epoch)
+    pub fn new(mtime: u64) -> Self {
+        let (time, date) = dos_datetime(mtime);
+        Self { data: Vec::new(), entries: Vec::new(), time, date }
+    }
+
+    /// Add a file, deflated unless `store` is set
+    pub fn add(&mut self, name: &str, content: &[u8], store: bool) -> Result<()> {
+        let mut crc = Crc::new();
+        crc.update(content);
+
+        let (method, body) = if store {
+            (0, content.to_vec())
+        } else {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+            encoder.write_all(content)?;
+            (8, encoder.finish()?)
+        };
+
+        let entry = Entry {
+            name: name.to_string(),
+            method,
+            crc: crc.sum(),
+            compressed: u32_len(body.len())?,
+            size: u32_len(content.len())?,
+            offset: u32_len(self.data.len())?,
+        };
+        let name_len = u16::try_from(name.len()).context("ZIP entry name too long")?;
+
+        self.data.extend_from_slice(&0x0403_4b50_u32.to_le_bytes());
+        for field in [20, UTF8_NAMES, entry.method, self.time, self.date] {
+            self.data.extend_from_slice(&field.to_le_bytes());
+        }
+        for field in [entry.crc, entry.compressed, entry.size] {
+            self.data.extend_from_slice(&field.to_le_bytes());
+        }
+        self.data.extend_from_slice(&name_len.to_le_bytes());
+        self.data.extend_from_slice(&0_u16.to_le_bytes());
+        self.data.extend_from_slice(name.as_bytes());
+        self.data.extend_from_slice(&body);
+
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Append the central directory and return the archive bytes
+    pub fn finish(mut self) -> Result<Vec<u8>> {
+        let directory_offset = u32_len(self.data.len())?;
+        let count = u16::try_from(self.entries.len()).context("Too many ZIP entries")?;
+
+        for entry in &self.entries {
+            self.data.extend_from_slice(&0x0201_4b50_u32.to_le_bytes());
+            for field in [20, 20, UTF8_NAMES, entry.method, self.time, self.date] {
+                self.data.extend_from_slice(&field.to_le_bytes());
+            }
+            for field in [entry.crc, entry.compressed, entry.size] {
+                self.data.extend_from_slice(&field.to_le_bytes());
+            }
+            // Name length, then empty extra field, comment, disk number and attributes
+            let name_len = u16::try_from(entry.name.len())?;
+            for field in [name_len, 0, 0, 0, 0] {
+                self.data.extend_from_slice(&field.to_le_bytes());
+            }
+            self.data.extend_from_slice(&0_u32.to_le_bytes());
+            self.data.extend_from_slice(&entry.offset.to_le_bytes());
+            self.data.extend_from_slice(entry.name.as_bytes());
+        }
+
+        let directory_size = u32_len(self.data.len())? - directory_offset;
+        self.data.extend_from_slice(&0x0605_4b50_u32.to_le_bytes());
+        for field in [0, 0, count, count] {
+            self.data.extend_from_slice(&field.to_le_bytes());
+        }
+        self.data.extend_from_slice(&directory_size.to_le_bytes());
+        self.data.extend_from_slice(&directory_offset.to_le_bytes());
+        self.data.extend_from_slice(&0_u16.to_le_bytes());
+        Ok(self.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::DeflateDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn test_stored_entry_layout() {
+        let mut zip = ZipWriter::default();
+        zip.add("mimetype", b"application/epub+zip", true).unwrap();
+        let data = zip.finish().unwrap();
+        assert_eq!(&data[..4], b"PK\x03\x04");
+        assert_eq!(&data[8..10], &[0, 0]);
+        assert_eq!(&data[30..38], b"mimetype");
+        assert_eq!(&data[38..58], b"application/epub+zip");
+        assert_eq!(&data[data.len() - 22..data.len() - 18], b"PK\x05\x06");
+    }
+
+    #[test]
+    fn test_dos_datetime() {
+        assert_eq!(dos_datetime(0), (0, DOS_EPOCH));
+        // 2023-11-14 22:13:20 UTC
+        assert_eq!(dos_datetime(1_700_000_000), ((22 << 11) | (13 << 5) | 10, (43 << 9) | (11 << 5) | 14));
+        let mut zip = ZipWriter::new(1_700_000_000);
+        zip.add("a", b"", true).unwrap();
+        assert_eq!(&zip.finish().unwrap()[10..14], &[0xaa, 0xb1, 0x6e, 0x57]);
+    }
+
+    #[test]
+    fn test_deflated_entry_round_trips_and_is_deterministic() {
+        let build = || {
+            let mut zip = ZipWriter::default();
+            zip.add("a.txt", &b"hello ".repeat(50), false).unwrap();
+            zip.finish().unwrap()
+        };
+        let data = build();
+        assert_eq!(data, build());
+
+        let compressed = u32::from_le_bytes(data[18..22].try_into().unwrap()) as usize;
+        let mut out = Vec::new();
+        DeflateDecoder::new(&data[35..35 + compressed]).read_to_end(&mut out).unwrap();
+        assert_eq!(out, b"hello ".repeat(50));
+    }
+}
@@ -0,0 +1,332 @@
+//! Atom feeds (site-wide and per tag) and OPML feed list
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use crate::links;
+use crate::slug::{slugify_with, Transliteration};
+use crate::security::escape_html;
+use crate::{excerpt, robots, Config, Post};
+
+static IMAGES: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<picture\b.*?</picture>|<img\b[^>]*>|<source\b[^>]*>").unwrap());
+
+static URL_ATTRIBUTE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)(\s(?:href|src)\s*=\s*)(["'])([^"']*)["']"#).unwrap());
+
+/// How much of each post goes into a feed entry
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedContent {
+    /// The whole sanitized post as `<content>`
+    #[default]
+    Full,
+    /// The frontmatter description, or the text before `<!--more-->` or the first paragraph, as `<summary>`
+    Summary,
+}
+
+/// Feed content policy
+#[derive(Debug, Clone, Deserialize)]
+pub struct FeedsConfig {
+    /// Most recent entries per feed (all when absent)
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// Full posts or first-paragraph summaries
+    #[serde(default)]
+    pub content: FeedContent,
+    /// Keep `<img>`/`<picture>` in entries
+    #[serde(default = "default_true")]
+    pub images: bool,
+    /// Base URL for entry links and ids, and for absolutizing links in content (defaults to `url`)
+    #[serde(default)]
+    pub canonical_url: Option<String>,
+}
+
+impl Default for FeedsConfig {
+    fn default() -> Self {
+        Self {
+            limit: None,
+            content: FeedContent::default(),
+            images: true,
+            canonical_url: None,
+        }
+    }
+}
+
+const fn default_true() -> bool {
+    true
+}
+
+impl FeedsConfig {
+    /// Base URL of entry links and ids
+    pub fn base_url<'a>(&'a self, config: &'a Config) -> &'a str {
+        self.canonical_url.as_deref().unwrap_or(&config.url).trim_end_matches('/')
+    }
+}
+
+/// Resolve relative `href`/`src` in a post's HTML to absolute URLs under `base`
+pub fn absolute_urls(html: &str, page: &str, base: &str) -> String {
+    URL_ATTRIBUTE
+        .replace_all(html, |cap: &Captures<'_>| {
+            let Some((target, fragment)) = links::resolve(page, &cap[3]) else {
+                return cap[0].to_string();
+            };
+            let target = target.strip_suffix("index.html").unwrap_or(&target);
+            let fragment = if fragment.is_empty() { String::new() } else { format!("#{fragment}") };
+            format!("{}{q}{base}/{target}{fragment}{q}", &cap[1], q = &cap[2])
+        })
+        .into_owned()
+}
+
+/// Entry element (`content` or `summary`) and its HTML according to the feed policy
+pub fn entry_html(config: &Config, post: &Post) -> (&'static str, String) {
+    let feeds = &config.feeds;
+    let (element, html) = match feeds.content {
+        FeedContent::Full => ("content", post.html.clone()),
+        FeedContent::Summary => match post.meta.description.as_deref().map(str::trim).filter(|d| !d.is_empty()) {
+            Some(description) => ("summary", format!("<p>{}</p>", escape_html(description))),
+            None => ("summary", excerpt::lead(post).to_string()),
+        },
+    };
+    let html = if feeds.images { html } else { IMAGES.replace_all(&html, "").into_owned() };
+    (element, absolute_urls(&html, &post.path(), feeds.base_url(config)))
+}
+
+/// A generated feed, as listed in `feeds.opml`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeedInfo {
+    /// Feed title
+    pub title: String,
+    /// Feed path relative to the site root
+    pub path: String,
+    /// Page the feed corresponds to, relative to the site root
+    pub html_path: String,
+}
+
+/// Escape text for XML element content and attribute values
+pub fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Render an Atom feed for `posts` (expected newest first)
+pub fn atom_feed(config: &Config, feed: &FeedInfo, posts: &[&Post]) -> String {
+    let base = config.feeds.base_url(config);
+    let posts = &posts[..config.feeds.limit.map_or(posts.len(), |limit| limit.min(posts.len()))];
+    let updated = posts
+        .iter()
+        .map(|p| p.modified())
+        .max()
+        .unwrap_or(DateTime::<Utc>::UNIX_EPOCH);
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    let _ = writeln!(xml, "  <title>{}</title>", escape_xml(&feed.title));
+    let _ = writeln!(xml, "  <id>{base}/{}</id>", escape_xml(&feed.path));
+    let _ = writeln!(xml, "  <link rel=\"self\" href=\"{base}/{}\"/>", escape_xml(&feed.path));
+    let _ = writeln!(xml, "  <link rel=\"alternate\" href=\"{base}/{}\"/>", escape_xml(&feed.html_path));
+    let _ = writeln!(xml, "  <updated>{}</updated>", updated.to_rfc3339());
+    let _ = writeln!(xml, "  <author><name>{}</name></author>", escape_xml(&config.author));
+
+    for post in posts {
+        let url = escape_xml(&post.permalink(base));
+        xml.push_str("  <entry>\n");
+        let _ = writeln!(xml, "    <title>{}</title>", escape_xml(&post.meta.title));
+        let _ = writeln!(xml, "    <id>{url}</id>");
+        let _ = writeln!(xml, "    <link rel=\"alternate\" href=\"{url}\"/>");
+        let _ = writeln!(xml, "    <published>{}</published>", post.meta.date.to_rfc3339());
+        let _ = writeln!(xml, "    <updated>{}</updated>", post.modified().to_rfc3339());
+        for tag in &post.meta.tags {
+            let _ = writeln!(xml, "    <category term=\"{}\"/>", escape_xml(tag));
+        }
+        let (element, html) = entry_html(config, post);
+        let _ = writeln!(xml, "    <{element} type=\"html\">{}</{element}>", escape_xml(&html));
+        xml.push_str("  </entry>\n");
+    }
+
+    xml.push_str("</feed>\n");
+    xml
+}
+
+/// Render an OPML 2.0 subscription list
+pub fn opml(config: &Config, feeds: &[FeedInfo]) -> String {
+    let base = config.url.trim_end_matches('/');
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<opml version=\"2.0\">\n");
+    let _ = writeln!(xml, "  <head><title>{} feeds</title></head>\n  <body>", escape_xml(&config.title));
+    for feed in feeds {
+        let _ = writeln!(
+            xml,
+            "    <outline type=\"rss\" text=\"{title}\" title=\"{title}\" xmlUrl=\"{base}/{xml_url}\" htmlUrl=\"{base}/{html_url}\"/>",
+            title = escape_xml(&feed.title),
+            xml_url = escape_xml(&feed.path),
+            html_url = escape_xml(&feed.html_path),
+        );
+    }
+    xml.push_str("  </body>\n</opml>\n");
+    xml
+}
+
+/// Group published posts by tag slug, keeping the first spelling of each tag as its title
+pub fn posts_by_tag(posts: &[Post], mode: Transliteration) -> BTreeMap<String, (String, Vec<&Post>)> {
+    let mut tags: BTreeMap<String, (String, Vec<&Post>)> = BTreeMap::new();
+    for post in posts.iter().filter(|p| p.meta.status.is_public()) {
+        for tag in &post.meta.tags {
+            let slug = slugify_with(tag, mode);
+            if slug.is_empty() {
+                continue;
+            }
+            tags.entry(slug)
+                .or_insert_with(|| (tag.clone(), Vec::new()))
+                .1
+                .push(post);
+        }
+    }
+    tags
+}
+
+/// Write `atom.xml`, `tags/<tag>/atom.xml` for every tag, and `feeds.opml`
+pub fn generate(config: &Config, posts: &[Post]) -> Result<Vec<FeedInfo>> {
+    let published: Vec<&Post> =
+        posts.iter().filter(|p| p.meta.status.is_public() && robots::is_listed(&p.meta)).collect();
+
+    let mut feeds = vec![FeedInfo {
+        title: config.title.clone(),
+        path: "atom.xml".to_string(),
+        html_path: String::new(),
+    }];
+    write(&config.output, &feeds[0], &atom_feed(config, &feeds[0], &published))?;
+
+    for (slug, (tag, mut tagged)) in posts_by_tag(posts, config.slugs) {
+        tagged.retain(|p| robots::is_listed(&p.meta));
+        if tagged.is_empty() {
+            continue;
+        }
+        let feed = FeedInfo {
+            title: format!("{} - {tag}", config.title),
+            path: format!("tags/{slug}/atom.xml"),
+            html_path: format!("tags/{slug}/"),
+        };
+        write(&config.output, &feed, &atom_feed(config, &feed, &tagged))?;
+        feeds.push(feed);
+    }
+
+    let path = config.output.join("feeds.opml");
+    fs::write(&path, opml(config, &feeds))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    Ok(feeds)
+}
+
+fn write(output_dir: &Path, feed: &FeedInfo, xml: &str) -> Result<()> {
+    let path = output_dir.join(&feed.path);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, xml).with_context(|| format!("Failed to write feed: {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PostMeta;
+
+    fn post(slug: &str, tags: &[&str]) -> Post {
+        Post {
+            meta: PostMeta {
+                title: format!("Post <{slug}>"),
+                slug: slug.to_string(),
+                tags: tags.iter().map(|t| (*t).to_string()).collect(),
+                ..PostMeta::default()
+            },
+            html: "<p>Body & more</p>".to_string(),
+            ..Post::default()
+        }
+    }
+
+    #[test]
+    fn test_posts_by_tag_groups_by_slug() {
+        let posts = vec![post("a", &["Web Security", "xss"]), post("b", &["web security"])];
+        let tags = posts_by_tag(&posts, Transliteration::Unicode);
+        assert_eq!(tags.len(), 2);
+        assert_eq!(tags["web-security"].0, "Web Security");
+        assert_eq!(tags["web-security"].1.len(), 2);
+    }
+
+    #[test]
+    fn test_atom_feed_escapes_content() {
+        let config = Config::default();
+        let posts = [post("a", &["xss"])];
+        let feed = FeedInfo {
+            title: "Tag".to_string(),
+            path: "tags/xss/atom.xml".to_string(),
+            html_path: "tags/xss/".to_string(),
+        };
+        let xml = atom_feed(&config, &feed, &posts.iter().collect::<Vec<_>>());
+        assert!(xml.contains("<title>Post &lt;a&gt;</title>"));
+        assert!(xml.contains("&lt;p&gt;Body &amp; more&lt;/p&gt;"));
+        assert!(xml.contains("href=\"https://example.com/tags/xss/atom.xml\""));
+    }
+
+    #[test]
+    fn test_feed_policy_summary_limit_and_images() {
+        let config = Config {
+            feeds: FeedsConfig {
+                limit: Some(1),
+                content: FeedContent::Summary,
+                images: false,
+                canonical_url: Some("https://canonical.example/".to_string()),
+            },
+            ..Config::default()
+        };
+        let mut first = post("a", &[]);
+        first.html = "<p>Intro <img src=\"/img/x.png\"> <a href=\"b.html#top\">next</a></p><p>Rest</p>".to_string();
+        let posts = [first, post("b", &[])];
+        let feed = FeedInfo {
+            title: "Blog".to_string(),
+            path: "atom.xml".to_string(),
+            html_path: String::new(),
+        };
+        let xml = atom_feed(&config, &feed, &posts.iter().collect::<Vec<_>>());
+        assert_eq!(xml.matches("<entry>").count(), 1);
+        assert!(xml.contains("<id>https://canonical.example/a.html</id>"));
+        assert!(xml.contains("<summary type=\"html\">&lt;p&gt;Intro  &lt;a href=&quot;https://canonical.example/b.html#top&quot;&gt;next&lt;/a&gt;&lt;/p&gt;</summary>"));
+    }
+
+    #[test]
+    fn test_absolute_urls() {
+        let html = "<a href=\"/\">home</a><a href=\"#s\">s</a><a href=\"https://x.example/\">x</a>";
+        let out = absolute_urls(html, "post.html", "https://example.com");
+        assert!(out.contains("href=\"https://example.com/\""));
+        assert!(out.contains("href=\"https://example.com/post.html#s\""));
+        assert!(out.contains("href=\"https://x.example/\""));
+    }
+
+    #[test]
+    fn test_opml_lists_feeds() {
+        let config = Config::default();
+        let feeds = vec![FeedInfo {
+            title: "Blog - xss".to_string(),
+            path: "tags/xss/atom.xml".to_string(),
+            html_path: "tags/xss/".to_string(),
+        }];
+        let xml = opml(&config, &feeds);
+        assert!(xml.contains("xmlUrl=\"https://example.com/tags/xss/atom.xml\""));
+    }
+}
@@ -0,0 +1,117 @@
+//! `report stale`: published posts not updated within a period
+//!
+//! Security advice ages: a hardening guide from three years ago may now
+//! recommend a deprecated cipher. The report lists published posts whose
+//! last update (`updated:`, or the publication date) is older than the given
+//! age, oldest first, optionally only those with certain tags, so reviews
+//! can be scheduled before a reader relies on outdated advice.
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Months, Utc};
+
+use crate::Post;
+
+/// How old a post may get before it is listed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Age {
+    /// Calendar months (`18m`, `2y`)
+    Months(u32),
+    /// Days (`90d`, `6w`)
+    Days(u32),
+}
+
+impl Age {
+    /// Parse `<n>y`, `<n>m`, `<n>w` or `<n>d`
+    pub fn parse(text: &str) -> Result<Self> {
+        let invalid = || anyhow::anyhow!("Invalid age {text:?} (e.g. 2y, 18m, 6w, 90d)");
+        let split = text.len().checked_sub(1).filter(|&i| text.is_char_boundary(i)).ok_or_else(invalid)?;
+        let (count, unit) = text.split_at(split);
+        let count: u32 = count.parse().ok().filter(|&n| n > 0).ok_or_else(invalid)?;
+        match unit {
+            "y" => count.checked_mul(12).map(Self::Months),
+            "m" => Some(Self::Months(count)),
+            "w" => count.checked_mul(7).map(Self::Days),
+            "d" => Some(Self::Days(count)),
+            _ => None,
+        }
+        .ok_or_else(invalid)
+    }
+
+    /// The instant `self` before `now`
+    pub fn cutoff(self, now: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Self::Months(months) => now.checked_sub_months(Months::new(months)).unwrap_or(DateTime::<Utc>::MIN_UTC),
+            Self::Days(days) => now - Duration::days(i64::from(days)),
+        }
+    }
+}
+
+/// Posts last modified before `cutoff`, oldest first; with `tags`, only posts carrying one of them
+pub fn stale<'a>(posts: &'a [Post], cutoff: DateTime<Utc>, tags: &[String]) -> Vec<&'a Post> {
+    let mut stale: Vec<&Post> = posts
+        .iter()
+        .filter(|post| post.modified() < cutoff)
+        .filter(|post| tags.is_empty() || post.meta.tags.iter().any(|tag| tags.iter().any(|t| t.eq_ignore_ascii_case(tag))))
+        .collect();
+    stale.sort_by(|a, b| a.modified().cmp(&b.modified()).then_with(|| a.meta.slug.cmp(&b.meta.slug)));
+    stale
+}
+
+/// Whole years and months between `then` and `now`, as `3y 2m`
+pub fn age_label(then: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let mut months = 0;
+    while then.checked_add_months(Months::new(months + 1)).is_some_and(|date| date <= now) {
+        months += 1;
+    }
+    match (months / 12, months % 12) {
+        (0, m) => format!("{m}m"),
+        (y, 0) => format!("{y}y"),
+        (y, m) => format!("{y}y {m}m"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PostMeta;
+
+    fn post(slug: &str, date: &str, updated: Option<&str>, tags: &[&str]) -> Post {
+        Post {
+            meta: PostMeta {
+                slug: slug.to_string(),
+                date: date.parse().unwrap(),
+                updated: updated.map(|u| u.parse().unwrap()),
+                tags: tags.iter().map(|t| (*t).to_string()).collect(),
+                ..PostMeta::default()
+            },
+            ..Post::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(Age::parse("2y").unwrap(), Age::Months(24));
+        assert_eq!(Age::parse("18m").unwrap(), Age::Months(18));
+        assert_eq!(Age::parse("6w").unwrap(), Age::Days(42));
+        assert_eq!(Age::parse("90d").unwrap(), Age::Days(90));
+        for bad in ["", "y", "0y", "2", "2h", "-1y", "2é"] {
+            assert!(Age::parse(bad).is_err(), "{bad:?} accepted");
+        }
+    }
+
+    #[test]
+    fn test_stale_posts_oldest_first_with_tags() {
+        let now: DateTime<Utc> = "2026-06-01T00:00:00Z".parse().unwrap();
+        let posts = [
+            post("tls", "2021-01-01T00:00:00Z", Some("2023-05-01T00:00:00Z"), &["TLS"]),
+            post("ssh", "2020-03-01T00:00:00Z", None, &["ssh"]),
+            post("fresh", "2020-01-01T00:00:00Z", Some("2026-01-01T00:00:00Z"), &["tls"]),
+        ];
+        let cutoff = Age::parse("2y").unwrap().cutoff(now);
+        let slugs = |stale: Vec<&Post>| stale.iter().map(|p| p.meta.slug.clone()).collect::<Vec<_>>();
+        assert_eq!(slugs(stale(&posts, cutoff, &[])), ["ssh", "tls"]);
+        assert_eq!(slugs(stale(&posts, cutoff, &["tls".to_string()])), ["tls"]);
+        assert_eq!(age_label(posts[1].modified(), now), "6y 3m");
+        assert_eq!(age_label(posts[0].modified(), now), "3y 1m");
+    }
+}
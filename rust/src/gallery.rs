@@ -0,0 +1,212 @@
+//! `{{< gallery dir="photos/" >}}`: CSS-only image galleries
+//!
+//! Every image in the directory is decoded and re-encoded, which drops EXIF
+//! and any other metadata (orientation is applied first), and gets a
+//! thumbnail. The gallery is a grid of thumbnails linking to the full-size
+//! images, each link carrying the image's SRI-style hash.
+
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sha2::{Digest, Sha256, Sha384};
+use std::fs;
+use std::io::Cursor;
+use std::path::{Component, Path};
+
+use crate::bundles::Asset;
+use crate::placeholders;
+use crate::security::escape_html as escape;
+
+/// The directive, alone on its line
+static DIRECTIVE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?m)^[ \t]*\{\{<\s*gallery\s+dir="([^"]*)"\s*>\}\}[ \t]*$"#).unwrap());
+
+/// Longest side of a thumbnail, in pixels
+const THUMBNAIL_SIZE: u32 = 480;
+
+/// JPEG quality for re-encoded images
+const JPEG_QUALITY: u8 = 88;
+
+/// Paragraph the directive becomes until the rendered HTML is available
+fn placeholder(index: usize) -> String {
+    format!("SECUREBLOG-GALLERY-{index}")
+}
+
+/// A rendered gallery and the images it publishes
+#[derive(Debug, Clone, Default)]
+pub struct Gallery {
+    /// Directory relative to the post, without a trailing slash
+    pub dir: String,
+    /// Gallery markup
+    pub html: String,
+    /// Full-size images and thumbnails
+    pub assets: Vec<Asset>,
+}
+
+/// Decode an image, baking in its EXIF orientation before the metadata is dropped
+pub fn decode(raw: &[u8]) -> Result<(DynamicImage, ImageFormat)> {
+    let reader = ImageReader::new(Cursor::new(raw)).with_guessed_format()?;
+    let format = reader.format().context("Unknown image format")?;
+    let mut decoder = reader.into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    Ok((image, format))
+}
+
+/// Encode as JPEG or PNG (the only formats published from galleries)
+pub fn encode(image: &DynamicImage, format: ImageFormat) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    if format == ImageFormat::Jpeg {
+        image.to_rgb8().write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY))?;
+    } else {
+        image.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)?;
+    }
+    Ok(bytes)
+}
+
+/// Asset under `<slug>/gallery/` named `<stem>[.thumb].<hash>.<ext>`
+fn asset(slug: &str, source: &str, stem: &str, suffix: &str, ext: &str, content: Vec<u8>, color: [u8; 3]) -> Asset {
+    let hash = format!("{:x}", Sha256::digest(&content));
+    Asset {
+        source: format!("{source}{suffix}"),
+        path: format!("{slug}/gallery/{stem}{suffix}.{}.{ext}", &hash[..16]),
+        content,
+        color: Some(color),
+    }
+}
+
+/// Build the gallery for `dir`, relative to the post's directory
+fn build(post_dir: &Path, slug: &str, dir: &str) -> Result<Gallery> {
+    let relative = dir.trim_end_matches('/');
+    if relative.is_empty() || !Path::new(relative).components().all(|c| matches!(c, Component::Normal(_))) {
+        anyhow::bail!("gallery dir must be a directory next to the post, got {dir:?}");
+    }
+    let mut entries: Vec<_> = fs::read_dir(post_dir.join(relative))
+        .with_context(|| format!("Failed to read gallery {}", post_dir.join(relative).display()))?
+        .collect::<Result<_, _>>()?;
+    entries.sort_by_key(fs::DirEntry::file_name);
+
+    let mut gallery = Gallery {
+        dir: relative.to_string(),
+        html: String::from("<div class=\"gallery\">\n"),
+        assets: Vec::new(),
+    };
+    for entry in entries.iter().filter(|e| e.file_type().is_ok_and(|t| t.is_file())) {
+        let path = entry.path();
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+        let (ext, format) = match ext.as_str() {
+            "jpg" | "jpeg" => ("jpg", ImageFormat::Jpeg),
+            "png" => ("png", ImageFormat::Png),
+            _ => continue,
+        };
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("image").to_string();
+        let raw = fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let (image, _) = decode(&raw).with_context(|| format!("Failed to decode {}", path.display()))?;
+        let thumbnail = image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+        let color = placeholders::dominant_color(&thumbnail);
+
+        let source = format!("{relative}/{}", entry.file_name().to_string_lossy());
+        let full = asset(slug, &source, &stem, "", ext, encode(&image, format)?, color);
+        let thumb = asset(slug, &source, &stem, ".thumb", ext, encode(&thumbnail, format)?, color);
+        gallery.html.push_str(&format!(
+            "<figure class=\"{}\"><a href=\"/{}\" data-integrity=\"sha384-{}\"><img src=\"/{}\" alt=\"{}\" width=\"{}\" height=\"{}\" loading=\"lazy\"></a></figure>\n",
+            placeholders::class(color),
+            escape(&full.path),
+            STANDARD.encode(Sha384::digest(&full.content)),
+            escape(&thumb.path),
+            escape(&stem),
+            thumbnail.width(),
+            thumbnail.height(),
+        ));
+        gallery.assets.push(full);
+        gallery.assets.push(thumb);
+    }
+    gallery.html.push_str("</div>");
+    Ok(gallery)
+}
+
+/// Replace gallery directives with placeholders and build each gallery
+pub fn expand(markdown: &str, source: &Path, slug: &str) -> Result<(String, Vec<Gallery>)> {
+    let post_dir = source.parent().unwrap_or_else(|| Path::new("."));
+    let mut galleries = Vec::new();
+    let mut error = None;
+    let replaced = DIRECTIVE.replace_all(markdown, |capture: &regex::Captures| {
+        match build(post_dir, slug, &capture[1]) {
+            Ok(gallery) => galleries.push(gallery),
+            Err(e) => {
+                error.get_or_insert(e);
+            }
+        }
+        placeholder(galleries.len().saturating_sub(1))
+    });
+    if let Some(error) = error {
+        return Err(error);
+    }
+    Ok((replaced.into_owned(), galleries))
+}
+
+/// Directories named by gallery directives, relative to the post (for `check --changed`)
+pub fn gallery_dirs(markdown: &str) -> Vec<String> {
+    DIRECTIVE.captures_iter(markdown).map(|capture| capture[1].trim_end_matches('/').to_string()).collect()
+}
+
+/// Put the gallery markup where the placeholders were rendered
+pub fn insert(html: &str, galleries: &[Gallery]) -> String {
+    let mut html = html.to_string();
+    for (index, gallery) in galleries.iter().enumerate() {
+        html = html.replace(&format!("<p>{}</p>", placeholder(index)), &gallery.html);
+    }
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbImage;
+
+    #[test]
+    fn test_reencoding_drops_metadata() {
+        let mut png = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::new(4, 2)).write_to(&mut Cursor::new(&mut png), ImageFormat::Png).unwrap();
+        // A tEXt chunk after IHDR, as cameras and editors add
+        let text = b"tEXtAuthor\0Jane Doe";
+        let mut crc = flate2::Crc::new();
+        crc.update(text);
+        let mut chunk = (u32::try_from(text.len()).unwrap() - 4).to_be_bytes().to_vec();
+        chunk.extend_from_slice(text);
+        chunk.extend_from_slice(&crc.sum().to_be_bytes());
+        let mut tagged = png[..33].to_vec();
+        tagged.extend_from_slice(&chunk);
+        tagged.extend_from_slice(&png[33..]);
+
+        let (image, format) = decode(&tagged).unwrap();
+        assert_eq!(format, ImageFormat::Png);
+        assert_eq!((image.width(), image.height()), (4, 2));
+        let clean = encode(&image, format).unwrap();
+        assert!(!clean.windows(8).any(|w| w == b"Jane Doe"));
+    }
+
+    #[test]
+    fn test_directive_becomes_placeholder() {
+        let (markdown, galleries) = expand("Intro\n\nNo gallery here.\n", Path::new("content/post.md"), "post").unwrap();
+        assert_eq!(markdown, "Intro\n\nNo gallery here.\n");
+        assert!(galleries.is_empty());
+        assert_eq!(gallery_dirs("{{< gallery dir=\"photos/\" >}}\n"), ["photos"]);
+
+        let gallery = Gallery { html: "<div class=\"gallery\"></div>".to_string(), ..Gallery::default() };
+        assert_eq!(insert("<p>SECUREBLOG-GALLERY-0</p>", &[gallery]), "<div class=\"gallery\"></div>");
+    }
+
+    #[test]
+    fn test_gallery_dir_is_confined() {
+        for dir in ["../photos", "/etc", "", "a/../../b"] {
+            let markdown = format!("{{{{< gallery dir=\"{dir}\" >}}}}\n");
+            assert!(expand(&markdown, Path::new("content/post.md"), "post").is_err(), "{dir:?} accepted");
+        }
+    }
+}
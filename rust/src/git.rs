@@ -0,0 +1,209 @@
+//! Git history of content files (read locally with gix)
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// A commit that changed a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Change {
+    /// Committer time
+    pub time: DateTime<Utc>,
+    /// Commit id
+    pub commit: gix::ObjectId,
+}
+
+/// First and latest commits that changed a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileHistory {
+    /// Commit that introduced the file
+    pub first: Change,
+    /// Most recent commit that modified the file
+    pub last: Change,
+}
+
+/// Open the repository containing `dir` and return it with its canonical working tree root
+pub fn open(dir: &Path) -> Result<(gix::Repository, PathBuf)> {
+    let repo = gix::discover(dir)
+        .with_context(|| format!("Failed to open git repository for {}", dir.display()))?;
+    let workdir = repo
+        .workdir()
+        .context("Git repository has no working tree")?
+        .canonicalize()?;
+    Ok((repo, workdir))
+}
+
+/// Path of `source` relative to the repository root, with `/` separators
+pub fn repo_path(workdir: &Path, source: &Path) -> Option<String> {
+    let absolute = source.canonicalize().ok()?;
+    crate::normalize::relative(&absolute, workdir).ok()
+}
+
+/// A commit that changed a file, with its message summary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    /// Committer time
+    pub time: DateTime<Utc>,
+    /// Commit id
+    pub commit: gix::ObjectId,
+    /// First line of the commit message
+    pub summary: String,
+}
+
+/// Walk history from HEAD in topological order (no commit before its
+/// descendants, like `git rev-list --topo-order`), calling `on_change` for
+/// every commit in which one of `paths` changed
+fn walk_changes(
+    repo: &gix::Repository,
+    paths: &[&str],
+    mut on_change: impl FnMut(&str, Change, &gix::Commit<'_>) -> Result<()>,
+) -> Result<()> {
+    use gix::traverse::commit::topo;
+
+    let head = repo.head_commit().context("Failed to resolve HEAD")?;
+    let walk = topo::Builder::from_iters(&repo.objects, [head.id], None::<Vec<gix::ObjectId>>)
+        .sorting(topo::Sorting::TopoOrder)
+        .build()?;
+
+    for info in walk {
+        let commit = repo.find_commit(info?.id)?;
+        let Some(time) = DateTime::from_timestamp(commit.time()?.seconds, 0) else {
+            continue;
+        };
+        let change = Change { time, commit: commit.id };
+        let tree = commit.tree()?;
+        let parents = commit
+            .parent_ids()
+            .map(|id| Ok(repo.find_commit(id.detach())?.tree()?))
+            .collect::<Result<Vec<_>>>()?;
+
+        for path in paths {
+            let Some(id) = entry_id(&tree, path)? else {
+                continue;
+            };
+            // A commit touches the file when no parent has the same blob
+            let mut changed = true;
+            for parent in &parents {
+                if entry_id(parent, path)? == Some(id) {
+                    changed = false;
+                    break;
+                }
+            }
+            if changed {
+                on_change(path, change, &commit)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Walk history from HEAD recording the commits in which each path's blob changed
+///
+/// The order is the ancestry, not committer time, which can be skewed or
+/// forged: `last` is the first change reached from HEAD and `first` the last.
+pub fn file_history(repo: &gix::Repository, paths: &[&str]) -> Result<HashMap<String, FileHistory>> {
+    let mut history: HashMap<String, FileHistory> = HashMap::new();
+    walk_changes(repo, paths, |path, change, _| {
+        history
+            .entry(path.to_string())
+            .and_modify(|entry| entry.first = change)
+            .or_insert(FileHistory { first: change, last: change });
+        Ok(())
+    })?;
+    Ok(history)
+}
+
+/// Every commit that changed each path, newest first (in walk order)
+pub fn file_log(repo: &gix::Repository, paths: &[&str]) -> Result<HashMap<String, Vec<LogEntry>>> {
+    let mut log: HashMap<String, Vec<LogEntry>> = HashMap::new();
+    walk_changes(repo, paths, |path, change, commit| {
+        let summary = commit.message()?.summary().to_string();
+        log.entry(path.to_string()).or_default().push(LogEntry { time: change.time, commit: change.commit, summary });
+        Ok(())
+    })?;
+    Ok(log)
+}
+
+fn entry_id(tree: &gix::Tree<'_>, path: &str) -> Result<Option<gix::ObjectId>> {
+    Ok(tree.lookup_entry_by_path(path)?.map(|entry| entry.object_id()))
+}
+
+/// History of each source file, keyed by the given paths (untracked files are absent)
+pub fn history_of(sources: &[&Path]) -> Result<HashMap<PathBuf, FileHistory>> {
+    let Some(first) = sources.first() else {
+        return Ok(HashMap::new());
+    };
+    let (repo, workdir) = open(first.parent().unwrap_or_else(|| Path::new(".")))?;
+
+    let paths: Vec<(PathBuf, String)> = sources
+        .iter()
+        .filter_map(|source| repo_path(&workdir, source).map(|p| (source.to_path_buf(), p)))
+        .collect();
+    let wanted: Vec<&str> = paths.iter().map(|(_, p)| p.as_str()).collect();
+    let history = file_history(&repo, &wanted)?;
+
+    Ok(paths
+        .into_iter()
+        .filter_map(|(source, path)| history.get(&path).map(|h| (source, *h)))
+        .collect())
+}
+
+/// Commit log of each source file, keyed by the given paths (untracked files are absent)
+pub fn log_of(sources: &[&Path]) -> Result<HashMap<PathBuf, Vec<LogEntry>>> {
+    let Some(first) = sources.first() else {
+        return Ok(HashMap::new());
+    };
+    let (repo, workdir) = open(first.parent().unwrap_or_else(|| Path::new(".")))?;
+
+    let paths: Vec<(PathBuf, String)> = sources
+        .iter()
+        .filter_map(|source| repo_path(&workdir, source).map(|p| (source.to_path_buf(), p)))
+        .collect();
+    let wanted: Vec<&str> = paths.iter().map(|(_, p)| p.as_str()).collect();
+    let mut log = file_log(&repo, &wanted)?;
+
+    Ok(paths
+        .into_iter()
+        .filter_map(|(source, path)| log.remove(&path).map(|entries| (source, entries)))
+        .collect())
+}
+
+/// Of `paths`, those whose working tree contents differ from HEAD (new files included)
+///
+/// Staged and unstaged edits count alike; a file deleted from the working tree is skipped.
+pub fn changed_since_head(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let Some(first) = paths.first() else {
+        return Ok(Vec::new());
+    };
+    let (repo, workdir) = open(first.parent().unwrap_or_else(|| Path::new(".")))?;
+    let tree = repo.head_commit().context("Failed to resolve HEAD")?.tree()?;
+
+    let mut changed = Vec::new();
+    for path in paths {
+        let (Some(relative), Ok(current)) = (repo_path(&workdir, path), std::fs::read(path)) else {
+            continue;
+        };
+        let committed = match tree.lookup_entry_by_path(&relative)? {
+            Some(entry) => Some(entry.object()?.data.clone()),
+            None => None,
+        };
+        if committed.as_deref() != Some(current.as_slice()) {
+            changed.push(path.clone());
+        }
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repo_path_is_relative_with_forward_slashes() {
+        let workdir = std::env::current_dir().unwrap().canonicalize().unwrap();
+        let source = Path::new("src").join("git.rs");
+        assert_eq!(repo_path(&workdir, &source).as_deref(), Some("src/git.rs"));
+        assert_eq!(repo_path(&workdir, Path::new("missing/file.md")), None);
+    }
+}
@@ -0,0 +1,388 @@
+//! Cloudflare Pages `_headers` file with the hardened response header set
+//!
+//! Every path gets CSP, HSTS, a deny-all Permissions-Policy and cross-origin
+//! isolation headers; `paths` entries replace or remove headers under a pattern.
+//! `check headers` compares what a deployed site actually sends against this set.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// File the host reads response headers from
+pub const HEADERS_FILE: &str = "_headers";
+
+/// CSP for script-free pages that only load same-origin resources
+pub const DEFAULT_CSP: &str = "default-src 'none'; img-src 'self' data:; style-src 'self'; font-src 'self'; \
+    manifest-src 'self'; script-src 'none'; object-src 'none'; frame-ancestors 'none'; base-uri 'self'; \
+    form-action 'none'; upgrade-insecure-requests";
+
+/// Powerful features denied by the default `Permissions-Policy`
+pub const PERMISSIONS: &[&str] = &[
+    "accelerometer", "ambient-light-sensor", "autoplay", "battery", "bluetooth", "camera", "display-capture",
+    "document-domain", "encrypted-media", "fullscreen", "geolocation", "gyroscope", "hid", "idle-detection",
+    "interest-cohort", "magnetometer", "microphone", "midi", "payment", "picture-in-picture",
+    "publickey-credentials-get", "screen-wake-lock", "serial", "sync-xhr", "usb", "web-share",
+    "xr-spatial-tracking",
+];
+
+/// Response header settings
+#[derive(Debug, Clone, Deserialize)]
+pub struct HeadersConfig {
+    /// Write `_headers` into the output
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// `Content-Security-Policy`
+    #[serde(default = "default_csp")]
+    pub csp: String,
+    /// `Strict-Transport-Security`
+    #[serde(default = "default_hsts")]
+    pub hsts: String,
+    /// `Cross-Origin-Opener-Policy`
+    #[serde(default = "default_same_origin")]
+    pub coop: String,
+    /// `Cross-Origin-Embedder-Policy`
+    #[serde(default = "default_coep")]
+    pub coep: String,
+    /// `Cross-Origin-Resource-Policy`
+    #[serde(default = "default_same_origin")]
+    pub corp: String,
+    /// Per-path overrides (`/feeds/*` → header → value, `~` removes the header)
+    #[serde(default)]
+    pub paths: BTreeMap<String, BTreeMap<String, Option<String>>>,
+}
+
+impl Default for HeadersConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            csp: default_csp(),
+            hsts: default_hsts(),
+            coop: default_same_origin(),
+            coep: default_coep(),
+            corp: default_same_origin(),
+            paths: BTreeMap::new(),
+        }
+    }
+}
+
+const fn default_true() -> bool {
+    true
+}
+
+fn default_csp() -> String {
+    DEFAULT_CSP.to_string()
+}
+
+fn default_hsts() -> String {
+    "max-age=63072000; includeSubDomains".to_string()
+}
+
+fn default_same_origin() -> String {
+    "same-origin".to_string()
+}
+
+fn default_coep() -> String {
+    "require-corp".to_string()
+}
+
+/// `Permissions-Policy` denying every feature in [`PERMISSIONS`]
+pub fn permissions_policy() -> String {
+    PERMISSIONS.iter().map(|feature| format!("{feature}=()")).collect::<Vec<_>>().join(", ")
+}
+
+/// Headers applied to every path, in output order
+pub fn site_headers(config: &HeadersConfig) -> Vec<(&'static str, String)> {
+    vec![
+        ("Content-Security-Policy", config.csp.clone()),
+        ("Strict-Transport-Security", config.hsts.clone()),
+        ("X-Content-Type-Options", "nosniff".to_string()),
+        ("X-Frame-Options", "DENY".to_string()),
+        ("Referrer-Policy", "strict-origin-when-cross-origin".to_string()),
+        ("Permissions-Policy", permissions_policy()),
+        ("Cross-Origin-Opener-Policy", config.coop.clone()),
+        ("Cross-Origin-Embedder-Policy", config.coep.clone()),
+        ("Cross-Origin-Resource-Policy", config.corp.clone()),
+    ]
+}
+
+/// Reject names and values that would inject extra lines or rules
+fn check(pattern: &str, name: &str, value: &str) -> Result<()> {
+    if !pattern.starts_with('/') || pattern.contains(char::is_whitespace) {
+        anyhow::bail!("Invalid header path pattern '{pattern}'");
+    }
+    if name.is_empty() || !name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-') {
+        anyhow::bail!("Invalid header name '{name}' for {pattern}");
+    }
+    if value.contains(['\r', '\n']) {
+        anyhow::bail!("Header {name} for {pattern} contains a line break");
+    }
+    Ok(())
+}
+
+/// `_headers` contents
+///
+/// Overrides detach the site-wide value (`! Name`) first; the host would otherwise
+/// join both values with a comma.
+pub fn render(config: &HeadersConfig) -> Result<String> {
+    let mut out = String::from("/*\n");
+    for (name, value) in site_headers(config) {
+        check("/*", name, &value)?;
+        let _ = writeln!(out, "  {name}: {value}");
+    }
+
+    for (pattern, headers) in &config.paths {
+        let _ = writeln!(out, "{pattern}");
+        for (name, value) in headers {
+            check(pattern, name, value.as_deref().unwrap_or_default())?;
+            let _ = writeln!(out, "  ! {name}");
+            if let Some(value) = value {
+                let _ = writeln!(out, "  {name}: {value}");
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Cloudflare-style path pattern match (`*` matches any run of characters)
+pub fn matches(pattern: &str, path: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Headers the generated `_headers` assigns to `path`
+pub fn expected_for(config: &HeadersConfig, path: &str) -> Vec<(String, String)> {
+    let mut headers: Vec<(String, String)> =
+        site_headers(config).into_iter().map(|(name, value)| (name.to_string(), value)).collect();
+    for (_, overrides) in config.paths.iter().filter(|(pattern, _)| matches(pattern, path)) {
+        for (name, value) in overrides {
+            headers.retain(|(existing, _)| !existing.eq_ignore_ascii_case(name));
+            if let Some(value) = value {
+                headers.push((name.clone(), value.clone()));
+            }
+        }
+    }
+    headers
+}
+
+/// Difference between an expected and a served header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Finding {
+    /// Header absent from the response (stripped by the host)
+    Missing(String),
+    /// Header served with another value (overridden by the host)
+    Changed {
+        /// Header name
+        name: String,
+        /// Value the generator emitted
+        expected: String,
+        /// Value the host served
+        actual: String,
+    },
+}
+
+/// Headers that make a page grade F when missing or changed
+const CRITICAL: &[&str] = &["Content-Security-Policy", "Strict-Transport-Security", "X-Content-Type-Options"];
+
+fn normalize(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Compare served headers (repeated names already joined with `, `) against the expected set
+pub fn audit(expected: &[(String, String)], actual: &[(String, String)]) -> Vec<Finding> {
+    expected
+        .iter()
+        .filter_map(|(name, value)| {
+            match actual.iter().find(|(served, _)| served.eq_ignore_ascii_case(name)) {
+                None => Some(Finding::Missing(name.clone())),
+                Some((_, served)) if normalize(served) != normalize(value) => Some(Finding::Changed {
+                    name: name.clone(),
+                    expected: value.clone(),
+                    actual: served.clone(),
+                }),
+                Some(_) => None,
+            }
+        })
+        .collect()
+}
+
+/// A (everything served as generated) to F (a critical header stripped or changed)
+pub fn grade(findings: &[Finding]) -> char {
+    let critical = findings.iter().any(|finding| {
+        let name = match finding {
+            Finding::Missing(name) | Finding::Changed { name, .. } => name,
+        };
+        CRITICAL.iter().any(|critical| critical.eq_ignore_ascii_case(name))
+    });
+    let missing = findings.iter().filter(|f| matches!(f, Finding::Missing(_))).count();
+    match (critical, missing, findings.len()) {
+        (true, _, _) => 'F',
+        (false, _, 0) => 'A',
+        (false, 0, _) => 'B',
+        (false, 1..=2, _) => 'C',
+        _ => 'D',
+    }
+}
+
+/// Pages fetched by `check headers`: the home page, feeds and literal override paths
+pub fn audit_paths(config: &HeadersConfig) -> Vec<String> {
+    let mut paths: Vec<String> = ["/", "/atom.xml", "/sitemap.xml", "/integrity.json"].map(String::from).to_vec();
+    for path in config.paths.keys().filter(|p| !p.contains('*')) {
+        if !paths.contains(path) {
+            paths.push(path.clone());
+        }
+    }
+    paths
+}
+
+#[cfg(feature = "network")]
+pub use online::check_site;
+
+#[cfg(feature = "network")]
+mod online {
+    use super::{audit, audit_paths, expected_for, grade, Finding, HeadersConfig};
+    use anyhow::{Context, Result};
+    use tracing::{info, warn};
+
+    /// Fetch each audited page of `site` and report headers that differ from `_headers`
+    ///
+    /// Returns the number of pages that did not get an A.
+    pub fn check_site(site: &str, config: &HeadersConfig) -> Result<usize> {
+        let agent = crate::net::agent();
+        let site = site.trim_end_matches('/');
+        let mut failing = 0;
+
+        for path in audit_paths(config) {
+            let url = format!("{site}{path}");
+            let response = agent.get(&url).call().with_context(|| format!("Failed to fetch {url}"))?;
+            if !response.status().is_success() {
+                warn!("{}: HTTP {}, skipped", url, response.status());
+                continue;
+            }
+
+            let mut served: Vec<(String, String)> = Vec::new();
+            for (name, value) in response.headers() {
+                let value = value.to_str().unwrap_or_default().to_string();
+                match served.iter_mut().find(|(existing, _)| existing == name.as_str()) {
+                    Some((_, joined)) => *joined = format!("{joined}, {value}"),
+                    None => served.push((name.as_str().to_string(), value)),
+                }
+            }
+
+            let findings = audit(&expected_for(config, &path), &served);
+            let grade = grade(&findings);
+            info!("{} {}", grade, url);
+            for finding in &findings {
+                match finding {
+                    Finding::Missing(name) => warn!("  {} missing", name),
+                    Finding::Changed { name, expected, actual } => {
+                        warn!("  {} is '{}', generated '{}'", name, actual, expected);
+                    }
+                }
+            }
+            if grade != 'A' {
+                failing += 1;
+            }
+        }
+        Ok(failing)
+    }
+}
+
+/// Write `_headers` into the output directory
+pub fn generate(output_dir: &Path, config: &HeadersConfig) -> Result<()> {
+    let path = output_dir.join(HEADERS_FILE);
+    fs::write(&path, render(config)?).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_headers_are_hardened() {
+        let out = render(&HeadersConfig::default()).unwrap();
+        assert!(out.starts_with("/*\n  Content-Security-Policy: default-src 'none';"));
+        assert!(out.contains("  Permissions-Policy: accelerometer=(), "));
+        assert!(out.contains("  Cross-Origin-Opener-Policy: same-origin\n"));
+        assert!(out.contains("  Cross-Origin-Embedder-Policy: require-corp\n"));
+        assert!(out.contains("  Cross-Origin-Resource-Policy: same-origin\n"));
+        assert!(!permissions_policy().contains("=(self)"));
+    }
+
+    #[test]
+    fn test_path_overrides_detach_site_value() {
+        let config: HeadersConfig = serde_yaml::from_str(
+            "paths:\n  /atom.xml:\n    Cross-Origin-Resource-Policy: cross-origin\n  /embed/*:\n    Cross-Origin-Embedder-Policy: ~\n",
+        )
+        .unwrap();
+        let out = render(&config).unwrap();
+        assert!(out.ends_with(concat!(
+            "/atom.xml\n  ! Cross-Origin-Resource-Policy\n  Cross-Origin-Resource-Policy: cross-origin\n",
+            "/embed/*\n  ! Cross-Origin-Embedder-Policy\n",
+        )));
+    }
+
+    #[test]
+    fn test_expected_headers_apply_overrides() {
+        assert!(matches("/*", "/posts/a.html"));
+        assert!(matches("/tags/*/atom.xml", "/tags/xss/atom.xml"));
+        assert!(!matches("/tags/*/atom.xml", "/tags/xss/index.html"));
+        assert!(matches("/atom.xml", "/atom.xml") && !matches("/atom.xml", "/atom.xml.sig"));
+
+        let mut config = HeadersConfig::default();
+        config.paths.insert("/atom.xml".to_string(), BTreeMap::from([("Cross-Origin-Resource-Policy".to_string(), Some("cross-origin".to_string())), ("X-Frame-Options".to_string(), None)]));
+        let expected = expected_for(&config, "/atom.xml");
+        assert!(expected.contains(&("Cross-Origin-Resource-Policy".to_string(), "cross-origin".to_string())));
+        assert!(!expected.iter().any(|(name, _)| name == "X-Frame-Options"));
+        assert_eq!(expected_for(&config, "/").len(), site_headers(&config).len());
+        assert_eq!(audit_paths(&config).len(), 4);
+    }
+
+    #[test]
+    fn test_audit_grades_stripped_and_overridden_headers() {
+        let expected = expected_for(&HeadersConfig::default(), "/");
+        let mut served: Vec<(String, String)> =
+            expected.iter().map(|(name, value)| (name.to_ascii_lowercase(), value.replace("; ", ";  "))).collect();
+        assert!(audit(&expected, &served).is_empty());
+        assert_eq!(grade(&[]), 'A');
+
+        served.retain(|(name, _)| name != "cross-origin-embedder-policy");
+        served.iter_mut().find(|(name, _)| name == "referrer-policy").unwrap().1 = "unsafe-url".to_string();
+        let findings = audit(&expected, &served);
+        assert_eq!(findings[0], Finding::Changed {
+            name: "Referrer-Policy".to_string(),
+            expected: "strict-origin-when-cross-origin".to_string(),
+            actual: "unsafe-url".to_string(),
+        });
+        assert_eq!(findings[1], Finding::Missing("Cross-Origin-Embedder-Policy".to_string()));
+        assert_eq!(grade(&findings), 'C');
+
+        served.retain(|(name, _)| name != "strict-transport-security");
+        assert_eq!(grade(&audit(&expected, &served)), 'F');
+    }
+
+    #[test]
+    fn test_rejects_injected_lines() {
+        let mut config = HeadersConfig::default();
+        config.paths.insert("/x".to_string(), BTreeMap::from([("X-A".to_string(), Some("a\n/*".to_string()))]));
+        assert!(render(&config).is_err());
+        let config = HeadersConfig { csp: "default-src 'none'\r\nSet-Cookie: a".to_string(), ..HeadersConfig::default() };
+        assert!(render(&config).is_err());
+    }
+}
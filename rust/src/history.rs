@@ -0,0 +1,269 @@
+//! Hash-chained snapshots of published content across builds
+//!
+//! Each build that publishes different content than the last snapshot adds
+//! `manifest-<N>.json` to the history directory: every post's path, dates and
+//! content hash, plus the SHA-256 of `manifest-<N-1>.json`. The directory is
+//! committed with the site and published under `archive/`, so readers and
+//! mirrors holding an older snapshot can check that the chain still leads to
+//! it and see every change since, including posts edited without an
+//! `updated:` date. `history verify` checks a chain.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::Post;
+
+/// Output directory snapshots are published under
+pub const PUBLISHED_DIR: &str = "archive";
+
+/// History settings
+#[derive(Debug, Clone, Deserialize)]
+pub struct HistoryConfig {
+    /// Directory holding the snapshots, committed with the site
+    #[serde(default = "default_dir")]
+    pub dir: PathBuf,
+}
+
+fn default_dir() -> PathBuf {
+    PathBuf::from("archive")
+}
+
+/// One published post in a snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    /// Output path
+    pub path: String,
+    pub title: String,
+    pub date: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated: Option<DateTime<Utc>>,
+    /// SHA-256 of the rendered content
+    pub sha256: String,
+}
+
+/// `manifest-<N>.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Position in the chain, from 1
+    pub number: u64,
+    pub generated: DateTime<Utc>,
+    /// `sha256:` of the previous snapshot file's bytes (absent for the first)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<String>,
+    pub posts: Vec<Entry>,
+}
+
+/// File name of snapshot `number`
+pub fn file_name(number: u64) -> String {
+    format!("manifest-{number}.json")
+}
+
+/// Snapshot entries of the published posts, sorted by path
+pub fn entries(posts: &[Post]) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = posts
+        .iter()
+        .map(|post| Entry {
+            path: post.path(),
+            title: post.meta.title.clone(),
+            date: post.meta.date,
+            updated: post.meta.updated,
+            sha256: post.hash.clone(),
+        })
+        .collect();
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    entries
+}
+
+fn digest(bytes: &[u8]) -> String {
+    format!("sha256:{:x}", Sha256::digest(bytes))
+}
+
+/// Raw bytes of every snapshot in `dir`, in chain order; fails on gaps
+pub fn load(dir: &Path) -> Result<Vec<(Snapshot, Vec<u8>)>> {
+    let mut snapshots = Vec::new();
+    if !dir.is_dir() {
+        return Ok(snapshots);
+    }
+    let mut numbers: Vec<u64> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().into_owned();
+            name.strip_prefix("manifest-")?.strip_suffix(".json")?.parse().ok()
+        })
+        .collect();
+    numbers.sort_unstable();
+    for (expected, number) in (1..).zip(numbers) {
+        if number != expected {
+            anyhow::bail!("{} is missing from {}", file_name(expected), dir.display());
+        }
+        let path = dir.join(file_name(number));
+        let bytes = fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let snapshot: Snapshot =
+            serde_json::from_slice(&bytes).with_context(|| format!("Invalid snapshot {}", path.display()))?;
+        snapshots.push((snapshot, bytes));
+    }
+    Ok(snapshots)
+}
+
+/// Problems with a chain: numbering and `previous` hashes (empty when intact)
+pub fn chain_problems(snapshots: &[(Snapshot, Vec<u8>)]) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut previous: Option<&[u8]> = None;
+    for (index, (snapshot, bytes)) in snapshots.iter().enumerate() {
+        let name = file_name(index as u64 + 1);
+        if snapshot.number != index as u64 + 1 {
+            problems.push(format!("{name} says it is number {}", snapshot.number));
+        }
+        let expected = previous.map(digest);
+        if snapshot.previous != expected {
+            problems.push(match expected {
+                Some(_) => format!("{name} does not chain to {}", file_name(index as u64)),
+                None => format!("{name} is first but names a previous snapshot"),
+            });
+        }
+        previous = Some(bytes);
+    }
+    problems
+}
+
+/// What changed between two snapshots; `true` marks edits without a new `updated:` date
+pub fn changes(before: &Snapshot, after: &Snapshot) -> Vec<(String, bool)> {
+    let mut changes = Vec::new();
+    for entry in &after.posts {
+        match before.posts.iter().find(|old| old.path == entry.path) {
+            None => changes.push((format!("added {}", entry.path), false)),
+            Some(old) if old.sha256 != entry.sha256 => {
+                let silent = old.updated == entry.updated;
+                changes.push((format!("changed {}", entry.path), silent));
+            }
+            Some(_) => {}
+        }
+    }
+    for old in before.posts.iter().filter(|old| !after.posts.iter().any(|e| e.path == old.path)) {
+        changes.push((format!("removed {}", old.path), false));
+    }
+    changes
+}
+
+/// Posts in any snapshot whose page the current posts no longer produce, as `(path, last title)`
+pub fn removed(dir: &Path, posts: &[Post]) -> Result<Vec<(String, String)>> {
+    let current: Vec<String> = posts.iter().map(Post::path).collect();
+    let mut removed: Vec<(String, String)> = Vec::new();
+    for (snapshot, _) in load(dir)?.into_iter().rev() {
+        for entry in snapshot.posts {
+            if !current.contains(&entry.path) && !removed.iter().any(|(path, _)| *path == entry.path) {
+                removed.push((entry.path, entry.title));
+            }
+        }
+    }
+    removed.sort();
+    Ok(removed)
+}
+
+/// Append a snapshot when the published posts differ from the last one; returns its number
+pub fn record(dir: &Path, posts: &[Post], now: DateTime<Utc>) -> Result<Option<u64>> {
+    let snapshots = load(dir)?;
+    let problems = chain_problems(&snapshots);
+    if !problems.is_empty() {
+        anyhow::bail!("History in {} is broken:\n  {}", dir.display(), problems.join("\n  "));
+    }
+    let posts = entries(posts);
+    if snapshots.last().is_some_and(|(last, _)| last.posts == posts) {
+        return Ok(None);
+    }
+    let snapshot = Snapshot {
+        number: snapshots.len() as u64 + 1,
+        generated: now,
+        previous: snapshots.last().map(|(_, bytes)| digest(bytes)),
+        posts,
+    };
+    fs::create_dir_all(dir)?;
+    let path = dir.join(file_name(snapshot.number));
+    fs::write(&path, serde_json::to_string_pretty(&snapshot)? + "\n")
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(Some(snapshot.number))
+}
+
+/// Record this build's snapshot if needed, then publish the whole chain under `archive/`
+pub fn apply(dir: &Path, output_dir: &Path, posts: &[Post], now: DateTime<Utc>) -> Result<()> {
+    if let Some(number) = record(dir, posts, now)? {
+        info!("📚 Recorded {} in {}", file_name(number), dir.display());
+    }
+    let target = output_dir.join(PUBLISHED_DIR);
+    if target.exists() {
+        anyhow::bail!("{} already exists in the output; history is published there", target.display());
+    }
+    fs::create_dir_all(&target)?;
+    for (snapshot, bytes) in load(dir)? {
+        fs::write(target.join(file_name(snapshot.number)), bytes)?;
+    }
+    Ok(())
+}
+
+/// Check a chain (a history directory or a mirror's `archive/`) and list its changes
+pub fn verify(dir: &Path) -> Result<usize> {
+    let snapshots = load(dir)?;
+    if snapshots.is_empty() {
+        anyhow::bail!("No snapshots in {}", dir.display());
+    }
+    let problems = chain_problems(&snapshots);
+    if !problems.is_empty() {
+        anyhow::bail!("History in {} is broken:\n  {}", dir.display(), problems.join("\n  "));
+    }
+    for pair in snapshots.windows(2) {
+        let (before, after) = (&pair[0].0, &pair[1].0);
+        info!("{} ({}):", file_name(after.number), after.generated.to_rfc3339());
+        for (change, silent) in changes(before, after) {
+            if silent {
+                warn!("  {change} without a new `updated:` date");
+            } else {
+                info!("  {change}");
+            }
+        }
+    }
+    Ok(snapshots.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn post(slug: &str, hash: &str) -> Post {
+        let mut post = Post { hash: hash.to_string(), ..Post::default() };
+        post.meta.slug = slug.to_string();
+        post
+    }
+
+    #[test]
+    fn test_snapshots_chain_and_skip_unchanged_builds() {
+        let dir = std::env::temp_dir().join(format!("secureblog-history-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let now = Utc.with_ymd_and_hms(2024, 5, 9, 0, 0, 0).unwrap();
+
+        assert_eq!(record(&dir, &[post("a", "1")], now).unwrap(), Some(1));
+        assert_eq!(record(&dir, &[post("a", "1")], now).unwrap(), None);
+        assert_eq!(record(&dir, &[post("a", "2"), post("b", "3")], now).unwrap(), Some(2));
+        assert_eq!(removed(&dir, &[post("b", "3")]).unwrap(), [("a.html".to_string(), String::new())]);
+
+        let snapshots = load(&dir).unwrap();
+        assert!(chain_problems(&snapshots).is_empty());
+        assert_eq!(snapshots[1].0.previous, Some(digest(&snapshots[0].1)));
+        assert_eq!(changes(&snapshots[0].0, &snapshots[1].0), [("changed a.html".to_string(), true), ("added b.html".to_string(), false)]);
+
+        // Rewriting an old snapshot breaks the link from the next one
+        let rewritten = Snapshot { posts: Vec::new(), ..snapshots[0].0.clone() };
+        fs::write(dir.join("manifest-1.json"), serde_json::to_vec(&rewritten).unwrap()).unwrap();
+        assert_eq!(chain_problems(&load(&dir).unwrap()), ["manifest-2.json does not chain to manifest-1.json"]);
+        assert!(record(&dir, &[], now).is_err());
+
+        fs::remove_file(dir.join("manifest-1.json")).unwrap();
+        assert!(load(&dir).unwrap_err().to_string().contains("manifest-1.json is missing"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
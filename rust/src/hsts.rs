@@ -0,0 +1,173 @@
+//! HSTS preload list readiness (the hstspreload.org submission requirements)
+
+use std::fmt;
+
+/// Minimum `max-age` accepted by the preload list (one year)
+pub const PRELOAD_MIN_MAX_AGE: u64 = 31_536_000;
+
+/// Parsed `Strict-Transport-Security` value
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Hsts {
+    /// `max-age` in seconds, if present and numeric
+    pub max_age: Option<u64>,
+    /// `includeSubDomains` directive
+    pub include_subdomains: bool,
+    /// `preload` directive
+    pub preload: bool,
+}
+
+/// Parse a header value; directive names are case-insensitive
+pub fn parse(value: &str) -> Hsts {
+    let mut hsts = Hsts::default();
+    for directive in value.split(';').map(str::trim) {
+        let (name, arg) = directive.split_once('=').unwrap_or((directive, ""));
+        match name.trim().to_ascii_lowercase().as_str() {
+            "max-age" => hsts.max_age = arg.trim().trim_matches('"').parse().ok(),
+            "includesubdomains" => hsts.include_subdomains = true,
+            "preload" => hsts.preload = true,
+            _ => {}
+        }
+    }
+    hsts
+}
+
+/// A preload requirement that is not met
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// No `Strict-Transport-Security` header on the HTTPS response
+    NoHeader,
+    /// `max-age` missing or below one year
+    MaxAgeTooShort(Option<u64>),
+    /// `includeSubDomains` missing
+    NoIncludeSubDomains,
+    /// `preload` missing
+    NoPreload,
+    /// `http://` does not redirect
+    NoHttpRedirect,
+    /// First `http://` redirect does not go to `https://` on the same host
+    RedirectElsewhere(String),
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoHeader => write!(f, "HTTPS response has no Strict-Transport-Security header"),
+            Self::MaxAgeTooShort(Some(age)) => {
+                write!(f, "max-age={age} is below the required {PRELOAD_MIN_MAX_AGE}")
+            }
+            Self::MaxAgeTooShort(None) => write!(f, "max-age is missing"),
+            Self::NoIncludeSubDomains => write!(f, "includeSubDomains directive is missing"),
+            Self::NoPreload => write!(f, "preload directive is missing"),
+            Self::NoHttpRedirect => write!(f, "http:// does not redirect to https://"),
+            Self::RedirectElsewhere(location) => {
+                write!(f, "http:// redirects to {location}; it must first go to https:// on the same host")
+            }
+        }
+    }
+}
+
+/// Problems with an HSTS header value
+pub fn header_problems(value: Option<&str>) -> Vec<Problem> {
+    let Some(value) = value else {
+        return vec![Problem::NoHeader];
+    };
+    let hsts = parse(value);
+    let mut problems = Vec::new();
+    if hsts.max_age.is_none_or(|age| age < PRELOAD_MIN_MAX_AGE) {
+        problems.push(Problem::MaxAgeTooShort(hsts.max_age));
+    }
+    if !hsts.include_subdomains {
+        problems.push(Problem::NoIncludeSubDomains);
+    }
+    if !hsts.preload {
+        problems.push(Problem::NoPreload);
+    }
+    problems
+}
+
+/// Problems with the first response to `http://<host>/` (its `Location`, if a redirect)
+pub fn redirect_problems(host: &str, location: Option<&str>) -> Vec<Problem> {
+    let Some(location) = location else {
+        return vec![Problem::NoHttpRedirect];
+    };
+    let target_host = location
+        .strip_prefix("https://")
+        .and_then(|rest| rest.split(['/', '?', '#']).next())
+        .map(|authority| authority.trim_end_matches(":443"));
+    if target_host.is_some_and(|target| target.eq_ignore_ascii_case(host)) {
+        Vec::new()
+    } else {
+        vec![Problem::RedirectElsewhere(location.to_string())]
+    }
+}
+
+#[cfg(feature = "network")]
+pub use online::check_domain;
+
+#[cfg(feature = "network")]
+mod online {
+    use super::{header_problems, redirect_problems, Problem};
+    use anyhow::{Context, Result};
+
+    /// Check `host` against the preload requirements
+    ///
+    /// The HTTPS request fails outright on an invalid certificate, which the
+    /// preload list also rejects.
+    pub fn check_domain(host: &str) -> Result<Vec<Problem>> {
+        let agent = crate::net::agent_without_redirects();
+
+        let https = format!("https://{host}/");
+        let response = agent.get(&https).call().with_context(|| format!("Failed to fetch {https}"))?;
+        let header = response
+            .headers()
+            .get("strict-transport-security")
+            .and_then(|value| value.to_str().ok());
+        let mut problems = header_problems(header);
+
+        let http = format!("http://{host}/");
+        let response = agent.get(&http).call().with_context(|| format!("Failed to fetch {http}"))?;
+        let location = response
+            .status()
+            .is_redirection()
+            .then(|| response.headers().get("location").and_then(|value| value.to_str().ok()))
+            .flatten();
+        problems.extend(redirect_problems(host, location));
+        Ok(problems)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_directives() {
+        assert_eq!(
+            parse("max-age=\"63072000\"; includeSubDomains; PRELOAD"),
+            Hsts { max_age: Some(63_072_000), include_subdomains: true, preload: true }
+        );
+        assert_eq!(parse("max-age=abc").max_age, None);
+    }
+
+    #[test]
+    fn test_header_requirements() {
+        assert!(header_problems(Some("max-age=63072000; includeSubDomains; preload")).is_empty());
+        assert_eq!(header_problems(None), [Problem::NoHeader]);
+        assert_eq!(
+            header_problems(Some("max-age=86400")),
+            [Problem::MaxAgeTooShort(Some(86400)), Problem::NoIncludeSubDomains, Problem::NoPreload]
+        );
+    }
+
+    #[test]
+    fn test_redirect_must_stay_on_host() {
+        assert!(redirect_problems("example.com", Some("https://example.com/")).is_empty());
+        assert!(redirect_problems("example.com", Some("https://EXAMPLE.com:443")).is_empty());
+        assert_eq!(redirect_problems("example.com", None), [Problem::NoHttpRedirect]);
+        assert_eq!(
+            redirect_problems("example.com", Some("https://www.example.com/")),
+            [Problem::RedirectElsewhere("https://www.example.com/".to_string())]
+        );
+        assert_eq!(redirect_problems("example.com", Some("http://example.com/")).len(), 1);
+    }
+}
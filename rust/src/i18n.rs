@@ -0,0 +1,177 @@
+//! Site language and locale-aware dates
+//!
+//! `<time>` elements show the date the way the site's language writes it
+//! (`9 May 2024`, `9. Mai 2024`, `2024年5月9日`) while the `datetime`
+//! attribute stays RFC 3339. Languages without a table fall back to ISO dates.
+//! Callers pass dates already in the display zone.
+
+use chrono::{DateTime, Datelike, TimeZone};
+use serde::Deserialize;
+use std::fmt::Display;
+
+use crate::security::escape_html as escape;
+
+/// Language settings
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct I18nConfig {
+    /// BCP 47 tag (`en`, `en-GB`, `de`, `ja`, ...)
+    #[serde(default = "default_language")]
+    pub language: String,
+}
+
+impl Default for I18nConfig {
+    fn default() -> Self {
+        Self { language: default_language() }
+    }
+}
+
+fn default_language() -> String {
+    "en".to_string()
+}
+
+/// Month names and field order for one language
+struct Locale {
+    months: [&'static str; 12],
+    /// `{d}`, `{month}` and `{y}` are replaced
+    pattern: &'static str,
+}
+
+const ENGLISH: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November",
+    "December",
+];
+
+const EN_US: Locale = Locale { months: ENGLISH, pattern: "{month} {d}, {y}" };
+const EN: Locale = Locale { months: ENGLISH, pattern: "{d} {month} {y}" };
+const DE: Locale = Locale {
+    months: [
+        "Januar", "Februar", "März", "April", "Mai", "Juni", "Juli", "August", "September", "Oktober", "November",
+        "Dezember",
+    ],
+    pattern: "{d}. {month} {y}",
+};
+const FR: Locale = Locale {
+    months: [
+        "janvier", "février", "mars", "avril", "mai", "juin", "juillet", "août", "septembre", "octobre", "novembre",
+        "décembre",
+    ],
+    pattern: "{d} {month} {y}",
+};
+const ES: Locale = Locale {
+    months: [
+        "enero", "febrero", "marzo", "abril", "mayo", "junio", "julio", "agosto", "septiembre", "octubre", "noviembre",
+        "diciembre",
+    ],
+    pattern: "{d} de {month} de {y}",
+};
+const IT: Locale = Locale {
+    months: [
+        "gennaio", "febbraio", "marzo", "aprile", "maggio", "giugno", "luglio", "agosto", "settembre", "ottobre",
+        "novembre", "dicembre",
+    ],
+    pattern: "{d} {month} {y}",
+};
+const PT: Locale = Locale {
+    months: [
+        "janeiro", "fevereiro", "março", "abril", "maio", "junho", "julho", "agosto", "setembro", "outubro", "novembro",
+        "dezembro",
+    ],
+    pattern: "{d} de {month} de {y}",
+};
+const NL: Locale = Locale {
+    months: [
+        "januari", "februari", "maart", "april", "mei", "juni", "juli", "augustus", "september", "oktober", "november",
+        "december",
+    ],
+    pattern: "{d} {month} {y}",
+};
+/// Genitive forms, as used after a day number
+const RU: Locale = Locale {
+    months: [
+        "января", "февраля", "марта", "апреля", "мая", "июня", "июля", "августа", "сентября", "октября", "ноября",
+        "декабря",
+    ],
+    pattern: "{d} {month} {y} г.",
+};
+const NUMERIC: [&str; 12] = ["1", "2", "3", "4", "5", "6", "7", "8", "9", "10", "11", "12"];
+const JA_ZH: Locale = Locale { months: NUMERIC, pattern: "{y}年{month}月{d}日" };
+const KO: Locale = Locale { months: NUMERIC, pattern: "{y}년 {month}월 {d}일" };
+
+/// Table for a language tag, by primary subtag (`en-US` and bare `en` use US order)
+fn locale(language: &str) -> Option<&'static Locale> {
+    let tag = language.trim().to_ascii_lowercase().replace('_', "-");
+    let primary = tag.split('-').next().unwrap_or("");
+    Some(match primary {
+        "en" if tag == "en" || tag == "en-us" => &EN_US,
+        "en" => &EN,
+        "de" => &DE,
+        "fr" => &FR,
+        "es" => &ES,
+        "it" => &IT,
+        "pt" => &PT,
+        "nl" => &NL,
+        "ru" => &RU,
+        "ja" | "zh" => &JA_ZH,
+        "ko" => &KO,
+        _ => return None,
+    })
+}
+
+/// Human-readable date in `language`, ISO `YYYY-MM-DD` when the language is unknown
+pub fn format_date<Tz: TimeZone>(date: &DateTime<Tz>, language: &str) -> String
+where
+    Tz::Offset: Display,
+{
+    match locale(language) {
+        Some(locale) => locale
+            .pattern
+            .replace("{d}", &date.day().to_string())
+            .replace("{month}", locale.months[date.month0() as usize])
+            .replace("{y}", &date.year().to_string()),
+        None => date.format("%Y-%m-%d").to_string(),
+    }
+}
+
+/// `<time>` with an RFC 3339 `datetime` and the date in `language`
+pub fn time_element<Tz: TimeZone>(date: &DateTime<Tz>, language: &str, class: Option<&str>) -> String
+where
+    Tz::Offset: Display,
+{
+    let class = class.map(|c| format!(" class=\"{}\"", escape(c))).unwrap_or_default();
+    format!(
+        "<time{class} datetime=\"{}\">{}</time>",
+        date.to_rfc3339(),
+        escape(&format_date(date, language))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn may_9() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 5, 9, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_month_names_and_order() {
+        let date = may_9();
+        assert_eq!(format_date(&date, "en"), "May 9, 2024");
+        assert_eq!(format_date(&date, "en-GB"), "9 May 2024");
+        assert_eq!(format_date(&date, "de-AT"), "9. Mai 2024");
+        assert_eq!(format_date(&date, "es"), "9 de mayo de 2024");
+        assert_eq!(format_date(&date, "ru"), "9 мая 2024 г.");
+        assert_eq!(format_date(&date, "ja"), "2024年5月9日");
+        assert_eq!(format_date(&date, "ko"), "2024년 5월 9일");
+        assert_eq!(format_date(&date, "tlh"), "2024-05-09");
+    }
+
+    #[test]
+    fn test_time_element_keeps_machine_readable_datetime() {
+        assert_eq!(
+            time_element(&may_9(), "fr", Some("dt-published")),
+            "<time class=\"dt-published\" datetime=\"2024-05-09T12:00:00+00:00\">9 mai 2024</time>"
+        );
+    }
+}
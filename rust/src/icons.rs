@@ -0,0 +1,180 @@
+//! Favicon, touch icon and web manifest generation from a single source image
+
+use anyhow::{Context, Result};
+use image::codecs::ico::{IcoEncoder, IcoFrame};
+use image::imageops::FilterType;
+use image::{DynamicImage, ExtendedColorType, ImageFormat};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+use walkdir::WalkDir;
+
+use crate::{inject, Config};
+
+/// Sizes embedded in `favicon.ico`
+const ICO_SIZES: &[u32] = &[16, 32, 48];
+
+/// PNG icons written to the output root (file name, size)
+const PNG_ICONS: &[(&str, u32)] = &[
+    ("favicon-16x16.png", 16),
+    ("favicon-32x32.png", 32),
+    ("apple-touch-icon.png", 180),
+    ("icon-192x192.png", 192),
+    ("icon-512x512.png", 512),
+];
+
+/// Icon generation settings
+#[derive(Debug, Clone, Deserialize)]
+pub struct IconsConfig {
+    /// Square source image (PNG or JPEG, ideally 512x512 or larger)
+    pub source: PathBuf,
+    /// Browser UI color in the web manifest
+    #[serde(default = "default_color")]
+    pub theme_color: String,
+    /// Splash background color in the web manifest
+    #[serde(default = "default_color")]
+    pub background_color: String,
+}
+
+fn default_color() -> String {
+    "#0a0a0a".to_string()
+}
+
+/// `site.webmanifest` contents
+///
+/// Deliberately has no `serviceworker` member: the site never installs scripts.
+pub fn web_manifest(config: &Config, icons: &IconsConfig) -> Value {
+    json!({
+        "name": config.title,
+        "short_name": config.title,
+        "start_url": "/",
+        "display": "browser",
+        "theme_color": icons.theme_color,
+        "background_color": icons.background_color,
+        "icons": [
+            { "src": "/icon-192x192.png", "sizes": "192x192", "type": "image/png" },
+            { "src": "/icon-512x512.png", "sizes": "512x512", "type": "image/png" },
+        ],
+    })
+}
+
+/// `<link>` elements referencing the generated icons
+pub fn head_links() -> String {
+    concat!(
+        "<link rel=\"icon\" href=\"/favicon.ico\" sizes=\"48x48\">\n",
+        "<link rel=\"icon\" type=\"image/png\" sizes=\"32x32\" href=\"/favicon-32x32.png\">\n",
+        "<link rel=\"icon\" type=\"image/png\" sizes=\"16x16\" href=\"/favicon-16x16.png\">\n",
+        "<link rel=\"apple-touch-icon\" sizes=\"180x180\" href=\"/apple-touch-icon.png\">\n",
+        "<link rel=\"manifest\" href=\"/site.webmanifest\">\n",
+    )
+    .to_string()
+}
+
+fn resize(source: &DynamicImage, size: u32) -> DynamicImage {
+    source.resize_to_fill(size, size, FilterType::Lanczos3)
+}
+
+/// Encode a multi-resolution `favicon.ico` with PNG frames
+fn encode_ico(source: &DynamicImage) -> Result<Vec<u8>> {
+    let frames = ICO_SIZES
+        .iter()
+        .map(|&size| {
+            let rgba = resize(source, size).to_rgba8();
+            IcoFrame::as_png(rgba.as_raw(), size, size, ExtendedColorType::Rgba8)
+                .context("Failed to encode favicon frame")
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut ico = Vec::new();
+    IcoEncoder::new(Cursor::new(&mut ico))
+        .encode_images(&frames)
+        .context("Failed to encode favicon.ico")?;
+    Ok(ico)
+}
+
+/// Write icons and the manifest, then reference them from every page head
+pub fn generate(config: &Config, icons: &IconsConfig) -> Result<()> {
+    let source = image::open(&icons.source)
+        .with_context(|| format!("Failed to open icon source: {}", icons.source.display()))?;
+    let output = &config.output;
+
+    fs::write(output.join("favicon.ico"), encode_ico(&source)?)?;
+    for (name, size) in PNG_ICONS {
+        // Re-encoding from decoded pixels drops any metadata from the source
+        resize(&source, *size)
+            .save_with_format(output.join(name), ImageFormat::Png)
+            .with_context(|| format!("Failed to write {name}"))?;
+    }
+    fs::write(
+        output.join("site.webmanifest"),
+        serde_json::to_string_pretty(&web_manifest(config, icons))?,
+    )?;
+
+    link_pages(output)
+}
+
+/// Inject the icon links into every HTML page in the output that has a `<head>`
+///
+/// Fragments and verification files without a `</head>` are left as they are.
+fn link_pages(output_dir: &Path) -> Result<()> {
+    let links = head_links();
+    for entry in WalkDir::new(output_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| matches!(e.path().extension().and_then(|s| s.to_str()), Some("html" | "htm")))
+    {
+        let page = entry.path();
+        let html = fs::read_to_string(page).with_context(|| format!("Failed to read page: {}", page.display()))?;
+        match inject::insert_before(&html, &["</head>"], &links) {
+            Some(html) => fs::write(page, html).with_context(|| format!("Failed to write page: {}", page.display()))?,
+            None => debug!("No </head> for icon links: {}", page.display()),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn icons() -> IconsConfig {
+        IconsConfig {
+            source: PathBuf::from("logo.png"),
+            theme_color: default_color(),
+            background_color: default_color(),
+        }
+    }
+
+    #[test]
+    fn test_web_manifest_has_no_service_worker() {
+        let manifest = web_manifest(&Config::default(), &icons());
+        assert!(manifest.get("serviceworker").is_none());
+        assert_eq!(manifest["icons"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_encode_ico_contains_all_sizes() {
+        let source = DynamicImage::new_rgba8(64, 64);
+        let ico = encode_ico(&source).unwrap();
+        // ICONDIR header: reserved (0), type (1 = icon), image count
+        assert_eq!(&ico[..4], &[0, 0, 1, 0]);
+        assert_eq!(u16::from_le_bytes([ico[4], ico[5]]), 3);
+    }
+
+    #[test]
+    fn test_link_pages_skips_pages_without_head() {
+        let dir = std::env::temp_dir().join(format!("secureblog-icons-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("index.html"), "<html><head></head><body></body></html>").unwrap();
+        fs::write(dir.join("google123.html"), "google-site-verification: google123.html").unwrap();
+
+        link_pages(&dir).unwrap();
+        assert!(fs::read_to_string(dir.join("index.html")).unwrap().contains("/site.webmanifest"));
+        assert_eq!(fs::read_to_string(dir.join("google123.html")).unwrap(), "google-site-verification: google123.html");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
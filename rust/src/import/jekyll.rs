@@ -0,0 +1,273 @@
+//! Jekyll importer: `_posts/`, `_drafts/`, Liquid tags and `_config.yml`
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use serde_yaml::Value;
+use std::fs;
+use std::path::Path;
+use tracing::warn;
+use walkdir::WalkDir;
+
+use super::{parse_date, write_config, write_posts, ImportedConfig, ImportedPost};
+use crate::slug::slugify;
+use crate::status::PostStatus;
+use crate::PostMeta;
+
+/// `YYYY-MM-DD-name.md` post file names
+static POST_FILENAME: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(\d{4}-\d{2}-\d{2})-(.+)\.(?:md|markdown)$").unwrap());
+
+/// `{% raw %}...{% endraw %}` blocks, kept verbatim
+static RAW_BLOCK: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?s)\{%-?\s*raw\s*-?%\}(.*?)\{%-?\s*endraw\s*-?%\}").unwrap());
+
+static HIGHLIGHT_START: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{%-?\s*highlight\s+([\w+#.-]+)[^%]*-?%\}").unwrap());
+
+static HIGHLIGHT_END: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{%-?\s*endhighlight\s*-?%\}").unwrap());
+
+static POST_URL: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{%-?\s*post_url\s+(\S+?)\s*-?%\}").unwrap());
+
+/// `{{ site.baseurl }}` / `{{ site.url }}` prefixes (links become site-relative)
+static SITE_URL: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{\{-?\s*site\.(?:baseurl|url)\s*-?\}\}").unwrap());
+
+/// `{{ '/path' | relative_url }}` filters
+static URL_FILTER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"\{\{-?\s*['"]([^'"]*)['"]\s*\|\s*(?:relative_url|absolute_url)\s*-?\}\}"#).unwrap()
+});
+
+/// Any Liquid tag or output left after conversion
+static LIQUID: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{%.*?%\}|\{\{.*?\}\}").unwrap());
+
+/// Convert Liquid tags to plain markdown, returning tags that could not be converted
+pub fn convert_body(body: &str) -> (String, Vec<String>) {
+    let mut output = String::with_capacity(body.len());
+    let mut unconverted = Vec::new();
+    let mut last = 0;
+
+    for block in RAW_BLOCK.captures_iter(body) {
+        let whole = block.get(0).expect("match");
+        output.push_str(&convert_segment(&body[last..whole.start()], &mut unconverted));
+        output.push_str(&block[1]);
+        last = whole.end();
+    }
+    output.push_str(&convert_segment(&body[last..], &mut unconverted));
+
+    (output, unconverted)
+}
+
+fn convert_segment(text: &str, unconverted: &mut Vec<String>) -> String {
+    let text = HIGHLIGHT_START.replace_all(text, "```$1");
+    let text = HIGHLIGHT_END.replace_all(&text, "```");
+    let text = POST_URL.replace_all(&text, |cap: &Captures<'_>| {
+        let name = cap[1].rsplit('/').next().unwrap_or_default();
+        let slug = POST_FILENAME
+            .captures(&format!("{name}.md"))
+            .map_or_else(|| slugify(name), |c| slugify(&c[2]));
+        format!("/{slug}.html")
+    });
+    let text = SITE_URL.replace_all(&text, "");
+    let text = URL_FILTER.replace_all(&text, "$1");
+
+    unconverted.extend(LIQUID.find_iter(&text).map(|m| m.as_str().to_string()));
+    text.into_owned()
+}
+
+/// Split `---` YAML frontmatter from the body
+fn split_frontmatter(content: &str) -> Option<(&str, &str)> {
+    let rest = content.strip_prefix("---")?;
+    let rest = rest.strip_prefix("\r\n").or_else(|| rest.strip_prefix('\n'))?;
+    let (yaml, after) = if let Some(after) = rest.strip_prefix("---") {
+        ("", after)
+    } else {
+        let end = rest.find("\n---")?;
+        (&rest[..end], &rest[end + 4..])
+    };
+    let body = after.split_once('\n').map_or("", |(_, body)| body);
+    Some((yaml, body))
+}
+
+fn as_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Jekyll `tags`/`categories`: a YAML list or a space-separated string
+fn word_list(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::Sequence(items)) => items.iter().filter_map(as_text).collect(),
+        Some(value) => as_text(value)
+            .map(|s| s.split_whitespace().map(str::to_string).collect())
+            .unwrap_or_default(),
+        None => Vec::new(),
+    }
+}
+
+/// Convert one Jekyll post; `draft` marks files from `_drafts/`
+pub fn convert_post(path: &Path, content: &str, draft: bool, now: DateTime<Utc>) -> Result<ImportedPost> {
+    let (yaml, body) = split_frontmatter(content).context("Missing YAML front matter")?;
+    let front: Value = if yaml.trim().is_empty() {
+        Value::Null
+    } else {
+        serde_yaml::from_str(yaml).context("Invalid YAML front matter")?
+    };
+    let field = |key: &str| front.get(key);
+
+    let file_name = path.file_name().and_then(|s| s.to_str()).unwrap_or_default();
+    let (file_date, file_slug) = POST_FILENAME.captures(file_name).map_or_else(
+        || (None, file_name.rsplit_once('.').map_or(file_name, |(stem, _)| stem).to_string()),
+        |cap| (parse_date(&cap[1]), cap[2].to_string()),
+    );
+
+    let title = field("title")
+        .and_then(as_text)
+        .unwrap_or_else(|| file_slug.replace('-', " "));
+    let slug = slugify(&field("slug").and_then(as_text).unwrap_or_else(|| file_slug.clone()));
+    let date = field("date")
+        .and_then(as_text)
+        .and_then(|d| parse_date(&d))
+        .or(file_date)
+        .unwrap_or(now);
+
+    let mut tags = word_list(field("categories").or_else(|| field("category")));
+    for tag in word_list(field("tags")).into_iter().chain(word_list(field("tag"))) {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+
+    let unpublished = matches!(field("published"), Some(Value::Bool(false)));
+    let status = if draft || unpublished { PostStatus::Draft } else { PostStatus::Published };
+
+    let (body, unconverted) = convert_body(body);
+    for tag in unconverted {
+        warn!("{}: unconverted Liquid tag {}", path.display(), tag);
+    }
+
+    Ok(ImportedPost {
+        meta: PostMeta {
+            title,
+            date,
+            tags,
+            slug,
+            status,
+            ..PostMeta::default()
+        },
+        body,
+        source: path.to_path_buf(),
+    })
+}
+
+/// Map `_config.yml` onto our configuration
+pub fn convert_config(content: &str) -> Result<ImportedConfig> {
+    let config: Value = serde_yaml::from_str(content).context("Invalid _config.yml")?;
+    let text = |key: &str| config.get(key).and_then(as_text).unwrap_or_default();
+
+    let url = format!(
+        "{}{}",
+        text("url").trim_end_matches('/'),
+        text("baseurl").trim_end_matches('/')
+    );
+    let author = match config.get("author") {
+        Some(Value::Mapping(author)) => author.get("name").and_then(as_text).unwrap_or_default(),
+        Some(value) => as_text(value).unwrap_or_default(),
+        None => String::new(),
+    };
+
+    Ok(ImportedConfig {
+        title: text("title"),
+        url,
+        author,
+    })
+}
+
+/// Import a Jekyll site into `content_dir`, writing `config_path` if absent
+///
+/// Returns the number of posts written.
+pub fn import(site: &Path, content_dir: &Path, config_path: &Path) -> Result<usize> {
+    let now = Utc::now();
+    let mut posts = Vec::new();
+
+    for (dir, draft) in [("_posts", false), ("_drafts", true)] {
+        for entry in WalkDir::new(site.join(dir))
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|e| e.file_type().is_file())
+            .filter(|e| matches!(e.path().extension().and_then(|s| s.to_str()), Some("md" | "markdown")))
+        {
+            let path = entry.path();
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            match convert_post(path, &content, draft, now) {
+                Ok(post) => posts.push(post),
+                Err(e) => warn!("Skipping {}: {:#}", path.display(), e),
+            }
+        }
+    }
+
+    let jekyll_config = site.join("_config.yml");
+    if jekyll_config.exists() {
+        write_config(config_path, &convert_config(&fs::read_to_string(&jekyll_config)?)?)?;
+    }
+
+    write_posts(content_dir, &posts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_convert_post_from_filename_and_quirks() {
+        let content = "---\nlayout: post\ntitle:  404 Not Found\ndate: 2015-03-04 10:00:00 +0200\ncategories: web security\ntags: [xss, web]\n---\nHello\n";
+        let post = convert_post(Path::new("_posts/2015-03-04-not-found.md"), content, false, Utc::now()).unwrap();
+        assert_eq!(post.meta.title, "404 Not Found");
+        assert_eq!(post.meta.slug, "not-found");
+        assert_eq!(post.meta.date, Utc.with_ymd_and_hms(2015, 3, 4, 8, 0, 0).unwrap());
+        assert_eq!(post.meta.tags, ["web", "security", "xss"]);
+        assert_eq!(post.meta.status, PostStatus::Published);
+        assert_eq!(post.body, "Hello\n");
+    }
+
+    #[test]
+    fn test_convert_post_unpublished_and_defaults() {
+        let post = convert_post(Path::new("_posts/2020-05-06-quick-note.md"), "---\npublished: false\n---\n", false, Utc::now()).unwrap();
+        assert_eq!(post.meta.title, "quick note");
+        assert_eq!(post.meta.date, Utc.with_ymd_and_hms(2020, 5, 6, 0, 0, 0).unwrap());
+        assert_eq!(post.meta.status, PostStatus::Draft);
+        assert!(convert_post(Path::new("_posts/a.md"), "no front matter", false, Utc::now()).is_err());
+        let draft = convert_post(Path::new("_drafts/idea.md"), "---\n---\nText\n", true, Utc::now()).unwrap();
+        assert_eq!((draft.meta.slug.as_str(), draft.meta.status), ("idea", PostStatus::Draft));
+        assert_eq!(draft.body, "Text\n");
+    }
+
+    #[test]
+    fn test_convert_body_liquid() {
+        let body = "{% highlight ruby linenos %}\nputs 1\n{% endhighlight %}\n[x]({% post_url 2010-07-21-old-post %}) \
+                    ![i]({{ site.baseurl }}/img/a.png) [c]({{ '/about/' | relative_url }})\n\
+                    {% raw %}{{ not_converted }}{% endraw %} {% include note.html %}";
+        let (out, unconverted) = convert_body(body);
+        assert!(out.starts_with("```ruby\nputs 1\n```\n"));
+        assert!(out.contains("[x](/old-post.html)"));
+        assert!(out.contains("![i](/img/a.png)"));
+        assert!(out.contains("[c](/about/)"));
+        assert!(out.contains("{{ not_converted }}"));
+        assert_eq!(unconverted, ["{% include note.html %}"]);
+    }
+
+    #[test]
+    fn test_convert_config() {
+        let config = convert_config("title: Sec Notes\nurl: https://me.github.io/\nbaseurl: /blog\nauthor:\n  name: Ada\n").unwrap();
+        assert_eq!(config.title, "Sec Notes");
+        assert_eq!(config.url, "https://me.github.io/blog");
+        assert_eq!(config.author, "Ada");
+    }
+}
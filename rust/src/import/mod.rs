@@ -0,0 +1,141 @@
+//! Importers from other static site generators
+
+pub mod jekyll;
+pub mod zola;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::PostMeta;
+
+/// A post converted from another generator
+#[derive(Debug, Clone)]
+pub struct ImportedPost {
+    /// Converted frontmatter
+    pub meta: PostMeta,
+    /// Markdown body
+    pub body: String,
+    /// Original file (for messages)
+    pub source: PathBuf,
+}
+
+/// Site settings mapped onto `config.yaml`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ImportedConfig {
+    /// Site title
+    pub title: String,
+    /// Site URL
+    pub url: String,
+    /// Author name
+    pub author: String,
+}
+
+/// Parse the date formats other generators accept in frontmatter and filenames
+///
+/// Timestamps without an offset are taken as UTC.
+pub fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Some(date.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%d %H:%M:%S %z", "%Y-%m-%d %H:%M %z", "%Y-%m-%dT%H:%M:%S%z"] {
+        if let Ok(date) = DateTime::parse_from_str(value, format) {
+            return Some(date.with_timezone(&Utc));
+        }
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"] {
+        if let Ok(date) = NaiveDateTime::parse_from_str(value, format) {
+            return Some(date.and_utc());
+        }
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|date| date.and_utc())
+}
+
+/// Serialize a post as `---` YAML frontmatter followed by its body
+pub fn to_markdown(post: &ImportedPost) -> Result<String> {
+    let yaml = serde_yaml::to_string(&post.meta)?;
+    Ok(format!("---\n{yaml}---\n\n{}\n", post.body.trim()))
+}
+
+/// Write imported posts into `content_dir`, never overwriting existing files
+///
+/// Returns the number of posts written.
+pub fn write_posts(content_dir: &Path, posts: &[ImportedPost]) -> Result<usize> {
+    fs::create_dir_all(content_dir)?;
+    let mut written = 0;
+
+    for post in posts {
+        let path = content_dir.join(format!("{}.md", post.meta.slug));
+        if path.exists() {
+            warn!("Skipping {}: {} already exists", post.source.display(), path.display());
+            continue;
+        }
+        fs::write(&path, to_markdown(post)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        info!("Imported {} -> {}", post.source.display(), path.display());
+        written += 1;
+    }
+
+    Ok(written)
+}
+
+/// Write `config.yaml` from imported settings unless one already exists
+pub fn write_config(path: &Path, config: &ImportedConfig) -> Result<()> {
+    if path.exists() {
+        warn!(
+            "{} exists, not overwriting; imported title: {:?}, url: {:?}, author: {:?}",
+            path.display(),
+            config.title,
+            config.url,
+            config.author
+        );
+        return Ok(());
+    }
+    fs::write(path, serde_yaml::to_string(config)?)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    info!("Wrote {}", path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse_date_formats() {
+        let expected = Utc.with_ymd_and_hms(2019, 1, 2, 9, 0, 0).unwrap();
+        assert_eq!(parse_date("2019-01-02 10:00:00 +0100"), Some(expected));
+        assert_eq!(parse_date("2019-01-02T09:00:00Z"), Some(expected));
+        assert_eq!(parse_date("2019-01-02 09:00"), Some(expected));
+        assert_eq!(parse_date("2019-01-02"), Some(Utc.with_ymd_and_hms(2019, 1, 2, 0, 0, 0).unwrap()));
+        assert_eq!(parse_date("January 2nd"), None);
+    }
+
+    #[test]
+    fn test_to_markdown_round_trips_frontmatter() {
+        let post = ImportedPost {
+            meta: PostMeta {
+                title: "Hello: world".to_string(),
+                slug: "hello".to_string(),
+                tags: vec!["xss".to_string()],
+                ..PostMeta::default()
+            },
+            body: "Body\n".to_string(),
+            source: PathBuf::from("_posts/2019-01-02-hello.md"),
+        };
+        let markdown = to_markdown(&post).unwrap();
+        let yaml = markdown.trim_start_matches("---\n").split("---\n").next().unwrap();
+        let meta: PostMeta = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(meta.title, "Hello: world");
+        assert_eq!(meta.tags, ["xss"]);
+        assert!(markdown.ends_with("---\n\nBody\n"));
+    }
+}
@@ -0,0 +1,256 @@
+//! Zola importer: TOML frontmatter, sections, taxonomies and `config.toml`
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use std::fs;
+use std::path::Path;
+use toml::{Table, Value};
+use tracing::warn;
+use walkdir::WalkDir;
+
+use super::{parse_date, write_config, write_posts, ImportedConfig, ImportedPost};
+use crate::slug::slugify;
+use crate::status::PostStatus;
+use crate::PostMeta;
+
+/// Section index file name
+const SECTION_INDEX: &str = "_index.md";
+
+/// `YYYY-MM-DD-name` / `YYYY-MM-DD_name` page file stems
+static DATED_STEM: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(\d{4}-\d{2}-\d{2})[-_](.+)$").unwrap());
+
+/// Content-relative links such as `@/blog/post.md#anchor`
+static INTERNAL_LINK: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\]\(@/([^)#\s]+?)\.md(#[^)\s]*)?\)").unwrap());
+
+/// Shortcode calls, which have no equivalent here
+static SHORTCODE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{\{\s*\w+\(.*?\)\s*\}\}|\{%\s*(?:end|\w+\(.*?\))\s*%\}").unwrap());
+
+/// Split `+++` TOML frontmatter from the body
+fn split_frontmatter(content: &str) -> Option<(&str, &str)> {
+    let rest = content.strip_prefix("+++")?;
+    let rest = rest.strip_prefix("\r\n").or_else(|| rest.strip_prefix('\n'))?;
+    let (toml, after) = if let Some(after) = rest.strip_prefix("+++") {
+        ("", after)
+    } else {
+        let end = rest.find("\n+++")?;
+        (&rest[..end], &rest[end + 4..])
+    };
+    let body = after.split_once('\n').map_or("", |(_, body)| body);
+    Some((toml, body))
+}
+
+fn parse_table(toml: &str) -> Result<Table> {
+    toml.parse::<Table>().context("Invalid TOML front matter")
+}
+
+/// TOML datetimes and date strings
+fn date_value(value: Option<&Value>) -> Option<DateTime<Utc>> {
+    match value? {
+        Value::Datetime(date) => parse_date(&date.to_string()),
+        Value::String(date) => parse_date(date),
+        _ => None,
+    }
+}
+
+/// Page slug: frontmatter `slug`, the bundle directory for `index.md`, else the file stem
+fn page_slug(path: &Path, front: &Table) -> (Option<DateTime<Utc>>, String) {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+    let stem = if stem == "index" {
+        path.parent()
+            .and_then(Path::file_name)
+            .and_then(|s| s.to_str())
+            .unwrap_or(stem)
+    } else {
+        stem
+    };
+    let (date, name) = DATED_STEM.captures(stem).map_or((None, stem), |cap| {
+        (parse_date(cap.get(1).map_or("", |m| m.as_str())), cap.get(2).map_or(stem, |m| m.as_str()))
+    });
+    let slug = front.get("slug").and_then(Value::as_str).unwrap_or(name);
+    (date, slugify(slug))
+}
+
+/// Rewrite `@/section/page.md` links to our flat URLs, returning shortcodes left in place
+pub fn convert_body(body: &str) -> (String, Vec<String>) {
+    let converted = INTERNAL_LINK.replace_all(body, |cap: &Captures<'_>| {
+        let path = &cap[1];
+        let name = path.strip_suffix("/index").unwrap_or(path);
+        let stem = name.rsplit('/').next().unwrap_or(name);
+        let stem = DATED_STEM.captures(stem).map_or(stem, |c| c.get(2).map_or(stem, |m| m.as_str()));
+        format!("](/{}.html{})", slugify(stem), cap.get(2).map_or("", |m| m.as_str()))
+    });
+    let shortcodes = SHORTCODE.find_iter(&converted).map(|m| m.as_str().to_string()).collect();
+    (converted.into_owned(), shortcodes)
+}
+
+/// Convert one Zola page; `section` is the title of the section it belongs to
+pub fn convert_page(path: &Path, content: &str, section: Option<&str>, now: DateTime<Utc>) -> Result<ImportedPost> {
+    let (toml, body) = split_frontmatter(content).context("Missing +++ TOML front matter")?;
+    let front = parse_table(toml)?;
+    let (file_date, slug) = page_slug(path, &front);
+
+    let title = front
+        .get("title")
+        .and_then(Value::as_str)
+        .map_or_else(|| slug.replace('-', " "), str::to_string);
+    let date = date_value(front.get("date")).or(file_date).unwrap_or(now);
+    let updated = date_value(front.get("updated")).filter(|updated| *updated > date);
+
+    // Every taxonomy term becomes a tag, plus the section the page lives in
+    let mut tags: Vec<String> = section.map(str::to_string).into_iter().collect();
+    if let Some(taxonomies) = front.get("taxonomies").and_then(Value::as_table) {
+        for (_, terms) in taxonomies.iter() {
+            for term in terms.as_array().into_iter().flatten().filter_map(Value::as_str) {
+                if !tags.iter().any(|t| t == term) {
+                    tags.push(term.to_string());
+                }
+            }
+        }
+    }
+
+    let draft = front.get("draft").and_then(Value::as_bool).unwrap_or(false);
+    let (body, shortcodes) = convert_body(body);
+    for shortcode in shortcodes {
+        warn!("{}: unconverted shortcode {}", path.display(), shortcode);
+    }
+
+    Ok(ImportedPost {
+        meta: PostMeta {
+            title,
+            date,
+            updated,
+            tags,
+            slug,
+            status: if draft { PostStatus::Draft } else { PostStatus::Published },
+            ..PostMeta::default()
+        },
+        body,
+        source: path.to_path_buf(),
+    })
+}
+
+/// Title of the nearest section above `page` (the root section is not a tag)
+fn section_of(content_dir: &Path, page: &Path) -> Result<Option<String>> {
+    let bundle = page.file_name().and_then(|s| s.to_str()) == Some("index.md");
+    // A bundle's own directory is the page, not a section
+    let skip = if bundle { 2 } else { 1 };
+
+    for dir in page.ancestors().skip(skip) {
+        if dir == content_dir || !dir.starts_with(content_dir) {
+            break;
+        }
+        let index = dir.join(SECTION_INDEX);
+        if index.is_file() {
+            let content = fs::read_to_string(&index)?;
+            let title = split_frontmatter(&content)
+                .and_then(|(toml, _)| parse_table(toml).ok())
+                .and_then(|front| front.get("title").and_then(Value::as_str).map(str::to_string));
+            let name = dir.file_name().and_then(|s| s.to_str()).unwrap_or_default();
+            return Ok(Some(title.unwrap_or_else(|| name.to_string())));
+        }
+    }
+    Ok(None)
+}
+
+/// Map `config.toml` onto our configuration
+pub fn convert_config(content: &str) -> Result<ImportedConfig> {
+    let config = content.parse::<Table>().context("Invalid config.toml")?;
+    let text = |value: Option<&Value>| value.and_then(Value::as_str).unwrap_or_default().to_string();
+
+    Ok(ImportedConfig {
+        title: text(config.get("title")),
+        url: text(config.get("base_url")).trim_end_matches('/').to_string(),
+        author: config
+            .get("author")
+            .or_else(|| config.get("extra").and_then(|extra| extra.get("author")))
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+    })
+}
+
+/// Import a Zola site into `content_dir`, writing `config_path` if absent
+///
+/// Returns the number of posts written.
+pub fn import(site: &Path, content_dir: &Path, config_path: &Path) -> Result<usize> {
+    let now = Utc::now();
+    let source = site.join("content");
+    let mut posts = Vec::new();
+
+    for entry in WalkDir::new(&source)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+    {
+        let path = entry.path();
+        let name = path.file_name().and_then(|s| s.to_str()).unwrap_or_default();
+        if name == SECTION_INDEX {
+            continue;
+        }
+        if path.extension().and_then(|s| s.to_str()) != Some("md") {
+            warn!("Co-located asset not imported: {}", path.display());
+            continue;
+        }
+
+        let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let section = section_of(&source, path)?;
+        match convert_page(path, &content, section.as_deref(), now) {
+            Ok(post) => posts.push(post),
+            Err(e) => warn!("Skipping {}: {:#}", path.display(), e),
+        }
+    }
+
+    let zola_config = site.join("config.toml");
+    if zola_config.exists() {
+        write_config(config_path, &convert_config(&fs::read_to_string(&zola_config)?)?)?;
+    }
+
+    write_posts(content_dir, &posts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_convert_page_frontmatter_and_taxonomies() {
+        let content = "+++\ntitle = \"Rust fuzzing\"\ndate = 2021-02-03T04:05:06Z\nupdated = \"2021-03-01\"\n\n[taxonomies]\ntags = [\"rust\", \"fuzzing\"]\ncategories = [\"Research\"]\n\n[extra]\ntoc = true\n+++\nBody\n";
+        let post = convert_page(Path::new("content/blog/fuzz.md"), content, Some("Blog"), Utc::now()).unwrap();
+        assert_eq!(post.meta.title, "Rust fuzzing");
+        assert_eq!(post.meta.slug, "fuzz");
+        assert_eq!(post.meta.date, Utc.with_ymd_and_hms(2021, 2, 3, 4, 5, 6).unwrap());
+        assert_eq!(post.meta.updated, Some(Utc.with_ymd_and_hms(2021, 3, 1, 0, 0, 0).unwrap()));
+        assert_eq!(post.meta.tags, ["Blog", "Research", "rust", "fuzzing"]);
+        assert_eq!(post.body, "Body\n");
+    }
+
+    #[test]
+    fn test_bundles_dated_stems_and_drafts() {
+        let post = convert_page(Path::new("content/blog/2020-01-02_kernel-bug/index.md"), "+++\ndraft = true\n+++\n", None, Utc::now()).unwrap();
+        assert_eq!(post.meta.slug, "kernel-bug");
+        assert_eq!(post.meta.date, Utc.with_ymd_and_hms(2020, 1, 2, 0, 0, 0).unwrap());
+        assert_eq!(post.meta.status, PostStatus::Draft);
+        assert!(convert_page(Path::new("content/a.md"), "---\ntitle: yaml\n---\n", None, Utc::now()).is_err());
+    }
+
+    #[test]
+    fn test_convert_body_links_and_shortcodes() {
+        let (body, shortcodes) = convert_body("See [a](@/blog/2020-01-02-old.md#fix) and [b](@/notes/bundle/index.md).\n{{ youtube(id=\"x\") }}\n");
+        assert!(body.contains("[a](/old.html#fix)"));
+        assert!(body.contains("[b](/bundle.html)"));
+        assert_eq!(shortcodes, ["{{ youtube(id=\"x\") }}"]);
+    }
+
+    #[test]
+    fn test_convert_config() {
+        let config = convert_config("base_url = \"https://sec.example/\"\ntitle = \"Notes\"\n\n[extra]\nauthor = \"Lin\"\n").unwrap();
+        assert_eq!(config.title, "Notes");
+        assert_eq!(config.url, "https://sec.example");
+        assert_eq!(config.author, "Lin");
+    }
+}
@@ -0,0 +1,210 @@
+//! `{{< include file="src/foo.rs" lines="10-30" hash="sha256:..." >}}`
+//!
+//! Replaces the directive with a fenced code block holding those lines of a
+//! repository file. The hash pins the snippet: if the file changes under the
+//! included lines the build fails until the new version is reviewed and the
+//! pin updated, so a post never silently publishes code nobody looked at.
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path};
+
+/// The directive, alone on its line
+static DIRECTIVE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*\{\{<\s*include\s+(.*?)\s*>\}\}\s*$").unwrap());
+
+/// `key="value"` attributes
+static ATTRIBUTE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(\w+)="([^"]*)""#).unwrap());
+
+/// Start of a fenced code block (directives inside one are left alone)
+static FENCE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s{0,3}(`{3,}|~{3,})").unwrap());
+
+/// Code fence language for a file extension
+fn language(path: &Path) -> &str {
+    match path.extension().and_then(|e| e.to_str()).unwrap_or("") {
+        "rs" => "rust",
+        "py" => "python",
+        "js" | "mjs" => "javascript",
+        "ts" => "typescript",
+        "sh" | "bash" => "sh",
+        "yml" => "yaml",
+        "h" => "c",
+        "md" => "markdown",
+        other => other,
+    }
+}
+
+/// Select `lines` (`N` or `N-M`, 1-based and inclusive) from `text`
+fn select_lines(text: &str, lines: Option<&str>) -> Result<String> {
+    let all: Vec<&str> = text.lines().collect();
+    let (start, end) = match lines {
+        None => (1, all.len()),
+        Some(range) => {
+            let (start, end) = range.split_once('-').unwrap_or((range, range));
+            let parse = |n: &str| n.trim().parse::<usize>().with_context(|| format!("Invalid lines=\"{range}\""));
+            (parse(start)?, parse(end)?)
+        }
+    };
+    if start == 0 || start > end || end > all.len() {
+        anyhow::bail!("Lines {start}-{end} are outside the file ({} lines)", all.len());
+    }
+    let mut snippet = all[start - 1..end].join("\n");
+    snippet.push('\n');
+    Ok(snippet)
+}
+
+/// `sha256:<hex>` of a snippet
+pub fn snippet_hash(snippet: &str) -> String {
+    format!("sha256:{:x}", Sha256::digest(snippet.as_bytes()))
+}
+
+/// Fenced block longer than any backtick run inside the snippet
+fn fenced(snippet: &str, language: &str) -> String {
+    let longest = snippet
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{fence}{language}\n{snippet}{fence}")
+}
+
+/// Expand one directive's attributes into a code block
+fn expand_directive(attributes: &str, root: &Path) -> Result<String> {
+    let mut attrs = HashMap::new();
+    for capture in ATTRIBUTE.captures_iter(attributes) {
+        let key = &capture[1];
+        if !matches!(key, "file" | "lines" | "hash") {
+            anyhow::bail!("Unknown include attribute: {key}");
+        }
+        attrs.insert(key.to_string(), capture[2].to_string());
+    }
+    let file = attrs.get("file").context("include needs file=\"...\"")?;
+
+    let relative = Path::new(file);
+    if file.contains('\\') || !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+        anyhow::bail!("Included file must be a relative path inside the repository: {file}");
+    }
+    let root = root.canonicalize().with_context(|| format!("Cannot resolve {}", root.display()))?;
+    let path = root.join(relative).canonicalize().with_context(|| format!("Included file not found: {file}"))?;
+    if !path.starts_with(&root) {
+        anyhow::bail!("Included file resolves outside the repository: {file}");
+    }
+
+    let text = fs::read_to_string(&path).with_context(|| format!("Failed to read {file}"))?;
+    let snippet = select_lines(&text, attrs.get("lines").map(String::as_str)).with_context(|| format!("In {file}"))?;
+    let actual = snippet_hash(&snippet);
+    match attrs.get("hash") {
+        None => anyhow::bail!("include of {file} has no hash pin; review it and add hash=\"{actual}\""),
+        Some(expected) if !expected.trim().eq_ignore_ascii_case(&actual) => {
+            anyhow::bail!("{file} changed since it was reviewed: pinned {expected}, now {actual}")
+        }
+        Some(_) => Ok(fenced(&snippet, language(relative))),
+    }
+}
+
+/// Expand every include directive in `markdown`, resolving files against `root`
+pub fn expand(markdown: &str, root: &Path) -> Result<String> {
+    if !markdown.contains("{{<") {
+        return Ok(markdown.to_string());
+    }
+    let mut output = String::with_capacity(markdown.len());
+    let mut fence: Option<String> = None;
+    for (number, line) in markdown.lines().enumerate() {
+        if let Some(open) = &fence {
+            if line.trim_start().starts_with(open.as_str()) {
+                fence = None;
+            }
+        } else if let Some(capture) = FENCE.captures(line) {
+            fence = Some(capture[1].to_string());
+        } else if let Some(capture) = DIRECTIVE.captures(line) {
+            let block = expand_directive(&capture[1], root).with_context(|| format!("include on line {}", number + 1))?;
+            output.push_str(&block);
+            output.push('\n');
+            continue;
+        }
+        output.push_str(line);
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+/// Repository files named by include directives (for `check --changed`)
+pub fn included_files(markdown: &str) -> Vec<String> {
+    markdown
+        .lines()
+        .filter_map(|line| DIRECTIVE.captures(line))
+        .flat_map(|capture| {
+            ATTRIBUTE
+                .captures_iter(&capture[1])
+                .filter(|a| &a[1] == "file")
+                .map(|a| a[2].to_string())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_included_files() {
+        let markdown = "Intro\n{{< include file=\"src/a.rs\" lines=\"1-2\" hash=\"sha256:x\" >}}\ntext file=\"b\"\n";
+        assert_eq!(included_files(markdown), ["src/a.rs"]);
+    }
+
+    #[test]
+    fn test_select_lines() {
+        let text = "one\ntwo\nthree\nfour\n";
+        assert_eq!(select_lines(text, Some("2-3")).unwrap(), "two\nthree\n");
+        assert_eq!(select_lines(text, Some("4")).unwrap(), "four\n");
+        assert_eq!(select_lines(text, None).unwrap(), text);
+        assert!(select_lines(text, Some("0-2")).is_err());
+        assert!(select_lines(text, Some("3-2")).is_err());
+        assert!(select_lines(text, Some("3-9")).is_err());
+        assert!(select_lines(text, Some("a-b")).is_err());
+    }
+
+    #[test]
+    fn test_fence_outlasts_backticks_in_snippet() {
+        assert_eq!(fenced("let x = 1;\n", "rust"), "```rust\nlet x = 1;\n```");
+        assert_eq!(fenced("````\n", ""), "`````\n````\n`````");
+    }
+
+    #[test]
+    fn test_expand_pins_the_snippet() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let snippet = select_lines(&fs::read_to_string(root.join("Cargo.toml")).unwrap(), Some("1-2")).unwrap();
+        let hash = snippet_hash(&snippet);
+
+        let markdown = format!("Intro\n{{{{< include file=\"Cargo.toml\" lines=\"1-2\" hash=\"{hash}\" >}}}}\nOutro\n");
+        assert_eq!(
+            expand(&markdown, root).unwrap(),
+            format!("Intro\n```toml\n{snippet}```\nOutro\n")
+        );
+
+        let stale = markdown.replace(&hash, &snippet_hash("old\n"));
+        assert!(expand(&stale, root).unwrap_err().chain().any(|e| e.to_string().contains("changed since it was reviewed")));
+        let unpinned = "{{< include file=\"Cargo.toml\" lines=\"1-2\" >}}\n";
+        assert!(expand(unpinned, root).unwrap_err().chain().any(|e| e.to_string().contains(&hash)));
+    }
+
+    #[test]
+    fn test_expand_rejects_paths_outside_the_repository() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        for file in ["../README.md", "/etc/passwd", "src/../../x", "src\\main.rs"] {
+            let markdown = format!("{{{{< include file=\"{file}\" hash=\"sha256:00\" >}}}}\n");
+            assert!(expand(&markdown, root).is_err(), "{file} accepted");
+        }
+    }
+
+    #[test]
+    fn test_directives_in_code_blocks_are_left_alone() {
+        let markdown = "```\n{{< include file=\"nope.rs\" >}}\n```\n";
+        assert_eq!(expand(markdown, Path::new(".")).unwrap(), markdown);
+    }
+}
@@ -0,0 +1,165 @@
+//! `init <dir>`: scaffold a new site with hardened defaults
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Starting `config.yaml`; `doctor` warns until title and url are changed
+const CONFIG: &str = r#"title: "SecureBlog"
+url: "https://example.com"
+author: "Anonymous"
+output: "dist"
+content: "content"
+use_blake3: true
+build_footer: true
+microformats: true
+json_ld: true
+headers:  # dist/_headers: CSP, HSTS, deny-all Permissions-Policy, COOP/COEP/CORP
+  enabled: true
+"#;
+
+const BASE_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <meta name="referrer" content="no-referrer">
+    <meta http-equiv="Content-Security-Policy" content="default-src 'none'; style-src 'self'; img-src 'self'; form-action 'none'; frame-ancestors 'none'; base-uri 'none'">
+    <title>{% block title %}{{ site_title }}{% endblock %}</title>
+    <link rel="stylesheet" href="/style.css">
+</head>
+<body>
+    <header><a href="/">{{ site_title }}</a></header>
+    <main>{% block content %}{% endblock %}</main>
+</body>
+</html>
+"#;
+
+const INDEX_TEMPLATE: &str = r#"{% extends "base.html" %}
+{% block content %}
+<ul>
+{% for post in posts %}
+    <li><time datetime="{{ post.date }}">{{ post.date }}</time> <a href="/{{ post.path }}">{{ post.title }}</a></li>
+{% endfor %}
+</ul>
+{% endblock %}
+"#;
+
+const POST_TEMPLATE: &str = r#"{% extends "base.html" %}
+{% block title %}{{ title }} - {{ site_title }}{% endblock %}
+{% block content %}
+<article class="h-entry">
+    <h1 class="p-name">{{ title }}</h1>
+    <time class="dt-published" datetime="{{ date }}">{{ date }}</time>
+    <div class="e-content">{{ html|safe }}</div>
+</article>
+{% endblock %}
+"#;
+
+const STYLE: &str = "body { max-width: 42rem; margin: 0 auto; padding: 1rem; font-family: system-ui, sans-serif; line-height: 1.6; }\n\
+.admonition { margin: 1rem 0; padding: 0.5rem 1rem; border-left: 0.25rem solid #0969da; background: #f6f8fa; }\n\
+.admonition-title { margin: 0; font-weight: bold; }\n\
+.admonition-tip { border-color: #1a7f37; }\n\
+.admonition-important { border-color: #8250df; }\n\
+.admonition-warning { border-color: #9a6700; }\n\
+.admonition-caution { border-color: #cf222e; }\n\
+.language-diff ins, .language-patch ins { background: #dafbe1; }\n\
+.language-diff del, .language-patch del { background: #ffebe9; }\n\
+.diff-header, .diff-hunk { font-weight: bold; }\n\
+.gallery { display: grid; grid-template-columns: repeat(auto-fill, minmax(10rem, 1fr)); gap: 0.5rem; }\n\
+.gallery figure { margin: 0; }\n\
+.gallery img { display: block; width: 100%; height: auto; }\n";
+
+const GITIGNORE: &str = "# Build output\n/dist/\n/dist-review/\n/dist-preview/\n/dist-export/\n.secureblog-cache.json\nbuild-report.json\n\n# Private keys (see `doctor`)\n/.secureblog/\n*.key\n";
+
+/// Sample post, published so the first build has something to show
+fn sample_post(now: DateTime<Utc>) -> String {
+    format!(
+        "---\ntitle: \"Hello, World\"\ndate: {}\nslug: \"hello-world\"\ntags: [\"meta\"]\nstatus: published\n---\n\n\
+         This site is static HTML with no JavaScript. Edit `content/hello-world.md`, then run `build`.\n",
+        now.to_rfc3339()
+    )
+}
+
+/// RFC 9116 stub; `Expires` is required and set one year out
+fn security_txt(now: DateTime<Utc>) -> String {
+    format!(
+        "Contact: mailto:security@example.com\nExpires: {}\nPreferred-Languages: en\n",
+        (now + Duration::days(365)).format("%Y-%m-%dT%H:%M:%SZ")
+    )
+}
+
+/// Files written by `init`, relative to the site directory
+pub fn files(now: DateTime<Utc>) -> Vec<(&'static str, String)> {
+    vec![
+        ("config.yaml", CONFIG.to_string()),
+        (".gitignore", GITIGNORE.to_string()),
+        ("templates/base.html", BASE_TEMPLATE.to_string()),
+        ("templates/index.html", INDEX_TEMPLATE.to_string()),
+        ("templates/post.html", POST_TEMPLATE.to_string()),
+        ("static/style.css", STYLE.to_string()),
+        ("static/.well-known/security.txt", security_txt(now)),
+        ("content/hello-world.md", sample_post(now)),
+    ]
+}
+
+/// Write the scaffold into `dir`, refusing to overwrite any existing file
+pub fn scaffold(dir: &Path, now: DateTime<Utc>) -> Result<Vec<PathBuf>> {
+    let files = files(now);
+    let existing: Vec<String> = files
+        .iter()
+        .map(|(path, _)| dir.join(path))
+        .filter(|path| path.exists())
+        .map(|path| path.display().to_string())
+        .collect();
+    if !existing.is_empty() {
+        anyhow::bail!("Refusing to overwrite: {}", existing.join(", "));
+    }
+
+    let mut written = Vec::new();
+    for (path, content) in files {
+        let path = dir.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_config_parses() {
+        let config: crate::Config = serde_yaml::from_str(CONFIG).unwrap();
+        assert!(config.headers.enabled);
+        assert_eq!(config.output, PathBuf::from("dist"));
+    }
+
+    #[test]
+    fn test_sample_post_is_published() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        let (meta, body) = crate::markdown::parse_frontmatter(&sample_post(now)).unwrap();
+        assert_eq!(meta.slug, "hello-world");
+        assert_eq!(meta.status, crate::status::PostStatus::Published);
+        assert!(!body.is_empty());
+    }
+
+    #[test]
+    fn test_security_txt_expires() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        assert!(security_txt(now).contains("Expires: 2025-03-01T00:00:00Z\n"));
+    }
+
+    #[test]
+    fn test_templates_have_no_scripts() {
+        for (path, content) in files(Utc::now()) {
+            assert!(!content.to_lowercase().contains("<script"), "{path}");
+        }
+    }
+}
@@ -0,0 +1,50 @@
+//! Insertion of generated fragments into rendered pages
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// Insert `fragment` before the last occurrence of the first marker found
+///
+/// Markers are tried in order and matched case-insensitively, e.g.
+/// `["</article>", "</main>", "</body>"]`. Returns `None` if no marker is present.
+pub fn insert_before(html: &str, markers: &[&str], fragment: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let position = markers.iter().find_map(|marker| lower.rfind(marker))?;
+
+    let mut result = String::with_capacity(html.len() + fragment.len());
+    result.push_str(&html[..position]);
+    result.push_str(fragment);
+    result.push_str(&html[position..]);
+    Some(result)
+}
+
+/// Insert `fragment` into the output file `page`, failing if no marker is found
+pub fn inject_into_file(page: &Path, markers: &[&str], fragment: &str) -> Result<()> {
+    let html = fs::read_to_string(page)
+        .with_context(|| format!("Failed to read page: {}", page.display()))?;
+    let updated = insert_before(&html, markers, fragment).with_context(|| {
+        format!("No insertion point ({}) in {}", markers.join(", "), page.display())
+    })?;
+    fs::write(page, updated).with_context(|| format!("Failed to write page: {}", page.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_before_prefers_first_marker() {
+        let html = "<body><article>Post</article><footer></footer></body>";
+        let result = insert_before(html, &["</article>", "</body>"], "<p>x</p>").unwrap();
+        assert_eq!(result, "<body><article>Post<p>x</p></article><footer></footer></body>");
+    }
+
+    #[test]
+    fn test_insert_before_falls_back_and_ignores_case() {
+        let html = "<HTML><BODY>Post</BODY></HTML>";
+        let result = insert_before(html, &["</article>", "</body>"], "<p>x</p>").unwrap();
+        assert_eq!(result, "<HTML><BODY>Post<p>x</p></BODY></HTML>");
+        assert!(insert_before("<p>no markers</p>", &["</body>"], "x").is_none());
+    }
+}
@@ -0,0 +1,166 @@
+//! schema.org JSON-LD structured data
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+use crate::{inject, Config, Post};
+
+/// The schema.org vocabulary every document uses
+const SCHEMA_CONTEXT: &str = "https://schema.org";
+
+/// `BlogPosting` for a post
+pub fn blog_posting(config: &Config, post: &Post) -> Value {
+    let url = post.permalink(&config.url);
+    json!({
+        "@context": SCHEMA_CONTEXT,
+        "@type": "BlogPosting",
+        "headline": post.meta.title,
+        "url": url,
+        "mainEntityOfPage": url,
+        "datePublished": post.meta.date.to_rfc3339(),
+        "dateModified": post.modified().to_rfc3339(),
+        "author": { "@type": "Person", "name": config.author },
+        "keywords": post.meta.tags.join(", "),
+    })
+}
+
+/// `WebSite` for the site index
+pub fn website(config: &Config) -> Value {
+    json!({
+        "@context": SCHEMA_CONTEXT,
+        "@type": "WebSite",
+        "name": config.title,
+        "url": format!("{}/", config.url.trim_end_matches('/')),
+        "author": { "@type": "Person", "name": config.author },
+    })
+}
+
+/// `BreadcrumbList` from the home page through `trail` (name, URL) items
+pub fn breadcrumbs(config: &Config, trail: &[(&str, &str)]) -> Value {
+    let home = format!("{}/", config.url.trim_end_matches('/'));
+    let items: Vec<Value> = std::iter::once((config.title.as_str(), home.as_str()))
+        .chain(trail.iter().copied())
+        .enumerate()
+        .map(|(index, (name, url))| {
+            json!({
+                "@type": "ListItem",
+                "position": index + 1,
+                "name": name,
+                "item": url,
+            })
+        })
+        .collect();
+
+    json!({
+        "@context": SCHEMA_CONTEXT,
+        "@type": "BreadcrumbList",
+        "itemListElement": items,
+    })
+}
+
+/// Properties whose values are URLs; other text may mention URLs freely
+const URL_FIELDS: &[&str] = &["url", "@id", "image", "sameAs", "mainEntityOfPage", "item"];
+
+/// Fail unless `value` (a URL or list of URLs) points at the site itself
+fn check_url(value: &Value, site: &str) -> Result<()> {
+    match value {
+        Value::String(s) => {
+            let on_site = s == site || s.starts_with(&format!("{site}/"));
+            let relative = !s.contains("://") && !s.starts_with("//");
+            if !on_site && !relative {
+                anyhow::bail!("JSON-LD references external URL '{s}'");
+            }
+            Ok(())
+        }
+        Value::Array(items) => items.iter().try_for_each(|v| check_url(v, site)),
+        _ => Ok(()),
+    }
+}
+
+/// Ensure every URL-typed property in the document points at the site itself
+pub fn validate(value: &Value, site_url: &str) -> Result<()> {
+    match value {
+        Value::Array(items) => items.iter().try_for_each(|v| validate(v, site_url)),
+        Value::Object(map) => map.iter().try_for_each(|(key, v)| {
+            if URL_FIELDS.contains(&key.as_str()) {
+                check_url(v, site_url.trim_end_matches('/'))
+            } else {
+                validate(v, site_url)
+            }
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Serialize documents into a `<script type="application/ld+json">` block
+///
+/// `<`, `>` and `&` are escaped so content can never close the script element.
+pub fn script_block(documents: &[Value]) -> Result<String> {
+    let json = serde_json::to_string(documents)?
+        .replace('<', "\\u003c")
+        .replace('>', "\\u003e")
+        .replace('&', "\\u0026");
+    Ok(format!("<script type=\"application/ld+json\">{json}</script>\n"))
+}
+
+/// Validate and inject structured data into a page's `<head>`
+fn inject_documents(page: &Path, documents: &[Value], site_url: &str) -> Result<()> {
+    for document in documents {
+        validate(document, site_url).with_context(|| format!("Invalid structured data for {}", page.display()))?;
+    }
+    inject::inject_into_file(page, &["</head>"], &script_block(documents)?)
+}
+
+/// Add JSON-LD to every post page and the index
+pub fn apply(config: &Config, posts: &[Post]) -> Result<()> {
+    for post in posts {
+        let url = post.permalink(&config.url);
+        inject_documents(
+            &config.output.join(post.path()),
+            &[blog_posting(config, post), breadcrumbs(config, &[(&post.meta.title, &url)])],
+            &config.url,
+        )?;
+    }
+
+    let index = config.output.join("index.html");
+    if fs::metadata(&index).is_ok() {
+        inject_documents(&index, &[website(config), breadcrumbs(config, &[])], &config.url)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_rejects_external_urls() {
+        let config = Config::default();
+        assert!(validate(&website(&config), &config.url).is_ok());
+        let doc = json!({ "@context": SCHEMA_CONTEXT, "image": "https://tracker.example/pixel.png" });
+        assert!(validate(&doc, &config.url).is_err());
+        let doc = json!({ "sameAs": ["https://example.com.evil.test/"] });
+        assert!(validate(&doc, &config.url).is_err());
+        let doc = json!({ "@id": "https://example.com/post.html", "headline": "Why https://tracker.example/ is bad" });
+        assert!(validate(&doc, &config.url).is_ok());
+    }
+
+    #[test]
+    fn test_script_block_escapes_markup() {
+        let doc = json!({ "headline": "</script><script>alert(1)</script>" });
+        let block = script_block(&[doc]).unwrap();
+        assert_eq!(block.matches("</script>").count(), 1);
+        assert!(block.contains("\\u003c/script\\u003e"));
+    }
+
+    #[test]
+    fn test_breadcrumbs_start_at_home() {
+        let config = Config::default();
+        let doc = breadcrumbs(&config, &[("Post", "https://example.com/post.html")]);
+        assert_eq!(doc["itemListElement"][0]["item"], "https://example.com/");
+        assert_eq!(doc["itemListElement"][1]["position"], 2);
+    }
+}
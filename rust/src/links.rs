@@ -0,0 +1,289 @@
+//! Internal link and fragment validation for generated output
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use walkdir::WalkDir;
+
+use crate::normalize;
+
+/// Element IDs usable as fragment targets (`id=` and legacy `<a name=`)
+static ID_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)<(?:[^>]*?\sid|a\b[^>]*?\sname)\s*=\s*["']([^"']+)["']"#).unwrap());
+
+/// References to other resources (`href=` and `src=`)
+static REF_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)\s(href|src)\s*=\s*["']([^"']*)["']"#).unwrap());
+
+/// Stylesheet references (`url(...)` and `@import`)
+static CSS_REF_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)url\(\s*["']?([^"')]+)["']?\s*\)|@import\s+["']([^"']+)["']"#).unwrap()
+});
+
+/// A reference found in a page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    /// Attribute the reference came from (`href` or `src`)
+    pub attr: String,
+    /// Raw attribute value
+    pub value: String,
+}
+
+/// IDs and references of a single HTML page
+#[derive(Debug, Default)]
+pub struct PageLinks {
+    /// Element IDs defined in the page
+    pub ids: BTreeSet<String>,
+    /// References to other resources
+    pub references: Vec<Reference>,
+}
+
+/// Link data for the whole output directory
+#[derive(Debug, Default)]
+pub struct SiteLinks {
+    /// HTML pages keyed by path relative to the output directory
+    pub pages: BTreeMap<String, PageLinks>,
+    /// Stylesheet references keyed by stylesheet path
+    pub stylesheets: BTreeMap<String, Vec<String>>,
+    /// Every file in the output directory (relative paths)
+    pub files: BTreeSet<String>,
+}
+
+/// A broken internal link
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkError {
+    /// Page containing the link
+    pub page: String,
+    /// Link as written in the page
+    pub link: String,
+    /// Why the link is broken
+    pub reason: String,
+}
+
+/// Scan every file in the output directory and extract IDs and references from HTML pages
+pub fn scan_site(output_dir: &Path) -> Result<SiteLinks> {
+    let mut site = SiteLinks::default();
+
+    for entry in WalkDir::new(output_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let path = entry.path();
+        let relative = normalize::relative(path, output_dir)?;
+
+        match path.extension().and_then(|s| s.to_str()) {
+            Some("html" | "htm") => {
+                let content = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read HTML file: {}", path.display()))?;
+                site.pages.insert(relative.clone(), parse_page(&content));
+            }
+            Some("css") => {
+                let content = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read CSS file: {}", path.display()))?;
+                site.stylesheets.insert(relative.clone(), parse_stylesheet(&content));
+            }
+            _ => {}
+        }
+        site.files.insert(relative);
+    }
+
+    Ok(site)
+}
+
+/// Extract IDs and references from HTML content
+pub fn parse_page(html: &str) -> PageLinks {
+    PageLinks {
+        ids: ID_PATTERN
+            .captures_iter(html)
+            .map(|cap| cap[1].to_string())
+            .collect(),
+        references: REF_PATTERN
+            .captures_iter(html)
+            .map(|cap| Reference {
+                attr: cap[1].to_ascii_lowercase(),
+                value: cap[2].to_string(),
+            })
+            .collect(),
+    }
+}
+
+/// Extract `url(...)` and `@import` references from CSS content
+pub fn parse_stylesheet(css: &str) -> Vec<String> {
+    CSS_REF_PATTERN
+        .captures_iter(css)
+        .filter_map(|cap| cap.get(1).or_else(|| cap.get(2)))
+        .map(|m| m.as_str().trim().to_string())
+        .collect()
+}
+
+/// Resolve a reference from `page` to a path relative to the output root
+///
+/// Returns `None` for external URLs and non-navigational schemes. The fragment is
+/// returned separately and is empty when absent.
+pub fn resolve(page: &str, reference: &str) -> Option<(String, String)> {
+    let (target, fragment) = reference.split_once('#').unwrap_or((reference, ""));
+    let target = target.split('?').next().unwrap_or_default();
+
+    if target.starts_with("//") || has_scheme(target) {
+        return None;
+    }
+
+    if target.is_empty() {
+        return Some((page.to_string(), fragment.to_string()));
+    }
+
+    let mut segments: Vec<&str> = if target.starts_with('/') {
+        Vec::new()
+    } else {
+        let mut dir: Vec<&str> = page.split('/').collect();
+        dir.pop();
+        dir
+    };
+
+    for segment in target.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            s => segments.push(s),
+        }
+    }
+
+    let mut path = segments.join("/");
+    if target.ends_with('/') || path.is_empty() {
+        if !path.is_empty() {
+            path.push('/');
+        }
+        path.push_str("index.html");
+    }
+
+    Some((path, fragment.to_string()))
+}
+
+/// `scheme:` prefix such as `https:`, `mailto:` or `data:`
+fn has_scheme(target: &str) -> bool {
+    target
+        .split_once(':')
+        .is_some_and(|(scheme, _)| {
+            !scheme.is_empty()
+                && !scheme.contains('/')
+                && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+        })
+}
+
+/// Validate internal page links and `#fragment` targets, including cross-page anchors
+pub fn check_links(site: &SiteLinks) -> Vec<LinkError> {
+    let mut errors = Vec::new();
+
+    for (page, links) in &site.pages {
+        for reference in &links.references {
+            let Some((mut target, fragment)) = resolve(page, &reference.value) else {
+                continue;
+            };
+
+            // `/tags` is served as `/tags/index.html`
+            let index = format!("{target}/index.html");
+            if !site.files.contains(&target) && site.files.contains(&index) {
+                target = index;
+            }
+
+            if !site.files.contains(&target) {
+                errors.push(LinkError {
+                    page: page.clone(),
+                    link: reference.value.clone(),
+                    reason: format!("target '{target}' does not exist"),
+                });
+                continue;
+            }
+
+            if fragment.is_empty() || reference.attr != "href" {
+                continue;
+            }
+
+            if let Some(target_page) = site.pages.get(&target) {
+                if !target_page.ids.contains(&fragment) {
+                    errors.push(LinkError {
+                        page: page.clone(),
+                        link: reference.value.clone(),
+                        reason: format!("no element with id '{fragment}' in '{target}'"),
+                    });
+                }
+            }
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn site(pages: &[(&str, &str)]) -> SiteLinks {
+        let mut site = SiteLinks::default();
+        for (path, html) in pages {
+            site.pages.insert((*path).to_string(), parse_page(html));
+            site.files.insert((*path).to_string());
+        }
+        site
+    }
+
+    #[test]
+    fn test_resolve_relative_and_absolute() {
+        assert_eq!(
+            resolve("posts/a.html", "../b.html#top"),
+            Some(("b.html".to_string(), "top".to_string()))
+        );
+        assert_eq!(
+            resolve("posts/a.html", "/tags/"),
+            Some(("tags/index.html".to_string(), String::new()))
+        );
+        assert_eq!(
+            resolve("a.html", "#intro"),
+            Some(("a.html".to_string(), "intro".to_string()))
+        );
+        assert_eq!(resolve("a.html", "https://example.com/#x"), None);
+        assert_eq!(resolve("a.html", "mailto:me@example.com"), None);
+    }
+
+    #[test]
+    fn test_check_links_detects_missing_fragment() {
+        let site = site(&[
+            ("index.html", r##"<a href="post.html#setup">Setup</a><a href="#top">Top</a>"##),
+            ("post.html", r#"<h2 id="install">Install</h2>"#),
+        ]);
+        let errors = check_links(&site);
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().any(|e| e.link == "post.html#setup"));
+        assert!(errors.iter().any(|e| e.link == "#top"));
+    }
+
+    #[test]
+    fn test_check_links_accepts_valid_anchors() {
+        let site = site(&[
+            ("index.html", r#"<a id="top" href="post.html#install">Install</a>"#),
+            ("post.html", r#"<h2 id="install">Install</h2><a href="index.html#top">Back</a>"#),
+        ]);
+        assert!(check_links(&site).is_empty());
+    }
+
+    #[test]
+    fn test_parse_stylesheet_references() {
+        let refs = parse_stylesheet(
+            r#"@import "base.css"; body { background: url('img/bg.png'); } @font-face { src: url(fonts/a.woff2) }"#,
+        );
+        assert_eq!(refs, vec!["base.css", "img/bg.png", "fonts/a.woff2"]);
+    }
+
+    #[test]
+    fn test_check_links_detects_missing_page() {
+        let site = site(&[("index.html", r#"<a href="gone.html">Gone</a>"#)]);
+        let errors = check_links(&site);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].reason.contains("gone.html"));
+    }
+}
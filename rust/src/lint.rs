@@ -0,0 +1,369 @@
+//! Site-specific content rules from the `rules:` list of `security-policy.yaml`
+//!
+//! A rule either denies or requires a pattern in a post's rendered HTML. The
+//! pattern is a regex or a simple CSS selector (`a[href*=doubleclick.net]`,
+//! `div.disclaimer`), and `tags:` limits a rule to posts with any of those
+//! tags. Error rules fail the build; warning rules are only logged.
+//!
+//! ```yaml
+//! rules:
+//!   - name: no-doubleclick
+//!     deny: { selector: "a[href*=doubleclick.net]" }
+//!     message: Do not link to ad trackers
+//!   - name: advisory-disclaimer
+//!     tags: [advisory]
+//!     require: { regex: "(?i)not (legal|financial) advice" }
+//!     severity: warning
+//!     message: Advisories need the standard disclaimer
+//! ```
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use tracing::{error, warn};
+
+use crate::Post;
+
+/// Start tags with their attribute text
+static START_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<([a-zA-Z][a-zA-Z0-9-]*)([^>]*)>").unwrap());
+
+/// `name`, `name=value`, `name="value"` or `name='value'`
+static ATTRIBUTE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"([^\s=/"'>]+)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+)))?"#).unwrap()
+});
+
+/// One part of a compound selector: `tag`, `.class`, `#id` or `[attr op value]`
+static SELECTOR_PART: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"^(?:([a-zA-Z][a-zA-Z0-9-]*)|\.([\w-]+)|#([\w-]+)|\[([\w-]+)(?:([~^$*]?=)["']?([^"'\]]*)["']?)?\])"#)
+        .unwrap()
+});
+
+/// What a failing rule does
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Fail the build (default)
+    #[default]
+    Error,
+    /// Log and continue
+    Warning,
+}
+
+/// A pattern as written in the policy file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PatternSpec {
+    /// Regular expression over the rendered HTML
+    #[serde(default)]
+    pub regex: Option<String>,
+    /// Compound CSS selector (tag, classes, id, attributes; no combinators)
+    #[serde(default)]
+    pub selector: Option<String>,
+}
+
+/// A rule as written in the policy file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleSpec {
+    /// Identifier shown with each finding
+    pub name: String,
+    /// Posts must not contain this
+    #[serde(default)]
+    pub deny: Option<PatternSpec>,
+    /// Posts must contain this
+    #[serde(default)]
+    pub require: Option<PatternSpec>,
+    /// Only posts with any of these tags (default: every post)
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub severity: Severity,
+    /// Explanation shown to the author
+    pub message: String,
+}
+
+/// Attribute test in a selector
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum AttributeTest {
+    Present,
+    Equals(String),
+    /// `~=`: whitespace-separated list contains
+    Word(String),
+    Prefix(String),
+    Suffix(String),
+    Contains(String),
+}
+
+/// Compiled compound selector
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Selector {
+    tag: Option<String>,
+    attributes: Vec<(String, AttributeTest)>,
+}
+
+impl Selector {
+    /// Parse `tag.class#id[attr=value]`
+    pub fn parse(selector: &str) -> Result<Self> {
+        let mut rest = selector.trim();
+        if rest.is_empty() {
+            anyhow::bail!("Empty selector");
+        }
+        let mut parsed = Self::default();
+        while !rest.is_empty() {
+            let caps = SELECTOR_PART
+                .captures(rest)
+                .with_context(|| format!("Unsupported selector syntax at `{rest}` in `{selector}`"))?;
+            if let Some(tag) = caps.get(1) {
+                if parsed.tag.is_some() || !parsed.attributes.is_empty() {
+                    anyhow::bail!("Tag name must come first in `{selector}`");
+                }
+                parsed.tag = Some(tag.as_str().to_ascii_lowercase());
+            } else if let Some(class) = caps.get(2) {
+                parsed.attributes.push(("class".to_string(), AttributeTest::Word(class.as_str().to_string())));
+            } else if let Some(id) = caps.get(3) {
+                parsed.attributes.push(("id".to_string(), AttributeTest::Equals(id.as_str().to_string())));
+            } else {
+                let name = caps[4].to_ascii_lowercase();
+                let value = caps.get(6).map_or("", |v| v.as_str()).to_string();
+                let test = match caps.get(5).map(|op| op.as_str()) {
+                    None => AttributeTest::Present,
+                    Some("=") => AttributeTest::Equals(value),
+                    Some("~=") => AttributeTest::Word(value),
+                    Some("^=") => AttributeTest::Prefix(value),
+                    Some("$=") => AttributeTest::Suffix(value),
+                    Some(_) => AttributeTest::Contains(value),
+                };
+                parsed.attributes.push((name, test));
+            }
+            rest = &rest[caps[0].len()..];
+        }
+        Ok(parsed)
+    }
+
+    /// Whether one start tag matches
+    fn matches_tag(&self, tag: &str, attributes: &[(String, String)]) -> bool {
+        if self.tag.as_deref().is_some_and(|t| !t.eq_ignore_ascii_case(tag)) {
+            return false;
+        }
+        self.attributes.iter().all(|(name, test)| {
+            attributes.iter().filter(|(n, _)| n == name).any(|(_, value)| match test {
+                AttributeTest::Present => true,
+                AttributeTest::Equals(v) => value == v,
+                AttributeTest::Word(v) => value.split_whitespace().any(|w| w == v),
+                AttributeTest::Prefix(v) => value.starts_with(v.as_str()),
+                AttributeTest::Suffix(v) => value.ends_with(v.as_str()),
+                AttributeTest::Contains(v) => value.contains(v.as_str()),
+            })
+        })
+    }
+
+    /// Start tags in `html` matching the selector
+    pub fn find<'h>(&self, html: &'h str) -> Vec<&'h str> {
+        START_TAG
+            .captures_iter(html)
+            .filter(|caps| {
+                let attributes: Vec<(String, String)> = ATTRIBUTE
+                    .captures_iter(&caps[2])
+                    .map(|a| {
+                        let value = a.get(2).or(a.get(3)).or(a.get(4)).map_or("", |v| v.as_str());
+                        (a[1].to_ascii_lowercase(), value.replace("&amp;", "&"))
+                    })
+                    .collect();
+                self.matches_tag(&caps[1], &attributes)
+            })
+            .map(|caps| caps.get(0).unwrap().as_str())
+            .collect()
+    }
+}
+
+/// Compiled pattern
+#[derive(Debug, Clone)]
+enum Pattern {
+    Regex(Regex),
+    Selector(Selector),
+}
+
+impl Pattern {
+    fn compile(spec: &PatternSpec) -> Result<Self> {
+        match (&spec.regex, &spec.selector) {
+            (Some(regex), None) => Ok(Self::Regex(Regex::new(regex)?)),
+            (None, Some(selector)) => Ok(Self::Selector(Selector::parse(selector)?)),
+            _ => anyhow::bail!("Give exactly one of `regex` or `selector`"),
+        }
+    }
+
+    /// Matched text, first match first
+    fn find<'h>(&self, html: &'h str) -> Vec<&'h str> {
+        match self {
+            Self::Regex(regex) => regex.find_iter(html).map(|m| m.as_str()).collect(),
+            Self::Selector(selector) => selector.find(html),
+        }
+    }
+}
+
+/// Compiled rule
+#[derive(Debug, Clone)]
+struct Rule {
+    name: String,
+    deny: bool,
+    pattern: Pattern,
+    tags: Vec<String>,
+    severity: Severity,
+    message: String,
+}
+
+/// A rule a post breaks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// Rule name
+    pub rule: String,
+    pub severity: Severity,
+    /// The rule's message, with the offending markup for deny rules
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.rule, self.message)
+    }
+}
+
+/// The site's compiled rules
+#[derive(Debug, Clone, Default)]
+pub struct Rules {
+    rules: Vec<Rule>,
+}
+
+impl Rules {
+    /// Compile the policy file's rules, naming the rule at fault
+    pub fn compile(specs: &[RuleSpec]) -> Result<Self> {
+        let rules = specs
+            .iter()
+            .map(|spec| {
+                let (deny, pattern) = match (&spec.deny, &spec.require) {
+                    (Some(pattern), None) => (true, pattern),
+                    (None, Some(pattern)) => (false, pattern),
+                    _ => anyhow::bail!("Rule `{}` needs exactly one of `deny` or `require`", spec.name),
+                };
+                Ok(Rule {
+                    name: spec.name.clone(),
+                    deny,
+                    pattern: Pattern::compile(pattern).with_context(|| format!("Invalid pattern in rule `{}`", spec.name))?,
+                    tags: spec.tags.clone(),
+                    severity: spec.severity,
+                    message: spec.message.clone(),
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { rules })
+    }
+
+    /// Rules `post` breaks
+    pub fn check(&self, post: &Post) -> Vec<Finding> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.tags.is_empty() || rule.tags.iter().any(|tag| post.meta.tags.contains(tag)))
+            .filter_map(|rule| {
+                let found = rule.pattern.find(&post.html);
+                let message = match (rule.deny, found.first()) {
+                    (true, Some(first)) => format!("{} (found `{}`)", rule.message, first),
+                    (false, None) => rule.message.clone(),
+                    _ => return None,
+                };
+                Some(Finding { rule: rule.name.clone(), severity: rule.severity, message })
+            })
+            .collect()
+    }
+}
+
+/// Log every finding and fail if any error rule is broken
+pub fn enforce(rules: &Rules, posts: &[Post]) -> Result<()> {
+    let mut errors = 0;
+    for post in posts {
+        for finding in rules.check(post) {
+            match finding.severity {
+                Severity::Error => {
+                    error!("{}: {}", post.source.display(), finding);
+                    errors += 1;
+                }
+                Severity::Warning => warn!("{}: {}", post.source.display(), finding),
+            }
+        }
+    }
+    if errors > 0 {
+        anyhow::bail!("Content rules failed with {} errors", errors);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PostMeta;
+
+    fn post(tags: &[&str], html: &str) -> Post {
+        Post {
+            meta: PostMeta { tags: tags.iter().map(|t| (*t).to_string()).collect(), ..PostMeta::default() },
+            html: html.to_string(),
+            ..Post::default()
+        }
+    }
+
+    #[test]
+    fn test_deny_and_require_rules() {
+        let specs: Vec<RuleSpec> = serde_yaml::from_str(
+            "- name: no-doubleclick
+  deny: { selector: 'a[href*=doubleclick.net]' }
+  message: No ad trackers
+- name: advisory-disclaimer
+  tags: [advisory]
+  require: { selector: div.disclaimer }
+  severity: warning
+  message: Advisories need a disclaimer
+",
+        )
+        .unwrap();
+        let rules = Rules::compile(&specs).unwrap();
+
+        let tracked = post(&[], r#"<a href="https://doubleclick.net/t">x</a>"#);
+        assert_eq!(
+            rules.check(&tracked),
+            [Finding {
+                rule: "no-doubleclick".to_string(),
+                severity: Severity::Error,
+                message: r#"No ad trackers (found `<a href="https://doubleclick.net/t">`)"#.to_string(),
+            }]
+        );
+        assert!(enforce(&rules, &[tracked]).is_err());
+
+        let advisory = post(&["advisory"], "<p>CVE-2024-0001</p>");
+        assert_eq!(rules.check(&advisory)[0].severity, Severity::Warning);
+        assert!(enforce(&rules, &[advisory]).is_ok());
+        assert!(rules.check(&post(&["advisory"], r#"<div class="disclaimer">x</div>"#)).is_empty());
+        assert!(rules.check(&post(&["notes"], "<p>x</p>")).is_empty());
+    }
+
+    #[test]
+    fn test_selector_matching() {
+        let html = r#"<p><a href="https://ad.doubleclick.net/x?a=1&amp;b=2">ad</a> <a class="btn primary" id="go">go</a></p>"#;
+        assert_eq!(Selector::parse("a[href*=doubleclick.net]").unwrap().find(html).len(), 1);
+        assert_eq!(Selector::parse("a[href$='a=1&b=2']").unwrap().find(html).len(), 1);
+        assert_eq!(Selector::parse("a.primary#go").unwrap().find(html), [r#"<a class="btn primary" id="go">"#]);
+        assert_eq!(Selector::parse(".prim").unwrap().find(html).len(), 0);
+        assert_eq!(Selector::parse("[href]").unwrap().find(html).len(), 1);
+        assert!(Selector::parse("div > p").is_err());
+        assert!(Selector::parse(".x p").is_err());
+    }
+
+    #[test]
+    fn test_rule_validation() {
+        let specs: Vec<RuleSpec> =
+            serde_yaml::from_str("- name: both\n  deny: { regex: x }\n  require: { regex: y }\n  message: m\n").unwrap();
+        assert!(Rules::compile(&specs).is_err());
+        let specs: Vec<RuleSpec> = serde_yaml::from_str("- name: bad\n  deny: { regex: '(' }\n  message: m\n").unwrap();
+        assert!(Rules::compile(&specs).unwrap_err().to_string().contains("`bad`"));
+        assert!(serde_yaml::from_str::<Vec<RuleSpec>>("- name: x\n  forbid: { regex: x }\n  message: m\n").is_err());
+    }
+}
@@ -17,36 +17,170 @@ use chrono::{DateTime, Utc};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use tracing::{debug, info, warn};
-use walkdir::WalkDir;
+use tracing_subscriber::prelude::*;
 
+mod abbreviations;
+mod activitypub;
+mod admonitions;
+mod anchors;
+mod annotations;
+mod anonymize;
+mod archetype;
+mod assets;
+mod attest;
+mod baseline;
+mod bench;
+mod bibliography;
+mod blogroll;
+mod buildinfo;
+mod buildreport;
+mod bundles;
+mod cache;
+mod cdn;
+mod changed;
+mod changelog;
+mod checksums;
+mod citations;
+mod cleartext;
+mod cli;
+mod comments;
+mod context;
+mod cosign;
+mod dates;
+mod detached;
+mod determinism;
+mod diff;
+#[cfg_attr(not(feature = "network"), allow(dead_code))]
+mod dns;
+mod doctor;
+mod explain;
+mod excerpt;
+mod export;
+mod feeds;
+mod freshness;
+mod gallery;
 mod generator;
+mod git;
+#[cfg_attr(not(feature = "network"), allow(dead_code))]
+mod headers;
+mod history;
+#[cfg_attr(not(feature = "network"), allow(dead_code))]
+mod hsts;
+mod i18n;
+mod icons;
+mod import;
+mod include;
+mod init;
+mod inject;
+mod jsonld;
+mod links;
+mod lint;
 mod markdown;
+mod microformats;
+#[cfg(feature = "network")]
+mod net;
+mod normalize;
+mod notify;
+mod orphans;
+mod outbound;
+mod overrides;
+mod owners;
+mod pdf;
+mod permalinks;
+mod placeholders;
+mod preview;
+mod prose;
+mod protected;
+mod qr;
+#[cfg_attr(not(feature = "network"), allow(dead_code))]
+mod rekor;
+mod report;
+mod reproducible;
+mod reviews;
+mod robots;
 mod security;
+mod signing;
+mod sink;
+mod slug;
+mod sniff;
+mod source;
+mod sourcearchive;
+mod stats;
+mod status;
+mod styles;
+mod svg;
 mod templates;
+mod theme;
+mod timezone;
+mod vendor;
+mod vfs;
+#[cfg_attr(not(feature = "network"), allow(dead_code))]
+mod webmention;
 
 /// Post metadata from YAML frontmatter
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PostMeta {
     /// Post title
     pub title: String,
-    /// Publication date
+    /// Publication date (required unless `git_dates` is set, which takes it from git history when omitted)
+    #[serde(default, deserialize_with = "timezone::deserialize")]
     pub date: DateTime<Utc>,
+    /// Last significant update
+    #[serde(default, deserialize_with = "timezone::deserialize_option", skip_serializing_if = "Option::is_none")]
+    pub updated: Option<DateTime<Utc>>,
+    /// Date by which the post should be re-checked; once passed, `check reviews` warns and the review banner is shown
+    #[serde(default, deserialize_with = "timezone::deserialize_option", skip_serializing_if = "Option::is_none")]
+    pub review_by: Option<DateTime<Utc>>,
+    /// Meta description and feed summary (derived from the lead when omitted)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
     /// Post tags
     #[serde(default)]
     pub tags: Vec<String>,
+    /// Series the post belongs to (exported together by `export epub --series`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub series: Option<String>,
     /// Post slug (URL path)
     #[serde(default)]
     pub slug: String,
-    /// Draft status
+    /// Stylesheet next to the post, scoped to its article and linked only from it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub css: Option<String>,
+    /// Workflow status (draft, review, scheduled, published, archived)
     #[serde(default)]
+    pub status: status::PostStatus,
+    /// Changes to the security policy for this post only (recorded in the build report)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<overrides::PolicyOverride>,
+    /// `<meta name="robots" content="noindex">`, and left out of feeds and the sitemap
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub noindex: bool,
+    /// `<meta name="robots" content="nofollow">`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub nofollow: bool,
+    /// `<meta name="robots" content="noarchive">`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub noarchive: bool,
+    /// Served only behind HTTP Basic auth from the `protected:` server config; implies `noindex`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub protected: bool,
+    /// Withdrawn: the URL keeps a tombstone page stating the retraction
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub retracted: bool,
+    /// Reason and signed statement shown on the retraction tombstone
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retraction: Option<status::Retraction>,
+    /// Legacy `draft: true`, treated as `status: draft`
+    #[serde(default, skip_serializing)]
     pub draft: bool,
 }
 
 /// Represents a blog post
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Post {
     /// Post metadata
     pub meta: PostMeta,
@@ -54,10 +188,62 @@ pub struct Post {
     pub content: String,
     /// Rendered HTML (sanitized)
     pub html: String,
+    /// Rendered HTML before the `<!--more-->` marker (empty without one)
+    pub excerpt: String,
     /// Content hash for integrity
     pub hash: String,
     /// Source file path
     pub source: PathBuf,
+    /// Source file text as the content source provided it, frontmatter included
+    pub source_text: String,
+    /// Colocated files of a page bundle, published with the post
+    pub assets: Vec<bundles::Asset>,
+    /// URL path from the permalink pattern, without the leading `/` (empty: `<slug>.html`)
+    pub route: String,
+}
+
+impl Post {
+    /// Output path relative to the site root
+    pub fn path(&self) -> String {
+        if self.route.is_empty() {
+            format!("{}.html", self.meta.slug)
+        } else if self.route.ends_with('/') {
+            format!("{}index.html", self.route)
+        } else if self.route.ends_with(".html") {
+            self.route.clone()
+        } else {
+            format!("{}.html", self.route)
+        }
+    }
+
+    /// Site-relative URL (`/2024/05/slug/`, `/slug.html`, ...)
+    pub fn url_path(&self) -> String {
+        if self.route.is_empty() {
+            format!("/{}", self.path())
+        } else {
+            format!("/{}", self.route)
+        }
+    }
+
+    /// Last modification date, falling back to the publication date
+    pub fn modified(&self) -> DateTime<Utc> {
+        self.meta.updated.unwrap_or(self.meta.date)
+    }
+
+    /// Absolute URL of the post under `base_url`
+    pub fn permalink(&self, base_url: &str) -> String {
+        format!("{}{}", base_url.trim_end_matches('/'), self.url_path())
+    }
+
+    /// Free the markdown and rendered HTML once every pass reading them has run
+    ///
+    /// Metadata, paths and the content hash stay for listings, history and the build report.
+    pub fn release_bodies(&mut self) {
+        self.content = String::new();
+        self.source_text = String::new();
+        self.html = String::new();
+        self.excerpt = String::new();
+    }
 }
 
 /// Main application configuration
@@ -75,20 +261,396 @@ pub struct Config {
     /// Content directory
     #[serde(default = "default_content")]
     pub content: PathBuf,
+    /// Where posts are read from: the working tree, a git revision or a tar archive
+    #[serde(default)]
+    pub source: source::SourceConfig,
     /// Enable BLAKE3 hashing (faster than SHA-256)
     #[serde(default)]
     pub use_blake3: bool,
+    /// Build cache file (persists data between builds)
+    #[serde(default = "default_cache")]
+    pub cache: PathBuf,
+    /// Summary of each build (counts, size, warnings, timings, manifest hash, tool versions)
+    #[serde(default = "default_build_report")]
+    pub build_report: PathBuf,
+    /// Percentage growth of an output file that triggers a size regression report
+    #[serde(default = "default_size_growth_threshold")]
+    pub size_growth_threshold: f64,
+    /// Fail the build when a size regression is detected
+    #[serde(default)]
+    pub fail_on_size_growth: bool,
+    /// Remove assets no page references from the final output
+    #[serde(default)]
+    pub prune_unreferenced_assets: bool,
+    /// Require SSH-signed commits from allowlisted keys for published posts
+    #[serde(default)]
+    pub signed_commits: Option<signing::SigningPolicy>,
+    /// Check post changes against per-directory `OWNERS` files
+    #[serde(default)]
+    pub owners: bool,
+    /// Accept unsigned changes authored by an `OWNERS` principal's email (spoofable, off by default)
+    #[serde(default)]
+    pub owners_allow_unsigned: bool,
+    /// Output directory for the review tree (`status: review` posts)
+    #[serde(default = "default_review_output")]
+    pub review_output: PathBuf,
+    /// Output directory of `preview`, whose pages refresh themselves (never deploy it)
+    #[serde(default = "default_preview_output")]
+    pub preview_output: PathBuf,
+    /// Add a "built from commit X" provenance footer to every page (changes every page on every commit)
+    #[serde(default)]
+    pub build_footer: bool,
+    /// Write `SHA256SUMS` and `B3SUMS` next to `integrity.json`
+    #[serde(default = "default_true")]
+    pub checksums: bool,
+    /// Publish each post's markdown under `src/`, listed at `/src/` and linked from the post
+    #[serde(default)]
+    pub source_archive: bool,
+    /// Fill missing `date`/`updated` from the first and last commits of each post
+    #[serde(default)]
+    pub git_dates: bool,
+    /// List the commits that changed each post, with dates and messages, at its end
+    #[serde(default)]
+    pub changelog: bool,
+    /// Pseudonymous output: no generator or author metadata, dates at UTC midnight
+    #[serde(default)]
+    pub anonymize: bool,
+    /// Files copied as-is into the output (after validation), e.g. `.well-known/security.txt`
+    #[serde(default = "default_static_dir")]
+    pub static_dir: PathBuf,
+    /// Site-specific words accepted by `check prose`
+    #[serde(default = "default_prose_words")]
+    pub prose_words: PathBuf,
+    /// Named security posture that `security_policy` refines
+    #[serde(default)]
+    pub policy: PolicyPreset,
+    /// Security policy: checks, severities, allowlisted hosts, size limits and content rules
+    #[serde(default = "default_security_policy")]
+    pub security_policy: PathBuf,
+    /// Record of webmentions already sent
+    #[serde(default = "default_webmention_state")]
+    pub webmention_state: PathBuf,
+    /// Aggregated access log counts written by `stats logs` and rendered at `/stats/`
+    #[serde(default = "default_stats_data")]
+    pub stats_data: PathBuf,
+    /// Blogroll and webring data, rendered at `/blogroll/` when present
+    #[serde(default = "default_blogroll")]
+    pub blogroll: PathBuf,
+    /// HTML put at the top of posts past their `review_by:` date, when present
+    #[serde(default = "default_review_banner")]
+    pub review_banner: PathBuf,
+    /// Archetype templates used by `new` (`<archetypes>/<kind>.md`)
+    #[serde(default = "default_archetypes")]
+    pub archetypes: PathBuf,
+    /// Comments directory (`<comments>/<slug>/*.yaml`)
+    #[serde(default = "default_comments")]
+    pub comments: PathBuf,
+    /// Add microformats2 markup to post pages
+    #[serde(default = "default_true")]
+    pub microformats: bool,
+    /// Add schema.org JSON-LD to posts and the index
+    #[serde(default = "default_true")]
+    pub json_ld: bool,
+    /// Add an inline SVG QR code of the canonical URL to each post
+    #[serde(default)]
+    pub qr_codes: bool,
+    /// Feed item count, full or summary content, images and canonical URLs
+    #[serde(default)]
+    pub feeds: feeds::FeedsConfig,
+    /// `_headers` response headers: CSP, HSTS, Permissions-Policy, COOP/COEP/CORP
+    #[serde(default)]
+    pub headers: headers::HeadersConfig,
+    /// Assumptions used by the `report` command
+    #[serde(default)]
+    pub report: report::ReportConfig,
+    /// Settings for `export`
+    #[serde(default)]
+    pub export: export::ExportConfig,
+    /// Favicon and web manifest generation (disabled when absent)
+    #[serde(default)]
+    pub icons: Option<icons::IconsConfig>,
+    /// Static ActivityPub export (disabled when absent)
+    #[serde(default)]
+    pub activitypub: Option<activitypub::ActivityPubConfig>,
+    /// Detached SSH signatures for feeds and the sitemap (disabled when absent)
+    #[serde(default)]
+    pub sign_files: Option<detached::SignFilesConfig>,
+    /// Hash-chained snapshots of published content, published under `archive/` (disabled when absent)
+    #[serde(default)]
+    pub history: Option<history::HistoryConfig>,
+    /// OpenPGP cleartext-signed markdown source next to each post (disabled when absent)
+    #[serde(default)]
+    pub sign_sources: Option<cleartext::SignSourcesConfig>,
+    /// Archived copies of the links in posts with cited tags, from `citations archive` (disabled when absent)
+    #[serde(default)]
+    pub citations: Option<citations::CitationsConfig>,
+    /// IndexNow key file and the search engine notifications of `deployed --notify` (disabled when absent)
+    #[serde(default)]
+    pub notify: Option<notify::NotifyConfig>,
+    /// External links rewritten to static no-referrer exit pages listed in a manifest (disabled when absent)
+    #[serde(default)]
+    pub outbound: Option<outbound::OutboundConfig>,
+    /// htpasswd and nginx/Apache/Caddy auth config for `protected: true` posts (disabled when absent)
+    #[serde(default)]
+    pub protected: Option<protected::ProtectedConfig>,
+    /// Symbol of the permalink anchor added to every post heading, e.g. `§` or `#` (disabled when absent)
+    #[serde(default)]
+    pub heading_anchors: Option<String>,
+    /// Theme directory whose files are verified against `<theme>.lock` beside it; its `static/` is copied into the output
+    #[serde(default)]
+    pub theme: Option<PathBuf>,
+    /// Slugs from non-Latin titles: `unicode`, `ascii`, `pinyin` or `romaji`
+    #[serde(default)]
+    pub slugs: slug::Transliteration,
+    /// URL pattern for posts (`/:year/:month/:slug/`) and trailing-slash style
+    #[serde(default)]
+    pub permalinks: permalinks::PermalinkConfig,
+    /// Pinned hash and build metadata checked by `--attest-self`
+    #[serde(default)]
+    pub expected_binary: Option<attest::ExpectedBinary>,
+    /// Sign `integrity.json` and log it in Rekor (disabled when absent, needs `sign_files`)
+    #[serde(default)]
+    pub rekor: Option<rekor::RekorConfig>,
+    /// Site language, used for human-readable dates
+    #[serde(default)]
+    pub i18n: i18n::I18nConfig,
+    /// Zone for frontmatter dates without an offset, and the zone dates are shown in
+    #[serde(default)]
+    pub timezone: timezone::TimezoneConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            title: "SecureBlog".to_string(),
+            url: "https://example.com".to_string(),
+            author: "Anonymous".to_string(),
+            output: default_output(),
+            content: default_content(),
+            source: source::SourceConfig::default(),
+            use_blake3: true,
+            cache: default_cache(),
+            build_report: default_build_report(),
+            size_growth_threshold: default_size_growth_threshold(),
+            fail_on_size_growth: false,
+            prune_unreferenced_assets: false,
+            review_output: default_review_output(),
+            preview_output: default_preview_output(),
+            signed_commits: None,
+            owners: false,
+            owners_allow_unsigned: false,
+            build_footer: false,
+            checksums: true,
+            source_archive: false,
+            git_dates: false,
+            changelog: false,
+            anonymize: false,
+            static_dir: default_static_dir(),
+            prose_words: default_prose_words(),
+            policy: PolicyPreset::default(),
+            security_policy: default_security_policy(),
+            webmention_state: default_webmention_state(),
+            stats_data: default_stats_data(),
+            blogroll: default_blogroll(),
+            review_banner: default_review_banner(),
+            archetypes: default_archetypes(),
+            comments: default_comments(),
+            microformats: true,
+            json_ld: true,
+            qr_codes: false,
+            feeds: feeds::FeedsConfig::default(),
+            headers: headers::HeadersConfig::default(),
+            report: report::ReportConfig::default(),
+            export: export::ExportConfig::default(),
+            icons: None,
+            activitypub: None,
+            sign_files: None,
+            sign_sources: None,
+            citations: None,
+            outbound: None,
+            notify: None,
+            protected: None,
+            history: None,
+            heading_anchors: None,
+            theme: None,
+            slugs: slug::Transliteration::default(),
+            permalinks: permalinks::PermalinkConfig::default(),
+            expected_binary: None,
+            rekor: None,
+            i18n: i18n::I18nConfig::default(),
+            timezone: timezone::TimezoneConfig::default(),
+        }
+    }
+}
+
+const fn default_true() -> bool {
+    true
 }
 
 fn default_output() -> PathBuf {
     PathBuf::from("dist")
 }
 
+fn default_review_output() -> PathBuf {
+    PathBuf::from("dist-review")
+}
+
+fn default_preview_output() -> PathBuf {
+    PathBuf::from("dist-preview")
+}
+
 fn default_content() -> PathBuf {
     PathBuf::from("content")
 }
 
-/// Security policy enforcement
+fn default_archetypes() -> PathBuf {
+    PathBuf::from("archetypes")
+}
+
+fn default_comments() -> PathBuf {
+    PathBuf::from("comments")
+}
+
+fn default_cache() -> PathBuf {
+    PathBuf::from(".secureblog-cache.json")
+}
+
+fn default_build_report() -> PathBuf {
+    PathBuf::from("build-report.json")
+}
+
+fn default_static_dir() -> PathBuf {
+    PathBuf::from("static")
+}
+
+fn default_prose_words() -> PathBuf {
+    PathBuf::from("prose-words.txt")
+}
+
+fn default_security_policy() -> PathBuf {
+    PathBuf::from("security-policy.yaml")
+}
+
+fn default_webmention_state() -> PathBuf {
+    PathBuf::from("webmentions.json")
+}
+
+fn default_stats_data() -> PathBuf {
+    PathBuf::from("stats.json")
+}
+
+fn default_blogroll() -> PathBuf {
+    PathBuf::from("data/blogroll.yaml")
+}
+
+fn default_review_banner() -> PathBuf {
+    PathBuf::from("partials/review-banner.html")
+}
+
+const fn default_size_growth_threshold() -> f64 {
+    20.0
+}
+
+/// Built-in output checks, named in the policy file's `severity:` map
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Check {
+    /// Scripts, event handlers and other JavaScript patterns
+    Javascript,
+    /// `style=""` attributes
+    InlineStyles,
+    /// Resources and imports from other origins
+    External,
+    /// Output pages over `max_page_size`
+    PageSize,
+    /// `data:` URIs in attributes and stylesheets
+    DataUri,
+    /// Files whose bytes do not match their extension, and executables
+    Mime,
+    /// Files valid as two formats at once (GIF/JS, PDF/HTML, image/ZIP)
+    Polyglot,
+    /// Dangerous extensions and HTML in image directories
+    FileType,
+    /// `<meta http-equiv="refresh">`, only ever added to `preview` builds
+    MetaRefresh,
+}
+
+impl Check {
+    /// Every built-in check
+    pub const ALL: [Self; 9] = [
+        Self::Javascript,
+        Self::InlineStyles,
+        Self::External,
+        Self::PageSize,
+        Self::DataUri,
+        Self::Mime,
+        Self::Polyglot,
+        Self::FileType,
+        Self::MetaRefresh,
+    ];
+
+    /// Rule id, as written in `severity:` and shown with findings
+    pub fn id(self) -> &'static str {
+        match self {
+            Self::Javascript => "javascript",
+            Self::InlineStyles => "inline_styles",
+            Self::External => "external",
+            Self::PageSize => "page_size",
+            Self::DataUri => "data_uri",
+            Self::Mime => "mime",
+            Self::Polyglot => "polyglot",
+            Self::FileType => "file_type",
+            Self::MetaRefresh => "meta_refresh",
+        }
+    }
+}
+
+/// Extensions that browsers or operating systems execute or render with scripts
+fn default_dangerous_extensions() -> Vec<String> {
+    [
+        "svgz", "xhtml", "xht", "shtml", "mht", "mhtml", "hta", "swf", "jar", "exe", "dll", "msi", "bat", "cmd",
+        "ps1", "sh", "vbs", "scr", "xsl", "xslt",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+/// Named security postures, chosen with `policy:` in config.yaml
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyPreset {
+    /// Strict, plus no inline styles, no `data:` URIs and pages under 1MB
+    Paranoid,
+    /// No JavaScript and no external resources (default)
+    #[default]
+    Strict,
+    /// No JavaScript; external resources allowed
+    Standard,
+}
+
+impl PolicyPreset {
+    /// The preset's policy, before `security-policy.yaml` overrides
+    pub fn policy(self) -> SecurityPolicy {
+        let strict = SecurityPolicy::default();
+        match self {
+            Self::Paranoid => SecurityPolicy {
+                no_inline_styles: true,
+                no_data_uris: true,
+                max_file_size: 1024 * 1024,
+                max_page_size: 1024 * 1024, // 1MB
+                ..strict
+            },
+            Self::Strict => strict,
+            Self::Standard => SecurityPolicy { no_external: false, ..strict },
+        }
+    }
+}
+
+/// Security policy enforcement, from `security-policy.yaml` when present
+///
+/// Unknown keys are rejected, so a typo cannot silently leave a default in place.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct SecurityPolicy {
     /// Reject any JavaScript
     pub no_javascript: bool,
@@ -96,8 +658,31 @@ pub struct SecurityPolicy {
     pub no_inline_styles: bool,
     /// Reject external resources
     pub no_external: bool,
+    /// Reject `data:` URIs (inline images, fonts and documents)
+    pub no_data_uris: bool,
+    /// Hosts still allowed under `no_external` (`fonts.example.com`, `*.example.org`)
+    pub allowed_hosts: Vec<String>,
+    /// `rel` tokens added to permitted external links besides `noopener noreferrer` (`nofollow`, `ugc`, `sponsored`)
+    pub external_link_rel: Vec<String>,
     /// Maximum file size (bytes)
     pub max_file_size: usize,
+    /// Maximum output HTML page size (bytes, 0 for no limit)
+    pub max_page_size: usize,
+    /// Publish PDF attachments (always sanitized: no scripts, actions, attachments or metadata)
+    pub allow_pdf: bool,
+    /// Extensions never published (`svgz`, `xhtml`, `hta`, ...)
+    pub dangerous_extensions: Vec<String>,
+    /// Directory names that must not contain HTML pages
+    pub image_dirs: Vec<String>,
+    /// Per-check severity; checks not listed are errors
+    pub severity: BTreeMap<Check, lint::Severity>,
+    /// Site-specific content rules
+    pub rules: Vec<lint::RuleSpec>,
+    /// Overrides by output path: `index.html` for one page, `labs/` for a section
+    pub overrides: BTreeMap<String, overrides::PolicyOverride>,
+    /// Frontmatter `policy:` overrides by output path, filled in at build time
+    #[serde(skip)]
+    pub pages: BTreeMap<String, overrides::PolicyOverride>,
 }
 
 impl Default for SecurityPolicy {
@@ -106,51 +691,568 @@ impl Default for SecurityPolicy {
             no_javascript: true,
             no_inline_styles: false,
             no_external: true,
+            no_data_uris: false,
+            allowed_hosts: Vec::new(),
+            external_link_rel: Vec::new(),
             max_file_size: 10 * 1024 * 1024, // 10MB
+            max_page_size: 0,
+            allow_pdf: true,
+            dangerous_extensions: default_dangerous_extensions(),
+            image_dirs: ["images", "img", "media", "uploads", "gallery"].map(String::from).to_vec(),
+            severity: BTreeMap::new(),
+            rules: Vec::new(),
+            overrides: BTreeMap::new(),
+            pages: BTreeMap::new(),
         }
     }
 }
 
+impl SecurityPolicy {
+    /// The preset's policy with the policy file's keys, if it exists, replacing the preset's
+    pub fn load(path: &Path, preset: PolicyPreset) -> Result<Self> {
+        if !path.exists() {
+            return Ok(preset.policy());
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let policy = Self::with_overrides(preset, &content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        policy.validate().with_context(|| format!("Invalid policy in {}", path.display()))?;
+        Ok(policy)
+    }
+
+    /// Apply the top-level keys of a policy file to a preset
+    fn with_overrides(preset: PolicyPreset, content: &str) -> Result<Self> {
+        let mut merged = serde_yaml::to_value(preset.policy())?;
+        match serde_yaml::from_str(content)? {
+            serde_yaml::Value::Null => {}
+            serde_yaml::Value::Mapping(overrides) => {
+                let base = merged.as_mapping_mut().context("Policy is not a mapping")?;
+                for (key, value) in overrides {
+                    base.insert(key, value);
+                }
+            }
+            _ => anyhow::bail!("Expected a mapping of policy settings"),
+        }
+        Ok(serde_yaml::from_value(merged)?)
+    }
+
+    /// Checks serde cannot express: limits, host syntax and rule patterns
+    pub fn validate(&self) -> Result<()> {
+        if self.max_file_size == 0 {
+            anyhow::bail!("max_file_size must be greater than 0");
+        }
+        let override_hosts = self.overrides.values().flat_map(|change| &change.allowed_hosts);
+        for host in self.allowed_hosts.iter().chain(override_hosts) {
+            let name = host.strip_prefix("*.").unwrap_or(host);
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.') {
+                anyhow::bail!("allowed_hosts entry `{host}` must be a host name, e.g. cdn.example.com or *.example.org");
+            }
+        }
+        for token in &self.external_link_rel {
+            if !security::EXTRA_LINK_REL.contains(&token.as_str()) {
+                anyhow::bail!("external_link_rel entry `{token}` must be one of {}", security::EXTRA_LINK_REL.join(", "));
+            }
+        }
+        lint::Rules::compile(&self.rules)?;
+        Ok(())
+    }
+
+    /// Severity of a built-in check
+    pub fn severity(&self, check: Check) -> lint::Severity {
+        self.severity.get(&check).copied().unwrap_or_default()
+    }
+
+    /// Whether `url` points at an allowlisted host
+    pub fn allows_url(&self, url: &str) -> bool {
+        let Some((_, rest)) = url.split_once("://") else {
+            return false;
+        };
+        let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+        let host = authority.rsplit('@').next().unwrap_or("").split(':').next().unwrap_or("").to_ascii_lowercase();
+        self.allowed_hosts.iter().any(|allowed| {
+            let allowed = allowed.to_ascii_lowercase();
+            match allowed.strip_prefix("*.") {
+                Some(domain) => host.ends_with(&format!(".{domain}")),
+                None => host == allowed,
+            }
+        })
+    }
+}
+
 /// Main entry point
 fn main() -> Result<()> {
-    // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_target(false)
+    // Initialize tracing, counting warnings for build-report.json
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_target(false))
+        .with(tracing_subscriber::filter::LevelFilter::INFO)
+        .with(buildreport::WarningCounter)
         .init();
 
     info!("SecureBlog-RS v{}", env!("CARGO_PKG_VERSION"));
     info!("Memory-safe static site generator");
 
+    let command = cli::parse(std::env::args().skip(1))?;
+
+    // doctor reports a broken config.yaml instead of stopping on it
+    if command == cli::Command::Doctor {
+        return run_doctor(&SecurityPolicy::default());
+    }
+
+    // init runs before a config.yaml exists
+    if let cli::Command::Init { dir } = &command {
+        let written = init::scaffold(dir, Utc::now())?;
+        info!("✅ Created {} files in {}", written.len(), dir.display());
+        info!("Set title, url and author in config.yaml, then run doctor");
+        return Ok(());
+    }
+
+    // Synthetic benchmarks ignore the site so releases can be compared anywhere
+    if let cli::Command::Bench { posts } = command {
+        info!("⏱️  Benchmarking {} synthetic posts", posts);
+        print!("{}", bench::table(&bench::run(posts)?));
+        return Ok(());
+    }
+
     // Load configuration
     let config = load_config()?;
     
-    // Security policy (strictest possible)
-    let policy = SecurityPolicy::default();
-    
+    // Security policy: strict built-in defaults unless security-policy.yaml says otherwise
+    let policy = SecurityPolicy::load(&config.security_policy, config.policy)?;
+
+    match command {
+        cli::Command::Build { rev, dry_run } => {
+            let config = match rev {
+                Some(rev) => revision_config(&config, &rev)?,
+                None => config,
+            };
+            if dry_run {
+                build_dry_run(&config, &policy)
+            } else {
+                build(&config, &policy)
+            }
+        }
+        cli::Command::Preview { refresh } => build_preview(&config, &policy, refresh),
+        cli::Command::Check(cli::CheckCommand::Prose, format) => check_prose(&config, format),
+        cli::Command::Check(cli::CheckCommand::Output { verbose }, format) => {
+            check_output(&config, &policy, format, verbose)
+        }
+        cli::Command::Check(cli::CheckCommand::Explain { rule }, _) => {
+            println!("{}", explain::explain(&policy, &rule)?);
+            Ok(())
+        }
+        cli::Command::Check(cli::CheckCommand::Changed { paths }, format) => {
+            check_changed(&config, &policy, paths, format)
+        }
+        cli::Command::Check(cli::CheckCommand::Headers { url }, _) => {
+            check_headers(&config, url.as_deref().unwrap_or(&config.url))
+        }
+        cli::Command::Check(cli::CheckCommand::Reviews, format) => check_reviews(&config, &policy, format),
+        cli::Command::Check(cli::CheckCommand::Determinism, format) => check_determinism(&config, &policy, format),
+        cli::Command::Check(cli::CheckCommand::Hsts { domain }, format) => check_hsts(&config, domain.as_deref(), format),
+        cli::Command::Check(cli::CheckCommand::Dns { domain, tlsa }, format) => {
+            check_dns(&config, domain.as_deref(), tlsa, format)
+        }
+        cli::Command::Webmention => send_webmentions(&config, &policy),
+        cli::Command::Deployed { notify } => mark_deployed(&config, notify),
+        cli::Command::CitationsArchive => archive_citations(&config, &policy),
+        cli::Command::Report { pageviews } => report(&config, pageviews),
+        cli::Command::ReportStale { older_than, tags } => report_stale(&config, &policy, older_than, &tags),
+        cli::Command::AttestSelf => attest_self(&config),
+        cli::Command::ThemeLock => {
+            let theme = config.theme.as_deref().context("No theme configured in config.yaml")?;
+            let count = theme::write_lock(theme)?;
+            info!("✅ Locked {} files in {}", count, theme::lock_path(theme)?.display());
+            Ok(())
+        }
+        cli::Command::ThemeInstall { source, rev } => {
+            let (dir, origin, count) = vendor::install(&source, &rev, Path::new(vendor::THEMES_DIR))?;
+            info!("✅ Installed {} files from {}@{} into {}", count, source, origin.commit, dir.display());
+            info!("   Review them, then set `theme: {}` in config.yaml", dir.display());
+            Ok(())
+        }
+        cli::Command::ThemeVerify => {
+            let theme = config.theme.as_deref().context("No theme configured in config.yaml")?;
+            match vendor::verify(theme)? {
+                Some(origin) => info!("✅ {} matches its lock and {}@{}", theme.display(), origin.source, origin.commit),
+                None => info!("✅ {} matches its lock (not installed by `theme install`)", theme.display()),
+            }
+            Ok(())
+        }
+        cli::Command::BaselineUpdate => baseline::update(Path::new(baseline::BASELINE), &config.output, &policy),
+        cli::Command::Status => show_status(&config, &policy),
+        cli::Command::HistoryVerify { dir } => {
+            let dir = match dir {
+                Some(dir) => dir,
+                None => config.history.as_ref().context("No history configured in config.yaml")?.dir.clone(),
+            };
+            let count = history::verify(&dir)?;
+            info!("✅ {} snapshots in {} form an unbroken chain", count, dir.display());
+            Ok(())
+        }
+        cli::Command::DumpContext { page } => dump_context(&config, &policy, &page),
+        cli::Command::Stats { logs } => {
+            stats::analyze_logs(&config, &logs)?;
+            info!("✅ Run build to publish /stats/");
+            Ok(())
+        }
+        cli::Command::Import { from, path } => {
+            let importer = match from {
+                cli::ImportFormat::Jekyll => import::jekyll::import,
+                cli::ImportFormat::Zola => import::zola::import,
+            };
+            let count = importer(&path, &config.content, Path::new("config.yaml"))?;
+            info!("✅ Imported {} posts into {}", count, config.content.display());
+            Ok(())
+        }
+        cli::Command::Export(cli::ExportCommand::Bundle { target }) => {
+            let mut posts = load_posts(&config.content, config.timezone.default_zone(), &policy)?;
+            permalinks::assign(&config, &mut posts)?;
+            let written = export::bundle::export(&config, &export::select(&posts, &target)?, &policy)?;
+            info!("✅ Exported {} bundles into {}", written.len(), config.export.output.display());
+            Ok(())
+        }
+        cli::Command::Export(cli::ExportCommand::Epub(selection)) => export_epub(&config, &policy, &selection),
+        cli::Command::Verify(cli::VerifyCommand::CosignBundle { bundle, artifact, identity, issuer }) => {
+            let artifact = artifact.unwrap_or_else(|| config.output.join("integrity.json"));
+            let expected = cosign::Expected { identity: &identity, issuer: &issuer };
+            let verified = cosign::verify_files(&bundle, &artifact, expected)?;
+            info!("✅ {} signed by {}", artifact.display(), verified.identity);
+            info!("   Rekor entry {} logged {}", verified.log_index, verified.integrated_time.to_rfc3339());
+            Ok(())
+        }
+        cli::Command::Verify(cli::VerifyCommand::Rekor { manifest, fingerprint }) => {
+            let manifest = manifest.unwrap_or_else(|| config.output.join("integrity.json"));
+            let fingerprint = match (fingerprint, config.rekor.as_ref().and_then(|r| r.fingerprint.clone()), &config.sign_files) {
+                (Some(fingerprint), _, _) | (None, Some(fingerprint), _) => fingerprint,
+                (None, None, Some(sign_files)) => rekor::key_fingerprint(&sign_files.key)?,
+                (None, None, None) => anyhow::bail!(
+                    "No trusted key: pass --fingerprint SHA256:..., or set rekor.fingerprint or sign_files.key"
+                ),
+            };
+            let verified = rekor::verify_manifest(&manifest, &fingerprint)?;
+            info!("✅ {} signed by {}", manifest.display(), verified.fingerprint);
+            info!(
+                "   Rekor entry {} logged {}, inclusion proof and checkpoint verified",
+                verified.log_index,
+                verified.integrated_time.to_rfc3339()
+            );
+            Ok(())
+        }
+        cli::Command::Export(cli::ExportCommand::Archive) => {
+            let path = export::archive::export(&config)?;
+            info!("✅ Exported {}", path.display());
+            Ok(())
+        }
+        cli::Command::Doctor | cli::Command::Init { .. } | cli::Command::Bench { .. } => {
+            unreachable!("handled before loading the config")
+        }
+        cli::Command::New { kind, title } => {
+            let path = archetype::create(&config, &kind, &title, Utc::now())?;
+            info!("✅ Created {}", path.display());
+            Ok(())
+        }
+    }
+}
+
+/// Generate the site and validate the output
+fn build(config: &Config, policy: &SecurityPolicy) -> Result<()> {
+    let mut timings = buildreport::Timings::start();
+
     // Clean output directory
-    if config.output.exists() {
-        fs::remove_dir_all(&config.output)
+    let files = vfs::current();
+    if files.exists(&config.output) {
+        files.remove_dir_all(&config.output)
             .context("Failed to clean output directory")?;
     }
-    fs::create_dir_all(&config.output)
+    files.create_dir_all(&config.output)
         .context("Failed to create output directory")?;
 
-    // Load and process posts in parallel (Rayon)
-    let posts = load_posts(&config.content, &policy)?;
+    // Load and process posts in parallel (Rayon) from the configured source
+    let content = source::open(&config.source, &config.content);
+    let site = SiteData::load()?;
+    let mut posts = load_posts_from(&*content, config.timezone.default_zone(), policy, &site)?;
     info!("Loaded {} posts", posts.len());
+    timings.lap("load");
+
+    // Publication and update dates from git history
+    if config.git_dates {
+        dates::apply_git_dates(&mut posts)?;
+        posts.sort_by(|a, b| b.meta.date.cmp(&a.meta.date));
+    } else {
+        dates::require_dates(&posts)?;
+    }
+
+    // URLs from the permalink pattern, once dates are final
+    permalinks::assign(config, &mut posts)?;
+
+    // Self-links on headings so readers can copy deep links
+    if let Some(symbol) = &config.heading_anchors {
+        for post in &mut posts {
+            post.html = anchors::apply(&post.html, symbol);
+        }
+    }
+
+    // Workflow status decides what is published, reviewed or tombstoned
+    let status::Partition { published: mut posts, mut review, mut archived } = status::partition(posts, Utc::now());
+
+    // Tombstones only need the title and path of archived posts
+    archived.iter_mut().for_each(Post::release_bodies);
+
+    // Frontmatter `policy:` overrides apply to each post's output page
+    let page_policy = policy.with_pages(&posts);
+    let policy = &page_policy;
+
+    // No time of day (and so no timezone) survives into the output; scheduling used the real dates
+    if config.anonymize {
+        for posts in [&mut posts, &mut review, &mut archived] {
+            anonymize::normalize_dates(posts);
+        }
+    }
+
+    // Site rules from the policy file, e.g. banned tracker links or required disclaimers
+    lint::enforce(&lint::Rules::compile(&policy.rules)?, &posts)?;
+
+    // Only publish content whose latest commit is signed by a trusted key
+    if let Some(signing) = &config.signed_commits {
+        signing::enforce(&posts, signing)?;
+    }
+
+    // Latest change to each post must come from an owner listed in the nearest OWNERS file
+    if config.owners {
+        owners::check_owners(config, &posts)?;
+    }
+
+    // The site, not a person, is the author of an anonymized build
+    let site_config;
+    let config = if config.anonymize {
+        site_config = anonymize::site_config(config);
+        &site_config
+    } else {
+        config
+    };
+
+    // Theme templates are rendered only if every theme file matches the theme's lock
+    if let Some(theme) = &config.theme {
+        theme::verify(theme)?;
+    }
+
+    // Site and theme templates may only include or extend templates inside their directories
+    theme::check_templates(&theme::template_dirs(config.theme.as_deref()))?;
 
     // Generate site (parallel rendering)
-    generator::generate_site(&config, &posts, &policy)?;
+    generator::generate_site(config, &posts, policy)?;
+    timings.lap("generate");
+
+    // Theme fonts, CSS and images from the verified theme, never over a generated page
+    if let Some(theme) = &config.theme {
+        theme::install(theme, &config.output)?;
+    }
+
+    // Site static files: SVGs sanitized, images stripped of metadata, scripts rejected
+    assets::copy(&config.static_dir, &config.output, policy)?;
+
+    // Per-post `css:` stylesheets, checked, scoped and fingerprinted
+    styles::apply(config, &posts, policy)?;
+
+    // Dominant-colour backgrounds shown while images load
+    placeholders::apply(config, &posts)?;
+
+    // Images and other files colocated with page bundles
+    bundles::publish(&config.output, &posts)?;
+
+    // Separate review tree with posts awaiting review (never deployed)
+    status::build_review(config, &posts, &review, policy)?;
+    review.iter_mut().for_each(Post::release_bodies);
+
+    // article:modified_time for updated posts
+    dates::apply(config, &posts)?;
+
+    // Commits that changed each post, from git history
+    if config.changelog {
+        changelog::apply(config, &posts)?;
+    }
+
+    // h-entry, h-card and p-category markup for IndieWeb readers
+    if config.microformats {
+        microformats::apply(config, &posts)?;
+    }
+
+    // Inline QR codes linking to each post's canonical URL
+    if config.qr_codes {
+        qr::apply(config, &posts)?;
+    }
+
+    // Favicons, touch icons and site.webmanifest
+    if let Some(icons) = &config.icons {
+        icons::generate(config, icons)?;
+    }
+
+    // schema.org structured data
+    if config.json_ld {
+        jsonld::apply(config, &posts)?;
+    }
+
+    // Static comments under each post
+    comments::apply(config, &posts, policy)?;
+
+    // Archived copies of the links cited by advisories, recorded by `citations archive`
+    if let Some(citations) = &config.citations {
+        citations::apply(config, &posts, citations)?;
+    }
+
+    // Authored markdown, cleartext-signed and linked from each post
+    if let Some(sign_sources) = &config.sign_sources {
+        cleartext::apply(config, &posts, sign_sources)?;
+    }
+
+    // Open archive of the raw markdown under /src/, hashed in the manifest like every page
+    if config.source_archive {
+        sourcearchive::apply(config, &posts)?;
+    }
+
+    // Posts past their `review_by:` date are marked as possibly outdated
+    reviews::apply(config, &posts)?;
+
+    // Recommended blogs and webring links, held to the external link policy, before outbound exit pages
+    blogroll::apply(config, policy)?;
+
+    // External links leave through per-URL exit pages that send no referrer
+    if let Some(outbound) = &config.outbound {
+        outbound::apply(&config.output, outbound)?;
+    }
+
+    // Password file and web server auth for protected posts, written outside the output
+    protected::apply(config, &posts)?;
+
+    // Report orphan pages and unreferenced assets (machine-readable files are added below)
+    orphans::check_orphans(&config.output, config.prune_unreferenced_assets)?;
+
+    // Archived and retracted posts keep their URL as an unlisted tombstone page
+    status::write_tombstones(config, &archived)?;
+
+    // Static page views and referrers from `stats logs`, no tracking scripts
+    stats::write_page(config)?;
+
+    // IndexNow ownership key for `deployed --notify`
+    if let Some(notify) = &config.notify {
+        notify::write_key_file(&config.output, notify)?;
+    }
+
+    // Old flat URLs keep working when the permalink pattern moves posts
+    permalinks::write_redirects(&config.output, &posts)?;
+
+    // Site-wide and per-tag Atom feeds plus feeds.opml
+    feeds::generate(config, &posts)?;
+
+    // Robots directives from frontmatter; noindex posts leave the sitemap
+    robots::apply(config, &posts)?;
+
+    // Meta descriptions from frontmatter or the lead, where templates left them out
+    excerpt::apply(config, &posts)?;
+
+    // Fediverse actor, outbox and WebFinger documents
+    if let Some(ap) = &config.activitypub {
+        activitypub::generate(config, ap, &posts)?;
+    }
+
+    // Activity streams were the last pass to read post bodies; the rest of the build works on the output
+    posts.iter_mut().for_each(Post::release_bodies);
+
+    // Snapshot of published content, chained to the previous one; deleted posts get tombstones
+    if let Some(history) = &config.history {
+        let now = if config.anonymize { anonymize::midnight(Utc::now()) } else { Utc::now() };
+        status::write_deleted_tombstones(config, &history::removed(&history.dir, &posts)?)?;
+        history::apply(&history.dir, &config.output, &posts, now)?;
+    }
+
+    // LF line endings in every generated text file, whatever the sources or platform used
+    normalize::text_output(&config.output)?;
+
+    // Generator and author metadata out, remaining timestamps to UTC midnight
+    if config.anonymize {
+        anonymize::apply(&config.output)?;
+    }
+
+    // Hardened response headers for the host, per-path overrides last
+    if config.headers.enabled {
+        headers::generate(&config.output, &config.headers)?;
+    }
+
+    // atom.xml.sig / sitemap.xml.sig so mirrors can verify feeds like the manifest
+    if let Some(sign_files) = &config.sign_files {
+        detached::apply(&config.output, sign_files)?;
+    }
+
+    timings.lap("postprocess");
+
+    // Provenance footer: source commit, generator version and manifest root hash
+    let rev = match &config.source {
+        source::SourceConfig::Git { rev } => Some(rev.as_str()),
+        _ => None,
+    };
+    let build_info = buildinfo::BuildInfo::collect(&config.content, &config.output, rev)?;
+    if config.build_footer && !config.anonymize {
+        buildinfo::apply(&config.output, &build_info)?;
+    }
 
     // Generate integrity manifest
-    let manifest = generate_manifest(&config.output)?;
-    fs::write(
-        config.output.join("integrity.json"),
-        serde_json::to_string_pretty(&manifest)?,
+    let manifest = generate_manifest(&config.output, &build_info, config.anonymize)?;
+    files.write(
+        &config.output.join("integrity.json"),
+        serde_json::to_string_pretty(&manifest)?.as_bytes(),
     )?;
 
-    // Security validation
-    security::validate_output(&config.output, &policy)?;
+    // Public, timestamped record of this manifest in a transparency log
+    if let Some(rekor) = &config.rekor {
+        log_manifest(config, rekor)?;
+    }
+
+    // Plain `sha256sum -c` / `b3sum -c` verification for mirrors
+    if config.checksums {
+        checksums::write(&config.output, config.sign_files.as_ref())?;
+    }
+
+    // build-id.txt, per-path hashes with Cache-Control advice, and the nginx ETag map
+    cdn::write(&config.output, &build_info.root)?;
+
+    timings.lap("manifest");
+
+    // Security and internal link validation; only findings outside the reviewed baseline fail
+    let known = baseline::Baseline::load(Path::new(baseline::BASELINE))?;
+    baseline::enforce(&known, &baseline::findings(&config.output, policy)?)?;
+
+    // Compare output sizes against the previous build
+    let mut build_cache = cache::BuildCache::load(&config.cache)?;
+    let sizes = cache::collect_sizes(&config.output)?;
+    let regressions = cache::size_regressions(&build_cache.sizes, &sizes, config.size_growth_threshold);
+    for regression in &regressions {
+        warn!(
+            "Size regression: {} grew {:.1}% ({} -> {} bytes)",
+            regression.path, regression.growth_percent, regression.previous, regression.current
+        );
+    }
+    if config.fail_on_size_growth && !regressions.is_empty() {
+        anyhow::bail!("{} output files exceeded the size growth threshold", regressions.len());
+    }
+    build_cache.sizes = sizes;
+    build_cache.save(&config.cache)?;
+    timings.lap("validate");
+
+    // Fixed mtimes and modes so mirrors don't leak when or by whom the site was built
+    let epoch = reproducible::source_date_epoch()?;
+    for dir in [&config.output, &config.review_output] {
+        if dir.exists() {
+            reproducible::normalize_tree(dir, epoch)?;
+        }
+    }
+    timings.lap("finalize");
+
+    // Counts, size, warnings and timings for CI to archive
+    let counts = buildreport::PostCounts { published: posts.len(), review: review.len(), archived: archived.len() };
+    let overrides = overrides::records(policy, &posts);
+    buildreport::BuildReport::collect(&config.output, &build_info.root, counts, overrides, timings)?
+        .write(&config.build_report)?;
 
     info!("✅ Site generated successfully");
     info!("📁 Output: {}", config.output.display());
@@ -159,21 +1261,623 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+/// Spellcheck and repeated-word lint of the markdown sources
+fn check_prose(config: &Config, format: cli::OutputFormat) -> Result<()> {
+    let wordlist = prose::load_wordlist(&config.prose_words)?;
+    let issues = prose::check_content(&config.content, &wordlist)?;
+
+    match format {
+        cli::OutputFormat::Text => issues.iter().for_each(|issue| warn!("{}", issue)),
+        cli::OutputFormat::Github => annotations::print(
+            &issues
+                .iter()
+                .map(|i| annotations::Annotation::error(i.file.display().to_string(), Some(i.line), &i.message))
+                .collect::<Vec<_>>(),
+        ),
+    }
+    if !issues.is_empty() {
+        anyhow::bail!("Prose check found {} issues", issues.len());
+    }
+
+    info!("✅ Prose check passed");
+    Ok(())
+}
+
+/// Warn about published posts past their `review_by:` date (never fails)
+fn check_reviews(config: &Config, policy: &SecurityPolicy, format: cli::OutputFormat) -> Result<()> {
+    let posts = load_posts(&config.content, config.timezone.default_zone(), policy)?;
+    let now = Utc::now();
+    let published = status::partition(posts, now).published;
+    let overdue = reviews::overdue(&published, now);
+    let zone = config.timezone.display_zone();
+
+    let problems: Vec<annotations::Annotation> = overdue
+        .iter()
+        .filter_map(|post| {
+            let review_by = post.meta.review_by?.with_timezone(&zone).format("%Y-%m-%d");
+            let line = fs::read_to_string(&post.source).ok().as_deref().and_then(reviews::frontmatter_line);
+            let message = format!("{} was due for review on {}", post.meta.slug, review_by);
+            Some(annotations::Annotation::warning(post.source.display().to_string(), line, message))
+        })
+        .collect();
+    match format {
+        cli::OutputFormat::Text => problems.iter().for_each(|problem| warn!("{}", problem.message)),
+        cli::OutputFormat::Github => annotations::print(&problems),
+    }
+
+    if overdue.is_empty() {
+        info!("✅ No published posts past their review date");
+    } else {
+        info!("🗓️  {} of {} published posts past their review date", overdue.len(), published.len());
+    }
+    Ok(())
+}
+
+/// Security violations and broken links in the last build's output that the baseline does not cover
+fn check_output(config: &Config, policy: &SecurityPolicy, format: cli::OutputFormat, verbose: bool) -> Result<()> {
+    if !config.output.exists() {
+        anyhow::bail!("No output in {}, run build first", config.output.display());
+    }
+    let known = baseline::Baseline::load(Path::new(baseline::BASELINE))?;
+    let policy = SecurityPolicy { pages: overrides::recorded_pages(&config.build_report)?, ..policy.clone() };
+    let findings = baseline::findings(&config.output, &policy)?;
+    if format == cli::OutputFormat::Github {
+        let new = baseline::new_findings(&known, &findings);
+        annotations::print(
+            &new.iter()
+                .map(|f| {
+                    let file = config.output.join(&f.file).display().to_string();
+                    annotations::Annotation::error(file, f.line, f.to_string())
+                })
+                .collect::<Vec<_>>(),
+        );
+    } else if verbose {
+        print!("{}", explain::verbose(&config.output, &baseline::new_findings(&known, &findings)));
+    }
+    baseline::enforce(&known, &findings)?;
+
+    info!("✅ No security violations or broken links outside {}", baseline::BASELINE);
+    Ok(())
+}
+
+/// Load, render and prose-check only the posts affected by `paths` (or by uncommitted changes)
+fn check_changed(config: &Config, policy: &SecurityPolicy, paths: Vec<PathBuf>, format: cli::OutputFormat) -> Result<()> {
+    let posts = changed::scan(&config.content)?;
+    let paths = if paths.is_empty() { git::changed_since_head(&changed::watched(&posts))? } else { paths };
+    let affected = changed::affected(&posts, &paths);
+    if affected.is_empty() {
+        info!("✅ No posts affected by {} changed files", paths.len());
+        return Ok(());
+    }
+
+    let wordlist = prose::load_wordlist(&config.prose_words)?;
+    let rules = lint::Rules::compile(&policy.rules)?;
+    let site = SiteData::load()?;
+    let problems: Vec<annotations::Annotation> = affected
+        .par_iter()
+        .map(|source| {
+            let file = source.display().to_string();
+            let mut problems = Vec::new();
+            let loaded = load_post(source, config.timezone.default_zone(), policy, &site).and_then(|post| match &post.meta.css {
+                Some(css) => styles::stylesheet(&post, css, policy).map(|_| post),
+                None => Ok(post),
+            });
+            match loaded {
+                Ok(post) => problems.extend(rules.check(&post).into_iter().map(|finding| annotations::Annotation {
+                    level: match finding.severity {
+                        lint::Severity::Error => annotations::Level::Error,
+                        lint::Severity::Warning => annotations::Level::Warning,
+                    },
+                    file: Some(file.clone()),
+                    line: None,
+                    message: finding.to_string(),
+                })),
+                Err(e) => problems.push(annotations::Annotation::error(&file, None, format!("{e:#}"))),
+            }
+            if let Ok(text) = fs::read_to_string(source) {
+                problems.extend(
+                    prose::check_source(source, &text, &wordlist)
+                        .into_iter()
+                        .map(|issue| annotations::Annotation::error(&file, Some(issue.line), issue.message)),
+                );
+            }
+            problems
+        })
+        .flatten()
+        .collect();
+
+    match format {
+        cli::OutputFormat::Text => problems.iter().for_each(|p| warn!("{}", p)),
+        cli::OutputFormat::Github => annotations::print(&problems),
+    }
+    if !problems.is_empty() {
+        anyhow::bail!("{} problems in {} affected posts", problems.len(), affected.len());
+    }
+    info!("✅ {} affected posts passed", affected.len());
+    Ok(())
+}
+
+/// Build the site twice into scratch directories and fail if the outputs differ in any byte
+fn check_determinism(config: &Config, policy: &SecurityPolicy, format: cli::OutputFormat) -> Result<()> {
+    let scratch = std::env::temp_dir().join(format!("secureblog-determinism-{}", std::process::id()));
+    let outputs = [scratch.join("first"), scratch.join("second")];
+    for dir in &outputs {
+        // Nothing shared with the real build; history and Rekor would record the first run
+        let run_config = Config {
+            output: dir.join("dist"),
+            review_output: dir.join("review"),
+            cache: dir.join(".secureblog-cache.json"),
+            build_report: dir.join("build-report.json"),
+            history: None,
+            rekor: None,
+            ..config.clone()
+        };
+        build(&run_config, policy)?;
+    }
+    let differences = determinism::compare(&outputs[0].join("dist"), &outputs[1].join("dist"))?;
+    fs::remove_dir_all(&scratch).with_context(|| format!("Failed to remove {}", scratch.display()))?;
+
+    let problems: Vec<annotations::Annotation> = differences
+        .iter()
+        .map(|d| annotations::Annotation::error(config.output.join(&d.path).display().to_string(), d.line, &d.message))
+        .collect();
+    match format {
+        cli::OutputFormat::Text => problems.iter().for_each(|p| warn!("{}", p)),
+        cli::OutputFormat::Github => annotations::print(&problems),
+    }
+    if !problems.is_empty() {
+        anyhow::bail!("{} files differ between two builds of the same sources", problems.len());
+    }
+    info!("✅ Two builds produced identical output");
+    Ok(())
+}
+
+/// Print the `doctor` checklist and fail if any item failed
+fn run_doctor(policy: &SecurityPolicy) -> Result<()> {
+    let checks = match load_config() {
+        Ok(config) => doctor::run(&config, policy),
+        Err(e) => vec![
+            doctor::check_version(),
+            doctor::Check::new("config", doctor::Outcome::Fail(format!("{e:#}"))),
+        ],
+    };
+
+    for check in &checks {
+        if matches!(check.outcome, doctor::Outcome::Pass(_)) {
+            info!("{}", check);
+        } else {
+            warn!("{}", check);
+        }
+    }
+    let failed = checks.iter().filter(|c| c.failed()).count();
+    if failed > 0 {
+        anyhow::bail!("doctor found {failed} problems");
+    }
+    info!("✅ Ready to build");
+    Ok(())
+}
+
+/// Print the binary's hash and build metadata, failing on any pinned value that differs
+fn attest_self(config: &Config) -> Result<()> {
+    let attestation = attest::SelfAttestation::collect()?;
+    info!("secureblog-rs {}", attestation.version);
+    info!("sha256: {}", attestation.sha256);
+    info!("rustc: {}", attestation.rustc);
+    info!("cargo_lock: {}", attestation.cargo_lock);
+
+    let Some(expected) = &config.expected_binary else {
+        warn!("No expected_binary in config.yaml; nothing to compare against");
+        return Ok(());
+    };
+    let mismatches = attestation.mismatches(expected);
+    for mismatch in &mismatches {
+        warn!("{}", mismatch);
+    }
+    if !mismatches.is_empty() {
+        anyhow::bail!("Generator binary does not match expected_binary ({} differences)", mismatches.len());
+    }
+    info!("✅ Generator binary matches expected_binary");
+    Ok(())
+}
+
+/// List posts by workflow status
+fn show_status(config: &Config, policy: &SecurityPolicy) -> Result<()> {
+    let posts = load_posts(&config.content, config.timezone.default_zone(), policy)?;
+    let now = Utc::now();
+    let zone = config.timezone.display_zone();
+
+    for (status, posts) in status::by_status(&posts) {
+        info!("{} ({})", status, posts.len());
+        for post in posts {
+            let pending = if status::disposition(&post.meta, now) == status::Disposition::Pending {
+                " (not yet due)"
+            } else {
+                ""
+            };
+            info!("  {}  {}{}", post.meta.date.with_timezone(&zone).format("%Y-%m-%d"), post.meta.slug, pending);
+        }
+    }
+    Ok(())
+}
+
+/// List published posts whose last update is older than `older_than`
+fn report_stale(config: &Config, policy: &SecurityPolicy, older_than: freshness::Age, tags: &[String]) -> Result<()> {
+    let mut posts = load_posts(&config.content, config.timezone.default_zone(), policy)?;
+    if config.git_dates {
+        dates::apply_git_dates(&mut posts)?;
+    } else {
+        dates::require_dates(&posts)?;
+    }
+    let now = Utc::now();
+    let published = status::partition(posts, now).published;
+    let cutoff = older_than.cutoff(now);
+    let stale = freshness::stale(&published, cutoff, tags);
+    let zone = config.timezone.display_zone();
+
+    for post in &stale {
+        info!(
+            "  {}  {:>6}  {}  {}",
+            post.modified().with_timezone(&zone).format("%Y-%m-%d"),
+            freshness::age_label(post.modified(), now),
+            post.url_path(),
+            post.meta.title
+        );
+    }
+    let since = cutoff.with_timezone(&zone).format("%Y-%m-%d");
+    info!("🕰️  {} of {} published posts not updated since {}", stale.len(), published.len(), since);
+    Ok(())
+}
+
+/// Build into the preview directory, then make every page refresh itself
+fn build_preview(config: &Config, policy: &SecurityPolicy, refresh: u32) -> Result<()> {
+    // Same pipeline and checks as a real build, but nothing is published or shared with it;
+    // history and Rekor would record work in progress as published
+    let mut review_output = config.preview_output.clone().into_os_string();
+    review_output.push("-review");
+    let preview_config = Config {
+        output: config.preview_output.clone(),
+        review_output: review_output.into(),
+        cache: config.preview_output.join(".secureblog-cache.json"),
+        build_report: config.preview_output.join("build-report.json"),
+        history: None,
+        rekor: None,
+        ..config.clone()
+    };
+    build(&preview_config, policy)?;
+
+    let pages = preview::apply(&config.preview_output, refresh)?;
+    info!("🔁 {} pages in {} refresh every {}s (do not deploy this directory)", pages, config.preview_output.display(), refresh);
+    Ok(())
+}
+
+/// Config building the posts of a git revision, refusing templates that differ from it
+fn revision_config(config: &Config, rev: &str) -> Result<Config> {
+    // Pin the revision to a commit so the content and provenance cannot disagree
+    let (commit, _) = source::files_at(rev, |_| false)?;
+
+    // Templates are rendered from the working tree, which must match the commit exactly
+    let mut dirs = theme::template_dirs(None);
+    dirs.extend(config.theme.clone());
+    let differences = source::differences(&commit, &dirs)?;
+    if !differences.is_empty() {
+        anyhow::bail!("Templates do not match {rev} ({commit}):\n  {}", differences.join("\n  "));
+    }
+
+    info!("📌 Building {} ({})", rev, commit);
+    Ok(Config { source: source::SourceConfig::Git { rev: commit }, ..config.clone() })
+}
+
+/// Build into a scratch directory and list what the build would change in the output
+fn build_dry_run(config: &Config, policy: &SecurityPolicy) -> Result<()> {
+    let scratch = std::env::temp_dir().join(format!("secureblog-dry-run-{}", std::process::id()));
+    // Nothing shared with the real build; history and Rekor would record this run
+    let run_config = Config {
+        output: scratch.join("dist"),
+        review_output: scratch.join("review"),
+        cache: scratch.join(".secureblog-cache.json"),
+        build_report: scratch.join("build-report.json"),
+        history: None,
+        rekor: None,
+        ..config.clone()
+    };
+    build(&run_config, policy)?;
+    let built = sink::Memory::default();
+    sink::copy_tree(&run_config.output, &built)?;
+    fs::remove_dir_all(&scratch).with_context(|| format!("Failed to remove {}", scratch.display()))?;
+
+    let current = sink::Memory::default();
+    if config.output.exists() {
+        sink::copy_tree(&config.output, &current)?;
+    }
+    let changes = built.changes_from(&current);
+    for change in &changes {
+        info!("  {}", change);
+    }
+    info!("🧪 Dry run: {} of {} files in {} would change (nothing written)", changes.len(), built.files().len(), config.output.display());
+    Ok(())
+}
+
+/// Print the template context of one page of the published site
+fn dump_context(config: &Config, policy: &SecurityPolicy, page: &str) -> Result<()> {
+    let mut posts = load_posts(&config.content, config.timezone.default_zone(), policy)?;
+    if config.git_dates {
+        dates::apply_git_dates(&mut posts)?;
+        posts.sort_by(|a, b| b.meta.date.cmp(&a.meta.date));
+    } else {
+        dates::require_dates(&posts)?;
+    }
+    permalinks::assign(config, &mut posts)?;
+    let mut posts = status::partition(posts, Utc::now()).published;
+    if config.anonymize {
+        anonymize::normalize_dates(&mut posts);
+        println!("{}", context::dump(&anonymize::site_config(config), &posts, page)?);
+    } else {
+        println!("{}", context::dump(config, &posts, page)?);
+    }
+    Ok(())
+}
+
+/// Estimate page weight, monthly bandwidth and carbon footprint of the built site
+fn report(config: &Config, pageviews: Option<u64>) -> Result<()> {
+    if !config.output.exists() {
+        anyhow::bail!("Output directory {} not found (run build first)", config.output.display());
+    }
+
+    let assumptions = report::ReportConfig {
+        monthly_pageviews: pageviews.unwrap_or(config.report.monthly_pageviews),
+        ..config.report.clone()
+    };
+    let mut weights = report::page_weights(&config.output)?;
+    weights.sort_by_key(|w| std::cmp::Reverse(w.transfer_bytes));
+
+    for weight in &weights {
+        info!(
+            "{:>8.1} KB  (HTML {:.1} KB)  {}",
+            kilobytes(weight.transfer_bytes),
+            kilobytes(weight.html_bytes),
+            weight.page
+        );
+    }
+
+    let estimate = report::estimate(&weights, &assumptions);
+    info!("📊 {} pages, average transfer {:.1} KB per view", weights.len(), kilobytes(estimate.average_bytes));
+    info!(
+        "📊 {} views/month: {:.2} MB, {:.3} kWh, {:.1} g CO2e",
+        assumptions.monthly_pageviews,
+        kilobytes(estimate.monthly_bytes) / 1000.0,
+        estimate.monthly_kwh,
+        estimate.monthly_grams_co2
+    );
+    Ok(())
+}
+
+/// Package the selected posts as an EPUB3 book
+fn export_epub(config: &Config, policy: &SecurityPolicy, selection: &cli::Selection) -> Result<()> {
+    let mut posts = load_posts(&config.content, config.timezone.default_zone(), policy)?;
+    permalinks::assign(config, &mut posts)?;
+    let (title, selected) = match selection {
+        cli::Selection::Series(name) => (name.clone(), export::select_series(&posts, name)?),
+        cli::Selection::Posts(targets) => {
+            let mut selected = Vec::new();
+            for target in targets {
+                selected.extend(export::select(&posts, target)?);
+            }
+            if targets.iter().any(|t| t == "all") {
+                selected.sort_by_key(|p| p.meta.date);
+            }
+            let title = match selected.as_slice() {
+                [post] => post.meta.title.clone(),
+                _ => config.title.clone(),
+            };
+            (title, selected)
+        }
+    };
+
+    let path = export::epub::export(config, &title, selected)?;
+    info!("✅ Exported {}", path.display());
+    Ok(())
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn kilobytes(bytes: u64) -> f64 {
+    bytes as f64 / 1000.0
+}
+
+/// Send webmentions for outbound links in published posts (post-deploy step)
+#[cfg(feature = "network")]
+fn send_webmentions(config: &Config, policy: &SecurityPolicy) -> Result<()> {
+    let mut posts = load_posts(&config.content, config.timezone.default_zone(), policy)?;
+    permalinks::assign(config, &mut posts)?;
+    let mut state = webmention::MentionState::load(&config.webmention_state)?;
+
+    // Save progress even if a later endpoint fails
+    let result = webmention::send_all(config, &posts, &mut state);
+    state.save(&config.webmention_state)?;
+
+    info!("✅ Sent {} webmentions", result?);
+    Ok(())
+}
+
+/// Webmentions require network access, which is compiled out by default
+#[cfg(not(feature = "network"))]
+fn send_webmentions(_config: &Config, _policy: &SecurityPolicy) -> Result<()> {
+    anyhow::bail!("Sending webmentions requires a build with `--features network`")
+}
+
+/// Record the output as deployed, listing the changed sitemap pages; `--notify` also tells search engines
+fn mark_deployed(config: &Config, send: bool) -> Result<()> {
+    let settings = config.notify.as_ref().context("No `notify:` section in the config")?;
+    settings.validate()?;
+    let previous = notify::Deployed::load(&settings.state)?;
+    let current = notify::Deployed::from_output(&config.output)?;
+    let urls = current.changed_urls(&previous, &config.url);
+    for url in &urls {
+        println!("{url}");
+    }
+
+    // A failed notification leaves the state alone, so the next run resends the same URLs
+    if send {
+        notify_search_engines(settings, &config.url, &urls)?;
+    }
+    current.save(&settings.state)?;
+    info!("✅ Recorded deploy of {} files, {} pages changed", current.files.len(), urls.len());
+    Ok(())
+}
+
+/// Submit changed URLs to IndexNow and ping the sitemap (online step)
+#[cfg(feature = "network")]
+fn notify_search_engines(settings: &notify::NotifyConfig, site: &str, urls: &[String]) -> Result<()> {
+    notify::notify(settings, site, urls)
+}
+
+/// Notifying search engines requires network access, which is compiled out by default
+#[cfg(not(feature = "network"))]
+fn notify_search_engines(_settings: &notify::NotifyConfig, _site: &str, _urls: &[String]) -> Result<()> {
+    anyhow::bail!("deployed --notify requires a build with `--features network`")
+}
+
+/// Submit the links of cited posts to web archives and record the snapshots (online step)
+#[cfg(feature = "network")]
+fn archive_citations(config: &Config, policy: &SecurityPolicy) -> Result<()> {
+    let citations = config.citations.as_ref().context("No `citations:` section in the config")?;
+    let mut posts = load_posts(&config.content, config.timezone.default_zone(), policy)?;
+    permalinks::assign(config, &mut posts)?;
+    let mut state = citations::CitationState::load(&citations.state)?;
+
+    // Save progress even if a later submission fails
+    let result = citations::archive_all(config, &posts, citations, &mut state);
+    state.save(&citations.state)?;
+
+    info!("✅ Archived {} new snapshots", result?);
+    Ok(())
+}
+
+/// Archiving requires network access, which is compiled out by default
+#[cfg(not(feature = "network"))]
+fn archive_citations(_config: &Config, _policy: &SecurityPolicy) -> Result<()> {
+    anyhow::bail!("Archiving citations requires a build with `--features network`")
+}
+
+/// Sign `integrity.json` and store its Rekor entry next to it
+#[cfg(feature = "network")]
+fn log_manifest(config: &Config, rekor: &rekor::RekorConfig) -> Result<()> {
+    let key = &config.sign_files.as_ref().context("rekor needs sign_files.key to sign integrity.json")?.key;
+    rekor::submit(rekor, &config.output.join("integrity.json"), key)?;
+    Ok(())
+}
+
+/// Submitting to Rekor requires network access, which is compiled out by default
+#[cfg(not(feature = "network"))]
+fn log_manifest(_config: &Config, _rekor: &rekor::RekorConfig) -> Result<()> {
+    anyhow::bail!("rekor requires a build with `--features network`")
+}
+
+/// Grade a deployed site's response headers against the generated `_headers`
+#[cfg(feature = "network")]
+fn check_headers(config: &Config, site: &str) -> Result<()> {
+    let failing = headers::check_site(site, &config.headers)?;
+    if failing > 0 {
+        anyhow::bail!("{failing} pages are not served with the generated headers");
+    }
+    info!("✅ {} serves every generated header", site);
+    Ok(())
+}
+
+/// Fetching the live site requires network access, which is compiled out by default
+#[cfg(not(feature = "network"))]
+fn check_headers(_config: &Config, _site: &str) -> Result<()> {
+    anyhow::bail!("check headers requires a build with `--features network`")
+}
+
+/// Report every HSTS preload requirement the domain (default: the site's host) misses
+#[cfg(feature = "network")]
+fn check_hsts(config: &Config, domain: Option<&str>, format: cli::OutputFormat) -> Result<()> {
+    let host = site_domain(config, domain)?;
+
+    let mut problems = Vec::new();
+    if config.headers.enabled {
+        let headers_file = config.output.join("_headers").display().to_string();
+        for problem in hsts::header_problems(Some(&config.headers.hsts)) {
+            problems.push(annotations::Annotation::error(&headers_file, None, problem.to_string()));
+        }
+    }
+    for problem in hsts::check_domain(&host)? {
+        problems.push(annotations::Annotation::general(annotations::Level::Error, format!("{host}: {problem}")));
+    }
+    match format {
+        cli::OutputFormat::Text => problems.iter().for_each(|p| match &p.file {
+            Some(_) => warn!("_headers: {}", p.message),
+            None => warn!("{}", p.message),
+        }),
+        cli::OutputFormat::Github => annotations::print(&problems),
+    }
+    let missing = problems.len();
+
+    if missing > 0 {
+        anyhow::bail!("{host} is not ready for the HSTS preload list ({missing} problems)");
+    }
+    info!("✅ {} meets the HSTS preload requirements (subdomains must also serve HTTPS)", host);
+    Ok(())
+}
+
+/// Report missing CAA, DNSSEC and (with `tlsa`) DANE protection for the domain
+#[cfg(feature = "network")]
+fn check_dns(config: &Config, domain: Option<&str>, tlsa: bool, format: cli::OutputFormat) -> Result<()> {
+    let host = site_domain(config, domain)?;
+    let gaps = dns::check_domain(&host, tlsa)?;
+    match format {
+        cli::OutputFormat::Text => gaps.iter().for_each(|gap| warn!("{}: {}", host, gap)),
+        cli::OutputFormat::Github => annotations::print(
+            &gaps
+                .iter()
+                .map(|gap| {
+                    let level = if gap.is_advisory() { annotations::Level::Warning } else { annotations::Level::Error };
+                    annotations::Annotation::general(level, format!("{host}: {gap}"))
+                })
+                .collect::<Vec<_>>(),
+        ),
+    }
+
+    let failing = gaps.iter().filter(|gap| !gap.is_advisory()).count();
+    if failing > 0 {
+        anyhow::bail!("{host} has {failing} DNS security gaps");
+    }
+    info!("✅ {} has CAA{} and DNSSEC", host, if tlsa { ", TLSA" } else { "" });
+    Ok(())
+}
+
+/// Explicit domain, or the host of the site URL
+#[cfg(feature = "network")]
+fn site_domain(config: &Config, domain: Option<&str>) -> Result<String> {
+    let site_host = url::Url::parse(&config.url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string));
+    domain
+        .map(str::to_string)
+        .or(site_host)
+        .context("No domain given and `url` has no host")
+}
+
+/// DNS lookups go through a DoH resolver, which is compiled out by default
+#[cfg(not(feature = "network"))]
+fn check_dns(_config: &Config, _domain: Option<&str>, _tlsa: bool, _format: cli::OutputFormat) -> Result<()> {
+    anyhow::bail!("check dns requires a build with `--features network`")
+}
+
+/// The preload check fetches the live site, which is compiled out by default
+#[cfg(not(feature = "network"))]
+fn check_hsts(_config: &Config, _domain: Option<&str>, _format: cli::OutputFormat) -> Result<()> {
+    anyhow::bail!("check hsts requires a build with `--features network`")
+}
+
 /// Load configuration from file
 fn load_config() -> Result<Config> {
     let config_path = Path::new("config.yaml");
-    if !config_path.exists() {
-        return Ok(Config {
-            title: "SecureBlog".to_string(),
-            url: "https://example.com".to_string(),
-            author: "Anonymous".to_string(),
-            output: default_output(),
-            content: default_content(),
-            use_blake3: true,
-        });
+    if !vfs::current().exists(config_path) {
+        return Ok(Config::default());
     }
 
-    let content = fs::read_to_string(config_path)
+    let content = vfs::current().read_to_string(config_path)
         .context("Failed to read config.yaml")?;
     let config: Config = serde_yaml::from_str(&content)
         .context("Failed to parse config.yaml")?;
@@ -181,38 +1885,83 @@ fn load_config() -> Result<Config> {
     Ok(config)
 }
 
+/// Site-wide data files every post is rendered against, read once per build
+#[derive(Debug, Clone)]
+pub struct SiteData {
+    /// Sources of `data/bibliography.yaml`
+    pub bibliography: bibliography::Bibliography,
+    /// Acronyms of `data/abbreviations.yaml`
+    pub abbreviations: abbreviations::Abbreviations,
+}
+
+impl SiteData {
+    /// Read the data files (empty where a file does not exist)
+    pub fn load() -> Result<Self> {
+        Ok(Self {
+            bibliography: bibliography::load_site()?,
+            abbreviations: abbreviations::Abbreviations::load(Path::new(abbreviations::ABBREVIATIONS))?,
+        })
+    }
+}
+
 /// Load all posts from content directory
-fn load_posts(content_dir: &Path, policy: &SecurityPolicy) -> Result<Vec<Post>> {
-    let posts: Result<Vec<_>> = WalkDir::new(content_dir)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| {
-            e.path().extension()
-                .and_then(|s| s.to_str())
-                .map_or(false, |ext| ext == "md" || ext == "markdown")
+///
+/// Every post is attempted; failures are reported together, with their paths, before failing.
+fn load_posts(content_dir: &Path, zone: chrono_tz::Tz, policy: &SecurityPolicy) -> Result<Vec<Post>> {
+    load_posts_from(&source::Directory { dir: content_dir.to_path_buf() }, zone, policy, &SiteData::load()?)
+}
+
+/// Load all posts of a content source (working tree, git revision or archive)
+fn load_posts_from(source: &dyn source::ContentSource, zone: chrono_tz::Tz, policy: &SecurityPolicy, site: &SiteData) -> Result<Vec<Post>> {
+    let results: Vec<(PathBuf, Result<Post>)> = source
+        .posts()?
+        .into_par_iter() // Parallel processing
+        .map(|(path, content)| {
+            let post = parse_post(&path, &content, zone, policy, site);
+            (path, post)
         })
-        .par_bridge() // Parallel processing
-        .map(|entry| load_post(entry.path(), policy))
         .collect();
 
-    let mut posts = posts?;
-    
+    let mut posts = Vec::with_capacity(results.len());
+    let mut failures = Vec::new();
+    for (path, result) in results {
+        match result {
+            Ok(post) => posts.push(post),
+            Err(e) => failures.push((path, e)),
+        }
+    }
+    if !failures.is_empty() {
+        return Err(load_failures(failures, posts.len()));
+    }
+
     // Sort by date (newest first)
     posts.sort_by(|a, b| b.meta.date.cmp(&a.meta.date));
-    
-    // Filter drafts in release mode
-    #[cfg(not(debug_assertions))]
-    {
-        posts.retain(|p| !p.meta.draft);
-    }
 
     Ok(posts)
 }
 
-/// Load a single post
-fn load_post(path: &Path, policy: &SecurityPolicy) -> Result<Post> {
-    let content = fs::read_to_string(path)
+/// One error listing every post that failed to load, sorted by path
+fn load_failures(mut failures: Vec<(PathBuf, anyhow::Error)>, loaded: usize) -> anyhow::Error {
+    failures.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut message = format!("{} of {} posts failed to load:", failures.len(), failures.len() + loaded);
+    for (path, e) in &failures {
+        message.push_str(&format!("\n  {}: {:#}", path.display(), e));
+    }
+    anyhow::anyhow!(message)
+}
+
+/// Load a single post, reading dates without an offset in `zone`
+fn load_post(path: &Path, zone: chrono_tz::Tz, policy: &SecurityPolicy, site: &SiteData) -> Result<Post> {
+    let content = vfs::current().read_to_string(path)
         .with_context(|| format!("Failed to read post: {}", path.display()))?;
+    parse_post(path, &content, zone, policy, site)
+}
+
+/// Render a post from its source text; `path` places it for slugs, bundles and errors
+fn parse_post(path: &Path, content: &str, zone: chrono_tz::Tz, policy: &SecurityPolicy, site: &SiteData) -> Result<Post> {
+    let source_text = content;
+    // CRLF checkouts render the same as LF ones
+    let content = normalize::newlines(content);
 
     // Check file size
     if content.len() > policy.max_file_size {
@@ -220,13 +1969,64 @@ fn load_post(path: &Path, policy: &SecurityPolicy) -> Result<Post> {
     }
 
     // Parse frontmatter and content
-    let (meta, markdown) = markdown::parse_frontmatter(&content)?;
+    let (mut meta, markdown) = markdown::parse_frontmatter(&content)?;
+    timezone::apply_default_zone(&mut meta, &content, zone)
+        .with_context(|| format!("Invalid date in {}", path.display()))?;
+    let post_policy = policy.for_post(meta.policy.as_ref());
+    let policy = &*post_policy;
+    if meta.draft {
+        meta.status = status::PostStatus::Draft;
+    }
+    if meta.protected {
+        meta.noindex = true;
+    }
+
+    // A page bundle is named after its directory
+    if meta.slug.is_empty() && bundles::is_bundle(path) {
+        meta.slug = bundles::bundle_slug(path).unwrap_or_default();
+    }
+
+    // Hash-pinned code snippets from files in the repository
+    let markdown = include::expand(&markdown, Path::new("."))
+        .with_context(|| format!("Failed to expand includes in {}", path.display()))?;
+
+    // `[@key]` citations numbered against the site's and the post's bibliography
+    let (cited, citations) = bibliography::expand(&markdown, &bibliography::load(&site.bibliography, path)?)
+        .with_context(|| format!("Failed to resolve citations in {}", path.display()))?;
+
+    // Gallery images are re-encoded without metadata; the markup goes in after rendering
+    let asset_slug = if meta.slug.is_empty() {
+        slug::slugify(&path.file_stem().unwrap_or_default().to_string_lossy())
+    } else {
+        meta.slug.clone()
+    };
+    let (rendered, galleries) = gallery::expand(&excerpt::mark(&cited), path, &asset_slug)?;
 
     // Render and sanitize HTML
-    let html = markdown::render_markdown(&markdown, policy)?;
+    let html = markdown::render_markdown(&rendered, policy)?;
+    let html = diff::apply(&admonitions::apply(&html));
+    let html = gallery::insert(&html, &galleries);
+
+    // Colocated assets of a page bundle (gallery originals excluded), with relative references pointed at them
+    let mut assets = if bundles::is_bundle(path) && !meta.slug.is_empty() {
+        bundles::collect(path, &meta.slug, policy)?
+    } else {
+        Vec::new()
+    };
+    assets.retain(|asset| !galleries.iter().any(|g| asset.source.starts_with(&format!("{}/", g.dir))));
+    assets.extend(galleries.into_iter().flat_map(|g| g.assets));
+    let html = pdf::annotate_links(&bundles::rewrite_references(&html, &assets), &assets);
+    let html = bibliography::insert(&html, &citations, &assets, policy)
+        .with_context(|| format!("Failed to list sources in {}", path.display()))?;
+
+    // The lead before `<!--more-->` is kept for summaries and descriptions
+    let (html, excerpt) = excerpt::split(&html);
+
+    // Known acronyms get their expansion in `<abbr title>`, once every placeholder is filled
+    let (html, excerpt) = (site.abbreviations.apply(&html), site.abbreviations.apply(&excerpt));
 
     // Calculate content hash
-    let hash = if meta.draft {
+    let hash = if meta.status == status::PostStatus::Draft {
         "DRAFT".to_string()
     } else {
         let mut hasher = Sha256::new();
@@ -238,32 +2038,40 @@ fn load_post(path: &Path, policy: &SecurityPolicy) -> Result<Post> {
         meta,
         content: markdown,
         html,
+        excerpt,
         hash,
         source: path.to_path_buf(),
+        source_text: source_text.to_string(),
+        assets,
+        route: String::new(),
     })
 }
 
 /// Generate integrity manifest
-fn generate_manifest(output_dir: &Path) -> Result<serde_json::Value> {
+///
+/// Anonymized manifests keep only the file list and root hash, dated at UTC midnight.
+fn generate_manifest(output_dir: &Path, build_info: &buildinfo::BuildInfo, anonymize: bool) -> Result<serde_json::Value> {
     let mut files = Vec::new();
 
-    for entry in WalkDir::new(output_dir)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-    {
-        let path = entry.path();
-        let relative = path.strip_prefix(output_dir)?;
-        
-        let content = fs::read(path)?;
-        let mut hasher = Sha256::new();
-        hasher.update(&content);
-        let hash = format!("{:x}", hasher.finalize());
+    // Streamed in chunks: large media never has to fit in memory
+    for path in vfs::current().files(output_dir)? {
+        let relative = normalize::relative(&path, output_dir)?;
+        let hashes = checksums::hash_file(&path)?;
 
         files.push(serde_json::json!({
-            "path": relative.display().to_string(),
-            "size": content.len(),
-            "sha256": hash,
+            "path": relative,
+            "size": hashes.size,
+            "sha256": hashes.sha256,
+            "blake3": hashes.blake3,
+        }));
+    }
+
+    if anonymize {
+        return Ok(serde_json::json!({
+            "version": "1.0",
+            "generated": anonymize::midnight(Utc::now()).to_rfc3339(),
+            "root": build_info.root,
+            "files": files,
         }));
     }
 
@@ -271,6 +2079,8 @@ fn generate_manifest(output_dir: &Path) -> Result<serde_json::Value> {
         "version": "1.0",
         "generated": Utc::now().to_rfc3339(),
         "generator": "secureblog-rs",
+        "root": build_info.root,
+        "build": build_info,
         "files": files,
     }))
 }
@@ -287,6 +2097,45 @@ mod tests {
         assert_eq!(policy.max_file_size, 10 * 1024 * 1024);
     }
 
+    #[test]
+    fn test_security_policy_file() {
+        let policy: SecurityPolicy = serde_yaml::from_str(
+            "no_inline_styles: true\nallowed_hosts: [fonts.example.com, '*.example.org']\nseverity: { inline_styles: warning }\n",
+        )
+        .unwrap();
+        assert!(policy.no_javascript && policy.no_inline_styles);
+        assert_eq!(policy.severity(Check::InlineStyles), lint::Severity::Warning);
+        assert_eq!(policy.severity(Check::External), lint::Severity::Error);
+        assert!(policy.allows_url("https://fonts.example.com/a.woff2"));
+        assert!(policy.allows_url("https://cdn.EXAMPLE.org:443/x"));
+        assert!(!policy.allows_url("https://example.org.evil.test/"));
+        assert!(!policy.allows_url("https://user@evil.test/?fonts.example.com"));
+        assert!(policy.validate().is_ok());
+
+        assert!(serde_yaml::from_str::<SecurityPolicy>("no_javscript: false\n").is_err());
+        assert!(serde_yaml::from_str::<SecurityPolicy>("severity: { scripts: warning }\n").is_err());
+        let bad_host = SecurityPolicy { allowed_hosts: vec!["https://cdn.example.com".to_string()], ..SecurityPolicy::default() };
+        assert!(bad_host.validate().is_err());
+        assert!(SecurityPolicy { max_file_size: 0, ..SecurityPolicy::default() }.validate().is_err());
+        let bad_rel = SecurityPolicy { external_link_rel: vec!["opener".to_string()], ..SecurityPolicy::default() };
+        assert!(bad_rel.validate().is_err());
+    }
+
+    #[test]
+    fn test_policy_presets() {
+        assert_eq!(PolicyPreset::Strict.policy(), SecurityPolicy::default());
+        let paranoid = PolicyPreset::Paranoid.policy();
+        assert!(paranoid.no_inline_styles && paranoid.no_data_uris && paranoid.no_external);
+        assert_eq!(paranoid.max_page_size, 1024 * 1024);
+        assert!(!PolicyPreset::Standard.policy().no_external);
+
+        // Keys in the policy file replace the preset's; everything else is kept
+        let policy = SecurityPolicy::with_overrides(PolicyPreset::Paranoid, "no_inline_styles: false\n").unwrap();
+        assert!(!policy.no_inline_styles && policy.no_data_uris);
+        assert_eq!(SecurityPolicy::with_overrides(PolicyPreset::Standard, "").unwrap(), PolicyPreset::Standard.policy());
+        assert!(SecurityPolicy::with_overrides(PolicyPreset::Strict, "no_js: true\n").is_err());
+    }
+
     #[test]
     fn test_config_defaults() {
         let config = Config {
@@ -296,8 +2145,45 @@ mod tests {
             output: default_output(),
             content: default_content(),
             use_blake3: false,
+            ..Config::default()
         };
         assert_eq!(config.output, PathBuf::from("dist"));
         assert_eq!(config.content, PathBuf::from("content"));
+        assert_eq!(config.cache, PathBuf::from(".secureblog-cache.json"));
+    }
+
+    #[test]
+    fn test_load_failures_are_reported_together() {
+        let failures = vec![
+            (PathBuf::from("content/b.md"), anyhow::anyhow!("Post exceeds maximum size")),
+            (PathBuf::from("content/a.md"), anyhow::anyhow!("missing field `title`").context("Invalid frontmatter")),
+        ];
+        assert_eq!(
+            load_failures(failures, 3).to_string(),
+            "2 of 5 posts failed to load:\n  content/a.md: Invalid frontmatter: missing field `title`\n  content/b.md: Post exceeds maximum size"
+        );
+    }
+
+    #[test]
+    fn test_release_bodies_keeps_what_later_passes_read() {
+        let mut post = Post { html: "<p>x</p>".to_string(), content: "x".to_string(), hash: "ab12".to_string(), ..Post::default() };
+        post.meta.slug = "hello".to_string();
+        post.release_bodies();
+        assert!(post.html.is_empty() && post.content.is_empty());
+        assert_eq!((post.path().as_str(), post.hash.as_str()), ("hello.html", "ab12"));
+    }
+
+    #[test]
+    fn test_every_bad_post_is_reported() {
+        let dir = std::env::temp_dir().join(format!("secureblog-load-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let policy = SecurityPolicy { max_file_size: 64, ..SecurityPolicy::default() };
+        fs::write(dir.join("big.md"), "x".repeat(65)).unwrap();
+        fs::write(dir.join("date.md"), "---\ntitle: Bad\ndate: May 9th\n---\nBody\n").unwrap();
+        let error = load_posts(&dir, chrono_tz::Tz::UTC, &policy).unwrap_err().to_string();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(error.starts_with("2 of 2 posts failed to load:"), "{error}");
+        assert!(error.contains("big.md: Post exceeds maximum size"), "{error}");
+        assert!(error.contains("date.md: "), "{error}");
     }
 }
\ No newline at end of file
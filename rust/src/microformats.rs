@@ -0,0 +1,145 @@
+//! Microformats2 (h-entry, h-card, p-category) markup helpers
+//!
+//! `apply` is the one place post pages get this markup, so the post template
+//! carries no mf2 classes of its own and pages never repeat a property.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, TimeZone};
+use std::fmt::Display;
+use std::fs;
+
+use crate::security::escape_html as escape;
+use crate::{i18n, inject, Config, Post};
+
+/// Author `h-card` as a `p-author` property
+pub fn h_card(name: &str, url: &str) -> String {
+    format!(
+        "<span class=\"p-author h-card\"><a class=\"p-name u-url\" href=\"{}\">{}</a></span>",
+        escape(url),
+        escape(name)
+    )
+}
+
+/// One `p-category` element per tag
+pub fn p_categories(tags: &[String]) -> String {
+    tags.iter()
+        .map(|tag| format!("<span class=\"p-category\">{}</span>", escape(tag)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Publication date as `dt-published` with a machine-readable `datetime`, shown in `language`
+pub fn dt_published<Tz: TimeZone>(date: &DateTime<Tz>, language: &str) -> String
+where
+    Tz::Offset: Display,
+{
+    i18n::time_element(date, language, Some("dt-published"))
+}
+
+/// Permalink as `u-url`
+pub fn u_url(url: &str, text: &str) -> String {
+    format!("<a class=\"u-url\" href=\"{}\">{}</a>", escape(url), escape(text))
+}
+
+/// Entry metadata footer: author card, permalink, date and categories
+///
+/// URLs are site-relative so the output passes the external resource check.
+pub fn entry_meta(config: &Config, post: &Post) -> String {
+    let mut html = String::from("<footer class=\"entry-meta\">");
+    html.push_str(&h_card(&config.author, "/"));
+    html.push(' ');
+    html.push_str(&u_url(&post.url_path(), "Permalink"));
+    html.push(' ');
+    html.push_str(&dt_published(&post.meta.date.with_timezone(&config.timezone.display_zone()), &config.i18n.language));
+    if !post.meta.tags.is_empty() {
+        html.push(' ');
+        html.push_str(&p_categories(&post.meta.tags));
+    }
+    html.push_str("</footer>\n");
+    html
+}
+
+/// Add `class` to the first `<tag ...>` element, merging with an existing class attribute
+pub fn add_class(html: &str, tag: &str, class: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let open = format!("<{tag}");
+    let start = lower
+        .match_indices(&open)
+        .map(|(i, _)| i)
+        .find(|&i| matches!(lower.as_bytes().get(i + open.len()), Some(b' ' | b'>' | b'\t' | b'\n')))?;
+    let end = start + lower[start..].find('>')?;
+    let element = &lower[start..end];
+
+    let mut result = String::with_capacity(html.len() + class.len() + 9);
+    if let Some(attr) = element.find("class=\"") {
+        let insert_at = start + attr + "class=\"".len();
+        result.push_str(&html[..insert_at]);
+        result.push_str(class);
+        result.push(' ');
+        result.push_str(&html[insert_at..]);
+    } else {
+        let insert_at = start + open.len();
+        result.push_str(&html[..insert_at]);
+        result.push_str(" class=\"");
+        result.push_str(class);
+        result.push('"');
+        result.push_str(&html[insert_at..]);
+    }
+    Some(result)
+}
+
+/// Mark each post page as an `h-entry` with a `p-name` title and append its entry metadata
+pub fn apply(config: &Config, posts: &[Post]) -> Result<()> {
+    for post in posts {
+        let page = config.output.join(post.path());
+        let html = fs::read_to_string(&page)
+            .with_context(|| format!("Failed to read page: {}", page.display()))?;
+
+        let html = add_class(&html, "article", "h-entry")
+            .or_else(|| add_class(&html, "main", "h-entry"))
+            .unwrap_or(html);
+        let html = add_class(&html, "h1", "p-name").unwrap_or(html);
+        let html = inject::insert_before(&html, &["</article>", "</main>", "</body>"], &entry_meta(config, post))
+            .with_context(|| format!("No insertion point for microformats in {}", page.display()))?;
+
+        fs::write(&page, html).with_context(|| format!("Failed to write page: {}", page.display()))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_class_merges_existing_attribute() {
+        assert_eq!(
+            add_class("<body><article id=\"x\">", "article", "h-entry").unwrap(),
+            "<body><article class=\"h-entry\" id=\"x\">"
+        );
+        assert_eq!(
+            add_class("<Article class=\"post\">", "article", "h-entry").unwrap(),
+            "<Article class=\"h-entry post\">"
+        );
+        assert!(add_class("<articles>", "article", "h-entry").is_none());
+    }
+
+    #[test]
+    fn test_entry_meta_contains_properties() {
+        let config = Config::default();
+        let post = Post {
+            meta: crate::PostMeta {
+                slug: "hello".to_string(),
+                tags: vec!["<xss>".to_string()],
+                ..crate::PostMeta::default()
+            },
+            ..Post::default()
+        };
+        let html = entry_meta(&config, &post);
+        assert!(html.contains("p-author h-card"));
+        assert!(html.contains("class=\"u-url\" href=\"/hello.html\""));
+        assert!(html.contains("<span class=\"p-category\">&lt;xss&gt;</span>"));
+        assert!(html.contains("<time class=\"dt-published\" datetime=\"1970-01-01T00:00:00+00:00\">January 1, 1970</time>"));
+    }
+}
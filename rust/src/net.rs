@@ -0,0 +1,25 @@
+//! Shared HTTP client for explicit online steps (`network` feature)
+
+use std::time::Duration;
+
+/// Maximum time for a single request, including redirects
+const TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Build an HTTP agent with conservative defaults
+///
+/// Status codes are not turned into errors so callers can report them.
+pub fn agent() -> ureq::Agent {
+    config().build().into()
+}
+
+/// Agent that returns redirects instead of following them
+pub fn agent_without_redirects() -> ureq::Agent {
+    config().max_redirects(0).build().into()
+}
+
+fn config() -> ureq::config::ConfigBuilder<ureq::typestate::AgentScope> {
+    ureq::Agent::config_builder()
+        .timeout_global(Some(TIMEOUT))
+        .user_agent(concat!("secureblog-rs/", env!("CARGO_PKG_VERSION")))
+        .http_status_as_error(false)
+}
@@ -0,0 +1,73 @@
+//! Platform-independent paths and line endings in generated artifacts
+//!
+//! A build on Windows must produce the same bytes as one on Linux: paths in
+//! URLs, manifests and reports always use `/`, sources checked out with CRLF
+//! line endings are read as LF, and generated text files are written with LF
+//! only, so checksums and signatures match whichever machine built the site.
+
+use anyhow::{Context, Result};
+use std::borrow::Cow;
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// Extensions of generated text files whose line endings are normalized
+pub const TEXT_EXTENSIONS: [&str; 10] = ["html", "htm", "xml", "json", "txt", "css", "svg", "opml", "map", "webmanifest"];
+
+/// `path` with `/` between its components, whatever the platform separator
+pub fn slashed(path: &Path) -> String {
+    path.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/")
+}
+
+/// `path` relative to `base`, with `/` separators
+pub fn relative(path: &Path, base: &Path) -> Result<String> {
+    Ok(slashed(path.strip_prefix(base)?))
+}
+
+/// Text with CRLF and lone CR line endings turned into LF
+pub fn newlines(text: &str) -> Cow<'_, str> {
+    if text.contains('\r') {
+        Cow::Owned(text.replace("\r\n", "\n").replace('\r', "\n"))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// Rewrite generated text files under `dir` that contain CR; returns how many changed
+pub fn text_output(dir: &Path) -> Result<usize> {
+    let mut changed = 0;
+    for entry in WalkDir::new(dir).into_iter().filter_map(Result::ok).filter(|e| e.file_type().is_file()) {
+        let path = entry.path();
+        let is_text = path.extension().and_then(|s| s.to_str()).is_some_and(|ext| TEXT_EXTENSIONS.contains(&ext));
+        if !is_text {
+            continue;
+        }
+        let Ok(text) = fs::read_to_string(path) else {
+            continue;
+        };
+        if let Cow::Owned(normalized) = newlines(&text) {
+            fs::write(path, normalized).with_context(|| format!("Failed to write {}", path.display()))?;
+            changed += 1;
+        }
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_slashed_paths() {
+        let path: PathBuf = ["2024", "05", "post", "index.html"].iter().collect();
+        assert_eq!(slashed(&path), "2024/05/post/index.html");
+        assert_eq!(relative(&Path::new("dist").join(&path), Path::new("dist")).unwrap(), "2024/05/post/index.html");
+    }
+
+    #[test]
+    fn test_newlines() {
+        assert_eq!(newlines("a\r\nb\rc\n"), "a\nb\nc\n");
+        assert!(matches!(newlines("a\nb\n"), Cow::Borrowed(_)));
+    }
+}
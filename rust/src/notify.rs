@@ -0,0 +1,279 @@
+//! Search engine notifications after a deploy: IndexNow and sitemap pings
+//!
+//! `deployed` compares the output's `integrity.json` with the manifest
+//! recorded at the previous deploy and lists the pages that were added,
+//! changed or removed. Only URLs in the sitemap (for a removed page, the
+//! sitemap of the previous deploy) are listed, so `noindex` and protected
+//! pages are never announced. Only with `--notify` (and a `network` build) are those
+//! URLs submitted to the IndexNow endpoints and the sitemap pinged; builds
+//! never contact search engines. IndexNow verifies ownership by fetching
+//! `/<key>.txt`, which the build writes when a key is configured.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Notification settings
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NotifyConfig {
+    /// IndexNow key, also published as `/<key>.txt`
+    #[serde(default)]
+    pub indexnow_key: Option<String>,
+    /// IndexNow endpoints; one shares submissions with every participating engine
+    #[serde(default = "default_endpoints")]
+    pub indexnow_endpoints: Vec<String>,
+    /// Sitemap ping URLs, the sitemap URL is appended URL-encoded (`https://.../ping?sitemap=`)
+    #[serde(default)]
+    pub sitemap_ping: Vec<String>,
+    /// Manifest of the last deploy, committed or kept by CI
+    #[serde(default = "default_state")]
+    pub state: PathBuf,
+}
+
+fn default_endpoints() -> Vec<String> {
+    vec!["https://api.indexnow.org/indexnow".to_string()]
+}
+
+fn default_state() -> PathBuf {
+    PathBuf::from("deployed.json")
+}
+
+impl NotifyConfig {
+    /// Key and endpoint checks; IndexNow keys are 8-128 of `[A-Za-z0-9-]`
+    pub fn validate(&self) -> Result<()> {
+        if let Some(key) = &self.indexnow_key {
+            if !(8..=128).contains(&key.len()) || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+                anyhow::bail!("notify.indexnow_key must be 8-128 letters, digits or dashes");
+            }
+        }
+        for url in self.indexnow_endpoints.iter().chain(&self.sitemap_ping) {
+            if !url.starts_with("https://") {
+                anyhow::bail!("notify endpoint {url} is not an https:// URL");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Output path → SHA-256 of each file at the last deploy
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deployed {
+    pub files: BTreeMap<String, String>,
+    /// URLs in `sitemap.xml` at that deploy, the only ones ever submitted
+    #[serde(default)]
+    pub listed: BTreeSet<String>,
+}
+
+impl Deployed {
+    /// Load the state file (empty if missing, so the first deploy lists every page)
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Write the state file
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Paths and hashes in the output's `integrity.json`, with the URLs of its `sitemap.xml`
+    ///
+    /// Without a sitemap no URL is listed.
+    pub fn from_output(output_dir: &Path) -> Result<Self> {
+        let manifest = output_dir.join("integrity.json");
+        let sitemap = output_dir.join("sitemap.xml");
+        let listed = match fs::read_to_string(&sitemap) {
+            Ok(xml) => crate::robots::sitemap_urls(&xml).into_iter().collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeSet::new(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", sitemap.display())),
+        };
+        Ok(Self { listed, ..Self::from_manifest(&manifest)? })
+    }
+
+    /// Paths and hashes listed in an `integrity.json`
+    pub fn from_manifest(manifest: &Path) -> Result<Self> {
+        let content = fs::read_to_string(manifest).with_context(|| format!("Failed to read {}", manifest.display()))?;
+        let manifest: serde_json::Value =
+            serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", manifest.display()))?;
+        let files = manifest["files"]
+            .as_array()
+            .context("integrity.json has no files list")?
+            .iter()
+            .filter_map(|file| Some((file["path"].as_str()?.to_string(), file["sha256"].as_str()?.to_string())))
+            .collect();
+        Ok(Self { files, listed: BTreeSet::new() })
+    }
+
+    /// HTML pages added, changed or removed since `before`, sorted
+    pub fn changed_pages(&self, before: &Self) -> Vec<String> {
+        let mut pages: Vec<String> = self
+            .files
+            .iter()
+            .filter(|(path, hash)| before.files.get(*path) != Some(*hash))
+            .map(|(path, _)| path.clone())
+            .chain(before.files.keys().filter(|path| !self.files.contains_key(*path)).cloned())
+            .filter(|path| path.ends_with(".html"))
+            .collect();
+        pages.sort();
+        pages
+    }
+
+    /// Public URLs of the changed pages that are in the sitemap, sorted
+    ///
+    /// A page still deployed must be in the current sitemap, a removed one in
+    /// the previous deploy's, so unlisted pages are never announced.
+    pub fn changed_urls(&self, before: &Self, site: &str) -> Vec<String> {
+        self.changed_pages(before)
+            .into_iter()
+            .filter_map(|path| {
+                let url = page_url(site, &path);
+                let listed = if self.files.contains_key(&path) { &self.listed } else { &before.listed };
+                listed.contains(&url).then_some(url)
+            })
+            .collect()
+    }
+}
+
+/// Public URL of an output page; `index.html` is served as its directory
+pub fn page_url(site: &str, path: &str) -> String {
+    let path = if path == "index.html" {
+        ""
+    } else {
+        path.strip_suffix("/index.html").map_or(path, |dir| &path[..dir.len() + 1])
+    };
+    format!("{}/{path}", site.trim_end_matches('/'))
+}
+
+/// Publish `/<key>.txt` for IndexNow ownership verification
+pub fn write_key_file(output_dir: &Path, config: &NotifyConfig) -> Result<()> {
+    config.validate()?;
+    if let Some(key) = &config.indexnow_key {
+        let path = output_dir.join(format!("{key}.txt"));
+        fs::write(&path, key).with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "network")]
+mod online {
+    use super::NotifyConfig;
+    use anyhow::{Context, Result};
+    use tracing::info;
+
+    /// URLs per IndexNow request, the protocol's limit
+    const BATCH: usize = 10_000;
+
+    /// Submit `urls` to every IndexNow endpoint and ping the sitemap; fails on the first rejection
+    pub fn notify(config: &NotifyConfig, site: &str, urls: &[String]) -> Result<()> {
+        let agent = crate::net::agent();
+        let site = site.trim_end_matches('/');
+        if let Some(key) = config.indexnow_key.as_ref().filter(|_| !urls.is_empty()) {
+            let host = url::Url::parse(site)?.host_str().context("Site URL has no host")?.to_string();
+            for endpoint in &config.indexnow_endpoints {
+                for batch in urls.chunks(BATCH) {
+                    let body = serde_json::json!({
+                        "host": host,
+                        "key": key,
+                        "keyLocation": format!("{site}/{key}.txt"),
+                        "urlList": batch,
+                    });
+                    let response = agent
+                        .post(endpoint)
+                        .header("content-type", "application/json; charset=utf-8")
+                        .send(body.to_string())
+                        .with_context(|| format!("Failed to submit to {endpoint}"))?;
+                    if !response.status().is_success() {
+                        anyhow::bail!("{endpoint} answered HTTP {}", response.status());
+                    }
+                }
+                info!("📣 Submitted {} URLs to {}", urls.len(), endpoint);
+            }
+        }
+
+        let sitemap: String = url::form_urlencoded::byte_serialize(format!("{site}/sitemap.xml").as_bytes()).collect();
+        for ping in &config.sitemap_ping {
+            let response = agent.get(&format!("{ping}{sitemap}")).call().with_context(|| format!("Failed to ping {ping}"))?;
+            if !response.status().is_success() {
+                anyhow::bail!("{ping} answered HTTP {}", response.status());
+            }
+            info!("📣 Pinged {}", ping);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "network")]
+pub use online::notify;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_pages() {
+        let before = Deployed {
+            listed: BTreeSet::new(),
+            files: BTreeMap::from([
+                ("index.html".to_string(), "a".to_string()),
+                ("old.html".to_string(), "b".to_string()),
+                ("same.html".to_string(), "c".to_string()),
+                ("style.css".to_string(), "d".to_string()),
+            ]),
+        };
+        let after = Deployed {
+            listed: BTreeSet::new(),
+            files: BTreeMap::from([
+                ("index.html".to_string(), "a2".to_string()),
+                ("2024/05/new/index.html".to_string(), "e".to_string()),
+                ("same.html".to_string(), "c".to_string()),
+                ("style.css".to_string(), "d2".to_string()),
+            ]),
+        };
+        assert_eq!(after.changed_pages(&before), ["2024/05/new/index.html", "index.html", "old.html"]);
+        assert_eq!(after.changed_pages(&after), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_changed_urls_are_limited_to_the_sitemap() {
+        let site = "https://example.com";
+        let url = |path: &str| page_url(site, path);
+        let before = Deployed {
+            files: BTreeMap::from([
+                ("gone/index.html".to_string(), "a".to_string()),
+                ("secret-gone/index.html".to_string(), "b".to_string()),
+            ]),
+            listed: BTreeSet::from([url("gone/index.html")]),
+        };
+        let after = Deployed {
+            files: BTreeMap::from([
+                ("index.html".to_string(), "c".to_string()),
+                ("internal/index.html".to_string(), "d".to_string()),
+            ]),
+            listed: BTreeSet::from([url("index.html")]),
+        };
+        assert_eq!(after.changed_urls(&before, site), ["https://example.com/gone/", "https://example.com/"]);
+    }
+
+    #[test]
+    fn test_page_urls() {
+        assert_eq!(page_url("https://example.com/", "index.html"), "https://example.com/");
+        assert_eq!(page_url("https://example.com", "2024/05/new/index.html"), "https://example.com/2024/05/new/");
+        assert_eq!(page_url("https://example.com", "post.html"), "https://example.com/post.html");
+    }
+
+    #[test]
+    fn test_key_validation() {
+        let config: NotifyConfig = serde_yaml::from_str("indexnow_key: \"a1b2c3d4e5f6\"\n").unwrap();
+        assert!(config.validate().is_ok());
+        let short = NotifyConfig { indexnow_key: Some("abc".to_string()), ..config.clone() };
+        assert!(short.validate().is_err());
+        let insecure = NotifyConfig { sitemap_ping: vec!["http://ping.example/?s=".to_string()], ..config };
+        assert!(insecure.validate().is_err());
+    }
+}
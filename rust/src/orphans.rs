@@ -0,0 +1,172 @@
+//! Orphan page and unreferenced asset detection
+
+use anyhow::{Context, Result};
+use std::collections::{BTreeSet, VecDeque};
+use std::path::Path;
+use tracing::{info, warn};
+
+use crate::links::{self, SiteLinks};
+
+/// Files that are discovered by clients and crawlers rather than linked from pages
+const EXEMPT_FILES: &[&str] = &[
+    "index.html",
+    "404.html",
+    "integrity.json",
+    "robots.txt",
+    "sitemap.xml",
+    "feed.xml",
+    "atom.xml",
+    "favicon.ico",
+    // Referenced from site.webmanifest
+    "icon-192x192.png",
+    "icon-512x512.png",
+];
+
+/// Result of the link graph analysis
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct OrphanReport {
+    /// HTML pages not reachable from `index.html`
+    pub orphan_pages: BTreeSet<String>,
+    /// Non-HTML files that no page or stylesheet references
+    pub unreferenced_assets: BTreeSet<String>,
+}
+
+/// Compute the link graph of the output directory and find orphans
+pub fn analyze(site: &SiteLinks) -> OrphanReport {
+    let reachable = reachable_from(site, "index.html");
+
+    let referenced: BTreeSet<String> = site
+        .pages
+        .iter()
+        .flat_map(|(page, links)| links.references.iter().map(move |r| (page, r.value.as_str())))
+        .chain(
+            site.stylesheets
+                .iter()
+                .flat_map(|(css, refs)| refs.iter().map(move |r| (css, r.as_str()))),
+        )
+        .filter_map(|(from, reference)| links::resolve(from, reference))
+        .map(|(target, _)| target)
+        .collect();
+
+    OrphanReport {
+        orphan_pages: site
+            .pages
+            .keys()
+            .filter(|page| !reachable.contains(*page) && !is_exempt(page))
+            .cloned()
+            .collect(),
+        unreferenced_assets: site
+            .files
+            .iter()
+            .filter(|file| !site.pages.contains_key(*file))
+            .filter(|file| !referenced.contains(*file) && !is_exempt(file))
+            .cloned()
+            .collect(),
+    }
+}
+
+/// Breadth-first walk of page and stylesheet references starting at `root`
+fn reachable_from(site: &SiteLinks, root: &str) -> BTreeSet<String> {
+    let mut seen = BTreeSet::new();
+    let mut queue = VecDeque::from([root.to_string()]);
+
+    while let Some(path) = queue.pop_front() {
+        if !site.files.contains(&path) || !seen.insert(path.clone()) {
+            continue;
+        }
+
+        let references: Vec<&str> = match (site.pages.get(&path), site.stylesheets.get(&path)) {
+            (Some(page), _) => page.references.iter().map(|r| r.value.as_str()).collect(),
+            (None, Some(refs)) => refs.iter().map(String::as_str).collect(),
+            (None, None) => continue,
+        };
+
+        for reference in references {
+            if let Some((target, _)) = links::resolve(&path, reference) {
+                let index = format!("{target}/index.html");
+                if !site.files.contains(&target) && site.files.contains(&index) {
+                    queue.push_back(index);
+                } else {
+                    queue.push_back(target);
+                }
+            }
+        }
+    }
+
+    seen
+}
+
+fn is_exempt(path: &str) -> bool {
+    EXEMPT_FILES.contains(&path) || path.starts_with(".well-known/")
+}
+
+/// Report orphans in the output directory, optionally deleting unreferenced assets
+pub fn check_orphans(output_dir: &Path, prune: bool) -> Result<OrphanReport> {
+    let site = links::scan_site(output_dir)?;
+    let report = analyze(&site);
+
+    for page in &report.orphan_pages {
+        warn!("Orphan page (not reachable from index): {}", page);
+    }
+
+    for asset in &report.unreferenced_assets {
+        if prune {
+            std::fs::remove_file(output_dir.join(asset))
+                .with_context(|| format!("Failed to remove unreferenced asset: {asset}"))?;
+            info!("Removed unreferenced asset: {}", asset);
+        } else {
+            warn!("Unreferenced asset: {}", asset);
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::links::parse_page;
+
+    fn site(pages: &[(&str, &str)], assets: &[&str]) -> SiteLinks {
+        let mut site = SiteLinks::default();
+        for (path, html) in pages {
+            site.pages.insert((*path).to_string(), parse_page(html));
+            site.files.insert((*path).to_string());
+        }
+        for asset in assets {
+            site.files.insert((*asset).to_string());
+        }
+        site
+    }
+
+    #[test]
+    fn test_orphan_pages_detected() {
+        let site = site(
+            &[
+                ("index.html", r#"<a href="a.html">A</a>"#),
+                ("a.html", r#"<a href="b.html">B</a>"#),
+                ("b.html", ""),
+                ("lonely.html", r#"<a href="index.html">Home</a>"#),
+            ],
+            &[],
+        );
+        let report = analyze(&site);
+        assert_eq!(report.orphan_pages, BTreeSet::from(["lonely.html".to_string()]));
+    }
+
+    #[test]
+    fn test_unreferenced_assets_detected() {
+        let mut site = site(
+            &[("index.html", r#"<link href="style.css"><img src="img/used.png">"#)],
+            &["style.css", "img/used.png", "img/unused.png", "fonts/a.woff2", "robots.txt"],
+        );
+        site.stylesheets
+            .insert("style.css".to_string(), vec!["fonts/a.woff2".to_string()]);
+        let report = analyze(&site);
+        assert!(report.orphan_pages.is_empty());
+        assert_eq!(
+            report.unreferenced_assets,
+            BTreeSet::from(["img/unused.png".to_string()])
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! Outbound links through static exit pages
+//!
+//! Every external link in the output is rewritten to `/out/<hash>.html`, a
+//! generated page with `<meta name="referrer" content="no-referrer">` that
+//! names the destination and links to it. Without a script or a server the
+//! query of an `/out/?h=` URL cannot be read, so each URL gets its own page,
+//! and following the link from it sends no referrer at all. The pages are
+//! listed with every page linking to them in `out/manifest.json`, a record
+//! of every site the blog sends readers to.
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use tracing::info;
+use walkdir::WalkDir;
+
+use crate::normalize;
+use crate::security::escape_html as escape;
+
+/// Name of the manifest inside the exit page directory
+pub const MANIFEST: &str = "manifest.json";
+
+/// Exit page settings
+#[derive(Debug, Clone, Deserialize)]
+pub struct OutboundConfig {
+    /// Output directory of the exit pages
+    #[serde(default = "default_dir")]
+    pub dir: String,
+}
+
+fn default_dir() -> String {
+    "out".to_string()
+}
+
+/// An `<a>` start tag
+static ANCHOR: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)<a\s[^>]*>").unwrap());
+
+/// An absolute or protocol-relative `href`, in either quote style
+static EXTERNAL_HREF: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)(\shref\s*=\s*)(?:"((?:https?:)?//[^"]*)"|'((?:https?:)?//[^']*)')"#).unwrap()
+});
+
+/// One destination in `out/manifest.json`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Destination {
+    pub url: String,
+    /// Output pages linking to it
+    pub pages: BTreeSet<String>,
+}
+
+/// First 16 hex digits of the URL's SHA-256
+pub fn hash(url: &str) -> String {
+    format!("{:x}", Sha256::digest(url.as_bytes()))[..16].to_string()
+}
+
+/// Site path of the exit page for `url`
+pub fn exit_path(dir: &str, url: &str) -> String {
+    format!("/{}/{}.html", dir.trim_matches('/'), hash(url))
+}
+
+/// Page with external links pointing at their exit pages, and the URLs it links to
+pub fn rewrite(html: &str, dir: &str) -> (String, Vec<String>) {
+    let mut urls = Vec::new();
+    let rewritten = ANCHOR
+        .replace_all(html, |tag: &regex::Captures| {
+            EXTERNAL_HREF
+                .replace(&tag[0], |href: &regex::Captures| {
+                    let quoted = href.get(2).or_else(|| href.get(3)).map_or("", |m| m.as_str());
+                    let url = quoted.replace("&amp;", "&");
+                    let path = exit_path(dir, &url);
+                    urls.push(url);
+                    format!("{}\"{path}\"", &href[1])
+                })
+                .into_owned()
+        })
+        .into_owned();
+    (rewritten, urls)
+}
+
+/// Exit page naming the destination, sending no referrer when followed
+pub fn exit_page(url: &str) -> String {
+    let url = escape(url);
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"referrer\" content=\"no-referrer\">\n\
+         <meta name=\"robots\" content=\"noindex, nofollow\">\n\
+         <title>Leaving the site</title>\n</head>\n<body>\n<main>\n\
+         <p>This link leaves the site for:</p>\n\
+         <p><a href=\"{url}\" rel=\"noopener noreferrer nofollow\">{url}</a></p>\n\
+         </main>\n</body>\n</html>\n"
+    )
+}
+
+/// Rewrite external links in every output page, then write the exit pages and manifest
+pub fn apply(output_dir: &Path, outbound: &OutboundConfig) -> Result<usize> {
+    let target = output_dir.join(outbound.dir.trim_matches('/'));
+    if target.exists() {
+        anyhow::bail!("{} already exists in the output; exit pages are written there", target.display());
+    }
+
+    let mut destinations: BTreeMap<String, Destination> = BTreeMap::new();
+    for entry in WalkDir::new(output_dir).sort_by_file_name().into_iter().filter_map(Result::ok) {
+        let path = entry.path();
+        let is_html = path.extension().is_some_and(|ext| ext == "html");
+        if !entry.file_type().is_file() || !is_html {
+            continue;
+        }
+        let html = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let (rewritten, urls) = rewrite(&html, &outbound.dir);
+        if urls.is_empty() {
+            continue;
+        }
+        let page = normalize::relative(path, output_dir)?;
+        for url in urls {
+            let destination = destinations.entry(hash(&url)).or_default();
+            destination.url = url;
+            destination.pages.insert(page.clone());
+        }
+        fs::write(path, rewritten).with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+
+    fs::create_dir_all(&target)?;
+    for (hash, destination) in &destinations {
+        let page = target.join(format!("{hash}.html"));
+        fs::write(&page, exit_page(&destination.url)).with_context(|| format!("Failed to write {}", page.display()))?;
+    }
+    let manifest = target.join(MANIFEST);
+    fs::write(&manifest, serde_json::to_string_pretty(&destinations)? + "\n")
+        .with_context(|| format!("Failed to write {}", manifest.display()))?;
+    info!("🚪 {} external destinations behind exit pages in /{}/", destinations.len(), outbound.dir.trim_matches('/'));
+    Ok(destinations.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_external_links_only() {
+        let html = "<p><a href=\"https://example.org/?a=1&amp;b=2\" rel=\"noopener noreferrer\">x</a> \
+                    <a class='ext' href='//cdn.test/'>y</a> <a href=\"/about.html\">z</a> \
+                    <link rel=\"stylesheet\" href=\"https://cdn.test/s.css\"></p>";
+        let (rewritten, urls) = rewrite(html, "out");
+        assert_eq!(urls, ["https://example.org/?a=1&b=2", "//cdn.test/"]);
+        assert_eq!(
+            rewritten,
+            format!(
+                "<p><a href=\"{}\" rel=\"noopener noreferrer\">x</a> <a class='ext' href=\"{}\">y</a> \
+                 <a href=\"/about.html\">z</a> <link rel=\"stylesheet\" href=\"https://cdn.test/s.css\"></p>",
+                exit_path("out", &urls[0]),
+                exit_path("out", &urls[1])
+            )
+        );
+    }
+
+    #[test]
+    fn test_exit_page_escapes_url() {
+        let page = exit_page("https://example.org/?q=\"><script>");
+        assert!(page.contains("<meta name=\"referrer\" content=\"no-referrer\">"));
+        assert!(page.contains("href=\"https://example.org/?q=&quot;&gt;&lt;script&gt;\""));
+        assert_eq!(exit_path("/out/", "https://example.org/").len(), "/out/.html".len() + 16);
+    }
+}
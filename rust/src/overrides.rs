@@ -0,0 +1,190 @@
+//! Per-page and per-section security policy overrides
+//!
+//! `security-policy.yaml` can change the policy for one output page
+//! (`index.html`) or a section (`labs/`), and a post can change it for itself
+//! with `policy:` in its frontmatter. Section entries apply from the shortest
+//! prefix to the longest, then the page's own entry, then the frontmatter.
+//! Scripts cannot be allowed anywhere. Every override in effect is recorded in
+//! the build report, which `check output` reads to validate the same way.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::{lint, Check, Post, SecurityPolicy};
+
+/// Changes to the site policy; unset fields keep the site's value
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_inline_styles: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_external: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_data_uris: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_page_size: Option<usize>,
+    /// Added to the site's `allowed_hosts`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_hosts: Vec<String>,
+    /// Replaces the site's severity for the listed checks
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub severity: BTreeMap<Check, lint::Severity>,
+}
+
+impl PolicyOverride {
+    /// Apply to a copy of the policy
+    pub fn apply(&self, policy: &mut SecurityPolicy) {
+        if let Some(value) = self.no_inline_styles {
+            policy.no_inline_styles = value;
+        }
+        if let Some(value) = self.no_external {
+            policy.no_external = value;
+        }
+        if let Some(value) = self.no_data_uris {
+            policy.no_data_uris = value;
+        }
+        if let Some(value) = self.max_page_size {
+            policy.max_page_size = value;
+        }
+        policy.allowed_hosts.extend(self.allowed_hosts.iter().cloned());
+        policy.severity.extend(self.severity.iter().map(|(check, severity)| (*check, *severity)));
+    }
+}
+
+/// Whether an `overrides:` key covers an output path
+fn covers(key: &str, path: &str) -> bool {
+    key == path || (key.ends_with('/') && path.starts_with(key))
+}
+
+impl SecurityPolicy {
+    /// The policy for one output file (path relative to the output directory)
+    pub fn for_path(&self, path: &str) -> Cow<'_, Self> {
+        let mut sections: Vec<(&String, &PolicyOverride)> =
+            self.overrides.iter().filter(|(key, _)| covers(key, path)).collect();
+        let page = self.pages.get(path);
+        if sections.is_empty() && page.is_none() {
+            return Cow::Borrowed(self);
+        }
+        sections.sort_by_key(|(key, _)| (key.len(), !key.ends_with('/')));
+        let mut policy = self.clone();
+        for (_, change) in sections {
+            change.apply(&mut policy);
+        }
+        if let Some(change) = page {
+            change.apply(&mut policy);
+        }
+        Cow::Owned(policy)
+    }
+
+    /// The policy for rendering a post, with its frontmatter override
+    pub fn for_post(&self, change: Option<&PolicyOverride>) -> Cow<'_, Self> {
+        match change {
+            Some(change) => {
+                let mut policy = self.clone();
+                change.apply(&mut policy);
+                Cow::Owned(policy)
+            }
+            None => Cow::Borrowed(self),
+        }
+    }
+
+    /// The policy with the frontmatter overrides of `posts`, keyed by output path
+    pub fn with_pages(&self, posts: &[Post]) -> Self {
+        let mut policy = self.clone();
+        policy.pages.extend(posts.iter().filter_map(|post| Some((post.path(), post.meta.policy.clone()?))));
+        policy
+    }
+}
+
+/// An override in effect, for the build report
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    /// Output path or section prefix it applies to
+    pub path: String,
+    /// `security-policy.yaml` or the post's source file
+    pub source: String,
+    /// The changes
+    #[serde(rename = "override")]
+    pub change: PolicyOverride,
+}
+
+/// Source named in records of section and page entries from the policy file
+pub const POLICY_FILE_SOURCE: &str = "security-policy.yaml";
+
+/// Every override in effect, policy file entries first
+pub fn records(policy: &SecurityPolicy, posts: &[Post]) -> Vec<Record> {
+    let from_file = policy.overrides.iter().map(|(path, change)| Record {
+        path: path.clone(),
+        source: POLICY_FILE_SOURCE.to_string(),
+        change: change.clone(),
+    });
+    let from_posts = posts.iter().filter_map(|post| {
+        Some(Record { path: post.path(), source: post.source.display().to_string(), change: post.meta.policy.clone()? })
+    });
+    from_file.chain(from_posts).collect()
+}
+
+/// Frontmatter overrides recorded by the last build, keyed by output path
+pub fn recorded_pages(build_report: &Path) -> Result<BTreeMap<String, PolicyOverride>> {
+    #[derive(Deserialize)]
+    struct Report {
+        #[serde(default)]
+        overrides: Vec<Record>,
+    }
+
+    if !build_report.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let content =
+        fs::read_to_string(build_report).with_context(|| format!("Failed to read {}", build_report.display()))?;
+    let report: Report =
+        serde_json::from_str(&content).with_context(|| format!("Invalid build report {}", build_report.display()))?;
+    Ok(report
+        .overrides
+        .into_iter()
+        .filter(|record| record.source != POLICY_FILE_SOURCE)
+        .map(|record| (record.path, record.change))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sections_then_page_overrides() {
+        let policy: SecurityPolicy = serde_yaml::from_str(
+            "no_inline_styles: true
+overrides:
+  index.html: { no_inline_styles: false }
+  labs/: { no_external: false, max_page_size: 100 }
+  labs/big/: { max_page_size: 1000 }
+",
+        )
+        .unwrap();
+        assert!(!policy.for_path("index.html").no_inline_styles);
+        assert!(policy.for_path("about.html").no_inline_styles);
+        assert!(matches!(policy.for_path("about.html"), Cow::Borrowed(_)));
+
+        let labs = policy.for_path("labs/big/post.html");
+        assert!(!labs.no_external && labs.no_inline_styles);
+        assert_eq!(labs.max_page_size, 1000);
+
+        let mut policy = policy;
+        policy.pages.insert(
+            "labs/big/post.html".to_string(),
+            PolicyOverride { max_page_size: Some(10), ..PolicyOverride::default() },
+        );
+        assert_eq!(policy.for_path("labs/big/post.html").max_page_size, 10);
+    }
+
+    #[test]
+    fn test_scripts_cannot_be_allowed() {
+        assert!(serde_yaml::from_str::<PolicyOverride>("no_javascript: false\n").is_err());
+    }
+}
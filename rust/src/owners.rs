@@ -0,0 +1,186 @@
+//! Per-directory content ownership (`OWNERS` files)
+//!
+//! Each line of an `OWNERS` file is a principal from `allowed_signers` or an
+//! inline SSH public key. The nearest `OWNERS` file above a post applies, and
+//! the post's latest commit must be signed by an owner key. Author emails are
+//! trivially forged, so an unsigned commit authored by an owner's email only
+//! passes with `owners_allow_unsigned`.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::error;
+
+use crate::signing::{self, AllowedSigner, Verdict};
+use crate::{git, Config, Post};
+
+/// File name looked up in each content directory
+pub const OWNERS_FILE: &str = "OWNERS";
+
+/// Parsed `OWNERS` file
+#[derive(Debug, Clone, Default)]
+pub struct Owners {
+    /// Principals resolved through `allowed_signers` (also matched against commit author emails)
+    pub principals: Vec<String>,
+    /// Inline keys declared directly in the file
+    pub keys: Vec<AllowedSigner>,
+}
+
+impl Owners {
+    /// Keys allowed to sign changes: inline keys plus allowlisted keys of listed principals
+    pub fn signers(&self, allowed: &[AllowedSigner]) -> Vec<AllowedSigner> {
+        allowed
+            .iter()
+            .filter(|s| self.principals.contains(&s.principal))
+            .chain(&self.keys)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Parse an `OWNERS` file
+pub fn parse_owners(content: &str) -> Result<Owners> {
+    let mut owners = Owners::default();
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.contains(' ') {
+            // `key-type base64 [comment]`, named by the comment if present
+            let name = line.split_whitespace().nth(2).unwrap_or("inline key");
+            let parsed = signing::parse_allowed_signers(&format!("{name} {line}"))?;
+            owners.keys.extend(parsed);
+        } else {
+            owners.principals.push(line.to_string());
+        }
+    }
+    Ok(owners)
+}
+
+/// Nearest `OWNERS` file between the post's directory and the content root
+pub fn owners_file(content_dir: &Path, source: &Path) -> Option<PathBuf> {
+    source
+        .ancestors()
+        .skip(1)
+        .take_while(|dir| dir.starts_with(content_dir))
+        .map(|dir| dir.join(OWNERS_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Check a post's latest commit against its owners
+///
+/// Unsigned commits are rejected unless `allow_unsigned` is set, in which case
+/// an owner principal's author email is enough.
+pub fn check_commit(
+    owners: &Owners,
+    allowed: &[AllowedSigner],
+    signature: Option<(&[u8], &[u8])>,
+    time: DateTime<Utc>,
+    author_email: &str,
+    allow_unsigned: bool,
+) -> std::result::Result<(), String> {
+    match signature {
+        Some((signature, signed_data)) => {
+            match signing::verify_signature(signature, signed_data, time, &owners.signers(allowed)) {
+                Verdict::Trusted(_) => Ok(()),
+                verdict => Err(format!("latest commit not signed by an owner: {verdict}")),
+            }
+        }
+        None if !allow_unsigned => Err(format!("unsigned latest commit by {author_email}")),
+        None if owners.principals.iter().any(|p| p.eq_ignore_ascii_case(author_email)) => Ok(()),
+        None => Err(format!("unsigned latest commit by non-owner {author_email}")),
+    }
+}
+
+/// Cross-check git authorship and signatures of every post against its `OWNERS` file
+pub fn check_owners(config: &Config, posts: &[Post]) -> Result<()> {
+    let allowed = match &config.signed_commits {
+        Some(policy) => signing::parse_allowed_signers(
+            &fs::read_to_string(&policy.allowed_signers).with_context(|| {
+                format!("Failed to read allowed signers: {}", policy.allowed_signers.display())
+            })?,
+        )?,
+        None => Vec::new(),
+    };
+
+    let Some(first) = posts.first() else {
+        return Ok(());
+    };
+    let (repo, _) = git::open(first.source.parent().unwrap_or_else(|| Path::new(".")))?;
+    let sources: Vec<&Path> = posts.iter().map(|p| p.source.as_path()).collect();
+    let history = git::history_of(&sources)?;
+
+    let mut violations = 0;
+    for post in posts {
+        let Some(path) = owners_file(&config.content, &post.source) else {
+            continue;
+        };
+        let owners = parse_owners(&fs::read_to_string(&path)?)
+            .with_context(|| format!("Invalid owners file: {}", path.display()))?;
+
+        let result = match history.get(&post.source) {
+            Some(file) => {
+                let commit = repo.find_commit(file.last.commit)?;
+                let author = commit.author()?.email.to_string();
+                let signature = commit.signature()?.map(|(sig, data)| (sig.to_vec(), data.to_bstring()));
+                check_commit(
+                    &owners,
+                    &allowed,
+                    signature.as_ref().map(|(sig, data)| (sig.as_slice(), data.as_slice())),
+                    signing::commit_time(&commit)?,
+                    &author,
+                    config.owners_allow_unsigned,
+                )
+            }
+            None => Err("post is not committed".to_string()),
+        };
+
+        if let Err(reason) = result {
+            error!("Ownership violation: {} ({}): {}", post.source.display(), path.display(), reason);
+            violations += 1;
+        }
+    }
+
+    if violations > 0 {
+        anyhow::bail!("{violations} posts violate their OWNERS policy");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIDyyOn4pd4irR3eS6ke1WW5BUHZ1X/qRKasby/KiNIBd";
+
+    #[test]
+    fn test_parse_owners() {
+        let owners = parse_owners(&format!("# security team\nbob@example.com\n{KEY} alice\n")).unwrap();
+        assert_eq!(owners.principals, ["bob@example.com"]);
+        assert_eq!(owners.keys.len(), 1);
+        assert_eq!(owners.keys[0].principal, "alice");
+    }
+
+    #[test]
+    fn test_unsigned_commits_rejected_by_default() {
+        let owners = parse_owners("bob@example.com\n").unwrap();
+        assert!(check_commit(&owners, &[], None, Utc::now(), "bob@example.com", false).is_err());
+    }
+
+    #[test]
+    fn test_unsigned_commits_need_owner_author_when_allowed() {
+        let owners = parse_owners("bob@example.com\n").unwrap();
+        assert!(check_commit(&owners, &[], None, Utc::now(), "Bob@example.com", true).is_ok());
+        assert!(check_commit(&owners, &[], None, Utc::now(), "mallory@example.com", true).is_err());
+    }
+
+    #[test]
+    fn test_signers_resolve_principals() {
+        let allowed = signing::parse_allowed_signers(&format!("bob@example.com {KEY}\ncarol@example.com {KEY}\n")).unwrap();
+        let owners = parse_owners("bob@example.com\n").unwrap();
+        let signers = owners.signers(&allowed);
+        assert_eq!(signers.len(), 1);
+        assert_eq!(signers[0].principal, "bob@example.com");
+    }
+}
@@ -0,0 +1,179 @@
+//! PDF attachments: sanitized, hashed and linked with their size and hash
+//!
+//! Every PDF published from a page bundle or `static/` is parsed and
+//! rewritten without JavaScript, launch and form-submission actions, open
+//! actions, embedded files, forms and document metadata. Links to a bundle's
+//! PDFs get the sanitized file's size and SHA-256 next to them, so readers can
+//! check the download against the page.
+
+use anyhow::{Context, Result};
+use lopdf::{Dictionary, Document, Object};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+
+use crate::bundles::Asset;
+use crate::security::escape_html as escape;
+
+/// Keys removed from every dictionary: automatic actions, scripts, attachments, forms and metadata
+const REMOVED_KEYS: [&[u8]; 9] =
+    [b"OpenAction", b"AA", b"JS", b"JavaScript", b"EmbeddedFiles", b"AcroForm", b"XFA", b"Metadata", b"PieceInfo"];
+
+/// Action types that run code, start programs or send data
+const DANGEROUS_ACTIONS: [&[u8]; 8] =
+    [b"JavaScript", b"Launch", b"SubmitForm", b"ImportData", b"ResetForm", b"GoToE", b"RichMediaExecute", b"Rendition"];
+
+/// Links whose target is a site-relative `.pdf`
+static PDF_LINK: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r##"(?s)<a ([^>]*)href="/([^"#?]+\.pdf)"([^>]*)>(.*?)</a>"##).unwrap());
+
+/// Whether a dictionary is an action of a dangerous type
+fn is_dangerous_action(dict: &Dictionary) -> bool {
+    matches!(dict.get(b"S"), Ok(Object::Name(name)) if DANGEROUS_ACTIONS.contains(&name.as_slice()))
+}
+
+/// Strip dangerous keys from a dictionary and the dictionaries nested in it; returns what was removed
+fn sanitize_dictionary(dict: &mut Dictionary) -> usize {
+    let mut removed = 0;
+    for key in REMOVED_KEYS {
+        if dict.remove(key).is_some() {
+            removed += 1;
+        }
+    }
+    let nested_actions: Vec<Vec<u8>> = dict
+        .iter()
+        .filter(|(_, value)| matches!(value, Object::Dictionary(d) if is_dangerous_action(d)))
+        .map(|(key, _)| key.clone())
+        .collect();
+    for key in nested_actions {
+        dict.remove(&key);
+        removed += 1;
+    }
+    for (_, value) in dict.iter_mut() {
+        removed += sanitize_object(value);
+    }
+    removed
+}
+
+fn sanitize_object(object: &mut Object) -> usize {
+    match object {
+        Object::Dictionary(dict) => sanitize_dictionary(dict),
+        Object::Stream(stream) => sanitize_dictionary(&mut stream.dict),
+        Object::Array(items) => items.iter_mut().map(sanitize_object).sum(),
+        _ => 0,
+    }
+}
+
+/// Rewrite a PDF without active content or metadata
+///
+/// Returns the sanitized bytes and how many dangerous entries were removed.
+pub fn sanitize(raw: &[u8]) -> Result<(Vec<u8>, usize)> {
+    let mut doc = Document::load_mem(raw).context("Not a readable PDF")?;
+    if doc.is_encrypted() {
+        anyhow::bail!("Encrypted PDFs cannot be checked");
+    }
+    let mut removed = 0;
+    for object in doc.objects.values_mut() {
+        let dangerous = match object {
+            Object::Dictionary(dict) => is_dangerous_action(dict),
+            _ => false,
+        };
+        if dangerous {
+            *object = Object::Null;
+            removed += 1;
+        } else {
+            removed += sanitize_object(object);
+        }
+    }
+    if doc.trailer.remove(b"Info").is_some() {
+        removed += 1;
+    }
+    doc.trailer.remove(b"ID");
+    doc.prune_objects();
+
+    let mut bytes = Vec::new();
+    doc.save_to(&mut bytes).context("Failed to write sanitized PDF")?;
+    Ok((bytes, removed))
+}
+
+/// `84 KB`-style size
+fn human_size(bytes: usize) -> String {
+    match bytes {
+        0..=1023 => format!("{bytes} B"),
+        1024..=1_048_575 => format!("{} KB", bytes.div_ceil(1024)),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+/// Add size and SHA-256 after each link to one of `assets`' PDFs
+pub fn annotate_links(html: &str, assets: &[Asset]) -> String {
+    PDF_LINK
+        .replace_all(html, |capture: &regex::Captures| {
+            let Some(asset) = assets.iter().find(|asset| asset.path == capture[2]) else {
+                return capture[0].to_string();
+            };
+            let hash = format!("{:x}", Sha256::digest(&asset.content));
+            format!(
+                "<a {}href=\"/{}\"{} type=\"application/pdf\">{}</a> <span class=\"attachment\">(PDF, {}, SHA-256 <code>{}</code>)</span>",
+                &capture[1],
+                &capture[2],
+                &capture[3],
+                &capture[4],
+                human_size(asset.content.len()),
+                escape(&hash)
+            )
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::dictionary;
+
+    fn pdf_with_actions() -> Vec<u8> {
+        let mut doc = Document::with_version("1.7");
+        let pages_id = doc.new_object_id();
+        let js = doc.add_object(dictionary! { "S" => "JavaScript", "JS" => Object::string_literal("app.alert(1)") });
+        let launch = dictionary! { "S" => "Launch", "F" => Object::string_literal("calc.exe") };
+        let page = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "AA" => dictionary! { "O" => js },
+            "Annots" => vec![Object::Dictionary(dictionary! { "Subtype" => "Link", "A" => launch })],
+        });
+        doc.objects.insert(pages_id, Object::Dictionary(dictionary! { "Type" => "Pages", "Kids" => vec![page.into()], "Count" => 1 }));
+        let catalog = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id, "OpenAction" => js });
+        let info = doc.add_object(dictionary! { "Author" => Object::string_literal("Jane Doe") });
+        doc.trailer.set("Root", catalog);
+        doc.trailer.set("Info", info);
+        let mut bytes = Vec::new();
+        doc.save_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_sanitize_strips_actions_and_metadata() {
+        let (clean, removed) = sanitize(&pdf_with_actions()).unwrap();
+        assert!(removed >= 4);
+        let text = String::from_utf8_lossy(&clean);
+        for needle in ["JavaScript", "Launch", "OpenAction", "calc.exe", "Jane Doe"] {
+            assert!(!text.contains(needle), "{needle} survived");
+        }
+        assert!(clean.starts_with(b"%PDF-"));
+        assert!(sanitize(b"<html>not a pdf</html>").is_err());
+    }
+
+    #[test]
+    fn test_links_show_size_and_hash() {
+        let assets = [Asset { path: "bug/advisory.0123456789abcdef.pdf".to_string(), content: vec![0; 2048], ..Asset::default() }];
+        let html = annotate_links(r#"<p><a href="/bug/advisory.0123456789abcdef.pdf">Advisory</a> <a href="/other.pdf">x</a></p>"#, &assets);
+        let hash = format!("{:x}", Sha256::digest(vec![0; 2048]));
+        assert_eq!(
+            html,
+            format!(
+                r#"<p><a href="/bug/advisory.0123456789abcdef.pdf" type="application/pdf">Advisory</a> <span class="attachment">(PDF, 2 KB, SHA-256 <code>{hash}</code>)</span> <a href="/other.pdf">x</a></p>"#
+            )
+        );
+    }
+}
@@ -0,0 +1,207 @@
+//! Permalink patterns such as `/:year/:month/:slug/`
+//!
+//! Every post gets its route once, after dates are final; pages, feeds,
+//! sitemaps and structured data all read it through `Post::path` and
+//! `Post::permalink`. Posts that move away from the flat `/<slug>.html`
+//! keep that URL working through `_redirects`.
+
+use anyhow::{Context, Result};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use tracing::info;
+
+use crate::slug::slugify_with;
+use crate::status::PostStatus;
+use crate::{Config, Post, PostMeta};
+
+/// URL pattern and trailing-slash style
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct PermalinkConfig {
+    /// Tokens: `:year`, `:month`, `:day`, `:slug` (required)
+    #[serde(default = "default_pattern")]
+    pub pattern: String,
+    /// For patterns ending in `/`: serve `/a/b/` from `a/b/index.html`, or `/a/b` from `a/b.html`
+    #[serde(default = "default_true")]
+    pub trailing_slash: bool,
+}
+
+impl Default for PermalinkConfig {
+    fn default() -> Self {
+        Self {
+            pattern: default_pattern(),
+            trailing_slash: true,
+        }
+    }
+}
+
+fn default_pattern() -> String {
+    "/:slug.html".to_string()
+}
+
+const fn default_true() -> bool {
+    true
+}
+
+/// Redirect rules for hosts that read `_redirects`
+pub const REDIRECTS: &str = "_redirects";
+
+/// Tokens a pattern may use
+const TOKENS: [&str; 4] = [":year", ":month", ":day", ":slug"];
+
+/// Reject patterns that could collide, escape the output or use unknown tokens
+pub fn validate(pattern: &str) -> Result<()> {
+    if !pattern.starts_with('/') {
+        anyhow::bail!("Permalink pattern must start with `/`: {pattern}");
+    }
+    if !pattern.contains(":slug") {
+        anyhow::bail!("Permalink pattern must contain :slug: {pattern}");
+    }
+    for segment in pattern.split('/') {
+        if segment == ".." || segment == "." {
+            anyhow::bail!("Permalink pattern must not contain `.` or `..` segments: {pattern}");
+        }
+    }
+    let mut rest = pattern;
+    while let Some(start) = rest.find(':') {
+        let token = &rest[start..];
+        let known = TOKENS
+            .iter()
+            .find(|t| token.starts_with(**t))
+            .with_context(|| format!("Unknown permalink token in {pattern}"))?;
+        rest = &token[known.len()..];
+    }
+    Ok(())
+}
+
+/// URL path of a post without the leading `/` (`2024/05/slug/`, `slug.html`, ...)
+///
+/// Date segments are the post's date in `zone`.
+pub fn route(config: &PermalinkConfig, meta: &PostMeta, zone: Tz) -> String {
+    let date = meta.date.with_timezone(&zone);
+    let route = config
+        .pattern
+        .replace(":year", &date.format("%Y").to_string())
+        .replace(":month", &date.format("%m").to_string())
+        .replace(":day", &date.format("%d").to_string())
+        .replace(":slug", &meta.slug);
+    let route = route.trim_start_matches('/');
+    if config.trailing_slash || !route.ends_with('/') {
+        route.to_string()
+    } else {
+        route.trim_end_matches('/').to_string()
+    }
+}
+
+/// Assign every post its route, failing if two non-draft posts would share a URL
+///
+/// Posts without a `slug:` get one from their title in the configured transliteration.
+pub fn assign(config: &Config, posts: &mut [Post]) -> Result<()> {
+    validate(&config.permalinks.pattern)?;
+    let mut seen: BTreeMap<String, &Path> = BTreeMap::new();
+    for post in posts.iter_mut() {
+        if post.meta.slug.is_empty() {
+            post.meta.slug = slugify_with(&post.meta.title, config.slugs);
+        }
+        post.route = route(&config.permalinks, &post.meta, config.timezone.display_zone());
+    }
+    for post in posts.iter().filter(|p| p.meta.status != PostStatus::Draft) {
+        if let Some(other) = seen.insert(post.path(), &post.source) {
+            anyhow::bail!("{} and {} both publish to /{}", other.display(), post.source.display(), post.route);
+        }
+    }
+    Ok(())
+}
+
+/// `_redirects` lines from each post's flat `/<slug>.html` to its permalink
+pub fn redirects(posts: &[Post]) -> String {
+    let mut rules = String::new();
+    for post in posts {
+        let legacy = format!("/{}.html", post.meta.slug);
+        if legacy != post.url_path() {
+            let _ = writeln!(rules, "{legacy} {} 301", post.url_path());
+        }
+    }
+    rules
+}
+
+/// Write `_redirects` when the pattern moved posts away from `/<slug>.html`
+pub fn write_redirects(output_dir: &Path, posts: &[Post]) -> Result<()> {
+    let rules = redirects(posts);
+    if rules.is_empty() {
+        return Ok(());
+    }
+    let path = output_dir.join(REDIRECTS);
+    fs::write(&path, &rules).with_context(|| format!("Failed to write {}", path.display()))?;
+    info!("↪️  {} redirects from /<slug>.html in {}", rules.lines().count(), REDIRECTS);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn post(slug: &str) -> Post {
+        Post {
+            meta: PostMeta {
+                slug: slug.to_string(),
+                date: Utc.with_ymd_and_hms(2024, 5, 9, 12, 0, 0).unwrap(),
+                ..PostMeta::default()
+            },
+            ..Post::default()
+        }
+    }
+
+    fn config(pattern: &str, trailing_slash: bool) -> Config {
+        Config {
+            permalinks: PermalinkConfig { pattern: pattern.to_string(), trailing_slash },
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn test_routes_and_output_paths() {
+        let mut posts = [post("xz")];
+        assign(&config("/:year/:month/:slug/", true), &mut posts).unwrap();
+        assert_eq!(posts[0].path(), "2024/05/xz/index.html");
+        assert_eq!(posts[0].permalink("https://example.com/"), "https://example.com/2024/05/xz/");
+
+        assign(&config("/:year/:month/:day/:slug/", false), &mut posts).unwrap();
+        assert_eq!(posts[0].path(), "2024/05/09/xz.html");
+        assert_eq!(posts[0].url_path(), "/2024/05/09/xz");
+
+        assign(&Config::default(), &mut posts).unwrap();
+        assert_eq!(posts[0].path(), "xz.html");
+        assert_eq!(posts[0].url_path(), "/xz.html");
+        assert!(redirects(&posts).is_empty());
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        for pattern in ["/:year/", ":slug.html", "/../:slug/", "/:category/:slug/"] {
+            assert!(validate(pattern).is_err(), "{pattern} accepted");
+        }
+    }
+
+    #[test]
+    fn test_collisions_and_redirects() {
+        let mut posts = [post("a"), post("a")];
+        assert!(assign(&config("/:year/:slug/", true), &mut posts).is_err());
+
+        let mut posts = [post("a")];
+        assign(&config("/:year/:slug/", true), &mut posts).unwrap();
+        assert_eq!(redirects(&posts), "/a.html /2024/a/ 301\n");
+    }
+
+    #[test]
+    fn test_missing_slugs_follow_transliteration() {
+        let mut posts = [Post { meta: PostMeta { title: "Привет, мир".to_string(), ..post("").meta }, ..Post::default() }];
+        let config = Config { slugs: crate::slug::Transliteration::Ascii, ..Config::default() };
+        assign(&config, &mut posts).unwrap();
+        assert_eq!(posts[0].path(), "privet-mir.html");
+    }
+}
@@ -0,0 +1,95 @@
+//! Dominant-colour placeholders for images
+//!
+//! Each processed raster image gets its most common colour as a `ph-rrggbb`
+//! class on the element showing it. The classes live in `placeholders.css`
+//! (the CSP and `no_inline_styles` rule out `style=""`), so images load over
+//! their own colour instead of a white flash, without any JavaScript.
+
+use anyhow::{Context, Result};
+use image::DynamicImage;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::fs;
+use tracing::info;
+
+use crate::{inject, Config, Post};
+
+/// Stylesheet with one rule per colour in use
+pub const STYLESHEET: &str = "placeholders.css";
+
+/// Most common colour, from 4-bit-per-channel buckets of a small thumbnail
+pub fn dominant_color(image: &DynamicImage) -> [u8; 3] {
+    let mut buckets: BTreeMap<[u8; 3], (u32, [u32; 3])> = BTreeMap::new();
+    for pixel in image.thumbnail(32, 32).to_rgb8().pixels() {
+        let [r, g, b] = pixel.0;
+        let (count, sums) = buckets.entry([r >> 4, g >> 4, b >> 4]).or_default();
+        *count += 1;
+        for (sum, channel) in sums.iter_mut().zip([r, g, b]) {
+            *sum += u32::from(channel);
+        }
+    }
+    // First bucket with the highest count, so ties resolve the same way every build
+    let Some((count, sums)) = buckets.into_values().reduce(|best, next| if next.0 > best.0 { next } else { best }) else {
+        return [255, 255, 255];
+    };
+    sums.map(|sum| u8::try_from(sum / count).unwrap_or(u8::MAX))
+}
+
+/// Dominant colour of an encoded image, if it can be decoded
+pub fn of_bytes(content: &[u8]) -> Option<[u8; 3]> {
+    image::load_from_memory(content).ok().map(|image| dominant_color(&image))
+}
+
+/// Class carrying a colour
+pub fn class(color: [u8; 3]) -> String {
+    format!("ph-{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+/// One `background-color` rule per colour
+pub fn stylesheet(colors: &BTreeSet<[u8; 3]>) -> String {
+    let mut css = String::new();
+    for color in colors {
+        let _ = writeln!(css, ".{} {{ background-color: #{} }}", class(*color), &class(*color)[3..]);
+    }
+    css
+}
+
+/// Write `placeholders.css` and link it from every post showing a coloured image
+pub fn apply(config: &Config, posts: &[Post]) -> Result<()> {
+    let colors: BTreeSet<[u8; 3]> = posts.iter().flat_map(|p| &p.assets).filter_map(|a| a.color).collect();
+    if colors.is_empty() {
+        return Ok(());
+    }
+    let path = config.output.join(STYLESHEET);
+    fs::write(&path, stylesheet(&colors)).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    let link = format!("<link rel=\"stylesheet\" href=\"/{STYLESHEET}\">\n");
+    for post in posts.iter().filter(|p| p.assets.iter().any(|a| a.color.is_some())) {
+        inject::inject_into_file(&config.output.join(post.path()), &["</head>"], &link)?;
+    }
+    info!("🎨 {} placeholder colours", colors.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn test_dominant_color_is_the_largest_area() {
+        let image = RgbImage::from_fn(100, 100, |x, _| if x < 70 { Rgb([200, 30, 30]) } else { Rgb([0, 0, 255]) });
+        let [r, g, b] = dominant_color(&DynamicImage::ImageRgb8(image));
+        assert!(r.abs_diff(200) <= 2 && g.abs_diff(30) <= 2 && b.abs_diff(30) <= 2, "{r} {g} {b}");
+    }
+
+    #[test]
+    fn test_stylesheet_rules() {
+        assert_eq!(class([10, 171, 255]), "ph-0aabff");
+        assert_eq!(
+            stylesheet(&BTreeSet::from([[0, 0, 0], [10, 171, 255]])),
+            ".ph-000000 { background-color: #000000 }\n.ph-0aabff { background-color: #0aabff }\n"
+        );
+        assert_eq!(of_bytes(b"not an image"), None);
+    }
+}
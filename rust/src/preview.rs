@@ -0,0 +1,67 @@
+//! Writing preview with `<meta http-equiv="refresh">` instead of live reload
+//!
+//! Live reload needs a script, and scripts are not allowed even locally. The
+//! `preview` command builds the site into its own directory and then adds a
+//! refresh tag to every page, so a browser pointed at any static file server
+//! re-fetches the page every few seconds while a post is being written.
+//! The tag is added after the build and its checks, never to the production
+//! output, and the output checks reject any page that carries one.
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+use crate::inject;
+
+/// Default seconds between refreshes
+pub const DEFAULT_INTERVAL: u32 = 2;
+
+/// A refresh tag in any spelling, with or without quotes
+static META_REFRESH: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)<meta\s[^>]*http-equiv\s*=\s*["']?refresh\b"#).unwrap());
+
+/// Byte offset of the first refresh tag in a page, if it has one
+pub fn refresh_at(html: &str) -> Option<usize> {
+    META_REFRESH.find(html).map(|m| m.start())
+}
+
+/// The tag added to preview pages
+pub fn refresh_tag(seconds: u32) -> String {
+    format!("<meta http-equiv=\"refresh\" content=\"{seconds}\">\n")
+}
+
+/// Add the refresh tag to every HTML page under `dir` with a `<head>`; returns how many were changed
+pub fn apply(dir: &Path, seconds: u32) -> Result<usize> {
+    let tag = refresh_tag(seconds);
+    let mut pages = 0;
+    for entry in WalkDir::new(dir).into_iter().filter_map(|e| e.ok()) {
+        let is_html = entry.path().extension().is_some_and(|ext| ext == "html");
+        if !entry.file_type().is_file() || !is_html {
+            continue;
+        }
+        let path = entry.path();
+        let html = fs::read_to_string(path).with_context(|| format!("Failed to read page: {}", path.display()))?;
+        if let Some(updated) = inject::insert_before(&html, &["</head>"], &tag) {
+            fs::write(path, updated).with_context(|| format!("Failed to write page: {}", path.display()))?;
+            pages += 1;
+        }
+    }
+    Ok(pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refresh_tags_are_found() {
+        assert_eq!(refresh_at(&refresh_tag(2)), Some(0));
+        assert!(refresh_at("<p>x</p><META HTTP-EQUIV=Refresh CONTENT=\"0; url=https://evil.test/\">").is_some());
+        assert!(refresh_at("<meta content=\"5\" http-equiv='refresh'>").is_some());
+        assert!(refresh_at("<meta http-equiv=\"Content-Security-Policy\" content=\"default-src 'none'\">").is_none());
+        assert!(refresh_at("<p>Use http-equiv=\"refresh\" sparingly</p>").is_none());
+    }
+}
@@ -0,0 +1,175 @@
+//! Spelling and style linting of markdown sources
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use unicase::UniCase;
+use walkdir::WalkDir;
+
+/// Inline spans that are not prose: code, URLs, link targets, HTML tags
+static NON_PROSE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"`[^`]*`|https?://\S+|\]\([^)]*\)|<[^>]+>").unwrap());
+
+/// Words, allowing inner apostrophes ("don't")
+static WORD: Lazy<Regex> = Lazy::new(|| Regex::new(r"[A-Za-z]+(?:'[A-Za-z]+)*").unwrap());
+
+/// A prose problem in a markdown source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProseIssue {
+    /// Source file
+    pub file: PathBuf,
+    /// 1-based line number
+    pub line: usize,
+    /// Description of the problem
+    pub message: String,
+}
+
+impl fmt::Display for ProseIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file.display(), self.line, self.message)
+    }
+}
+
+/// Load the per-site word list (one word per line, `#` starts a comment)
+pub fn load_wordlist(path: &Path) -> Result<HashSet<String>> {
+    if !path.exists() {
+        return Ok(HashSet::new());
+    }
+
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read word list: {}", path.display()))?;
+
+    Ok(content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect())
+}
+
+/// Lint every markdown file in the content directory
+pub fn check_content(content_dir: &Path, wordlist: &HashSet<String>) -> Result<Vec<ProseIssue>> {
+    let mut issues = Vec::new();
+
+    for entry in WalkDir::new(content_dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| {
+            e.path().extension()
+                .and_then(|s| s.to_str())
+                .is_some_and(|ext| ext == "md" || ext == "markdown")
+        })
+    {
+        let path = entry.path();
+        let source = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read post: {}", path.display()))?;
+        issues.extend(check_source(path, &source, wordlist));
+    }
+
+    Ok(issues)
+}
+
+/// Lint a single markdown source, skipping frontmatter and code blocks
+pub fn check_source(file: &Path, source: &str, wordlist: &HashSet<String>) -> Vec<ProseIssue> {
+    let mut issues = Vec::new();
+    let mut in_frontmatter = source.starts_with("---");
+    let mut fence: Option<&str> = None;
+    let mut previous: Option<String> = None;
+
+    for (index, line) in source.lines().enumerate() {
+        let trimmed = line.trim_start();
+
+        if in_frontmatter {
+            if index > 0 && line.trim_end() == "---" {
+                in_frontmatter = false;
+            }
+            continue;
+        }
+
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if let Some(marker) = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m)) {
+            fence = Some(marker);
+            continue;
+        }
+
+        // Indented code blocks and blank lines end the current sentence context
+        if line.starts_with("    ") || line.starts_with('\t') || trimmed.is_empty() {
+            previous = None;
+            continue;
+        }
+
+        let prose = NON_PROSE.replace_all(line, " ");
+        for word in WORD.find_iter(&prose).map(|m| m.as_str()) {
+            let lower = word.to_lowercase();
+
+            if previous.as_deref() == Some(lower.as_str()) {
+                issues.push(ProseIssue {
+                    file: file.to_path_buf(),
+                    line: index + 1,
+                    message: format!("repeated word '{word}'"),
+                });
+            }
+
+            if !wordlist.contains(&lower) {
+                if let Some(corrections) = typos_dict::WORD.find(&UniCase::new(word)) {
+                    issues.push(ProseIssue {
+                        file: file.to_path_buf(),
+                        line: index + 1,
+                        message: format!(
+                            "possible misspelling '{word}' (did you mean {}?)",
+                            corrections.join(", ")
+                        ),
+                    });
+                }
+            }
+
+            previous = Some(lower);
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_misspelling_with_line_number() {
+        let source = "---\ntitle: Test\n---\n\nFirst line.\nThis is teh second line.\n";
+        let issues = check_source(Path::new("post.md"), source, &HashSet::new());
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].line, 6);
+        assert!(issues[0].message.contains("teh"));
+    }
+
+    #[test]
+    fn test_detects_repeated_words_across_lines() {
+        let source = "The the attack works\nwhen the\nthe server is old.\n";
+        let issues = check_source(Path::new("post.md"), source, &HashSet::new());
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].line, 1);
+        assert_eq!(issues[1].line, 3);
+    }
+
+    #[test]
+    fn test_skips_code_and_custom_words() {
+        let source = "Inline `teh` is code.\n\n```\nteh teh\n```\n\nSee https://example.com/teh today.\nteh is our name.\n";
+        let wordlist = HashSet::from(["teh".to_string()]);
+        assert!(check_source(Path::new("post.md"), source, &wordlist).is_empty());
+
+        let issues = check_source(Path::new("post.md"), source, &HashSet::new());
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].line, 8);
+    }
+}
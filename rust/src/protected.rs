@@ -0,0 +1,295 @@
+//! HTTP Basic auth for posts marked `protected: true`
+//!
+//! A static site cannot check a password itself, so the build writes what
+//! the web server needs instead: an `htpasswd` file from the bcrypt hashes in
+//! config.yaml and nginx, Apache and Caddy snippets covering the URL of every
+//! protected post. They go to a directory outside the output, since the
+//! password file must never be published. Protected posts are also treated
+//! as `noindex`, which keeps them out of feeds and the sitemap, and are left
+//! out of everything else that republishes posts away from their own URL:
+//! the index listing, ActivityPub and exports.
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+use tracing::info;
+
+use crate::{Config, Post};
+
+/// Password file inside the server config directory
+pub const HTPASSWD: &str = "htpasswd";
+
+/// nginx `include` with one `location` per protected post
+pub const NGINX: &str = "protected-nginx.conf";
+
+/// Apache `Include` with one `<Location>` per protected post
+pub const APACHE: &str = "protected-apache.conf";
+
+/// Caddyfile snippet (`import`) with a `basic_auth` block
+pub const CADDY: &str = "protected.Caddyfile";
+
+/// Protected page settings
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProtectedConfig {
+    /// User name → bcrypt hash (`htpasswd -nB user`)
+    pub users: BTreeMap<String, String>,
+    /// Directory the server files are written to, outside the output
+    #[serde(default = "default_dir")]
+    pub dir: PathBuf,
+    /// Where the server reads the deployed password file
+    #[serde(default = "default_htpasswd_path")]
+    pub htpasswd_path: String,
+    /// Realm shown in the browser's login prompt
+    #[serde(default = "default_realm")]
+    pub realm: String,
+}
+
+fn default_dir() -> PathBuf {
+    PathBuf::from("server")
+}
+
+fn default_htpasswd_path() -> String {
+    "/etc/secureblog/htpasswd".to_string()
+}
+
+fn default_realm() -> String {
+    "Protected".to_string()
+}
+
+/// bcrypt, the only scheme nginx, Apache and Caddy all accept
+static BCRYPT: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\$2[aby]\$\d{2}\$[./A-Za-z0-9]{53}$").unwrap());
+
+/// Characters that would need quoting rules that differ between the three servers
+fn is_unquotable(c: char) -> bool {
+    c.is_whitespace() || c.is_control() || matches!(c, '"' | '\'' | '\\' | ';' | '{' | '}' | '#' | '$')
+}
+
+impl ProtectedConfig {
+    /// Reject missing users, non-bcrypt hashes and values that cannot be written safely
+    pub fn validate(&self) -> Result<()> {
+        if self.users.is_empty() {
+            anyhow::bail!("protected.users is empty: no one could read protected posts");
+        }
+        for (user, hash) in &self.users {
+            if user.is_empty() || user.contains(':') || user.chars().any(is_unquotable) {
+                anyhow::bail!("protected.users: invalid user name {user:?}");
+            }
+            if !BCRYPT.is_match(hash) {
+                anyhow::bail!("protected.users.{user}: expected a bcrypt hash (`htpasswd -nB {user}`), not a password or weaker hash");
+            }
+        }
+        if self.htpasswd_path.is_empty() || self.htpasswd_path.chars().any(is_unquotable) {
+            anyhow::bail!("protected.htpasswd_path must be a path without spaces or quotes");
+        }
+        if self.realm.is_empty() || self.realm.chars().any(|c| c.is_control() || c == '"' || c == '\\') {
+            anyhow::bail!("protected.realm must be plain text without quotes");
+        }
+        Ok(())
+    }
+}
+
+/// URL paths to protect for a post: directory routes and `.html` pages with everything under
+/// them, extensionless URLs exactly
+///
+/// Files beside a flat page (`slug.md.asc`) are not covered, so nothing else is published for
+/// protected posts.
+fn protected_paths(post: &Post) -> Result<Vec<String>> {
+    let mut paths = vec![post.url_path()];
+    if !post.url_path().ends_with('/') {
+        paths.push(format!("/{}", post.path()));
+    }
+    paths.dedup();
+    if let Some(path) = paths.iter().find(|path| path.chars().any(is_unquotable)) {
+        anyhow::bail!("Protected post URL {path} cannot be written into server config");
+    }
+    Ok(paths)
+}
+
+/// Whether `path` is protected with everything it prefixes
+fn is_prefix(path: &str) -> bool {
+    path.ends_with('/') || path.ends_with(".html")
+}
+
+/// `htpasswd` contents
+pub fn htpasswd(config: &ProtectedConfig) -> String {
+    config.users.iter().map(|(user, hash)| format!("{user}:{hash}\n")).collect()
+}
+
+/// nginx locations
+pub fn nginx(config: &ProtectedConfig, paths: &[String]) -> String {
+    let mut conf = String::from("# include inside the server block\n");
+    for path in paths {
+        let modifier = if is_prefix(path) { "^~" } else { "=" };
+        let _ = writeln!(
+            conf,
+            "location {modifier} {path} {{\n    auth_basic \"{}\";\n    auth_basic_user_file {};\n}}",
+            config.realm, config.htpasswd_path
+        );
+    }
+    conf
+}
+
+/// Apache locations
+pub fn apache(config: &ProtectedConfig, paths: &[String]) -> String {
+    let mut conf = String::from("# Include inside the VirtualHost\n");
+    for path in paths {
+        let (open, close) = if is_prefix(path) {
+            (format!("<Location \"{path}\">"), "</Location>")
+        } else {
+            (format!("<LocationMatch \"^{}$\">", regex::escape(path)), "</LocationMatch>")
+        };
+        let _ = writeln!(
+            conf,
+            "{open}\n    AuthType Basic\n    AuthName \"{}\"\n    AuthUserFile \"{}\"\n    Require valid-user\n{close}",
+            config.realm, config.htpasswd_path
+        );
+    }
+    conf
+}
+
+/// Caddy matcher and `basic_auth` block with the hashes inline
+pub fn caddy(config: &ProtectedConfig, paths: &[String]) -> String {
+    let mut conf = String::from("# import inside the site block\n");
+    if paths.is_empty() {
+        return conf;
+    }
+    let matched: Vec<String> = paths.iter().map(|p| if is_prefix(p) { format!("{p}*") } else { p.clone() }).collect();
+    let _ = writeln!(conf, "@protected path {}", matched.join(" "));
+    let _ = writeln!(conf, "basic_auth @protected bcrypt \"{}\" {{", config.realm);
+    for (user, hash) in &config.users {
+        let _ = writeln!(conf, "    {user} {hash}");
+    }
+    conf.push_str("}\n");
+    conf
+}
+
+/// Write the password file and server snippets for the protected posts
+pub fn apply(config: &Config, posts: &[Post]) -> Result<()> {
+    let protected: Vec<&Post> = posts.iter().filter(|post| post.meta.protected).collect();
+    let Some(settings) = &config.protected else {
+        if let Some(post) = protected.first() {
+            anyhow::bail!("{} is marked protected: true but config.yaml has no protected: users", post.source.display());
+        }
+        return Ok(());
+    };
+    settings.validate()?;
+    if settings.dir.starts_with(&config.output) {
+        anyhow::bail!("protected.dir must be outside the output directory, or the password file would be published");
+    }
+
+    let mut paths = Vec::new();
+    for post in &protected {
+        paths.extend(protected_paths(post)?);
+    }
+    paths.sort();
+    paths.dedup();
+
+    fs::create_dir_all(&settings.dir).with_context(|| format!("Failed to create {}", settings.dir.display()))?;
+    for (name, content) in [
+        (HTPASSWD, htpasswd(settings)),
+        (NGINX, nginx(settings, &paths)),
+        (APACHE, apache(settings, &paths)),
+        (CADDY, caddy(settings, &paths)),
+    ] {
+        let path = settings.dir.join(name);
+        fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    info!("🔒 {} protected paths, server config in {}", paths.len(), settings.dir.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: &str = "$2y$05$abcdefghijklmnopqrstuuNKZRnbWj0bCmpwmWUYzJuIRxvEvsVmq";
+
+    fn settings() -> ProtectedConfig {
+        serde_yaml::from_str(&format!("users:\n  alice: \"{HASH}\"\n")).unwrap()
+    }
+
+    #[test]
+    fn test_validate_requires_bcrypt() {
+        assert!(settings().validate().is_ok());
+        let mut plain = settings();
+        plain.users.insert("bob".to_string(), "hunter2".to_string());
+        assert!(plain.validate().is_err());
+        let mut sha = settings();
+        sha.users.insert("carol".to_string(), "{SHA}W6ph5Mm5Pz8GgiULbPgzG37mj9g=".to_string());
+        assert!(sha.validate().is_err());
+        let mut name = settings();
+        name.users.insert("a b".to_string(), HASH.to_string());
+        assert!(name.validate().is_err());
+    }
+
+    #[test]
+    fn test_server_snippets() {
+        let settings = settings();
+        let paths = ["/2024/05/internal/".to_string(), "/notes.html".to_string(), "/drafts/plan".to_string()];
+        assert_eq!(htpasswd(&settings), format!("alice:{HASH}\n"));
+
+        let nginx = nginx(&settings, &paths);
+        assert!(nginx.contains("location ^~ /2024/05/internal/ {\n    auth_basic \"Protected\";\n    auth_basic_user_file /etc/secureblog/htpasswd;\n}"));
+        assert!(nginx.contains("location ^~ /notes.html {"));
+        assert!(nginx.contains("location = /drafts/plan {"));
+
+        let apache = apache(&settings, &paths);
+        assert!(apache.contains("<Location \"/notes.html\">\n    AuthType Basic\n"));
+        assert!(apache.contains("<LocationMatch \"^/drafts/plan$\">"));
+
+        let caddy = caddy(&settings, &paths);
+        assert!(caddy.contains("@protected path /2024/05/internal/* /notes.html* /drafts/plan\n"));
+        assert!(caddy.contains(&format!("basic_auth @protected bcrypt \"Protected\" {{\n    alice {HASH}\n}}\n")));
+        assert!(!super::caddy(&settings, &[]).contains("basic_auth"));
+    }
+
+    #[test]
+    fn test_protected_body_stays_out_of_public_outputs() {
+        use crate::{activitypub, context, export, feeds, PostMeta};
+
+        let dir = std::env::temp_dir().join(format!("secureblog-protected-{}", std::process::id()));
+        let config = Config { output: dir.clone(), ..Config::default() };
+        let post = |slug: &str, protected: bool| Post {
+            meta: PostMeta {
+                title: slug.to_string(),
+                slug: slug.to_string(),
+                tags: vec!["ops".to_string()],
+                protected,
+                ..PostMeta::default()
+            },
+            html: format!("<p>Body of {slug}: {}</p>", if protected { "SECRET-INCIDENT" } else { "public" }),
+            ..Post::default()
+        };
+        let posts = [post("open", false), post("internal", true)];
+
+        let ap: activitypub::ActivityPubConfig = serde_yaml::from_str("{}").unwrap();
+        activitypub::generate(&config, &ap, &posts).unwrap();
+        feeds::generate(&config, &posts).unwrap();
+        let mut leaks = Vec::new();
+        let mut dirs = vec![dir.clone()];
+        while let Some(current) = dirs.pop() {
+            for entry in fs::read_dir(&current).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if fs::read_to_string(&path).unwrap().contains("SECRET-INCIDENT") {
+                    leaks.push(path);
+                }
+            }
+        }
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(leaks.is_empty(), "protected body published in {leaks:?}");
+
+        let index = context::dump(&config, &posts, "/").unwrap();
+        assert!(index.contains("Body of open") && !index.contains("SECRET-INCIDENT"));
+        let exported: Vec<&str> = export::select(&posts, "all").unwrap().iter().map(|p| p.meta.slug.as_str()).collect();
+        assert_eq!(exported, ["open"]);
+        assert!(export::select(&posts, "internal").is_err());
+    }
+}
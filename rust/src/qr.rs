@@ -0,0 +1,62 @@
+//! Per-post QR codes linking to the canonical URL
+
+use anyhow::{Context, Result};
+use qrcode::render::svg;
+use qrcode::{EcLevel, QrCode};
+
+use crate::security::escape_html;
+use crate::{inject, svg::sanitize_svg, Config, Post};
+
+/// Render a sanitized inline SVG QR code for `url`
+pub fn qr_svg(url: &str) -> Result<String> {
+    let code = QrCode::with_error_correction_level(url.as_bytes(), EcLevel::M)
+        .with_context(|| format!("Failed to encode QR code for {url}"))?;
+    let svg = code
+        .render::<svg::Color<'_>>()
+        .min_dimensions(128, 128)
+        .quiet_zone(true)
+        .build();
+    Ok(sanitize_svg(&svg))
+}
+
+/// Figure holding the QR code and the URL it encodes
+pub fn qr_figure(url: &str) -> Result<String> {
+    Ok(format!(
+        "<figure class=\"post-qr\">{}<figcaption>{}</figcaption></figure>\n",
+        qr_svg(url)?,
+        escape_html(url)
+    ))
+}
+
+/// Append a QR code for its canonical URL to every post page
+pub fn apply(config: &Config, posts: &[Post]) -> Result<()> {
+    for post in posts {
+        let figure = qr_figure(&post.permalink(&config.url))?;
+        inject::inject_into_file(
+            &config.output.join(post.path()),
+            &["</article>", "</main>", "</body>"],
+            &figure,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qr_svg_is_sanitized_svg() {
+        let svg = qr_svg("https://example.com/post.html").unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("viewBox=\""));
+        assert!(svg.contains("<path") || svg.contains("<rect"));
+        assert!(!svg.contains("<?xml"));
+    }
+
+    #[test]
+    fn test_qr_figure_escapes_caption() {
+        let html = qr_figure("https://example.com/a.html?x=<b>").unwrap();
+        assert!(html.contains("<figcaption>https://example.com/a.html?x=&lt;b&gt;</figcaption>"));
+    }
+}
@@ -0,0 +1,414 @@
+//! Rekor transparency log entries for the build manifest
+//!
+//! With `rekor:` configured, the build signs `integrity.json` with the
+//! `sign_files` key, submits the signature to Rekor and stores the returned
+//! entry (signed entry timestamp and inclusion proof) as
+//! `integrity.json.rekor.json`. `verify --rekor` checks all of it offline
+//! against the Rekor key compiled into the binary, and requires the logged
+//! signing key to be the expected one: anyone can log their own signature.
+
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use ssh_key::{HashAlg, PublicKey, SshSig};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{cosign, detached};
+
+/// Suffix of the stored log entry next to the manifest
+pub const ENTRY_SUFFIX: &str = ".rekor.json";
+
+/// Transparency log settings
+#[derive(Debug, Clone, Deserialize)]
+pub struct RekorConfig {
+    /// Rekor instance (entries are verified against the public-good log key)
+    #[serde(default = "default_url")]
+    pub url: String,
+    /// Fingerprint (`SHA256:...`) the signing key must have (defaults to the `sign_files` key's)
+    #[serde(default)]
+    pub fingerprint: Option<String>,
+}
+
+fn default_url() -> String {
+    "https://rekor.sigstore.dev".to_string()
+}
+
+/// Path of the stored log entry for `manifest`
+pub fn entry_path(manifest: &Path) -> PathBuf {
+    let mut name = manifest.as_os_str().to_os_string();
+    name.push(ENTRY_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Log entry as returned by `POST /api/v1/log/entries`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    /// Entry UUID
+    #[serde(default)]
+    pub uuid: String,
+    /// Canonicalized entry body (base64)
+    pub body: String,
+    /// Time the entry was logged (Unix seconds)
+    pub integrated_time: i64,
+    /// Hex SHA-256 of the log's public key
+    #[serde(rename = "logID")]
+    pub log_id: String,
+    /// Global index of the entry
+    pub log_index: i64,
+    /// Signed entry timestamp and inclusion proof
+    pub verification: Verification,
+}
+
+/// Evidence that the log accepted the entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Verification {
+    /// Rekor's signature over the entry (base64)
+    pub signed_entry_timestamp: String,
+    /// Merkle inclusion proof with a signed checkpoint
+    pub inclusion_proof: Option<ApiInclusionProof>,
+}
+
+/// Inclusion proof with hex hashes, as Rekor's API returns it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiInclusionProof {
+    /// Index of the entry in its tree
+    pub log_index: u64,
+    /// Tree size the proof is for
+    pub tree_size: u64,
+    /// Root hash (hex)
+    pub root_hash: String,
+    /// Audit path (hex), leaf to root
+    pub hashes: Vec<String>,
+    /// Signed note committing to the tree size and root
+    pub checkpoint: String,
+}
+
+/// Merkle inclusion proof in decoded form
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InclusionProof {
+    /// Index of the entry in its tree
+    pub log_index: u64,
+    /// Tree size the proof is for
+    pub tree_size: u64,
+    /// Root hash
+    pub root_hash: Vec<u8>,
+    /// Audit path, leaf to root
+    pub hashes: Vec<Vec<u8>>,
+    /// Signed note committing to the tree size and root
+    pub checkpoint: String,
+}
+
+fn decode_hex(value: &str) -> Result<Vec<u8>> {
+    if value.len() % 2 != 0 {
+        anyhow::bail!("Odd-length hex string");
+    }
+    // Also keeps the two-byte slices below on character boundaries
+    if !value.bytes().all(|b| b.is_ascii_hexdigit()) {
+        anyhow::bail!("Invalid hex: {value}");
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&value[i..i + 2], 16).with_context(|| format!("Invalid hex: {value}")))
+        .collect()
+}
+
+impl TryFrom<&ApiInclusionProof> for InclusionProof {
+    type Error = anyhow::Error;
+
+    fn try_from(proof: &ApiInclusionProof) -> Result<Self> {
+        Ok(Self {
+            log_index: proof.log_index,
+            tree_size: proof.tree_size,
+            root_hash: decode_hex(&proof.root_hash)?,
+            hashes: proof.hashes.iter().map(|h| decode_hex(h)).collect::<Result<_>>()?,
+            checkpoint: proof.checkpoint.clone(),
+        })
+    }
+}
+
+/// RFC 6962 leaf hash of an entry body
+pub fn leaf_hash(body: &[u8]) -> Vec<u8> {
+    Sha256::new().chain_update([0_u8]).chain_update(body).finalize().to_vec()
+}
+
+fn node_hash(left: &[u8], right: &[u8]) -> Vec<u8> {
+    Sha256::new().chain_update([1_u8]).chain_update(left).chain_update(right).finalize().to_vec()
+}
+
+/// Root hash implied by an audit path (RFC 9162, section 2.1.3.2)
+pub fn root_from_inclusion(leaf: &[u8], index: u64, size: u64, hashes: &[Vec<u8>]) -> Result<Vec<u8>> {
+    if index >= size {
+        anyhow::bail!("Leaf index {index} is outside a tree of size {size}");
+    }
+    let (mut fn_, mut sn) = (index, size - 1);
+    let mut root = leaf.to_vec();
+    for hash in hashes {
+        if sn == 0 {
+            anyhow::bail!("Inclusion proof is too long");
+        }
+        if fn_ & 1 == 1 || fn_ == sn {
+            root = node_hash(hash, &root);
+            while fn_ & 1 == 0 && fn_ != 0 {
+                fn_ >>= 1;
+                sn >>= 1;
+            }
+        } else {
+            root = node_hash(&root, hash);
+        }
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+    if sn != 0 {
+        anyhow::bail!("Inclusion proof is too short");
+    }
+    Ok(root)
+}
+
+/// Checkpoint (signed note) is signed by Rekor and commits to `tree_size` and `root_hash`
+pub fn verify_checkpoint(checkpoint: &str, tree_size: u64, root_hash: &[u8]) -> Result<()> {
+    let (text, signatures) = checkpoint.split_once("\n\n").context("Malformed checkpoint")?;
+    let text = format!("{text}\n");
+    let mut lines = text.lines().skip(1);
+    let size: u64 = lines.next().and_then(|l| l.parse().ok()).context("Checkpoint has no tree size")?;
+    let root = lines
+        .next()
+        .and_then(|l| STANDARD.decode(l).ok())
+        .context("Checkpoint has no root hash")?;
+    if size != tree_size || root != root_hash {
+        anyhow::bail!("Checkpoint does not match the inclusion proof");
+    }
+
+    // Signature lines: "— <origin> <base64(4-byte key hint || signature)>"
+    let hint = decode_hex(&cosign::rekor_log_id()?)?;
+    let signed = signatures
+        .lines()
+        .filter_map(|line| line.strip_prefix("\u{2014} "))
+        .filter_map(|line| line.rsplit_once(' '))
+        .filter_map(|(_, signature)| STANDARD.decode(signature).ok())
+        .filter(|signature| signature.len() > 4 && signature[..4] == hint[..4])
+        .any(|signature| cosign::verify_rekor_signature(text.as_bytes(), &signature[4..]).is_ok());
+    if !signed {
+        anyhow::bail!("Checkpoint is not signed by the Rekor log key");
+    }
+    Ok(())
+}
+
+/// Entry body is included in the tree the signed checkpoint describes
+pub fn verify_inclusion(body: &[u8], proof: &InclusionProof) -> Result<()> {
+    let root = root_from_inclusion(&leaf_hash(body), proof.log_index, proof.tree_size, &proof.hashes)?;
+    if root != proof.root_hash {
+        anyhow::bail!("Inclusion proof does not lead to the logged root hash");
+    }
+    verify_checkpoint(&proof.checkpoint, proof.tree_size, &proof.root_hash)
+}
+
+/// `rekord` entry for an SSH-signed file; Rekor keeps only the file's hash
+pub fn proposed_entry(content: &[u8], signature_pem: &str, public_key: &str) -> serde_json::Value {
+    serde_json::json!({
+        "apiVersion": "0.0.1",
+        "kind": "rekord",
+        "spec": {
+            "signature": {
+                "format": "ssh",
+                "content": STANDARD.encode(signature_pem),
+                "publicKey": { "content": STANDARD.encode(public_key) },
+            },
+            "data": { "content": STANDARD.encode(content) },
+        },
+    })
+}
+
+/// Offline check of a stored entry
+#[derive(Debug, Clone)]
+pub struct Verified {
+    /// Fingerprint of the SSH key that signed the manifest
+    pub fingerprint: String,
+    /// Global log index
+    pub log_index: i64,
+    /// When the entry was logged
+    pub integrated_time: DateTime<Utc>,
+}
+
+/// SHA-256 fingerprint of the public half of the `sign_files` key
+pub fn key_fingerprint(key_path: &Path) -> Result<String> {
+    Ok(detached::load_key(key_path)?.public_key().fingerprint(HashAlg::Sha256).to_string())
+}
+
+/// Verify `entry` for `content` signed by the armored SSH `signature` with the key `fingerprint`
+pub fn verify_entry(entry: &LogEntry, content: &[u8], signature: &[u8], fingerprint: &str) -> Result<Verified> {
+    if entry.log_id != cosign::rekor_log_id()? {
+        anyhow::bail!("Entry is from an unknown transparency log ({})", entry.log_id);
+    }
+    let set = STANDARD.decode(&entry.verification.signed_entry_timestamp)?;
+    let payload = cosign::set_payload(&entry.body, entry.integrated_time, &entry.log_id, entry.log_index);
+    cosign::verify_rekor_signature(payload.as_bytes(), &set).context("Invalid signed entry timestamp")?;
+
+    let body = STANDARD.decode(&entry.body)?;
+    let proof = entry
+        .verification
+        .inclusion_proof
+        .as_ref()
+        .context("Entry has no inclusion proof")?;
+    verify_inclusion(&body, &InclusionProof::try_from(proof)?)?;
+
+    // The logged entry is this manifest and this signature
+    let body: serde_json::Value = serde_json::from_slice(&body).context("Entry body is not JSON")?;
+    let spec = &body["spec"];
+    if body["kind"] != "rekord" || spec["signature"]["format"] != "ssh" {
+        anyhow::bail!("Entry is not an SSH-signed rekord");
+    }
+    if spec["data"]["hash"]["value"].as_str() != Some(&format!("{:x}", Sha256::digest(content))) {
+        anyhow::bail!("Entry is for a different manifest");
+    }
+    let decode = |value: &serde_json::Value| value.as_str().and_then(|s| STANDARD.decode(s).ok());
+    if decode(&spec["signature"]["content"]).as_deref() != Some(signature) {
+        anyhow::bail!("Entry has a different signature");
+    }
+    let key = decode(&spec["signature"]["publicKey"]["content"])
+        .and_then(|key| String::from_utf8(key).ok())
+        .context("Entry has no public key")?;
+    let key = PublicKey::from_openssh(key.trim()).context("Invalid public key in entry")?;
+    let logged = key.fingerprint(HashAlg::Sha256).to_string();
+    if logged != fingerprint.trim() {
+        anyhow::bail!("Entry is signed by {logged}, expected {}", fingerprint.trim());
+    }
+    let sig = SshSig::from_pem(signature).context("Invalid SSH signature")?;
+    key.verify(detached::FILE_NAMESPACE, content, &sig)
+        .context("Manifest signature does not verify")?;
+
+    Ok(Verified {
+        fingerprint: logged,
+        log_index: entry.log_index,
+        integrated_time: DateTime::from_timestamp(entry.integrated_time, 0).context("Invalid integrated time")?,
+    })
+}
+
+/// Verify `manifest` against its `.sig` and stored `.rekor.json`, signed with the key `fingerprint`
+pub fn verify_manifest(manifest: &Path, fingerprint: &str) -> Result<Verified> {
+    let content = fs::read(manifest).with_context(|| format!("Failed to read {}", manifest.display()))?;
+    let signature_path = detached::signature_path(manifest);
+    let signature = fs::read(&signature_path).with_context(|| format!("Failed to read {}", signature_path.display()))?;
+    let entry_path = entry_path(manifest);
+    let entry: LogEntry = serde_json::from_str(
+        &fs::read_to_string(&entry_path).with_context(|| format!("Failed to read {}", entry_path.display()))?,
+    )
+    .with_context(|| format!("Invalid log entry: {}", entry_path.display()))?;
+    verify_entry(&entry, &content, &signature, fingerprint)
+}
+
+#[cfg(feature = "network")]
+pub use online::submit;
+
+#[cfg(feature = "network")]
+mod online {
+    use super::{entry_path, proposed_entry, verify_entry, LogEntry, RekorConfig};
+    use crate::detached;
+    use anyhow::{Context, Result};
+    use ssh_key::HashAlg;
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::path::Path;
+    use tracing::info;
+
+    /// Sign `manifest`, log the signature in Rekor and store the verified entry next to it
+    pub fn submit(config: &RekorConfig, manifest: &Path, key_path: &Path) -> Result<LogEntry> {
+        let key = detached::load_key(key_path)?;
+        let content = fs::read(manifest).with_context(|| format!("Failed to read {}", manifest.display()))?;
+        let signature = detached::sign(&key, &content)?;
+        let signature_path = detached::signature_path(manifest);
+        fs::write(&signature_path, &signature)
+            .with_context(|| format!("Failed to write {}", signature_path.display()))?;
+
+        let url = format!("{}/api/v1/log/entries", config.url.trim_end_matches('/'));
+        let proposed = proposed_entry(&content, &signature, &key.public_key().to_openssh()?);
+        let mut response = crate::net::agent()
+            .post(&url)
+            .header("content-type", "application/json")
+            .send(proposed.to_string())
+            .with_context(|| format!("Failed to submit to {url}"))?;
+        let text = response.body_mut().read_to_string()?;
+        if !response.status().is_success() {
+            anyhow::bail!("Rekor answered HTTP {}: {}", response.status(), text.trim());
+        }
+
+        let entries: BTreeMap<String, LogEntry> = serde_json::from_str(&text).context("Unexpected Rekor response")?;
+        let (uuid, mut entry) = entries.into_iter().next().context("Rekor returned no entry")?;
+        entry.uuid = uuid;
+        let fingerprint = key.public_key().fingerprint(HashAlg::Sha256).to_string();
+        verify_entry(&entry, &content, signature.as_bytes(), &fingerprint)
+            .context("Rekor returned an entry that does not verify")?;
+
+        let path = entry_path(manifest);
+        fs::write(&path, serde_json::to_string_pretty(&entry)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        info!("🪵 Logged {} in Rekor at index {}", manifest.display(), entry.log_index);
+        Ok(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(n: u8) -> Vec<Vec<u8>> {
+        (0..n).map(|i| leaf_hash(&[i])).collect()
+    }
+
+    /// Root of a full tree over `leaves` (RFC 6962 MTH)
+    fn mth(leaves: &[Vec<u8>]) -> Vec<u8> {
+        if leaves.len() == 1 {
+            return leaves[0].clone();
+        }
+        let split = leaves.len().next_power_of_two() / 2;
+        node_hash(&mth(&leaves[..split]), &mth(&leaves[split..]))
+    }
+
+    #[test]
+    fn test_inclusion_in_seven_leaf_tree() {
+        let l = leaves(7);
+        // Leaf 3: sibling 2, then the hash of 0..2, then the hash of 4..7
+        let path = vec![l[2].clone(), mth(&l[..2]), mth(&l[4..])];
+        assert_eq!(root_from_inclusion(&l[3], 3, 7, &path).unwrap(), mth(&l));
+        // Leaf 6 (last, unbalanced): the hash of 4..6, then the hash of 0..4
+        let path = vec![mth(&l[4..6]), mth(&l[..4])];
+        assert_eq!(root_from_inclusion(&l[6], 6, 7, &path).unwrap(), mth(&l));
+        assert_ne!(root_from_inclusion(&l[5], 6, 7, &path).unwrap(), mth(&l));
+    }
+
+    #[test]
+    fn test_inclusion_rejects_bad_shapes() {
+        let l = leaves(4);
+        assert!(root_from_inclusion(&l[0], 4, 4, &[]).is_err());
+        assert!(root_from_inclusion(&l[0], 0, 4, &[l[1].clone()]).is_err());
+        assert_eq!(root_from_inclusion(&l[0], 0, 1, &[]).unwrap(), l[0]);
+    }
+
+    #[test]
+    fn test_checkpoint_must_match_proof() {
+        let root = vec![7_u8; 32];
+        let checkpoint = format!("rekor.sigstore.dev - 1\n10\n{}\n\n\u{2014} rekor.sigstore.dev wNI9ajBEAiA=\n", STANDARD.encode(&root));
+        let err = verify_checkpoint(&checkpoint, 11, &root).unwrap_err();
+        assert!(err.to_string().contains("does not match"));
+        let err = verify_checkpoint(&checkpoint, 10, &root).unwrap_err();
+        assert!(err.to_string().contains("not signed"));
+    }
+
+    #[test]
+    fn test_proposed_entry_and_paths() {
+        let entry = proposed_entry(b"{}", "SIG", "ssh-ed25519 AAAA");
+        assert_eq!(entry["kind"], "rekord");
+        assert_eq!(entry["spec"]["data"]["content"], "e30=");
+        assert_eq!(entry_path(Path::new("dist/integrity.json")), PathBuf::from("dist/integrity.json.rekor.json"));
+        assert_eq!(decode_hex("00ff").unwrap(), [0, 255]);
+        assert!(decode_hex("0").is_err());
+        assert!(decode_hex("+f").is_err());
+        assert!(decode_hex("é").is_err());
+    }
+}
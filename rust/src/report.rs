@@ -0,0 +1,221 @@
+//! Page weight, bandwidth and carbon footprint estimates for the built site
+
+use anyhow::{Context, Result};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::Path;
+
+use crate::links::{self, SiteLinks};
+
+/// Extensions served compressed by a typical static host
+const COMPRESSIBLE: &[&str] = &[
+    "html", "htm", "css", "svg", "xml", "json", "txt", "webmanifest", "ico",
+];
+
+/// Assumptions behind the estimates
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReportConfig {
+    /// Page views per month spread evenly over all pages
+    #[serde(default = "default_monthly_pageviews")]
+    pub monthly_pageviews: u64,
+    /// Energy per gigabyte transferred (Sustainable Web Design model)
+    #[serde(default = "default_kwh_per_gb")]
+    pub kwh_per_gb: f64,
+    /// Grid carbon intensity (global average)
+    #[serde(default = "default_grams_co2_per_kwh")]
+    pub grams_co2_per_kwh: f64,
+}
+
+impl Default for ReportConfig {
+    fn default() -> Self {
+        Self {
+            monthly_pageviews: default_monthly_pageviews(),
+            kwh_per_gb: default_kwh_per_gb(),
+            grams_co2_per_kwh: default_grams_co2_per_kwh(),
+        }
+    }
+}
+
+const fn default_monthly_pageviews() -> u64 {
+    10_000
+}
+
+const fn default_kwh_per_gb() -> f64 {
+    0.81
+}
+
+const fn default_grams_co2_per_kwh() -> f64 {
+    442.0
+}
+
+/// Transfer size of one page on an uncached first view
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageWeight {
+    /// Page path relative to the output root
+    pub page: String,
+    /// Compressed size of the HTML document
+    pub html_bytes: u64,
+    /// Compressed HTML plus every subresource it loads
+    pub transfer_bytes: u64,
+}
+
+/// Site-wide monthly estimate
+#[derive(Debug, Clone, PartialEq)]
+pub struct Estimate {
+    /// Mean transfer size per page view
+    pub average_bytes: u64,
+    /// Bandwidth for the assumed monthly page views
+    pub monthly_bytes: u64,
+    /// Energy used by that transfer
+    pub monthly_kwh: f64,
+    /// Emissions for that energy
+    pub monthly_grams_co2: f64,
+}
+
+/// Size of a file as sent over the wire (gzip for text formats)
+pub fn transfer_size(path: &str, content: &[u8]) -> Result<u64> {
+    let compressible = Path::new(path)
+        .extension()
+        .and_then(|s| s.to_str())
+        .is_some_and(|ext| COMPRESSIBLE.contains(&ext.to_ascii_lowercase().as_str()));
+    if !compressible {
+        return Ok(content.len() as u64);
+    }
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(content)?;
+    Ok(encoder.finish()?.len() as u64)
+}
+
+/// Files loaded when `page` is viewed: `src=` targets, stylesheets and their own references
+pub fn subresources(site: &SiteLinks, page: &str) -> BTreeSet<String> {
+    let mut resources = BTreeSet::new();
+    let Some(links) = site.pages.get(page) else {
+        return resources;
+    };
+
+    let mut stylesheets = Vec::new();
+    for reference in &links.references {
+        let Some((target, _)) = links::resolve(page, &reference.value) else {
+            continue;
+        };
+        if site.stylesheets.contains_key(&target) {
+            stylesheets.push(target.clone());
+        } else if reference.attr != "src" {
+            continue;
+        }
+        if site.files.contains(&target) {
+            resources.insert(target);
+        }
+    }
+
+    // Fonts and images pulled in from CSS, following `@import`
+    while let Some(css) = stylesheets.pop() {
+        for reference in site.stylesheets.get(&css).into_iter().flatten() {
+            if let Some((target, _)) = links::resolve(&css, reference) {
+                if site.files.contains(&target)
+                    && resources.insert(target.clone())
+                    && site.stylesheets.contains_key(&target)
+                {
+                    stylesheets.push(target);
+                }
+            }
+        }
+    }
+
+    resources
+}
+
+/// Compute the transfer weight of every HTML page in the output
+pub fn page_weights(output_dir: &Path) -> Result<Vec<PageWeight>> {
+    let site = links::scan_site(output_dir)?;
+    let size_of = |path: &str| -> Result<u64> {
+        let content = std::fs::read(output_dir.join(path))
+            .with_context(|| format!("Failed to read output file: {path}"))?;
+        transfer_size(path, &content)
+    };
+
+    site.pages
+        .keys()
+        .map(|page| {
+            let html_bytes = size_of(page)?;
+            let assets = subresources(&site, page)
+                .iter()
+                .map(|asset| size_of(asset))
+                .sum::<Result<u64>>()?;
+            Ok(PageWeight {
+                page: page.clone(),
+                html_bytes,
+                transfer_bytes: html_bytes + assets,
+            })
+        })
+        .collect()
+}
+
+/// Monthly bandwidth and carbon estimate from per-page weights
+#[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn estimate(weights: &[PageWeight], config: &ReportConfig) -> Estimate {
+    let total: u64 = weights.iter().map(|w| w.transfer_bytes).sum();
+    let average_bytes = total.checked_div(weights.len() as u64).unwrap_or(0);
+    let monthly_bytes = average_bytes.saturating_mul(config.monthly_pageviews);
+    let monthly_kwh = monthly_bytes as f64 / 1e9 * config.kwh_per_gb;
+
+    Estimate {
+        average_bytes,
+        monthly_bytes,
+        monthly_kwh,
+        monthly_grams_co2: monthly_kwh * config.grams_co2_per_kwh,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::links::parse_page;
+
+    #[test]
+    fn test_transfer_size_compresses_text_only() {
+        let text = "hello world ".repeat(100);
+        assert!(transfer_size("index.html", text.as_bytes()).unwrap() < 100);
+        assert_eq!(transfer_size("img/photo.jpg", text.as_bytes()).unwrap(), 1200);
+    }
+
+    #[test]
+    fn test_subresources_follow_stylesheets_not_anchors() {
+        let mut site = SiteLinks::default();
+        site.pages.insert(
+            "post.html".to_string(),
+            parse_page(r#"<link href="style.css"><img src="a.png"><a href="other.html">x</a>"#),
+        );
+        site.stylesheets
+            .insert("style.css".to_string(), vec!["font.woff2".to_string()]);
+        for file in ["post.html", "other.html", "style.css", "a.png", "font.woff2"] {
+            site.files.insert(file.to_string());
+        }
+
+        let resources = subresources(&site, "post.html");
+        let expected: BTreeSet<String> = ["a.png", "font.woff2", "style.css"]
+            .iter()
+            .map(|s| (*s).to_string())
+            .collect();
+        assert_eq!(resources, expected);
+    }
+
+    #[test]
+    fn test_estimate_scales_with_pageviews() {
+        let weights = [
+            PageWeight { page: "a.html".into(), html_bytes: 1_000, transfer_bytes: 10_000 },
+            PageWeight { page: "b.html".into(), html_bytes: 1_000, transfer_bytes: 30_000 },
+        ];
+        let config = ReportConfig { monthly_pageviews: 50_000, ..ReportConfig::default() };
+        let estimate = estimate(&weights, &config);
+        assert_eq!(estimate.average_bytes, 20_000);
+        assert_eq!(estimate.monthly_bytes, 1_000_000_000);
+        assert!((estimate.monthly_kwh - 0.81).abs() < 1e-9);
+        assert!((estimate.monthly_grams_co2 - 358.02).abs() < 1e-6);
+        assert_eq!(self::estimate(&[], &config).monthly_bytes, 0);
+    }
+}
@@ -0,0 +1,87 @@
+//! Normalized timestamps and permissions for everything the generator writes
+//!
+//! Files get mode `0644`, directories `0755`, and every mtime is
+//! `SOURCE_DATE_EPOCH` (or the Unix epoch when unset), so mirrors made with
+//! rsync or tar do not reveal when or where a build ran.
+
+use anyhow::{Context, Result};
+use std::fs::{self, File};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+/// Mode of written files
+pub const FILE_MODE: u32 = 0o644;
+
+/// Mode of written directories
+pub const DIR_MODE: u32 = 0o755;
+
+/// Timestamp for output files and archive entries, from `SOURCE_DATE_EPOCH`
+pub fn source_date_epoch() -> Result<u64> {
+    parse_epoch(std::env::var("SOURCE_DATE_EPOCH").ok().as_deref())
+}
+
+fn parse_epoch(value: Option<&str>) -> Result<u64> {
+    match value.map(str::trim).filter(|v| !v.is_empty()) {
+        None => Ok(0),
+        Some(v) => v.parse().with_context(|| format!("Invalid SOURCE_DATE_EPOCH: {v}")),
+    }
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+        .with_context(|| format!("Failed to set permissions of {}", path.display()))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> Result<()> {
+    Ok(())
+}
+
+fn set_mtime(path: &Path, mtime: SystemTime) -> Result<()> {
+    File::open(path)
+        .and_then(|file| file.set_modified(mtime))
+        .with_context(|| format!("Failed to set mtime of {}", path.display()))
+}
+
+/// Normalize one written file
+pub fn normalize_file(path: &Path, epoch: u64) -> Result<()> {
+    set_mode(path, FILE_MODE)?;
+    set_mtime(path, UNIX_EPOCH + Duration::from_secs(epoch))
+}
+
+/// Normalize a whole output tree, directories after their contents
+pub fn normalize_tree(dir: &Path, epoch: u64) -> Result<()> {
+    let mtime = UNIX_EPOCH + Duration::from_secs(epoch);
+    for entry in WalkDir::new(dir).contents_first(true) {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type().is_dir() {
+            set_mode(path, DIR_MODE)?;
+            // Directory handles can only be opened for timestamps on Unix
+            if cfg!(unix) {
+                set_mtime(path, mtime)?;
+            }
+        } else if entry.file_type().is_file() {
+            set_mode(path, FILE_MODE)?;
+            set_mtime(path, mtime)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_epoch() {
+        assert_eq!(parse_epoch(None).unwrap(), 0);
+        assert_eq!(parse_epoch(Some("")).unwrap(), 0);
+        assert_eq!(parse_epoch(Some("1700000000\n")).unwrap(), 1_700_000_000);
+        assert!(parse_epoch(Some("yesterday")).is_err());
+        assert!(parse_epoch(Some("-5")).is_err());
+    }
+}
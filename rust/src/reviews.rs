@@ -0,0 +1,124 @@
+//! Review reminders from `review_by:` frontmatter
+//!
+//! Authors date the point at which a post's advice should be re-checked.
+//! Once a published post passes it, `check reviews` warns, and when the
+//! banner partial exists the build puts it at the top of the post so readers
+//! know the content may be outdated. `{{ review_by }}` in the partial becomes
+//! the review date.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use std::fs;
+use tracing::info;
+
+use crate::security::escape_html as escape;
+use crate::{Config, Post};
+
+/// Replaced by the post's review date in the banner partial
+pub const DATE_PLACEHOLDER: &str = "{{ review_by }}";
+
+/// Posts whose review date is before `now`, most overdue first
+pub fn overdue(posts: &[Post], now: DateTime<Utc>) -> Vec<&Post> {
+    let mut overdue: Vec<&Post> = posts.iter().filter(|post| post.meta.review_by.is_some_and(|date| date < now)).collect();
+    overdue.sort_by(|a, b| a.meta.review_by.cmp(&b.meta.review_by).then_with(|| a.meta.slug.cmp(&b.meta.slug)));
+    overdue
+}
+
+/// 1-based line of the `review_by:` key in a post's source, for annotations
+pub fn frontmatter_line(source: &str) -> Option<usize> {
+    source.lines().position(|line| line.starts_with("review_by:")).map(|i| i + 1)
+}
+
+/// The banner partial with the post's review date filled in
+pub fn banner(partial: &str, review_by: DateTime<Utc>, zone: Tz) -> String {
+    let date = review_by.with_timezone(&zone).format("%Y-%m-%d").to_string();
+    partial.replace(DATE_PLACEHOLDER, &escape(&date))
+}
+
+/// Insert `fragment` right after the opening tag of the first of `tags` present
+///
+/// Tags are tried in order and matched case-insensitively, e.g. `["article", "main", "body"]`.
+fn insert_after_open(html: &str, tags: &[&str], fragment: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let position = tags.iter().find_map(|tag| {
+        let open = format!("<{tag}");
+        lower.match_indices(&open).find_map(|(start, _)| {
+            let rest = &lower[start + open.len()..];
+            if rest.starts_with(|c: char| c == '>' || c.is_ascii_whitespace()) {
+                rest.find('>').map(|end| start + open.len() + end + 1)
+            } else {
+                None
+            }
+        })
+    })?;
+
+    let mut result = String::with_capacity(html.len() + fragment.len());
+    result.push_str(&html[..position]);
+    result.push_str(fragment);
+    result.push_str(&html[position..]);
+    Some(result)
+}
+
+/// Put the banner partial, if it exists, at the top of every overdue post
+pub fn apply(config: &Config, posts: &[Post]) -> Result<()> {
+    if !config.review_banner.exists() {
+        return Ok(());
+    }
+    let partial = fs::read_to_string(&config.review_banner)
+        .with_context(|| format!("Failed to read {}", config.review_banner.display()))?;
+    let overdue = overdue(posts, Utc::now());
+    for post in &overdue {
+        let Some(review_by) = post.meta.review_by else { continue };
+        let page = config.output.join(post.path());
+        let html = fs::read_to_string(&page).with_context(|| format!("Failed to read page: {}", page.display()))?;
+        let fragment = banner(&partial, review_by, config.timezone.display_zone());
+        let updated = insert_after_open(&html, &["article", "main", "body"], &fragment)
+            .with_context(|| format!("No <article>, <main> or <body> in {}", page.display()))?;
+        fs::write(&page, updated).with_context(|| format!("Failed to write page: {}", page.display()))?;
+    }
+    if !overdue.is_empty() {
+        info!("🗓️  Review banner on {} posts past their review date", overdue.len());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PostMeta;
+
+    fn post(slug: &str, review_by: Option<&str>) -> Post {
+        Post {
+            meta: PostMeta { slug: slug.to_string(), review_by: review_by.map(|d| d.parse().unwrap()), ..PostMeta::default() },
+            ..Post::default()
+        }
+    }
+
+    #[test]
+    fn test_overdue_posts() {
+        let now: DateTime<Utc> = "2026-06-01T00:00:00Z".parse().unwrap();
+        let posts = [
+            post("recent", Some("2026-05-01T00:00:00Z")),
+            post("unset", None),
+            post("future", Some("2027-01-01T00:00:00Z")),
+            post("oldest", Some("2025-01-01T00:00:00Z")),
+        ];
+        let slugs: Vec<&str> = overdue(&posts, now).iter().map(|p| p.meta.slug.as_str()).collect();
+        assert_eq!(slugs, ["oldest", "recent"]);
+        assert_eq!(frontmatter_line("---\ntitle: x\nreview_by: 2025-01-01\n---\n"), Some(3));
+    }
+
+    #[test]
+    fn test_banner_goes_after_the_article_tag() {
+        let date: DateTime<Utc> = "2025-01-01T12:00:00Z".parse().unwrap();
+        let fragment = banner("<p class=\"outdated\">Due for review since {{ review_by }}</p>", date, Tz::UTC);
+        let html = "<body><nav></nav><ARTICLE class=\"post\"><h1>T</h1></article></body>";
+        assert_eq!(
+            insert_after_open(html, &["article", "main", "body"], &fragment).unwrap(),
+            "<body><nav></nav><ARTICLE class=\"post\"><p class=\"outdated\">Due for review since 2025-01-01</p><h1>T</h1></article></body>"
+        );
+        assert_eq!(insert_after_open("<body><aside>x</aside></body>", &["a", "body"], "!").unwrap(), "<body>!<aside>x</aside></body>");
+        assert!(insert_after_open("<p>no body</p>", &["body"], "!").is_none());
+    }
+}
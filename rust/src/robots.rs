@@ -0,0 +1,96 @@
+//! Per-page robots directives from frontmatter
+//!
+//! `noindex`, `nofollow` and `noarchive` become a `<meta name="robots">` tag
+//! on the post's page. A `noindex` post is still published and linked from
+//! the site, but left out of the feeds and removed from `sitemap.xml`.
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::fs;
+
+use crate::{inject, Config, Post, PostMeta};
+
+/// `<url>` entries of a sitemap with their `<loc>`
+static SITEMAP_URL: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)[ \t]*<url>\s*<loc>(.*?)</loc>.*?</url>[ \t]*\n?").unwrap());
+
+/// Directives set in the frontmatter, in `content` order
+pub fn directives(meta: &PostMeta) -> Vec<&'static str> {
+    [(meta.noindex, "noindex"), (meta.nofollow, "nofollow"), (meta.noarchive, "noarchive")]
+        .into_iter()
+        .filter_map(|(set, directive)| set.then_some(directive))
+        .collect()
+}
+
+/// Whether a post belongs in feeds and the sitemap (never when protected)
+pub fn is_listed(meta: &PostMeta) -> bool {
+    !meta.noindex && !meta.protected
+}
+
+/// `<meta name="robots">` for a post with any directive
+pub fn meta_tag(meta: &PostMeta) -> Option<String> {
+    let directives = directives(meta);
+    (!directives.is_empty()).then(|| format!("<meta name=\"robots\" content=\"{}\">\n", directives.join(", ")))
+}
+
+/// The `<loc>` of every `<url>` entry of a sitemap
+pub fn sitemap_urls(xml: &str) -> Vec<String> {
+    SITEMAP_URL.captures_iter(xml).map(|capture| capture[1].trim().replace("&amp;", "&")).collect()
+}
+
+/// Sitemap without the entries whose `<loc>` is in `unlisted`
+pub fn prune_sitemap(xml: &str, unlisted: &[String]) -> String {
+    SITEMAP_URL
+        .replace_all(xml, |capture: &regex::Captures| {
+            let loc = capture[1].trim().replace("&amp;", "&");
+            if unlisted.contains(&loc) {
+                String::new()
+            } else {
+                capture[0].to_string()
+            }
+        })
+        .into_owned()
+}
+
+/// Add robots tags to post pages and drop `noindex` posts from the sitemap
+pub fn apply(config: &Config, posts: &[Post]) -> Result<()> {
+    for post in posts {
+        if let Some(tag) = meta_tag(&post.meta) {
+            inject::inject_into_file(&config.output.join(post.path()), &["</head>"], &tag)?;
+        }
+    }
+
+    let unlisted: Vec<String> =
+        posts.iter().filter(|p| !is_listed(&p.meta)).map(|p| p.permalink(&config.url)).collect();
+    let sitemap = config.output.join("sitemap.xml");
+    if unlisted.is_empty() || !sitemap.is_file() {
+        return Ok(());
+    }
+    let xml = fs::read_to_string(&sitemap).with_context(|| format!("Failed to read {}", sitemap.display()))?;
+    fs::write(&sitemap, prune_sitemap(&xml, &unlisted)).with_context(|| format!("Failed to write {}", sitemap.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_meta_tag() {
+        let mut meta = PostMeta::default();
+        assert_eq!(meta_tag(&meta), None);
+        meta.noindex = true;
+        meta.noarchive = true;
+        assert_eq!(meta_tag(&meta).unwrap(), "<meta name=\"robots\" content=\"noindex, noarchive\">\n");
+        assert!(!is_listed(&meta));
+    }
+
+    #[test]
+    fn test_prune_sitemap() {
+        let xml = "<urlset>\n  <url>\n    <loc>https://example.com/a.html</loc>\n    <lastmod>2024-05-09</lastmod>\n  </url>\n  \
+                   <url><loc>https://example.com/b.html</loc></url>\n</urlset>\n";
+        assert_eq!(
+            prune_sitemap(xml, &["https://example.com/a.html".to_string()]),
+            "<urlset>\n  <url><loc>https://example.com/b.html</loc></url>\n</urlset>\n"
+        );
+    }
+}
@@ -1,37 +1,143 @@
 //! Security validation and sanitization module
 
+use aho_corasick::AhoCorasick;
 use anyhow::{Context, Result};
 use once_cell::sync::Lazy;
-use regex::Regex;
+use regex::{Regex, RegexSet};
+use std::fmt;
 use std::path::Path;
 use tracing::{error, warn};
 use walkdir::WalkDir;
 
-use crate::SecurityPolicy;
+use crate::lint::Severity;
+use crate::{normalize, preview, sniff, Check, SecurityPolicy};
+
+/// Patterns of JavaScript and other active content
+const JS_SOURCES: [&str; 14] = [
+    r"<script\b",
+    r"javascript:",
+    r"on\w+\s*=", // onclick, onload, etc.
+    r"<iframe\b",
+    r"<object\b",
+    r"<embed\b",
+    r"<applet\b",
+    r"eval\s*\(",
+    r"Function\s*\(",
+    r"setTimeout\s*\(",
+    r"setInterval\s*\(",
+    r"\.innerHTML\s*=",
+    r"document\.write",
+    r"window\.location",
+];
 
 /// Regex patterns for detecting JavaScript and other security issues
-static JS_PATTERNS: Lazy<Vec<Regex>> = Lazy::new(|| {
-    vec![
-        Regex::new(r"<script\b").unwrap(),
-        Regex::new(r"javascript:").unwrap(),
-        Regex::new(r"on\w+\s*=").unwrap(), // onclick, onload, etc.
-        Regex::new(r"<iframe\b").unwrap(),
-        Regex::new(r"<object\b").unwrap(),
-        Regex::new(r"<embed\b").unwrap(),
-        Regex::new(r"<applet\b").unwrap(),
-        Regex::new(r"eval\s*\(").unwrap(),
-        Regex::new(r"Function\s*\(").unwrap(),
-        Regex::new(r"setTimeout\s*\(").unwrap(),
-        Regex::new(r"setInterval\s*\(").unwrap(),
-        Regex::new(r"\.innerHTML\s*=").unwrap(),
-        Regex::new(r"document\.write").unwrap(),
-        Regex::new(r"window\.location").unwrap(),
-    ]
+static JS_PATTERNS: Lazy<Vec<Regex>> = Lazy::new(|| JS_SOURCES.iter().map(|p| Regex::new(p).unwrap()).collect());
+
+/// All of [`JS_PATTERNS`] in one pass; only the patterns that matched are run again for their line
+static JS_SET: Lazy<RegexSet> = Lazy::new(|| RegexSet::new(JS_SOURCES).unwrap());
+
+/// Inline `style` attributes
+static INLINE_STYLE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"style\s*=\s*["'][^"']*["']"#).unwrap());
+
+/// Absolute `src`/`href` URLs
+static EXTERNAL_RESOURCE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(src|href)\s*=\s*["'](https?://[^"']+)["']"#).unwrap());
+
+/// Script in stylesheets
+static CSS_SCRIPT: Lazy<Regex> = Lazy::new(|| Regex::new(r"javascript:|expression\s*\(|behavior\s*:").unwrap());
+
+/// Absolute `@import`s
+static CSS_IMPORT: Lazy<Regex> = Lazy::new(|| Regex::new(r#"@import\s+["']?(https?://[^"']+)"#).unwrap());
+
+/// Literals the HTML checks need, found in a single scan of the page
+///
+/// Every match of the script block, inline style, data: URI and external
+/// resource patterns contains one of these, so a page without it skips the
+/// regex entirely; most pages contain none of them.
+const NEEDLES: [&str; 4] = ["<script", "style", "data:", "http"];
+
+/// Case-insensitive scanner for [`NEEDLES`]
+static PREFILTER: Lazy<AhoCorasick> =
+    Lazy::new(|| AhoCorasick::builder().ascii_case_insensitive(true).build(NEEDLES).unwrap());
+
+/// Which of [`NEEDLES`] occur in `content`
+fn needles(content: &str) -> [bool; NEEDLES.len()] {
+    let mut present = [false; NEEDLES.len()];
+    for found in PREFILTER.find_iter(content) {
+        present[found.pattern().as_usize()] = true;
+        if present.iter().all(|&p| p) {
+            break;
+        }
+    }
+    present
+}
+
+/// JSON-LD data blocks (structured data, never executed)
+static JSON_LD_BLOCK: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)<script\s+type\s*=\s*["']application/ld\+json["']\s*>(.*?)</script>"#).unwrap()
 });
 
+/// Remove JSON-LD blocks whose body is valid JSON so they are not flagged as scripts
+fn strip_json_ld(content: &str) -> std::borrow::Cow<'_, str> {
+    JSON_LD_BLOCK.replace_all(content, |cap: &regex::Captures<'_>| {
+        if serde_json::from_str::<serde_json::Value>(&cap[1]).is_ok() {
+            // Keep the line count so reported line numbers still match the file
+            "\n".repeat(cap[0].matches('\n').count())
+        } else {
+            cap[0].to_string()
+        }
+    })
+}
+
+/// `data:` URIs in attributes and `url()`
+static DATA_URI: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)(?:\b(?:src|href|srcset|poster|data)\s*=\s*["']?|url\(\s*["']?)\s*data:"#).unwrap()
+});
+
+/// A security problem in an output file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// Check that found it
+    pub check: Check,
+    /// File relative to the output directory
+    pub file: String,
+    /// 1-based line of the first occurrence, when known
+    pub line: Option<usize>,
+    /// Description of the problem
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} in {}", self.message, self.file)
+    }
+}
+
+/// 1-based line containing byte `offset`
+fn line_at(content: &str, offset: usize) -> usize {
+    content[..offset].matches('\n').count() + 1
+}
+
 /// Validate that output directory contains no JavaScript or security issues
 pub fn validate_output(output_dir: &Path, policy: &SecurityPolicy) -> Result<()> {
+    let violations = output_violations(output_dir, policy)?;
+
+    if !violations.is_empty() {
+        error!("Security violations detected:");
+        for violation in &violations {
+            error!("  - {}", violation);
+        }
+        anyhow::bail!("Security validation failed with {} violations", violations.len());
+    }
+
+    Ok(())
+}
+
+/// Every security violation in the output directory, naming files relative to it
+///
+/// Violations of checks the policy downgrades to warnings are logged, not returned.
+pub fn output_violations(output_dir: &Path, policy: &SecurityPolicy) -> Result<Vec<Violation>> {
     let mut violations = Vec::new();
+    let mut warnings = Vec::new();
 
     for entry in WalkDir::new(output_dir)
         .into_iter()
@@ -39,68 +145,133 @@ pub fn validate_output(output_dir: &Path, policy: &SecurityPolicy) -> Result<()>
         .filter(|e| e.file_type().is_file())
     {
         let path = entry.path();
-        
+        let shown = normalize::slashed(path.strip_prefix(output_dir).unwrap_or(path));
+        let policy = policy.for_path(&shown);
+        let mut found = Vec::new();
+
+        // Bytes must match the extension, whatever the file (only its start and end are read)
+        let sample = sniff::sample(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        if let Some(message) = sniff::mismatch(&shown, &sample.head) {
+            found.push(Violation { check: Check::Mime, file: shown.clone(), line: None, message });
+        }
+        if let Some(message) = sniff::polyglot(&sample.head, &sample.tail) {
+            found.push(Violation { check: Check::Polyglot, file: shown.clone(), line: None, message });
+        }
+        if let Some(message) = sniff::dangerous_type(&shown, &policy.dangerous_extensions, &policy.image_dirs) {
+            found.push(Violation { check: Check::FileType, file: shown.clone(), line: None, message });
+        }
+
         // Only check HTML/CSS/JS files
         let ext = path.extension().and_then(|s| s.to_str());
         match ext {
             Some("html") | Some("htm") => {
-                validate_html_file(path, policy, &mut violations)?;
+                validate_html_file(path, &shown, &policy, &mut found)?;
             }
             Some("css") => {
-                validate_css_file(path, policy, &mut violations)?;
+                validate_css_file(path, &shown, &policy, &mut found)?;
             }
             Some("js") if policy.no_javascript => {
-                violations.push(format!("JavaScript file found: {}", path.display()));
+                found.push(Violation {
+                    check: Check::Javascript,
+                    file: shown,
+                    line: None,
+                    message: "JavaScript file found".to_string(),
+                });
             }
             _ => {}
         }
-    }
 
-    if !violations.is_empty() {
-        error!("Security violations detected:");
-        for violation in &violations {
-            error!("  - {}", violation);
-        }
-        anyhow::bail!("Security validation failed with {} violations", violations.len());
+        let (errors, downgraded): (Vec<_>, Vec<_>) =
+            found.into_iter().partition(|v| policy.severity(v.check) == Severity::Error);
+        violations.extend(errors);
+        warnings.extend(downgraded);
     }
 
-    Ok(())
+    for warning in &warnings {
+        warn!("{}", warning);
+    }
+    Ok(violations)
 }
 
 /// Validate HTML file for security issues
-fn validate_html_file(path: &Path, policy: &SecurityPolicy, violations: &mut Vec<String>) -> Result<()> {
+fn validate_html_file(path: &Path, shown: &str, policy: &SecurityPolicy, violations: &mut Vec<Violation>) -> Result<()> {
     let content = std::fs::read_to_string(path)
         .with_context(|| format!("Failed to read HTML file: {}", path.display()))?;
 
+    // Check page size
+    if policy.max_page_size > 0 && content.len() > policy.max_page_size {
+        violations.push(Violation {
+            check: Check::PageSize,
+            file: shown.to_string(),
+            line: None,
+            message: format!("Page of {} bytes exceeds the {} byte limit", content.len(), policy.max_page_size),
+        });
+    }
+
+    let [script, style, data, http] = needles(&content);
+
     // Check for JavaScript patterns
     if policy.no_javascript {
-        for pattern in JS_PATTERNS.iter() {
-            if pattern.is_match(&content) {
-                violations.push(format!(
-                    "JavaScript pattern '{}' found in {}",
-                    pattern.as_str(),
-                    path.display()
-                ));
+        let scripted = if script { strip_json_ld(&content) } else { std::borrow::Cow::Borrowed(content.as_str()) };
+        for index in &JS_SET.matches(&scripted) {
+            let pattern = &JS_PATTERNS[index];
+            if let Some(found) = pattern.find(&scripted) {
+                violations.push(Violation {
+                    check: Check::Javascript,
+                    file: shown.to_string(),
+                    line: Some(line_at(&scripted, found.start())),
+                    message: format!("JavaScript pattern '{}' found", pattern.as_str()),
+                });
             }
         }
     }
 
+    // Refresh tags belong to preview builds only
+    if let Some(found) = preview::refresh_at(&content) {
+        violations.push(Violation {
+            check: Check::MetaRefresh,
+            file: shown.to_string(),
+            line: Some(line_at(&content, found)),
+            message: "meta refresh found".to_string(),
+        });
+    }
+
+    // Check for data: URIs
+    if policy.no_data_uris && data {
+        if let Some(found) = DATA_URI.find(&content) {
+            violations.push(Violation {
+                check: Check::DataUri,
+                file: shown.to_string(),
+                line: Some(line_at(&content, found.start())),
+                message: "data: URI found".to_string(),
+            });
+        }
+    }
+
     // Check for inline styles
-    if policy.no_inline_styles {
-        let style_regex = Regex::new(r#"style\s*=\s*["'][^"']*["']"#).unwrap();
-        if style_regex.is_match(&content) {
-            violations.push(format!("Inline styles found in {}", path.display()));
+    if policy.no_inline_styles && style {
+        if let Some(found) = INLINE_STYLE.find(&content) {
+            violations.push(Violation {
+                check: Check::InlineStyles,
+                file: shown.to_string(),
+                line: Some(line_at(&content, found.start())),
+                message: "Inline styles found".to_string(),
+            });
         }
     }
 
     // Check for external resources
-    if policy.no_external {
-        let external_regex = Regex::new(r#"(src|href)\s*=\s*["'](https?://[^"']+)["']"#).unwrap();
-        for cap in external_regex.captures_iter(&content) {
+    if policy.no_external && http {
+        for cap in EXTERNAL_RESOURCE.captures_iter(&content) {
             let url = &cap[2];
-            // Allow same-origin resources
-            if !url.starts_with('/') && !url.starts_with('#') {
-                violations.push(format!("External resource '{}' in {}", url, path.display()));
+            // Allow same-origin resources and allowlisted hosts
+            if !url.starts_with('/') && !url.starts_with('#') && !policy.allows_url(url) {
+                violations.push(Violation {
+                    check: Check::External,
+                    file: shown.to_string(),
+                    line: Some(line_at(&content, cap.get(0).map_or(0, |m| m.start()))),
+                    message: format!("External resource '{url}'"),
+                });
             }
         }
     }
@@ -109,28 +280,89 @@ fn validate_html_file(path: &Path, policy: &SecurityPolicy, violations: &mut Vec
 }
 
 /// Validate CSS file for security issues
-fn validate_css_file(path: &Path, policy: &SecurityPolicy, violations: &mut Vec<String>) -> Result<()> {
+fn validate_css_file(path: &Path, shown: &str, policy: &SecurityPolicy, violations: &mut Vec<Violation>) -> Result<()> {
     let content = std::fs::read_to_string(path)
         .with_context(|| format!("Failed to read CSS file: {}", path.display()))?;
 
+    for (check, message) in css_violations(&content, policy) {
+        violations.push(Violation { check, file: shown.to_string(), line: None, message });
+    }
+
+    Ok(())
+}
+
+/// Security problems in a stylesheet (JavaScript, external imports)
+pub fn css_violations(content: &str, policy: &SecurityPolicy) -> Vec<(Check, String)> {
+    let mut violations = Vec::new();
+
     // Check for JavaScript in CSS
-    if policy.no_javascript {
-        let js_in_css = Regex::new(r"javascript:|expression\s*\(|behavior\s*:").unwrap();
-        if js_in_css.is_match(&content) {
-            violations.push(format!("JavaScript in CSS found in {}", path.display()));
-        }
+    if policy.no_javascript && CSS_SCRIPT.is_match(content) {
+        violations.push((Check::Javascript, "JavaScript in CSS found".to_string()));
+    }
+
+    // Check for inline data: fonts and images
+    if policy.no_data_uris && DATA_URI.is_match(content) {
+        violations.push((Check::DataUri, "data: URI in CSS found".to_string()));
     }
 
     // Check for external imports
     if policy.no_external {
-        let import_regex = Regex::new(r"@import\s+[\"']?(https?://[^\"']+)").unwrap();
-        for cap in import_regex.captures_iter(&content) {
-            let url = &cap[1];
-            violations.push(format!("External CSS import '{}' in {}", url, path.display()));
+        for cap in CSS_IMPORT.captures_iter(content).filter(|cap| !policy.allows_url(&cap[1])) {
+            violations.push((Check::External, format!("External CSS import '{}'", &cap[1])));
         }
     }
 
-    Ok(())
+    violations
+}
+
+/// Escape text for HTML element content and attribute values
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// `rel` tokens a policy may add to external links
+pub const EXTRA_LINK_REL: [&str; 3] = ["nofollow", "ugc", "sponsored"];
+
+/// An `<a>` start tag as the sanitizer serializes it (attributes always double-quoted)
+static ANCHOR: Lazy<Regex> = Lazy::new(|| Regex::new(r#"<a((?:\s+[^\s="/>]+="[^"]*")*)>"#).unwrap());
+
+/// An `href` leaving the site: absolute `http(s)` or protocol-relative
+static EXTERNAL_HREF: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\shref="(?i:https?:)?//"#).unwrap());
+
+/// `rel` value of external links under a policy
+pub fn external_link_rel(policy: &SecurityPolicy) -> String {
+    let mut rel = vec!["noopener", "noreferrer"];
+    for token in &policy.external_link_rel {
+        if !rel.contains(&token.as_str()) {
+            rel.push(token);
+        }
+    }
+    rel.join(" ")
+}
+
+/// Add `rel` to the external links of sanitized HTML (the sanitizer has already dropped any `rel`)
+fn add_external_link_rel(html: &str, policy: &SecurityPolicy) -> String {
+    let rel = external_link_rel(policy);
+    ANCHOR
+        .replace_all(html, |capture: &regex::Captures| {
+            if EXTERNAL_HREF.is_match(&capture[1]) {
+                format!("<a{} rel=\"{rel}\">", &capture[1])
+            } else {
+                capture[0].to_string()
+            }
+        })
+        .into_owned()
 }
 
 /// Sanitize HTML content using ammonia
@@ -146,10 +378,20 @@ pub fn sanitize_html(html: &str, policy: &SecurityPolicy) -> String {
         "table", "thead", "tbody", "tr", "th", "td",
         "hr", "div", "span", "article", "section",
         "header", "footer", "nav", "aside", "main",
+        "ins", "del",
     ].iter().copied().collect();
 
     builder.tags(allowed_tags);
 
+    // `> [!NOTE]` callouts: fixed classes and ARIA attributes only
+    builder.add_tag_attributes("aside", &["role", "aria-label"]);
+    builder.add_allowed_classes("aside", &crate::admonitions::ASIDE_CLASSES);
+    builder.add_allowed_classes("p", &[crate::admonitions::TITLE_CLASS]);
+
+    // Diff blocks: language class on `<code>`, header and hunk spans
+    builder.add_allowed_classes("code", &crate::diff::CODE_CLASSES);
+    builder.add_allowed_classes("span", &crate::diff::SPAN_CLASSES);
+
     // Remove all event handlers
     builder.rm_tag_attributes("*", &[
         "onclick", "onload", "onerror", "onmouseover", "onmouseout",
@@ -167,7 +409,10 @@ pub fn sanitize_html(html: &str, policy: &SecurityPolicy) -> String {
         builder.rm_tag_attributes("*", &["style"]);
     }
 
-    builder.clean(html).to_string()
+    // `rel` only on links leaving the site, added below from the policy
+    builder.link_rel(None);
+
+    add_external_link_rel(&builder.clean(html).to_string(), policy)
 }
 
 #[cfg(test)]
@@ -200,6 +445,103 @@ mod tests {
         assert!(!clean.contains("javascript:"));
     }
 
+    #[test]
+    fn test_sanitize_html_sets_rel_on_external_links() {
+        let policy = SecurityPolicy { external_link_rel: vec!["nofollow".to_string()], ..SecurityPolicy::default() };
+        let dirty = r#"<a href="https://example.org/" rel="opener">x</a> <a title="a>b" href="//cdn.test/">y</a> <a href="/about.html">z</a>"#;
+        assert_eq!(
+            sanitize_html(dirty, &policy),
+            r#"<a href="https://example.org/" rel="noopener noreferrer nofollow">x</a> <a title="a&gt;b" href="//cdn.test/" rel="noopener noreferrer nofollow">y</a> <a href="/about.html">z</a>"#
+        );
+    }
+
+    #[test]
+    fn test_strip_json_ld_keeps_invalid_blocks() {
+        let data = r#"<script type="application/ld+json">{"@type":"WebSite"}</script><p>x</p>"#;
+        assert_eq!(strip_json_ld(data), "<p>x</p>");
+        let code = r#"<script type="application/ld+json">alert(1)</script>"#;
+        assert!(JS_PATTERNS[0].is_match(&strip_json_ld(code)));
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html(r#"<a href="x">'&'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;&#39;&amp;&#39;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn test_json_ld_stripping_keeps_line_numbers() {
+        let page = "<head>\n<script type=\"application/ld+json\">\n{}\n</script>\n</head>\n<script>x</script>";
+        let scripted = strip_json_ld(page);
+        let found = JS_PATTERNS[0].find(&scripted).unwrap();
+        assert_eq!(line_at(&scripted, found.start()), 6);
+    }
+
+    #[test]
+    fn test_policy_severity_and_allowlist() {
+        let dir = std::env::temp_dir().join(format!("secureblog-security-policy-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let page = r#"<p style="color:red"><img src="https://img.example.org/a.png"><img src="https://evil.test/b.png"></p>"#;
+        std::fs::write(dir.join("index.html"), page).unwrap();
+
+        let policy = SecurityPolicy {
+            no_inline_styles: true,
+            allowed_hosts: vec!["*.example.org".to_string()],
+            max_page_size: 16,
+            severity: [(Check::InlineStyles, Severity::Warning)].into_iter().collect(),
+            ..SecurityPolicy::default()
+        };
+        let violations = output_violations(&dir, &policy).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let checks: Vec<Check> = violations.iter().map(|v| v.check).collect();
+        assert_eq!(checks, [Check::PageSize, Check::External]);
+        assert_eq!(violations[1].message, "External resource 'https://evil.test/b.png'");
+    }
+
+    #[test]
+    fn test_section_override_relaxes_one_page() {
+        let dir = std::env::temp_dir().join(format!("secureblog-security-overrides-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("labs")).unwrap();
+        std::fs::write(dir.join("index.html"), r#"<p style="color:red">x</p>"#).unwrap();
+        std::fs::write(dir.join("labs/a.html"), r#"<p style="color:red">x</p>"#).unwrap();
+
+        let policy: SecurityPolicy =
+            serde_yaml::from_str("no_inline_styles: true\noverrides:\n  index.html: { no_inline_styles: false }\n").unwrap();
+        let violations = output_violations(&dir, &policy).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].file, "labs/a.html");
+    }
+
+    #[test]
+    fn test_data_uri_detection() {
+        assert!(DATA_URI.is_match(r#"<img src="data:image/png;base64,AA">"#));
+        assert!(DATA_URI.is_match("background: url( 'data:font/woff2;base64,AA')"));
+        assert!(!DATA_URI.is_match(r#"<p data-x="1">metadata: none</p>"#));
+        let policy = SecurityPolicy { no_data_uris: true, ..SecurityPolicy::default() };
+        assert_eq!(css_violations("a{background:url(data:x)}", &policy)[0].0, Check::DataUri);
+    }
+
+    #[test]
+    fn test_paranoid_output_rejects_data_uris() {
+        let dir = std::env::temp_dir().join(format!("secureblog-security-data-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("index.html"), r#"<p><img src="data:image/png;base64,AA" alt=""></p>"#).unwrap();
+        std::fs::write(dir.join("style.css"), "a{background:url('data:image/gif;base64,AA')}").unwrap();
+
+        let violations = output_violations(&dir, &crate::PolicyPreset::Paranoid.policy()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let mut files: Vec<&str> =
+            violations.iter().filter(|v| v.check == Check::DataUri).map(|v| v.file.as_str()).collect();
+        files.sort_unstable();
+        assert_eq!(files, ["index.html", "style.css"]);
+    }
+
     #[test]
     fn test_js_pattern_detection() {
         let patterns = &*JS_PATTERNS;
@@ -207,5 +549,13 @@ mod tests {
         assert!(patterns.iter().any(|p| p.is_match("javascript:void(0)")));
         assert!(patterns.iter().any(|p| p.is_match("onclick='alert()'")));
         assert!(patterns.iter().any(|p| p.is_match("<iframe src=")));
+        assert_eq!(JS_SET.matches("<p onclick=x>").iter().collect::<Vec<_>>(), [2]);
+    }
+
+    #[test]
+    fn test_needles_prefilter() {
+        assert_eq!(needles("<p>plain text</p>"), [false; 4]);
+        assert_eq!(needles(r#"<SCRIPT type="x"><img src="HTTPS://a" STYLE="">"#), [true, true, false, true]);
+        assert_eq!(needles("url(Data:x)"), [false, false, true, false]);
     }
 }
\ No newline at end of file
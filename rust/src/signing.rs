@@ -0,0 +1,322 @@
+//! Signed-commit enforcement: every published post's latest commit must carry
+//! an SSH signature from an allowlisted key
+//!
+//! Signatures are verified in-process against a git `allowed_signers` file, so
+//! the build does not depend on the local git or gpg configuration.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::Deserialize;
+use ssh_key::{HashAlg, PublicKey, SshSig};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{error, info};
+
+use crate::{git, Post};
+
+/// Namespace git uses for SSH commit signatures
+const GIT_NAMESPACE: &str = "git";
+
+/// Signed-commit policy settings
+#[derive(Debug, Clone, Deserialize)]
+pub struct SigningPolicy {
+    /// Trusted keys in `ssh-keygen` / git `allowed_signers` format
+    pub allowed_signers: PathBuf,
+}
+
+/// A trusted key and the principal it belongs to
+#[derive(Debug, Clone)]
+pub struct AllowedSigner {
+    /// Principal (usually an email address)
+    pub principal: String,
+    /// Public key
+    pub key: PublicKey,
+    /// `namespaces=` the key may sign in (any when absent)
+    pub namespaces: Option<Vec<String>>,
+    /// `valid-after=`: signatures made earlier are rejected
+    pub valid_after: Option<DateTime<Utc>>,
+    /// `valid-before=`: signatures made later are rejected
+    pub valid_before: Option<DateTime<Utc>>,
+}
+
+impl AllowedSigner {
+    /// Signer with no restrictions
+    pub fn new(principal: &str, key: PublicKey) -> Self {
+        Self { principal: principal.to_string(), key, namespaces: None, valid_after: None, valid_before: None }
+    }
+
+    /// Whether the key may sign in `namespace` at `time`
+    pub fn valid_for(&self, namespace: &str, time: DateTime<Utc>) -> bool {
+        self.namespaces.as_ref().is_none_or(|names| names.iter().any(|name| name == namespace))
+            && self.valid_after.is_none_or(|after| time >= after)
+            && self.valid_before.is_none_or(|before| time < before)
+    }
+}
+
+/// Outcome of checking one commit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// Valid signature from the named principal
+    Trusted(String),
+    /// No signature at all
+    Unsigned,
+    /// OpenPGP or S/MIME signature, which is not verified
+    Unsupported,
+    /// Valid format but the key (fingerprint) is not allowlisted
+    Untrusted(String),
+    /// Malformed signature or verification failure
+    Invalid(String),
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Trusted(principal) => write!(f, "signed by {principal}"),
+            Self::Unsigned => f.write_str("commit is not signed"),
+            Self::Unsupported => f.write_str("only SSH commit signatures are supported"),
+            Self::Untrusted(fingerprint) => write!(f, "signing key {fingerprint} is not in allowed_signers"),
+            Self::Invalid(reason) => write!(f, "invalid signature: {reason}"),
+        }
+    }
+}
+
+/// Options field split on the commas outside quotes
+fn split_options(field: &str) -> Vec<&str> {
+    let mut options = Vec::new();
+    let (mut start, mut quoted) = (0, false);
+    for (i, c) in field.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                options.push(&field[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    options.push(&field[start..]);
+    options
+}
+
+/// `valid-after`/`valid-before` time: `YYYYMMDD[HHMM[SS]]`, read as UTC (a trailing `Z` is accepted)
+fn parse_validity(value: &str) -> Result<DateTime<Utc>> {
+    let digits = value.strip_suffix(['Z', 'z']).unwrap_or(value);
+    let padded = match digits.len() {
+        8 => format!("{digits}000000"),
+        12 => format!("{digits}00"),
+        14 => digits.to_string(),
+        _ => anyhow::bail!("invalid time {value:?}"),
+    };
+    let time = NaiveDateTime::parse_from_str(&padded, "%Y%m%d%H%M%S").with_context(|| format!("invalid time {value:?}"))?;
+    Ok(time.and_utc())
+}
+
+/// Apply one key option to `signer`; options this verifier cannot honour are errors
+fn apply_option(signer: &mut AllowedSigner, option: &str) -> Result<()> {
+    let (name, value) = option.split_once('=').unwrap_or((option, ""));
+    let value = value.trim_matches('"');
+    match name.to_ascii_lowercase().as_str() {
+        "namespaces" => {
+            let names: Vec<String> = value.split(',').map(str::trim).map(str::to_string).collect();
+            if names.iter().any(|name| name.is_empty() || name.contains(['*', '?', '!'])) {
+                anyhow::bail!("namespace patterns are not supported: {value:?}");
+            }
+            signer.namespaces = Some(names);
+        }
+        "valid-after" => signer.valid_after = Some(parse_validity(value)?),
+        "valid-before" => signer.valid_before = Some(parse_validity(value)?),
+        _ => anyhow::bail!("unsupported option {name:?}"),
+    }
+    Ok(())
+}
+
+/// Parse an `allowed_signers` file (`principals [options] key-type base64 [comment]`)
+///
+/// `namespaces=`, `valid-after=` and `valid-before=` are enforced by
+/// [`verify_signature`]; any other option (`cert-authority` included) is
+/// rejected rather than silently ignored.
+pub fn parse_allowed_signers(content: &str) -> Result<Vec<AllowedSigner>> {
+    let mut signers = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let key_start = fields
+            .iter()
+            .position(|f| f.starts_with("ssh-") || f.starts_with("ecdsa-") || f.starts_with("sk-"))
+            .filter(|&i| i > 0 && i + 1 < fields.len())
+            .with_context(|| format!("allowed_signers line {}: missing principal or key", number + 1))?;
+        let key = PublicKey::from_openssh(&fields[key_start..=key_start + 1].join(" "))
+            .with_context(|| format!("allowed_signers line {}: invalid public key", number + 1))?;
+
+        let mut template = AllowedSigner::new("", key);
+        for option in fields[1..key_start].iter().flat_map(|field| split_options(field)) {
+            apply_option(&mut template, option).with_context(|| format!("allowed_signers line {}", number + 1))?;
+        }
+        for principal in fields[0].split(',') {
+            signers.push(AllowedSigner { principal: principal.to_string(), ..template.clone() });
+        }
+    }
+    Ok(signers)
+}
+
+/// Check a commit signature over `signed_data`, made at `time`, against the allowlist
+pub fn verify_signature(signature: &[u8], signed_data: &[u8], time: DateTime<Utc>, signers: &[AllowedSigner]) -> Verdict {
+    if !signature.starts_with(b"-----BEGIN SSH SIGNATURE-----") {
+        return Verdict::Unsupported;
+    }
+
+    let sig = match SshSig::from_pem(signature) {
+        Ok(sig) => sig,
+        Err(e) => return Verdict::Invalid(e.to_string()),
+    };
+    let matching: Vec<&AllowedSigner> = signers.iter().filter(|s| s.key.key_data() == sig.public_key()).collect();
+    if matching.is_empty() {
+        let fingerprint = PublicKey::from(sig.public_key().clone()).fingerprint(HashAlg::Sha256);
+        return Verdict::Untrusted(fingerprint.to_string());
+    }
+    let Some(signer) = matching.into_iter().find(|s| s.valid_for(GIT_NAMESPACE, time)) else {
+        return Verdict::Invalid(format!("key is not allowed to sign git commits at {}", time.to_rfc3339()));
+    };
+
+    match signer.key.verify(GIT_NAMESPACE, signed_data, &sig) {
+        Ok(()) => Verdict::Trusted(signer.principal.clone()),
+        Err(e) => Verdict::Invalid(e.to_string()),
+    }
+}
+
+/// Committer time of a commit, the time its signature is checked at
+pub fn commit_time(commit: &gix::Commit<'_>) -> Result<DateTime<Utc>> {
+    DateTime::from_timestamp(commit.time()?.seconds, 0).context("Commit time out of range")
+}
+
+/// Verify the signature of commit `id`
+fn commit_verdict(repo: &gix::Repository, id: gix::ObjectId, signers: &[AllowedSigner]) -> Result<Verdict> {
+    let commit = repo.find_commit(id)?;
+    let time = commit_time(&commit)?;
+    Ok(match commit.signature()? {
+        Some((signature, signed)) => verify_signature(&signature, &signed.to_bstring(), time, signers),
+        None => Verdict::Unsigned,
+    })
+}
+
+/// Refuse to publish posts whose latest commit is not signed by an allowlisted key
+pub fn enforce(posts: &[Post], policy: &SigningPolicy) -> Result<()> {
+    let content = fs::read_to_string(&policy.allowed_signers).with_context(|| {
+        format!("Failed to read allowed signers: {}", policy.allowed_signers.display())
+    })?;
+    let signers = parse_allowed_signers(&content)?;
+
+    let Some(first) = posts.first() else {
+        return Ok(());
+    };
+    let (repo, _) = git::open(first.source.parent().unwrap_or_else(|| Path::new(".")))?;
+    let sources: Vec<&Path> = posts.iter().map(|p| p.source.as_path()).collect();
+    let history = git::history_of(&sources)?;
+
+    let mut violations = 0;
+    for post in posts {
+        let verdict = match history.get(&post.source) {
+            Some(file) => commit_verdict(&repo, file.last.commit, &signers)?,
+            None => Verdict::Invalid("post is not committed".to_string()),
+        };
+        if let Verdict::Trusted(principal) = &verdict {
+            info!("🔏 {} signed by {}", post.source.display(), principal);
+        } else {
+            error!("Unsigned content: {}: {}", post.source.display(), verdict);
+            violations += 1;
+        }
+    }
+
+    if violations > 0 {
+        anyhow::bail!("{violations} posts failed the signed-commit policy");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALLOWED: &str = "# trusted contributors\nalice@example.com,alice namespaces=\"git\" ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIDyyOn4pd4irR3eS6ke1WW5BUHZ1X/qRKasby/KiNIBd laptop\n";
+
+    /// `ssh-keygen -Y sign -n git` over `SIGNED` with the key above
+    const SIGNATURE: &str = "-----BEGIN SSH SIGNATURE-----
+U1NIU0lHAAAAAQAAADMAAAALc3NoLWVkMjU1MTkAAAAgPLI6fil3iKtHd5LqR7VZbkFQdn
+Vf+pEpqxvL8qI0gF0AAAADZ2l0AAAAAAAAAAZzaGE1MTIAAABTAAAAC3NzaC1lZDI1NTE5
+AAAAQCmaTPxn/uomOwaYPv+xUYzYkoFcWqT9Ww3glvaXsznmMGSAtrAc7EGAOoEyrndGPK
+CWUfzp0vFN6D+Hdaex6Q0=
+-----END SSH SIGNATURE-----
+";
+
+    const SIGNED: &[u8] = b"tree 0\n\nSigned post\n";
+
+    fn at(date: &str) -> DateTime<Utc> {
+        parse_validity(date).unwrap()
+    }
+
+    #[test]
+    fn test_parse_allowed_signers() {
+        let signers = parse_allowed_signers(ALLOWED).unwrap();
+        let principals: Vec<&str> = signers.iter().map(|s| s.principal.as_str()).collect();
+        assert_eq!(principals, ["alice@example.com", "alice"]);
+        assert!(parse_allowed_signers("alice@example.com\n").is_err());
+    }
+
+    #[test]
+    fn test_allowed_signers_options() {
+        let key = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIDyyOn4pd4irR3eS6ke1WW5BUHZ1X/qRKasby/KiNIBd";
+        let signers =
+            parse_allowed_signers(&format!("a namespaces=\"file,git\",valid-after=20240101,valid-before=20250101Z {key}\n")).unwrap();
+        assert_eq!(signers[0].namespaces.as_deref(), Some(["file".to_string(), "git".to_string()].as_slice()));
+        assert!(signers[0].valid_for("git", at("20240601")));
+        assert!(!signers[0].valid_for("git", at("20231231")));
+        assert!(!signers[0].valid_for("git", at("20250101")));
+        assert!(!signers[0].valid_for("email", at("20240601")));
+
+        assert!(parse_allowed_signers(&format!("a cert-authority {key}\n")).is_err());
+        assert!(parse_allowed_signers(&format!("a namespaces=\"g*\" {key}\n")).is_err());
+        assert!(parse_allowed_signers(&format!("a valid-before=2025 {key}\n")).is_err());
+    }
+
+    #[test]
+    fn test_verify_signature_trusted_and_tampered() {
+        let signers = parse_allowed_signers(ALLOWED).unwrap();
+        assert_eq!(
+            verify_signature(SIGNATURE.as_bytes(), SIGNED, at("20240601"), &signers),
+            Verdict::Trusted("alice@example.com".to_string())
+        );
+        assert!(matches!(
+            verify_signature(SIGNATURE.as_bytes(), b"tree 0\n\nEdited post\n", at("20240601"), &signers),
+            Verdict::Invalid(_)
+        ));
+        assert!(matches!(verify_signature(SIGNATURE.as_bytes(), SIGNED, at("20240601"), &[]), Verdict::Untrusted(_)));
+    }
+
+    #[test]
+    fn test_verify_signature_outside_namespace_or_validity() {
+        let mut signers = parse_allowed_signers(ALLOWED).unwrap();
+        signers.truncate(1);
+        signers[0].valid_before = Some(at("20240101"));
+        assert!(matches!(
+            verify_signature(SIGNATURE.as_bytes(), SIGNED, at("20240601"), &signers),
+            Verdict::Invalid(_)
+        ));
+        signers[0].valid_before = None;
+        signers[0].namespaces = Some(vec!["file".to_string()]);
+        assert!(matches!(
+            verify_signature(SIGNATURE.as_bytes(), SIGNED, at("20240601"), &signers),
+            Verdict::Invalid(_)
+        ));
+    }
+
+    #[test]
+    fn test_pgp_signatures_unsupported() {
+        let pgp = b"-----BEGIN PGP SIGNATURE-----\n\niQ==\n-----END PGP SIGNATURE-----\n";
+        assert_eq!(verify_signature(pgp, SIGNED, at("20240601"), &[]), Verdict::Unsupported);
+    }
+}
@@ -0,0 +1,170 @@
+//! Where a finished site is written
+//!
+//! [`OutputSink`] takes output files by their site path (`2024/05/post/index.html`).
+//! [`Disk`] writes them under a directory (post bundles), [`Memory`] keeps
+//! them for tests and `build --dry-run`, and [`TarStream`] streams a
+//! deterministic tar archive to any writer (the site archive). [`copy_tree`]
+//! publishes a built tree into any of them, so exports, dry runs and tests
+//! share one code path.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::export::tar::TarWriter;
+use crate::{normalize, theme, vfs};
+
+/// Destination of output files
+pub trait OutputSink: Send + Sync {
+    /// Create or replace the file at `path`, relative to the site root with `/` separators
+    fn write(&self, path: &str, content: &[u8]) -> Result<()>;
+}
+
+/// Files under a directory on disk
+#[derive(Debug, Clone)]
+pub struct Disk {
+    /// Site root
+    pub root: PathBuf,
+}
+
+impl OutputSink for Disk {
+    fn write(&self, path: &str, content: &[u8]) -> Result<()> {
+        theme::check_template_name(path).with_context(|| format!("Invalid output path {path:?}"))?;
+        let target = self.root.join(path);
+        let files = vfs::current();
+        if let Some(parent) = target.parent() {
+            files.create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        files.write(&target, content).with_context(|| format!("Failed to write {}", target.display()))
+    }
+}
+
+/// Files kept in memory, sorted by path
+#[derive(Debug, Default)]
+pub struct Memory {
+    files: Mutex<BTreeMap<String, Vec<u8>>>,
+}
+
+impl Memory {
+    /// Every file written so far
+    pub fn files(&self) -> BTreeMap<String, Vec<u8>> {
+        self.files.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Files added, changed and removed relative to `before`, as `+ path`, `~ path` and `- path`
+    pub fn changes_from(&self, before: &Memory) -> Vec<String> {
+        let (before, after) = (before.files(), self.files());
+        let mut changes = Vec::new();
+        for (path, content) in &after {
+            match before.get(path) {
+                None => changes.push(format!("+ {path}")),
+                Some(old) if old != content => changes.push(format!("~ {path}")),
+                Some(_) => {}
+            }
+        }
+        changes.extend(before.keys().filter(|path| !after.contains_key(*path)).map(|path| format!("- {path}")));
+        changes
+    }
+}
+
+impl OutputSink for Memory {
+    fn write(&self, path: &str, content: &[u8]) -> Result<()> {
+        self.files.lock().unwrap_or_else(|e| e.into_inner()).insert(path.to_string(), content.to_vec());
+        Ok(())
+    }
+}
+
+/// A tar archive streamed to a writer, each file flushed as it is written
+pub struct TarStream<W: Write + Send> {
+    state: Mutex<(TarWriter, W)>,
+}
+
+impl<W: Write + Send> TarStream<W> {
+    /// Stream to `writer`; every entry carries `mtime`
+    pub fn new(writer: W, mtime: u64) -> Self {
+        Self { state: Mutex::new((TarWriter::new(mtime), writer)) }
+    }
+
+    /// Add a directory entry (`path` without trailing slash)
+    pub fn add_dir(&self, path: &str) -> Result<()> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let (tar, writer) = &mut *state;
+        tar.add_dir(path)?;
+        writer.write_all(&tar.drain()).with_context(|| format!("Failed to stream {path}/"))
+    }
+
+    /// Write the end-of-archive marker and return the writer
+    pub fn finish(self) -> Result<W> {
+        let (tar, mut writer) = self.state.into_inner().unwrap_or_else(|e| e.into_inner());
+        writer.write_all(&tar.finish())?;
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+impl<W: Write + Send> OutputSink for TarStream<W> {
+    fn write(&self, path: &str, content: &[u8]) -> Result<()> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let (tar, writer) = &mut *state;
+        tar.add_file(path, content)?;
+        writer.write_all(&tar.drain()).with_context(|| format!("Failed to stream {path}"))
+    }
+}
+
+/// Write every file under `dir` to `sink`, in path order; returns how many
+pub fn copy_tree(dir: &Path, sink: &dyn OutputSink) -> Result<usize> {
+    let files = vfs::current();
+    let paths = files.files(dir).with_context(|| format!("Failed to list {}", dir.display()))?;
+    for path in &paths {
+        let content = files.read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        sink.write(&normalize::relative(path, dir)?, &content)?;
+    }
+    Ok(paths.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::tar::read_files;
+
+    #[test]
+    fn test_memory_changes() {
+        let (before, after) = (Memory::default(), Memory::default());
+        before.write("index.html", b"old").unwrap();
+        before.write("gone.html", b"x").unwrap();
+        after.write("index.html", b"new").unwrap();
+        after.write("feeds/atom.xml", b"<feed/>").unwrap();
+        assert_eq!(after.changes_from(&before), ["+ feeds/atom.xml", "~ index.html", "- gone.html"]);
+        assert_eq!(after.files()["index.html"], b"new");
+    }
+
+    #[test]
+    fn test_tar_stream_round_trips() {
+        let stream = TarStream::new(Vec::new(), 0);
+        stream.write("index.html", b"<p>home</p>").unwrap();
+        stream.write("posts/a.html", b"<p>a</p>").unwrap();
+        let data = stream.finish().unwrap();
+        assert_eq!(
+            read_files(&data).unwrap(),
+            [("index.html".to_string(), b"<p>home</p>".to_vec()), ("posts/a.html".to_string(), b"<p>a</p>".to_vec())]
+        );
+    }
+
+    #[test]
+    fn test_disk_creates_parent_directories() {
+        let root = std::env::temp_dir().join(format!("secureblog-sink-dirs-{}", std::process::id()));
+        let disk = Disk { root: root.clone() };
+        disk.write("2024/05/post/index.html", b"<p>post</p>").unwrap();
+        assert_eq!(std::fs::read(root.join("2024/05/post/index.html")).unwrap(), b"<p>post</p>");
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_disk_rejects_escaping_paths() {
+        let disk = Disk { root: std::env::temp_dir().join(format!("secureblog-sink-{}", std::process::id())) };
+        assert!(disk.write("../escape.html", b"x").is_err());
+        assert!(disk.write("/etc/passwd", b"x").is_err());
+    }
+}
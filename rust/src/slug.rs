@@ -0,0 +1,82 @@
+//! URL slug generation
+//!
+//! Titles in other scripts either keep their letters (`unicode`) or are
+//! transliterated first: `ascii` for any script, `pinyin` for Chinese and
+//! `romaji` for Japanese kana, each falling back to `ascii` for the rest.
+
+use pinyin::ToPinyin;
+use serde::Deserialize;
+use wana_kana::ConvertJapanese;
+
+/// How non-Latin titles become slugs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transliteration {
+    /// Keep Unicode letters and digits (`привет-мир`)
+    #[default]
+    Unicode,
+    /// Closest ASCII spelling (`privet-mir`)
+    Ascii,
+    /// Hanzi as toneless pinyin syllables (`zhong-guo`)
+    Pinyin,
+    /// Hiragana and katakana as romaji (`toukyou`)
+    Romaji,
+}
+
+/// Slug of `text` in the given transliteration mode
+pub fn slugify_with(text: &str, mode: Transliteration) -> String {
+    match mode {
+        Transliteration::Unicode => slugify(text),
+        Transliteration::Ascii => slugify(&deunicode::deunicode(text)),
+        Transliteration::Pinyin => {
+            // One word per syllable, so `中国` becomes `zhong-guo` rather than `zhongguo`
+            let spelled: String = text
+                .chars()
+                .map(|c| c.to_pinyin().map_or_else(|| c.to_string(), |p| format!(" {} ", p.plain())))
+                .collect();
+            slugify(&deunicode::deunicode(&spelled))
+        }
+        Transliteration::Romaji => slugify(&deunicode::deunicode(&text.to_romaji())),
+    }
+}
+
+/// Lowercase slug of the alphanumeric runs in `text`, joined by single dashes
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    let mut pending_dash = false;
+
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            if pending_dash && !slug.is_empty() {
+                slug.push('-');
+            }
+            pending_dash = false;
+            slug.extend(c.to_lowercase());
+        } else {
+            pending_dash = true;
+        }
+    }
+
+    slug
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Hello, World!"), "hello-world");
+        assert_eq!(slugify("  Supply--Chain  "), "supply-chain");
+        assert_eq!(slugify("CVE-2024-3094"), "cve-2024-3094");
+    }
+
+    #[test]
+    fn test_transliteration_modes() {
+        assert_eq!(slugify_with("Привет, мир", Transliteration::Unicode), "привет-мир");
+        assert_eq!(slugify_with("Привет, мир", Transliteration::Ascii), "privet-mir");
+        assert_eq!(slugify_with("中国 CVE-2024", Transliteration::Pinyin), "zhong-guo-cve-2024");
+        assert_eq!(slugify_with("とうきょう", Transliteration::Romaji), "toukyou");
+        assert_eq!(slugify_with("Ünïcödé", Transliteration::Ascii), "unicode");
+    }
+}
@@ -0,0 +1,269 @@
+//! Content sniffing of output files
+//!
+//! Browsers and CDNs may trust a file's bytes over its extension, so a page
+//! saved as `photo.png` can still be rendered as HTML, and an executable can
+//! ride along under any name. The regex checks only read HTML and CSS; this
+//! pass reads the first bytes of every file and confirms they are what the
+//! extension claims. Files that are valid as two formats at once (a GIF that
+//! is also JavaScript, a PDF with an HTML prologue) and file types that are
+//! dangerous wherever they are served are rejected as well. Only a file's
+//! first kilobyte and its last 64 KiB are read, so large media never has to
+//! fit in memory.
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Bytes at the start of a file that are sniffed, as browsers do
+const HEAD: u64 = 1024;
+
+/// Bytes at the end that can hold a ZIP end-of-central-directory record (22 bytes plus the longest comment)
+const TAIL: u64 = 22 + 0xffff;
+
+/// The parts of a file the checks read
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Sample {
+    /// The first kilobyte
+    pub head: Vec<u8>,
+    /// The last 64 KiB or so (the whole file when it is small)
+    pub tail: Vec<u8>,
+}
+
+/// Read the start and end of the file at `path`, never more than about 65 KiB
+pub fn sample(path: &Path) -> io::Result<Sample> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let mut head = Vec::new();
+    (&mut file).take(HEAD).read_to_end(&mut head)?;
+    if len <= HEAD + TAIL {
+        file.rewind()?;
+        let mut tail = Vec::new();
+        file.read_to_end(&mut tail)?;
+        return Ok(Sample { head, tail });
+    }
+    file.seek(SeekFrom::End(-i64::try_from(TAIL).map_err(io::Error::other)?))?;
+    let mut tail = Vec::new();
+    file.take(TAIL).read_to_end(&mut tail)?;
+    Ok(Sample { head, tail })
+}
+
+/// What the first bytes of a file look like
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Png,
+    Jpeg,
+    Gif,
+    Webp,
+    Avif,
+    Ico,
+    Pdf,
+    Woff,
+    Woff2,
+    Gzip,
+    Zip,
+    /// ELF, PE, Mach-O, WebAssembly or a `#!` script
+    Executable,
+    /// HTML markup at the start of the file
+    Html,
+    /// Any other UTF-8 text
+    Text,
+    /// Unrecognized binary
+    Binary,
+}
+
+/// Classify content by its leading bytes
+pub fn sniff(content: &[u8]) -> Kind {
+    const SIGNATURES: [(&[u8], Kind); 18] = [
+        (b"\x89PNG\r\n\x1a\n", Kind::Png),
+        (b"\xff\xd8\xff", Kind::Jpeg),
+        (b"GIF87a", Kind::Gif),
+        (b"GIF89a", Kind::Gif),
+        (b"\x00\x00\x01\x00", Kind::Ico),
+        (b"%PDF-", Kind::Pdf),
+        (b"wOFF", Kind::Woff),
+        (b"wOF2", Kind::Woff2),
+        (b"\x1f\x8b", Kind::Gzip),
+        (b"PK\x03\x04", Kind::Zip),
+        (b"\x7fELF", Kind::Executable),
+        (b"MZ", Kind::Executable),
+        (b"\xfe\xed\xfa\xce", Kind::Executable),
+        (b"\xfe\xed\xfa\xcf", Kind::Executable),
+        (b"\xce\xfa\xed\xfe", Kind::Executable),
+        (b"\xcf\xfa\xed\xfe", Kind::Executable),
+        (b"\x00asm", Kind::Executable),
+        (b"#!", Kind::Executable),
+    ];
+    if let Some((_, kind)) = SIGNATURES.iter().find(|(magic, _)| content.starts_with(magic)) {
+        return *kind;
+    }
+    if content.len() >= 12 && &content[..4] == b"RIFF" && &content[8..12] == b"WEBP" {
+        return Kind::Webp;
+    }
+    if content.len() >= 12 && &content[4..8] == b"ftyp" && matches!(&content[8..12], b"avif" | b"avis") {
+        return Kind::Avif;
+    }
+    let head = &content[..content.len().min(1024)];
+    match std::str::from_utf8(head) {
+        // A multi-byte character cut at the window edge is still text
+        Ok(text) => classify_text(text),
+        Err(e) if e.error_len().is_none() => classify_text(std::str::from_utf8(&head[..e.valid_up_to()]).unwrap_or("")),
+        Err(_) => Kind::Binary,
+    }
+}
+
+fn classify_text(text: &str) -> Kind {
+    let start = text.trim_start_matches('\u{feff}').trim_start().to_ascii_lowercase();
+    if ["<!doctype html", "<html", "<head", "<body", "<script", "<iframe"].iter().any(|tag| start.starts_with(tag)) {
+        Kind::Html
+    } else {
+        Kind::Text
+    }
+}
+
+/// Whether a kind is a binary format a second format could hide in
+fn is_binary_format(kind: Kind) -> bool {
+    matches!(
+        kind,
+        Kind::Png | Kind::Jpeg | Kind::Gif | Kind::Webp | Kind::Avif | Kind::Ico | Kind::Pdf | Kind::Woff | Kind::Woff2
+    )
+}
+
+/// Why a binary file is also valid as another format, if it is
+///
+/// Only the first kilobyte (`head`) is searched for markup, which is what
+/// browsers sniff. A ZIP archive is only readable if its end-of-central-directory
+/// record is in the last 64 KiB, so only `tail` is searched for one.
+pub fn polyglot(head: &[u8], tail: &[u8]) -> Option<String> {
+    let kind = sniff(head);
+    if !is_binary_format(kind) {
+        return None;
+    }
+    // `GIF89a/*` starts a JavaScript comment that the image data closes
+    if kind == Kind::Gif && head.get(6..8) == Some(b"/*") {
+        return Some("GIF header is also the start of a script".to_string());
+    }
+    let text = String::from_utf8_lossy(&head[..head.len().min(1024)]).to_ascii_lowercase();
+    if let Some(tag) = ["<script", "<html", "<body", "<iframe", "<svg", "<!doctype html"].iter().find(|tag| text.contains(*tag)) {
+        return Some(format!("{kind:?} contains HTML markup ({tag})"));
+    }
+    if kind != Kind::Pdf && text.contains("%pdf-") {
+        return Some(format!("{kind:?} also contains a PDF header"));
+    }
+    if tail.windows(4).any(|window| window == b"PK\x05\x06") {
+        return Some(format!("{kind:?} also contains a ZIP archive"));
+    }
+    None
+}
+
+/// Why a file's type or location is dangerous regardless of its content, if it is
+///
+/// `dangerous` are extensions never published; HTML in one of `image_dirs`
+/// (any path segment) is rejected because uploads there are trusted as images.
+pub fn dangerous_type(path: &str, dangerous: &[String], image_dirs: &[String]) -> Option<String> {
+    let name = path.rsplit('/').next().unwrap_or(path).to_ascii_lowercase();
+    let ext = name.rsplit_once('.').map_or("", |(_, ext)| ext);
+    if dangerous.iter().any(|d| d.trim_start_matches('.').eq_ignore_ascii_case(ext)) {
+        return Some(format!(".{ext} files are not published"));
+    }
+    let html = matches!(ext, "html" | "htm" | "xhtml" | "xht" | "shtml");
+    let dirs: Vec<&str> = path.split('/').collect();
+    let in_image_dir = dirs[..dirs.len() - 1].iter().any(|dir| image_dirs.iter().any(|d| d.eq_ignore_ascii_case(dir)));
+    if html && in_image_dir {
+        return Some("HTML page in an image directory".to_string());
+    }
+    None
+}
+
+/// Why `content` (at least the first kilobyte) does not match the extension of `path`, if it does not
+pub fn mismatch(path: &str, content: &[u8]) -> Option<String> {
+    let kind = sniff(content);
+    if kind == Kind::Executable {
+        return Some("Executable content".to_string());
+    }
+    let ext = path.rsplit_once('.').map_or("", |(_, ext)| ext).to_ascii_lowercase();
+    let expected: &[Kind] = match ext.as_str() {
+        "png" => &[Kind::Png],
+        "jpg" | "jpeg" => &[Kind::Jpeg],
+        "gif" => &[Kind::Gif],
+        "webp" => &[Kind::Webp],
+        "avif" => &[Kind::Avif],
+        "ico" => &[Kind::Ico, Kind::Png],
+        "pdf" => &[Kind::Pdf],
+        "woff" => &[Kind::Woff],
+        "woff2" => &[Kind::Woff2],
+        "gz" => &[Kind::Gzip],
+        "html" | "htm" => &[Kind::Html, Kind::Text],
+        "css" | "txt" | "xml" | "json" | "webmanifest" | "svg" | "asc" | "md" | "csv" | "map" | "opml" | "atom"
+        | "rss" | "pub" => &[Kind::Text],
+        // Unknown extensions are only checked for executables
+        _ => return None,
+    };
+    if expected.contains(&kind) {
+        return None;
+    }
+    Some(format!("Content looks like {kind:?}, not .{ext}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff_signatures() {
+        assert_eq!(sniff(b"\x89PNG\r\n\x1a\n...."), Kind::Png);
+        assert_eq!(sniff(b"RIFF\x00\x00\x00\x00WEBPVP8 "), Kind::Webp);
+        assert_eq!(sniff(b"\x00\x00\x00\x1cftypavif"), Kind::Avif);
+        assert_eq!(sniff(b"\x7fELF\x02\x01"), Kind::Executable);
+        assert_eq!(sniff("\u{feff}<!DOCTYPE html>".as_bytes()), Kind::Html);
+        assert_eq!(sniff("body { content: \"é\" }".as_bytes()), Kind::Text);
+        assert_eq!(sniff(b"\x00\x01\xff\xfe\x80"), Kind::Binary);
+    }
+
+    #[test]
+    fn test_extension_mismatches() {
+        assert_eq!(mismatch("img/a.png", b"<html><script>x</script>").unwrap(), "Content looks like Html, not .png");
+        assert_eq!(mismatch("robots.txt", b"<!doctype html><p>hi").unwrap(), "Content looks like Html, not .txt");
+        assert_eq!(mismatch("download.bin", b"MZ\x90\x00").unwrap(), "Executable content");
+        assert!(mismatch("favicon.ico", b"\x89PNG\r\n\x1a\n").is_none());
+        assert!(mismatch("index.html", b"<!DOCTYPE html>").is_none());
+        assert!(mismatch("atom.xml.sig", b"\x30\x82\x01").is_none());
+    }
+
+    #[test]
+    fn test_polyglots() {
+        let whole_file_polyglot = |content: &[u8]| polyglot(content, content);
+        assert_eq!(whole_file_polyglot(b"GIF89a/*\x00\x00*/=alert(1)//").unwrap(), "GIF header is also the start of a script");
+        assert_eq!(whole_file_polyglot(b"%PDF-1.4\n<html><body>x").unwrap(), "Pdf contains HTML markup (<html)");
+        assert_eq!(whole_file_polyglot(b"\xff\xd8\xff\xe0JFIF%PDF-1.7").unwrap(), "Jpeg also contains a PDF header");
+        assert_eq!(whole_file_polyglot(b"\x89PNG\r\n\x1a\n....PK\x05\x06").unwrap(), "Png also contains a ZIP archive");
+        assert!(whole_file_polyglot(b"GIF89a\x01\x00\x01\x00").is_none());
+        assert!(whole_file_polyglot(b"<html><body>PK\x05\x06").is_none());
+    }
+
+    #[test]
+    fn test_sample_reads_only_the_ends_of_large_files() {
+        let path = std::env::temp_dir().join(format!("secureblog-sniff-{}.png", std::process::id()));
+        let mut content = b"\x89PNG\r\n\x1a\n".to_vec();
+        content.resize(1 << 20, 0);
+        content.extend_from_slice(b"PK\x05\x06");
+        content.extend_from_slice(&[0; 18]);
+        std::fs::write(&path, &content).unwrap();
+        let sample = sample(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(sample.head, content[..1024]);
+        assert_eq!(sample.tail.len(), 22 + 0xffff);
+        assert_eq!(polyglot(&sample.head, &sample.tail).unwrap(), "Png also contains a ZIP archive");
+    }
+
+    #[test]
+    fn test_dangerous_types() {
+        let dangerous = vec!["svgz".to_string(), ".xhtml".to_string()];
+        let image_dirs = vec!["images".to_string()];
+        assert_eq!(dangerous_type("logo.SVGZ", &dangerous, &image_dirs).unwrap(), ".svgz files are not published");
+        assert!(dangerous_type("a/b.xhtml", &dangerous, &image_dirs).is_some());
+        assert_eq!(dangerous_type("images/x/a.html", &dangerous, &image_dirs).unwrap(), "HTML page in an image directory");
+        assert!(dangerous_type("images.html", &dangerous, &image_dirs).is_none());
+        assert!(dangerous_type("posts/images-guide.html", &dangerous, &image_dirs).is_none());
+    }
+}
@@ -0,0 +1,212 @@
+//! Where post sources are read from
+//!
+//! The build normally reads `content/` from the working tree. `source:` in
+//! config.yaml can instead read the posts of a git revision straight from the
+//! object database, so uncommitted edits and untracked files never reach the
+//! output, or of a tar archive, e.g. a reviewed release of the content.
+//! Post paths keep their working tree form (`content/<post>.md`), so slugs,
+//! bundles and git dates resolve the same whatever the source.
+
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::export::tar;
+use crate::{git, normalize, vfs};
+
+/// Content source settings
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum SourceConfig {
+    /// `content/` in the working tree
+    #[default]
+    Directory,
+    /// `content/` at a commit, branch or tag of the repository
+    Git {
+        /// Revision to build
+        rev: String,
+    },
+    /// `content/` inside a `.tar` or `.tar.gz` of the site
+    Tar {
+        /// Archive path
+        archive: PathBuf,
+    },
+}
+
+/// Post sources for a build
+pub trait ContentSource {
+    /// Markdown files under the content directory as `(path, text)`, sorted by path
+    fn posts(&self) -> Result<Vec<(PathBuf, String)>>;
+}
+
+fn is_markdown(path: &str) -> bool {
+    path.ends_with(".md") || path.ends_with(".markdown")
+}
+
+/// Markdown entries of `files` under `dir`, decoded, sorted by path
+fn markdown_under(dir: &str, files: impl IntoIterator<Item = (String, Vec<u8>)>) -> Result<Vec<(PathBuf, String)>> {
+    let prefix = format!("{}/", dir.trim_end_matches('/'));
+    let mut posts = Vec::new();
+    for (path, bytes) in files {
+        let relative = path.trim_start_matches("./");
+        if !relative.starts_with(&prefix) || !is_markdown(relative) {
+            continue;
+        }
+        let text = String::from_utf8(bytes).with_context(|| format!("{relative} is not UTF-8"))?;
+        posts.push((PathBuf::from(relative), text));
+    }
+    posts.sort();
+    Ok(posts)
+}
+
+/// The content directory on disk
+pub struct Directory {
+    /// Content directory
+    pub dir: PathBuf,
+}
+
+impl ContentSource for Directory {
+    fn posts(&self) -> Result<Vec<(PathBuf, String)>> {
+        let files = vfs::current();
+        if !files.exists(&self.dir) {
+            return Ok(Vec::new());
+        }
+        let mut posts = Vec::new();
+        for path in files.files(&self.dir).with_context(|| format!("Failed to list {}", self.dir.display()))? {
+            if !is_markdown(&path.to_string_lossy()) {
+                continue;
+            }
+            let text = files.read_to_string(&path).with_context(|| format!("Failed to read post: {}", path.display()))?;
+            posts.push((path, text));
+        }
+        Ok(posts)
+    }
+}
+
+/// The content directory at a git revision, read from the object database
+pub struct GitRevision {
+    /// Content directory, relative to the working directory
+    pub dir: PathBuf,
+    /// Commit, branch or tag
+    pub rev: String,
+}
+
+impl ContentSource for GitRevision {
+    fn posts(&self) -> Result<Vec<(PathBuf, String)>> {
+        let dir = normalize::slashed(&self.dir);
+        let (_, files) = files_at(&self.rev, |path| path.starts_with(&format!("{dir}/")) && is_markdown(path))?;
+        markdown_under(&dir, files)
+    }
+}
+
+/// Commit `rev` resolves to and its files accepted by `wanted`, relative to the working directory
+pub fn files_at(rev: &str, wanted: impl Fn(&str) -> bool) -> Result<(String, Vec<(String, Vec<u8>)>)> {
+    let (repo, workdir) = git::open(Path::new("."))?;
+    let commit = repo
+        .rev_parse_single(rev)
+        .with_context(|| format!("Revision {rev} not found"))?
+        .object()?
+        .peel_to_commit()?;
+    let mut recorder = gix::traverse::tree::Recorder::default();
+    commit.tree()?.traverse().breadthfirst(&mut recorder)?;
+
+    // Tree paths are relative to the repository root, post paths to the working directory
+    let cwd = normalize::relative(&std::env::current_dir()?.canonicalize()?, &workdir)?;
+    let mut files = Vec::new();
+    for entry in recorder.records.into_iter().filter(|e| e.mode.is_blob()) {
+        let path = entry.filepath.to_string();
+        let relative = if cwd.is_empty() { Some(path.as_str()) } else { path.strip_prefix(&format!("{cwd}/")) };
+        if let Some(relative) = relative.filter(|r| wanted(r)) {
+            files.push((relative.to_string(), repo.find_object(entry.oid)?.detach().data));
+        }
+    }
+    Ok((commit.id.to_string(), files))
+}
+
+/// Files under `dirs` whose working tree copy is missing, extra or different from `rev`
+pub fn differences(rev: &str, dirs: &[PathBuf]) -> Result<Vec<String>> {
+    let prefixes: Vec<String> = dirs.iter().map(|dir| format!("{}/", normalize::slashed(dir))).collect();
+    let (_, committed) = files_at(rev, |path| prefixes.iter().any(|prefix| path.starts_with(prefix)))?;
+    let mut committed: BTreeMap<String, Vec<u8>> = committed.into_iter().collect();
+
+    let files = vfs::current();
+    let mut differences = Vec::new();
+    for dir in dirs.iter().filter(|dir| files.exists(dir)) {
+        for path in files.files(dir)? {
+            let relative = normalize::slashed(&path);
+            match committed.remove(&relative) {
+                Some(content) if content == files.read(&path)? => {}
+                Some(_) => differences.push(format!("{relative} differs from {rev}")),
+                None => differences.push(format!("{relative} is not in {rev}")),
+            }
+        }
+    }
+    differences.extend(committed.into_keys().map(|path| format!("{path} is missing from the working tree")));
+    Ok(differences)
+}
+
+/// The content directory inside a tar archive
+pub struct TarArchive {
+    /// Content directory inside the archive
+    pub dir: PathBuf,
+    /// `.tar` or `.tar.gz` file
+    pub archive: PathBuf,
+}
+
+impl ContentSource for TarArchive {
+    fn posts(&self) -> Result<Vec<(PathBuf, String)>> {
+        let mut data = vfs::current()
+            .read(&self.archive)
+            .with_context(|| format!("Failed to read {}", self.archive.display()))?;
+        if data.starts_with(&[0x1f, 0x8b]) {
+            let mut unpacked = Vec::new();
+            GzDecoder::new(data.as_slice())
+                .read_to_end(&mut unpacked)
+                .with_context(|| format!("Failed to decompress {}", self.archive.display()))?;
+            data = unpacked;
+        }
+        let files = tar::read_files(&data).with_context(|| format!("Invalid archive {}", self.archive.display()))?;
+        markdown_under(&normalize::slashed(&self.dir), files)
+    }
+}
+
+/// The configured source of the posts in `content_dir`
+pub fn open(config: &SourceConfig, content_dir: &Path) -> Box<dyn ContentSource> {
+    let dir = content_dir.to_path_buf();
+    match config {
+        SourceConfig::Directory => Box::new(Directory { dir }),
+        SourceConfig::Git { rev } => Box::new(GitRevision { dir, rev: rev.clone() }),
+        SourceConfig::Tar { archive } => Box::new(TarArchive { dir, archive: archive.clone() }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::tar::TarWriter;
+
+    #[test]
+    fn test_tar_archive_posts() {
+        let mut writer = TarWriter::default();
+        writer.add_file("content/b.md", b"---\ntitle: B\n---\n").unwrap();
+        writer.add_file("content/a/index.md", b"---\ntitle: A\n---\n").unwrap();
+        writer.add_file("content/a/diagram.png", b"\x89PNG").unwrap();
+        writer.add_file("drafts/c.md", b"x").unwrap();
+        let archive = std::env::temp_dir().join(format!("secureblog-source-{}.tar", std::process::id()));
+        std::fs::write(&archive, writer.finish()).unwrap();
+
+        let source = TarArchive { dir: PathBuf::from("content"), archive: archive.clone() };
+        let paths: Vec<PathBuf> = source.posts().unwrap().into_iter().map(|(path, _)| path).collect();
+        assert_eq!(paths, [PathBuf::from("content/a/index.md"), PathBuf::from("content/b.md")]);
+        std::fs::remove_file(&archive).unwrap();
+    }
+
+    #[test]
+    fn test_source_config() {
+        assert_eq!(serde_yaml::from_str::<SourceConfig>("type: git\nrev: v1.2\n").unwrap(), SourceConfig::Git { rev: "v1.2".to_string() });
+        assert!(serde_yaml::from_str::<SourceConfig>("type: git\n").is_err());
+    }
+}
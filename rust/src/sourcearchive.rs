@@ -0,0 +1,128 @@
+//! Raw markdown sources published under `/src/`
+//!
+//! With `source_archive: true` the markdown every published post was built
+//! from, as the content source (`source:`) provided it, is copied byte for
+//! byte to `src/<page>.md`, listed on `/src/` and linked from
+//! the post with its SHA-256. The copies are ordinary output files, so
+//! `integrity.json` and the checksum lists cover them like any page. Protected
+//! posts are left out: their source is as private as their page.
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+use std::fs;
+use tracing::info;
+
+use crate::security::escape_html as escape;
+use crate::{inject, Config, Post};
+
+/// Output directory of the sources and their index
+pub const SOURCE_DIR: &str = "src";
+
+/// Output path of a post's source: the page path under `src/` with `.md` for `.html`
+pub fn source_path(page: &str) -> String {
+    format!("{SOURCE_DIR}/{}.md", page.strip_suffix(".html").unwrap_or(page))
+}
+
+/// "View source" paragraph for a post's page
+pub fn view_link(path: &str, hash: &str) -> String {
+    format!(
+        "<p class=\"view-source\"><a href=\"/{}\" type=\"text/markdown\">View source</a> (SHA-256 <code>{}</code>)</p>\n",
+        escape(path),
+        escape(hash)
+    )
+}
+
+/// `/src/` listing every published source with its hash
+pub fn index(config: &Config, sources: &[(&Post, String, String)]) -> String {
+    let title = format!("Sources - {}", config.title);
+    let mut html = format!(
+        concat!(
+            "<!DOCTYPE html>\n<html lang=\"{language}\">\n<head>\n<meta charset=\"utf-8\">\n",
+            "<title>{title}</title>\n</head>\n<body>\n<main>\n<h1>{title}</h1>\n<ul class=\"sources\">\n",
+        ),
+        language = escape(&config.i18n.language),
+        title = escape(&title),
+    );
+    for (post, path, hash) in sources {
+        let _ = writeln!(
+            html,
+            "<li><a href=\"{}\">{}</a>: <a href=\"/{}\" type=\"text/markdown\">{}</a> <code>{}</code></li>",
+            escape(&post.url_path()),
+            escape(&post.meta.title),
+            escape(path),
+            escape(path.rsplit('/').next().unwrap_or(path)),
+            escape(hash)
+        );
+    }
+    html.push_str("</ul>\n</main>\n</body>\n</html>\n");
+    html
+}
+
+/// Copy each published post's markdown under `src/`, link it from the post and write the listing
+pub fn apply(config: &Config, posts: &[Post]) -> Result<usize> {
+    let mut sources = Vec::new();
+    for post in posts.iter().filter(|post| !post.meta.protected) {
+        // The text that was built, whichever content source it came from
+        let content = post.source_text.as_bytes();
+        let hash = format!("{:x}", Sha256::digest(content));
+        let page = post.path();
+        let path = source_path(&page);
+        let target = config.output.join(&path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&target, content).with_context(|| format!("Failed to write {}", target.display()))?;
+        inject::inject_into_file(&config.output.join(&page), &["</article>", "</main>", "</body>"], &view_link(&path, &hash))?;
+        sources.push((post, path, hash));
+    }
+
+    let listing = config.output.join(SOURCE_DIR).join("index.html");
+    if let Some(parent) = listing.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&listing, index(config, &sources)).with_context(|| format!("Failed to write {}", listing.display()))?;
+    info!("📜 Published {} markdown sources under /{}/", sources.len(), SOURCE_DIR);
+    Ok(sources.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PostMeta;
+
+    #[test]
+    fn test_source_paths_mirror_pages() {
+        assert_eq!(source_path("hardening-nginx.html"), "src/hardening-nginx.md");
+        assert_eq!(source_path("2024/05/hardening-nginx/index.html"), "src/2024/05/hardening-nginx/index.md");
+    }
+
+    #[test]
+    fn test_links_carry_the_hash() {
+        assert_eq!(
+            view_link("src/x.md", "ab12"),
+            "<p class=\"view-source\"><a href=\"/src/x.md\" type=\"text/markdown\">View source</a> (SHA-256 <code>ab12</code>)</p>\n"
+        );
+        let post = Post { meta: PostMeta { title: "A <b>".to_string(), slug: "a".to_string(), ..PostMeta::default() }, ..Post::default() };
+        let html = index(&Config::default(), &[(&post, "src/a.md".to_string(), "ab12".to_string())]);
+        assert!(html.contains("<li><a href=\"/a.html\">A &lt;b&gt;</a>: <a href=\"/src/a.md\" type=\"text/markdown\">a.md</a> <code>ab12</code></li>"));
+    }
+
+    #[test]
+    fn test_publishes_the_text_that_was_built() {
+        let output = std::env::temp_dir().join(format!("secureblog-src-{}", std::process::id()));
+        fs::create_dir_all(&output).unwrap();
+        fs::write(output.join("a.html"), "<main></main>").unwrap();
+        let config = Config { output: output.clone(), ..Config::default() };
+        let post = Post {
+            meta: PostMeta { slug: "a".to_string(), ..PostMeta::default() },
+            source: "content/not-in-the-working-tree.md".into(),
+            source_text: "---\ntitle: A\n---\nCommitted text\n".to_string(),
+            ..Post::default()
+        };
+        assert_eq!(apply(&config, &[post]).unwrap(), 1);
+        let published = fs::read_to_string(output.join("src/a.md")).unwrap();
+        fs::remove_dir_all(&output).unwrap();
+        assert_eq!(published, "---\ntitle: A\n---\nCommitted text\n");
+    }
+}
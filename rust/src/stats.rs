@@ -0,0 +1,342 @@
+//! Access log statistics without tracking: page views, referrers and daily visitors
+//!
+//! `stats logs` aggregates common/combined format logs into a counts-only data
+//! file; the build renders it as `stats/index.html`. Client addresses are only
+//! used as salted, truncated hashes to count daily visitors and are never saved.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, FixedOffset, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::hash::{BuildHasher, Hasher};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use tracing::info;
+
+use crate::security::escape_html;
+use crate::Config;
+
+/// Output path of the rendered page
+pub const STATS_PAGE: &str = "stats/index.html";
+
+/// Rows shown per table
+const TOP: usize = 25;
+
+/// User agents that are not counted
+const BOT_MARKERS: &[&str] = &["bot", "crawl", "spider", "slurp", "monitor", "curl/", "wget/"];
+
+/// `host ident user [time] "request" status size ["referer" "user-agent"]`
+static LOG_LINE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"^(\S+) \S+ \S+ \[([^\]]+)\] "(\S+) (\S+)[^"]*" (\d{3}) \S+(?: "([^"]*)" "([^"]*)")?"#).unwrap()
+});
+
+/// One request from an access log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    /// Client address (only ever hashed)
+    pub client: String,
+    /// Request time
+    pub time: DateTime<FixedOffset>,
+    /// HTTP method
+    pub method: String,
+    /// Path without query string
+    pub path: String,
+    /// Response status
+    pub status: u16,
+    /// Referrer host, if any
+    pub referrer: Option<String>,
+    /// User agent (combined format only)
+    pub user_agent: String,
+}
+
+/// Parse a common or combined log format line
+pub fn parse_line(line: &str) -> Option<Request> {
+    let cap = LOG_LINE.captures(line)?;
+    let time = DateTime::parse_from_str(&cap[2], "%d/%b/%Y:%H:%M:%S %z").ok()?;
+    let path = cap[4].split(['?', '#']).next().unwrap_or_default().to_string();
+    let referrer = cap
+        .get(6)
+        .map(|m| m.as_str())
+        .and_then(|r| r.split_once("://"))
+        .and_then(|(_, rest)| rest.split(['/', '?', '#']).next())
+        .filter(|host| !host.is_empty())
+        .map(str::to_ascii_lowercase);
+
+    Some(Request {
+        client: cap[1].to_string(),
+        time,
+        method: cap[3].to_string(),
+        path,
+        status: cap[5].parse().ok()?,
+        referrer,
+        user_agent: cap.get(7).map_or_else(String::new, |m| m.as_str().to_string()),
+    })
+}
+
+/// Successful page loads by people (no assets, errors or known bots)
+pub fn is_page_view(request: &Request) -> bool {
+    let is_page = Path::new(&request.path)
+        .extension()
+        .is_none_or(|ext| ext.eq_ignore_ascii_case("html"));
+    let agent = request.user_agent.to_ascii_lowercase();
+
+    request.method == "GET"
+        && (200..300).contains(&request.status)
+        && is_page
+        && !request.path.starts_with("/stats/")
+        && !BOT_MARKERS.iter().any(|marker| agent.contains(marker))
+}
+
+/// Daily totals
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Day {
+    /// Page views
+    pub views: u64,
+    /// Distinct visitors (address and user agent) that day
+    pub visitors: u64,
+}
+
+/// Aggregated counts (the only thing persisted)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stats {
+    /// Views per page path
+    #[serde(default)]
+    pub pages: BTreeMap<String, u64>,
+    /// Views per external referrer host
+    #[serde(default)]
+    pub referrers: BTreeMap<String, u64>,
+    /// Totals per day (`YYYY-MM-DD`, UTC)
+    #[serde(default)]
+    pub days: BTreeMap<String, Day>,
+}
+
+impl Stats {
+    /// Aggregate page views; `own_host` referrers (internal navigation) are not counted
+    pub fn aggregate(requests: impl IntoIterator<Item = Request>, own_host: &str) -> Self {
+        // Fresh random salt per run: hashes cannot be matched across runs or brute-forced offline
+        let salt = RandomState::new().build_hasher().finish().to_le_bytes();
+        let mut visitors: HashSet<(String, [u8; 4])> = HashSet::new();
+        let mut stats = Self::default();
+
+        for request in requests.into_iter().filter(is_page_view) {
+            let day = request.time.with_timezone(&Utc).format("%Y-%m-%d").to_string();
+            *stats.pages.entry(request.path.clone()).or_default() += 1;
+            if let Some(referrer) = request.referrer.filter(|host| host != own_host) {
+                *stats.referrers.entry(referrer).or_default() += 1;
+            }
+
+            let digest = Sha256::new()
+                .chain_update(salt)
+                .chain_update(request.client.as_bytes())
+                .chain_update(request.user_agent.as_bytes())
+                .finalize();
+            let visitor = [digest[0], digest[1], digest[2], digest[3]];
+            let totals = stats.days.entry(day.clone()).or_default();
+            totals.views += 1;
+            if visitors.insert((day, visitor)) {
+                totals.visitors += 1;
+            }
+        }
+        stats
+    }
+
+    /// Add the counts of a later run
+    ///
+    /// Visitor hashes are salted per run, so daily visitors from two runs are
+    /// summed; logs for the same day should be analyzed once.
+    pub fn merge(&mut self, other: Self) {
+        for (page, views) in other.pages {
+            *self.pages.entry(page).or_default() += views;
+        }
+        for (referrer, views) in other.referrers {
+            *self.referrers.entry(referrer).or_default() += views;
+        }
+        for (day, totals) in other.days {
+            let day = self.days.entry(day).or_default();
+            day.views += totals.views;
+            day.visitors += totals.visitors;
+        }
+    }
+
+    /// Total page views
+    pub fn views(&self) -> u64 {
+        self.days.values().map(|d| d.views).sum()
+    }
+
+    /// Load the data file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read stats: {}", path.display()))?;
+        serde_json::from_str(&content).with_context(|| format!("Failed to parse stats: {}", path.display()))
+    }
+
+    /// Save the data file
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write stats: {}", path.display()))
+    }
+}
+
+/// Host part of the site URL
+fn host_of(url: &str) -> String {
+    url.split_once("://")
+        .map_or(url, |(_, rest)| rest)
+        .split(['/', ':'])
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+/// Requests in one access log; bytes that are not UTF-8 are replaced rather than failing the log
+fn read_log(log: &Path) -> Result<Vec<Request>> {
+    let file = File::open(log).with_context(|| format!("Failed to read {}", log.display()))?;
+    let mut reader = BufReader::new(file);
+    let mut requests = Vec::new();
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line).with_context(|| format!("Failed to read {}", log.display()))? == 0 {
+            return Ok(requests);
+        }
+        requests.extend(parse_line(String::from_utf8_lossy(&line).trim_end()));
+    }
+}
+
+/// Aggregate access logs and add them to the stats data file
+pub fn analyze_logs(config: &Config, logs: &[impl AsRef<Path>]) -> Result<Stats> {
+    let mut requests = Vec::new();
+    for log in logs {
+        requests.extend(read_log(log.as_ref())?);
+    }
+
+    let mut stats = if config.stats_data.exists() { Stats::load(&config.stats_data)? } else { Stats::default() };
+    stats.merge(Stats::aggregate(requests, &host_of(&config.url)));
+    stats.save(&config.stats_data)?;
+    info!("📈 {} page views over {} days -> {}", stats.views(), stats.days.len(), config.stats_data.display());
+    Ok(stats)
+}
+
+fn table(html: &mut String, heading: &str, rows: &BTreeMap<String, u64>) {
+    let mut rows: Vec<(&String, &u64)> = rows.iter().collect();
+    rows.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    let max = rows.first().map_or(0, |(_, &count)| count);
+
+    let _ = writeln!(html, "<section>\n<h2>{heading}</h2>\n<table>");
+    for (label, count) in rows.into_iter().take(TOP) {
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td>{count}</td><td><meter min=\"0\" max=\"{max}\" value=\"{count}\"></meter></td></tr>",
+            escape_html(label)
+        );
+    }
+    html.push_str("</table>\n</section>\n");
+}
+
+/// Static stats page: tables and `<meter>` bars, no scripts
+pub fn render(config: &Config, stats: &Stats) -> String {
+    let title = format!("Stats - {}", config.title);
+    let mut html = format!(
+        concat!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n",
+            "<meta name=\"robots\" content=\"noindex\">\n<title>{title}</title>\n</head>\n<body>\n<main>\n",
+            "<h1>{title}</h1>\n<p>{views} page views. Counted from server logs; no cookies, scripts or stored addresses.</p>\n",
+        ),
+        title = escape_html(&title),
+        views = stats.views(),
+    );
+
+    html.push_str("<section>\n<h2>Daily</h2>\n<table>\n<tr><th>Day</th><th>Views</th><th>Visitors</th></tr>\n");
+    for (day, totals) in stats.days.iter().rev().take(TOP) {
+        let _ = writeln!(html, "<tr><td>{day}</td><td>{}</td><td>{}</td></tr>", totals.views, totals.visitors);
+    }
+    html.push_str("</table>\n</section>\n");
+    table(&mut html, "Pages", &stats.pages);
+    table(&mut html, "Referrers", &stats.referrers);
+
+    html.push_str("</main>\n</body>\n</html>\n");
+    html
+}
+
+/// Write `stats/index.html` from the saved data, if any
+pub fn write_page(config: &Config) -> Result<()> {
+    if !config.stats_data.exists() {
+        return Ok(());
+    }
+    let stats = Stats::load(&config.stats_data)?;
+    let path = config.output.join(STATS_PAGE);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, render(config, &stats)).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMBINED: &str = r#"203.0.113.9 - - [10/Oct/2024:13:55:36 -0700] "GET /posts/xss.html?utm=x HTTP/1.1" 200 2326 "https://news.example/item?id=1" "Mozilla/5.0""#;
+
+    #[test]
+    fn test_parse_common_and_combined() {
+        let request = parse_line(COMBINED).unwrap();
+        assert_eq!(request.path, "/posts/xss.html");
+        assert_eq!(request.status, 200);
+        assert_eq!(request.referrer.as_deref(), Some("news.example"));
+        assert_eq!(request.time.with_timezone(&Utc).format("%Y-%m-%d %H").to_string(), "2024-10-10 20");
+
+        let common = parse_line(r#"::1 - bob [10/Oct/2024:13:55:36 +0000] "GET /style.css HTTP/1.1" 304 -"#).unwrap();
+        assert_eq!((common.referrer.as_deref(), common.user_agent.as_str()), (None, ""));
+        assert!(!is_page_view(&common));
+        assert!(parse_line("garbage").is_none());
+    }
+
+    #[test]
+    fn test_aggregate_counts_without_addresses() {
+        let lines = [
+            COMBINED.to_string(),
+            COMBINED.replace("news.example/item?id=1", "example.com/"),
+            COMBINED.replace("203.0.113.9", "198.51.100.7").replace("Mozilla/5.0", "Googlebot/2.1"),
+            COMBINED.replace("203.0.113.9", "198.51.100.7").replace(" 200 ", " 404 "),
+        ];
+        let stats = Stats::aggregate(lines.iter().filter_map(|l| parse_line(l)), "example.com");
+        assert_eq!(stats.pages["/posts/xss.html"], 2);
+        assert_eq!(stats.referrers.len(), 1);
+        assert_eq!(stats.days["2024-10-10"], Day { views: 2, visitors: 1 });
+        let saved = serde_json::to_string(&stats).unwrap();
+        assert!(!saved.contains("203.0.113.9") && !saved.contains("Mozilla"));
+    }
+
+    #[test]
+    fn test_analyze_logs_merges_and_reads_invalid_utf8() {
+        let dir = std::env::temp_dir().join(format!("secureblog-stats-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("access.log");
+        let mut content = b"\xff\xfe not a request\n".to_vec();
+        content.extend_from_slice(COMBINED.as_bytes());
+        content.push(b'\n');
+        fs::write(&log, content).unwrap();
+        let config = Config { stats_data: dir.join("stats.json"), ..Config::default() };
+
+        analyze_logs(&config, &[&log]).unwrap();
+        let stats = analyze_logs(&config, &[&log]).unwrap();
+        assert_eq!(stats.pages["/posts/xss.html"], 2);
+        assert_eq!(Stats::load(&config.stats_data).unwrap(), stats);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_render_escapes_labels() {
+        let mut stats = Stats::default();
+        stats.pages.insert("/<script>".to_string(), 3);
+        let html = render(&Config::default(), &stats);
+        assert!(html.contains("&lt;script&gt;"));
+        assert!(!html.contains("<script"));
+        assert_eq!(host_of("https://Example.com:8443/blog"), "example.com");
+    }
+}
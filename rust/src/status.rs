@@ -0,0 +1,305 @@
+//! Post workflow states and their build behavior
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use tracing::{debug, info};
+
+use crate::security::escape_html;
+use crate::{bundles, generator, Config, Post, PostMeta, SecurityPolicy};
+
+/// Workflow status from the `status:` frontmatter field
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PostStatus {
+    /// Work in progress, never built
+    Draft,
+    /// Built into the separate review tree only
+    Review,
+    /// Published once its `date` has passed
+    Scheduled,
+    /// Live on the site (default)
+    #[default]
+    Published,
+    /// Removed from listings, its URL replaced by a tombstone page
+    Archived,
+}
+
+impl PostStatus {
+    /// Every status, in workflow order
+    pub const ALL: [Self; 5] = [
+        Self::Draft,
+        Self::Review,
+        Self::Scheduled,
+        Self::Published,
+        Self::Archived,
+    ];
+
+    /// Whether posts with this status may appear on the public site
+    pub const fn is_public(self) -> bool {
+        matches!(self, Self::Scheduled | Self::Published)
+    }
+}
+
+impl fmt::Display for PostStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Draft => "draft",
+            Self::Review => "review",
+            Self::Scheduled => "scheduled",
+            Self::Published => "published",
+            Self::Archived => "archived",
+        })
+    }
+}
+
+/// What the build does with a post
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Disposition {
+    /// Not built at all
+    Excluded,
+    /// Built into the review tree
+    Review,
+    /// Scheduled for a future date, not built yet
+    Pending,
+    /// Built into the site
+    Published,
+    /// Replaced by a tombstone page
+    Tombstoned,
+}
+
+/// Build behavior of a post at time `now`
+pub fn disposition(meta: &PostMeta, now: DateTime<Utc>) -> Disposition {
+    if meta.retracted && meta.status != PostStatus::Draft {
+        return Disposition::Tombstoned;
+    }
+    match meta.status {
+        PostStatus::Draft => Disposition::Excluded,
+        PostStatus::Review => Disposition::Review,
+        PostStatus::Scheduled if meta.date > now => Disposition::Pending,
+        PostStatus::Scheduled | PostStatus::Published => Disposition::Published,
+        PostStatus::Archived => Disposition::Tombstoned,
+    }
+}
+
+/// Optional details of a retraction (`retraction:` in frontmatter)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Retraction {
+    /// Why the post was withdrawn
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Armored signature over the retraction statement (e.g. `gpg --clearsign`), shown verbatim
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+/// Posts split by build behavior
+#[derive(Debug, Default)]
+pub struct Partition {
+    /// Posts built into the site
+    pub published: Vec<Post>,
+    /// Posts only built into the review tree
+    pub review: Vec<Post>,
+    /// Posts whose URL gets a tombstone page
+    pub archived: Vec<Post>,
+}
+
+/// Split posts by their disposition at time `now` (order is preserved)
+pub fn partition(posts: Vec<Post>, now: DateTime<Utc>) -> Partition {
+    let mut partition = Partition::default();
+    for post in posts {
+        match disposition(&post.meta, now) {
+            Disposition::Published => partition.published.push(post),
+            Disposition::Review => partition.review.push(post),
+            Disposition::Tombstoned => partition.archived.push(post),
+            Disposition::Pending => info!("Scheduled for {}: {}", post.meta.date.to_rfc3339(), post.meta.slug),
+            Disposition::Excluded => debug!("Skipping draft: {}", post.meta.slug),
+        }
+    }
+    partition
+}
+
+/// Render the published and review posts into the review tree
+pub fn build_review(config: &Config, published: &[Post], review: &[Post], policy: &SecurityPolicy) -> Result<()> {
+    if config.review_output.exists() {
+        fs::remove_dir_all(&config.review_output).context("Failed to clean review directory")?;
+    }
+    if review.is_empty() {
+        return Ok(());
+    }
+    fs::create_dir_all(&config.review_output).context("Failed to create review directory")?;
+
+    let mut posts: Vec<Post> = published.iter().chain(review).cloned().collect();
+    posts.sort_by(|a, b| b.meta.date.cmp(&a.meta.date));
+    let review_config = Config {
+        output: config.review_output.clone(),
+        ..config.clone()
+    };
+    generator::generate_site(&review_config, &posts, policy)?;
+    bundles::publish(&config.review_output, &posts)?;
+
+    info!("📝 {} posts in review: {}", review.len(), config.review_output.display());
+    Ok(())
+}
+
+/// Unlisted page with a title and notice, kept at a URL that no longer has its post
+fn tombstone(config: &Config, title: &str, label: &str, notice: &str) -> String {
+    format!(
+        concat!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n",
+            "<meta name=\"robots\" content=\"noindex\">\n<title>{title} ({label}) - {site}</title>\n",
+            "</head>\n<body>\n<main>\n<article class=\"tombstone\">\n<h1>{title}</h1>\n",
+            "{notice}",
+            "<p><a href=\"/\">{site}</a></p>\n</article>\n</main>\n</body>\n</html>\n",
+        ),
+        title = escape_html(title),
+        label = label,
+        notice = notice,
+        site = escape_html(&config.title),
+    )
+}
+
+/// Retraction statement with the optional reason and signature
+fn retraction_notice(retraction: Option<&Retraction>) -> String {
+    let mut notice = "<p>This post was retracted by its author.</p>\n".to_string();
+    let Some(retraction) = retraction else {
+        return notice;
+    };
+    if let Some(reason) = &retraction.reason {
+        notice.push_str(&format!("<p class=\"retraction-reason\">Reason: {}</p>\n", escape_html(reason)));
+    }
+    if let Some(signature) = &retraction.signature {
+        notice.push_str(&format!("<pre class=\"retraction-signature\">{}</pre>\n", escape_html(signature.trim())));
+    }
+    notice
+}
+
+/// Tombstone page kept at an archived or retracted post's URL
+pub fn tombstone_page(config: &Config, post: &Post) -> String {
+    if post.meta.retracted {
+        tombstone(config, &post.meta.title, "retracted", &retraction_notice(post.meta.retraction.as_ref()))
+    } else {
+        tombstone(config, &post.meta.title, "archived", "<p>This post was archived and is no longer maintained.</p>\n")
+    }
+}
+
+/// Tombstone page kept at the URL of a post whose source was deleted
+pub fn deleted_page(config: &Config, title: &str) -> String {
+    tombstone(config, title, "removed", "<p>This post was removed.</p>\n")
+}
+
+/// Write tombstone pages for archived and retracted posts
+pub fn write_tombstones(config: &Config, posts: &[Post]) -> Result<()> {
+    for post in posts {
+        let path = config.output.join(post.path());
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, tombstone_page(config, post))
+            .with_context(|| format!("Failed to write tombstone: {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Write tombstone pages at the old URLs of deleted posts, as `(path, title)`
+///
+/// URLs the build already filled, e.g. with a redirect, are left alone.
+pub fn write_deleted_tombstones(config: &Config, removed: &[(String, String)]) -> Result<()> {
+    for (page, title) in removed {
+        let path = config.output.join(page);
+        if path.exists() {
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, deleted_page(config, title))
+            .with_context(|| format!("Failed to write tombstone: {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Posts grouped by status for the `status` command
+pub fn by_status(posts: &[Post]) -> Vec<(PostStatus, Vec<&Post>)> {
+    PostStatus::ALL
+        .iter()
+        .map(|&status| (status, posts.iter().filter(|p| p.meta.status == status).collect()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn post(slug: &str, status: PostStatus, day: u32) -> Post {
+        Post {
+            meta: PostMeta {
+                title: slug.to_string(),
+                slug: slug.to_string(),
+                date: Utc.with_ymd_and_hms(2024, 6, day, 0, 0, 0).unwrap(),
+                status,
+                ..PostMeta::default()
+            },
+            ..Post::default()
+        }
+    }
+
+    #[test]
+    fn test_status_parses_lowercase() {
+        let status: PostStatus = serde_yaml::from_str("review").unwrap();
+        assert_eq!(status, PostStatus::Review);
+        assert!(serde_yaml::from_str::<PostStatus>("live").is_err());
+        assert_eq!(PostStatus::default(), PostStatus::Published);
+    }
+
+    #[test]
+    fn test_partition_by_disposition() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 15, 0, 0, 0).unwrap();
+        let posts = vec![
+            post("draft", PostStatus::Draft, 1),
+            post("review", PostStatus::Review, 2),
+            post("due", PostStatus::Scheduled, 10),
+            post("future", PostStatus::Scheduled, 20),
+            post("live", PostStatus::Published, 3),
+            post("old", PostStatus::Archived, 4),
+        ];
+        let partition = partition(posts, now);
+        let slugs = |posts: &[Post]| posts.iter().map(|p| p.meta.slug.clone()).collect::<Vec<_>>();
+        assert_eq!(slugs(&partition.published), ["due", "live"]);
+        assert_eq!(slugs(&partition.review), ["review"]);
+        assert_eq!(slugs(&partition.archived), ["old"]);
+    }
+
+    #[test]
+    fn test_tombstone_escapes_title() {
+        let mut archived = post("old", PostStatus::Archived, 1);
+        archived.meta.title = "<b>Old</b>".to_string();
+        let html = tombstone_page(&Config::default(), &archived);
+        assert!(html.contains("<h1>&lt;b&gt;Old&lt;/b&gt;</h1>"));
+        assert!(html.contains("noindex"));
+    }
+
+    #[test]
+    fn test_retracted_posts_are_tombstoned_with_reason() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 15, 0, 0, 0).unwrap();
+        let mut retracted = post("cve", PostStatus::Published, 1);
+        retracted.meta.retracted = true;
+        retracted.meta.retraction = Some(Retraction {
+            reason: Some("PoC targeted the <wrong> version".to_string()),
+            signature: Some("-----BEGIN PGP SIGNATURE-----\n...\n".to_string()),
+        });
+        assert_eq!(disposition(&retracted.meta, now), Disposition::Tombstoned);
+
+        let html = tombstone_page(&Config::default(), &retracted);
+        assert!(html.contains("<title>cve (retracted)"));
+        assert!(html.contains("Reason: PoC targeted the &lt;wrong&gt; version"));
+        assert!(html.contains("<pre class=\"retraction-signature\">-----BEGIN PGP SIGNATURE-----"));
+
+        retracted.meta.status = PostStatus::Draft;
+        assert_eq!(disposition(&retracted.meta, now), Disposition::Excluded);
+    }
+}
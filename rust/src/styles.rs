@@ -0,0 +1,226 @@
+//! Per-post stylesheets from `css:` frontmatter
+//!
+//! The file sits next to the post, passes the same checks as any output CSS,
+//! has every selector scoped to the post's `<article>`, and is published under
+//! a content-hashed name linked only from that post.
+
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sha2::{Digest, Sha256, Sha384};
+use std::fs;
+use std::path::{Component, Path};
+use tracing::info;
+
+use crate::security::css_violations;
+use crate::{inject, Config, Post, SecurityPolicy};
+
+/// Container every selector is scoped to
+const SCOPE: &str = "article";
+
+/// Output directory for post stylesheets
+const CSS_DIR: &str = "css";
+
+/// `/* ... */` comments
+static COMMENT: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)/\*.*?\*/").unwrap());
+
+/// `url(...)` pointing off-site
+static EXTERNAL_URL: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(?i)url\(\s*["']?\s*(?:[a-z][a-z0-9+.-]*:|//)"#).unwrap());
+
+/// At-rules whose block holds rules that need scoping
+const GROUPING_RULES: [&str; 4] = ["@media", "@supports", "@container", "@layer"];
+
+/// At-rules whose block is copied as is (no selectors inside)
+const VERBATIM_RULES: [&str; 3] = ["@font-face", "@keyframes", "@-webkit-keyframes"];
+
+/// Offset of the first `{`, `}` or `;` outside parentheses and strings
+fn top_level(input: &str, targets: &[char]) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut quote = None;
+    for (i, c) in input.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth = depth.saturating_sub(1),
+            (None, c) if depth == 0 && targets.contains(&c) => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Offset of the `}` closing a block whose `{` precedes `input`
+fn block_end(input: &str) -> Option<usize> {
+    let mut depth = 1usize;
+    let mut rest = 0;
+    while let Some(i) = top_level(&input[rest..], &['{', '}']).map(|i| rest + i) {
+        if input[i..].starts_with('{') {
+            depth += 1;
+        } else {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i);
+            }
+        }
+        rest = i + 1;
+    }
+    None
+}
+
+/// Prefix one selector with the scope; document-level selectors become the scope itself
+fn scope_selector(selector: &str) -> String {
+    let selector = selector.trim();
+    for root in ["html", "body", ":root"] {
+        if selector == root {
+            return SCOPE.to_string();
+        }
+        if let Some(rest) = selector.strip_prefix(root).filter(|rest| rest.starts_with([' ', '>', '+', '~'])) {
+            return format!("{SCOPE}{rest}");
+        }
+    }
+    format!("{SCOPE} {selector}")
+}
+
+/// Split a selector list on commas outside parentheses and strings
+fn selectors(list: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut rest = list;
+    while let Some(i) = top_level(rest, &[',']) {
+        parts.push(&rest[..i]);
+        rest = &rest[i + 1..];
+    }
+    parts.push(rest);
+    parts
+}
+
+/// Scope every rule in `css` to the post container
+pub fn scope(css: &str) -> Result<String> {
+    let mut output = String::new();
+    let mut rest = COMMENT.replace_all(css, "").trim().to_string();
+    while !rest.is_empty() {
+        let end = top_level(&rest, &['{', ';']).context("Unterminated CSS rule")?;
+        let prelude = rest[..end].trim().to_string();
+        if rest[end..].starts_with(';') {
+            let lower = prelude.to_ascii_lowercase();
+            if !lower.starts_with("@charset") {
+                anyhow::bail!("Unsupported CSS statement in post stylesheet: {prelude}");
+            }
+            rest = rest[end + 1..].trim_start().to_string();
+            continue;
+        }
+
+        let body_start = end + 1;
+        let close = body_start + block_end(&rest[body_start..]).context("Unbalanced braces in CSS")?;
+        let body = rest[body_start..close].trim();
+        let lower = prelude.to_ascii_lowercase();
+        let keyword = lower.split_whitespace().next().unwrap_or("");
+        if GROUPING_RULES.contains(&keyword) {
+            output.push_str(&format!("{prelude} {{\n{}}}\n", scope(body)?));
+        } else if VERBATIM_RULES.contains(&keyword) {
+            output.push_str(&format!("{prelude} {{ {body} }}\n"));
+        } else if keyword.starts_with('@') {
+            anyhow::bail!("Unsupported CSS at-rule in post stylesheet: {keyword}");
+        } else {
+            let scoped = selectors(&prelude).into_iter().map(scope_selector).collect::<Vec<_>>().join(", ");
+            output.push_str(&format!("{scoped} {{ {body} }}\n"));
+        }
+        rest = rest[close + 1..].trim_start().to_string();
+    }
+    Ok(output)
+}
+
+/// Read, check and scope the stylesheet named in a post's frontmatter
+pub fn stylesheet(post: &Post, file: &str, policy: &SecurityPolicy) -> Result<String> {
+    if !Path::new(file).components().all(|c| matches!(c, Component::Normal(_))) {
+        anyhow::bail!("css: must name a file next to the post, got {file}");
+    }
+    let dir = post.source.parent().unwrap_or_else(|| Path::new("."));
+    let path = dir.join(file);
+    let css = fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let mut violations: Vec<String> = css_violations(&css, policy).into_iter().map(|(_, v)| v).collect();
+    if css.to_ascii_lowercase().contains("@import") {
+        violations.push("@import".to_string());
+    }
+    if policy.no_external && EXTERNAL_URL.is_match(&css) {
+        violations.push("External url()".to_string());
+    }
+    if !violations.is_empty() {
+        anyhow::bail!("{} rejected: {}", path.display(), violations.join(", "));
+    }
+    scope(&css).with_context(|| format!("In {}", path.display()))
+}
+
+/// Publish each post's stylesheet as `css/<slug>.<hash>.css` and link it with SRI
+pub fn apply(config: &Config, posts: &[Post], policy: &SecurityPolicy) -> Result<()> {
+    let mut count = 0;
+    for post in posts {
+        let Some(file) = &post.meta.css else {
+            continue;
+        };
+        let css = stylesheet(post, file, policy)?;
+        let hash = format!("{:x}", Sha256::digest(css.as_bytes()));
+        let name = format!("{CSS_DIR}/{}.{}.css", post.meta.slug, &hash[..16]);
+        let target = config.output.join(&name);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&target, &css).with_context(|| format!("Failed to write {}", target.display()))?;
+
+        let link = format!(
+            "<link rel=\"stylesheet\" href=\"/{name}\" integrity=\"sha384-{}\">\n",
+            STANDARD.encode(Sha384::digest(css.as_bytes()))
+        );
+        inject::inject_into_file(&config.output.join(post.path()), &["</head>"], &link)?;
+        count += 1;
+    }
+    if count > 0 {
+        info!("🎨 Scoped stylesheets for {} posts", count);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selectors_are_scoped() {
+        let css = "/* c */ h1, .note > p { color: red }\nbody { margin: 0 }\nhtml > p { x: y }";
+        assert_eq!(
+            scope(css).unwrap(),
+            "article h1, article .note > p { color: red }\narticle { margin: 0 }\narticle > p { x: y }\n"
+        );
+    }
+
+    #[test]
+    fn test_grouping_rules_are_scoped_inside() {
+        let css = "@media (max-width: 40rem) { pre, a[href*=\",\"] { overflow: auto } }\n@font-face { font-family: X; src: url(x.woff2) }";
+        assert_eq!(
+            scope(css).unwrap(),
+            "@media (max-width: 40rem) {\narticle pre, article a[href*=\",\"] { overflow: auto }\n}\n\
+             @font-face { font-family: X; src: url(x.woff2) }\n"
+        );
+    }
+
+    #[test]
+    fn test_unsupported_and_malformed_css_is_rejected() {
+        assert!(scope("@import \"x.css\";").is_err());
+        assert!(scope("@namespace svg url(http://www.w3.org/2000/svg) { }").is_err());
+        assert!(scope("p { color: red").is_err());
+        assert!(scope("p").is_err());
+        assert_eq!(scope("@charset \"utf-8\"; p { a: b }").unwrap(), "article p { a: b }\n");
+    }
+
+    #[test]
+    fn test_external_urls_are_detected() {
+        assert!(EXTERNAL_URL.is_match("background: url('https://evil.example/x.png')"));
+        assert!(EXTERNAL_URL.is_match("background: url(//evil.example/x.png)"));
+        assert!(EXTERNAL_URL.is_match("background: url(data:image/png;base64,AA)"));
+        assert!(!EXTERNAL_URL.is_match("background: url(diagram.png)"));
+    }
+}
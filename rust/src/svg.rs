@@ -0,0 +1,66 @@
+//! SVG sanitization (allowlisted shapes and presentation attributes only)
+
+use std::collections::{HashMap, HashSet};
+
+/// Elements kept in sanitized SVG (names as html5ever reports them in the SVG namespace)
+const SVG_TAGS: &[&str] = &[
+    "svg", "g", "path", "rect", "circle", "ellipse", "line", "polyline", "polygon",
+    "title", "desc", "defs", "linearGradient", "radialGradient", "stop",
+];
+
+/// Attributes kept on every element (no `href`, `style` or event handlers)
+const SVG_ATTRIBUTES: &[&str] = &[
+    "xmlns", "version", "width", "height", "viewBox", "preserveAspectRatio",
+    "d", "x", "y", "x1", "y1", "x2", "y2", "cx", "cy", "r", "rx", "ry", "points",
+    "fill", "fill-rule", "fill-opacity", "stroke", "stroke-width", "stroke-linecap",
+    "stroke-linejoin", "stroke-opacity", "opacity", "transform", "offset", "stop-color",
+    "shape-rendering", "role", "aria-label", "id", "class",
+];
+
+/// Sanitize SVG markup, dropping scripts, `foreignObject`, links and any external reference
+pub fn sanitize_svg(svg: &str) -> String {
+    // The XML prolog and doctype are not needed for inline or standalone SVG
+    let body = svg
+        .find("<svg")
+        .or_else(|| svg.find("<SVG"))
+        .map_or(svg, |start| &svg[start..]);
+
+    let tag_attributes: HashMap<&str, HashSet<&str>> = SVG_TAGS
+        .iter()
+        .map(|tag| (*tag, SVG_ATTRIBUTES.iter().copied().collect()))
+        .collect();
+
+    ammonia::Builder::empty()
+        .tags(SVG_TAGS.iter().copied().collect())
+        .tag_attributes(tag_attributes)
+        .clean_content_tags(HashSet::from(["script", "style", "foreignobject"]))
+        .strip_comments(true)
+        .url_schemes(HashSet::new())
+        .link_rel(None)
+        .clean(body)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_svg_removes_scripts_and_handlers() {
+        let dirty = r##"<?xml version="1.0"?><svg xmlns="http://www.w3.org/2000/svg" onload="alert(1)"><script>alert(1)</script><rect width="10" height="10" fill="#000"/></svg>"##;
+        let clean = sanitize_svg(dirty);
+        assert!(!clean.contains("script"));
+        assert!(!clean.contains("onload"));
+        assert!(clean.contains("<rect"));
+        assert!(clean.contains("fill=\"#000\""));
+    }
+
+    #[test]
+    fn test_sanitize_svg_removes_external_references() {
+        let dirty = r#"<svg><a href="https://evil.example"><path d="M0 0"/></a><image href="https://evil.example/x.png"/><foreignObject><p>x</p></foreignObject></svg>"#;
+        let clean = sanitize_svg(dirty);
+        assert!(!clean.contains("evil"));
+        assert!(!clean.contains("<p>"));
+        assert!(clean.contains("<path d=\"M0 0\""));
+    }
+}
@@ -0,0 +1,463 @@
+//! Vendored theme assets pinned by a lock beside the theme
+//!
+//! A theme directory ships its templates, and its fonts, CSS and images under
+//! `static/`. Every file of the theme must be listed in its lock, which lives
+//! next to the theme as `themes/<name>.lock` (`sha256sum` format, paths
+//! relative to the theme), with a matching hash before anything is rendered or
+//! copied into the output, so an updated theme cannot add or change templates
+//! or published files without a reviewed lock change, and cannot ship its own
+//! lock. A theme vendored by `theme install` also records where it came from
+//! in `# source:` and `# commit:` lines. Theme assets are copied after the
+//! pages are generated and may not replace any of them.
+//!
+//! Theme templates are untrusted too: every `include`/`extends`/`import`
+//! reference must resolve inside the site or theme template directories.
+//! [`check_templates`] reads and scans each template once through a
+//! [`TemplateCache`], however many templates include it.
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use tracing::info;
+use walkdir::WalkDir;
+
+use crate::normalize;
+
+/// Extension of the lock file next to the theme directory
+const LOCK_EXTENSION: &str = "lock";
+
+/// Asset directory inside the theme
+const ASSETS: &str = "static";
+
+/// Template directory of the site and of the theme
+const TEMPLATES: &str = "templates";
+
+/// `{% include "x" %}`, `{% extends 'x' %}`, `{% import "x" as m %}`, `{% from "x" import m %}`
+static TEMPLATE_REFERENCE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"\{%-?\s*(?:include|extends|import|from)\s+(?:"([^"]*)"|'([^']*)')"#).unwrap()
+});
+
+/// Lock of a theme directory: `themes/<name>` is locked by `themes/<name>.lock`
+pub fn lock_path(theme: &Path) -> Result<PathBuf> {
+    let mut name = theme.file_name().with_context(|| format!("Theme path has no name: {}", theme.display()))?.to_owned();
+    name.push(".");
+    name.push(LOCK_EXTENSION);
+    Ok(theme.with_file_name(name))
+}
+
+/// Parse a theme lock: `<sha256>  <path>` lines, `#` comments allowed
+pub fn parse_lock(text: &str) -> Result<BTreeMap<String, String>> {
+    let mut entries = BTreeMap::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (hash, path) = line
+            .split_once("  ")
+            .or_else(|| line.split_once(" *"))
+            .with_context(|| format!("Line {}: expected `<sha256>  <path>`", number + 1))?;
+        if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            anyhow::bail!("Line {}: invalid SHA-256 `{hash}`", number + 1);
+        }
+        let confined = Path::new(path).components().all(|c| matches!(c, Component::Normal(_)));
+        if path.is_empty() || !confined {
+            anyhow::bail!("Line {}: path must be relative without `..`: {path}", number + 1);
+        }
+        if entries.insert(path.to_string(), hash.to_ascii_lowercase()).is_some() {
+            anyhow::bail!("Line {}: {path} is listed twice", number + 1);
+        }
+    }
+    Ok(entries)
+}
+
+/// Where a vendored theme came from, as recorded in its lock
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Origin {
+    /// Git URL or repository path given to `theme install`
+    pub source: String,
+    /// Full commit id the files were taken from
+    pub commit: String,
+}
+
+/// The `# source:` and `# commit:` lines of a lock, if both are present
+pub fn parse_origin(text: &str) -> Option<Origin> {
+    let field = |name: &str| {
+        text.lines()
+            .find_map(|line| line.strip_prefix("# ")?.strip_prefix(name)?.strip_prefix(':'))
+            .map(|value| value.trim().to_string())
+    };
+    Some(Origin { source: field("source")?, commit: field("commit")? })
+}
+
+/// Every regular file under `root`, keyed by `/`-separated relative path
+fn files_under(root: &Path) -> Result<BTreeMap<String, Vec<u8>>> {
+    let mut files = BTreeMap::new();
+    if !root.is_dir() {
+        return Ok(files);
+    }
+    for entry in WalkDir::new(root) {
+        let entry = entry?;
+        if entry.file_type().is_symlink() {
+            anyhow::bail!("Theme file is a symlink: {}", entry.path().display());
+        }
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative = normalize::relative(entry.path(), root)?;
+        let content = fs::read(entry.path()).with_context(|| format!("Failed to read {}", entry.path().display()))?;
+        files.insert(relative, content);
+    }
+    Ok(files)
+}
+
+/// Every file of the theme, keyed by path relative to the theme
+pub fn theme_files(theme: &Path) -> Result<BTreeMap<String, Vec<u8>>> {
+    files_under(theme)
+}
+
+/// Read and parse the theme's lock, with its origin if it was vendored
+pub fn read_lock(theme: &Path) -> Result<(BTreeMap<String, String>, Option<Origin>)> {
+    let path = lock_path(theme)?;
+    let text = fs::read_to_string(&path)
+        .with_context(|| format!("Theme {} has no {} (run `theme lock` after reviewing it)", theme.display(), path.display()))?;
+    let lock = parse_lock(&text).with_context(|| format!("Invalid theme lock {}", path.display()))?;
+    Ok((lock, parse_origin(&text)))
+}
+
+/// Problems between the assets on disk and the lock (empty when they agree)
+pub fn lock_problems(assets: &BTreeMap<String, Vec<u8>>, lock: &BTreeMap<String, String>) -> Vec<String> {
+    let mut problems = Vec::new();
+    for (path, content) in assets {
+        match lock.get(path) {
+            None => problems.push(format!("{path} is not in the lock")),
+            Some(expected) if *expected != format!("{:x}", Sha256::digest(content)) => {
+                problems.push(format!("{path} does not match its locked hash"));
+            }
+            Some(_) => {}
+        }
+    }
+    for path in lock.keys().filter(|path| !assets.contains_key(*path)) {
+        problems.push(format!("{path} is in the lock but missing from the theme"));
+    }
+    problems
+}
+
+/// Check every file of the theme against its lock
+pub fn verify(theme: &Path) -> Result<()> {
+    let (lock, _) = read_lock(theme)?;
+    let problems = lock_problems(&theme_files(theme)?, &lock);
+    if !problems.is_empty() {
+        anyhow::bail!("Theme {} failed verification:\n  {}", theme.display(), problems.join("\n  "));
+    }
+    Ok(())
+}
+
+/// Theme assets that would replace a file already in the output
+pub fn overwritten(assets: &BTreeMap<String, Vec<u8>>, output_dir: &Path) -> Vec<String> {
+    assets.keys().filter(|path| output_dir.join(path).exists()).cloned().collect()
+}
+
+/// Verify every theme file against the lock, then copy the assets into the output
+///
+/// Runs after the pages are generated; an asset at the path of a generated
+/// file (an `index.html`, a feed) is refused rather than published over it.
+pub fn install(theme: &Path, output_dir: &Path) -> Result<usize> {
+    verify(theme)?;
+    let assets = files_under(&theme.join(ASSETS))?;
+    let clashes = overwritten(&assets, output_dir);
+    if !clashes.is_empty() {
+        anyhow::bail!("Theme {} would overwrite generated files:\n  {}", theme.display(), clashes.join("\n  "));
+    }
+
+    for (path, content) in &assets {
+        let target = output_dir.join(path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&target, content).with_context(|| format!("Failed to write {}", target.display()))?;
+    }
+    info!("🎨 Installed {} verified theme assets from {}", assets.len(), theme.display());
+    Ok(assets.len())
+}
+
+/// Lock text for a theme's files, with its origin if it was vendored
+pub fn lock_text(files: &BTreeMap<String, Vec<u8>>, origin: Option<&Origin>) -> String {
+    let mut lock = "# Theme lock: SHA-256 of every file in the theme, checked before each build\n".to_string();
+    if let Some(origin) = origin {
+        let _ = writeln!(lock, "# source: {}\n# commit: {}", origin.source, origin.commit);
+    }
+    for (path, content) in files {
+        let _ = writeln!(lock, "{:x}  {path}", Sha256::digest(content));
+    }
+    lock
+}
+
+/// Write the theme's lock for its current files (after reviewing them)
+///
+/// The origin of a vendored theme is kept from the existing lock.
+pub fn write_lock(theme: &Path) -> Result<usize> {
+    let files = theme_files(theme)?;
+    let origin = read_lock(theme).ok().and_then(|(_, origin)| origin);
+    let path = lock_path(theme)?;
+    fs::write(&path, lock_text(&files, origin.as_ref())).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(files.len())
+}
+
+/// Template directories in lookup order: the site's own, then the theme's
+pub fn template_dirs(theme: Option<&Path>) -> Vec<PathBuf> {
+    std::iter::once(PathBuf::from(TEMPLATES))
+        .chain(theme.map(|theme| theme.join(TEMPLATES)))
+        .collect()
+}
+
+/// Reject template names that are absolute or could climb out of a template directory
+pub fn check_template_name(name: &str) -> Result<()> {
+    let confined = Path::new(name).components().all(|c| matches!(c, Component::Normal(_)));
+    if name.is_empty() || name.contains(['\\', '\0']) || !confined {
+        anyhow::bail!("Template name must be a relative path without `..`: {name:?}");
+    }
+    Ok(())
+}
+
+/// Names referenced by `include`/`extends`/`import`/`from` tags
+pub fn template_references(source: &str) -> Vec<&str> {
+    TEMPLATE_REFERENCE
+        .captures_iter(source)
+        .filter_map(|c| c.get(1).or_else(|| c.get(2)))
+        .map(|m| m.as_str())
+        .collect()
+}
+
+/// Resolve a template name to a file that really lives under one of `dirs`
+///
+/// Symlinks are followed and the target re-checked, so a link inside a theme
+/// cannot point at files elsewhere on disk either.
+pub fn resolve_template(dirs: &[PathBuf], name: &str) -> Result<PathBuf> {
+    check_template_name(name)?;
+    for dir in dirs.iter().filter(|dir| dir.is_dir()) {
+        let candidate = dir.join(name);
+        if !candidate.exists() {
+            continue;
+        }
+        let root = dir.canonicalize()?;
+        let resolved = candidate.canonicalize()?;
+        if !resolved.starts_with(&root) {
+            anyhow::bail!("Template {name} resolves outside {}: {}", dir.display(), resolved.display());
+        }
+        return Ok(resolved);
+    }
+    anyhow::bail!("Template {name} not found in {}", dirs.iter().map(|d| d.display().to_string()).collect::<Vec<_>>().join(", "))
+}
+
+/// The references of a template, scanned once
+#[derive(Debug)]
+pub struct Template {
+    /// Names it includes, extends or imports, in order
+    pub references: Vec<String>,
+}
+
+/// Template references scanned once and looked up by name (safe to share between threads)
+///
+/// Lookups by name resolve and read the file only the first time. Scanned
+/// templates are keyed by content hash, so identical files (a site template
+/// copied from its theme) share one entry. Rendering does not go through
+/// this cache; it only serves the reference check.
+#[derive(Debug, Default)]
+pub struct TemplateCache {
+    dirs: Vec<PathBuf>,
+    names: RwLock<HashMap<String, Arc<Template>>>,
+    parsed: RwLock<HashMap<String, Arc<Template>>>,
+}
+
+impl TemplateCache {
+    /// Cache for templates looked up in `dirs`, in order
+    pub fn new(dirs: Vec<PathBuf>) -> Self {
+        Self { dirs, ..Self::default() }
+    }
+
+    /// The template `name` resolves to, read from disk on first use
+    pub fn get(&self, name: &str) -> Result<Arc<Template>> {
+        if let Some(template) = self.names.read().unwrap_or_else(|e| e.into_inner()).get(name) {
+            return Ok(Arc::clone(template));
+        }
+        let template = self.load(resolve_template(&self.dirs, name)?)?;
+        self.names.write().unwrap_or_else(|e| e.into_inner()).insert(name.to_string(), Arc::clone(&template));
+        Ok(template)
+    }
+
+    /// Read and scan an already resolved template, reusing an identical one
+    fn load(&self, path: PathBuf) -> Result<Arc<Template>> {
+        let source = fs::read_to_string(&path).with_context(|| format!("Failed to read template {}", path.display()))?;
+        let hash = format!("{:x}", Sha256::digest(source.as_bytes()));
+        let mut parsed = self.parsed.write().unwrap_or_else(|e| e.into_inner());
+        let template = parsed.entry(hash).or_insert_with(|| {
+            let references = template_references(&source).into_iter().map(str::to_string).collect();
+            Arc::new(Template { references })
+        });
+        Ok(Arc::clone(template))
+    }
+
+    /// Number of distinct template sources read so far
+    pub fn loaded(&self) -> usize {
+        self.parsed.read().unwrap_or_else(|e| e.into_inner()).len()
+    }
+}
+
+/// Check every reference in every template before anything is rendered
+pub fn check_templates(dirs: &[PathBuf]) -> Result<usize> {
+    let cache = TemplateCache::new(dirs.to_vec());
+    let mut checked = 0;
+    for dir in dirs.iter().filter(|dir| dir.is_dir()) {
+        for entry in WalkDir::new(dir).follow_links(false) {
+            let entry = entry?;
+            if !entry.file_type().is_file() && !entry.file_type().is_symlink() {
+                continue;
+            }
+            let path = normalize::relative(entry.path(), dir)?;
+            let Ok(template) = cache.load(resolve_template(std::slice::from_ref(dir), &path)?) else {
+                continue;
+            };
+            for name in &template.references {
+                cache.get(name).with_context(|| format!("In {}", entry.path().display()))?;
+                checked += 1;
+            }
+        }
+    }
+    info!("📄 {} templates read", cache.loaded());
+    Ok(checked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMPTY: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    #[test]
+    fn test_parse_lock() {
+        let lock = parse_lock(&format!("# comment\n{EMPTY}  fonts/a.woff2\n")).unwrap();
+        assert_eq!(lock.get("fonts/a.woff2").map(String::as_str), Some(EMPTY));
+        assert!(parse_lock("abc  style.css").is_err());
+        assert!(parse_lock(&format!("{EMPTY}  ../escape.css")).is_err());
+        assert!(parse_lock(&format!("{EMPTY}  /etc/passwd")).is_err());
+        assert!(parse_lock(&format!("{EMPTY}  a.css\n{EMPTY}  a.css")).is_err());
+    }
+
+    #[test]
+    fn test_lock_lives_outside_the_theme() {
+        assert_eq!(lock_path(Path::new("themes/minimal")).unwrap(), Path::new("themes/minimal.lock"));
+        assert_eq!(lock_path(Path::new("themes/paper.v2/")).unwrap(), Path::new("themes/paper.v2.lock"));
+        assert!(lock_path(Path::new("..")).is_err());
+    }
+
+    #[test]
+    fn test_assets_may_not_overwrite_generated_pages() {
+        let dir = std::env::temp_dir().join(format!("secureblog-theme-output-{}", std::process::id()));
+        fs::create_dir_all(dir.join("posts/hello")).unwrap();
+        fs::write(dir.join("index.html"), "<html></html>").unwrap();
+        fs::write(dir.join("posts/hello/index.html"), "<html></html>").unwrap();
+
+        let assets = BTreeMap::from([
+            ("css/theme.css".to_string(), Vec::new()),
+            ("index.html".to_string(), b"<script>".to_vec()),
+            ("posts/hello/index.html".to_string(), Vec::new()),
+        ]);
+        assert_eq!(overwritten(&assets, &dir), ["index.html", "posts/hello/index.html"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_lock_records_origin() {
+        let files = BTreeMap::from([("templates/base.html".to_string(), Vec::new())]);
+        let origin = Origin { source: "https://example.com/theme.git".to_string(), commit: "0123abcd".to_string() };
+        let text = lock_text(&files, Some(&origin));
+        assert_eq!(parse_origin(&text), Some(origin));
+        assert_eq!(parse_lock(&text).unwrap().get("templates/base.html").map(String::as_str), Some(EMPTY));
+        assert_eq!(parse_origin(&lock_text(&files, None)), None);
+    }
+
+    #[test]
+    fn test_lock_problems() {
+        let lock = BTreeMap::from([("a.css".to_string(), EMPTY.to_string()), ("gone.png".to_string(), EMPTY.to_string())]);
+        let assets = BTreeMap::from([
+            ("a.css".to_string(), b"body{}".to_vec()),
+            ("new.js".to_string(), Vec::new()),
+        ]);
+        assert_eq!(
+            lock_problems(&assets, &lock),
+            [
+                "a.css does not match its locked hash",
+                "new.js is not in the lock",
+                "gone.png is in the lock but missing from the theme",
+            ]
+        );
+
+        let assets = BTreeMap::from([("a.css".to_string(), Vec::new()), ("gone.png".to_string(), Vec::new())]);
+        assert!(lock_problems(&assets, &lock).is_empty());
+    }
+
+    #[test]
+    fn test_template_references() {
+        let source = r#"{% extends "base.html" %}{%- include 'partials/nav.html' -%}
+            {% import "macros.html" as m %}{% from "forms.html" import field %}{{ include }}"#;
+        assert_eq!(
+            template_references(source),
+            ["base.html", "partials/nav.html", "macros.html", "forms.html"]
+        );
+    }
+
+    #[test]
+    fn test_template_traversal_is_rejected() {
+        for name in [
+            "../config.yaml",
+            "partials/../../secret.html",
+            "/etc/passwd",
+            "./base.html",
+            "..\\..\\secret.html",
+            "partials\\nav.html",
+            "",
+            "base.html\0",
+        ] {
+            assert!(check_template_name(name).is_err(), "{name:?} accepted");
+            assert!(resolve_template(&template_dirs(None), name).is_err(), "{name:?} resolved");
+        }
+        assert!(check_template_name("partials/nav.html").is_ok());
+        assert!(check_template_name("base.html").is_ok());
+    }
+
+    #[test]
+    fn test_template_cache_reads_each_template_once() {
+        let dir = std::env::temp_dir().join(format!("secureblog-templates-{}", std::process::id()));
+        fs::create_dir_all(dir.join("partials")).unwrap();
+        fs::write(dir.join("post.html"), r#"{% extends "base.html" %}"#).unwrap();
+        fs::write(dir.join("base.html"), "<html></html>").unwrap();
+        fs::write(dir.join("partials/copy.html"), "<html></html>").unwrap();
+
+        let cache = TemplateCache::new(vec![dir.clone()]);
+        let post = cache.get("post.html").unwrap();
+        assert_eq!(post.references, ["base.html"]);
+        assert!(Arc::ptr_eq(&cache.get("base.html").unwrap(), &cache.get("partials/copy.html").unwrap()));
+        assert_eq!(cache.loaded(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(Arc::ptr_eq(&post, &cache.get("post.html").unwrap()));
+        assert!(cache.get("missing.html").is_err());
+    }
+
+    #[test]
+    fn test_template_dirs_prefer_site_over_theme() {
+        assert_eq!(
+            template_dirs(Some(Path::new("themes/minimal"))),
+            [PathBuf::from("templates"), PathBuf::from("themes/minimal/templates")]
+        );
+        assert_eq!(template_dirs(None), [PathBuf::from("templates")]);
+    }
+}
@@ -0,0 +1,158 @@
+//! Frontmatter date parsing and time zones
+//!
+//! `date:` and `updated:` may carry an offset (`2024-05-09T23:30:00+02:00`),
+//! or be a bare date or local time, which is read in the site's default zone.
+//! Everything is kept in UTC internally; shown dates and permalink date
+//! segments use the display zone, so a late-evening post keeps its day.
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Deserializer};
+
+use crate::PostMeta;
+
+/// Zones for reading and showing dates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub struct TimezoneConfig {
+    /// IANA zone for frontmatter dates without an offset (UTC when unset)
+    #[serde(default)]
+    pub default: Option<Tz>,
+    /// IANA zone dates are shown in (the default zone when unset)
+    #[serde(default)]
+    pub display: Option<Tz>,
+}
+
+impl TimezoneConfig {
+    /// Zone for dates written without an offset
+    pub fn default_zone(&self) -> Tz {
+        self.default.unwrap_or(Tz::UTC)
+    }
+
+    /// Zone for rendered dates
+    pub fn display_zone(&self) -> Tz {
+        self.display.unwrap_or_else(|| self.default_zone())
+    }
+}
+
+/// Local time formats accepted without an offset
+const LOCAL_FORMATS: [&str; 4] = ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"];
+
+/// A frontmatter date as written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrontmatterDate {
+    /// Carried an offset
+    Instant(DateTime<Utc>),
+    /// Bare date (midnight) or local time, meaning depends on the zone
+    Local(NaiveDateTime),
+}
+
+impl FrontmatterDate {
+    /// The instant, reading local times as UTC
+    fn as_utc(self) -> DateTime<Utc> {
+        match self {
+            Self::Instant(date) => date,
+            Self::Local(local) => local.and_utc(),
+        }
+    }
+}
+
+/// Parse a frontmatter date
+pub fn parse(value: &str) -> Result<FrontmatterDate> {
+    let value = value.trim();
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Ok(FrontmatterDate::Instant(date.with_timezone(&Utc)));
+    }
+    if let Ok(date) = DateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S %z") {
+        return Ok(FrontmatterDate::Instant(date.with_timezone(&Utc)));
+    }
+    if let Some(local) = LOCAL_FORMATS.iter().find_map(|f| NaiveDateTime::parse_from_str(value, f).ok()) {
+        return Ok(FrontmatterDate::Local(local));
+    }
+    if let Ok(day) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(FrontmatterDate::Local(day.and_time(NaiveTime::MIN)));
+    }
+    anyhow::bail!("Invalid date {value:?}: expected YYYY-MM-DD, a local time or RFC 3339 with an offset")
+}
+
+/// A local time in `zone` as UTC (the earlier instant when the clock goes back)
+pub fn localize(local: NaiveDateTime, zone: Tz) -> Result<DateTime<Utc>> {
+    zone.from_local_datetime(&local)
+        .earliest()
+        .map(|date| date.with_timezone(&Utc))
+        .ok_or_else(|| anyhow::anyhow!("{local} does not exist in {zone} (daylight saving gap)"))
+}
+
+/// Value of a top-level `key:` in YAML frontmatter, unquoted
+fn frontmatter_value<'a>(content: &'a str, key: &str) -> Option<&'a str> {
+    let mut lines = content.lines();
+    if lines.next()?.trim_end() != "---" {
+        return None;
+    }
+    lines
+        .take_while(|line| line.trim_end() != "---")
+        .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))
+        .map(|value| value.trim().trim_matches(|c| c == '"' || c == '\''))
+}
+
+/// Re-read `date` and `updated` written without an offset as local times in `zone`
+///
+/// Deserialization reads them as UTC; this moves them to the instant the author meant.
+pub fn apply_default_zone(meta: &mut PostMeta, content: &str, zone: Tz) -> Result<()> {
+    if let Some(FrontmatterDate::Local(local)) = frontmatter_value(content, "date").map(parse).transpose()? {
+        meta.date = localize(local, zone)?;
+    }
+    if let Some(FrontmatterDate::Local(local)) = frontmatter_value(content, "updated").map(parse).transpose()? {
+        meta.updated = Some(localize(local, zone)?);
+    }
+    Ok(())
+}
+
+/// `deserialize_with` for `date:`, local times read as UTC until `apply_default_zone`
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<Utc>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse(&value).map(FrontmatterDate::as_utc).map_err(serde::de::Error::custom)
+}
+
+/// `deserialize_with` for `updated:`
+pub fn deserialize_option<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|value| parse(&value).map(FrontmatterDate::as_utc).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offsets_are_kept_and_local_times_use_the_default_zone() {
+        let content = "---\ntitle: x\ndate: 2024-05-09 23:30\nupdated: \"2024-05-10T08:00:00+02:00\"\n---\nBody\n";
+        let mut meta = PostMeta {
+            date: deserialize(serde_yaml::Value::from("2024-05-09 23:30")).unwrap(),
+            updated: deserialize_option(serde_yaml::Value::from("2024-05-10T08:00:00+02:00")).unwrap(),
+            ..PostMeta::default()
+        };
+        apply_default_zone(&mut meta, content, chrono_tz::Europe::Berlin).unwrap();
+        assert_eq!(meta.date.to_rfc3339(), "2024-05-09T21:30:00+00:00");
+        assert_eq!(meta.updated.unwrap().to_rfc3339(), "2024-05-10T06:00:00+00:00");
+    }
+
+    #[test]
+    fn test_display_zone_keeps_the_authors_day() {
+        let config = TimezoneConfig { default: Some(chrono_tz::America::Los_Angeles), display: None };
+        let local = NaiveDate::from_ymd_opt(2024, 5, 9).unwrap().and_hms_opt(22, 0, 0).unwrap();
+        assert_eq!(parse("2024-05-09 22:00").unwrap(), FrontmatterDate::Local(local));
+        let date = localize(local, config.default_zone()).unwrap();
+        assert_eq!(date.format("%d").to_string(), "10");
+        assert_eq!(date.with_timezone(&config.display_zone()).format("%d").to_string(), "09");
+    }
+
+    #[test]
+    fn test_invalid_and_nonexistent_times() {
+        assert!(parse("May 9th").is_err());
+        let gap = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap().and_hms_opt(2, 30, 0).unwrap();
+        assert!(localize(gap, chrono_tz::Europe::Berlin).is_err());
+        assert_eq!(parse("2024-03-31").unwrap(), FrontmatterDate::Local(gap.date().and_time(NaiveTime::MIN)));
+    }
+}
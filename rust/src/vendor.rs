@@ -0,0 +1,218 @@
+//! `theme install <git-url-or-path>@<rev>` and `theme verify`
+//!
+//! Themes are vendored rather than fetched at build time: the files of the
+//! requested revision are copied into `themes/<name>/` and `themes/<name>.lock`
+//! records the source, the full commit id and the hash of every file. The
+//! build only checks the lock; `theme verify` also reads the pinned commit
+//! again and confirms the vendored files are exactly what it contains.
+//! Symlinks and submodules in a theme are rejected.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::theme::{self, Origin};
+
+/// Directory themes are vendored into
+pub const THEMES_DIR: &str = "themes";
+
+/// Split `<source>@<rev>`; the revision is required so installs are reproducible
+pub fn parse_spec(spec: &str) -> Result<(String, String)> {
+    match spec.rsplit_once('@') {
+        // `git@host:org/theme` without a revision splits inside the URL
+        Some((source, rev)) if !source.is_empty() && !rev.is_empty() && !rev.contains(':') => {
+            Ok((source.to_string(), rev.to_string()))
+        }
+        _ => anyhow::bail!("Expected <git-url-or-path>@<rev> (a tag, branch or commit): {spec}"),
+    }
+}
+
+/// Directory name for a theme source: its last path segment without `.git`
+pub fn theme_name(source: &str) -> Result<String> {
+    let last = source.trim_end_matches('/').rsplit(['/', ':']).next().unwrap_or_default();
+    let name = last.strip_suffix(".git").unwrap_or(last);
+    let valid = matches!(Path::new(name).components().collect::<Vec<_>>().as_slice(), [Component::Normal(_)]);
+    if name.is_empty() || name.starts_with('.') || !valid {
+        anyhow::bail!("Cannot derive a theme name from {source}");
+    }
+    Ok(name.to_string())
+}
+
+/// Commit id and files of `rev` in a repository
+fn tree_files(repo: &gix::Repository, rev: &str) -> Result<(String, BTreeMap<String, Vec<u8>>)> {
+    let commit = repo
+        .rev_parse_single(rev)
+        .with_context(|| format!("Revision {rev} not found"))?
+        .object()?
+        .peel_to_commit()?;
+    let mut recorder = gix::traverse::tree::Recorder::default();
+    commit.tree()?.traverse().breadthfirst(&mut recorder)?;
+
+    let mut files = BTreeMap::new();
+    for entry in recorder.records {
+        let path = entry.filepath.to_string();
+        if entry.mode.is_tree() {
+            continue;
+        }
+        if entry.mode.is_link() || entry.mode.is_commit() {
+            anyhow::bail!("Theme contains a symlink or submodule: {path}");
+        }
+        files.insert(path, repo.find_object(entry.oid)?.detach().data);
+    }
+    Ok((commit.id.to_string(), files))
+}
+
+/// Commit id and files of `rev` from a repository path or git URL
+fn fetch(source: &str, rev: &str) -> Result<(String, BTreeMap<String, Vec<u8>>)> {
+    if Path::new(source).exists() {
+        let repo = gix::open(source).with_context(|| format!("{source} is not a git repository"))?;
+        return tree_files(&repo, rev);
+    }
+    online::fetch(source, rev)
+}
+
+#[cfg(feature = "network")]
+mod online {
+    use anyhow::{Context, Result};
+    use std::collections::BTreeMap;
+    use std::sync::atomic::AtomicBool;
+
+    /// Clone into a temporary bare repository, removed on return, and read `rev` from it
+    pub fn fetch(url: &str, rev: &str) -> Result<(String, BTreeMap<String, Vec<u8>>)> {
+        let dir = tempfile::Builder::new().prefix("secureblog-theme-").tempdir()?;
+        let mut clone = gix::prepare_clone_bare(url, dir.path()).with_context(|| format!("Failed to clone {url}"))?;
+        let (repo, _) = clone
+            .fetch_only(gix::progress::Discard, &AtomicBool::new(false))
+            .with_context(|| format!("Failed to clone {url}"))?;
+        super::tree_files(&repo, rev)
+    }
+}
+
+#[cfg(not(feature = "network"))]
+mod online {
+    use anyhow::Result;
+    use std::collections::BTreeMap;
+
+    /// Cloning requires network access, which is compiled out by default
+    pub fn fetch(url: &str, _rev: &str) -> Result<(String, BTreeMap<String, Vec<u8>>)> {
+        anyhow::bail!("Installing from {url} requires a build with `--features network` (or clone it and pass the path)")
+    }
+}
+
+/// Vendor `source` at `rev` into `<themes_dir>/<name>` and lock it; returns the theme directory
+///
+/// A theme installed before (its lock has a `# source:`) is replaced; any
+/// other existing directory is left alone.
+pub fn install(source: &str, rev: &str, themes_dir: &Path) -> Result<(PathBuf, Origin, usize)> {
+    let name = theme_name(source)?;
+    let target = themes_dir.join(&name);
+    if target.exists() && !matches!(theme::read_lock(&target), Ok((_, Some(_)))) {
+        anyhow::bail!("{} exists and was not installed by `theme install`", target.display());
+    }
+    let (commit, files) = fetch(source, rev)?;
+    let origin = Origin { source: source.to_string(), commit };
+
+    let staging = themes_dir.join(format!(".{name}.partial"));
+    let _ = fs::remove_dir_all(&staging);
+    for (path, content) in &files {
+        let file = staging.join(path);
+        if let Some(parent) = file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&file, content).with_context(|| format!("Failed to write {}", file.display()))?;
+    }
+    fs::create_dir_all(&staging)?;
+    theme::check_templates(&theme::template_dirs(Some(&staging)))
+        .with_context(|| format!("Theme {source}@{rev} references templates outside its directories"))?;
+
+    if target.exists() {
+        fs::remove_dir_all(&target).with_context(|| format!("Failed to remove {}", target.display()))?;
+    }
+    fs::rename(&staging, &target).with_context(|| format!("Failed to move the theme into {}", target.display()))?;
+    let lock = theme::lock_path(&target)?;
+    fs::write(&lock, theme::lock_text(&files, Some(&origin))).with_context(|| format!("Failed to write {}", lock.display()))?;
+    Ok((target, origin, files.len()))
+}
+
+/// Differences between the pinned commit and the vendored files (empty when identical)
+pub fn upstream_problems(pinned: &BTreeMap<String, Vec<u8>>, vendored: &BTreeMap<String, Vec<u8>>) -> Vec<String> {
+    let mut problems = Vec::new();
+    for (path, content) in vendored {
+        match pinned.get(path) {
+            None => problems.push(format!("{path} is not in the pinned commit")),
+            Some(upstream) if upstream != content => problems.push(format!("{path} differs from the pinned commit")),
+            Some(_) => {}
+        }
+    }
+    for path in pinned.keys().filter(|path| !vendored.contains_key(*path)) {
+        problems.push(format!("{path} is in the pinned commit but missing from the theme"));
+    }
+    problems
+}
+
+/// Check the theme against its lock, then against the pinned commit if it was vendored
+///
+/// Returns the origin that was re-checked, if any.
+pub fn verify(theme_dir: &Path) -> Result<Option<Origin>> {
+    theme::verify(theme_dir)?;
+    let (_, origin) = theme::read_lock(theme_dir)?;
+    let Some(origin) = origin else {
+        return Ok(None);
+    };
+    let (commit, pinned) = fetch(&origin.source, &origin.commit)?;
+    if commit != origin.commit {
+        anyhow::bail!("{} resolved to {commit}, not the pinned {}", origin.source, origin.commit);
+    }
+    let problems = upstream_problems(&pinned, &theme::theme_files(theme_dir)?);
+    if !problems.is_empty() {
+        anyhow::bail!("Theme {} differs from {}@{}:\n  {}", theme_dir.display(), origin.source, origin.commit, problems.join("\n  "));
+    }
+    Ok(Some(origin))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_spec() {
+        assert_eq!(parse_spec("../minimal@v1.2").unwrap(), ("../minimal".to_string(), "v1.2".to_string()));
+        assert_eq!(
+            parse_spec("git@github.com:me/minimal.git@main").unwrap(),
+            ("git@github.com:me/minimal.git".to_string(), "main".to_string())
+        );
+        assert!(parse_spec("git@github.com:me/minimal.git").is_err());
+        assert!(parse_spec("https://example.com/minimal.git").is_err());
+        assert!(parse_spec("minimal@").is_err());
+    }
+
+    #[test]
+    fn test_theme_name() {
+        assert_eq!(theme_name("https://example.com/org/minimal.git").unwrap(), "minimal");
+        assert_eq!(theme_name("git@github.com:minimal.git").unwrap(), "minimal");
+        assert_eq!(theme_name("../themes/paper/").unwrap(), "paper");
+        assert!(theme_name("..").is_err());
+        assert!(theme_name("https://example.com/.git").is_err());
+    }
+
+    #[test]
+    fn test_upstream_problems() {
+        let pinned = BTreeMap::from([
+            ("templates/base.html".to_string(), b"<html>".to_vec()),
+            ("static/a.css".to_string(), Vec::new()),
+        ]);
+        let vendored = BTreeMap::from([
+            ("templates/base.html".to_string(), b"<html><script>".to_vec()),
+            ("static/x.css".to_string(), Vec::new()),
+        ]);
+        assert_eq!(
+            upstream_problems(&pinned, &vendored),
+            [
+                "static/x.css is not in the pinned commit",
+                "templates/base.html differs from the pinned commit",
+                "static/a.css is in the pinned commit but missing from the theme",
+            ]
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! Filesystem access for the build pipeline
+//!
+//! Loading the config and posts, cleaning the output and writing the
+//! manifest go through [`Filesystem`] instead of `std::fs`, so the generator
+//! can run where the host filesystem is not fully available. Under
+//! `wasm32-wasip1` only the directories the runtime preopened are reachable
+//! (`wasmtime run --dir . secureblog-rs.wasm`), which [`current`] enforces
+//! with `Preopened`: a path that would escape the preopened site directory
+//! fails up front instead of deep inside the runtime. `Preopened` is only
+//! compiled for WASI; native builds use [`Host`], and the tests check its
+//! rule natively.
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+#[cfg(any(target_os = "wasi", test))]
+use std::path::Component;
+
+/// The filesystem operations the build needs
+pub trait Filesystem: Send + Sync {
+    /// Whole file contents
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// The file as a stream, for files too large to read at once
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>>;
+
+    /// Whole file contents as UTF-8
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        String::from_utf8(self.read(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Create or replace a file
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+
+    /// Create a directory and its parents
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+
+    /// Remove a directory and everything in it
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()>;
+
+    /// Whether anything exists at `path`
+    fn exists(&self, path: &Path) -> bool;
+
+    /// Every regular file under `dir`, sorted by path
+    fn files(&self, dir: &Path) -> io::Result<Vec<PathBuf>>;
+}
+
+/// The host filesystem through `std::fs`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Host;
+
+impl Filesystem for Host {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        std::fs::read(path)
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        Ok(Box::new(File::open(path)?))
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        std::fs::write(path, contents)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        std::fs::create_dir_all(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        std::fs::remove_dir_all(path)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn files(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        for entry in WalkDir::new(dir).sort_by_file_name() {
+            let entry = entry.map_err(io::Error::other)?;
+            if entry.file_type().is_file() {
+                files.push(entry.into_path());
+            }
+        }
+        Ok(files)
+    }
+}
+
+/// Host access limited to relative paths inside the preopened working directory
+#[cfg(any(target_os = "wasi", test))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Preopened;
+
+#[cfg(any(target_os = "wasi", test))]
+impl Preopened {
+    /// `path` itself if it stays inside the working directory
+    fn check<'a>(&self, path: &'a Path) -> io::Result<&'a Path> {
+        let inside = path.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+        if inside {
+            Ok(path)
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("{} is outside the preopened site directory", path.display()),
+            ))
+        }
+    }
+}
+
+#[cfg(any(target_os = "wasi", test))]
+impl Filesystem for Preopened {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        Host.read(self.check(path)?)
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        Host.open(self.check(path)?)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        Host.write(self.check(path)?, contents)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        Host.create_dir_all(self.check(path)?)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        Host.remove_dir_all(self.check(path)?)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.check(path).is_ok_and(|path| Host.exists(path))
+    }
+
+    fn files(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        Host.files(self.check(dir)?)
+    }
+}
+
+/// The filesystem of this platform: `Preopened` under WASI, [`Host`] elsewhere
+pub fn current() -> &'static dyn Filesystem {
+    #[cfg(target_os = "wasi")]
+    {
+        &Preopened
+    }
+    #[cfg(not(target_os = "wasi"))]
+    {
+        &Host
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preopened_rejects_paths_outside_the_site() {
+        assert!(Preopened.check(Path::new("content/post.md")).is_ok());
+        assert!(Preopened.check(Path::new("./dist/index.html")).is_ok());
+        assert!(Preopened.check(Path::new("../secrets/key")).is_err());
+        assert!(Preopened.check(Path::new("content/../../x")).is_err());
+        assert!(Preopened.check(Path::new("/etc/passwd")).is_err());
+        assert!(!Preopened.exists(Path::new("/")));
+    }
+}
@@ -0,0 +1,292 @@
+//! Webmention sending for published posts
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::links;
+
+/// `<link>` and `<a>` start tags
+static LINK_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<(?:link|a)\b[^>]*>").unwrap());
+
+/// Attributes inside a start tag
+static ATTRIBUTE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?is)\s([a-z-]+)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+))"#).unwrap());
+
+/// Outcome recorded for a source/target pair
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MentionStatus {
+    /// Endpoint accepted the webmention
+    Sent,
+    /// Target does not advertise an endpoint
+    NoEndpoint,
+}
+
+/// A webmention already handled for a source/target pair
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SentMention {
+    /// Outcome of the attempt
+    pub status: MentionStatus,
+    /// Endpoint the mention was sent to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    /// When the attempt was made (RFC 3339)
+    pub at: String,
+}
+
+/// Sent mentions keyed by source URL, then target URL
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MentionState {
+    /// Handled mentions
+    #[serde(default)]
+    pub mentions: BTreeMap<String, BTreeMap<String, SentMention>>,
+}
+
+impl MentionState {
+    /// Load the state file (empty if missing)
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read webmention state: {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse webmention state: {}", path.display()))
+    }
+
+    /// Write the state file
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write webmention state: {}", path.display()))
+    }
+
+    /// Whether a mention from `source` to `target` was already handled
+    pub fn contains(&self, source: &str, target: &str) -> bool {
+        self.mentions
+            .get(source)
+            .is_some_and(|targets| targets.contains_key(target))
+    }
+
+    /// Record the outcome for a source/target pair
+    pub fn record(&mut self, source: &str, target: &str, mention: SentMention) {
+        self.mentions
+            .entry(source.to_string())
+            .or_default()
+            .insert(target.to_string(), mention);
+    }
+}
+
+/// Outbound `http(s)` links in rendered post HTML, excluding the site itself
+pub fn outbound_links(html: &str, site_url: &str) -> Vec<String> {
+    let site = site_url.trim_end_matches('/');
+    let mut targets: Vec<String> = links::parse_page(html)
+        .references
+        .into_iter()
+        .filter(|r| r.attr == "href")
+        .map(|r| r.value)
+        .filter(|url| url.starts_with("https://") || url.starts_with("http://"))
+        .filter(|url| url != site && !url.starts_with(&format!("{site}/")))
+        .collect();
+    targets.sort();
+    targets.dedup();
+    targets
+}
+
+/// Endpoint advertised in a `Link` header (`<url>; rel="webmention"`)
+pub fn endpoint_from_link_header(header: &str) -> Option<String> {
+    header.split(',').find_map(|link| {
+        let (url, params) = link.split_once(';')?;
+        let url = url.trim().strip_prefix('<')?.strip_suffix('>')?;
+        params
+            .split(';')
+            .filter_map(|param| param.trim().split_once('='))
+            .any(|(key, value)| {
+                key.trim().eq_ignore_ascii_case("rel")
+                    && value
+                        .trim()
+                        .trim_matches('"')
+                        .split_whitespace()
+                        .any(|rel| rel.eq_ignore_ascii_case("webmention"))
+            })
+            .then(|| url.to_string())
+    })
+}
+
+/// Endpoint advertised by the first `<link>`/`<a>` with `rel="webmention"`
+pub fn endpoint_from_html(html: &str) -> Option<String> {
+    LINK_TAG.find_iter(html).find_map(|tag| {
+        let mut rel = None;
+        let mut href = None;
+        for cap in ATTRIBUTE.captures_iter(tag.as_str()) {
+            let value = cap
+                .get(2)
+                .or_else(|| cap.get(3))
+                .or_else(|| cap.get(4))
+                .map_or("", |m| m.as_str());
+            match cap[1].to_ascii_lowercase().as_str() {
+                "rel" => rel = Some(value.to_string()),
+                "href" => href = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        let is_webmention = rel?
+            .split_whitespace()
+            .any(|r| r.eq_ignore_ascii_case("webmention"));
+        is_webmention.then_some(href).flatten()
+    })
+}
+
+#[cfg(feature = "network")]
+pub use online::send_all;
+
+#[cfg(feature = "network")]
+mod online {
+    use super::{endpoint_from_html, endpoint_from_link_header, outbound_links};
+    use super::{MentionState, MentionStatus, SentMention};
+    use anyhow::{Context, Result};
+    use chrono::Utc;
+    use tracing::{info, warn};
+    use url::Url;
+
+    use crate::{status, Config, Post};
+
+    /// Discover the webmention endpoint of `target`, resolved against the final URL
+    fn discover(agent: &ureq::Agent, target: &str) -> Result<Option<String>> {
+        let mut response = agent
+            .get(target)
+            .call()
+            .with_context(|| format!("Failed to fetch {target}"))?;
+        let base = Url::parse(target)?;
+
+        let from_header = response
+            .headers()
+            .get_all("link")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .find_map(endpoint_from_link_header);
+
+        let endpoint = match from_header {
+            Some(endpoint) => Some(endpoint),
+            None if response.status().is_success() => {
+                let body = response.body_mut().read_to_string().unwrap_or_default();
+                endpoint_from_html(&body)
+            }
+            None => None,
+        };
+
+        endpoint
+            .map(|e| base.join(&e).map(String::from))
+            .transpose()
+            .context("Invalid webmention endpoint URL")
+    }
+
+    /// Send webmentions for every outbound link not yet recorded in the state
+    ///
+    /// Returns the number of mentions accepted by their endpoints.
+    pub fn send_all(config: &Config, posts: &[Post], state: &mut MentionState) -> Result<usize> {
+        let agent = crate::net::agent();
+        let mut sent = 0;
+        let now = Utc::now();
+
+        for post in posts
+            .iter()
+            .filter(|p| status::disposition(&p.meta, now) == status::Disposition::Published)
+        {
+            let source = post.permalink(&config.url);
+            for target in outbound_links(&post.html, &config.url) {
+                if state.contains(&source, &target) {
+                    continue;
+                }
+
+                let endpoint = match discover(&agent, &target) {
+                    Ok(endpoint) => endpoint,
+                    Err(e) => {
+                        warn!("Skipping {}: {:#}", target, e);
+                        continue;
+                    }
+                };
+
+                let Some(endpoint) = endpoint else {
+                    state.record(&source, &target, SentMention {
+                        status: MentionStatus::NoEndpoint,
+                        endpoint: None,
+                        at: Utc::now().to_rfc3339(),
+                    });
+                    continue;
+                };
+
+                let response = agent
+                    .post(&endpoint)
+                    .send_form([("source", source.as_str()), ("target", target.as_str())])
+                    .with_context(|| format!("Failed to send webmention to {endpoint}"))?;
+
+                if response.status().is_success() {
+                    info!("Sent webmention {} -> {}", source, target);
+                    state.record(&source, &target, SentMention {
+                        status: MentionStatus::Sent,
+                        endpoint: Some(endpoint),
+                        at: Utc::now().to_rfc3339(),
+                    });
+                    sent += 1;
+                } else {
+                    warn!(
+                        "Webmention endpoint {} rejected {} -> {}: HTTP {}",
+                        endpoint, source, target, response.status()
+                    );
+                }
+            }
+        }
+
+        Ok(sent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outbound_links_excludes_own_site() {
+        let html = r#"<a href="https://other.example/post">x</a>
+            <a href="https://blog.example/about.html">self</a>
+            <a href="/local.html">local</a>
+            <a href="https://other.example/post">dup</a>"#;
+        assert_eq!(
+            outbound_links(html, "https://blog.example/"),
+            vec!["https://other.example/post"]
+        );
+    }
+
+    #[test]
+    fn test_endpoint_from_link_header() {
+        assert_eq!(
+            endpoint_from_link_header(r#"<https://a.example/next>; rel="next", </wm>; rel="webmention""#),
+            Some("/wm".to_string())
+        );
+        assert_eq!(endpoint_from_link_header("<https://a.example/x>; rel=me"), None);
+    }
+
+    #[test]
+    fn test_endpoint_from_html() {
+        let html = r#"<a href="/x">x</a><link href="https://a.example/wm" rel="webmention">"#;
+        assert_eq!(endpoint_from_html(html), Some("https://a.example/wm".to_string()));
+        assert_eq!(endpoint_from_html(r#"<link rel="stylesheet" href="s.css">"#), None);
+    }
+
+    #[test]
+    fn test_state_deduplicates() {
+        let mut state = MentionState::default();
+        assert!(!state.contains("https://s/a", "https://t/b"));
+        state.record("https://s/a", "https://t/b", SentMention {
+            status: MentionStatus::Sent,
+            endpoint: Some("https://t/wm".to_string()),
+            at: "2026-01-01T00:00:00Z".to_string(),
+        });
+        assert!(state.contains("https://s/a", "https://t/b"));
+    }
+}